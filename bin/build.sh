@@ -27,6 +27,8 @@ set_target_info() {
         # Linux targets
         "aarch64-unknown-linux-gnu") PLATFORM="linux-arm64-gnu"; LIB_NAME="libprotect_ffi.so" ;;
         "x86_64-unknown-linux-gnu") PLATFORM="linux-x64-gnu"; LIB_NAME="libprotect_ffi.so" ;;
+        "aarch64-unknown-linux-musl") PLATFORM="linux-arm64-musl"; LIB_NAME="libprotect_ffi.so" ;;
+        "x86_64-unknown-linux-musl") PLATFORM="linux-x64-musl"; LIB_NAME="libprotect_ffi.so" ;;
 
         # Windows target
         "x86_64-pc-windows-msvc") PLATFORM="win32-x64-msvc"; LIB_NAME="protect_ffi.dll" ;;
@@ -50,9 +52,14 @@ detect_platform() {
                 *) error "Unsupported macOS arch: [$arch]"; exit 1 ;;
             esac ;;
         linux)
+            local libc="gnu"
+            if compgen -G "/lib/ld-musl-*.so.1" >/dev/null; then
+                libc="musl"
+            fi
+
             case "$arch" in
-                aarch64) set_target_info "aarch64-unknown-linux-gnu" ;;
-                x86_64|amd64) set_target_info "x86_64-unknown-linux-gnu" ;;
+                aarch64) set_target_info "aarch64-unknown-linux-$libc" ;;
+                x86_64|amd64) set_target_info "x86_64-unknown-linux-$libc" ;;
                 *) error "Unsupported Linux arch: [$arch]"; exit 1 ;;
             esac ;;
         mingw*|msys*|cygwin*)
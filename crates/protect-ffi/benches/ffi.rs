@@ -0,0 +1,179 @@
+//! Criterion benchmarks of the FFI functions.
+//!
+//! The benchmarks call the exported functions the same way the PHP extension does, so they
+//! include argument parsing, envelope emission, and serialization. They need the `CS_*`
+//! environment variables of a ZeroKMS workspace and are skipped when no client can be created.
+//!
+//! Run them with `cargo bench -p protect-ffi`, and compare against a saved baseline with
+//! `--save-baseline <name>` and `--baseline <name>`.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use protect_ffi::{decrypt_bulk, encrypt, encrypt_bulk, free_client, free_string, new_client};
+use serde_json::{json, Value};
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+const BULK_SIZE: usize = 1_000;
+
+const CONFIG: &str = r#"{
+    "v": 2,
+    "tables": {
+        "users": {
+            "email": {
+                "cast_as": "text",
+                "indexes": {"unique": {}, "match": {}}
+            },
+            "profile": {
+                "cast_as": "jsonb",
+                "indexes": {"ste_vec": {"prefix": "users.profile"}}
+            }
+        }
+    }
+}"#;
+
+/// A client created from [`CONFIG`], freed when dropped.
+struct BenchClient(*mut protect_ffi::Client);
+
+impl BenchClient {
+    fn new() -> Option<Self> {
+        let config = CString::new(CONFIG).unwrap();
+        let mut error: *mut c_char = ptr::null_mut();
+        let client = new_client(config.as_ptr(), &mut error);
+
+        if client.is_null() {
+            eprintln!("skipping FFI benchmarks: {}", take_string(error));
+            return None;
+        }
+
+        Some(Self(client))
+    }
+}
+
+impl Drop for BenchClient {
+    fn drop(&mut self) {
+        free_client(self.0);
+    }
+}
+
+/// Copy and free a string returned by the library.
+fn take_string(string: *mut c_char) -> String {
+    if string.is_null() {
+        return String::new();
+    }
+
+    let owned = unsafe { CStr::from_ptr(string) }
+        .to_string_lossy()
+        .into_owned();
+    free_string(string);
+    owned
+}
+
+/// Unwrap the result of an FFI call, panicking with the reported error.
+fn expect_result(result: *mut c_char, error: *mut c_char) -> String {
+    assert!(!result.is_null(), "FFI call failed: {}", take_string(error));
+    take_string(result)
+}
+
+fn call_bulk(
+    function: extern "C" fn(
+        *const protect_ffi::Client,
+        *const c_char,
+        *mut *mut c_char,
+    ) -> *mut c_char,
+    client: &BenchClient,
+    items: &CString,
+) -> String {
+    let mut error: *mut c_char = ptr::null_mut();
+    expect_result(function(client.0, items.as_ptr(), &mut error), error)
+}
+
+/// A JSON document with nested objects and arrays, for `ste_vec` indexing.
+fn large_document() -> String {
+    let orders: Vec<Value> = (0..50)
+        .map(|index| {
+            json!({
+                "id": index,
+                "sku": format!("SKU-{index:05}"),
+                "quantity": index % 7 + 1,
+                "shipping": {"city": "Melbourne", "postcode": format!("3{index:03}")},
+            })
+        })
+        .collect();
+
+    json!({
+        "name": "Ada Lovelace",
+        "email": "ada@example.com",
+        "roles": ["admin", "billing", "support"],
+        "orders": orders,
+    })
+    .to_string()
+}
+
+fn encrypt_items(table: &str, column: &str, plaintext: &str, count: usize) -> CString {
+    let items: Vec<Value> = (0..count)
+        .map(|_| json!({"plaintext": plaintext, "column": column, "table": table}))
+        .collect();
+
+    CString::new(serde_json::to_string(&items).unwrap()).unwrap()
+}
+
+fn bench_ffi(c: &mut Criterion) {
+    let Some(client) = BenchClient::new() else {
+        return;
+    };
+
+    let mut group = c.benchmark_group("ffi");
+
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("encrypt", |b| {
+        let plaintext = CString::new("ada@example.com").unwrap();
+        let column = CString::new("email").unwrap();
+        let table = CString::new("users").unwrap();
+
+        b.iter(|| {
+            let mut error: *mut c_char = ptr::null_mut();
+            let result = encrypt(
+                client.0,
+                plaintext.as_ptr(),
+                column.as_ptr(),
+                table.as_ptr(),
+                ptr::null(),
+                &mut error,
+            );
+            expect_result(result, error)
+        });
+    });
+
+    group.bench_function("encrypt_ste_vec_large_document", |b| {
+        let items = encrypt_items("users", "profile", &large_document(), 1);
+        b.iter(|| call_bulk(encrypt_bulk, &client, &items));
+    });
+
+    group.throughput(Throughput::Elements(BULK_SIZE as u64));
+    group.bench_function("encrypt_bulk_1k", |b| {
+        let items = encrypt_items("users", "email", "ada@example.com", BULK_SIZE);
+        b.iter(|| call_bulk(encrypt_bulk, &client, &items));
+    });
+
+    group.bench_function("decrypt_bulk_1k", |b| {
+        let items = encrypt_items("users", "email", "ada@example.com", BULK_SIZE);
+        let envelopes: Vec<Value> =
+            serde_json::from_str(&call_bulk(encrypt_bulk, &client, &items)).unwrap();
+        let decrypt_items: Vec<Value> = envelopes
+            .iter()
+            .map(|envelope| json!({"ciphertext": envelope.to_string()}))
+            .collect();
+        let decrypt_items = CString::new(serde_json::to_string(&decrypt_items).unwrap()).unwrap();
+
+        b.iter(|| call_bulk(decrypt_bulk, &client, &decrypt_items));
+    });
+
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = bench_ffi
+}
+criterion_main!(benches);
@@ -0,0 +1,89 @@
+//! Load-time compatibility checks between the library and its caller.
+//!
+//! A caller binds the library's functions through a C header, so a header and library from
+//! different releases can disagree about a function's parameters without either side noticing
+//! until a call crashes. [`ABI_VERSION`] is increased whenever an exported function changes in
+//! a way an old header cannot call safely. A caller compares it with the version its header was
+//! written for, then calls `selfcheck` with the sizes of the C types as it sees them, before
+//! calling anything else.
+
+use crate::Error;
+use std::ffi::c_char;
+use std::mem::size_of;
+
+/// The version of the library's exported functions and their calling conventions.
+pub const ABI_VERSION: u32 = 1;
+
+/// The sizes of the C types passed across the FFI boundary, as seen by the caller.
+#[derive(Debug)]
+pub struct TypeSizes {
+    /// The size of a pointer, such as `char*` or `Client*`.
+    pub pointer: usize,
+    /// The size of `int32_t`, returned by status functions such as `job_poll`.
+    pub int32: usize,
+}
+
+impl TypeSizes {
+    /// The sizes of the C types as seen by the library.
+    pub fn native() -> Self {
+        Self {
+            pointer: size_of::<*const c_char>(),
+            int32: size_of::<i32>(),
+        }
+    }
+}
+
+/// Check that a caller expects this library's ABI and sees its C types at the same sizes.
+///
+/// # Errors
+///
+/// Returns [`Error::IncompatibleAbi`] describing the first mismatch.
+pub fn check(abi_version: u32, caller: &TypeSizes) -> Result<(), Error> {
+    if abi_version != ABI_VERSION {
+        return Err(Error::IncompatibleAbi(format!(
+            "the caller expects ABI version {abi_version}, but the library implements version {ABI_VERSION}"
+        )));
+    }
+
+    let native = TypeSizes::native();
+    for (name, caller_size, native_size) in [
+        ("pointer", caller.pointer, native.pointer),
+        ("int32_t", caller.int32, native.int32),
+    ] {
+        if caller_size != native_size {
+            return Err(Error::IncompatibleAbi(format!(
+                "the caller sees a {name} as {caller_size} bytes, but the library uses {native_size} bytes"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_accepts_native_sizes() {
+        assert!(check(ABI_VERSION, &TypeSizes::native()).is_ok());
+    }
+
+    #[test]
+    fn test_check_rejects_other_abi_versions() {
+        let result = check(ABI_VERSION + 1, &TypeSizes::native());
+
+        assert!(matches!(result, Err(Error::IncompatibleAbi(_))));
+    }
+
+    #[test]
+    fn test_check_rejects_mismatched_sizes() {
+        let caller = TypeSizes {
+            pointer: 4,
+            ..TypeSizes::native()
+        };
+
+        let error = check(ABI_VERSION, &caller).unwrap_err();
+        assert!(error.to_string().contains("pointer as 4 bytes"));
+    }
+}
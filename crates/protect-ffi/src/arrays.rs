@@ -0,0 +1,144 @@
+//! Plaintexts for array columns such as `text[]` and `int[]`.
+//!
+//! An array value is encrypted as a whole so that decryption returns the complete array, while
+//! each element is encrypted separately with the column's indexes so that every element carries
+//! its own index terms in the envelope.
+
+use crate::encrypt_config::CastAs;
+use crate::normalization::IndexNormalization;
+use crate::{plaintext_target, Error};
+use cipherstash_client::{
+    encryption::PlaintextTarget,
+    schema::{ColumnConfig, ColumnType},
+};
+use serde_json::Value;
+
+/// Parse an array plaintext into its normalized JSON text and the plaintext of each element.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidArray`] if the plaintext is not a JSON array, or if any element is
+/// not a value of the `element_type`.
+pub fn parse(plaintext: &str, element_type: CastAs) -> Result<(String, Vec<String>), Error> {
+    let Ok(Value::Array(values)) = serde_json::from_str(plaintext) else {
+        return Err(Error::InvalidArray(
+            "the plaintext is not a JSON array".to_string(),
+        ));
+    };
+
+    let elements = values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            element_plaintext(value, element_type).ok_or_else(|| {
+                Error::InvalidArray(format!("element {index} is not a valid `{element_type}`"))
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    Ok((serde_json::to_string(&values)?, elements))
+}
+
+/// The plaintext of a single array element, or `None` if it does not match the element type.
+fn element_plaintext(value: &Value, element_type: CastAs) -> Option<String> {
+    match (element_type, value) {
+        (CastAs::Text, Value::String(text)) => Some(text.clone()),
+        (CastAs::Int, Value::Number(number)) => number
+            .as_i64()
+            .and_then(|number| i32::try_from(number).ok())
+            .map(|number| number.to_string()),
+        _ => None,
+    }
+}
+
+/// Create the plaintext targets for an array value.
+///
+/// The first target is the whole array, encrypted as text without indexes. The targets of each
+/// element follow, using the column's element configuration and indexes, with one additional
+/// target per index source. Only the index terms of the element targets are kept.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidArray`] if the plaintext is not a valid array of the `element_type`.
+pub fn targets(
+    plaintext: &str,
+    element_type: CastAs,
+    column_config: &ColumnConfig,
+    normalization: IndexNormalization,
+) -> Result<Vec<PlaintextTarget>, Error> {
+    let (array, elements) = parse(plaintext, element_type)?;
+
+    let mut array_config = column_config.clone();
+    array_config.cast_type = ColumnType::Utf8Str;
+    array_config.indexes.clear();
+
+    let per_element = normalization.sources().len() + 1;
+    let mut targets = Vec::with_capacity(elements.len() * per_element + 1);
+    targets.push(plaintext_target::new(array, &array_config)?);
+
+    for element in elements {
+        targets.extend(normalization.targets(element, column_config)?);
+    }
+
+    Ok(targets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_text_array() {
+        let (array, elements) = parse(r#"[ "admin", "editor" ]"#, CastAs::Text).unwrap();
+
+        assert_eq!(array, r#"["admin","editor"]"#);
+        assert_eq!(elements, ["admin", "editor"]);
+    }
+
+    #[test]
+    fn test_parse_int_array() {
+        let (array, elements) = parse("[1, -2, 2147483647]", CastAs::Int).unwrap();
+
+        assert_eq!(array, "[1,-2,2147483647]");
+        assert_eq!(elements, ["1", "-2", "2147483647"]);
+    }
+
+    #[test]
+    fn test_parse_empty_array() {
+        let (array, elements) = parse("[]", CastAs::Text).unwrap();
+
+        assert_eq!(array, "[]");
+        assert!(elements.is_empty());
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_elements() {
+        for (plaintext, element_type) in [
+            (r#"["admin", null]"#, CastAs::Text),
+            (r#"["admin", 1]"#, CastAs::Text),
+            ("[1, 1.5]", CastAs::Int),
+            ("[2147483648]", CastAs::Int),
+            (r#"["1"]"#, CastAs::Int),
+        ] {
+            let result = parse(plaintext, element_type);
+            assert!(matches!(result, Err(Error::InvalidArray(_))), "{plaintext}");
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_non_arrays() {
+        for plaintext in ["admin", r#""admin""#, r#"{"tags": []}"#] {
+            let result = parse(plaintext, CastAs::Text);
+            assert!(matches!(result, Err(Error::InvalidArray(_))), "{plaintext}");
+        }
+    }
+
+    #[test]
+    fn test_targets_include_each_element() {
+        let column_config = ColumnConfig::build("tags".to_string()).casts_as(ColumnType::Utf8Str);
+
+        let targets = targets(r#"["admin", "editor"]"#, CastAs::Text, &column_config).unwrap();
+
+        assert_eq!(targets.len(), 3);
+    }
+}
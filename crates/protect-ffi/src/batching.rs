@@ -0,0 +1,426 @@
+//! Micro-batching of single-item `encrypt` and `decrypt` calls.
+//!
+//! PHP code that encrypts or decrypts one value at a time makes one ZeroKMS request per value.
+//! With the `batching` client option, a call opens a batch and waits for the batching window,
+//! while calls on other threads that arrive within the window join it. The batch is then
+//! encrypted or decrypted in one request, as `encrypt_bulk` and `decrypt_bulk` would, and each
+//! call receives its own result.
+//!
+//! A bulk request fails as a whole, so when a batch fails, each call is retried on its own. A
+//! call with a bad context or ciphertext therefore fails with its own error, without failing
+//! the other calls of its batch.
+
+use crate::context::{with_kinds_from_value, ContextCache};
+use crate::encrypt_config::{CastAs, Identifier};
+use crate::{
+    decrypt_bulk_inner, decrypt_inner, encrypt_bulk_inner, encrypt_inner, plaintext_targets,
+    Client, Encrypted, Error,
+};
+use cipherstash_client::encryption::PlaintextTarget;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// The largest batching window, in milliseconds.
+pub const MAX_WINDOW_MS: u64 = 100;
+
+/// The batching window of a client.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct BatchWindow {
+    /// How long a batch stays open for calls to join it, in milliseconds.
+    #[serde(default = "default_window_ms")]
+    pub window_ms: u64,
+    /// The most calls in a batch. Calls after a batch is full open a new batch.
+    #[serde(default = "default_max_items")]
+    pub max_items: usize,
+}
+
+/// Default batching window, in milliseconds.
+fn default_window_ms() -> u64 {
+    2
+}
+
+/// Default number of calls in a batch.
+fn default_max_items() -> usize {
+    1000
+}
+
+impl BatchWindow {
+    /// Check that the window and batch size are in range.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first setting that is out of range.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_WINDOW_MS).contains(&self.window_ms) {
+            return Err(format!("`window_ms` must be between 1 and {MAX_WINDOW_MS}"));
+        }
+        if self.max_items < 2 {
+            return Err("`max_items` must be at least 2".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// An `encrypt` call: its plaintext, column, and encryption context.
+type EncryptCall = (String, Identifier, Option<Value>);
+
+/// A `decrypt` call: its ciphertext and encryption context.
+type DecryptCall = (String, Option<Value>);
+
+/// The open batches of a client, shared with the clients derived from it.
+pub struct Batching {
+    /// `encrypt` calls, batched by the client's default cipher.
+    encrypts: Batcher<usize, EncryptCall, Encrypted>,
+    /// `decrypt` calls, which carry their keyset in the ciphertext.
+    decrypts: Batcher<(), DecryptCall, String>,
+}
+
+impl Batching {
+    /// Create the batches of a client.
+    pub fn new(window: BatchWindow) -> Self {
+        Self {
+            encrypts: Batcher::new(window),
+            decrypts: Batcher::new(window),
+        }
+    }
+}
+
+/// A call waiting in a batch for its result, or for `None` when the batch failed.
+type Waiter<T, R> = (T, oneshot::Sender<Option<R>>);
+
+/// The open batch of each key.
+struct Batcher<K, T, R> {
+    window: Duration,
+    max_items: usize,
+    open: Mutex<HashMap<K, Arc<Mutex<Vec<Waiter<T, R>>>>>>,
+}
+
+impl<K: Clone + Eq + Hash, T, R> Batcher<K, T, R> {
+    fn new(window: BatchWindow) -> Self {
+        Self {
+            window: Duration::from_millis(window.window_ms),
+            max_items: window.max_items,
+            open: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Add a call to the open batch of a key and wait for its result. The call that opens a
+    /// batch waits for the window, then runs the batch for every call in it.
+    ///
+    /// Returns `None` when the batch failed, so the call can be retried on its own.
+    async fn submit<F, Fut>(&self, key: K, call: T, run: F) -> Option<R>
+    where
+        F: FnOnce(Vec<T>) -> Fut,
+        Fut: Future<Output = Result<Vec<R>, Error>>,
+    {
+        let (sender, receiver) = oneshot::channel();
+
+        let opened = {
+            let mut open = lock(&self.open);
+            match open.get(&key).cloned() {
+                Some(batch) => {
+                    let mut waiters = lock(&batch);
+                    waiters.push((call, sender));
+                    if waiters.len() >= self.max_items {
+                        open.remove(&key);
+                    }
+                    None
+                }
+                None => {
+                    let batch = Arc::new(Mutex::new(vec![(call, sender)]));
+                    open.insert(key.clone(), batch.clone());
+                    Some(batch)
+                }
+            }
+        };
+
+        if let Some(batch) = opened {
+            tokio::time::sleep(self.window).await;
+
+            {
+                let mut open = lock(&self.open);
+                if open
+                    .get(&key)
+                    .is_some_and(|current| Arc::ptr_eq(current, &batch))
+                {
+                    open.remove(&key);
+                }
+            }
+
+            let waiters = std::mem::take(&mut *lock(&batch));
+            let (calls, senders): (Vec<T>, Vec<_>) = waiters.into_iter().unzip();
+
+            match run(calls).await {
+                Ok(results) if results.len() == senders.len() => {
+                    for (sender, result) in senders.into_iter().zip(results) {
+                        let _ = sender.send(Some(result));
+                    }
+                }
+                _ => {
+                    for sender in senders {
+                        let _ = sender.send(None);
+                    }
+                }
+            }
+        }
+
+        receiver.await.ok().flatten()
+    }
+}
+
+/// Lock a mutex, recovering the data if another thread panicked while holding it.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Encrypt a plaintext for a column, in a batch when the client batches calls.
+///
+/// # Errors
+///
+/// Returns an error if the column is unknown, the context or plaintext is invalid, or
+/// encryption fails.
+pub async fn encrypt(
+    client: &Client,
+    plaintext: String,
+    identifier: Identifier,
+    context: Option<&Value>,
+) -> Result<Encrypted, Error> {
+    if let Some(batching) = &client.batching {
+        // Derived clients may encrypt under a different default keyset.
+        let key = Arc::as_ptr(&client.cipher) as usize;
+        let call = (plaintext.clone(), identifier.clone(), context.cloned());
+        let encrypted = batching
+            .encrypts
+            .submit(key, call, |calls| encrypt_batch(client, calls))
+            .await;
+
+        if let Some(encrypted) = encrypted {
+            return Ok(encrypted);
+        }
+    }
+
+    let (targets, cast_as) = targets(
+        client,
+        plaintext,
+        &identifier,
+        context,
+        &mut ContextCache::default(),
+    )?;
+    encrypt_inner(client, targets, &identifier, &cast_as, None).await
+}
+
+/// Decrypt a ciphertext, in a batch when the client batches calls.
+///
+/// # Errors
+///
+/// Returns an error if the client is write-only, the context or ciphertext is invalid, or
+/// decryption fails.
+pub async fn decrypt(
+    client: &Client,
+    ciphertext: String,
+    context: Option<&Value>,
+) -> Result<String, Error> {
+    client.ensure_can_decrypt()?;
+
+    if let Some(batching) = &client.batching {
+        let call = (ciphertext.clone(), context.cloned());
+        let plaintext = batching
+            .decrypts
+            .submit((), call, |calls| decrypt_batch(client, calls))
+            .await;
+
+        if let Some(plaintext) = plaintext {
+            return Ok(plaintext);
+        }
+    }
+
+    let (encryption_context, context_kinds) = match context {
+        Some(context) => with_kinds_from_value(context)?,
+        None => (Vec::new(), Vec::new()),
+    };
+    decrypt_inner(client, ciphertext, encryption_context, &context_kinds, None).await
+}
+
+/// Create the plaintext targets of an `encrypt` call.
+fn targets(
+    client: &Client,
+    plaintext: String,
+    identifier: &Identifier,
+    context: Option<&Value>,
+    contexts: &mut ContextCache,
+) -> Result<(Vec<PlaintextTarget>, CastAs), Error> {
+    let encryption_context = match context {
+        Some(context) => contexts.encryption_context(context)?,
+        None => Vec::new(),
+    };
+
+    let (column_config, cast_as) = client.column(identifier)?;
+
+    let targets = plaintext_targets(
+        plaintext,
+        column_config,
+        *cast_as,
+        client.text_policy(identifier),
+        client.ste_vec_policy(identifier),
+        encryption_context,
+    )?;
+
+    Ok((targets, *cast_as))
+}
+
+/// Encrypt a batch of `encrypt` calls in one request per keyset.
+async fn encrypt_batch(client: &Client, calls: Vec<EncryptCall>) -> Result<Vec<Encrypted>, Error> {
+    let mut contexts = ContextCache::default();
+    let items = calls
+        .into_iter()
+        .map(|(plaintext, identifier, context)| {
+            let (targets, cast_as) = targets(
+                client,
+                plaintext,
+                &identifier,
+                context.as_ref(),
+                &mut contexts,
+            )?;
+            Ok((targets, identifier, cast_as))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    encrypt_bulk_inner(client, items, None).await
+}
+
+/// Decrypt a batch of `decrypt` calls in one request.
+async fn decrypt_batch(client: &Client, calls: Vec<DecryptCall>) -> Result<Vec<String>, Error> {
+    let mut ciphertexts = Vec::with_capacity(calls.len());
+    let mut context_kinds = Vec::new();
+    let mut contexts = ContextCache::default();
+
+    for (ciphertext, context) in calls {
+        let encryption_context = match &context {
+            Some(context) => {
+                let (encryption_context, kinds) = contexts.with_kinds(context)?;
+                context_kinds.extend(kinds);
+                encryption_context
+            }
+            None => Vec::new(),
+        };
+        ciphertexts.push((ciphertext, encryption_context));
+    }
+
+    context_kinds.sort_unstable();
+    context_kinds.dedup();
+
+    decrypt_bulk_inner(client, ciphertexts, &context_kinds, None).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_window_defaults_and_validation() {
+        let window: BatchWindow = serde_json::from_str("{}").unwrap();
+        assert_eq!(
+            window,
+            BatchWindow {
+                window_ms: 2,
+                max_items: 1000
+            }
+        );
+        assert!(window.validate().is_ok());
+
+        for window in [
+            r#"{"window_ms": 0}"#,
+            r#"{"window_ms": 101}"#,
+            r#"{"max_items": 1}"#,
+        ] {
+            let window: BatchWindow = serde_json::from_str(window).unwrap();
+            assert!(window.validate().is_err(), "{window:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_submit_runs_concurrent_calls_in_one_batch() {
+        let batcher: Batcher<(), u32, u32> = Batcher::new(BatchWindow {
+            window_ms: 20,
+            max_items: 10,
+        });
+        let runs = Mutex::new(Vec::new());
+        let run = |calls: Vec<u32>| {
+            lock(&runs).push(calls.len());
+            async move { Ok(calls.into_iter().map(|call| call * 2).collect()) }
+        };
+
+        let results = tokio::join!(
+            batcher.submit((), 1, run),
+            batcher.submit((), 2, run),
+            batcher.submit((), 3, run),
+        );
+
+        assert_eq!(results, (Some(2), Some(4), Some(6)));
+        assert_eq!(*lock(&runs), [3]);
+    }
+
+    #[test]
+    fn test_submit_batches_calls_from_threads() {
+        const THREADS: u32 = 8;
+
+        let batcher: Batcher<(), u32, u32> = Batcher::new(BatchWindow {
+            window_ms: 20,
+            max_items: 100,
+        });
+        let runs = Mutex::new(Vec::new());
+
+        let results: Vec<Option<u32>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..THREADS)
+                .map(|call| {
+                    let (batcher, runs) = (&batcher, &runs);
+                    scope.spawn(move || {
+                        let run = |calls: Vec<u32>| {
+                            lock(runs).push(calls.len());
+                            async move { Ok(calls.into_iter().map(|call| call * 2).collect()) }
+                        };
+                        crate::runtime()
+                            .unwrap()
+                            .block_on(batcher.submit((), call, run))
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        assert_eq!(
+            results,
+            (0..THREADS).map(|call| Some(call * 2)).collect::<Vec<_>>()
+        );
+        assert_eq!(lock(&runs).iter().sum::<usize>(), THREADS as usize);
+        assert!(lock(&runs).len() < THREADS as usize);
+    }
+
+    #[tokio::test]
+    async fn test_submit_returns_none_when_the_batch_fails() {
+        let batcher: Batcher<(), u32, u32> = Batcher::new(BatchWindow {
+            window_ms: 1,
+            max_items: 10,
+        });
+
+        let result = batcher
+            .submit((), 1, |_| async {
+                Err(Error::InvariantViolation("batch failed".to_string()))
+            })
+            .await;
+
+        assert_eq!(result, None);
+    }
+}
@@ -0,0 +1,324 @@
+//! On-host throughput measurement for `run_benchmark`.
+//!
+//! Benchmarks run the same code paths as the FFI functions, including envelope emission and
+//! serialization, against the client's real ZeroKMS workspace. They measure what a PHP worker
+//! on the same host would see, so they are useful when tuning runtime and allocator options.
+
+use crate::encrypt_config::Identifier;
+use crate::{
+    decrypt_bulk_inner, decrypt_inner, encrypt_bulk_inner, encrypt_inner, plaintext_targets,
+    Client, Encrypted, Error,
+};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Maximum number of timed iterations.
+const MAX_ITERATIONS: usize = 1_000;
+
+/// Maximum number of items per iteration.
+const MAX_BATCH_SIZE: usize = 10_000;
+
+/// Operation measured by a benchmark.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BenchmarkOperation {
+    /// One `encrypt` call per item.
+    Encrypt,
+    /// One `encrypt_bulk` call per iteration.
+    EncryptBulk,
+    /// One `decrypt` call per item.
+    Decrypt,
+    /// One `decrypt_bulk` call per iteration.
+    DecryptBulk,
+}
+
+/// Benchmark request, parsed from JSON.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BenchmarkSpec {
+    /// Operation to measure.
+    pub operation: BenchmarkOperation,
+    /// Table of the column to encrypt for.
+    pub table: String,
+    /// Column to encrypt for.
+    pub column: String,
+    /// Plaintext encrypted for every item.
+    pub plaintext: String,
+    /// Number of items per iteration.
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Number of timed iterations.
+    #[serde(default = "default_iterations")]
+    pub iterations: usize,
+    /// Number of untimed iterations run first, to warm up connections and caches.
+    #[serde(default = "default_warmup")]
+    pub warmup: usize,
+    /// Mean iteration time above which the benchmark is reported as a regression.
+    #[serde(default)]
+    pub max_mean_ms: Option<f64>,
+}
+
+fn default_batch_size() -> usize {
+    1
+}
+
+fn default_iterations() -> usize {
+    10
+}
+
+fn default_warmup() -> usize {
+    1
+}
+
+impl BenchmarkSpec {
+    /// Check that the spec describes a benchmark that can run in bounded time.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidBenchmark`] if the iterations or batch size are out of range.
+    pub fn validate(&self) -> Result<(), Error> {
+        if !(1..=MAX_ITERATIONS).contains(&self.iterations) {
+            return Err(Error::InvalidBenchmark(format!(
+                "`iterations` must be between 1 and {MAX_ITERATIONS}"
+            )));
+        }
+
+        if !(1..=MAX_BATCH_SIZE).contains(&self.batch_size) {
+            return Err(Error::InvalidBenchmark(format!(
+                "`batch_size` must be between 1 and {MAX_BATCH_SIZE}"
+            )));
+        }
+
+        if self.warmup > self.iterations {
+            return Err(Error::InvalidBenchmark(
+                "`warmup` must not exceed `iterations`".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Timing summary of a benchmark run.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct BenchmarkReport {
+    /// Operation measured.
+    pub operation: BenchmarkOperation,
+    /// Number of timed iterations.
+    pub iterations: usize,
+    /// Number of items per iteration.
+    pub batch_size: usize,
+    /// Mean iteration time in milliseconds.
+    pub mean_ms: f64,
+    /// Fastest iteration time in milliseconds.
+    pub min_ms: f64,
+    /// Slowest iteration time in milliseconds.
+    pub max_ms: f64,
+    /// Median iteration time in milliseconds.
+    pub p50_ms: f64,
+    /// 95th percentile iteration time in milliseconds.
+    pub p95_ms: f64,
+    /// Items processed per second across all timed iterations.
+    pub items_per_second: f64,
+    /// Whether the mean iteration time is within `max_mean_ms`, when a budget was given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub within_budget: Option<bool>,
+}
+
+/// Summarize the timed iterations of a benchmark.
+pub fn summarize(spec: &BenchmarkSpec, durations: &[Duration]) -> BenchmarkReport {
+    let mut millis: Vec<f64> = durations
+        .iter()
+        .map(|duration| duration.as_secs_f64() * 1_000.0)
+        .collect();
+    millis.sort_by(f64::total_cmp);
+
+    let total_ms: f64 = millis.iter().sum();
+    let mean_ms = total_ms / millis.len().max(1) as f64;
+    let percentile = |fraction: f64| {
+        let rank = (fraction * millis.len() as f64).ceil() as usize;
+        millis
+            .get(rank.saturating_sub(1))
+            .copied()
+            .unwrap_or_default()
+    };
+    let items = (spec.batch_size * millis.len()) as f64;
+
+    BenchmarkReport {
+        operation: spec.operation,
+        iterations: millis.len(),
+        batch_size: spec.batch_size,
+        mean_ms,
+        min_ms: millis.first().copied().unwrap_or_default(),
+        max_ms: millis.last().copied().unwrap_or_default(),
+        p50_ms: percentile(0.5),
+        p95_ms: percentile(0.95),
+        items_per_second: if total_ms > 0.0 {
+            items / (total_ms / 1_000.0)
+        } else {
+            0.0
+        },
+        within_budget: spec.max_mean_ms.map(|budget| mean_ms <= budget),
+    }
+}
+
+/// Run a benchmark against a client.
+///
+/// # Errors
+///
+/// Returns an error if the spec is invalid, the column is unknown, or any operation fails.
+pub async fn run(client: &Client, spec: &BenchmarkSpec) -> Result<BenchmarkReport, Error> {
+    spec.validate()?;
+
+    let identifier = client.resolve(Identifier::new(spec.table.as_str(), spec.column.as_str()));
+    client.column(&identifier)?;
+
+    // Decryption benchmarks decrypt the same envelopes in every iteration, whatever the
+    // client's output profile.
+    let envelopes = match spec.operation {
+        BenchmarkOperation::Encrypt | BenchmarkOperation::EncryptBulk => Vec::new(),
+        BenchmarkOperation::Decrypt | BenchmarkOperation::DecryptBulk => {
+            encrypt_batch(client, spec, &identifier)
+                .await?
+                .into_iter()
+                .map(|encrypted| Ok(serde_json::to_string(&client.emit_envelope(encrypted)?)?))
+                .collect::<Result<Vec<_>, Error>>()?
+        }
+    };
+
+    let mut durations = Vec::with_capacity(spec.iterations);
+
+    for iteration in 0..spec.warmup + spec.iterations {
+        let started = Instant::now();
+
+        match spec.operation {
+            BenchmarkOperation::Encrypt => {
+                for _ in 0..spec.batch_size {
+                    encrypt_one(client, spec, &identifier).await?;
+                }
+            }
+            BenchmarkOperation::EncryptBulk => {
+                for encrypted in encrypt_batch(client, spec, &identifier).await? {
+                    serde_json::to_string(&client.emit(encrypted)?)?;
+                }
+            }
+            BenchmarkOperation::Decrypt => {
+                for envelope in &envelopes {
+                    decrypt_inner(client, envelope.clone(), Vec::new(), &[], None).await?;
+                }
+            }
+            BenchmarkOperation::DecryptBulk => {
+                let ciphertexts = envelopes
+                    .iter()
+                    .map(|envelope| (envelope.clone(), Vec::new()))
+                    .collect();
+                decrypt_bulk_inner(client, ciphertexts, &[], None).await?;
+            }
+        }
+
+        if iteration >= spec.warmup {
+            durations.push(started.elapsed());
+        }
+    }
+
+    Ok(summarize(spec, &durations))
+}
+
+/// Encrypt the spec's plaintext once and serialize the emitted value, as `encrypt` does.
+async fn encrypt_one(
+    client: &Client,
+    spec: &BenchmarkSpec,
+    identifier: &Identifier,
+) -> Result<String, Error> {
+    let (column_config, cast_as) = client.column(identifier)?;
+
+    let targets = plaintext_targets(
+        spec.plaintext.clone(),
+        column_config,
+        *cast_as,
+        client.text_policy(identifier),
+        client.ste_vec_policy(identifier),
+        Vec::new(),
+    )?;
+    let encrypted = encrypt_inner(client, targets, identifier, cast_as, None).await?;
+
+    Ok(serde_json::to_string(&client.emit(encrypted)?)?)
+}
+
+/// Encrypt a batch of the spec's plaintext in one pipeline, as `encrypt_bulk` does.
+async fn encrypt_batch(
+    client: &Client,
+    spec: &BenchmarkSpec,
+    identifier: &Identifier,
+) -> Result<Vec<Encrypted>, Error> {
+    let (column_config, cast_as) = client.column(identifier)?;
+
+    let bulk_targets = (0..spec.batch_size)
+        .map(|_| {
+            let targets = plaintext_targets(
+                spec.plaintext.clone(),
+                column_config,
+                *cast_as,
+                client.text_policy(identifier),
+                client.ste_vec_policy(identifier),
+                Vec::new(),
+            )?;
+            Ok((targets, identifier.clone(), *cast_as))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    encrypt_bulk_inner(client, bulk_targets, None).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(json: &str) -> BenchmarkSpec {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_spec_defaults() {
+        let spec = spec(
+            r#"{"operation": "encrypt_bulk", "table": "users", "column": "email", "plaintext": "a"}"#,
+        );
+
+        assert_eq!(spec.operation, BenchmarkOperation::EncryptBulk);
+        assert_eq!(spec.batch_size, 1);
+        assert_eq!(spec.iterations, 10);
+        assert_eq!(spec.warmup, 1);
+        assert!(spec.validate().is_ok());
+    }
+
+    #[test]
+    fn test_spec_rejects_unbounded_runs() {
+        for json in [
+            r#"{"operation": "encrypt", "table": "t", "column": "c", "plaintext": "a", "iterations": 0}"#,
+            r#"{"operation": "encrypt", "table": "t", "column": "c", "plaintext": "a", "batch_size": 1000000}"#,
+            r#"{"operation": "encrypt", "table": "t", "column": "c", "plaintext": "a", "iterations": 2, "warmup": 3}"#,
+        ] {
+            let result = spec(json).validate();
+            assert!(matches!(result, Err(Error::InvalidBenchmark(_))), "{json}");
+        }
+    }
+
+    #[test]
+    fn test_summarize_statistics() {
+        let spec = spec(
+            r#"{"operation": "decrypt_bulk", "table": "t", "column": "c", "plaintext": "a", "batch_size": 100, "max_mean_ms": 25.0}"#,
+        );
+        let durations: Vec<Duration> = [40, 10, 20, 30].map(Duration::from_millis).to_vec();
+
+        let report = summarize(&spec, &durations);
+
+        assert_eq!(report.iterations, 4);
+        assert_eq!(report.mean_ms, 25.0);
+        assert_eq!(report.min_ms, 10.0);
+        assert_eq!(report.max_ms, 40.0);
+        assert_eq!(report.p50_ms, 20.0);
+        assert_eq!(report.p95_ms, 40.0);
+        assert_eq!(report.items_per_second, 4_000.0);
+        assert_eq!(report.within_budget, Some(true));
+    }
+}
@@ -0,0 +1,133 @@
+//! Reconciliation of stored envelope data types with the currently configured column type.
+//!
+//! When a column's `cast_as` changes after data was written, envelopes record the type the value
+//! was encrypted as in `dt`. Decryption accepts values whose stored type widens losslessly into
+//! the configured type, and rejects narrowing or unrelated conversions.
+
+use crate::encrypt_config::CastAs;
+use crate::Error;
+use serde_json::Value;
+
+/// Parse an envelope `dt` value into a [`CastAs`], returning `None` for unrecognized types.
+pub fn parse_data_type(data_type: &str) -> Option<CastAs> {
+    serde_json::from_value(Value::String(data_type.to_string())).ok()
+}
+
+/// Whether every value of the `stored` type is exactly representable in the `configured` type.
+fn is_widening(stored: CastAs, configured: CastAs) -> bool {
+    use CastAs::*;
+
+    matches!(
+        (stored, configured),
+        (SmallInt, Int | BigInt | Real | Double) | (Int, BigInt | Double) | (Real, Double)
+    )
+}
+
+/// Whether both types are numeric and the `configured` type cannot represent every stored value.
+fn is_narrowing(stored: CastAs, configured: CastAs) -> bool {
+    is_numeric(stored) && is_numeric(configured) && !is_widening(stored, configured)
+}
+
+fn is_numeric(cast_as: CastAs) -> bool {
+    matches!(
+        cast_as,
+        CastAs::SmallInt | CastAs::Int | CastAs::BigInt | CastAs::Real | CastAs::Double
+    )
+}
+
+/// Check that a decrypted plaintext stored as `stored` can be returned as `configured`.
+///
+/// Identical types and widening conversions return the plaintext unchanged, since its text
+/// representation is valid for both types. The plaintext is checked against the stored type so
+/// that a value which does not match its recorded type is not silently reinterpreted.
+///
+/// # Errors
+///
+/// Returns [`Error::NarrowingCast`] if the configured type cannot represent every stored value,
+/// or [`Error::IncompatibleCast`] if the types are unrelated or the plaintext does not match
+/// its stored type.
+pub fn reconcile(plaintext: String, stored: CastAs, configured: CastAs) -> Result<String, Error> {
+    if stored == configured {
+        return Ok(plaintext);
+    }
+
+    if is_narrowing(stored, configured) {
+        return Err(Error::NarrowingCast { stored, configured });
+    }
+
+    if !is_widening(stored, configured) || !matches_type(&plaintext, stored) {
+        return Err(Error::IncompatibleCast { stored, configured });
+    }
+
+    Ok(plaintext)
+}
+
+/// Whether a plaintext's text representation is a valid value of a numeric type.
+fn matches_type(plaintext: &str, cast_as: CastAs) -> bool {
+    let plaintext = plaintext.trim();
+
+    match cast_as {
+        CastAs::SmallInt => plaintext.parse::<i16>().is_ok(),
+        CastAs::Int => plaintext.parse::<i32>().is_ok(),
+        CastAs::BigInt => plaintext.parse::<i64>().is_ok(),
+        CastAs::Real => plaintext.parse::<f32>().is_ok(),
+        CastAs::Double => plaintext.parse::<f64>().is_ok(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_data_type() {
+        assert_eq!(parse_data_type("big_int"), Some(CastAs::BigInt));
+        assert_eq!(parse_data_type("jsonb"), Some(CastAs::JsonB));
+        assert_eq!(parse_data_type("varchar"), None);
+    }
+
+    #[test]
+    fn test_reconcile_widening_keeps_plaintext() {
+        let result = reconcile("42".to_string(), CastAs::Int, CastAs::BigInt).unwrap();
+        assert_eq!(result, "42");
+
+        let result = reconcile("1.5".to_string(), CastAs::Real, CastAs::Double).unwrap();
+        assert_eq!(result, "1.5");
+
+        let result = reconcile("-7".to_string(), CastAs::SmallInt, CastAs::Double).unwrap();
+        assert_eq!(result, "-7");
+    }
+
+    #[test]
+    fn test_reconcile_rejects_narrowing() {
+        for (stored, configured) in [
+            (CastAs::BigInt, CastAs::Int),
+            (CastAs::Int, CastAs::SmallInt),
+            (CastAs::Double, CastAs::Real),
+            (CastAs::BigInt, CastAs::Double),
+            (CastAs::Int, CastAs::Real),
+        ] {
+            let result = reconcile("1".to_string(), stored, configured);
+            assert!(
+                matches!(result, Err(Error::NarrowingCast { .. })),
+                "{stored} -> {configured}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_reconcile_rejects_incompatible_types() {
+        let result = reconcile("42".to_string(), CastAs::Text, CastAs::Int);
+        assert!(matches!(result, Err(Error::IncompatibleCast { .. })));
+
+        let result = reconcile("not a number".to_string(), CastAs::Int, CastAs::BigInt);
+        assert!(matches!(result, Err(Error::IncompatibleCast { .. })));
+    }
+
+    #[test]
+    fn test_reconcile_same_type_is_unchanged() {
+        let result = reconcile("john@example.com".to_string(), CastAs::Text, CastAs::Text);
+        assert_eq!(result.unwrap(), "john@example.com");
+    }
+}
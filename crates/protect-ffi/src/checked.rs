@@ -0,0 +1,204 @@
+//! Checked pointers for strings and clients handed across the FFI boundary.
+//!
+//! With the `checked-pointers` feature enabled, every string and client returned by this library
+//! is prefixed with a magic header that is validated before the pointer is used or freed. A
+//! pointer that was not returned by this library, or that was already freed, is reported through
+//! the invalid pointer handler and leaked instead of corrupting the heap.
+//!
+//! Double-free detection is best-effort: the header of a freed allocation is overwritten with a
+//! freed marker, but the allocator may reuse that memory before the pointer is freed again.
+
+use crate::safe_ffi::report_invalid_pointer;
+use crate::Client;
+use std::alloc::{self, Layout};
+use std::ffi::{c_char, CString};
+use std::mem::{align_of, offset_of, size_of};
+use std::ptr;
+
+const STRING_MAGIC: u64 = 0x7072_6f74_6563_7473;
+const CLIENT_MAGIC: u64 = 0x7072_6f74_6563_7463;
+const FREED_MAGIC: u64 = 0x6672_6565_6466_7265;
+
+/// Header preceding the bytes of a checked string.
+#[repr(C)]
+struct StringHeader {
+    magic: u64,
+    /// Length of the string data, including the null terminator.
+    len: usize,
+}
+
+/// A client allocation preceded by its magic header.
+#[repr(C)]
+struct CheckedClient {
+    magic: u64,
+    client: Client,
+}
+
+const STRING_HEADER_SIZE: usize = size_of::<StringHeader>();
+
+/// Result of validating a pointer's magic header.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Validity {
+    /// The pointer was returned by this library and has not been freed.
+    Live,
+    /// The pointer was returned by this library and has already been freed.
+    Freed,
+    /// The pointer was not returned by this library.
+    Foreign,
+}
+
+impl Validity {
+    fn of(magic: u64, expected: u64) -> Self {
+        match magic {
+            _ if magic == expected => Self::Live,
+            FREED_MAGIC => Self::Freed,
+            _ => Self::Foreign,
+        }
+    }
+
+    /// Describe an invalid pointer passed to the named function.
+    pub fn describe(&self, function: &str) -> String {
+        match self {
+            Self::Live => format!("`{function}` received a valid pointer"),
+            Self::Freed => format!("`{function}` received a pointer that was already freed"),
+            Self::Foreign => {
+                format!("`{function}` received a pointer that was not returned by this library")
+            }
+        }
+    }
+}
+
+fn string_layout(len: usize) -> Layout {
+    Layout::from_size_align(STRING_HEADER_SIZE + len, align_of::<StringHeader>())
+        .expect("string allocation size fits in `isize`")
+}
+
+/// Copy a C string into a checked allocation and return a pointer to its data.
+pub fn string_into_raw(string: CString) -> *mut c_char {
+    let bytes = string.as_bytes_with_nul();
+    let layout = string_layout(bytes.len());
+
+    unsafe {
+        let base = alloc::alloc(layout);
+        if base.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+
+        base.cast::<StringHeader>().write(StringHeader {
+            magic: STRING_MAGIC,
+            len: bytes.len(),
+        });
+
+        let data = base.add(STRING_HEADER_SIZE);
+        ptr::copy_nonoverlapping(bytes.as_ptr(), data, bytes.len());
+        data.cast()
+    }
+}
+
+/// Free a string returned by [`string_into_raw`], reporting invalid pointers instead.
+///
+/// # Safety
+///
+/// The caller must ensure the pointer is non-null and that the memory preceding it is readable.
+pub fn free_string(string: *mut c_char) {
+    unsafe {
+        let header = string
+            .cast::<u8>()
+            .sub(STRING_HEADER_SIZE)
+            .cast::<StringHeader>();
+
+        match Validity::of(ptr::read_unaligned(header.cast::<u64>()), STRING_MAGIC) {
+            Validity::Live => {
+                let len = (*header).len;
+                (*header).magic = FREED_MAGIC;
+                alloc::dealloc(header.cast(), string_layout(len));
+            }
+            invalid => report_invalid_pointer(&invalid.describe("free_string")),
+        }
+    }
+}
+
+fn client_header(client: *const Client) -> *mut CheckedClient {
+    client
+        .cast::<u8>()
+        .wrapping_sub(offset_of!(CheckedClient, client))
+        .cast::<CheckedClient>()
+        .cast_mut()
+}
+
+/// Move a client into a checked allocation and return a pointer to the client.
+pub fn client_into_raw(client: Client) -> *mut Client {
+    let checked = Box::into_raw(Box::new(CheckedClient {
+        magic: CLIENT_MAGIC,
+        client,
+    }));
+
+    unsafe { ptr::addr_of_mut!((*checked).client) }
+}
+
+/// Validate a client pointer returned by [`client_into_raw`].
+///
+/// # Safety
+///
+/// The caller must ensure the pointer is non-null and that the memory preceding it is readable.
+pub fn validate_client(client: *const Client) -> Validity {
+    unsafe {
+        Validity::of(
+            ptr::read_unaligned(client_header(client).cast::<u64>()),
+            CLIENT_MAGIC,
+        )
+    }
+}
+
+/// Free a client returned by [`client_into_raw`], reporting invalid pointers instead.
+///
+/// # Safety
+///
+/// The caller must ensure the pointer is non-null and that the memory preceding it is readable.
+pub fn free_client(client: *mut Client) {
+    match validate_client(client) {
+        Validity::Live => unsafe {
+            let checked = client_header(client);
+            (*checked).magic = FREED_MAGIC;
+            drop(Box::from_raw(checked));
+        },
+        invalid => report_invalid_pointer(&invalid.describe("free_client")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    #[test]
+    fn test_checked_string_round_trip() {
+        let string = string_into_raw(CString::new("john@example.com").unwrap());
+
+        let restored = unsafe { CStr::from_ptr(string) };
+        assert_eq!(restored.to_str().unwrap(), "john@example.com");
+
+        free_string(string);
+    }
+
+    #[test]
+    fn test_foreign_string_is_not_freed() {
+        let mut buffer = [0u8; STRING_HEADER_SIZE + 4];
+        buffer[STRING_HEADER_SIZE..].copy_from_slice(b"abc\0");
+        let string = unsafe { buffer.as_mut_ptr().add(STRING_HEADER_SIZE) }.cast::<c_char>();
+
+        free_string(string);
+
+        assert_eq!(&buffer[STRING_HEADER_SIZE..], b"abc\0");
+    }
+
+    #[test]
+    fn test_validity_of_magic() {
+        assert_eq!(Validity::of(STRING_MAGIC, STRING_MAGIC), Validity::Live);
+        assert_eq!(Validity::of(FREED_MAGIC, STRING_MAGIC), Validity::Freed);
+        assert_eq!(Validity::of(CLIENT_MAGIC, STRING_MAGIC), Validity::Foreign);
+        assert!(Validity::Freed
+            .describe("free_string")
+            .contains("already freed"));
+    }
+}
@@ -0,0 +1,341 @@
+//! Client runtime options controlling envelope output and operation behavior.
+
+use crate::{
+    batching::BatchWindow, config_format::ConfigFormat, envelope, index_encoding::IndexEncoding,
+    rate_limit::RateLimit, signing::SigningKey, term_cache::CacheConfig, Error,
+};
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// Runtime options for a [`crate::Client`], parsed from JSON.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClientOptions {
+    /// Envelope schema version written by encryption operations.
+    pub emit_version: u16,
+    /// Hex-encoded HMAC key used to sign emitted envelopes and verify them on decryption.
+    pub signing_key: Option<SigningKey>,
+    /// Whether decryption rejects unsigned envelopes and raw ciphertexts when a signing key
+    /// is configured. Enabled by default, since otherwise removing an envelope's signature
+    /// skips its verification; disable it only while migrating unsigned data.
+    pub require_signature: bool,
+    /// Shape of the values returned by `encrypt` and `encrypt_bulk`.
+    pub output_profile: OutputProfile,
+    /// Text encoding of `hm` and `ob` index terms and `ste_vec` selectors and terms.
+    pub index_encoding: IndexEncoding,
+    /// Whether configurations with `match` indexes that set `include_original` are rejected.
+    pub forbid_include_original: bool,
+    /// Whether decryption is disabled, for services that encrypt data but must never read it.
+    pub write_only: bool,
+    /// Rate limit of the client's ZeroKMS calls, shared with the clients derived from it.
+    pub rate_limit: Option<RateLimit>,
+    /// Window in which concurrent single-item `encrypt` and `decrypt` calls are batched.
+    pub batching: Option<BatchWindow>,
+    /// The most queued and in-flight operations of the client and the clients derived from it,
+    /// beyond which new operations are rejected.
+    pub max_outstanding: Option<usize>,
+    /// The most bytes a plaintext may have, beyond which encryption is refused before any work
+    /// is done.
+    pub max_plaintext_bytes: Option<usize>,
+    /// How requested table and column names are matched to the configuration.
+    pub identifier_lookup: IdentifierLookup,
+    /// Whether the client refuses connections to hosts other than CTS and ZeroKMS.
+    pub offline: bool,
+    /// Whether decryption refuses envelopes whose `exp` expiry has passed.
+    pub enforce_expiry: bool,
+    /// Size and lifetime of the cache of search term values, shared with the clients derived
+    /// from the client under the same keyset.
+    pub search_term_cache: Option<CacheConfig>,
+    /// Format of the encryption configuration, or `None` to detect it from the configuration's
+    /// text.
+    pub config_format: Option<ConfigFormat>,
+}
+
+/// Shape of encrypted values, for the database the values are stored in.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputProfile {
+    /// An envelope with its index terms, for a single `eql_v2_encrypted` column in PostgreSQL.
+    #[default]
+    Postgres,
+    /// An object of storage columns, holding the envelope and each index term separately.
+    Mysql,
+}
+
+/// How requested table and column names are matched to the names in the configuration.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum IdentifierLookup {
+    /// Names must match exactly.
+    #[default]
+    Exact,
+    /// Names match ignoring case, such as PostgreSQL's lowercase folding of unquoted names.
+    /// Envelopes record the configured names.
+    CaseInsensitive,
+}
+
+/// Overrides applied to a client derived with `derive_client`, for a view of the client scoped
+/// to a request or tenant. Omitted fields keep the values of the parent client.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClientOverlay {
+    /// Encryption context used by requests that supply none.
+    pub context: Option<serde_json::Value>,
+    /// Named keyset that columns without a keyset of their own are encrypted under.
+    pub keyset: Option<String>,
+    /// Whether decryption is disabled. A write-only parent cannot derive a client that decrypts.
+    pub write_only: bool,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            emit_version: envelope::CURRENT_VERSION,
+            signing_key: None,
+            require_signature: true,
+            output_profile: OutputProfile::default(),
+            index_encoding: IndexEncoding::default(),
+            forbid_include_original: false,
+            write_only: false,
+            rate_limit: None,
+            batching: None,
+            max_outstanding: None,
+            max_plaintext_bytes: None,
+            identifier_lookup: IdentifierLookup::default(),
+            offline: false,
+            enforce_expiry: false,
+            search_term_cache: None,
+            config_format: None,
+        }
+    }
+}
+
+impl FromStr for ClientOptions {
+    type Err = Error;
+
+    fn from_str(data: &str) -> Result<Self, Self::Err> {
+        let options: ClientOptions = serde_json::from_str(data)?;
+
+        if !envelope::SUPPORTED_VERSIONS.contains(&options.emit_version) {
+            return Err(Error::UnsupportedEnvelopeVersion(
+                options.emit_version.into(),
+            ));
+        }
+
+        if let Some(rate_limit) = &options.rate_limit {
+            rate_limit.validate().map_err(|reason| {
+                Error::InvalidClientOptions(format!("invalid `rate_limit`: {reason}"))
+            })?;
+        }
+
+        if let Some(batching) = &options.batching {
+            batching.validate().map_err(|reason| {
+                Error::InvalidClientOptions(format!("invalid `batching`: {reason}"))
+            })?;
+        }
+
+        if let Some(search_term_cache) = &options.search_term_cache {
+            search_term_cache.validate().map_err(|reason| {
+                Error::InvalidClientOptions(format!("invalid `search_term_cache`: {reason}"))
+            })?;
+        }
+
+        if options.max_outstanding == Some(0) {
+            return Err(Error::InvalidClientOptions(
+                "`max_outstanding` must be at least 1".to_string(),
+            ));
+        }
+
+        if options.max_plaintext_bytes == Some(0) {
+            return Err(Error::InvalidClientOptions(
+                "`max_plaintext_bytes` must be at least 1".to_string(),
+            ));
+        }
+
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_only_option() {
+        let options = ClientOptions::from_str(r#"{"write_only": true}"#).unwrap();
+
+        assert!(options.write_only);
+        assert!(!ClientOptions::default().write_only);
+    }
+
+    #[test]
+    fn test_rate_limit_option() {
+        let options =
+            ClientOptions::from_str(r#"{"rate_limit": {"requests_per_second": 20, "burst": 5}}"#)
+                .unwrap();
+        assert_eq!(options.rate_limit.map(|limit| limit.burst), Some(5));
+
+        let result = ClientOptions::from_str(r#"{"rate_limit": {"requests_per_second": -1}}"#);
+        assert!(matches!(result, Err(Error::InvalidClientOptions(_))));
+    }
+
+    #[test]
+    fn test_batching_option() {
+        let options = ClientOptions::from_str(r#"{"batching": {"window_ms": 5}}"#).unwrap();
+        assert_eq!(options.batching.map(|window| window.window_ms), Some(5));
+
+        let result = ClientOptions::from_str(r#"{"batching": {"window_ms": 1000}}"#);
+        assert!(matches!(result, Err(Error::InvalidClientOptions(_))));
+    }
+
+    #[test]
+    fn test_max_outstanding_option() {
+        let options = ClientOptions::from_str(r#"{"max_outstanding": 64}"#).unwrap();
+        assert_eq!(options.max_outstanding, Some(64));
+
+        let result = ClientOptions::from_str(r#"{"max_outstanding": 0}"#);
+        assert!(matches!(result, Err(Error::InvalidClientOptions(_))));
+    }
+
+    #[test]
+    fn test_max_plaintext_bytes_option() {
+        let options = ClientOptions::from_str(r#"{"max_plaintext_bytes": 1048576}"#).unwrap();
+        assert_eq!(options.max_plaintext_bytes, Some(1_048_576));
+
+        let result = ClientOptions::from_str(r#"{"max_plaintext_bytes": 0}"#);
+        assert!(matches!(result, Err(Error::InvalidClientOptions(_))));
+    }
+
+    #[test]
+    fn test_identifier_lookup_option() {
+        let options =
+            ClientOptions::from_str(r#"{"identifier_lookup": "case_insensitive"}"#).unwrap();
+        assert_eq!(options.identifier_lookup, IdentifierLookup::CaseInsensitive);
+        assert_eq!(
+            ClientOptions::default().identifier_lookup,
+            IdentifierLookup::Exact
+        );
+    }
+
+    #[test]
+    fn test_offline_option() {
+        let options = ClientOptions::from_str(r#"{"offline": true}"#).unwrap();
+
+        assert!(options.offline);
+        assert!(!ClientOptions::default().offline);
+    }
+
+    #[test]
+    fn test_enforce_expiry_option() {
+        let options = ClientOptions::from_str(r#"{"enforce_expiry": true}"#).unwrap();
+
+        assert!(options.enforce_expiry);
+        assert!(!ClientOptions::default().enforce_expiry);
+    }
+
+    #[test]
+    fn test_search_term_cache_option() {
+        let options =
+            ClientOptions::from_str(r#"{"search_term_cache": {"capacity": 500}}"#).unwrap();
+        let config = options.search_term_cache.unwrap();
+        assert_eq!((config.capacity, config.ttl_ms), (500, 60_000));
+
+        let result = ClientOptions::from_str(r#"{"search_term_cache": {"ttl_ms": 0}}"#);
+        assert!(matches!(result, Err(Error::InvalidClientOptions(_))));
+    }
+
+    #[test]
+    fn test_config_format_option() {
+        let options = ClientOptions::from_str(r#"{"config_format": "yaml"}"#).unwrap();
+        assert_eq!(options.config_format, Some(ConfigFormat::Yaml));
+
+        let result = ClientOptions::from_str(r#"{"config_format": "xml"}"#);
+        assert!(matches!(result, Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn test_client_overlay() {
+        let overlay: ClientOverlay =
+            serde_json::from_str(r#"{"context": {"tag": ["tenant-a"]}, "keyset": "tenant_a"}"#)
+                .unwrap();
+        assert_eq!(overlay.keyset.as_deref(), Some("tenant_a"));
+        assert!(overlay.context.is_some());
+
+        let result = serde_json::from_str::<ClientOverlay>(r#"{"dataset": "tenant_a"}"#);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_default_options() {
+        let options = ClientOptions::from_str("{}").unwrap();
+
+        assert_eq!(options, ClientOptions::default());
+        assert_eq!(options.emit_version, envelope::CURRENT_VERSION);
+    }
+
+    #[test]
+    fn test_emit_version_option() {
+        let options = ClientOptions::from_str(r#"{"emit_version": 1}"#).unwrap();
+
+        assert_eq!(options.emit_version, 1);
+    }
+
+    #[test]
+    fn test_unsupported_emit_version_fails() {
+        let result = ClientOptions::from_str(r#"{"emit_version": 7}"#);
+
+        assert!(matches!(result, Err(Error::UnsupportedEnvelopeVersion(7))));
+    }
+
+    #[test]
+    fn test_signing_options() {
+        let options = ClientOptions::from_str(
+            r#"{"signing_key": "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"}"#,
+        )
+        .unwrap();
+
+        assert!(options.signing_key.is_some());
+        assert!(options.require_signature);
+
+        let options = ClientOptions::from_str(
+            r#"{"signing_key": "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f", "require_signature": false}"#,
+        )
+        .unwrap();
+
+        assert!(!options.require_signature);
+    }
+
+    #[test]
+    fn test_output_profile_option() {
+        let options = ClientOptions::from_str(r#"{"output_profile": "mysql"}"#).unwrap();
+
+        assert_eq!(options.output_profile, OutputProfile::Mysql);
+        assert_eq!(
+            ClientOptions::default().output_profile,
+            OutputProfile::Postgres
+        );
+    }
+
+    #[test]
+    fn test_index_encoding_option() {
+        let options = ClientOptions::from_str(r#"{"index_encoding": "base64"}"#).unwrap();
+
+        assert_eq!(options.index_encoding, IndexEncoding::Base64);
+        assert_eq!(ClientOptions::default().index_encoding, IndexEncoding::Hex);
+    }
+
+    #[test]
+    fn test_forbid_include_original_option() {
+        let options = ClientOptions::from_str(r#"{"forbid_include_original": true}"#).unwrap();
+
+        assert!(options.forbid_include_original);
+        assert!(!ClientOptions::default().forbid_include_original);
+    }
+
+    #[test]
+    fn test_unknown_option_fails() {
+        let result = ClientOptions::from_str(r#"{"emit_versions": 1}"#);
+
+        assert!(matches!(result, Err(Error::Parse(_))));
+    }
+}
@@ -0,0 +1,199 @@
+//! Snapshots of a client's non-secret state.
+//!
+//! Creating a client reads its credentials, contacts CTS and ZeroKMS, and resolves their hosts.
+//! `export_client_state` captures what a client knows that is not secret: a digest of its
+//! configuration, its keyset IDs, and the CTS and ZeroKMS endpoints with the addresses their
+//! hosts resolve to. `import_client_state` compares a snapshot with a client, so a worker
+//! spawned or forked from a supervisor can confirm that it serves the same configuration from
+//! the same endpoints as the snapshot it was given.
+//!
+//! The encryption library keeps its service tokens, their expiry, and its connections to
+//! itself, so snapshots do not include token metadata, and importing one checks a client rather
+//! than shortening its creation. Credentials are never part of a snapshot.
+
+use crate::network::{Endpoint, NetworkPolicy};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+/// Version of the snapshot format.
+pub const STATE_VERSION: u16 = 1;
+
+/// A snapshot of a client's non-secret state.
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClientState {
+    /// Version of the snapshot format.
+    #[serde(rename = "v")]
+    pub version: u16,
+    /// The version of the library that exported the snapshot.
+    pub library_version: String,
+    /// When the snapshot was exported, in seconds since the Unix epoch.
+    pub exported_at: u64,
+    /// SHA-256 digest of the client's encryption configuration.
+    pub config_digest: String,
+    /// The ID of each named keyset.
+    pub keysets: BTreeMap<String, Uuid>,
+    /// The CTS endpoint.
+    pub cts: ResolvedEndpoint,
+    /// The ZeroKMS endpoint.
+    pub zerokms: ResolvedEndpoint,
+}
+
+/// A service endpoint, with the addresses its host resolved to.
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ResolvedEndpoint {
+    /// The service's base URL.
+    pub url: String,
+    /// The service's host.
+    pub host: String,
+    /// The service's port.
+    pub port: u16,
+    /// The addresses the host resolved to, empty if it was not resolved.
+    pub addresses: Vec<String>,
+}
+
+/// How a snapshot compares with a client, returned by `import_client_state`.
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    /// Whether the snapshot describes a client like this one.
+    pub compatible: bool,
+    /// How the snapshot differs from the client.
+    pub differences: Vec<String>,
+    /// How long ago the snapshot was exported, in seconds.
+    pub age_secs: u64,
+}
+
+/// Capture the state of a client, resolving the hosts of its endpoints when `resolve` is set.
+/// Hosts the client's network policy forbids, and hosts that fail to resolve, have no
+/// addresses.
+pub async fn capture(
+    config_digest: &str,
+    keysets: &HashMap<String, Uuid>,
+    network: &NetworkPolicy,
+    resolve: bool,
+) -> ClientState {
+    ClientState {
+        version: STATE_VERSION,
+        library_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: now(),
+        config_digest: config_digest.to_string(),
+        keysets: keysets
+            .iter()
+            .map(|(name, id)| (name.clone(), *id))
+            .collect(),
+        cts: resolve_endpoint(network, &network.cts, resolve).await,
+        zerokms: resolve_endpoint(network, &network.zerokms, resolve).await,
+    }
+}
+
+async fn resolve_endpoint(
+    network: &NetworkPolicy,
+    endpoint: &Endpoint,
+    resolve: bool,
+) -> ResolvedEndpoint {
+    let addresses = if resolve && network.check(endpoint).is_ok() {
+        tokio::net::lookup_host((endpoint.host.as_str(), endpoint.port))
+            .await
+            .map(|addresses| addresses.map(|address| address.ip().to_string()).collect())
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+
+    ResolvedEndpoint {
+        url: endpoint.url.clone(),
+        host: endpoint.host.clone(),
+        port: endpoint.port,
+        addresses,
+    }
+}
+
+impl ClientState {
+    /// Compare an imported snapshot with the state of a client. Resolved addresses are not
+    /// compared, since they change with DNS.
+    pub fn compare(&self, current: &ClientState) -> ImportReport {
+        let mut differences = Vec::new();
+
+        if self.version > STATE_VERSION {
+            differences.push(format!(
+                "the snapshot format version {} is newer than the supported version {STATE_VERSION}",
+                self.version
+            ));
+        }
+        if self.library_version != current.library_version {
+            differences.push(format!(
+                "the snapshot was exported by protect-ffi {}, but the client is protect-ffi {}",
+                self.library_version, current.library_version
+            ));
+        }
+        if self.config_digest != current.config_digest {
+            differences.push("the encryption configurations differ".to_string());
+        }
+        if self.keysets != current.keysets {
+            differences.push("the named keysets differ".to_string());
+        }
+        for (service, imported, current) in [
+            ("CTS", &self.cts, &current.cts),
+            ("ZeroKMS", &self.zerokms, &current.zerokms),
+        ] {
+            if imported.url != current.url {
+                differences.push(format!(
+                    "the snapshot's {service} endpoint is `{}`, but the client's is `{}`",
+                    imported.url, current.url
+                ));
+            }
+        }
+
+        ImportReport {
+            compatible: differences.is_empty(),
+            differences,
+            age_secs: current.exported_at.saturating_sub(self.exported_at),
+        }
+    }
+}
+
+/// The current time, in seconds since the Unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> NetworkPolicy {
+        let endpoint = |host: &str| Endpoint::new(format!("https://{host}/"), Some(host), None);
+        NetworkPolicy {
+            offline: true,
+            cts: endpoint("ap-southeast-2.aws.auth.viturhosted.net"),
+            zerokms: endpoint("ap-southeast-2.aws.viturhosted.net"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compare_snapshots() {
+        let keysets = HashMap::from([("tenant_a".to_string(), Uuid::nil())]);
+        let mut snapshot = capture("digest", &keysets, &policy(), false).await;
+        snapshot.exported_at -= 60;
+
+        let current = capture("digest", &keysets, &policy(), false).await;
+        let report = snapshot.compare(&current);
+        assert!(report.compatible);
+        assert!(report.age_secs >= 60);
+
+        let roundtrip: ClientState =
+            serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+        assert_eq!(roundtrip, snapshot);
+
+        snapshot.config_digest = "other".to_string();
+        snapshot.zerokms.url = "https://us-east-1.aws.viturhosted.net/".to_string();
+        let report = snapshot.compare(&current);
+        assert!(!report.compatible);
+        assert_eq!(report.differences.len(), 2);
+    }
+}
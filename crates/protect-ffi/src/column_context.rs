@@ -0,0 +1,135 @@
+//! Encryption context derived from the configuration of a column.
+//!
+//! Context that every value of a column should carry, such as its table name or deployment
+//! environment, is easy to forget on one of the code paths that encrypt it. A column can
+//! instead declare it as `context_from`, a context such as
+//! `{"value": [{"key": "table", "value": "$table"}], "tag": ["${APP_ENV}"]}`, in which `$table`
+//! and `$column` stand for the column's table and column names and `$$` is a literal `$`.
+//! Environment variables are resolved by configuration interpolation when the configuration is
+//! parsed. The derived context is added to the context of every value encrypted for the column,
+//! and to the context of every envelope of the column that is decrypted, so callers never supply
+//! it. Raw ciphertexts do not name their column, so they must be decrypted with the derived
+//! context supplied explicitly.
+//!
+//! `jwt` and `associated_data` entries describe a request or a row rather than a column, so
+//! they are not allowed.
+
+use crate::context::{items_from_value, ContextItem};
+use crate::encrypt_config::Identifier;
+use serde_json::Value;
+
+/// The context kinds a column cannot derive.
+const DISALLOWED_KINDS: &[&str] = &["jwt", "associated_data"];
+
+/// The context items a column's `context_from` template derives.
+///
+/// # Errors
+///
+/// Returns a description of the problem if the template is not a valid context, contains a
+/// disallowed kind, or references a variable other than `$table` and `$column`.
+pub fn derive(template: &Value, identifier: &Identifier) -> Result<Vec<ContextItem>, String> {
+    let object = template
+        .as_object()
+        .ok_or_else(|| "`context_from` must be a context object".to_string())?;
+
+    if let Some(kind) = DISALLOWED_KINDS
+        .iter()
+        .find(|kind| object.contains_key(**kind))
+    {
+        return Err(format!(
+            "`context_from` cannot contain `{kind}` context, which varies by request"
+        ));
+    }
+
+    let context = fill(template, identifier)?;
+    items_from_value(&context).map_err(|error| format!("`context_from` is invalid: {error}"))
+}
+
+/// A template with the variables of every string replaced.
+fn fill(template: &Value, identifier: &Identifier) -> Result<Value, String> {
+    Ok(match template {
+        Value::String(text) => Value::String(substitute(text, identifier)?),
+        Value::Array(values) => Value::Array(
+            values
+                .iter()
+                .map(|value| fill(value, identifier))
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), fill(value, identifier)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+        value => value.clone(),
+    })
+}
+
+/// A string with `$table` and `$column` replaced by the identifier's names, and `$$` by `$`.
+fn substitute(text: &str, identifier: &Identifier) -> Result<String, String> {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('$') {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let name_len = after
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(after.len());
+
+        match &after[..name_len] {
+            "table" => output.push_str(&identifier.table),
+            "column" => output.push_str(&identifier.column),
+            "" if after.starts_with('$') => {
+                output.push('$');
+                rest = &after[1..];
+                continue;
+            }
+            name => {
+                return Err(format!(
+                    "`${name}` in `{text}` is not a variable, expected `$table` or `$column`, or `$$` for a literal `$`"
+                ))
+            }
+        }
+        rest = &after[name_len..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_derive() {
+        let identifier = Identifier::new("users", "email");
+
+        let items = derive(
+            &json!({"value": [{"key": "table", "value": "$table"}], "tag": ["$table.$column", "$$5"]}),
+            &identifier,
+        )
+        .unwrap();
+        assert_eq!(
+            items,
+            [
+                ContextItem::Tag("users.email".to_string()),
+                ContextItem::Tag("$5".to_string()),
+                ContextItem::Value("table".to_string(), "users".to_string()),
+            ]
+        );
+
+        for template in [
+            json!(["$table"]),
+            json!({"tag": ["$tabel"]}),
+            json!({"tag": ["$"]}),
+            json!({"associated_data": "$table"}),
+            json!({"jwt": {"token": "x", "claims": {}}}),
+            json!({"value": [{"key": "protect.table", "value": "$table"}]}),
+        ] {
+            assert!(derive(&template, &identifier).is_err(), "{template}");
+        }
+    }
+}
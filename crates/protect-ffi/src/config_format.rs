@@ -0,0 +1,138 @@
+//! Encryption configurations written in YAML or TOML.
+//!
+//! PHP projects often keep infrastructure configuration in YAML or TOML files. `new_client` and
+//! `validate_config` accept a configuration in either format, as well as JSON, and convert it
+//! to JSON before parsing, so every format is validated the same way. The format is detected
+//! from the text of the configuration, or set with the `config_format` client option and the
+//! `format` parameter of `validate_config`.
+
+use crate::Error;
+use serde::Deserialize;
+use std::borrow::Cow;
+use strum::Display;
+
+/// The format an encryption configuration is written in.
+#[derive(Clone, Copy, Debug, Deserialize, Display, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigFormat {
+    /// A JSON object.
+    #[strum(serialize = "JSON")]
+    Json,
+    /// A YAML mapping. Requires the `yaml` feature.
+    #[strum(serialize = "YAML")]
+    Yaml,
+    /// A TOML document. Requires the `toml` feature.
+    #[strum(serialize = "TOML")]
+    Toml,
+}
+
+/// The format of a configuration, detected from its first line that is not blank or a comment.
+///
+/// A line starting with `{` is JSON. A line starting with a `[table]` header, or a `key = value`
+/// line whose key has no colon, is TOML. Anything else is YAML.
+pub fn detect(data: &str) -> ConfigFormat {
+    let Some(line) = data
+        .lines()
+        .map(|line| line.trim_start_matches('\u{feff}').trim())
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+    else {
+        return ConfigFormat::Json;
+    };
+
+    if line.starts_with('{') {
+        ConfigFormat::Json
+    } else if line.starts_with('[')
+        || line
+            .split_once('=')
+            .is_some_and(|(key, _)| !key.contains(':'))
+    {
+        ConfigFormat::Toml
+    } else {
+        ConfigFormat::Yaml
+    }
+}
+
+/// The JSON text of a configuration written in a format.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidConfigSyntax`] if the configuration is not valid in its format, or
+/// [`Error::Unimplemented`] if support for the format was not built.
+pub fn to_json(data: &str, format: ConfigFormat) -> Result<Cow<'_, str>, Error> {
+    let value: serde_json::Value = match format {
+        ConfigFormat::Json => return Ok(Cow::Borrowed(data)),
+        #[cfg(feature = "yaml")]
+        ConfigFormat::Yaml => serde_yaml::from_str(data)
+            .map_err(|err| Error::InvalidConfigSyntax(format, err.to_string()))?,
+        #[cfg(feature = "toml")]
+        ConfigFormat::Toml => toml::from_str(data)
+            .map_err(|err| Error::InvalidConfigSyntax(format, err.to_string()))?,
+        #[allow(unreachable_patterns)]
+        _ => {
+            return Err(Error::Unimplemented(format!(
+                "{format} configuration, which requires building with the `{}` feature",
+                format.to_string().to_lowercase()
+            )))
+        }
+    };
+
+    Ok(Cow::Owned(value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        for (data, format) in [
+            (r#"{"v": 2, "tables": {}}"#, ConfigFormat::Json),
+            ("", ConfigFormat::Json),
+            (
+                "# encryption\nv: 2\ntables:\n  users: {}\n",
+                ConfigFormat::Yaml,
+            ),
+            ("---\nv: 2\n", ConfigFormat::Yaml),
+            ("url: \"a=b\"\n", ConfigFormat::Yaml),
+            (
+                "v = 2\n\n[tables.users.email]\ncast_as = \"text\"\n",
+                ConfigFormat::Toml,
+            ),
+            ("# encryption\n[tables.users.email]\n", ConfigFormat::Toml),
+        ] {
+            assert_eq!(detect(data), format, "{data}");
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "yaml", feature = "toml"))]
+    fn test_to_json() {
+        let expected = serde_json::json!({
+            "v": 2,
+            "tables": {"users": {"email": {"cast_as": "text", "indexes": {"unique": {}}}}}
+        });
+
+        let yaml = "v: 2\ntables:\n  users:\n    email:\n      cast_as: text\n      indexes:\n        unique: {}\n";
+        let json = to_json(yaml, ConfigFormat::Yaml).unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            expected
+        );
+
+        let toml = "v = 2\n\n[tables.users.email]\ncast_as = \"text\"\nindexes = { unique = {} }\n";
+        let json = to_json(toml, ConfigFormat::Toml).unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            expected
+        );
+
+        assert!(matches!(
+            to_json("v: [2", ConfigFormat::Yaml),
+            Err(Error::InvalidConfigSyntax(ConfigFormat::Yaml, _))
+        ));
+        assert!(matches!(
+            to_json("v = ", ConfigFormat::Toml),
+            Err(Error::InvalidConfigSyntax(ConfigFormat::Toml, _))
+        ));
+    }
+}
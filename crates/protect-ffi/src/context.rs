@@ -0,0 +1,685 @@
+//! Encryption context parsing into ZeroKMS context values.
+//!
+//! Value keys beginning with [`RESERVED_PREFIX`] are reserved for context the library binds
+//! itself, such as the row identity of `associated_data` and the expiry of expiring values.
+
+use crate::{jwt, Error};
+use cipherstash_client::zerokms;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+
+/// The prefix of the value keys reserved for context bound by the library.
+pub const RESERVED_PREFIX: &str = "protect.";
+
+/// The value key associated data is bound to the ciphertext under.
+pub const ASSOCIATED_DATA_KEY: &str = "protect.associated_data";
+
+/// A single parsed encryption context entry.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContextItem {
+    /// Identity claim name resolved from the CTS token.
+    IdentityClaim(String),
+    /// Free-form string tag.
+    Tag(String),
+    /// Key-value pair with a canonically stringified value.
+    Value(String, String),
+    /// Caller-supplied data identifying where the value is stored, such as its row's primary
+    /// key.
+    AssociatedData(String),
+}
+
+impl ContextItem {
+    /// The context kind name, as used in the context JSON.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::IdentityClaim(_) => "identity_claim",
+            Self::Tag(_) => "tag",
+            Self::Value(..) => "value",
+            Self::AssociatedData(_) => "associated_data",
+        }
+    }
+}
+
+impl From<ContextItem> for zerokms::Context {
+    fn from(item: ContextItem) -> Self {
+        match item {
+            ContextItem::IdentityClaim(claim) => zerokms::Context::new_identity_claim(&claim),
+            ContextItem::Tag(tag) => zerokms::Context::new_tag(&tag),
+            ContextItem::Value(key, value) => zerokms::Context::new_value(&key, &value),
+            ContextItem::AssociatedData(data) => {
+                zerokms::Context::new_value(ASSOCIATED_DATA_KEY, &data)
+            }
+        }
+    }
+}
+
+/// Context derived from the claims of a JWT.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JwtContext {
+    /// The compact-serialized token.
+    token: String,
+    /// Mapping of claim names to context targets: `tag` or `value:<key>`.
+    claims: BTreeMap<String, String>,
+    /// Signature verification settings. Required, since an unverified token's claims are
+    /// whatever the caller chose.
+    verify: jwt::Verification,
+    /// Allowed clock skew in seconds for `exp` and `nbf` checks.
+    #[serde(default)]
+    leeway: u64,
+}
+
+/// Converts already-parsed JSON encryption context into ZeroKMS context objects.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidContext`] if any entry would otherwise be dropped.
+pub fn encryption_context_from_value(context: &Value) -> Result<Vec<zerokms::Context>, Error> {
+    Ok(items_from_value(context)?
+        .into_iter()
+        .map(zerokms::Context::from)
+        .collect())
+}
+
+/// Converts already-parsed JSON encryption context into ZeroKMS context objects, along with the
+/// distinct context kinds supplied for use in diagnostics.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidContext`] if any entry would otherwise be dropped.
+pub fn with_kinds_from_value(
+    context: &Value,
+) -> Result<(Vec<zerokms::Context>, Vec<&'static str>), Error> {
+    let items = items_from_value(context)?;
+    let kinds = kinds(&items);
+
+    Ok((
+        items.into_iter().map(zerokms::Context::from).collect(),
+        kinds,
+    ))
+}
+
+/// Parsed encryption contexts, keyed by their JSON, for the duration of one bulk call.
+///
+/// Bulk items usually share a handful of tenant contexts, so parsing each item's context on its
+/// own repeats the same work, including verifying any `jwt` entry, thousands of times. A cache
+/// parses each distinct context once. It is not kept across calls, so a JWT context is always
+/// verified again against the current time.
+#[derive(Debug, Default)]
+pub struct ContextCache {
+    parsed: HashMap<String, Vec<ContextItem>>,
+}
+
+impl ContextCache {
+    /// Converts JSON encryption context into ZeroKMS context objects, parsing it only if an
+    /// identical context was not parsed before.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidContext`] if any entry would otherwise be dropped.
+    pub fn encryption_context(&mut self, context: &Value) -> Result<Vec<zerokms::Context>, Error> {
+        Ok(self
+            .items(context)?
+            .iter()
+            .cloned()
+            .map(zerokms::Context::from)
+            .collect())
+    }
+
+    /// Converts JSON encryption context into ZeroKMS context objects, along with the distinct
+    /// context kinds supplied, parsing it only if an identical context was not parsed before.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidContext`] if any entry would otherwise be dropped.
+    pub fn with_kinds(
+        &mut self,
+        context: &Value,
+    ) -> Result<(Vec<zerokms::Context>, Vec<&'static str>), Error> {
+        let items = self.items(context)?;
+
+        Ok((
+            items.iter().cloned().map(zerokms::Context::from).collect(),
+            kinds(items),
+        ))
+    }
+
+    fn items(&mut self, context: &Value) -> Result<&[ContextItem], Error> {
+        // Object keys are sorted, so equal contexts serialize identically.
+        let items = match self.parsed.entry(context.to_string()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(items_from_value(context)?),
+        };
+
+        Ok(items.as_slice())
+    }
+}
+
+/// The distinct kinds of parsed context entries, sorted.
+fn kinds(items: &[ContextItem]) -> Vec<&'static str> {
+    let mut kinds: Vec<&'static str> = items.iter().map(ContextItem::kind).collect();
+    kinds.sort_unstable();
+    kinds.dedup();
+    kinds
+}
+
+/// Whether a ZeroKMS decryption error indicates that the supplied context was rejected, either
+/// by a service policy denial or by the ciphertext's authentication check failing.
+///
+/// The errors of the error's chain are matched by type: a failed AEAD authentication check,
+/// which is how a record decrypted with different context fails, or a `403 Forbidden` response.
+/// Messages are not inspected, so other errors that mention context are not denials.
+pub fn is_context_denial(error: &(dyn std::error::Error + 'static)) -> bool {
+    let mut source = Some(error);
+
+    while let Some(error) = source {
+        if error.is::<aead::Error>() {
+            return true;
+        }
+        if let Some(error) = error.downcast_ref::<reqwest::Error>() {
+            if error.status() == Some(reqwest::StatusCode::FORBIDDEN) {
+                return true;
+            }
+        }
+        source = error.source();
+    }
+
+    false
+}
+
+/// Render supplied context kinds for an error message.
+pub fn describe_kinds(kinds: &[&'static str]) -> String {
+    if kinds.is_empty() {
+        "none".to_string()
+    } else {
+        kinds.join(", ")
+    }
+}
+
+/// Parses JSON encryption context into [`ContextItem`] entries.
+///
+/// Value entries accept strings, numbers, and booleans, which are stringified canonically.
+/// Nested objects are flattened into dotted keys, so `{"key": "tenant", "value": {"id": 5}}`
+/// becomes `tenant.id = 5`. Every entry must be understood: unknown context kinds, non-array
+/// containers, and entries of the wrong shape are rejected rather than silently dropped.
+///
+/// A `jwt` entry derives context from token claims using a claim mapping such as
+/// `{"sub": "value:user", "org_id": "tag"}`, after verifying the token's signature. An
+/// `associated_data` entry is a single non-empty string.
+///
+/// # Errors
+///
+/// Returns [`Error::Parse`] if the context is not valid JSON, or [`Error::InvalidContext`] if
+/// any entry would otherwise be dropped.
+pub fn parse_items(context_json: &str) -> Result<Vec<ContextItem>, Error> {
+    items_from_value(&serde_json::from_str(context_json)?)
+}
+
+/// Parses already-parsed JSON encryption context into context items, as [`parse_items`] does.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidContext`] if any entry would otherwise be dropped.
+pub fn items_from_value(context: &Value) -> Result<Vec<ContextItem>, Error> {
+    let context = context
+        .as_object()
+        .ok_or_else(|| invalid("$", "expected an object"))?;
+
+    let mut items = Vec::new();
+
+    for (kind, entries) in context {
+        if kind == "jwt" {
+            jwt_items(entries, &mut items)?;
+            continue;
+        }
+
+        if kind == "associated_data" {
+            let data = string(entries, "$.associated_data")?;
+            if data.is_empty() {
+                return Err(invalid("$.associated_data", "must not be empty"));
+            }
+            items.push(ContextItem::AssociatedData(data));
+            continue;
+        }
+
+        let entries = entries
+            .as_array()
+            .ok_or_else(|| invalid(format!("$.{kind}"), "expected an array"))?;
+
+        for (index, entry) in entries.iter().enumerate() {
+            let path = format!("$.{kind}[{index}]");
+
+            match kind.as_str() {
+                "identity_claim" => items.push(ContextItem::IdentityClaim(string(entry, &path)?)),
+                "tag" => items.push(ContextItem::Tag(string(entry, &path)?)),
+                "value" => value_pair(entry, &path, &mut items)?,
+                _ => {
+                    return Err(invalid(
+                        format!("$.{kind}"),
+                        "unknown context kind, expected `identity_claim`, `tag`, `value`, `jwt`, or `associated_data`",
+                    ))
+                }
+            }
+        }
+    }
+
+    Ok(items)
+}
+
+fn jwt_items(entry: &Value, items: &mut Vec<ContextItem>) -> Result<(), Error> {
+    let context: JwtContext =
+        serde_json::from_value(entry.clone()).map_err(|err| invalid("$.jwt", err.to_string()))?;
+    let claims = jwt::decode(&context.token, &context.verify, context.leeway)?;
+
+    for (claim, target) in context.claims {
+        let path = format!("$.jwt.claims.{claim}");
+        let value = claims
+            .get(&claim)
+            .ok_or_else(|| invalid(&path, "claim is not present in the token"))?;
+
+        match target.split_once(':') {
+            // ZeroKMS binds an identity claim by name from the client's CTS token, not from
+            // this token, so mapping a claim to one would silently bind a different value.
+            None if target == "identity_claim" => {
+                return Err(invalid(
+                    path,
+                    "`identity_claim` binds the claim of the client's CTS token, not this token, map the claim to `value:<key>` instead",
+                ))
+            }
+            None if target == "tag" => match value {
+                Value::Array(tags) => {
+                    for (index, tag) in tags.iter().enumerate() {
+                        items.push(ContextItem::Tag(string(tag, &format!("{path}[{index}]"))?));
+                    }
+                }
+                tag => items.push(ContextItem::Tag(string(tag, &path)?)),
+            },
+            Some(("value", key)) if !key.is_empty() => {
+                flatten(key.to_string(), value, &path, items)?
+            }
+            _ => {
+                return Err(invalid(
+                    path,
+                    format!(
+                    "unknown target `{target}`, expected `tag` or `value:<key>`"
+                ),
+                ))
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn value_pair(entry: &Value, path: &str, items: &mut Vec<ContextItem>) -> Result<(), Error> {
+    let pair = entry
+        .as_object()
+        .ok_or_else(|| invalid(path, "expected an object with `key` and `value`"))?;
+
+    if let Some(field) = pair
+        .keys()
+        .find(|field| !matches!(field.as_str(), "key" | "value"))
+    {
+        return Err(invalid(format!("{path}.{field}"), "unexpected field"));
+    }
+
+    let key = match pair.get("key") {
+        Some(key) => string(key, &format!("{path}.key"))?,
+        None => return Err(invalid(format!("{path}.key"), "missing required field")),
+    };
+
+    match pair.get("value") {
+        Some(value) => flatten(key, value, &format!("{path}.value"), items),
+        None => Err(invalid(format!("{path}.value"), "missing required field")),
+    }
+}
+
+fn flatten(
+    key: String,
+    value: &Value,
+    path: &str,
+    items: &mut Vec<ContextItem>,
+) -> Result<(), Error> {
+    if key.starts_with(RESERVED_PREFIX) {
+        return Err(invalid(
+            path,
+            format!("keys beginning with `{RESERVED_PREFIX}` are reserved"),
+        ));
+    }
+
+    match value {
+        Value::Object(fields) => flatten_object(&key, fields, path, items),
+        scalar => {
+            items.push(ContextItem::Value(key, canonical_scalar(scalar, path)?));
+            Ok(())
+        }
+    }
+}
+
+fn flatten_object(
+    key: &str,
+    fields: &Map<String, Value>,
+    path: &str,
+    items: &mut Vec<ContextItem>,
+) -> Result<(), Error> {
+    if fields.is_empty() {
+        return Err(invalid(path, "nested object must not be empty"));
+    }
+
+    let mut fields: Vec<_> = fields.iter().collect();
+    fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    for (field, value) in fields {
+        flatten(
+            format!("{key}.{field}"),
+            value,
+            &format!("{path}.{field}"),
+            items,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Stringify a scalar context value canonically.
+fn canonical_scalar(value: &Value, path: &str) -> Result<String, Error> {
+    match value {
+        Value::String(value) => Ok(value.clone()),
+        Value::Number(number) => Ok(number.to_string()),
+        Value::Bool(flag) => Ok(flag.to_string()),
+        Value::Null => Err(invalid(path, "null values are not supported")),
+        Value::Array(_) => Err(invalid(path, "arrays are not supported")),
+        Value::Object(_) => Err(invalid(path, "expected a scalar value")),
+    }
+}
+
+fn string(value: &Value, path: &str) -> Result<String, Error> {
+    value
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| invalid(path, "expected a string"))
+}
+
+fn invalid(path: impl Into<String>, reason: impl Into<String>) -> Error {
+    Error::InvalidContext {
+        path: path.into(),
+        reason: reason.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(context: Value) -> Result<Vec<ContextItem>, Error> {
+        parse_items(&context.to_string())
+    }
+
+    fn invalid_path(result: Result<Vec<ContextItem>, Error>) -> String {
+        match result {
+            Err(Error::InvalidContext { path, .. }) => path,
+            other => panic!("expected `InvalidContext` error, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_all_context_kinds() {
+        let items = parse(json!({
+            "identity_claim": ["sub"],
+            "tag": ["pii"],
+            "value": [{"key": "tenant_id", "value": "tenant_1"}]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            items,
+            [
+                ContextItem::IdentityClaim("sub".to_string()),
+                ContextItem::Tag("pii".to_string()),
+                ContextItem::Value("tenant_id".to_string(), "tenant_1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_non_string_values() {
+        let items = parse(json!({
+            "value": [
+                {"key": "age", "value": 42},
+                {"key": "ratio", "value": 0.5},
+                {"key": "admin", "value": true}
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            items,
+            [
+                ContextItem::Value("age".to_string(), "42".to_string()),
+                ContextItem::Value("ratio".to_string(), "0.5".to_string()),
+                ContextItem::Value("admin".to_string(), "true".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_nested_value_objects_are_flattened() {
+        let items = parse(json!({
+            "value": [{"key": "tenant", "value": {"region": "eu", "org": {"id": 7}}}]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            items,
+            [
+                ContextItem::Value("tenant.org.id".to_string(), "7".to_string()),
+                ContextItem::Value("tenant.region".to_string(), "eu".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_associated_data() {
+        let items = parse(json!({
+            "associated_data": "users:42",
+            "tag": ["pii"]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            items,
+            [
+                ContextItem::AssociatedData("users:42".to_string()),
+                ContextItem::Tag("pii".to_string()),
+            ]
+        );
+        assert_eq!(items[0].kind(), "associated_data");
+
+        assert_eq!(
+            invalid_path(parse(json!({"associated_data": ""}))),
+            "$.associated_data"
+        );
+        assert_eq!(
+            invalid_path(parse(json!({"associated_data": ["users:42"]}))),
+            "$.associated_data"
+        );
+        assert_eq!(
+            invalid_path(parse(json!({
+                "value": [{"key": "protect.associated_data", "value": "users:7"}]
+            }))),
+            "$.value[0].value"
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_dropped_entries() {
+        assert_eq!(invalid_path(parse(json!({"tag": ["pii", 3]}))), "$.tag[1]");
+        assert_eq!(invalid_path(parse(json!({"tag": "pii"}))), "$.tag");
+        assert_eq!(invalid_path(parse(json!({"tags": ["pii"]}))), "$.tags");
+        assert_eq!(invalid_path(parse(json!(["pii"]))), "$");
+        assert_eq!(
+            invalid_path(parse(json!({"value": [{"key": "role"}]}))),
+            "$.value[0].value"
+        );
+        assert_eq!(
+            invalid_path(parse(json!({"value": [{"key": "role", "value": null}]}))),
+            "$.value[0].value"
+        );
+        assert_eq!(
+            invalid_path(parse(json!({"value": [{"key": "a", "value": {"b": [1]}}]}))),
+            "$.value[0].value.b"
+        );
+        assert_eq!(
+            invalid_path(parse(
+                json!({"value": [{"key": "a", "value": "b", "extra": 1}]})
+            )),
+            "$.value[0].extra"
+        );
+    }
+
+    #[test]
+    fn test_parse_jwt_claim_mapping() {
+        let token = jwt::tests::sign(
+            json!({"sub": "user_1", "org_id": 42, "roles": ["admin", "billing"]}),
+            jwt::tests::TEST_SECRET,
+        );
+
+        let items = parse(json!({
+            "jwt": {
+                "token": token,
+                "claims": {"sub": "value:user", "org_id": "value:tenant", "roles": "tag"},
+                "verify": {"algorithm": "HS256", "secret": jwt::tests::TEST_SECRET}
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            items,
+            [
+                ContextItem::Value("tenant".to_string(), "42".to_string()),
+                ContextItem::Tag("admin".to_string()),
+                ContextItem::Tag("billing".to_string()),
+                ContextItem::Value("user".to_string(), "user_1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_jwt_rejects_missing_claims_and_unknown_targets() {
+        let token = jwt::tests::sign(json!({"sub": "user_1"}), jwt::tests::TEST_SECRET);
+        let verify = json!({"algorithm": "HS256", "secret": jwt::tests::TEST_SECRET});
+
+        for claims in [
+            json!({"org_id": "value:tenant"}),
+            json!({"sub": "subject"}),
+            json!({"sub": "identity_claim"}),
+        ] {
+            let (claim, _) = claims.as_object().unwrap().iter().next().unwrap();
+            assert_eq!(
+                invalid_path(parse(json!({
+                    "jwt": {"token": token, "claims": claims, "verify": verify}
+                }))),
+                format!("$.jwt.claims.{claim}")
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_jwt_requires_verification() {
+        let token = jwt::tests::sign(json!({"sub": "user_1"}), jwt::tests::TEST_SECRET);
+
+        assert_eq!(
+            invalid_path(parse(json!({
+                "jwt": {"token": token, "claims": {"sub": "value:user"}}
+            }))),
+            "$.jwt"
+        );
+    }
+
+    #[test]
+    fn test_parse_jwt_with_bad_signature_fails() {
+        let token = jwt::tests::sign(json!({"sub": "user_1"}), "other-secret");
+
+        let result = parse(json!({
+            "jwt": {
+                "token": token,
+                "claims": {"sub": "value:user"},
+                "verify": {"algorithm": "HS256", "secret": jwt::tests::TEST_SECRET}
+            }
+        }));
+
+        assert!(matches!(result, Err(Error::InvalidToken(_))));
+    }
+
+    #[test]
+    fn test_with_kinds_lists_distinct_kinds() {
+        let context = json!({
+            "value": [{"key": "a", "value": "1"}, {"key": "b", "value": "2"}],
+            "tag": ["pii"]
+        });
+
+        let (encryption_context, kinds) = with_kinds_from_value(&context).unwrap();
+
+        assert_eq!(encryption_context.len(), 3);
+        assert_eq!(kinds, ["tag", "value"]);
+        assert_eq!(describe_kinds(&kinds), "tag, value");
+        assert_eq!(describe_kinds(&[]), "none");
+    }
+
+    #[test]
+    fn test_from_value_matches_parsed_json() {
+        let context = json!({"tag": ["pii"], "identity_claim": ["sub"]});
+
+        let (from_value, kinds) = with_kinds_from_value(&context).unwrap();
+        let parsed = parse_items(&context.to_string()).unwrap();
+
+        assert_eq!(from_value.len(), parsed.len());
+        assert_eq!(kinds, ["identity_claim", "tag"]);
+        assert!(encryption_context_from_value(&json!({"tag": "pii"})).is_err());
+    }
+
+    #[test]
+    fn test_cache_parses_repeated_contexts_once() {
+        let mut cache = ContextCache::default();
+        let tenant_1 = json!({"value": [{"key": "tenant", "value": "1"}], "tag": ["pii"]});
+        let reordered = json!({"tag": ["pii"], "value": [{"key": "tenant", "value": "1"}]});
+        let tenant_2 = json!({"value": [{"key": "tenant", "value": "2"}]});
+
+        for context in [&tenant_1, &reordered, &tenant_2, &tenant_1] {
+            assert_eq!(
+                cache.encryption_context(context).unwrap().len(),
+                encryption_context_from_value(context).unwrap().len()
+            );
+        }
+        let (_, kinds) = cache.with_kinds(&reordered).unwrap();
+
+        assert_eq!(cache.parsed.len(), 2);
+        assert_eq!(kinds, ["tag", "value"]);
+        assert!(cache.encryption_context(&json!({"tag": "pii"})).is_err());
+        assert_eq!(cache.parsed.len(), 2);
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("decryption failed")]
+    struct DecryptFailed(#[source] aead::Error);
+
+    #[test]
+    fn test_is_context_denial() {
+        assert!(is_context_denial(&aead::Error));
+        assert!(is_context_denial(&DecryptFailed(aead::Error)));
+
+        let error =
+            Error::Runtime("invalid context: aead authentication tag forbidden".to_string());
+        assert!(!is_context_denial(&error));
+        let error = std::io::Error::other("context deadline exceeded");
+        assert!(!is_context_denial(&error));
+    }
+
+    #[test]
+    fn test_parse_empty_context() {
+        assert!(parse(json!({})).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,325 @@
+//! Named encryption context presets defined in the configuration.
+//!
+//! Context policy written out at every call site drifts: one call tags a tenant as `tenant:5`,
+//! another as `tenant-5`, and values encrypted by one cannot be decrypted by the other. A
+//! configuration can instead define presets under `contexts`, such as
+//! `{"tenant_scoped": {"value": [{"key": "tenant", "value": "{tenant}"}]}}`, and requests pass
+//! `"@tenant_scoped"`, or `{"@tenant_scoped": {"tenant": "acme"}}` with parameters, in place of
+//! a context. Each `{name}` placeholder in a string of the preset is replaced with the parameter
+//! of that name, and `{{` and `}}` are literal braces. Every placeholder must have a parameter
+//! and every parameter a placeholder, so a misspelt parameter fails instead of producing a
+//! context that decrypts nothing.
+
+use crate::context::encryption_context_from_value;
+use crate::Error;
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+
+/// The prefix of a reference to a preset.
+pub const REFERENCE_PREFIX: char = '@';
+
+/// The context presets of a configuration.
+#[derive(Debug, Default)]
+pub struct ContextPresets {
+    presets: HashMap<String, Preset>,
+}
+
+/// A context with placeholders for its parameters.
+#[derive(Debug)]
+struct Preset {
+    template: Value,
+    placeholders: BTreeSet<String>,
+}
+
+impl ContextPresets {
+    /// Parse the `contexts` of a configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidContextPreset`] if a preset is not an object, has a malformed
+    /// placeholder, or, when its placeholders are filled in, is not a valid context. Presets
+    /// with a `jwt` entry are only checked when they are used, since their token is a parameter.
+    pub fn new(contexts: &Map<String, Value>) -> Result<Self, Error> {
+        let mut presets = HashMap::with_capacity(contexts.len());
+
+        for (name, template) in contexts {
+            let invalid = |reason: String| Error::InvalidContextPreset(name.clone(), reason);
+
+            let Some(object) = template.as_object() else {
+                return Err(invalid("expected a context object".to_string()));
+            };
+
+            let mut placeholders = BTreeSet::new();
+            collect_placeholders(template, &mut placeholders).map_err(invalid)?;
+
+            if !object.contains_key("jwt") {
+                let sample: Map<String, Value> = placeholders
+                    .iter()
+                    .map(|placeholder| (placeholder.clone(), Value::from(placeholder.as_str())))
+                    .collect();
+                encryption_context_from_value(&fill(template, &sample))
+                    .map_err(|error| invalid(error.to_string()))?;
+            }
+
+            presets.insert(
+                name.clone(),
+                Preset {
+                    template: template.clone(),
+                    placeholders,
+                },
+            );
+        }
+
+        Ok(Self { presets })
+    }
+
+    /// A request's context with a reference to a preset replaced by the preset, filled in with
+    /// the reference's parameters. Contexts that are not references are returned unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidContext`] if the context references an unknown preset, or its
+    /// parameters do not match the preset's placeholders.
+    pub fn expand<'a>(&self, context: &'a Value) -> Result<Cow<'a, Value>, Error> {
+        let (name, params) = match context {
+            Value::String(reference) => (reference.as_str(), None),
+            Value::Object(object) if object.len() == 1 => {
+                let (reference, params) = object.iter().next().expect("one entry");
+                (reference.as_str(), Some(params))
+            }
+            _ => return Ok(Cow::Borrowed(context)),
+        };
+        let Some(name) = name.strip_prefix(REFERENCE_PREFIX) else {
+            return Ok(Cow::Borrowed(context));
+        };
+        let path = format!("$.{REFERENCE_PREFIX}{name}");
+
+        let preset = self.presets.get(name).ok_or_else(|| {
+            invalid(
+                "$",
+                format!("unknown context preset `{name}`, define it under `contexts` in the configuration"),
+            )
+        })?;
+
+        let empty = Map::new();
+        let params = match params {
+            None => &empty,
+            Some(Value::Object(params)) => params,
+            Some(_) => return Err(invalid(path, "expected an object of parameters")),
+        };
+
+        if let Some(param) = params
+            .keys()
+            .find(|param| !preset.placeholders.contains(*param))
+        {
+            return Err(invalid(
+                format!("{path}.{param}"),
+                format!("context preset `{name}` has no `{{{param}}}` placeholder"),
+            ));
+        }
+        if let Some(placeholder) = preset
+            .placeholders
+            .iter()
+            .find(|placeholder| !params.contains_key(*placeholder))
+        {
+            return Err(invalid(
+                path,
+                format!("missing parameter `{placeholder}` of context preset `{name}`"),
+            ));
+        }
+        for (param, value) in params {
+            if !matches!(value, Value::String(_) | Value::Number(_) | Value::Bool(_)) {
+                return Err(invalid(
+                    format!("{path}.{param}"),
+                    "expected a string, number, or boolean",
+                ));
+            }
+        }
+
+        Ok(Cow::Owned(fill(&preset.template, params)))
+    }
+}
+
+/// Collect the placeholder names of every string in a template.
+fn collect_placeholders(
+    template: &Value,
+    placeholders: &mut BTreeSet<String>,
+) -> Result<(), String> {
+    match template {
+        Value::String(text) => {
+            substitute(text, &mut |name| {
+                placeholders.insert(name.to_string());
+                String::new()
+            })?;
+        }
+        Value::Array(values) => {
+            for value in values {
+                collect_placeholders(value, placeholders)?;
+            }
+        }
+        Value::Object(fields) => {
+            for value in fields.values() {
+                collect_placeholders(value, placeholders)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// A template with each placeholder replaced by its parameter. The template's placeholders were
+/// checked when it was parsed, and the parameters against its placeholders.
+fn fill(template: &Value, params: &Map<String, Value>) -> Value {
+    match template {
+        Value::String(text) => Value::String(
+            substitute(text, &mut |name| match &params[name] {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            })
+            .unwrap_or_else(|_| text.clone()),
+        ),
+        Value::Array(values) => {
+            Value::Array(values.iter().map(|value| fill(value, params)).collect())
+        }
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), fill(value, params)))
+                .collect(),
+        ),
+        value => value.clone(),
+    }
+}
+
+/// A string with each `{name}` placeholder replaced by `replace(name)`, and `{{` and `}}`
+/// replaced by literal braces.
+fn substitute(text: &str, replace: &mut dyn FnMut(&str) -> String) -> Result<String, String> {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find(['{', '}']) {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        if rest[start..].starts_with("{{") || rest[start..].starts_with("}}") {
+            output.push_str(&rest[start..start + 1]);
+            rest = &after[1..];
+            continue;
+        }
+        if rest[start..].starts_with('}') {
+            return Err(format!("`}}` in `{text}` is not escaped as `}}}}`"));
+        }
+
+        let end = after
+            .find('}')
+            .ok_or_else(|| format!("`{{` in `{text}` is not closed with `}}`"))?;
+        let name = &after[..end];
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!(
+                "`{{{name}}}` in `{text}` is not a valid placeholder, expected letters, digits, and underscores"
+            ));
+        }
+
+        output.push_str(&replace(name));
+        rest = &after[end + 1..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+fn invalid(path: impl Into<String>, reason: impl Into<String>) -> Error {
+    Error::InvalidContext {
+        path: path.into(),
+        reason: reason.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn presets() -> ContextPresets {
+        let contexts = json!({
+            "tenant_scoped": {
+                "tag": ["tenant:{tenant}", "{{literal}}"],
+                "value": [{"key": "tenant", "value": {"id": "{tenant}", "region": "{region}"}}]
+            },
+            "service": {"identity_claim": ["sub"]}
+        });
+
+        ContextPresets::new(contexts.as_object().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_expand_references() {
+        let presets = presets();
+
+        let context = json!({"@tenant_scoped": {"tenant": "acme", "region": 2}});
+        assert_eq!(
+            presets.expand(&context).unwrap().into_owned(),
+            json!({
+                "tag": ["tenant:acme", "{literal}"],
+                "value": [{"key": "tenant", "value": {"id": "acme", "region": "2"}}]
+            })
+        );
+
+        let context = json!("@service");
+        assert_eq!(
+            presets.expand(&context).unwrap().into_owned(),
+            json!({"identity_claim": ["sub"]})
+        );
+
+        let context = json!({"tag": ["@service"]});
+        assert!(matches!(presets.expand(&context), Ok(Cow::Borrowed(_))));
+    }
+
+    #[test]
+    fn test_expand_rejects_invalid_references() {
+        let presets = presets();
+
+        for (context, expected_path) in [
+            (json!("@unknown"), "$"),
+            (
+                json!({"@tenant_scoped": {"tenant": "acme"}}),
+                "$.@tenant_scoped",
+            ),
+            (
+                json!({"@tenant_scoped": {"tenant": "acme", "region": 2, "tenat": "x"}}),
+                "$.@tenant_scoped.tenat",
+            ),
+            (
+                json!({"@tenant_scoped": {"tenant": ["acme"], "region": 2}}),
+                "$.@tenant_scoped.tenant",
+            ),
+            (json!({"@service": "sub"}), "$.@service"),
+        ] {
+            match presets.expand(&context) {
+                Err(Error::InvalidContext { path, .. }) => assert_eq!(path, expected_path),
+                other => panic!("expected `InvalidContext` for {context}, got: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_new_rejects_invalid_presets() {
+        for contexts in [
+            json!({"tenant_scoped": ["tenant"]}),
+            json!({"tenant_scoped": {"tag": ["tenant:{tenant"]}}),
+            json!({"tenant_scoped": {"tag": ["tenant:{}"]}}),
+            json!({"tenant_scoped": {"tags": ["{tenant}"]}}),
+            json!({"tenant_scoped": {"value": [{"key": "protect.tenant", "value": "{tenant}"}]}}),
+        ] {
+            assert!(
+                matches!(
+                    ContextPresets::new(contexts.as_object().unwrap()),
+                    Err(Error::InvalidContextPreset(..))
+                ),
+                "{contexts}"
+            );
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//! Crash reports written when an operation panics.
+//!
+//! A panic inside an exported function is caught and returned as an [`Error::Panic`], but the
+//! error alone says little about where the library failed, and a panic that is not caught takes
+//! the PHP process down with it. Once a crash report path is set with `set_crash_report_path`,
+//! a process-wide panic hook appends a report to that file for every panic, before the panic is
+//! converted to an error: the operation that was running, the panic's location and message,
+//! a backtrace, and the versions of this library and the encryption library.
+//!
+//! Panic messages can contain values, such as a plaintext in the message of a failed
+//! `unwrap`, which `Debug` formats in double quotes, so double-quoted text in the message is
+//! redacted. Reports are JSON lines, one per panic, so a file collects every crash of a process.
+//!
+//! [`Error::Panic`]: crate::Error::Panic
+
+use crate::abi;
+use serde::Serialize;
+use std::backtrace::Backtrace;
+use std::cell::Cell;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::panic::{self, PanicHookInfo};
+use std::path::PathBuf;
+use std::sync::{Once, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The version of the encryption library this library is built against.
+const CIPHERSTASH_CLIENT_VERSION: &str = "0.23.0";
+
+/// The most characters of a panic message kept in a report.
+const MAX_MESSAGE_LENGTH: usize = 512;
+
+static REPORT_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+static INSTALL_HOOK: Once = Once::new();
+
+thread_local! {
+    /// The exported function running on this thread, if any.
+    static OPERATION: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+/// A report of a panic.
+#[derive(Debug, Serialize)]
+struct CrashReport {
+    /// When the panic happened, in seconds since the Unix epoch.
+    timestamp: u64,
+    /// The exported function that panicked, if the panic happened in one.
+    operation: Option<&'static str>,
+    /// The name of the thread that panicked, if it has one.
+    thread: Option<String>,
+    /// The source location of the panic.
+    location: Option<String>,
+    /// The panic message, with double-quoted text redacted.
+    message: String,
+    /// The backtrace of the panicking thread.
+    backtrace: String,
+    /// The versions of this library and its dependencies.
+    versions: Versions,
+}
+
+/// The versions recorded in a crash report.
+#[derive(Debug, Serialize)]
+struct Versions {
+    #[serde(rename = "protect-ffi")]
+    protect_ffi: &'static str,
+    #[serde(rename = "cipherstash-client")]
+    cipherstash_client: &'static str,
+    abi: u32,
+    os: &'static str,
+    arch: &'static str,
+}
+
+/// Marks the exported function running on this thread until it is dropped.
+#[derive(Debug)]
+pub struct OperationGuard {
+    previous: Option<&'static str>,
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        OPERATION.with(|operation| operation.set(self.previous));
+    }
+}
+
+/// Record the exported function running on this thread, for crash reports.
+pub fn enter(operation: &'static str) -> OperationGuard {
+    let previous = OPERATION.with(|current| current.replace(Some(operation)));
+    OperationGuard { previous }
+}
+
+/// Set the file crash reports are appended to, installing the panic hook on first use. Passing
+/// `None` stops writing reports.
+pub fn set_path(path: Option<PathBuf>) {
+    INSTALL_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            write_report(info);
+            previous(info);
+        }));
+    });
+
+    *REPORT_PATH
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = path;
+}
+
+/// Append a report of a panic to the crash report file, if one is set.
+///
+/// Failures to write are ignored, since a panic hook has nowhere to report them.
+fn write_report(info: &PanicHookInfo<'_>) {
+    let Some(path) = REPORT_PATH
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+    else {
+        return;
+    };
+
+    let report = report(info);
+    let Ok(mut line) = serde_json::to_string(&report) else {
+        return;
+    };
+    line.push('\n');
+
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+        let _ = file.write_all(line.as_bytes());
+    }
+}
+
+fn report(info: &PanicHookInfo<'_>) -> CrashReport {
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_default();
+
+    CrashReport {
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs()),
+        operation: OPERATION.with(Cell::get),
+        thread: std::thread::current().name().map(str::to_string),
+        location: info.location().map(ToString::to_string),
+        message: redact(&message),
+        backtrace: Backtrace::force_capture().to_string(),
+        versions: Versions {
+            protect_ffi: env!("CARGO_PKG_VERSION"),
+            cipherstash_client: CIPHERSTASH_CLIENT_VERSION,
+            abi: abi::ABI_VERSION,
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+        },
+    }
+}
+
+/// A panic message with the text inside double quotes replaced, truncated to
+/// [`MAX_MESSAGE_LENGTH`] characters. Quotes escaped with a backslash inside quoted text do not
+/// end it.
+fn redact(message: &str) -> String {
+    let mut redacted = String::with_capacity(message.len().min(MAX_MESSAGE_LENGTH));
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for c in message.chars().take(MAX_MESSAGE_LENGTH) {
+        match (quoted, c) {
+            (true, _) if escaped => escaped = false,
+            (true, '\\') => escaped = true,
+            (true, '"') => {
+                redacted.push_str("[redacted]\"");
+                quoted = false;
+            }
+            (true, _) => {}
+            (false, c) => {
+                redacted.push(c);
+                quoted = c == '"';
+            }
+        }
+    }
+    if quoted {
+        redacted.push_str("[redacted]");
+    }
+
+    redacted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(
+            redact(r#"called `Result::unwrap()` on an `Err` value: Parse("john@example.com")"#),
+            r#"called `Result::unwrap()` on an `Err` value: Parse("[redacted]")"#
+        );
+        assert_eq!(
+            redact(r#"Parse("a \"quoted\" secret") at 3"#),
+            r#"Parse("[redacted]") at 3"#
+        );
+        assert_eq!(redact("index out of bounds"), "index out of bounds");
+        assert_eq!(redact("unterminated \"secret"), "unterminated \"[redacted]");
+        assert_eq!(redact(&"a".repeat(1000)).len(), MAX_MESSAGE_LENGTH);
+    }
+
+    #[test]
+    fn test_enter_records_the_operation() {
+        assert_eq!(OPERATION.with(Cell::get), None);
+        {
+            let _outer = enter("encrypt_bulk");
+            {
+                let _inner = enter("create_search_terms");
+                assert_eq!(OPERATION.with(Cell::get), Some("create_search_terms"));
+            }
+            assert_eq!(OPERATION.with(Cell::get), Some("encrypt_bulk"));
+        }
+        assert_eq!(OPERATION.with(Cell::get), None);
+    }
+}
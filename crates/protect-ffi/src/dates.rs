@@ -0,0 +1,113 @@
+//! Validation of `date` column plaintexts.
+//!
+//! Order-revealing encryption terms for dates order by calendar day, so plaintexts must be
+//! calendar dates without a time or timezone. A timestamp can fall on a different calendar day
+//! depending on the timezone it is read in, so it is rejected rather than silently truncated.
+
+use crate::Error;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+
+/// Parse a `date` plaintext in `YYYY-MM-DD` format.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidDate`] if the plaintext is a timestamp or is not a valid calendar
+/// date.
+pub fn parse(plaintext: &str) -> Result<NaiveDate, Error> {
+    let plaintext = plaintext.trim();
+
+    if let Ok(date) = NaiveDate::parse_from_str(plaintext, "%Y-%m-%d") {
+        return Ok(date);
+    }
+
+    let is_timestamp = DateTime::parse_from_rfc3339(plaintext).is_ok()
+        || NaiveDateTime::parse_from_str(plaintext, "%Y-%m-%dT%H:%M:%S%.f").is_ok()
+        || NaiveDateTime::parse_from_str(plaintext, "%Y-%m-%d %H:%M:%S%.f").is_ok();
+
+    if is_timestamp {
+        return Err(Error::InvalidDate(
+            "timestamps are not supported, convert the value to a calendar date in the intended timezone first",
+        ));
+    }
+
+    Err(Error::InvalidDate(
+        "expected a calendar date in `YYYY-MM-DD` format",
+    ))
+}
+
+/// Normalize a `date` plaintext to its canonical `YYYY-MM-DD` form.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidDate`] if the plaintext is not a valid calendar date.
+pub fn normalize(plaintext: &str) -> Result<String, Error> {
+    Ok(parse(plaintext)?.format("%Y-%m-%d").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Dates in calendar order, spanning the Unix epoch, leap days, and century boundaries.
+    const CALENDAR_ORDER: &[&str] = &[
+        "0001-01-01",
+        "1600-02-29",
+        "1899-12-31",
+        "1900-01-01",
+        "1969-12-31",
+        "1970-01-01",
+        "1970-01-02",
+        "2000-02-29",
+        "2000-03-01",
+        "2038-01-19",
+        "2038-01-20",
+        "9999-12-31",
+    ];
+
+    #[test]
+    fn test_parse_preserves_calendar_order() {
+        let dates: Vec<NaiveDate> = CALENDAR_ORDER
+            .iter()
+            .map(|plaintext| parse(plaintext).unwrap())
+            .collect();
+
+        for pair in dates.windows(2) {
+            assert!(pair[0] < pair[1], "{} < {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn test_normalize_is_canonical() {
+        assert_eq!(normalize("2020-1-5").unwrap(), "2020-01-05");
+        assert_eq!(normalize(" 2020-11-10 ").unwrap(), "2020-11-10");
+
+        for plaintext in CALENDAR_ORDER {
+            assert_eq!(normalize(plaintext).unwrap(), *plaintext);
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_timestamps() {
+        for plaintext in [
+            "2020-11-10T23:30:00Z",
+            "2020-11-10T23:30:00-05:00",
+            "2020-11-10T23:30:00",
+            "2020-11-10 23:30:00",
+        ] {
+            match parse(plaintext) {
+                Err(Error::InvalidDate(reason)) => {
+                    assert!(reason.contains("timestamps"), "{plaintext}")
+                }
+                other => panic!("expected `InvalidDate` error, got: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_dates() {
+        for plaintext in ["2021-02-29", "2020-13-01", "10/11/2020", "yesterday", ""] {
+            let result = parse(plaintext);
+            assert!(matches!(result, Err(Error::InvalidDate(_))), "{plaintext}");
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! Deprecated names of renamed exported functions.
+//!
+//! Bindings written against an older release call the library by the names in their copy of the
+//! C header, so a renamed function keeps its old name as a wrapper generated by
+//! [`deprecated_alias!`](crate::deprecated_alias) until the next major release. The first call to
+//! each wrapper in a process is reported through the deprecation handler, or to standard error
+//! if none is set, so the binding can be updated before the old name is removed.
+
+use std::ffi::{c_char, CString};
+use std::sync::{Mutex, RwLock};
+
+/// Callback invoked with the name of a deprecated function and the name of its replacement.
+pub type DeprecationHandler = extern "C" fn(name: *const c_char, replacement: *const c_char);
+
+static DEPRECATION_HANDLER: RwLock<Option<DeprecationHandler>> = RwLock::new(None);
+
+/// The deprecated functions already reported in this process.
+static REPORTED: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+/// Export a deprecated name of an exported function, as a wrapper with the same parameters that
+/// reports its first call before calling the function.
+///
+/// The wrapper must also be declared in the C header, and listed in `DEPRECATED` in the function
+/// manifest with its replacement.
+#[macro_export]
+macro_rules! deprecated_alias {
+    ($old:ident => $new:ident($($parameter:ident: $ty:ty),* $(,)?) $(-> $returns:ty)?) => {
+        #[doc = concat!("Deprecated: use [`", stringify!($new), "()`] instead.")]
+        #[no_mangle]
+        pub extern "C" fn $old($($parameter: $ty),*) $(-> $returns)? {
+            $crate::deprecation::report(stringify!($old), stringify!($new));
+            $new($($parameter),*)
+        }
+    };
+}
+
+/// Set the callback invoked on the first call to each deprecated function.
+///
+/// Passing `None` restores the default of writing the report to standard error.
+pub fn set_deprecation_handler(handler: Option<DeprecationHandler>) {
+    let mut current = DEPRECATION_HANDLER
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    *current = handler;
+}
+
+/// Report a call to a deprecated function through the registered handler, or to standard error
+/// if none is set, unless the function was already reported in this process.
+// Only called by `deprecated_alias!` wrappers, and no exported function has been renamed yet.
+#[cfg_attr(not(test), allow(dead_code))]
+pub fn report(name: &'static str, replacement: &'static str) {
+    {
+        let mut reported = REPORTED
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if reported.contains(&name) {
+            return;
+        }
+        reported.push(name);
+    }
+
+    let handler = *DEPRECATION_HANDLER
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    match (handler, CString::new(name), CString::new(replacement)) {
+        (Some(handler), Ok(name), Ok(replacement)) => handler(name.as_ptr(), replacement.as_ptr()),
+        _ => eprintln!("protect-ffi: `{name}()` is deprecated, use `{replacement}()` instead"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static REPORTS: AtomicUsize = AtomicUsize::new(0);
+
+    extern "C" fn count_reports(name: *const c_char, replacement: *const c_char) {
+        let name = unsafe { std::ffi::CStr::from_ptr(name) };
+        let replacement = unsafe { std::ffi::CStr::from_ptr(replacement) };
+        assert_eq!(name.to_str(), Ok("deprecation_test_old_sum"));
+        assert_eq!(replacement.to_str(), Ok("deprecation_test_sum"));
+        REPORTS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[no_mangle]
+    pub extern "C" fn deprecation_test_sum(a: i32, b: i32) -> i32 {
+        a + b
+    }
+
+    deprecated_alias!(deprecation_test_old_sum => deprecation_test_sum(a: i32, b: i32) -> i32);
+
+    #[test]
+    fn test_deprecated_alias_reports_first_call() {
+        set_deprecation_handler(Some(count_reports));
+
+        assert_eq!(deprecation_test_old_sum(2, 3), 5);
+        assert_eq!(deprecation_test_old_sum(4, 5), 9);
+        assert_eq!(REPORTS.load(Ordering::SeqCst), 1);
+
+        set_deprecation_handler(None);
+    }
+}
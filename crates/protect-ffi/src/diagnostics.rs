@@ -0,0 +1,125 @@
+//! Startup diagnostics for client creation.
+//!
+//! A client reads its credentials and service endpoints from environment variables only, so a
+//! client that works in one environment and not another almost always differs in which of them
+//! are set. When creating a client fails to configure or reach ZeroKMS, the error carries these
+//! diagnostics: the configuration sources consulted and whether each variable is present, empty,
+//! or missing. Values are never included, so the diagnostics are safe to log.
+
+use serde::Serialize;
+use std::ffi::OsString;
+
+/// The environment variables a client reads, and whether each is required.
+const VARIABLES: &[(&str, bool)] = &[
+    ("CS_CLIENT_ID", true),
+    ("CS_CLIENT_ACCESS_KEY", true),
+    ("CS_CLIENT_KEY", true),
+    ("CS_WORKSPACE_CRN", true),
+    ("CS_WORKSPACE_ID", false),
+    ("CS_REGION", false),
+    ("CS_CTS_HOST", false),
+    ("CS_ZEROKMS_HOST", false),
+    ("CS_CONSOLE_HOST", false),
+];
+
+/// Whether an environment variable is set.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VariableState {
+    /// The variable has a value.
+    Present,
+    /// The variable is set to an empty or whitespace-only value.
+    Empty,
+    /// The variable is not set.
+    Missing,
+}
+
+impl VariableState {
+    fn of(value: Option<OsString>) -> Self {
+        match value {
+            Some(value) if value.to_string_lossy().trim().is_empty() => Self::Empty,
+            Some(_) => Self::Present,
+            None => Self::Missing,
+        }
+    }
+}
+
+/// An environment variable a client reads.
+#[derive(Debug, Serialize)]
+pub struct Variable {
+    /// The variable's name.
+    pub name: &'static str,
+    /// Whether client creation fails without the variable.
+    pub required: bool,
+    /// Whether the variable is set.
+    pub state: VariableState,
+}
+
+/// The configuration sources a client reads at startup.
+#[derive(Debug, Serialize)]
+pub struct StartupDiagnostics {
+    /// The configuration sources consulted, in order.
+    pub sources: Vec<&'static str>,
+    /// The configuration files read, which is always empty because credentials are read from
+    /// the environment only.
+    pub files: Vec<String>,
+    /// The environment variables read.
+    pub variables: Vec<Variable>,
+    /// The required variables that are missing or empty.
+    pub missing_required: Vec<&'static str>,
+}
+
+impl StartupDiagnostics {
+    /// Inspect the environment of the current process.
+    pub fn collect() -> Self {
+        Self::from_lookup(|name| std::env::var_os(name))
+    }
+
+    fn from_lookup(lookup: impl Fn(&str) -> Option<OsString>) -> Self {
+        let variables: Vec<Variable> = VARIABLES
+            .iter()
+            .map(|&(name, required)| Variable {
+                name,
+                required,
+                state: VariableState::of(lookup(name)),
+            })
+            .collect();
+
+        let missing_required = variables
+            .iter()
+            .filter(|variable| variable.required && variable.state != VariableState::Present)
+            .map(|variable| variable.name)
+            .collect();
+
+        Self {
+            sources: vec!["environment"],
+            files: Vec::new(),
+            variables,
+            missing_required,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnostics_report_variable_states_without_values() {
+        let diagnostics = StartupDiagnostics::from_lookup(|name| match name {
+            "CS_CLIENT_ID" => Some("secret-client-id".into()),
+            "CS_CLIENT_KEY" => Some(" ".into()),
+            _ => None,
+        });
+
+        assert_eq!(diagnostics.variables[0].state, VariableState::Present);
+        assert_eq!(diagnostics.variables[2].state, VariableState::Empty);
+        assert_eq!(
+            diagnostics.missing_required,
+            ["CS_CLIENT_ACCESS_KEY", "CS_CLIENT_KEY", "CS_WORKSPACE_CRN"]
+        );
+
+        let json = serde_json::to_string(&diagnostics).unwrap();
+        assert!(!json.contains("secret-client-id"));
+    }
+}
@@ -0,0 +1,180 @@
+//! Empty, whitespace-only, and oversized plaintexts.
+//!
+//! Text columns accept empty strings by default, and can reject them with `allow_empty: false`
+//! or trim surrounding whitespace with `trim: true`. Trimming happens before the empty check,
+//! so a trimmed column treats whitespace-only text as empty. A `max_length` rejects text with
+//! more characters, like a `varchar(n)` database column, after trimming. Array columns apply
+//! each option to each `text[]` element. Every other type rejects empty and whitespace-only
+//! plaintexts, since they cannot be parsed as a value of the type.
+
+use crate::encrypt_config::CastAs;
+use crate::normalization::IndexNormalization;
+use crate::Error;
+use serde_json::Value;
+
+/// How a text column prepares plaintexts for encryption and indexing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextPolicy {
+    /// Whether empty text is encrypted, rather than rejected.
+    pub allow_empty: bool,
+    /// Whether leading and trailing whitespace is removed before encryption.
+    pub trim: bool,
+    /// The most characters text may have, if limited.
+    pub max_length: Option<usize>,
+    /// The Unicode normalization of the column's `unique` and `match` index terms.
+    pub normalization: IndexNormalization,
+}
+
+impl Default for TextPolicy {
+    fn default() -> Self {
+        Self {
+            allow_empty: true,
+            trim: false,
+            max_length: None,
+            normalization: IndexNormalization::default(),
+        }
+    }
+}
+
+/// Apply a column's text policy to a plaintext, rejecting empty values the column does not
+/// accept.
+///
+/// # Errors
+///
+/// Returns [`Error::EmptyPlaintext`] if the plaintext is empty or whitespace-only and its type
+/// or column does not accept empty values, or [`Error::PlaintextTooLong`] if text is longer than
+/// its column's `max_length`.
+pub fn prepare(plaintext: String, cast_as: CastAs, policy: TextPolicy) -> Result<String, Error> {
+    match cast_as {
+        CastAs::Text => prepare_text(plaintext, policy),
+        CastAs::TextArray if !policy.allow_empty || policy.trim || policy.max_length.is_some() => {
+            // Malformed arrays are left for array parsing to report.
+            let Ok(Value::Array(elements)) = serde_json::from_str::<Value>(&plaintext) else {
+                return Ok(plaintext);
+            };
+
+            let elements = elements
+                .into_iter()
+                .map(|element| match element {
+                    Value::String(text) => Ok(Value::String(prepare_text(text, policy)?)),
+                    element => Ok(element),
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            Ok(serde_json::to_string(&elements)?)
+        }
+        CastAs::TextArray => Ok(plaintext),
+        _ if plaintext.trim().is_empty() => Err(Error::EmptyPlaintext(cast_as)),
+        _ => Ok(plaintext),
+    }
+}
+
+/// Apply a text policy to a single text value.
+fn prepare_text(plaintext: String, policy: TextPolicy) -> Result<String, Error> {
+    let plaintext = if policy.trim {
+        plaintext.trim().to_string()
+    } else {
+        plaintext
+    };
+
+    if plaintext.is_empty() && !policy.allow_empty {
+        return Err(Error::EmptyPlaintext(CastAs::Text));
+    }
+
+    if let Some(max_length) = policy.max_length {
+        let length = plaintext.chars().count();
+        if length > max_length {
+            return Err(Error::PlaintextTooLong { length, max_length });
+        }
+    }
+
+    Ok(plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STRICT: TextPolicy = TextPolicy {
+        allow_empty: false,
+        trim: true,
+        max_length: None,
+        normalization: IndexNormalization {
+            unique: None,
+            r#match: None,
+            match_edge_ngrams: None,
+            match_limits: None,
+        },
+    };
+
+    #[test]
+    fn test_text_accepts_empty_by_default() {
+        let result = prepare(String::new(), CastAs::Text, TextPolicy::default());
+        assert_eq!(result.unwrap(), "");
+
+        let result = prepare("  ".to_string(), CastAs::Text, TextPolicy::default());
+        assert_eq!(result.unwrap(), "  ");
+    }
+
+    #[test]
+    fn test_text_trims_and_rejects_empty() {
+        let result = prepare(" Ada ".to_string(), CastAs::Text, STRICT);
+        assert_eq!(result.unwrap(), "Ada");
+
+        let result = prepare(" \t".to_string(), CastAs::Text, STRICT);
+        assert!(matches!(result, Err(Error::EmptyPlaintext(CastAs::Text))));
+    }
+
+    #[test]
+    fn test_text_array_elements_follow_policy() {
+        let result = prepare(r#"[" a ","b"]"#.to_string(), CastAs::TextArray, STRICT);
+        assert_eq!(result.unwrap(), r#"["a","b"]"#);
+
+        let result = prepare(r#"["a"," "]"#.to_string(), CastAs::TextArray, STRICT);
+        assert!(matches!(result, Err(Error::EmptyPlaintext(CastAs::Text))));
+    }
+
+    #[test]
+    fn test_text_longer_than_max_length_is_rejected() {
+        let policy = TextPolicy {
+            max_length: Some(4),
+            ..STRICT
+        };
+
+        assert_eq!(
+            prepare(" café ".to_string(), CastAs::Text, policy).unwrap(),
+            "café"
+        );
+        assert!(matches!(
+            prepare("cafés".to_string(), CastAs::Text, policy),
+            Err(Error::PlaintextTooLong {
+                length: 5,
+                max_length: 4
+            })
+        ));
+        assert!(matches!(
+            prepare(r#"["ab","abcde"]"#.to_string(), CastAs::TextArray, policy),
+            Err(Error::PlaintextTooLong { length: 5, .. })
+        ));
+    }
+
+    #[test]
+    fn test_other_types_reject_empty() {
+        for cast_as in [
+            CastAs::Int,
+            CastAs::Date,
+            CastAs::Double,
+            CastAs::JsonB,
+            CastAs::BigUInt,
+        ] {
+            let result = prepare(" ".to_string(), cast_as, TextPolicy::default());
+            assert!(
+                matches!(result, Err(Error::EmptyPlaintext(rejected)) if rejected == cast_as),
+                "{cast_as}"
+            );
+        }
+
+        let result = prepare("42".to_string(), CastAs::Int, TextPolicy::default());
+        assert_eq!(result.unwrap(), "42");
+    }
+}
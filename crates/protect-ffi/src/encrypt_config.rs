@@ -1,16 +1,40 @@
 //! Encryption configuration parsing and conversion for CipherStash column configurations.
 
+use crate::column_context;
+use crate::config_format::{self, ConfigFormat};
+use crate::context::ContextItem;
+use crate::context_presets::ContextPresets;
+use crate::empty::TextPolicy;
+use crate::floats::FloatFormat;
+use crate::interpolation;
+use crate::json_paths::JsonPath;
+use crate::lints::{self, ColumnIndexes, Lint};
+use crate::ngrams::{LimitOverflow, MatchLimits, MatchTokenizer};
+use crate::normalization::{IndexNormalization, UnicodeNormalization};
+use crate::ste_vec::SteVecPolicy;
 use cipherstash_client::schema::{
-    column::{Index, IndexType, TokenFilter, Tokenizer},
+    column::{Index, IndexType, TokenFilter},
     ColumnConfig, ColumnType,
 };
+use serde::de::{self, Deserializer, MapAccess, Visitor};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, str::FromStr};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::collections::hash_map::Entry;
+use std::fs;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::{collections::HashMap, fmt, str::FromStr};
 use strum::Display;
+use uuid::Uuid;
 
 /// Supported schema versions.
 const SUPPORTED_SCHEMA_VERSIONS: &[u32] = &[2];
 
+/// The most files a chain of `extends` may nest.
+const MAX_EXTENDS_DEPTH: usize = 8;
+
 /// Table and column identifier for encryption configuration lookup.
 #[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct Identifier {
@@ -33,11 +57,131 @@ impl Identifier {
 
         Self { table, column }
     }
+
+    /// The identifier with its table and column names in lowercase, for case-insensitive
+    /// lookups.
+    pub fn case_folded(&self) -> Self {
+        Self::new(self.table.to_lowercase(), self.column.to_lowercase())
+    }
+}
+
+/// The most columns suggested for an unknown column.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Look up the configuration and type of a column.
+///
+/// # Errors
+///
+/// Returns [`crate::Error::UnknownColumn`], with the closest configured columns, if the column
+/// is not configured.
+pub fn column<'a>(
+    columns: &'a HashMap<Identifier, (ColumnConfig, CastAs)>,
+    identifier: &Identifier,
+) -> Result<&'a (ColumnConfig, CastAs), crate::Error> {
+    columns.get(identifier).ok_or_else(|| {
+        crate::Error::UnknownColumn(identifier.clone(), suggestions(columns.keys(), identifier))
+    })
+}
+
+/// The configured columns closest to an unknown column, closest first.
+///
+/// Names are compared as `table.column`, ignoring case, by edit distance. Columns more than a
+/// third of the name's length away are not suggested, so unrelated names get no suggestion.
+fn suggestions<'a>(
+    configured: impl Iterator<Item = &'a Identifier>,
+    identifier: &Identifier,
+) -> Vec<Identifier> {
+    let requested = format!("{}.{}", identifier.table, identifier.column).to_lowercase();
+    let max_distance = (requested.chars().count() / 3).max(1);
+
+    let mut candidates: Vec<(usize, &Identifier)> = configured
+        .filter_map(|candidate| {
+            let name = format!("{}.{}", candidate.table, candidate.column).to_lowercase();
+            let distance = edit_distance(&requested, &name);
+            (distance <= max_distance).then_some((distance, candidate))
+        })
+        .collect();
+    candidates.sort_by(|(a_distance, a), (b_distance, b)| {
+        (a_distance, &a.table, &a.column).cmp(&(b_distance, &b.table, &b.column))
+    });
+
+    candidates
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate.clone())
+        .collect()
+}
+
+/// The number of single-character insertions, deletions, and substitutions between two names.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Format suggested columns as a hint appended to an error message, or nothing when there are
+/// none.
+pub fn did_you_mean(suggestions: &[Identifier]) -> String {
+    let names: Vec<String> = suggestions
+        .iter()
+        .map(|identifier| format!("`{}.{}`", identifier.table, identifier.column))
+        .collect();
+
+    match names.as_slice() {
+        [] => String::new(),
+        [name] => format!(" (did you mean {name}?)"),
+        [names @ .., last] => format!(" (did you mean {} or {last}?)", names.join(", ")),
+    }
+}
+
+/// Map the case-folded form of each configured column to its configured identifier.
+///
+/// # Errors
+///
+/// Returns [`crate::Error::InvalidClientOptions`] if two columns differ only in the case of
+/// their names, so a case-insensitive lookup could not tell them apart.
+pub fn case_folded_identifiers(
+    config: &EncryptConfig,
+) -> Result<HashMap<Identifier, Identifier>, crate::Error> {
+    let mut identifiers: Vec<Identifier> = config
+        .tables
+        .0
+        .iter()
+        .flat_map(|(table_name, table)| {
+            table
+                .0
+                .keys()
+                .map(move |column_name| Identifier::new(table_name, column_name))
+        })
+        .collect();
+    identifiers.sort_by(|a, b| (&a.table, &a.column).cmp(&(&b.table, &b.column)));
+
+    let mut folded = HashMap::with_capacity(identifiers.len());
+    for identifier in identifiers {
+        if let Some(existing) = folded.insert(identifier.case_folded(), identifier.clone()) {
+            return Err(crate::Error::InvalidClientOptions(format!(
+                "`identifier_lookup` is `case_insensitive`, but columns `{}.{}` and `{}.{}` differ only in case",
+                existing.table, existing.column, identifier.table, identifier.column
+            )));
+        }
+    }
+
+    Ok(folded)
 }
 
 /// Collection of table configurations indexed by table name.
 #[derive(Debug, Deserialize, Serialize, Clone, Default)]
-pub struct Tables(HashMap<String, Table>);
+pub struct Tables(#[serde(deserialize_with = "unique_tables")] HashMap<String, Table>);
 
 impl IntoIterator for Tables {
     type Item = (String, Table);
@@ -49,8 +193,8 @@ impl IntoIterator for Tables {
 }
 
 /// Table configuration containing column definitions indexed by column name.
-#[derive(Debug, Deserialize, Serialize, Clone, Default)]
-pub struct Table(HashMap<String, Column>);
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+pub struct Table(#[serde(deserialize_with = "unique_columns")] HashMap<String, Column>);
 
 impl IntoIterator for Table {
     type Item = (String, Column);
@@ -61,6 +205,85 @@ impl IntoIterator for Table {
     }
 }
 
+/// Deserialize the tables of a configuration, rejecting conflicting definitions of a table.
+fn unique_tables<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<String, Table>, D::Error> {
+    deserializer.deserialize_map(UniqueEntries::new("table"))
+}
+
+/// Deserialize the columns of a table, rejecting conflicting definitions of a column.
+fn unique_columns<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<String, Column>, D::Error> {
+    deserializer.deserialize_map(UniqueEntries::new("column"))
+}
+
+/// Deserialize the named keysets of a configuration, rejecting conflicting definitions of a
+/// keyset.
+fn unique_keysets<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<String, Uuid>, D::Error> {
+    deserializer.deserialize_map(UniqueEntries::new("keyset"))
+}
+
+/// Visitor for a JSON object whose keys name configuration entries.
+///
+/// JSON parsers keep the last of duplicate keys, so a second definition of a column would
+/// silently replace the first. Repeated identical definitions are accepted, and conflicting
+/// ones are rejected with the position of both entries.
+struct UniqueEntries<V> {
+    kind: &'static str,
+    marker: PhantomData<V>,
+}
+
+impl<V> UniqueEntries<V> {
+    fn new(kind: &'static str) -> Self {
+        Self {
+            kind,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, V: Deserialize<'de> + PartialEq> Visitor<'de> for UniqueEntries<V> {
+    type Value = HashMap<String, V>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "an object of {} names", self.kind)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut entries: HashMap<String, (usize, V)> =
+            HashMap::with_capacity(access.size_hint().unwrap_or(0));
+        let mut position = 0;
+
+        while let Some((name, value)) = access.next_entry::<String, V>()? {
+            position += 1;
+
+            match entries.entry(name) {
+                Entry::Vacant(entry) => {
+                    entry.insert((position, value));
+                }
+                Entry::Occupied(entry) if entry.get().1 != value => {
+                    return Err(de::Error::custom(format!(
+                        "{} `{}` is defined twice with different options, as entries {} and {position}",
+                        self.kind,
+                        entry.key(),
+                        entry.get().0,
+                    )));
+                }
+                Entry::Occupied(_) => {}
+            }
+        }
+
+        Ok(entries
+            .into_iter()
+            .map(|(name, (_, value))| (name, value))
+            .collect())
+    }
+}
+
 /// Root encryption configuration structure parsed from JSON.
 #[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct EncryptConfig {
@@ -69,6 +292,12 @@ pub struct EncryptConfig {
     pub version: u32,
     /// The set of table configurations.
     pub tables: Tables,
+    /// Named ZeroKMS keysets that columns can reference to encrypt under a distinct key.
+    #[serde(default, deserialize_with = "unique_keysets")]
+    pub keysets: HashMap<String, Uuid>,
+    /// Named encryption context presets that requests can reference in place of a context.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub contexts: Map<String, Value>,
 }
 
 /// Column configuration with casting and encryption indexes.
@@ -80,6 +309,28 @@ pub struct Column {
     /// Collection of encryption indexes for this column.
     #[serde(default)]
     indexes: Indexes,
+    /// Name of the keyset used to encrypt this column, or the default keyset when absent.
+    #[serde(default)]
+    keyset: Option<String>,
+    /// Output formatting for decrypted values of `real` and `double` columns.
+    #[serde(default)]
+    float_format: Option<FloatFormat>,
+    /// Whether empty text is encrypted for `text` and `text[]` columns, which is the default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    allow_empty: Option<bool>,
+    /// Whether surrounding whitespace is trimmed from `text` and `text[]` plaintexts.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    trim: bool,
+    /// The most characters `text` and `text[]` plaintexts may have.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_length: Option<usize>,
+    /// Lints of the column's type and indexes that are accepted without an error or warning.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    allow_lints: Vec<Lint>,
+    /// Encryption context added to every value of the column, with `$table` and `$column`
+    /// standing for its names.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    context_from: Option<Value>,
 }
 
 /// Data type casting options for encrypted columns.
@@ -98,6 +349,11 @@ pub enum CastAs {
     Int,
     /// Treat as a 64-bit integer.
     BigInt,
+    /// Treat as an unsigned integer of up to 128 bits, encrypted as decimal text. Supports
+    /// equality queries only.
+    #[serde(rename = "big_uint")]
+    #[strum(serialize = "big_uint")]
+    BigUInt,
     /// Treat as a single-precision float.
     Real,
     /// Treat as a double-precision float.
@@ -108,6 +364,25 @@ pub enum CastAs {
     #[serde(rename = "jsonb")]
     #[strum(serialize = "jsonb")]
     JsonB,
+    /// Treat as an array of UTF-8 text values, each indexed with the column's indexes.
+    #[serde(rename = "text[]")]
+    #[strum(serialize = "text[]")]
+    TextArray,
+    /// Treat as an array of 32-bit integers, each indexed with the column's indexes.
+    #[serde(rename = "int[]")]
+    #[strum(serialize = "int[]")]
+    IntArray,
+}
+
+impl CastAs {
+    /// The type of each element for array types, or `None` for scalar types.
+    pub fn element_type(self) -> Option<CastAs> {
+        match self {
+            CastAs::TextArray => Some(CastAs::Text),
+            CastAs::IntArray => Some(CastAs::Int),
+            _ => None,
+        }
+    }
 }
 
 /// Collection of indexes for searchable encryption and uniqueness constraints.
@@ -128,6 +403,27 @@ pub struct Indexes {
     ste_vec_index: Option<SteVecIndexOpts>,
 }
 
+impl Indexes {
+    /// The text the `unique` and `match` index terms are created from.
+    fn normalization(&self) -> IndexNormalization {
+        IndexNormalization {
+            unique: self
+                .unique_index
+                .as_ref()
+                .and_then(|opts| opts.unicode_normalization),
+            r#match: self
+                .match_index
+                .as_ref()
+                .and_then(|opts| opts.unicode_normalization),
+            match_edge_ngrams: self
+                .match_index
+                .as_ref()
+                .and_then(|opts| opts.tokenizer.edge_ngrams(&opts.token_filters)),
+            match_limits: self.match_index.as_ref().and_then(MatchIndexOpts::limits),
+        }
+    }
+}
+
 /// Configuration options for order-revealing encryption indexes.
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct OreIndexOpts {}
@@ -136,8 +432,8 @@ pub struct OreIndexOpts {}
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct MatchIndexOpts {
     /// The tokenizer to use for splitting text.
-    #[serde(default = "default_tokenizer")]
-    tokenizer: Tokenizer,
+    #[serde(default)]
+    tokenizer: MatchTokenizer,
     /// Token filters to apply to tokens.
     #[serde(default)]
     token_filters: Vec<TokenFilter>,
@@ -150,6 +446,48 @@ pub struct MatchIndexOpts {
     /// Whether to include the original value in the index.
     #[serde(default)]
     include_original: bool,
+    /// Unicode normalization applied to text before tokenizing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    unicode_normalization: Option<UnicodeNormalization>,
+    /// The most tokens indexed per value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<usize>,
+    /// The most bytes of text tokenized per value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_input_bytes: Option<usize>,
+    /// What happens to text over `max_tokens` or `max_input_bytes`.
+    #[serde(default, skip_serializing_if = "LimitOverflow::is_truncate")]
+    overflow: LimitOverflow,
+}
+
+impl MatchIndexOpts {
+    /// The limits on the text this index tokenizes, if any.
+    fn limits(&self) -> Option<MatchLimits> {
+        (self.max_tokens.is_some() || self.max_input_bytes.is_some()).then_some(MatchLimits {
+            tokenizer: self.tokenizer,
+            max_tokens: self.max_tokens,
+            max_input_bytes: self.max_input_bytes,
+            overflow: self.overflow,
+        })
+    }
+
+    /// Check that the tokenizer and limits are in range.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first option that is out of range.
+    fn validate(&self) -> Result<(), String> {
+        self.tokenizer.validate()?;
+
+        if self.max_tokens == Some(0) {
+            return Err("`max_tokens` must be at least 1".to_string());
+        }
+        if self.max_input_bytes == Some(0) {
+            return Err("`max_input_bytes` must be at least 1".to_string());
+        }
+
+        Ok(())
+    }
 }
 
 /// Configuration options for structured text encryption vectors.
@@ -157,11 +495,45 @@ pub struct MatchIndexOpts {
 pub struct SteVecIndexOpts {
     /// The prefix for the structured text encryption vector.
     prefix: String,
+    /// The deepest level of nesting indexed, where fields of the root are at depth 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_depth: Option<usize>,
+    /// The largest number of entries a document may produce.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_entries: Option<usize>,
+    /// The paths indexed, with their ancestors, or every path when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    include: Vec<String>,
+    /// The paths not indexed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    exclude: Vec<String>,
 }
 
-/// Default tokenizer for match indexes.
-fn default_tokenizer() -> Tokenizer {
-    Tokenizer::Standard
+impl SteVecIndexOpts {
+    /// The parts of a document this index covers.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first limit that cannot be applied.
+    fn policy(&self) -> Result<SteVecPolicy, String> {
+        if self.max_entries == Some(0) {
+            return Err("`max_entries` must be at least 1".to_string());
+        }
+
+        let parse = |paths: &[String]| {
+            paths
+                .iter()
+                .map(|path| JsonPath::parse_filter(path).map_err(|error| error.to_string()))
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        Ok(SteVecPolicy {
+            max_depth: self.max_depth,
+            max_entries: self.max_entries,
+            include: parse(&self.include)?,
+            exclude: parse(&self.exclude)?,
+        })
+    }
 }
 
 /// Default hash function count for bloom filters.
@@ -181,15 +553,22 @@ pub struct UniqueIndexOpts {
     /// Token filters to apply to unique index tokens.
     #[serde(default)]
     token_filters: Vec<TokenFilter>,
+    /// Shorthand for a `downcase` token filter, so equality ignores letter case.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    case_insensitive: bool,
+    /// Unicode normalization applied to text before hashing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    unicode_normalization: Option<UnicodeNormalization>,
 }
 
+/// Array types convert to the column type of their elements, since indexes apply per element.
 impl From<CastAs> for ColumnType {
     fn from(value: CastAs) -> Self {
         match value {
-            CastAs::Text => ColumnType::Utf8Str,
+            CastAs::Text | CastAs::TextArray | CastAs::BigUInt => ColumnType::Utf8Str,
             CastAs::Boolean => ColumnType::Boolean,
             CastAs::SmallInt => ColumnType::SmallInt,
-            CastAs::Int => ColumnType::Int,
+            CastAs::Int | CastAs::IntArray => ColumnType::Int,
             CastAs::BigInt => ColumnType::BigInt,
             CastAs::Real | CastAs::Double => ColumnType::Float,
             CastAs::Date => ColumnType::Date,
@@ -198,6 +577,143 @@ impl From<CastAs> for ColumnType {
     }
 }
 
+/// The configuration JSON merged with the sources listed in its `extends` array.
+///
+/// Each source is a path to a JSON, YAML, or TOML file, or an object. Sources are merged in
+/// order, followed by the configuration itself, each applied to the sources before it as a JSON
+/// Merge Patch (RFC 7386): objects are merged key by key, other values replace the earlier
+/// value, and `null` removes it. Sources may extend others in turn. Relative paths are resolved
+/// against the directory of the file that lists them, or the working directory for the
+/// configuration itself.
+///
+/// # Errors
+///
+/// Returns [`crate::Error::InvalidExtends`] if `extends` is not an array of paths and objects,
+/// or if a file cannot be read, extends itself, or nests more than [`MAX_EXTENDS_DEPTH`] files.
+fn compose(json: &str) -> Result<Cow<'_, str>, crate::Error> {
+    if !json.contains("\"extends\"") {
+        return Ok(Cow::Borrowed(json));
+    }
+
+    let config: Value = serde_json::from_str(json).map_err(crate::Error::Parse)?;
+    if config.get("extends").is_none() {
+        return Ok(Cow::Borrowed(json));
+    }
+
+    Ok(Cow::Owned(
+        compose_value(config, None, &mut Vec::new())?.to_string(),
+    ))
+}
+
+/// A configuration merged with its `extends` sources, whose relative paths are resolved against
+/// `dir`. `files` holds the files being composed, outermost first.
+fn compose_value(
+    mut config: Value,
+    dir: Option<&Path>,
+    files: &mut Vec<PathBuf>,
+) -> Result<Value, crate::Error> {
+    let sources = match config
+        .as_object_mut()
+        .and_then(|config| config.remove("extends"))
+    {
+        None => return Ok(config),
+        Some(Value::Array(sources)) => sources,
+        Some(_) => {
+            return Err(crate::Error::InvalidExtends(
+                "`extends` must be an array of file paths and objects".to_string(),
+            ))
+        }
+    };
+
+    let mut merged = Value::Object(Map::new());
+    for source in sources {
+        let source = match source {
+            Value::String(path) => load_source(&path, dir, files)?,
+            Value::Object(_) => compose_value(source, dir, files)?,
+            _ => {
+                return Err(crate::Error::InvalidExtends(format!(
+                    "`{source}` is not a file path or an object"
+                )))
+            }
+        };
+        merge_patch(&mut merged, source);
+    }
+    merge_patch(&mut merged, config);
+
+    Ok(merged)
+}
+
+/// Read and compose a configuration file listed in `extends`.
+fn load_source(
+    path: &str,
+    dir: Option<&Path>,
+    files: &mut Vec<PathBuf>,
+) -> Result<Value, crate::Error> {
+    let path = match dir {
+        Some(dir) => dir.join(path),
+        None => PathBuf::from(path),
+    };
+    let unreadable = |err: std::io::Error| {
+        crate::Error::InvalidExtends(format!("cannot read `{}`: {err}", path.display()))
+    };
+
+    let file = fs::canonicalize(&path).map_err(unreadable)?;
+    if files.contains(&file) {
+        return Err(crate::Error::InvalidExtends(format!(
+            "`{}` extends itself",
+            path.display()
+        )));
+    }
+    if files.len() >= MAX_EXTENDS_DEPTH {
+        return Err(crate::Error::InvalidExtends(format!(
+            "`{}` nests more than {MAX_EXTENDS_DEPTH} files",
+            path.display()
+        )));
+    }
+
+    let data = fs::read_to_string(&file).map_err(unreadable)?;
+    let format = match file.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => ConfigFormat::Json,
+        Some("yaml" | "yml") => ConfigFormat::Yaml,
+        Some("toml") => ConfigFormat::Toml,
+        _ => config_format::detect(&data),
+    };
+    let json = config_format::to_json(&data, format)?;
+    let json = interpolation::interpolate(&json)?;
+    let source: Value = serde_json::from_str(&json).map_err(crate::Error::Parse)?;
+
+    files.push(file);
+    let dir = files
+        .last()
+        .and_then(|file| file.parent())
+        .map(Path::to_path_buf);
+    let composed = compose_value(source, dir.as_deref(), files);
+    files.pop();
+
+    composed
+}
+
+/// Apply a JSON Merge Patch (RFC 7386) to a value.
+pub fn merge_patch(target: &mut Value, patch: Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch;
+        return;
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    if let Value::Object(target) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(&key);
+            } else {
+                merge_patch(target.entry(key).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
 impl FromStr for EncryptConfig {
     type Err = crate::Error;
 
@@ -208,11 +724,165 @@ impl FromStr for EncryptConfig {
             return Err(crate::Error::UnsupportedSchemaVersion(config.version));
         }
 
+        for (table_name, table) in &config.tables.0 {
+            for (column_name, column) in &table.0 {
+                #[cfg(not(feature = "match-index"))]
+                if column.indexes.match_index.is_some() {
+                    return Err(crate::Error::Unimplemented(format!(
+                        "`match` index on column `{table_name}.{column_name}`, which requires building with the `match-index` feature"
+                    )));
+                }
+
+                #[cfg(not(feature = "ste-vec"))]
+                if column.indexes.ste_vec_index.is_some() {
+                    return Err(crate::Error::Unimplemented(format!(
+                        "`ste_vec` index on column `{table_name}.{column_name}`, which requires building with the `ste-vec` feature"
+                    )));
+                }
+
+                if let Some(template) = &column.context_from {
+                    column_context::derive(template, &Identifier::new(table_name, column_name))
+                        .map_err(|message| {
+                            crate::Error::InvalidColumn(
+                                Identifier::new(table_name, column_name),
+                                message,
+                            )
+                        })?;
+                }
+
+                if let Some(keyset) = &column.keyset {
+                    if !config.keysets.contains_key(keyset) {
+                        return Err(crate::Error::UnknownKeyset(keyset.clone()));
+                    }
+                }
+
+                if column.float_format.is_some()
+                    && !matches!(column.cast_as, CastAs::Real | CastAs::Double)
+                {
+                    return Err(crate::Error::InvalidColumn(
+                        Identifier::new(table_name, column_name),
+                        "`float_format` requires `cast_as` to be `real` or `double`".to_string(),
+                    ));
+                }
+
+                if (column.allow_empty.is_some() || column.trim || column.max_length.is_some())
+                    && !matches!(column.cast_as, CastAs::Text | CastAs::TextArray)
+                {
+                    return Err(crate::Error::InvalidColumn(
+                        Identifier::new(table_name, column_name),
+                        "`allow_empty`, `trim`, and `max_length` require `cast_as` to be `text` or `text[]`"
+                            .to_string(),
+                    ));
+                }
+
+                if column.max_length == Some(0) {
+                    return Err(crate::Error::InvalidColumn(
+                        Identifier::new(table_name, column_name),
+                        "`max_length` must be at least 1".to_string(),
+                    ));
+                }
+
+                if let Some(opts) = &column.indexes.match_index {
+                    opts.validate().map_err(|message| {
+                        crate::Error::InvalidColumn(
+                            Identifier::new(table_name, column_name),
+                            message,
+                        )
+                    })?;
+                }
+
+                let normalization = column.indexes.normalization();
+                if (normalization.unique.is_some() || normalization.r#match.is_some())
+                    && !matches!(column.cast_as, CastAs::Text | CastAs::TextArray)
+                {
+                    return Err(crate::Error::InvalidColumn(
+                        Identifier::new(table_name, column_name),
+                        "`unicode_normalization` requires `cast_as` to be `text` or `text[]`"
+                            .to_string(),
+                    ));
+                }
+
+                if normalization.match_limits.is_some()
+                    && !matches!(column.cast_as, CastAs::Text | CastAs::TextArray)
+                {
+                    return Err(crate::Error::InvalidColumn(
+                        Identifier::new(table_name, column_name),
+                        "`max_tokens` and `max_input_bytes` require `cast_as` to be `text` or `text[]`"
+                            .to_string(),
+                    ));
+                }
+
+                if column
+                    .indexes
+                    .unique_index
+                    .as_ref()
+                    .is_some_and(|opts| opts.case_insensitive)
+                    && !matches!(column.cast_as, CastAs::Text | CastAs::TextArray)
+                {
+                    return Err(crate::Error::InvalidColumn(
+                        Identifier::new(table_name, column_name),
+                        "`case_insensitive` requires `cast_as` to be `text` or `text[]`"
+                            .to_string(),
+                    ));
+                }
+
+                if column.cast_as == CastAs::BigUInt
+                    && (column.indexes.ore_index.is_some() || column.indexes.match_index.is_some())
+                {
+                    return Err(crate::Error::InvalidColumn(
+                        Identifier::new(table_name, column_name),
+                        "`big_uint` columns support equality queries only, so only `unique` indexes are allowed".to_string(),
+                    ));
+                }
+
+                if column.cast_as.element_type().is_some() && column.indexes.ste_vec_index.is_some()
+                {
+                    return Err(crate::Error::InvalidColumn(
+                        Identifier::new(table_name, column_name),
+                        "`ste_vec` indexes are not supported on array columns".to_string(),
+                    ));
+                }
+
+                if let Some(opts) = &column.indexes.ste_vec_index {
+                    opts.policy().map_err(|message| {
+                        crate::Error::InvalidColumn(
+                            Identifier::new(table_name, column_name),
+                            message,
+                        )
+                    })?;
+                }
+
+                if let Some(lint) = column.lints().into_iter().find(|lint| lint.is_error()) {
+                    return Err(crate::Error::InvalidColumn(
+                        Identifier::new(table_name, column_name),
+                        lint.message(column.cast_as),
+                    ));
+                }
+            }
+        }
+
+        config.context_presets()?;
+
         Ok(config)
     }
 }
 
 impl EncryptConfig {
+    /// Parse a configuration written in a format, or in the format detected from its text when
+    /// `format` is `None`, resolving its `${NAME}` environment variable references.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration is not valid in its format, references an unset
+    /// environment variable without a fallback, extends a source that cannot be read, or fails
+    /// the checks of [`EncryptConfig::from_str`].
+    pub fn parse(data: &str, format: Option<ConfigFormat>) -> Result<Self, crate::Error> {
+        let format = format.unwrap_or_else(|| config_format::detect(data));
+        let json = config_format::to_json(data, format)?;
+        let json = interpolation::interpolate(&json)?;
+        Self::from_str(&compose(&json)?)
+    }
+
     /// Convert the encryption configuration into a [`HashMap`] mapping [`Identifier`] to
     /// [`ColumnConfig`] for fast column lookups.
     pub fn into_config_map(self) -> HashMap<Identifier, (ColumnConfig, CastAs)> {
@@ -226,14 +896,193 @@ impl EncryptConfig {
         }
         map
     }
+
+    /// The [`Identifier`] of each column whose `match` index sets `include_original`, in table
+    /// and column order.
+    pub fn include_original_columns(&self) -> Vec<Identifier> {
+        let mut identifiers = Vec::new();
+        for (table_name, columns) in &self.tables.0 {
+            for (column_name, column) in &columns.0 {
+                if column
+                    .indexes
+                    .match_index
+                    .as_ref()
+                    .is_some_and(|opts| opts.include_original)
+                {
+                    identifiers.push(Identifier::new(table_name, column_name));
+                }
+            }
+        }
+        identifiers.sort_by(|a, b| (&a.table, &a.column).cmp(&(&b.table, &b.column)));
+        identifiers
+    }
+
+    /// The lints of each column that are not allowed by its `allow_lints`, with the column's
+    /// [`Identifier`] and description, in table and column order.
+    ///
+    /// A configuration parsed with [`EncryptConfig::from_str`] has warning lints only.
+    pub fn lints(&self) -> Vec<(Identifier, Lint, String)> {
+        let mut lints = Vec::new();
+        for (table_name, columns) in &self.tables.0 {
+            for (column_name, column) in &columns.0 {
+                for lint in column.lints() {
+                    lints.push((
+                        Identifier::new(table_name, column_name),
+                        lint,
+                        lint.message(column.cast_as),
+                    ));
+                }
+            }
+        }
+        lints.sort_by(|(a, ..), (b, ..)| (&a.table, &a.column).cmp(&(&b.table, &b.column)));
+        lints
+    }
+
+    /// Map each [`Identifier`] whose column sets a `float_format` to that format.
+    pub fn float_formats(&self) -> HashMap<Identifier, FloatFormat> {
+        let mut map = HashMap::new();
+        for (table_name, columns) in &self.tables.0 {
+            for (column_name, column) in &columns.0 {
+                if let Some(float_format) = column.float_format {
+                    map.insert(Identifier::new(table_name, column_name), float_format);
+                }
+            }
+        }
+        map
+    }
+
+    /// Map each [`Identifier`] whose column sets `allow_empty`, `trim`, `max_length`, an index
+    /// `unicode_normalization`, an edge `match` tokenizer, or `match` limits to its text policy.
+    pub fn text_policies(&self) -> HashMap<Identifier, TextPolicy> {
+        let mut map = HashMap::new();
+        for (table_name, columns) in &self.tables.0 {
+            for (column_name, column) in &columns.0 {
+                let policy = TextPolicy {
+                    allow_empty: column.allow_empty.unwrap_or(true),
+                    trim: column.trim,
+                    max_length: column.max_length,
+                    normalization: column.indexes.normalization(),
+                };
+                if policy != TextPolicy::default() {
+                    map.insert(Identifier::new(table_name, column_name), policy);
+                }
+            }
+        }
+        map
+    }
+
+    /// Map each [`Identifier`] whose `ste_vec` index limits the parts of documents it covers to
+    /// its policy.
+    pub fn ste_vec_policies(&self) -> HashMap<Identifier, SteVecPolicy> {
+        let mut map = HashMap::new();
+        for (table_name, columns) in &self.tables.0 {
+            for (column_name, column) in &columns.0 {
+                let Some(Ok(policy)) = column
+                    .indexes
+                    .ste_vec_index
+                    .as_ref()
+                    .map(|opts| opts.policy())
+                else {
+                    continue;
+                };
+                if policy != SteVecPolicy::default() {
+                    map.insert(Identifier::new(table_name, column_name), policy);
+                }
+            }
+        }
+        map
+    }
+
+    /// Map each [`Identifier`] whose column references a named keyset to that keyset's name.
+    pub fn column_keysets(&self) -> HashMap<Identifier, String> {
+        let mut map = HashMap::new();
+        for (table_name, columns) in &self.tables.0 {
+            for (column_name, column) in &columns.0 {
+                if let Some(keyset) = &column.keyset {
+                    map.insert(Identifier::new(table_name, column_name), keyset.clone());
+                }
+            }
+        }
+        map
+    }
+
+    /// Map each [`Identifier`] whose column sets `context_from` to the context it derives.
+    pub fn column_contexts(&self) -> HashMap<Identifier, Vec<ContextItem>> {
+        let mut map = HashMap::new();
+        for (table_name, columns) in &self.tables.0 {
+            for (column_name, column) in &columns.0 {
+                let identifier = Identifier::new(table_name, column_name);
+                let Some(Ok(items)) = column
+                    .context_from
+                    .as_ref()
+                    .map(|template| column_context::derive(template, &identifier))
+                else {
+                    continue;
+                };
+                map.insert(identifier, items);
+            }
+        }
+        map
+    }
+
+    /// Parse the configuration's named encryption context presets.
+    pub fn context_presets(&self) -> Result<ContextPresets, crate::Error> {
+        ContextPresets::new(&self.contexts)
+    }
+
+    /// A SHA-256 digest of the configuration, in hex, which is the same for configurations that
+    /// differ only in formatting, key order, or the format they were written in.
+    pub fn digest(&self) -> Result<String, crate::Error> {
+        let canonical = canonical(serde_json::to_value(self)?);
+        Ok(hex::encode(Sha256::digest(
+            canonical.to_string().as_bytes(),
+        )))
+    }
+}
+
+/// A JSON value with the keys of every object in sorted order.
+fn canonical(value: Value) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut entries: Vec<(String, Value)> = object.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, canonical(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(canonical).collect()),
+        value => value,
+    }
 }
 
 impl Column {
+    /// The lints of the column's type and indexes, except those it allows.
+    fn lints(&self) -> Vec<Lint> {
+        let indexes = ColumnIndexes {
+            unique: self.indexes.unique_index.is_some(),
+            ore: self.indexes.ore_index.is_some(),
+            r#match: self.indexes.match_index.is_some(),
+            ste_vec: self.indexes.ste_vec_index.is_some(),
+        };
+
+        lints::check(self.cast_as, indexes)
+            .into_iter()
+            .filter(|lint| !self.allow_lints.contains(lint))
+            .collect()
+    }
+
     /// Convert this column configuration into a [`ColumnConfig`].
     pub fn into_column_config(self, name: &str) -> ColumnConfig {
         let mut config = ColumnConfig::build(name.to_string()).casts_as(self.cast_as.into());
 
-        if let Some(opts) = self.indexes.unique_index {
+        if let Some(mut opts) = self.indexes.unique_index {
+            if opts.case_insensitive && !opts.token_filters.contains(&TokenFilter::Downcase) {
+                opts.token_filters.push(TokenFilter::Downcase);
+            }
+
             config = config.add_index(Index::new(IndexType::Unique {
                 token_filters: opts.token_filters,
             }))
@@ -243,9 +1092,10 @@ impl Column {
             config = config.add_index(Index::new_ore());
         }
 
+        #[cfg(feature = "match-index")]
         if let Some(opts) = self.indexes.match_index {
             config = config.add_index(Index::new(IndexType::Match {
-                tokenizer: opts.tokenizer,
+                tokenizer: opts.tokenizer.library_tokenizer(),
                 token_filters: opts.token_filters,
                 k: opts.k,
                 m: opts.m,
@@ -253,8 +1103,11 @@ impl Column {
             }));
         }
 
-        if let Some(SteVecIndexOpts { prefix }) = self.indexes.ste_vec_index {
-            config = config.add_index(Index::new(IndexType::SteVec { prefix }))
+        #[cfg(feature = "ste-vec")]
+        if let Some(opts) = self.indexes.ste_vec_index {
+            config = config.add_index(Index::new(IndexType::SteVec {
+                prefix: opts.prefix,
+            }))
         }
 
         config
@@ -264,6 +1117,8 @@ impl Column {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ngrams::EdgeNgrams;
+    use cipherstash_client::schema::column::Tokenizer;
     use serde_json::json;
 
     /// Parse valid JSON configuration into a [`HashMap`] mapping [`Identifier`] to
@@ -328,10 +1183,13 @@ mod tests {
             (CastAs::SmallInt, "small_int"),
             (CastAs::Int, "int"),
             (CastAs::BigInt, "big_int"),
+            (CastAs::BigUInt, "big_uint"),
             (CastAs::Real, "real"),
             (CastAs::Double, "double"),
             (CastAs::Date, "date"),
             (CastAs::JsonB, "jsonb"),
+            (CastAs::TextArray, "text[]"),
+            (CastAs::IntArray, "int[]"),
         ];
 
         for (cast_as, expected_string) in test_cases {
@@ -347,10 +1205,13 @@ mod tests {
             (CastAs::SmallInt, ColumnType::SmallInt),
             (CastAs::Int, ColumnType::Int),
             (CastAs::BigInt, ColumnType::BigInt),
+            (CastAs::BigUInt, ColumnType::Utf8Str),
             (CastAs::Real, ColumnType::Float),
             (CastAs::Double, ColumnType::Float),
             (CastAs::Date, ColumnType::Date),
             (CastAs::JsonB, ColumnType::JsonB),
+            (CastAs::TextArray, ColumnType::Utf8Str),
+            (CastAs::IntArray, ColumnType::Int),
         ];
 
         for (cast_as, expected_column_type) in test_cases {
@@ -510,6 +1371,31 @@ mod tests {
         assert_eq!(*cast_as, CastAs::Text);
     }
 
+    #[test]
+    fn test_unique_index_case_insensitive() {
+        for indexes in [
+            json!({"unique": {"case_insensitive": true}}),
+            json!({"unique": {"case_insensitive": true, "token_filters": [{"kind": "downcase"}]}}),
+        ] {
+            let config_json = config_with_indexes("users", "email", "text", indexes);
+            let parsed_config = parse_config(config_json);
+            let (column_config, _) = get_column_config(&parsed_config, "users", "email");
+
+            assert_eq!(
+                column_config.indexes[0].index_type,
+                IndexType::Unique {
+                    token_filters: vec![TokenFilter::Downcase]
+                }
+            );
+        }
+
+        let indexes = json!({"unique": {"case_insensitive": true}});
+        let config_json = config_with_indexes("users", "id", "big_uint", indexes);
+        let result = EncryptConfig::from_str(&config_json.to_string());
+
+        assert!(matches!(result, Err(crate::Error::InvalidColumn(_, _))));
+    }
+
     #[test]
     fn test_ore_index() {
         let indexes = json!({"ore": {}});
@@ -577,6 +1463,88 @@ mod tests {
         assert_eq!(*cast_as, CastAs::Text);
     }
 
+    #[test]
+    fn test_match_index_edge_ngram_tokenizer() {
+        let indexes = json!({
+            "match": {
+                "tokenizer": {"kind": "edge_ngram", "min_length": 2, "max_length": 8},
+                "token_filters": [{"kind": "downcase"}]
+            }
+        });
+        let config_json = config_with_indexes("users", "name", "text", indexes);
+        let config = EncryptConfig::from_str(&config_json.to_string()).unwrap();
+        let text_policies = config.text_policies();
+        let parsed_config = config.into_config_map();
+        let (column_config, _) = get_column_config(&parsed_config, "users", "name");
+
+        assert!(matches!(
+            column_config.indexes[0].index_type,
+            IndexType::Match {
+                tokenizer: Tokenizer::Standard,
+                ..
+            }
+        ));
+        assert_eq!(
+            text_policies[&Identifier::new("users", "name")]
+                .normalization
+                .match_edge_ngrams,
+            Some(EdgeNgrams {
+                words: true,
+                min_length: 2,
+                max_length: 8,
+                downcase: true,
+            })
+        );
+    }
+
+    #[test]
+    fn test_match_index_limits() {
+        let indexes = json!({
+            "match": {"max_tokens": 100, "max_input_bytes": 4096, "overflow": "error"}
+        });
+        let config_json = config_with_indexes("users", "bio", "text", indexes);
+        let config = EncryptConfig::from_str(&config_json.to_string()).unwrap();
+
+        assert_eq!(
+            config.text_policies()[&Identifier::new("users", "bio")]
+                .normalization
+                .match_limits,
+            Some(MatchLimits {
+                tokenizer: MatchTokenizer::Standard,
+                max_tokens: Some(100),
+                max_input_bytes: Some(4096),
+                overflow: LimitOverflow::Error,
+            })
+        );
+
+        for (cast_as, indexes) in [
+            ("text", json!({"match": {"max_tokens": 0}})),
+            ("int", json!({"match": {"max_input_bytes": 64}})),
+        ] {
+            let config_json = config_with_indexes("users", "bio", cast_as, indexes);
+            let result = EncryptConfig::from_str(&config_json.to_string());
+
+            assert!(matches!(result, Err(crate::Error::InvalidColumn(_, _))));
+        }
+    }
+
+    #[test]
+    fn test_match_index_rejects_token_length_out_of_range() {
+        for tokenizer in [
+            json!({"kind": "ngram", "token_length": 0}),
+            json!({"kind": "prefix", "min_length": 4, "max_length": 2}),
+        ] {
+            let indexes = json!({"match": {"tokenizer": tokenizer}});
+            let config_json = config_with_indexes("users", "name", "text", indexes);
+            let result = EncryptConfig::from_str(&config_json.to_string());
+
+            assert!(
+                matches!(result, Err(crate::Error::InvalidColumn(_, _))),
+                "{tokenizer}"
+            );
+        }
+    }
+
     #[test]
     fn test_ste_vec_index() {
         let indexes = json!({
@@ -710,4 +1678,646 @@ mod tests {
             other => panic!("expected `Parse` error, got: {:?}", other),
         }
     }
+
+    #[test]
+    fn test_config_parsing_conflicting_duplicate_column_fails() {
+        let config = r#"{"v": 2, "tables": {"users": {
+            "email": {"cast_as": "text", "indexes": {"unique": {}}},
+            "name": {"cast_as": "text"},
+            "email": {"cast_as": "text", "indexes": {"match": {}}}
+        }}}"#;
+
+        let error = EncryptConfig::from_str(config).unwrap_err();
+        assert!(matches!(error, crate::Error::Parse(_)));
+        assert!(error.to_string().contains(
+            "column `email` is defined twice with different options, as entries 1 and 3"
+        ));
+
+        let config = r#"{"v": 2, "tables": {"users": {
+            "email": {"cast_as": "text"},
+            "email": {"cast_as": "text"}
+        }}}"#;
+        let parsed = EncryptConfig::from_str(config).unwrap().into_config_map();
+        assert_eq!(parsed.len(), 1);
+    }
+
+    #[test]
+    fn test_config_parsing_conflicting_duplicate_table_and_keyset_fail() {
+        let config = r#"{"v": 2, "tables": {
+            "users": {"email": {"cast_as": "text"}},
+            "users": {"name": {"cast_as": "text"}}
+        }}"#;
+        let error = EncryptConfig::from_str(config).unwrap_err();
+        assert!(error.to_string().contains("table `users` is defined twice"));
+
+        let config = r#"{"v": 2, "tables": {}, "keysets": {
+            "pii": "5d7b1e1a-6c1b-4d3f-9a2e-0c8f1b2a3d4e",
+            "pii": "0c8f1b2a-3d4e-4d3f-9a2e-5d7b1e1a6c1b"
+        }}"#;
+        let error = EncryptConfig::from_str(config).unwrap_err();
+        assert!(error.to_string().contains("keyset `pii` is defined twice"));
+    }
+
+    #[test]
+    fn test_unknown_column_suggests_closest_columns() {
+        let config = parse_config(json!({
+            "v": 2,
+            "tables": {
+                "users": {"email": {}, "emails": {}, "name": {}},
+                "orders": {"total": {}}
+            }
+        }));
+
+        let error = column(&config, &Identifier::new("users", "emial")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unknown column `users.emial` (did you mean `users.email` or `users.emails`?)"
+        );
+
+        let error = column(&config, &Identifier::new("Users", "Name")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unknown column `Users.Name` (did you mean `users.name`?)"
+        );
+
+        let error = column(&config, &Identifier::new("invoices", "due_on")).unwrap_err();
+        assert_eq!(error.to_string(), "unknown column `invoices.due_on`");
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("users.email", "users.email"), 0);
+        assert_eq!(edit_distance("users.emial", "users.email"), 2);
+        assert_eq!(edit_distance("users.mail", "users.email"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_case_folded_identifiers() {
+        let config =
+            EncryptConfig::from_str(r#"{"v": 2, "tables": {"Users": {"Email": {}, "name": {}}}}"#)
+                .unwrap();
+
+        let folded = case_folded_identifiers(&config).unwrap();
+
+        assert_eq!(
+            folded.get(&Identifier::new("users", "email")),
+            Some(&Identifier::new("Users", "Email"))
+        );
+        assert_eq!(
+            Identifier::new("USERS", "NAME").case_folded(),
+            Identifier::new("users", "name")
+        );
+    }
+
+    #[test]
+    fn test_case_folded_identifiers_rejects_columns_differing_in_case() {
+        let config =
+            EncryptConfig::from_str(r#"{"v": 2, "tables": {"users": {"Email": {}, "email": {}}}}"#)
+                .unwrap();
+
+        let error = case_folded_identifiers(&config).unwrap_err();
+
+        assert!(error
+            .to_string()
+            .contains("columns `users.Email` and `users.email` differ only in case"));
+    }
+
+    #[test]
+    fn test_column_keysets() {
+        let config_json = json!({
+            "v": 2,
+            "keysets": {
+                "pii": "5d7b1e1a-6c1b-4d3f-9a2e-0c8f1b2a3d4e"
+            },
+            "tables": {
+                "users": {
+                    "email": {"cast_as": "text", "keyset": "pii"},
+                    "name": {"cast_as": "text"}
+                }
+            }
+        });
+
+        let config = EncryptConfig::from_str(&config_json.to_string()).unwrap();
+        let column_keysets = config.column_keysets();
+
+        assert_eq!(config.keysets.len(), 1);
+        assert_eq!(
+            column_keysets.get(&Identifier::new("users", "email")),
+            Some(&"pii".to_string())
+        );
+        assert!(!column_keysets.contains_key(&Identifier::new("users", "name")));
+    }
+
+    #[test]
+    fn test_config_parsing_unknown_keyset_fails() {
+        let invalid_json = json!({
+            "v": 2,
+            "tables": {
+                "users": {
+                    "email": {"cast_as": "text", "keyset": "pci"}
+                }
+            }
+        });
+
+        let result = EncryptConfig::from_str(&invalid_json.to_string());
+
+        match result.unwrap_err() {
+            crate::Error::UnknownKeyset(keyset) => {
+                assert_eq!(keyset, "pci");
+            }
+            other => panic!("expected `UnknownKeyset` error, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_text_policies() {
+        let config_json = json!({
+            "v": 2,
+            "tables": {
+                "users": {
+                    "email": {"cast_as": "text", "allow_empty": false, "trim": true},
+                    "tags": {"cast_as": "text[]", "trim": true, "max_length": 32},
+                    "name": {"cast_as": "text", "allow_empty": true}
+                }
+            }
+        });
+
+        let config = EncryptConfig::from_str(&config_json.to_string()).unwrap();
+        let text_policies = config.text_policies();
+
+        assert_eq!(
+            text_policies.get(&Identifier::new("users", "email")),
+            Some(&TextPolicy {
+                allow_empty: false,
+                trim: true,
+                ..TextPolicy::default()
+            })
+        );
+        assert_eq!(
+            text_policies.get(&Identifier::new("users", "tags")),
+            Some(&TextPolicy {
+                trim: true,
+                max_length: Some(32),
+                ..TextPolicy::default()
+            })
+        );
+        assert!(!text_policies.contains_key(&Identifier::new("users", "name")));
+    }
+
+    #[test]
+    fn test_text_policy_requires_text_column() {
+        for column in [
+            json!({"cast_as": "int", "allow_empty": false}),
+            json!({"cast_as": "int", "max_length": 8}),
+            json!({"cast_as": "text", "max_length": 0}),
+        ] {
+            let invalid_json = json!({
+                "v": 2,
+                "tables": {"users": {"age": column}}
+            });
+
+            let result = EncryptConfig::from_str(&invalid_json.to_string());
+
+            assert!(
+                matches!(result, Err(crate::Error::InvalidColumn(_, _))),
+                "{column}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_unicode_normalization() {
+        let config_json = json!({
+            "v": 2,
+            "tables": {
+                "users": {
+                    "name": {
+                        "cast_as": "text",
+                        "indexes": {
+                            "unique": {"unicode_normalization": "nfc"},
+                            "match": {"unicode_normalization": "NFKC"},
+                            "ore": {}
+                        }
+                    }
+                }
+            }
+        });
+
+        let config = EncryptConfig::from_str(&config_json.to_string()).unwrap();
+        let text_policies = config.text_policies();
+
+        assert_eq!(
+            text_policies[&Identifier::new("users", "name")].normalization,
+            IndexNormalization {
+                unique: Some(UnicodeNormalization::Nfc),
+                r#match: Some(UnicodeNormalization::Nfkc),
+                ..IndexNormalization::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_unicode_normalization_requires_text_column() {
+        let invalid_json = json!({
+            "v": 2,
+            "tables": {
+                "users": {
+                    "id": {
+                        "cast_as": "big_uint",
+                        "indexes": {"unique": {"unicode_normalization": "nfc"}}
+                    }
+                }
+            }
+        });
+
+        let result = EncryptConfig::from_str(&invalid_json.to_string());
+
+        assert!(matches!(result, Err(crate::Error::InvalidColumn(_, _))));
+    }
+
+    #[test]
+    fn test_include_original_columns() {
+        let config_json = json!({
+            "v": 2,
+            "tables": {
+                "users": {
+                    "notes": {"indexes": {"match": {"include_original": true}}},
+                    "bio": {"indexes": {"match": {}}}
+                },
+                "articles": {
+                    "title": {"indexes": {"match": {"include_original": true}}}
+                }
+            }
+        });
+
+        let config = EncryptConfig::from_str(&config_json.to_string()).unwrap();
+
+        assert_eq!(
+            config.include_original_columns(),
+            [
+                Identifier::new("articles", "title"),
+                Identifier::new("users", "notes")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_float_formats() {
+        let config_json = json!({
+            "v": 2,
+            "tables": {
+                "products": {
+                    "price": {"cast_as": "double", "float_format": {"precision": 2}},
+                    "weight": {"cast_as": "real"}
+                }
+            }
+        });
+
+        let config = EncryptConfig::from_str(&config_json.to_string()).unwrap();
+        let float_formats = config.float_formats();
+
+        assert_eq!(
+            float_formats
+                .get(&Identifier::new("products", "price"))
+                .and_then(|float_format| float_format.precision),
+            Some(2)
+        );
+        assert!(!float_formats.contains_key(&Identifier::new("products", "weight")));
+    }
+
+    #[test]
+    fn test_config_parsing_float_format_on_text_column_fails() {
+        let invalid_json = json!({
+            "v": 2,
+            "tables": {
+                "users": {
+                    "email": {"cast_as": "text", "float_format": {}}
+                }
+            }
+        });
+
+        let result = EncryptConfig::from_str(&invalid_json.to_string());
+
+        match result.unwrap_err() {
+            crate::Error::InvalidColumn(identifier, _) => {
+                assert_eq!(identifier, Identifier::new("users", "email"));
+            }
+            other => panic!("expected `InvalidColumn` error, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_array_column_uses_element_config() {
+        let config_json = config_with_indexes("users", "tags", "text[]", json!({"unique": {}}));
+        let parsed_config = parse_config(config_json);
+
+        let (column_config, cast_as) = get_column_config(&parsed_config, "users", "tags");
+        assert_eq!(*cast_as, CastAs::TextArray);
+        assert_eq!(cast_as.element_type(), Some(CastAs::Text));
+        assert_eq!(column_config.cast_type, ColumnType::Utf8Str);
+        assert_eq!(column_config.indexes.len(), 1);
+    }
+
+    #[test]
+    fn test_config_parsing_ste_vec_on_array_column_fails() {
+        let invalid_json = config_with_indexes(
+            "users",
+            "tags",
+            "text[]",
+            json!({"ste_vec": {"prefix": "users/tags"}}),
+        );
+
+        let result = EncryptConfig::from_str(&invalid_json.to_string());
+
+        assert!(matches!(result, Err(crate::Error::InvalidColumn(..))));
+    }
+
+    #[test]
+    #[cfg(not(feature = "match-index"))]
+    fn test_config_parsing_match_index_without_feature_fails() {
+        let json = config_with_indexes("users", "bio", "text", json!({"match": {}}));
+
+        let result = EncryptConfig::from_str(&json.to_string());
+
+        assert!(matches!(result, Err(crate::Error::Unimplemented(_))));
+    }
+
+    #[test]
+    #[cfg(not(feature = "ste-vec"))]
+    fn test_config_parsing_ste_vec_without_feature_fails() {
+        let json = config_with_indexes(
+            "users",
+            "metadata",
+            "jsonb",
+            json!({"ste_vec": {"prefix": "users/metadata"}}),
+        );
+
+        let result = EncryptConfig::from_str(&json.to_string());
+
+        assert!(matches!(result, Err(crate::Error::Unimplemented(_))));
+    }
+
+    #[test]
+    fn test_ste_vec_policies() {
+        let config_json = config_with_indexes(
+            "users",
+            "metadata",
+            "jsonb",
+            json!({"ste_vec": {
+                "prefix": "users/metadata",
+                "max_depth": 3,
+                "exclude": ["$.raw_payload"]
+            }}),
+        );
+
+        let config = EncryptConfig::from_str(&config_json.to_string()).unwrap();
+        let policy = &config.ste_vec_policies()[&Identifier::new("users", "metadata")];
+
+        assert_eq!(policy.max_depth, Some(3));
+        assert_eq!(policy.max_entries, None);
+        assert!(policy.include.is_empty());
+        assert_eq!(policy.exclude, [JsonPath::parse("$.raw_payload").unwrap()]);
+
+        let config_json = config_with_indexes(
+            "users",
+            "metadata",
+            "jsonb",
+            json!({"ste_vec": {"prefix": "p"}}),
+        );
+        let config = EncryptConfig::from_str(&config_json.to_string()).unwrap();
+        assert!(config.ste_vec_policies().is_empty());
+    }
+
+    #[test]
+    fn test_config_parsing_invalid_ste_vec_limits_fail() {
+        for ste_vec in [
+            json!({"prefix": "users/metadata", "max_entries": 0}),
+            json!({"prefix": "users/metadata", "include": ["profile.email"]}),
+            json!({"prefix": "users/metadata", "exclude": ["$"]}),
+        ] {
+            let invalid_json =
+                config_with_indexes("users", "metadata", "jsonb", json!({"ste_vec": ste_vec}));
+            let result = EncryptConfig::from_str(&invalid_json.to_string());
+
+            assert!(matches!(result, Err(crate::Error::InvalidColumn(..))));
+        }
+    }
+
+    #[test]
+    fn test_config_parsing_lints_index_and_type_combinations() {
+        for (cast_as, indexes) in [
+            ("int", json!({"match": {}})),
+            ("int[]", json!({"match": {}})),
+            ("jsonb", json!({"ore": {}})),
+            ("text", json!({"ste_vec": {"prefix": "users/bio"}})),
+        ] {
+            let invalid_json = config_with_indexes("users", "bio", cast_as, indexes);
+            let result = EncryptConfig::from_str(&invalid_json.to_string());
+
+            match result {
+                Err(crate::Error::InvalidColumn(identifier, message)) => {
+                    assert_eq!(identifier, Identifier::new("users", "bio"));
+                    assert!(message.contains("allow_lints"), "{message}");
+                }
+                other => panic!("expected `InvalidColumn` error, got: {other:?}"),
+            }
+        }
+
+        let config_json = config_with_indexes("users", "profile", "jsonb", json!({"unique": {}}));
+        let config = EncryptConfig::from_str(&config_json.to_string()).unwrap();
+        let lints = config.lints();
+        assert_eq!(lints.len(), 1);
+        assert_eq!(
+            (&lints[0].0, lints[0].1),
+            (&Identifier::new("users", "profile"), Lint::UniqueOnJsonb)
+        );
+    }
+
+    #[test]
+    fn test_config_parsing_allowed_lints() {
+        let config_json = json!({
+            "v": 2,
+            "tables": {
+                "users": {
+                    "age": {
+                        "cast_as": "int",
+                        "indexes": {"match": {}},
+                        "allow_lints": ["match_requires_text"]
+                    },
+                    "profile": {
+                        "cast_as": "jsonb",
+                        "indexes": {"unique": {}},
+                        "allow_lints": ["unique_on_jsonb"]
+                    }
+                }
+            }
+        });
+
+        let config = EncryptConfig::from_str(&config_json.to_string()).unwrap();
+        assert!(config.lints().is_empty());
+    }
+
+    #[test]
+    fn test_merge_patch() {
+        let mut target = json!({
+            "v": 2,
+            "tables": {"users": {"email": {"cast_as": "text", "indexes": {"unique": {}}}}}
+        });
+        merge_patch(
+            &mut target,
+            json!({
+                "tables": {
+                    "users": {"email": {"indexes": {"unique": null, "match": {}}}},
+                    "orders": {"total": {"cast_as": "int"}}
+                }
+            }),
+        );
+
+        assert_eq!(
+            target,
+            json!({
+                "v": 2,
+                "tables": {
+                    "users": {"email": {"cast_as": "text", "indexes": {"match": {}}}},
+                    "orders": {"total": {"cast_as": "int"}}
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_config_parsing_extends() {
+        let dir = std::env::temp_dir().join(format!("protect-ffi-extends-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("base.json"),
+            json!({
+                "v": 2,
+                "tables": {"users": {"email": {"cast_as": "text", "indexes": {"unique": {}}}}}
+            })
+            .to_string(),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("orders.json"),
+            json!({
+                "extends": ["base.json"],
+                "tables": {"orders": {"total": {"cast_as": "int", "indexes": {"ore": {}}}}}
+            })
+            .to_string(),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("cycle.json"),
+            json!({"extends": ["cycle.json"]}).to_string(),
+        )
+        .unwrap();
+
+        let config_json = json!({
+            "extends": [
+                dir.join("orders.json").display().to_string(),
+                {"tables": {"users": {"email": {"indexes": {"ore": {}}}}}}
+            ],
+            "tables": {"users": {"email": {"indexes": {"unique": null}}}}
+        });
+        let config = EncryptConfig::parse(&config_json.to_string(), None).unwrap();
+        let parsed_config = config.into_config_map();
+
+        let (email, _) = get_column_config(&parsed_config, "users", "email");
+        assert_eq!(email.indexes.len(), 1);
+        assert_eq!(email.indexes[0].index_type, IndexType::Ore);
+        get_column_config(&parsed_config, "orders", "total");
+
+        for extends in [
+            json!([dir.join("cycle.json").display().to_string()]),
+            json!([dir.join("missing.json").display().to_string()]),
+            json!("base.json"),
+        ] {
+            let config_json = json!({"extends": extends, "v": 2, "tables": {}});
+            let result = EncryptConfig::parse(&config_json.to_string(), None);
+
+            assert!(
+                matches!(result, Err(crate::Error::InvalidExtends(_))),
+                "{extends}"
+            );
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_config_parsing_big_uint_allows_unique_index_only() {
+        let valid_json =
+            config_with_indexes("orders", "snowflake", "big_uint", json!({"unique": {}}));
+        assert!(EncryptConfig::from_str(&valid_json.to_string()).is_ok());
+
+        for indexes in [json!({"ore": {}}), json!({"match": {}})] {
+            let invalid_json = config_with_indexes("orders", "snowflake", "big_uint", indexes);
+            let result = EncryptConfig::from_str(&invalid_json.to_string());
+
+            assert!(matches!(result, Err(crate::Error::InvalidColumn(..))));
+        }
+    }
+
+    #[test]
+    fn test_config_digest() {
+        let config = |json: &str| EncryptConfig::from_str(json).unwrap().digest().unwrap();
+
+        let digest = config(
+            r#"{"v": 2, "tables": {"users": {"email": {"cast_as": "text"}, "name": {"cast_as": "text"}}}}"#,
+        );
+        assert_eq!(digest.len(), 64);
+        assert_eq!(
+            config(
+                r#"{"tables": {"users": {"name": {"cast_as": "text"}, "email": {"cast_as": "text"}}}, "v": 2}"#
+            ),
+            digest
+        );
+        assert_ne!(
+            config(r#"{"v": 2, "tables": {"users": {"email": {"cast_as": "text"}}}}"#),
+            digest
+        );
+    }
+
+    #[test]
+    fn test_config_parsing_context_presets() {
+        let config = EncryptConfig::from_str(
+            r#"{"v": 2, "tables": {}, "contexts": {"tenant_scoped": {"tag": ["tenant:{tenant}"]}}}"#,
+        )
+        .unwrap();
+        assert!(config.context_presets().is_ok());
+
+        assert!(matches!(
+            EncryptConfig::from_str(
+                r#"{"v": 2, "tables": {}, "contexts": {"tenant_scoped": {"tag": ["tenant:{tenant"]}}}"#,
+            ),
+            Err(crate::Error::InvalidContextPreset(..))
+        ));
+    }
+
+    #[test]
+    fn test_column_contexts() {
+        let config = EncryptConfig::from_str(
+            r#"{"v": 2, "tables": {"users": {"email": {"cast_as": "text", "context_from": {"value": [{"key": "table", "value": "$table"}]}}, "name": {"cast_as": "text"}}}}"#,
+        )
+        .unwrap();
+
+        let column_contexts = config.column_contexts();
+        assert_eq!(
+            column_contexts.get(&Identifier::new("users", "email")),
+            Some(&vec![ContextItem::Value(
+                "table".to_string(),
+                "users".to_string()
+            )])
+        );
+        assert!(!column_contexts.contains_key(&Identifier::new("users", "name")));
+
+        assert!(matches!(
+            EncryptConfig::from_str(
+                r#"{"v": 2, "tables": {"users": {"email": {"cast_as": "text", "context_from": {"tag": ["$schema"]}}}}}"#,
+            ),
+            Err(crate::Error::InvalidColumn(..))
+        ));
+    }
 }
@@ -0,0 +1,1753 @@
+//! Envelope versioning, inspection, and validation utilities.
+
+use crate::{
+    encrypt_config::{CastAs, Identifier},
+    expiry, index_encoding,
+    index_params::{IndexParams, CURRENT_TERM_VERSION},
+    signing::SIGNATURE_FIELD,
+    Encrypted, Error, SteVecEntry,
+};
+use cipherstash_client::{
+    schema::{column::IndexType, ColumnConfig},
+    zerokms::EncryptedRecord,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Envelope schema version emitted by encryption operations.
+pub const CURRENT_VERSION: u16 = 2;
+
+/// Supported envelope schema versions.
+pub const SUPPORTED_VERSIONS: &[u16] = &[1, 2, 3];
+
+/// Field names for each envelope schema version.
+struct Layout {
+    /// Whether the `dt` data type field is required.
+    data_type: bool,
+    /// Whether the optional `ks` keyset field is recognized.
+    keyset: bool,
+    /// HMAC index field name.
+    unique: &'static str,
+    /// Order-revealing encryption index field name.
+    ore: &'static str,
+    /// Bloom filter index field name.
+    r#match: &'static str,
+    /// Whether the optional `ae` array element index terms field is recognized.
+    array_elements: bool,
+    /// Whether the optional `exp` expiry field is recognized.
+    expiry: bool,
+    /// Whether the `ix` index term parameters field is required.
+    index_params: bool,
+}
+
+/// Field names used by version 1 envelopes.
+const V1_LAYOUT: Layout = Layout {
+    data_type: false,
+    keyset: false,
+    unique: "u",
+    ore: "o",
+    r#match: "m",
+    array_elements: false,
+    expiry: false,
+    index_params: false,
+};
+
+/// Field names used by version 2 envelopes.
+const V2_LAYOUT: Layout = Layout {
+    data_type: true,
+    keyset: true,
+    unique: "hm",
+    ore: "ob",
+    r#match: "bf",
+    array_elements: true,
+    expiry: true,
+    index_params: false,
+};
+
+/// Field names used by version 3 envelopes, which add the `ix` index term parameters to the
+/// version 2 fields.
+const V3_LAYOUT: Layout = Layout {
+    index_params: true,
+    ..V2_LAYOUT
+};
+
+/// Field names of the `unique`, `ore`, and `match` index terms for an envelope version.
+///
+/// Envelopes without a version use the current field names.
+pub fn index_field_names(version: Option<u64>) -> [&'static str; 3] {
+    let layout = match version {
+        Some(1) => &V1_LAYOUT,
+        _ => &V2_LAYOUT,
+    };
+
+    [layout.unique, layout.ore, layout.r#match]
+}
+
+/// Legacy version 1 envelope layout written by older CipherStash tooling.
+///
+/// Version 1 envelopes use different field names for index terms and do not record the
+/// data type, so converting to the current layout requires the column configuration.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "k")]
+pub enum EncryptedV1 {
+    /// Encrypted ciphertext with encryption indexes based on column configuration.
+    #[serde(rename = "ct")]
+    Ciphertext {
+        /// Base85-encoded ciphertext containing the encrypted data.
+        #[serde(rename = "c")]
+        ciphertext: String,
+        /// HMAC index for exact equality queries and uniqueness constraints.
+        #[serde(rename = "u")]
+        unique_index: Option<String>,
+        /// Order-revealing encryption index for range queries and sorting.
+        #[serde(rename = "o")]
+        ore_index: Option<Vec<String>>,
+        /// Bloom filter index for full-text search queries.
+        #[serde(rename = "m")]
+        match_index: Option<Vec<u16>>,
+        /// Table and column identifier for this encrypted value.
+        #[serde(rename = "i")]
+        identifier: Identifier,
+        /// Schema version for backward compatibility.
+        #[serde(rename = "v")]
+        version: u16,
+    },
+    /// Encrypted ciphertext with structured text encryption vector for JSONB containment queries.
+    #[serde(rename = "sv")]
+    SteVec {
+        /// Base85-encoded ciphertext containing the encrypted data.
+        #[serde(rename = "c")]
+        ciphertext: String,
+        /// Structured text encryption vector for JSONB containment queries.
+        #[serde(rename = "sv")]
+        ste_vec_index: Option<Vec<SteVecEntry>>,
+        /// Table and column identifier for this encrypted value.
+        #[serde(rename = "i")]
+        identifier: Identifier,
+        /// Schema version for backward compatibility.
+        #[serde(rename = "v")]
+        version: u16,
+    },
+}
+
+/// Version 3 envelope layout, which records the parameters of each index term.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EncryptedV3 {
+    /// The envelope's fields in the version 2 layout, with a `v` of 3.
+    #[serde(flatten)]
+    pub encrypted: Encrypted,
+    /// Version and parameters of the scheme of each index term.
+    #[serde(rename = "ix")]
+    pub index_params: IndexParams,
+}
+
+/// An encrypted envelope in any supported schema version.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum VersionedEnvelope {
+    /// Legacy version 1 envelope.
+    V1(EncryptedV1),
+    /// Current version 2 envelope.
+    V2(Encrypted),
+    /// Version 3 envelope with per-term index parameters.
+    V3(EncryptedV3),
+}
+
+impl VersionedEnvelope {
+    /// Parse an envelope JSON string, selecting the layout from its `v` field.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Parse`] if the envelope is malformed, or
+    /// [`Error::UnsupportedEnvelopeVersion`] if the version is not supported.
+    pub fn parse(envelope_json: &str) -> Result<Self, Error> {
+        let value: Value = serde_json::from_str(envelope_json)?;
+
+        match value.get("v").and_then(Value::as_u64) {
+            Some(1) => Ok(Self::V1(serde_json::from_value(value)?)),
+            Some(2) | None => Ok(Self::V2(serde_json::from_value(value)?)),
+            Some(3) => Ok(Self::V3(serde_json::from_value(value)?)),
+            Some(version) => Err(Error::UnsupportedEnvelopeVersion(version)),
+        }
+    }
+
+    /// Wrap an encrypted value in the layout for the requested version.
+    ///
+    /// Version 3 envelopes record the current parameters of the column's indexes, which must be
+    /// those the value's index terms were created with.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnsupportedEnvelopeVersion`] if the version is not supported, or
+    /// [`Error::InvalidEnvelope`] if the value expires and the version cannot record its expiry.
+    pub fn emit(
+        mut encrypted: Encrypted,
+        version: u16,
+        index_params: &IndexParams,
+    ) -> Result<Self, Error> {
+        if encrypted.expires_at().is_some() {
+            expiry::check_version(version)?;
+        }
+
+        match version {
+            1 => Ok(Self::V1(downgrade(encrypted))),
+            2 => {
+                set_version(&mut encrypted, 2);
+                Ok(Self::V2(encrypted))
+            }
+            3 => {
+                set_version(&mut encrypted, 3);
+                Ok(Self::V3(EncryptedV3 {
+                    encrypted,
+                    index_params: index_params.clone(),
+                }))
+            }
+            version => Err(Error::UnsupportedEnvelopeVersion(version.into())),
+        }
+    }
+
+    /// The envelope's fields in the version 2 layout, with the parameters of its index terms
+    /// for version 3 envelopes.
+    ///
+    /// Version 1 envelopes are upgraded with the column's data type.
+    pub fn into_encrypted(self, cast_as: &CastAs) -> (Encrypted, Option<IndexParams>) {
+        match self {
+            Self::V1(legacy) => (upgrade(legacy, cast_as), None),
+            Self::V2(encrypted) => (encrypted, None),
+            Self::V3(EncryptedV3 {
+                encrypted,
+                index_params,
+            }) => (encrypted, Some(index_params)),
+        }
+    }
+
+    /// The table and column identifier recorded in the envelope.
+    pub fn identifier(&self) -> &Identifier {
+        match self {
+            Self::V1(EncryptedV1::Ciphertext { identifier, .. })
+            | Self::V1(EncryptedV1::SteVec { identifier, .. })
+            | Self::V2(Encrypted::Ciphertext { identifier, .. })
+            | Self::V2(Encrypted::SteVec { identifier, .. })
+            | Self::V3(EncryptedV3 {
+                encrypted: Encrypted::Ciphertext { identifier, .. },
+                ..
+            })
+            | Self::V3(EncryptedV3 {
+                encrypted: Encrypted::SteVec { identifier, .. },
+                ..
+            }) => identifier,
+        }
+    }
+
+    /// The name of the keyset recorded in the envelope, if any.
+    pub fn keyset(&self) -> Option<&str> {
+        match self {
+            Self::V1(_) => None,
+            Self::V2(encrypted) | Self::V3(EncryptedV3 { encrypted, .. }) => match encrypted {
+                Encrypted::Ciphertext { keyset, .. } | Encrypted::SteVec { keyset, .. } => {
+                    keyset.as_deref()
+                }
+            },
+        }
+    }
+
+    /// The expiry recorded in the envelope, absent for values that do not expire.
+    pub fn expires_at(&self) -> Option<u64> {
+        match self {
+            Self::V1(_) => None,
+            Self::V2(encrypted) | Self::V3(EncryptedV3 { encrypted, .. }) => encrypted.expires_at(),
+        }
+    }
+
+    /// The data type recorded in the envelope, absent for version 1 envelopes.
+    pub fn data_type(&self) -> Option<&str> {
+        match self {
+            Self::V1(_) => None,
+            Self::V2(encrypted) | Self::V3(EncryptedV3 { encrypted, .. }) => match encrypted {
+                Encrypted::Ciphertext { data_type, .. } | Encrypted::SteVec { data_type, .. } => {
+                    Some(data_type)
+                }
+            },
+        }
+    }
+
+    /// Consume the envelope and return its base85-encoded ciphertext.
+    pub fn into_ciphertext(self) -> String {
+        match self {
+            Self::V1(EncryptedV1::Ciphertext { ciphertext, .. })
+            | Self::V1(EncryptedV1::SteVec { ciphertext, .. })
+            | Self::V2(Encrypted::Ciphertext { ciphertext, .. })
+            | Self::V2(Encrypted::SteVec { ciphertext, .. })
+            | Self::V3(EncryptedV3 {
+                encrypted: Encrypted::Ciphertext { ciphertext, .. },
+                ..
+            })
+            | Self::V3(EncryptedV3 {
+                encrypted: Encrypted::SteVec { ciphertext, .. },
+                ..
+            }) => ciphertext,
+        }
+    }
+}
+
+/// Set the schema version recorded in an envelope.
+fn set_version(encrypted: &mut Encrypted, value: u16) {
+    match encrypted {
+        Encrypted::Ciphertext { version, .. } | Encrypted::SteVec { version, .. } => {
+            *version = value
+        }
+    }
+}
+
+/// Convert a current envelope into the version 1 layout.
+pub fn downgrade(encrypted: Encrypted) -> EncryptedV1 {
+    match encrypted {
+        Encrypted::Ciphertext {
+            ciphertext,
+            unique_index,
+            ore_index,
+            match_index,
+            identifier,
+            ..
+        } => EncryptedV1::Ciphertext {
+            ciphertext,
+            unique_index,
+            ore_index,
+            match_index,
+            identifier,
+            version: 1,
+        },
+        Encrypted::SteVec {
+            ciphertext,
+            ste_vec_index,
+            identifier,
+            ..
+        } => EncryptedV1::SteVec {
+            ciphertext,
+            ste_vec_index,
+            identifier,
+            version: 1,
+        },
+    }
+}
+
+/// Convert a version 1 envelope into the current layout using the column's data type.
+pub fn upgrade(legacy: EncryptedV1, cast_as: &CastAs) -> Encrypted {
+    match legacy {
+        EncryptedV1::Ciphertext {
+            ciphertext,
+            unique_index,
+            ore_index,
+            match_index,
+            identifier,
+            ..
+        } => Encrypted::Ciphertext {
+            ciphertext,
+            data_type: cast_as.to_string(),
+            unique_index,
+            ore_index,
+            match_index,
+            elements: None,
+            identifier,
+            keyset: None,
+            expires_at: None,
+            version: CURRENT_VERSION,
+        },
+        EncryptedV1::SteVec {
+            ciphertext,
+            ste_vec_index,
+            identifier,
+            ..
+        } => Encrypted::SteVec {
+            ciphertext,
+            data_type: cast_as.to_string(),
+            ste_vec_index,
+            identifier,
+            keyset: None,
+            expires_at: None,
+            version: CURRENT_VERSION,
+        },
+    }
+}
+
+/// Outcome of upgrading an envelope to the current schema version.
+#[derive(Debug, Serialize)]
+pub struct UpgradeOutcome {
+    /// The upgraded envelope, absent when the record requires re-encryption. Version 3
+    /// envelopes keep their version.
+    pub envelope: Option<VersionedEnvelope>,
+    /// Whether the envelope was rewritten.
+    pub upgraded: bool,
+    /// Whether the record must be decrypted and re-encrypted to reach the current schema.
+    pub requires_reencryption: bool,
+    /// Reasons the record requires re-encryption.
+    pub reasons: Vec<String>,
+}
+
+/// Upgrade an envelope to the current schema version without decrypting it.
+///
+/// Version 1 field names are rewritten and legacy CBOR hex-encoded ciphertexts are re-encoded
+/// as base85 MessagePack, leaving the encrypted bytes and keys untouched. Records whose
+/// ciphertext cannot be decoded, or that are missing index terms the column is configured to
+/// produce, are flagged as requiring re-encryption instead. So are version 3 envelopes with a
+/// term whose recorded parameters differ from the column's current `index_params`, checked term
+/// by term.
+pub fn upgrade_to_current(
+    envelope: VersionedEnvelope,
+    column_config: &ColumnConfig,
+    cast_as: &CastAs,
+    index_params: &IndexParams,
+) -> UpgradeOutcome {
+    let mut upgraded = matches!(envelope, VersionedEnvelope::V1(_));
+    let (mut encrypted, recorded_params) = envelope.into_encrypted(cast_as);
+
+    let mut reasons = recorded_params
+        .as_ref()
+        .map(|recorded_params| recorded_params.incompatibilities(index_params))
+        .unwrap_or_default();
+    let has_index = |predicate: fn(&IndexType) -> bool| {
+        column_config
+            .indexes
+            .iter()
+            .any(|index| predicate(&index.index_type))
+    };
+
+    match &mut encrypted {
+        Encrypted::Ciphertext {
+            ciphertext,
+            unique_index,
+            ore_index,
+            match_index,
+            elements,
+            ..
+        } => {
+            upgraded |= reencode_ciphertext(ciphertext, "c", &mut reasons);
+
+            // Array values carry their index terms per element rather than on the envelope.
+            let required = [
+                (
+                    has_index(|index| matches!(index, IndexType::Unique { .. })),
+                    unique_index.is_none(),
+                    "hm",
+                ),
+                (
+                    has_index(|index| matches!(index, IndexType::Ore)),
+                    ore_index.is_none(),
+                    "ob",
+                ),
+                (
+                    has_index(|index| matches!(index, IndexType::Match { .. })),
+                    match_index.is_none(),
+                    "bf",
+                ),
+            ];
+
+            for (configured, missing, field) in required {
+                if configured && missing && elements.is_none() {
+                    reasons.push(format!("missing `{field}` term for a configured index"));
+                }
+            }
+        }
+        Encrypted::SteVec {
+            ciphertext,
+            ste_vec_index,
+            ..
+        } => {
+            upgraded |= reencode_ciphertext(ciphertext, "c", &mut reasons);
+
+            match ste_vec_index {
+                Some(entries) => {
+                    for (index, entry) in entries.iter_mut().enumerate() {
+                        let field = format!("sv[{index}].r");
+                        upgraded |= reencode_ciphertext(&mut entry.record, &field, &mut reasons);
+                    }
+                }
+                None if has_index(|index| matches!(index, IndexType::SteVec { .. })) => {
+                    reasons.push("missing `sv` entries for a configured index".to_string());
+                }
+                None => {}
+            }
+        }
+    }
+
+    let requires_reencryption = !reasons.is_empty();
+    let envelope = match recorded_params {
+        Some(index_params) => VersionedEnvelope::V3(EncryptedV3 {
+            encrypted,
+            index_params,
+        }),
+        None => VersionedEnvelope::V2(encrypted),
+    };
+
+    UpgradeOutcome {
+        envelope: (!requires_reencryption).then_some(envelope),
+        upgraded: upgraded && !requires_reencryption,
+        requires_reencryption,
+        reasons,
+    }
+}
+
+/// Re-encode a legacy ciphertext as base85 MessagePack in place.
+///
+/// Returns whether the ciphertext was rewritten. Ciphertexts that cannot be decoded in any
+/// known encoding are recorded in `reasons`.
+fn reencode_ciphertext(ciphertext: &mut String, field: &str, reasons: &mut Vec<String>) -> bool {
+    if EncryptedRecord::from_mp_base85(ciphertext).is_ok() {
+        return false;
+    }
+
+    match EncryptedRecord::from_cbor_hex(ciphertext).map(|record| record.to_mp_base85()) {
+        Ok(Ok(reencoded)) => {
+            *ciphertext = reencoded;
+            true
+        }
+        _ => {
+            reasons.push(format!("`{field}` is not a decodable ciphertext"));
+            false
+        }
+    }
+}
+
+/// Extract the ciphertext and keyset reference from decryption input that may be a raw
+/// ciphertext or an envelope.
+///
+/// Base85-encoded MessagePack records never begin with `{`, so input starting with `{` is
+/// parsed as an envelope of any supported version.
+///
+/// # Errors
+///
+/// Returns an error if the input looks like an envelope but cannot be parsed.
+pub fn extract_ciphertext(input: String) -> Result<ExtractedCiphertext, Error> {
+    if input.trim_start().starts_with('{') {
+        let envelope = VersionedEnvelope::parse(&input)?;
+        let keyset = envelope.keyset().map(str::to_string);
+        let expires_at = envelope.expires_at();
+        let identifier = Some(envelope.identifier().clone());
+        let data_type = envelope
+            .data_type()
+            .map(|data_type| (envelope.identifier().clone(), data_type.to_string()));
+
+        Ok(ExtractedCiphertext {
+            ciphertext: envelope.into_ciphertext(),
+            keyset,
+            expires_at,
+            identifier,
+            data_type,
+        })
+    } else {
+        Ok(ExtractedCiphertext {
+            ciphertext: input,
+            keyset: None,
+            expires_at: None,
+            identifier: None,
+            data_type: None,
+        })
+    }
+}
+
+/// Check that input is an `sv` envelope, whose root ciphertext holds the full JSON document,
+/// and return the envelope JSON.
+///
+/// Some database drivers return `jsonb` values as a JSON string holding the envelope, so a
+/// string is decoded once before the envelope kind is checked.
+///
+/// # Errors
+///
+/// Returns [`Error::Parse`] if the input is not JSON, or [`Error::InvalidEnvelope`] if it is not
+/// an `sv` envelope.
+pub fn ste_vec_root(input: &str) -> Result<String, Error> {
+    let (envelope_json, envelope): (String, Value) = match serde_json::from_str(input)? {
+        Value::String(inner) => {
+            let envelope = serde_json::from_str(&inner)?;
+            (inner, envelope)
+        }
+        envelope => (input.to_string(), envelope),
+    };
+
+    match envelope.get("k") {
+        Some(Value::String(kind)) if kind == "sv" => Ok(envelope_json),
+        Some(Value::String(kind)) => Err(Error::InvalidEnvelope(format!(
+            "expected an `sv` envelope, found a `{kind}` envelope"
+        ))),
+        _ => Err(Error::InvalidEnvelope(
+            "expected an `sv` envelope, found no `k` field".to_string(),
+        )),
+    }
+}
+
+/// Ciphertext extracted from decryption input, with the envelope metadata it was stored with.
+#[derive(Debug, PartialEq)]
+pub struct ExtractedCiphertext {
+    /// The base85-encoded ciphertext.
+    pub ciphertext: String,
+    /// The name of the keyset recorded in the envelope, if any.
+    pub keyset: Option<String>,
+    /// The expiry recorded in the envelope, absent for values that do not expire.
+    pub expires_at: Option<u64>,
+    /// The envelope identifier, absent for raw ciphertexts.
+    pub identifier: Option<Identifier>,
+    /// The envelope identifier and the data type the value was encrypted as, absent for raw
+    /// ciphertexts and version 1 envelopes.
+    pub data_type: Option<(Identifier, String)>,
+}
+
+/// Rendering options for [`format`].
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct FormatOptions {
+    /// Whether to render multi-line indented output.
+    pretty: bool,
+    /// Whether to render object keys in lexicographic order.
+    sort_keys: bool,
+    /// Whether to truncate ciphertexts and index terms.
+    redact: bool,
+    /// Number of leading characters kept when a value is truncated.
+    max_length: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            pretty: true,
+            sort_keys: true,
+            redact: true,
+            max_length: 16,
+        }
+    }
+}
+
+/// Human-readable view of an [`Encrypted`] envelope.
+#[derive(Serialize)]
+struct EnvelopeView {
+    /// Envelope kind (`ciphertext` or `ste_vec`).
+    kind: &'static str,
+    /// Decoded `table.column` identifier.
+    identifier: String,
+    /// Name of the keyset the value was encrypted under, absent for the default keyset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keyset: Option<String>,
+    /// Data type for casting, absent for version 1 envelopes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data_type: Option<String>,
+    /// When the value expires, in seconds since the Unix epoch, absent for values that do not
+    /// expire.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<u64>,
+    /// Schema version.
+    version: u16,
+    /// Possibly truncated ciphertext.
+    ciphertext: String,
+    /// Summary of the encryption indexes present in the envelope.
+    indexes: IndexSummary,
+    /// Version and parameters of each index term, recorded by version 3 envelopes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    index_params: Option<IndexParams>,
+}
+
+/// Summary of the encryption indexes present in an envelope.
+#[derive(Default, Serialize)]
+struct IndexSummary {
+    /// Possibly truncated HMAC index term.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unique: Option<String>,
+    /// Possibly truncated order-revealing encryption index terms.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ore: Option<Vec<String>>,
+    /// Number of bloom filter positions in the match index.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    match_bits: Option<usize>,
+    /// Number of array elements with their own index terms.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    array_elements: Option<usize>,
+    /// Number of structured text encryption vector entries.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ste_vec_entries: Option<usize>,
+}
+
+/// Render an envelope JSON string as a human-readable summary.
+///
+/// # Errors
+///
+/// Returns [`Error::Parse`] if the envelope is not a valid payload for its version.
+pub fn format(envelope_json: &str, options: &FormatOptions) -> Result<String, Error> {
+    let view = match VersionedEnvelope::parse(envelope_json)? {
+        VersionedEnvelope::V2(encrypted) => EnvelopeView::new(encrypted, options),
+        VersionedEnvelope::V3(EncryptedV3 {
+            encrypted,
+            index_params,
+        }) => EnvelopeView {
+            index_params: Some(index_params),
+            ..EnvelopeView::new(encrypted, options)
+        },
+        VersionedEnvelope::V1(legacy) => EnvelopeView {
+            data_type: None,
+            version: 1,
+            ..EnvelopeView::new(upgrade(legacy, &CastAs::default()), options)
+        },
+    };
+
+    let rendered = if options.sort_keys {
+        // `serde_json::Map` keeps keys ordered, so round-tripping through `Value` sorts them.
+        let value = serde_json::to_value(&view)?;
+        render(&value, options.pretty)?
+    } else {
+        render(&view, options.pretty)?
+    };
+
+    Ok(rendered)
+}
+
+impl EnvelopeView {
+    fn new(encrypted: Encrypted, options: &FormatOptions) -> Self {
+        match encrypted {
+            Encrypted::Ciphertext {
+                ciphertext,
+                data_type,
+                unique_index,
+                ore_index,
+                match_index,
+                elements,
+                identifier,
+                keyset,
+                expires_at,
+                version,
+            } => Self {
+                kind: "ciphertext",
+                identifier: format!("{}.{}", identifier.table, identifier.column),
+                keyset,
+                data_type: Some(data_type),
+                expires_at,
+                version,
+                ciphertext: truncate(&ciphertext, options),
+                indexes: IndexSummary {
+                    unique: unique_index.map(|term| truncate(&term, options)),
+                    ore: ore_index
+                        .map(|terms| terms.iter().map(|term| truncate(term, options)).collect()),
+                    match_bits: match_index.map(|bits| bits.len()),
+                    array_elements: elements.map(|elements| elements.len()),
+                    ste_vec_entries: None,
+                },
+                index_params: None,
+            },
+            Encrypted::SteVec {
+                ciphertext,
+                data_type,
+                ste_vec_index,
+                identifier,
+                keyset,
+                expires_at,
+                version,
+            } => Self {
+                kind: "ste_vec",
+                identifier: format!("{}.{}", identifier.table, identifier.column),
+                keyset,
+                data_type: Some(data_type),
+                expires_at,
+                version,
+                ciphertext: truncate(&ciphertext, options),
+                indexes: IndexSummary {
+                    ste_vec_entries: ste_vec_index.map(|entries| entries.len()),
+                    ..Default::default()
+                },
+                index_params: None,
+            },
+        }
+    }
+}
+
+/// Truncate a value to the configured length when redaction is enabled.
+fn truncate(value: &str, options: &FormatOptions) -> String {
+    let length = value.chars().count();
+
+    if !options.redact || length <= options.max_length {
+        return value.to_string();
+    }
+
+    let prefix: String = value.chars().take(options.max_length).collect();
+    format!("{prefix}...({length} chars)")
+}
+
+fn render<T: Serialize>(value: &T, pretty: bool) -> Result<String, Error> {
+    if pretty {
+        serde_json::to_string_pretty(value).map_err(Error::from)
+    } else {
+        serde_json::to_string(value).map_err(Error::from)
+    }
+}
+
+/// Result of validating an envelope against the expected schema.
+#[derive(Debug, Serialize)]
+pub struct ValidationReport {
+    /// Whether the envelope matches the schema.
+    pub valid: bool,
+    /// The envelope schema version, if it could be determined.
+    pub version: Option<u64>,
+    /// Field-level problems found in the envelope.
+    pub problems: Vec<Problem>,
+}
+
+/// A field-level schema problem.
+#[derive(Debug, Serialize)]
+pub struct Problem {
+    /// JSON path to the offending field.
+    pub path: String,
+    /// Description of the problem.
+    pub message: String,
+}
+
+/// Validate an envelope JSON string against the schema for its version.
+///
+/// Schema problems are collected into the returned [`ValidationReport`] rather than
+/// failing on the first mismatch.
+///
+/// # Errors
+///
+/// Returns [`Error::Parse`] if the input is not valid JSON.
+pub fn validate(envelope_json: &str) -> Result<ValidationReport, Error> {
+    let value: Value = serde_json::from_str(envelope_json)?;
+    let mut validator = Validator::default();
+
+    let version = match value.as_object() {
+        Some(envelope) => validator.envelope(envelope),
+        None => {
+            validator.problem("$", "expected an object");
+            None
+        }
+    };
+
+    Ok(ValidationReport {
+        valid: validator.problems.is_empty(),
+        version,
+        problems: validator.problems,
+    })
+}
+
+/// Collects schema problems while walking an envelope.
+#[derive(Default)]
+struct Validator {
+    problems: Vec<Problem>,
+}
+
+impl Validator {
+    fn problem(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.problems.push(Problem {
+            path: path.into(),
+            message: message.into(),
+        });
+    }
+
+    fn envelope(&mut self, envelope: &Map<String, Value>) -> Option<u64> {
+        let version = match envelope.get("v") {
+            Some(Value::Number(number)) if number.as_u64().is_some() => {
+                let version = number.as_u64();
+                let supported = SUPPORTED_VERSIONS
+                    .iter()
+                    .any(|supported| version == Some(u64::from(*supported)));
+                if !supported {
+                    self.problem(
+                        "$.v",
+                        format!("unsupported version, expected one of {SUPPORTED_VERSIONS:?}"),
+                    );
+                }
+                version
+            }
+            Some(_) => {
+                self.problem("$.v", "expected an unsigned integer");
+                None
+            }
+            None => {
+                self.problem("$.v", "missing required field");
+                None
+            }
+        };
+
+        let layout = match version {
+            Some(1) => &V1_LAYOUT,
+            Some(3) => &V3_LAYOUT,
+            _ => &V2_LAYOUT,
+        };
+
+        let kind_fields = match envelope.get("k").and_then(Value::as_str) {
+            Some("ct") => {
+                self.ciphertext_indexes("$", envelope, layout);
+                if layout.array_elements {
+                    self.array_elements(envelope, layout);
+                    vec![layout.unique, layout.ore, layout.r#match, "ae"]
+                } else {
+                    vec![layout.unique, layout.ore, layout.r#match]
+                }
+            }
+            Some("sv") => {
+                self.ste_vec_index(envelope);
+                vec!["sv"]
+            }
+            Some(other) => {
+                self.problem(
+                    "$.k",
+                    format!("unknown envelope kind `{other}`, expected `ct` or `sv`"),
+                );
+                Vec::new()
+            }
+            None if envelope.contains_key("k") => {
+                self.problem("$.k", "expected a string");
+                Vec::new()
+            }
+            None => {
+                self.problem("$.k", "missing required field");
+                Vec::new()
+            }
+        };
+
+        match envelope.get("c") {
+            Some(Value::String(ciphertext)) if ciphertext.is_empty() => {
+                self.problem("$.c", "ciphertext must not be empty")
+            }
+            Some(Value::String(_)) => {}
+            Some(_) => self.problem("$.c", "expected a string"),
+            None => self.problem("$.c", "missing required field"),
+        }
+
+        match envelope.get("dt") {
+            Some(data_type @ Value::String(name)) if layout.data_type => {
+                if serde_json::from_value::<CastAs>(data_type.clone()).is_err() {
+                    self.problem("$.dt", format!("unknown data type `{name}`"));
+                }
+            }
+            Some(_) if layout.data_type => self.problem("$.dt", "expected a string"),
+            None if layout.data_type => self.problem("$.dt", "missing required field"),
+            _ => {}
+        }
+
+        match envelope.get("i") {
+            Some(Value::Object(identifier)) => {
+                for (field, name) in [("t", "table"), ("c", "column")] {
+                    match identifier.get(field) {
+                        Some(Value::String(_)) => {}
+                        Some(_) => self.problem(
+                            format!("$.i.{field}"),
+                            format!("expected {name} name string"),
+                        ),
+                        None => self.problem(format!("$.i.{field}"), "missing required field"),
+                    }
+                }
+            }
+            Some(_) => self.problem("$.i", "expected an object"),
+            None => self.problem("$.i", "missing required field"),
+        }
+
+        match envelope.get("ks") {
+            Some(Value::String(keyset)) if layout.keyset && keyset.is_empty() => {
+                self.problem("$.ks", "keyset name must not be empty")
+            }
+            Some(Value::String(_)) if layout.keyset => {}
+            Some(_) if layout.keyset => self.problem("$.ks", "expected a string"),
+            _ => {}
+        }
+
+        match envelope.get("exp") {
+            Some(Value::Number(number)) if layout.expiry && number.as_u64().is_some() => {}
+            Some(_) if layout.expiry => self.problem("$.exp", "expected an unsigned integer"),
+            _ => {}
+        }
+
+        match envelope.get("ix") {
+            Some(Value::Object(index_params)) if layout.index_params => {
+                self.index_params(index_params)
+            }
+            Some(_) if layout.index_params => self.problem("$.ix", "expected an object"),
+            None if layout.index_params => self.problem("$.ix", "missing required field"),
+            _ => {}
+        }
+
+        match envelope.get(SIGNATURE_FIELD) {
+            None => {}
+            Some(Value::String(signature)) => self.hex(&format!("$.{SIGNATURE_FIELD}"), signature),
+            Some(_) => self.problem(format!("$.{SIGNATURE_FIELD}"), "expected a hex string"),
+        }
+
+        if !kind_fields.is_empty() {
+            for key in envelope.keys().map(String::as_str) {
+                let common = matches!(key, "k" | "c" | "i" | "v")
+                    || key == SIGNATURE_FIELD
+                    || (layout.data_type && key == "dt")
+                    || (layout.keyset && key == "ks")
+                    || (layout.expiry && key == "exp")
+                    || (layout.index_params && key == "ix");
+                if !common && !kind_fields.contains(&key) {
+                    self.problem(format!("$.{key}"), "unexpected field");
+                }
+            }
+        }
+
+        version
+    }
+
+    fn ciphertext_indexes(&mut self, path: &str, fields: &Map<String, Value>, layout: &Layout) {
+        match fields.get(layout.unique) {
+            None | Some(Value::Null) => {}
+            Some(Value::String(term)) => {
+                self.index_term(&format!("{path}.{}", layout.unique), term)
+            }
+            Some(_) => self.problem(
+                format!("{path}.{}", layout.unique),
+                "expected a hex string or null",
+            ),
+        }
+
+        match fields.get(layout.ore) {
+            None | Some(Value::Null) => {}
+            Some(Value::Array(terms)) => {
+                for (index, term) in terms.iter().enumerate() {
+                    let path = format!("{path}.{}[{index}]", layout.ore);
+                    match term {
+                        Value::String(term) => self.index_term(&path, term),
+                        _ => self.problem(path, "expected a hex string"),
+                    }
+                }
+            }
+            Some(_) => self.problem(
+                format!("{path}.{}", layout.ore),
+                "expected an array or null",
+            ),
+        }
+
+        match fields.get(layout.r#match) {
+            None | Some(Value::Null) => {}
+            Some(Value::Array(bits)) => {
+                for (index, bit) in bits.iter().enumerate() {
+                    if bit
+                        .as_u64()
+                        .and_then(|bit| u16::try_from(bit).ok())
+                        .is_none()
+                    {
+                        self.problem(
+                            format!("{path}.{}[{index}]", layout.r#match),
+                            "expected an integer between 0 and 65535",
+                        );
+                    }
+                }
+            }
+            Some(_) => self.problem(
+                format!("{path}.{}", layout.r#match),
+                "expected an array or null",
+            ),
+        }
+    }
+
+    fn array_elements(&mut self, envelope: &Map<String, Value>, layout: &Layout) {
+        let elements = match envelope.get("ae") {
+            None => return,
+            Some(Value::Array(elements)) => elements,
+            Some(_) => return self.problem("$.ae", "expected an array"),
+        };
+
+        for (index, element) in elements.iter().enumerate() {
+            let path = format!("$.ae[{index}]");
+            let Some(element) = element.as_object() else {
+                self.problem(path, "expected an object");
+                continue;
+            };
+
+            self.ciphertext_indexes(&path, element, layout);
+
+            for key in element.keys().map(String::as_str) {
+                if ![layout.unique, layout.ore, layout.r#match].contains(&key) {
+                    self.problem(format!("{path}.{key}"), "unexpected field");
+                }
+            }
+        }
+    }
+
+    fn ste_vec_index(&mut self, envelope: &Map<String, Value>) {
+        let entries = match envelope.get("sv") {
+            None | Some(Value::Null) => return,
+            Some(Value::Array(entries)) => entries,
+            Some(_) => return self.problem("$.sv", "expected an array or null"),
+        };
+
+        for (index, entry) in entries.iter().enumerate() {
+            let path = format!("$.sv[{index}]");
+            let Some(entry) = entry.as_object() else {
+                self.problem(path, "expected an object");
+                continue;
+            };
+
+            for field in ["s", "t"] {
+                match entry.get(field) {
+                    Some(Value::String(term)) => self.index_term(&format!("{path}.{field}"), term),
+                    Some(_) => self.problem(format!("{path}.{field}"), "expected a hex string"),
+                    None => self.problem(format!("{path}.{field}"), "missing required field"),
+                }
+            }
+
+            match entry.get("r") {
+                Some(Value::String(_)) => {}
+                Some(_) => self.problem(format!("{path}.r"), "expected a string"),
+                None => self.problem(format!("{path}.r"), "missing required field"),
+            }
+
+            match entry.get("pa") {
+                Some(Value::Bool(_)) => {}
+                Some(_) => self.problem(format!("{path}.pa"), "expected a boolean"),
+                None => self.problem(format!("{path}.pa"), "missing required field"),
+            }
+        }
+    }
+
+    fn index_params(&mut self, index_params: &Map<String, Value>) {
+        for (field, params) in index_params {
+            let path = format!("$.ix.{field}");
+            if !matches!(field.as_str(), "hm" | "ob" | "bf" | "sv") {
+                self.problem(path, "unexpected field");
+                continue;
+            }
+            let Some(params) = params.as_object() else {
+                self.problem(path, "expected an object");
+                continue;
+            };
+
+            match params.get("v").and_then(Value::as_u64) {
+                Some(version) if version > u64::from(CURRENT_TERM_VERSION) => self.problem(
+                    format!("{path}.v"),
+                    format!("unsupported index version, expected at most {CURRENT_TERM_VERSION}"),
+                ),
+                Some(_) => {}
+                None if params.contains_key("v") => {
+                    self.problem(format!("{path}.v"), "expected an unsigned integer")
+                }
+                None => self.problem(format!("{path}.v"), "missing required field"),
+            }
+
+            for (key, value) in params {
+                let valid = match key.as_str() {
+                    "v" => true,
+                    "k" | "m" => value.as_u64().is_some(),
+                    "t" | "s" => value.is_string(),
+                    _ => {
+                        self.problem(format!("{path}.{key}"), "unexpected field");
+                        continue;
+                    }
+                };
+                if !valid {
+                    let expected = if key == "t" || key == "s" {
+                        "expected a string"
+                    } else {
+                        "expected an unsigned integer"
+                    };
+                    self.problem(format!("{path}.{key}"), expected);
+                }
+            }
+        }
+    }
+
+    fn hex(&mut self, path: &str, term: &str) {
+        if hex::decode(term).is_err() {
+            self.problem(path, "expected a hex string");
+        }
+    }
+
+    fn index_term(&mut self, path: &str, term: &str) {
+        if index_encoding::decode(term).is_none() {
+            self.problem(path, "expected a hex or prefixed base64 string");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalization::IndexNormalization;
+    use serde_json::json;
+
+    const TEST_CIPHERTEXT: &str = "mBbKlk}G7QdaGiNj$dL7#+AOrA^}*VJx";
+    const TEST_UNIQUE_INDEX: &str = "f3ca71fd39ae9d3d1d1fc25141bcb6da";
+
+    fn ciphertext_envelope() -> String {
+        json!({
+            "k": "ct",
+            "c": TEST_CIPHERTEXT,
+            "dt": "text",
+            "hm": TEST_UNIQUE_INDEX,
+            "ob": null,
+            "bf": [1124, 2134, 987],
+            "i": {"t": "users", "c": "email"},
+            "v": 2
+        })
+        .to_string()
+    }
+
+    fn compact_options() -> FormatOptions {
+        FormatOptions {
+            pretty: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_format_redacts_ciphertext_and_terms() {
+        let rendered = format(&ciphertext_envelope(), &compact_options()).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+
+        assert_eq!(parsed["kind"], "ciphertext");
+        assert_eq!(parsed["identifier"], "users.email");
+        assert_eq!(parsed["ciphertext"], "mBbKlk}G7QdaGiNj...(32 chars)");
+        assert_eq!(parsed["indexes"]["unique"], "f3ca71fd39ae9d3d...(32 chars)");
+        assert_eq!(parsed["indexes"]["match_bits"], 3);
+        assert!(parsed["indexes"].get("ore").is_none());
+    }
+
+    #[test]
+    fn test_format_without_redaction_keeps_full_values() {
+        let options = FormatOptions {
+            redact: false,
+            ..compact_options()
+        };
+
+        let rendered = format(&ciphertext_envelope(), &options).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+
+        assert_eq!(parsed["ciphertext"], TEST_CIPHERTEXT);
+        assert_eq!(parsed["indexes"]["unique"], TEST_UNIQUE_INDEX);
+    }
+
+    #[test]
+    fn test_format_sorted_keys() {
+        let rendered = format(&ciphertext_envelope(), &compact_options()).unwrap();
+
+        assert!(rendered.starts_with(r#"{"ciphertext":"#));
+    }
+
+    #[test]
+    fn test_format_unsorted_keys_keeps_view_order() {
+        let options = FormatOptions {
+            sort_keys: false,
+            ..compact_options()
+        };
+
+        let rendered = format(&ciphertext_envelope(), &options).unwrap();
+
+        assert!(rendered.starts_with(r#"{"kind":"ciphertext","#));
+    }
+
+    #[test]
+    fn test_format_pretty_output_is_multi_line() {
+        let rendered = format(&ciphertext_envelope(), &FormatOptions::default()).unwrap();
+
+        assert!(rendered.lines().count() > 1);
+    }
+
+    #[test]
+    fn test_format_ste_vec_envelope() {
+        let envelope = json!({
+            "k": "sv",
+            "c": TEST_CIPHERTEXT,
+            "dt": "jsonb",
+            "sv": [{"s": "dd46", "t": "2230", "r": TEST_CIPHERTEXT, "pa": false}],
+            "i": {"t": "users", "c": "metadata"},
+            "v": 2
+        })
+        .to_string();
+
+        let rendered = format(&envelope, &compact_options()).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+
+        assert_eq!(parsed["kind"], "ste_vec");
+        assert_eq!(parsed["indexes"]["ste_vec_entries"], 1);
+    }
+
+    #[test]
+    fn test_validate_valid_ciphertext_envelope() {
+        let report = validate(&ciphertext_envelope()).unwrap();
+
+        assert!(report.valid);
+        assert_eq!(report.version, Some(2));
+        assert!(report.problems.is_empty());
+    }
+
+    #[test]
+    fn test_validate_accepts_base64_index_terms() {
+        let envelope = serde_json::json!({
+            "k": "ct",
+            "c": TEST_CIPHERTEXT,
+            "dt": "text",
+            "hm": "b64:88px/Tk=",
+            "ob": ["b64:88px/Tk="],
+            "bf": null,
+            "i": {"t": "users", "c": "email"},
+            "v": 2
+        })
+        .to_string();
+
+        let report = validate(&envelope).unwrap();
+
+        assert!(report.valid, "{:?}", report.problems);
+    }
+
+    #[test]
+    fn test_validate_signed_envelope() {
+        let mut envelope: Value = serde_json::from_str(&ciphertext_envelope()).unwrap();
+        envelope[SIGNATURE_FIELD] = json!("abcd");
+        assert!(validate(&envelope.to_string()).unwrap().valid);
+
+        envelope[SIGNATURE_FIELD] = json!(12);
+        let report = validate(&envelope.to_string()).unwrap();
+        let paths: Vec<&str> = report.problems.iter().map(|p| p.path.as_str()).collect();
+
+        assert_eq!(paths, ["$.sig"]);
+    }
+
+    #[test]
+    fn test_validate_reports_field_level_problems() {
+        let envelope = json!({
+            "k": "ct",
+            "c": "",
+            "dt": "varchar",
+            "hm": "not-hex",
+            "ob": ["abcd", 12],
+            "bf": [1, 70000],
+            "i": {"t": "users"},
+            "v": 2,
+            "extra": true
+        })
+        .to_string();
+
+        let report = validate(&envelope).unwrap();
+        let paths: Vec<&str> = report.problems.iter().map(|p| p.path.as_str()).collect();
+
+        assert!(!report.valid);
+        assert_eq!(
+            paths,
+            ["$.hm", "$.ob[1]", "$.bf[1]", "$.c", "$.dt", "$.i.c", "$.extra"]
+        );
+    }
+
+    #[test]
+    fn test_validate_ste_vec_entries() {
+        let envelope = json!({
+            "k": "sv",
+            "c": TEST_CIPHERTEXT,
+            "dt": "jsonb",
+            "sv": [{"s": "dd46", "t": "2230", "r": TEST_CIPHERTEXT}, "entry"],
+            "i": {"t": "users", "c": "metadata"},
+            "v": 2
+        })
+        .to_string();
+
+        let report = validate(&envelope).unwrap();
+        let paths: Vec<&str> = report.problems.iter().map(|p| p.path.as_str()).collect();
+
+        assert_eq!(paths, ["$.sv[0].pa", "$.sv[1]"]);
+    }
+
+    #[test]
+    fn test_validate_array_elements() {
+        let envelope = json!({
+            "k": "ct",
+            "c": TEST_CIPHERTEXT,
+            "dt": "text[]",
+            "hm": null,
+            "ae": [{"hm": TEST_UNIQUE_INDEX}, {"hm": "not-hex", "u": "abcd"}, 1],
+            "i": {"t": "users", "c": "tags"},
+            "v": 2
+        })
+        .to_string();
+
+        let report = validate(&envelope).unwrap();
+        let paths: Vec<&str> = report.problems.iter().map(|p| p.path.as_str()).collect();
+
+        assert_eq!(paths, ["$.ae[1].hm", "$.ae[1].u", "$.ae[2]"]);
+    }
+
+    #[test]
+    fn test_format_counts_array_elements() {
+        let envelope = json!({
+            "k": "ct",
+            "c": TEST_CIPHERTEXT,
+            "dt": "int[]",
+            "hm": null,
+            "ob": null,
+            "bf": null,
+            "ae": [{"hm": TEST_UNIQUE_INDEX}, {"hm": TEST_UNIQUE_INDEX}],
+            "i": {"t": "users", "c": "scores"},
+            "v": 2
+        })
+        .to_string();
+
+        let rendered = format(&envelope, &compact_options()).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+
+        assert_eq!(parsed["data_type"], "int[]");
+        assert_eq!(parsed["indexes"]["array_elements"], 2);
+    }
+
+    #[test]
+    fn test_validate_unsupported_version_and_missing_kind() {
+        let envelope =
+            json!({"c": TEST_CIPHERTEXT, "dt": "text", "i": {"t": "a", "c": "b"}, "v": 9})
+                .to_string();
+
+        let report = validate(&envelope).unwrap();
+        let paths: Vec<&str> = report.problems.iter().map(|p| p.path.as_str()).collect();
+
+        assert_eq!(report.version, Some(9));
+        assert_eq!(paths, ["$.v", "$.k"]);
+    }
+
+    #[test]
+    fn test_validate_v1_envelope_uses_v1_field_names() {
+        let envelope = json!({
+            "k": "ct",
+            "c": TEST_CIPHERTEXT,
+            "u": TEST_UNIQUE_INDEX,
+            "m": [1, 2],
+            "hm": TEST_UNIQUE_INDEX,
+            "i": {"t": "users", "c": "email"},
+            "v": 1
+        })
+        .to_string();
+
+        let report = validate(&envelope).unwrap();
+        let paths: Vec<&str> = report.problems.iter().map(|p| p.path.as_str()).collect();
+
+        assert_eq!(report.version, Some(1));
+        assert_eq!(paths, ["$.hm"]);
+    }
+
+    #[test]
+    fn test_parse_v1_envelope() {
+        let envelope = json!({
+            "k": "ct",
+            "c": TEST_CIPHERTEXT,
+            "u": TEST_UNIQUE_INDEX,
+            "o": null,
+            "m": [1, 2],
+            "i": {"t": "users", "c": "email"},
+            "v": 1
+        })
+        .to_string();
+
+        let parsed = VersionedEnvelope::parse(&envelope).unwrap();
+
+        assert!(matches!(parsed, VersionedEnvelope::V1(_)));
+        assert_eq!(parsed.identifier(), &Identifier::new("users", "email"));
+        assert_eq!(parsed.into_ciphertext(), TEST_CIPHERTEXT);
+    }
+
+    #[test]
+    fn test_parse_unsupported_envelope_version_fails() {
+        let result = VersionedEnvelope::parse(r#"{"k": "ct", "v": 9}"#);
+
+        assert!(matches!(result, Err(Error::UnsupportedEnvelopeVersion(9))));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "unsupported envelope version 9: expected one of [1, 2, 3]"
+        );
+    }
+
+    #[test]
+    fn test_downgrade_and_upgrade_roundtrip() {
+        let encrypted: Encrypted = serde_json::from_str(&ciphertext_envelope()).unwrap();
+
+        let legacy = downgrade(encrypted);
+        let legacy_json = serde_json::to_value(&legacy).unwrap();
+        assert_eq!(legacy_json["u"], TEST_UNIQUE_INDEX);
+        assert_eq!(legacy_json["v"], 1);
+        assert!(legacy_json.get("dt").is_none());
+
+        let upgraded = serde_json::to_value(upgrade(legacy, &CastAs::Text)).unwrap();
+        let original: Value = serde_json::from_str(&ciphertext_envelope()).unwrap();
+        assert_eq!(upgraded, original);
+    }
+
+    #[test]
+    fn test_emit_versions() {
+        let encrypted: Encrypted = serde_json::from_str(&ciphertext_envelope()).unwrap();
+        let emitted = VersionedEnvelope::emit(encrypted, 1, &email_index_params()).unwrap();
+
+        let emitted_json = serde_json::to_value(&emitted).unwrap();
+        assert_eq!(emitted_json["v"], 1);
+        assert_eq!(emitted_json["k"], "ct");
+
+        let encrypted: Encrypted = serde_json::from_str(&ciphertext_envelope()).unwrap();
+        let result = VersionedEnvelope::emit(encrypted, 4, &email_index_params());
+        assert!(matches!(result, Err(Error::UnsupportedEnvelopeVersion(4))));
+    }
+
+    #[test]
+    fn test_version_3_envelopes_record_index_params() {
+        let encrypted: Encrypted = serde_json::from_str(&ciphertext_envelope()).unwrap();
+        let emitted = VersionedEnvelope::emit(encrypted, 3, &email_index_params()).unwrap();
+
+        let emitted_json = serde_json::to_value(&emitted).unwrap();
+        assert_eq!(emitted_json["v"], 3);
+        assert_eq!(emitted_json["hm"], TEST_UNIQUE_INDEX);
+        assert_eq!(emitted_json["ix"], json!({"hm": {"v": 1}}));
+
+        let report = validate(&emitted_json.to_string()).unwrap();
+        assert!(report.valid, "{:?}", report.problems);
+
+        let parsed = VersionedEnvelope::parse(&emitted_json.to_string()).unwrap();
+        assert_eq!(parsed.identifier(), &Identifier::new("users", "email"));
+        assert_eq!(parsed.into_ciphertext(), TEST_CIPHERTEXT);
+
+        let mut newer = emitted_json.clone();
+        newer["ix"]["hm"]["v"] = json!(2);
+        let report = validate(&newer.to_string()).unwrap();
+        assert_eq!(report.problems[0].path, "$.ix.hm.v");
+
+        let outcome = upgrade_to_current(
+            VersionedEnvelope::parse(&newer.to_string()).unwrap(),
+            &email_column_config(),
+            &CastAs::Text,
+            &email_index_params(),
+        );
+        assert!(outcome.requires_reencryption);
+        assert!(outcome.reasons[0].starts_with("`hm` term was created with index version 2"));
+
+        let mut missing = emitted_json;
+        missing.as_object_mut().unwrap().remove("ix");
+        let report = validate(&missing.to_string()).unwrap();
+        assert_eq!(report.problems[0].path, "$.ix");
+    }
+
+    #[test]
+    fn test_extract_ciphertext_from_raw_and_envelope_input() {
+        let raw = extract_ciphertext(TEST_CIPHERTEXT.to_string()).unwrap();
+        assert_eq!(raw.ciphertext, TEST_CIPHERTEXT);
+        assert_eq!(raw.keyset, None);
+        assert_eq!(raw.data_type, None);
+
+        let from_envelope = extract_ciphertext(ciphertext_envelope()).unwrap();
+        assert_eq!(from_envelope.ciphertext, TEST_CIPHERTEXT);
+        assert_eq!(from_envelope.keyset, None);
+        assert_eq!(
+            from_envelope.data_type,
+            Some((Identifier::new("users", "email"), "text".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_ste_vec_root_accepts_encoded_envelopes() {
+        let envelope = json!({
+            "k": "sv",
+            "c": TEST_CIPHERTEXT,
+            "dt": "jsonb",
+            "sv": [],
+            "i": {"t": "users", "c": "metadata"},
+            "v": 2
+        })
+        .to_string();
+
+        assert_eq!(ste_vec_root(&envelope).unwrap(), envelope);
+
+        let encoded = Value::String(envelope.clone()).to_string();
+        assert_eq!(ste_vec_root(&encoded).unwrap(), envelope);
+
+        let result = ste_vec_root(&ciphertext_envelope());
+        assert!(matches!(result, Err(Error::InvalidEnvelope(_))));
+        assert!(matches!(
+            ste_vec_root(TEST_CIPHERTEXT),
+            Err(Error::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_keyset_reference_roundtrip() {
+        let mut envelope: Value = serde_json::from_str(&ciphertext_envelope()).unwrap();
+        envelope["ks"] = json!("pii");
+        let envelope_json = envelope.to_string();
+
+        let keyset = extract_ciphertext(envelope_json.clone()).unwrap().keyset;
+        assert_eq!(keyset.as_deref(), Some("pii"));
+        assert!(validate(&envelope_json).unwrap().valid);
+
+        let rendered = format(&envelope_json, &compact_options()).unwrap();
+        let parsed: Value = serde_json::from_str(&rendered).unwrap();
+        assert_eq!(parsed["keyset"], "pii");
+
+        let encrypted: Encrypted = serde_json::from_value(envelope).unwrap();
+        let downgraded = serde_json::to_value(downgrade(encrypted)).unwrap();
+        assert!(downgraded.get("ks").is_none());
+    }
+
+    #[test]
+    fn test_expiry_roundtrip() {
+        let mut envelope: Value = serde_json::from_str(&ciphertext_envelope()).unwrap();
+        envelope["exp"] = json!(1_767_225_600);
+        let envelope_json = envelope.to_string();
+
+        let expires_at = extract_ciphertext(envelope_json.clone())
+            .unwrap()
+            .expires_at;
+        assert_eq!(expires_at, Some(1_767_225_600));
+        assert!(validate(&envelope_json).unwrap().valid);
+
+        let rendered = format(&envelope_json, &compact_options()).unwrap();
+        let parsed: Value = serde_json::from_str(&rendered).unwrap();
+        assert_eq!(parsed["expires_at"], 1_767_225_600);
+
+        envelope["exp"] = json!("2026-01-01");
+        let report = validate(&envelope.to_string()).unwrap();
+        assert_eq!(report.problems[0].path, "$.exp");
+
+        let encrypted: Encrypted = serde_json::from_str(&envelope_json).unwrap();
+        assert!(matches!(
+            VersionedEnvelope::emit(encrypted, 1, &email_index_params()),
+            Err(Error::InvalidEnvelope(_))
+        ));
+    }
+
+    #[test]
+    fn test_format_v1_envelope_omits_data_type() {
+        let envelope = serde_json::to_string(&downgrade(
+            serde_json::from_str(&ciphertext_envelope()).unwrap(),
+        ))
+        .unwrap();
+
+        let rendered = format(&envelope, &compact_options()).unwrap();
+        let parsed: Value = serde_json::from_str(&rendered).unwrap();
+
+        assert_eq!(parsed["version"], 1);
+        assert!(parsed.get("data_type").is_none());
+    }
+
+    fn test_record() -> EncryptedRecord {
+        EncryptedRecord {
+            iv: [7; 16],
+            ciphertext: vec![1, 2, 3, 4],
+            tag: vec![5, 6, 7, 8],
+            descriptor: "users/email".to_string(),
+            dataset_id: None,
+        }
+    }
+
+    fn email_column_config() -> ColumnConfig {
+        ColumnConfig::build("email".to_string()).add_index(
+            cipherstash_client::schema::column::Index::new(IndexType::Unique {
+                token_filters: vec![],
+            }),
+        )
+    }
+
+    fn email_index_params() -> IndexParams {
+        IndexParams::for_column(&email_column_config(), &IndexNormalization::default())
+    }
+
+    fn legacy_ciphertext(ciphertext: &str, unique_index: Option<&str>) -> EncryptedV1 {
+        EncryptedV1::Ciphertext {
+            ciphertext: ciphertext.to_string(),
+            unique_index: unique_index.map(str::to_string),
+            ore_index: None,
+            match_index: None,
+            identifier: Identifier::new("users", "email"),
+            version: 1,
+        }
+    }
+
+    fn legacy_envelope(ciphertext: &str, unique_index: Option<&str>) -> VersionedEnvelope {
+        VersionedEnvelope::V1(legacy_ciphertext(ciphertext, unique_index))
+    }
+
+    #[test]
+    fn test_upgrade_to_current_renames_fields() {
+        let ciphertext = test_record().to_mp_base85().unwrap();
+        let envelope = legacy_envelope(&ciphertext, Some(TEST_UNIQUE_INDEX));
+
+        let outcome = upgrade_to_current(
+            envelope,
+            &email_column_config(),
+            &CastAs::Text,
+            &email_index_params(),
+        );
+
+        assert!(outcome.upgraded);
+        assert!(!outcome.requires_reencryption);
+        match outcome.envelope {
+            Some(VersionedEnvelope::V2(Encrypted::Ciphertext {
+                ciphertext: upgraded_ciphertext,
+                unique_index,
+                version,
+                ..
+            })) => {
+                assert_eq!(upgraded_ciphertext, ciphertext);
+                assert_eq!(unique_index.as_deref(), Some(TEST_UNIQUE_INDEX));
+                assert_eq!(version, CURRENT_VERSION);
+            }
+            other => panic!("expected upgraded ciphertext envelope, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_upgrade_to_current_reencodes_cbor_hex_ciphertext() {
+        let record = test_record();
+        let envelope = legacy_envelope(&record.to_cbor_hex().unwrap(), Some(TEST_UNIQUE_INDEX));
+
+        let outcome = upgrade_to_current(
+            envelope,
+            &email_column_config(),
+            &CastAs::Text,
+            &email_index_params(),
+        );
+
+        let Some(VersionedEnvelope::V2(Encrypted::Ciphertext { ciphertext, .. })) =
+            outcome.envelope
+        else {
+            panic!("expected upgraded ciphertext envelope");
+        };
+        assert_eq!(ciphertext, record.to_mp_base85().unwrap());
+    }
+
+    #[test]
+    fn test_upgrade_to_current_current_envelope_is_unchanged() {
+        let ciphertext = test_record().to_mp_base85().unwrap();
+        let legacy = legacy_ciphertext(&ciphertext, Some(TEST_UNIQUE_INDEX));
+        let envelope = VersionedEnvelope::V2(upgrade(legacy, &CastAs::Text));
+
+        let outcome = upgrade_to_current(
+            envelope,
+            &email_column_config(),
+            &CastAs::Text,
+            &email_index_params(),
+        );
+
+        assert!(!outcome.upgraded);
+        assert!(outcome.envelope.is_some());
+    }
+
+    #[test]
+    fn test_upgrade_to_current_flags_missing_terms_and_bad_ciphertext() {
+        let envelope = legacy_envelope("not a ciphertext", None);
+
+        let outcome = upgrade_to_current(
+            envelope,
+            &email_column_config(),
+            &CastAs::Text,
+            &email_index_params(),
+        );
+
+        assert!(outcome.requires_reencryption);
+        assert!(!outcome.upgraded);
+        assert!(outcome.envelope.is_none());
+        assert_eq!(outcome.reasons.len(), 2);
+    }
+
+    #[test]
+    fn test_validate_non_object_envelope() {
+        let report = validate("[]").unwrap();
+
+        assert!(!report.valid);
+        assert_eq!(report.problems[0].path, "$");
+    }
+
+    #[test]
+    fn test_validate_malformed_json_fails() {
+        assert!(matches!(validate("{"), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn test_format_invalid_envelope_fails() {
+        let result = format(r#"{"k": "unknown"}"#, &FormatOptions::default());
+
+        assert!(matches!(result, Err(Error::Parse(_))));
+    }
+}
@@ -0,0 +1,144 @@
+//! The catalog of error codes returned by `get_error_catalog`.
+//!
+//! Every [`Error`] variant has a stable `code`, which `get_last_error_code` reports for the most
+//! recent error, with whether the operation may succeed if retried unchanged and the HTTP status
+//! a web application would typically respond with. Wrappers can generate their exception types
+//! from the catalog instead of matching messages. The catalog and [`Error::catalog_entry`] are
+//! generated from one list, so a new variant cannot compile without an entry.
+
+use crate::Error;
+use serde::Serialize;
+
+/// An error code, with how callers should handle errors of that code.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ErrorEntry {
+    /// Stable code of the error, in snake case.
+    pub code: &'static str,
+    /// Name of the `Error` variant.
+    pub name: &'static str,
+    /// Whether an operation that failed with the error may succeed if retried unchanged.
+    pub retryable: bool,
+    /// HTTP status a web application would typically respond with.
+    pub http_status: u16,
+}
+
+macro_rules! error_catalog {
+    ($($name:ident => $code:literal, $retryable:literal, $http_status:literal;)*) => {
+        /// Every error code, in the order of the `Error` variants.
+        pub const CATALOG: &[ErrorEntry] = &[$(ErrorEntry {
+            code: $code,
+            name: stringify!($name),
+            retryable: $retryable,
+            http_status: $http_status,
+        }),*];
+
+        impl Error {
+            /// The catalog entry of the error.
+            pub fn catalog_entry(&self) -> &'static ErrorEntry {
+                match self {
+                    $(Error::$name { .. } => &ErrorEntry {
+                        code: $code,
+                        name: stringify!($name),
+                        retryable: $retryable,
+                        http_status: $http_status,
+                    },)*
+                }
+            }
+        }
+    };
+}
+
+error_catalog! {
+    Config => "config", false, 500;
+    ZeroKMS => "zerokms", true, 502;
+    Encryption => "encryption", false, 500;
+    TypeParse => "type_parse", false, 400;
+    Parse => "parse", false, 400;
+    Utf8 => "utf8", false, 400;
+    InvalidConfigSyntax => "invalid_config_syntax", false, 500;
+    InvalidInterpolation => "invalid_interpolation", false, 500;
+    InvalidExtends => "invalid_extends", false, 500;
+    UnsupportedSchemaVersion => "unsupported_schema_version", false, 400;
+    UnsupportedEnvelopeVersion => "unsupported_envelope_version", false, 400;
+    MissingSignature => "missing_signature", false, 422;
+    InvalidSignature => "invalid_signature", false, 422;
+    InvalidContextPreset => "invalid_context_preset", false, 500;
+    InvalidContext => "invalid_context", false, 400;
+    InvalidToken => "invalid_token", false, 401;
+    ContextMismatch => "context_mismatch", false, 403;
+    NarrowingCast => "narrowing_cast", false, 422;
+    IncompatibleCast => "incompatible_cast", false, 422;
+    InvalidArray => "invalid_array", false, 422;
+    InvalidUnsignedInteger => "invalid_unsigned_integer", false, 422;
+    InvalidInteger => "invalid_integer", false, 422;
+    EmptyPlaintext => "empty_plaintext", false, 422;
+    PlaintextTooLong => "plaintext_too_long", false, 422;
+    MatchLimitExceeded => "match_limit_exceeded", false, 422;
+    InvalidDate => "invalid_date", false, 422;
+    InvalidJsonPath => "invalid_json_path", false, 400;
+    InvalidIndexTerm => "invalid_index_term", false, 400;
+    InvalidSearchTerm => "invalid_search_term", false, 400;
+    IncompatibleIndexParams => "incompatible_index_params", false, 409;
+    InvalidBenchmark => "invalid_benchmark", false, 400;
+    InvalidStorage => "invalid_storage", false, 400;
+    InvalidEnvelope => "invalid_envelope", false, 400;
+    InvalidJsonDocument => "invalid_json_document", false, 422;
+    InvalidFloat => "invalid_float", false, 422;
+    InvalidColumn => "invalid_column", false, 500;
+    UnknownKeyset => "unknown_keyset", false, 400;
+    UnknownColumn => "unknown_column", false, 400;
+    RateLimited => "rate_limited", true, 429;
+    InvalidClientOptions => "invalid_client_options", false, 500;
+    InvalidJob => "invalid_job", false, 409;
+    ClientStartup => "client_startup", true, 503;
+    Credentials => "credentials", true, 503;
+    NetworkForbidden => "network_forbidden", false, 500;
+    IncompatibleAbi => "incompatible_abi", false, 500;
+    DecryptionDisabled => "decryption_disabled", false, 403;
+    Expired => "expired", false, 410;
+    Overloaded => "overloaded", true, 503;
+    ConfigMismatch => "config_mismatch", false, 409;
+    PlaintextTooLarge => "plaintext_too_large", false, 413;
+    Base85 => "base85", false, 400;
+    Unimplemented => "unimplemented", false, 501;
+    Runtime => "runtime", false, 500;
+    NullPointer => "null_pointer", false, 500;
+    InvalidPointer => "invalid_pointer", false, 500;
+    StringConversion => "string_conversion", false, 400;
+    InvariantViolation => "invariant_violation", false, 500;
+    Panic => "panic", false, 500;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_catalog_codes_are_unique() {
+        let codes: HashSet<_> = CATALOG.iter().map(|entry| entry.code).collect();
+
+        assert_eq!(codes.len(), CATALOG.len());
+        assert!(CATALOG.iter().all(|entry| entry
+            .code
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')));
+    }
+
+    #[test]
+    fn test_catalog_entry_of_error() {
+        let entry = Error::Overloaded { outstanding: 64 }.catalog_entry();
+        assert_eq!(
+            (entry.code, entry.name, entry.retryable, entry.http_status),
+            ("overloaded", "Overloaded", true, 503)
+        );
+
+        assert_eq!(Error::NullPointer.catalog_entry().code, "null_pointer");
+        assert_eq!(
+            Error::InvariantViolation("test".to_string())
+                .catalog_entry()
+                .code,
+            "invariant_violation"
+        );
+    }
+}
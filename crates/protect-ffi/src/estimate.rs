@@ -0,0 +1,465 @@
+//! Offline storage estimates for `estimate_storage`.
+//!
+//! Estimates are computed without ZeroKMS. Each sample value is turned into an envelope with
+//! placeholder ciphertext and index terms of the sizes the configured indexes produce, and the
+//! envelopes are measured like the payload statistics of `encrypt_bulk`. Fixed-size terms use
+//! the sizes of the real terms, while `match` terms are computed with the column's tokenizer
+//! and bloom filter parameters under a dummy key, since their length depends on the text.
+//!
+//! Ciphertext sizes are approximate: record framing varies slightly with the keyset and data
+//! key, so estimates are intended for comparing index choices rather than exact provisioning.
+
+use crate::encrypt_config::{self, CastAs, EncryptConfig, Identifier};
+use crate::index_params::IndexParams;
+use crate::normalization::IndexNormalization;
+use crate::stats::{self, BulkStats};
+use crate::ste_vec::{self, SteVecPolicy};
+use crate::{
+    arrays, empty, envelope, plaintext_targets, ElementTerms, Encrypted, Error, SteVecEntry,
+};
+use cipherstash_client::schema::{
+    column::{IndexType, TokenFilter, Tokenizer},
+    ColumnConfig,
+};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::Sha256;
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Key used to place `match` terms in the bloom filter. Any key gives the same distribution.
+const DUMMY_KEY: &[u8] = b"protect-ffi storage estimate";
+
+/// Bytes a ciphertext record adds to its plaintext: nonce, authentication tag, data key IV,
+/// keyset identifier, and MessagePack framing.
+const RECORD_OVERHEAD_BYTES: usize = 96;
+
+/// Bytes of a `unique` index term, an HMAC-SHA256.
+const UNIQUE_TERM_BYTES: usize = 32;
+
+/// Bytes of an `ore` index term for a 64-bit value, with 8 blocks of 8 bits.
+const ORE_TERM_BYTES: usize = 408;
+
+/// Bytes of text covered by each `ore` term of a text value.
+const ORE_TEXT_BYTES_PER_TERM: usize = 8;
+
+/// Bytes of a `ste_vec` selector and of a `ste_vec` term.
+const STE_VEC_TERM_BYTES: usize = 32;
+
+/// A representative value of a configured column.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sample {
+    /// The table of the column.
+    pub table: String,
+    /// The column the value would be stored in.
+    pub column: String,
+    /// The plaintext value.
+    pub plaintext: String,
+}
+
+/// Estimate the storage size of sample values under an encryption configuration.
+///
+/// # Errors
+///
+/// Returns an error if the configuration is invalid, a sample names an unknown column, or a
+/// sample plaintext cannot be encrypted for its column.
+pub fn estimate(config_json: &str, samples: Vec<Sample>) -> Result<BulkStats, Error> {
+    let config = EncryptConfig::from_str(config_json)?;
+    let keysets = config.column_keysets();
+    let text_policies = config.text_policies();
+    let ste_vec_policies = config.ste_vec_policies();
+    let columns = config.into_config_map();
+    let mut stats = BulkStats::default();
+
+    for sample in samples {
+        let identifier = Identifier::new(sample.table, sample.column);
+        let (column_config, cast_as) = encrypt_config::column(&columns, &identifier)?;
+
+        // Measure the plaintext as stored, and reject plaintexts that encryption would reject.
+        let text_policy = text_policies.get(&identifier).copied().unwrap_or_default();
+        let ste_vec_policy = ste_vec_policies
+            .get(&identifier)
+            .unwrap_or(&ste_vec::UNLIMITED);
+        let plaintext = empty::prepare(sample.plaintext, *cast_as, text_policy)?;
+        plaintext_targets(
+            plaintext.clone(),
+            column_config,
+            *cast_as,
+            text_policy,
+            ste_vec_policy,
+            Vec::new(),
+        )?;
+
+        let keyset = keysets.get(&identifier).cloned();
+        let encrypted = placeholder(
+            &plaintext,
+            column_config,
+            *cast_as,
+            &text_policy.normalization,
+            ste_vec_policy,
+            identifier,
+            keyset,
+        )?;
+        let measured = stats::measure(&encrypted)?;
+        let index_params = IndexParams::for_column(column_config, &text_policy.normalization);
+        let envelope =
+            envelope::VersionedEnvelope::emit(encrypted, envelope::CURRENT_VERSION, &index_params)?;
+
+        stats.record(plaintext.len(), measured, &serde_json::to_value(&envelope)?)?;
+    }
+
+    Ok(stats)
+}
+
+/// An encrypted value with placeholder ciphertext and index terms of the expected sizes.
+fn placeholder(
+    plaintext: &str,
+    column_config: &ColumnConfig,
+    cast_as: CastAs,
+    normalization: &IndexNormalization,
+    ste_vec_policy: &SteVecPolicy,
+    identifier: Identifier,
+    keyset: Option<String>,
+) -> Result<Encrypted, Error> {
+    let ciphertext = ciphertext(encoded_len(plaintext, cast_as));
+
+    if cast_as == CastAs::JsonB {
+        let ste_vec_index = column_config
+            .indexes
+            .iter()
+            .any(|index| matches!(index.index_type, IndexType::SteVec { .. }))
+            .then(|| {
+                let mut entries = Vec::new();
+                let document = ste_vec_policy.indexed(&serde_json::from_str(plaintext)?)?;
+                ste_vec_entries(&document, false, &mut entries);
+                Ok::<_, Error>(entries)
+            })
+            .transpose()?;
+
+        return Ok(Encrypted::SteVec {
+            ciphertext,
+            data_type: cast_as.to_string(),
+            ste_vec_index,
+            identifier,
+            keyset,
+            expires_at: None,
+            version: envelope::CURRENT_VERSION,
+        });
+    }
+
+    let (terms, elements) = match cast_as.element_type() {
+        Some(element_type) => {
+            let (_, elements) = arrays::parse(plaintext, element_type)?;
+            let elements = elements
+                .iter()
+                .map(|element| index_terms(element, column_config, element_type, normalization))
+                .collect();
+
+            (ElementTerms::default(), Some(elements))
+        }
+        None => (
+            index_terms(plaintext, column_config, cast_as, normalization),
+            None,
+        ),
+    };
+
+    Ok(Encrypted::Ciphertext {
+        ciphertext,
+        data_type: cast_as.to_string(),
+        unique_index: terms.unique_index,
+        ore_index: terms.ore_index,
+        match_index: terms.match_index,
+        elements,
+        identifier,
+        keyset,
+        expires_at: None,
+        version: envelope::CURRENT_VERSION,
+    })
+}
+
+/// Placeholder index terms of a scalar value for each configured index.
+fn index_terms(
+    plaintext: &str,
+    column_config: &ColumnConfig,
+    cast_as: CastAs,
+    normalization: &IndexNormalization,
+) -> ElementTerms {
+    let mut terms = ElementTerms::default();
+
+    for index in &column_config.indexes {
+        match &index.index_type {
+            IndexType::Unique { .. } => {
+                terms.unique_index = Some(hex_placeholder(UNIQUE_TERM_BYTES));
+            }
+            IndexType::Ore => {
+                let count = match cast_as {
+                    CastAs::Text => plaintext.len().div_ceil(ORE_TEXT_BYTES_PER_TERM).max(1),
+                    _ => 1,
+                };
+                terms.ore_index = Some(vec![hex_placeholder(ORE_TERM_BYTES); count]);
+            }
+            IndexType::Match {
+                tokenizer,
+                token_filters,
+                k,
+                m,
+                include_original,
+            } => {
+                let text = match normalization.source_of(&index.index_type) {
+                    Some(source) => source.apply(plaintext),
+                    None => plaintext.to_string(),
+                };
+                let tokens = tokenize(&text, tokenizer, token_filters, *include_original);
+                terms.match_index = Some(bloom_filter(&tokens, *k, *m));
+            }
+            IndexType::SteVec { .. } => {}
+        }
+    }
+
+    terms
+}
+
+/// Split text into the distinct tokens a `match` index adds to its bloom filter.
+fn tokenize(
+    text: &str,
+    tokenizer: &Tokenizer,
+    token_filters: &[TokenFilter],
+    include_original: bool,
+) -> BTreeSet<String> {
+    let text = if token_filters
+        .iter()
+        .any(|filter| matches!(filter, TokenFilter::Downcase))
+    {
+        text.to_lowercase()
+    } else {
+        text.to_string()
+    };
+
+    let mut tokens: BTreeSet<String> = match tokenizer {
+        Tokenizer::Ngram { token_length } => {
+            let chars: Vec<char> = text.chars().collect();
+            if chars.len() <= *token_length {
+                BTreeSet::from([text.clone()])
+            } else {
+                chars
+                    .windows((*token_length).max(1))
+                    .map(|window| window.iter().collect())
+                    .collect()
+            }
+        }
+        _ => text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_string)
+            .collect(),
+    };
+
+    if include_original {
+        tokens.insert(text);
+    }
+
+    tokens
+}
+
+/// The set bloom filter positions of tokens, with `k` positions per token in `m` bits.
+fn bloom_filter(tokens: &BTreeSet<String>, k: usize, m: usize) -> Vec<u16> {
+    let mut positions = BTreeSet::new();
+
+    for token in tokens {
+        for hash in 0..k {
+            let mut mac =
+                HmacSha256::new_from_slice(DUMMY_KEY).expect("HMAC accepts keys of any length");
+            mac.update(&(hash as u32).to_be_bytes());
+            mac.update(token.as_bytes());
+
+            let digest = mac.finalize().into_bytes();
+            let value = u64::from_be_bytes(digest[..8].try_into().expect("digest has 32 bytes"));
+            positions.insert((value % m.max(1) as u64) as u16);
+        }
+    }
+
+    positions.into_iter().collect()
+}
+
+/// Placeholder `ste_vec` entries for a JSON value: one per value in the document.
+fn ste_vec_entries(value: &Value, parent_is_array: bool, entries: &mut Vec<SteVecEntry>) {
+    entries.push(SteVecEntry {
+        tokenized_selector: hex_placeholder(STE_VEC_TERM_BYTES),
+        term: hex_placeholder(STE_VEC_TERM_BYTES),
+        record: ciphertext(value.to_string().len()),
+        parent_is_array,
+    });
+
+    match value {
+        Value::Object(fields) => {
+            for field in fields.values() {
+                ste_vec_entries(field, false, entries);
+            }
+        }
+        Value::Array(elements) => {
+            for element in elements {
+                ste_vec_entries(element, true, entries);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Bytes of a plaintext as encoded for encryption, excluding its type tag.
+fn encoded_len(plaintext: &str, cast_as: CastAs) -> usize {
+    match cast_as {
+        CastAs::Boolean => 1,
+        CastAs::SmallInt => 2,
+        CastAs::Int | CastAs::Date => 4,
+        CastAs::BigInt | CastAs::Real | CastAs::Double => 8,
+        _ => plaintext.len(),
+    }
+}
+
+/// Placeholder base85 ciphertext for a plaintext of `encoded_len` bytes.
+fn ciphertext(encoded_len: usize) -> String {
+    let record_len = RECORD_OVERHEAD_BYTES + encoded_len + 1;
+    "0".repeat(record_len.div_ceil(4) * 5)
+}
+
+/// Placeholder hex index term of `len` bytes.
+fn hex_placeholder(len: usize) -> String {
+    "0".repeat(len * 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config() -> String {
+        json!({
+            "v": 2,
+            "tables": {
+                "users": {
+                    "email": {"cast_as": "text", "indexes": {"unique": {}, "match": {}}},
+                    "age": {"cast_as": "int", "indexes": {"ore": {}}},
+                    "tags": {"cast_as": "text[]", "indexes": {"unique": {}}},
+                    "profile": {"cast_as": "jsonb", "indexes": {"ste_vec": {"prefix": "users.profile"}}}
+                }
+            }
+        })
+        .to_string()
+    }
+
+    fn sample(column: &str, plaintext: &str) -> Sample {
+        Sample {
+            table: "users".to_string(),
+            column: column.to_string(),
+            plaintext: plaintext.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_estimate_sizes_fixed_index_terms() {
+        let stats = estimate(
+            &config(),
+            vec![sample("age", "29"), sample("tags", r#"["a","b"]"#)],
+        )
+        .unwrap();
+
+        let age = &stats.columns["users.age"];
+        assert_eq!(age.index_bytes.ore, (ORE_TERM_BYTES * 2 + 4) as u64);
+        assert_eq!(age.index_bytes.unique, 0);
+        assert_eq!(age.ciphertext_bytes, 130);
+
+        let tags = &stats.columns["users.tags"];
+        assert_eq!(
+            tags.index_bytes.unique,
+            2 * (UNIQUE_TERM_BYTES * 2 + 2) as u64
+        );
+    }
+
+    #[test]
+    fn test_estimate_match_terms_grow_with_text() {
+        let stats = estimate(
+            &config(),
+            vec![
+                sample("email", "ada@example.com"),
+                sample("email", "a much longer note with many distinct words in it"),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(stats.total.items, 2);
+        assert!(stats.columns["users.email"].index_bytes.r#match > 0);
+
+        let short = bloom_filter(
+            &tokenize("ada@example.com", &Tokenizer::Standard, &[], false),
+            6,
+            2048,
+        );
+        assert!(short.len() <= 18);
+        assert!(short.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_estimate_ste_vec_entries() {
+        let stats = estimate(
+            &config(),
+            vec![sample("profile", r#"{"name":"Ada","roles":["admin"]}"#)],
+        )
+        .unwrap();
+
+        // The document, `name`, `roles`, and the `roles` element.
+        let mut entries = Vec::new();
+        ste_vec_entries(
+            &json!({"name": "Ada", "roles": ["admin"]}),
+            false,
+            &mut entries,
+        );
+        assert_eq!(entries.len(), 4);
+        assert!(entries[3].parent_is_array);
+        assert!(stats.columns["users.profile"].index_bytes.ste_vec > 0);
+    }
+
+    #[test]
+    fn test_estimate_ste_vec_entries_of_array_and_scalar_roots() {
+        let mut entries = Vec::new();
+        ste_vec_entries(&json!(["admin", "editor"]), false, &mut entries);
+        assert_eq!(entries.len(), 3);
+        assert!(!entries[0].parent_is_array);
+        assert!(entries[1].parent_is_array && entries[2].parent_is_array);
+
+        let mut entries = Vec::new();
+        ste_vec_entries(&json!("admin"), false, &mut entries);
+        assert_eq!(entries.len(), 1);
+        assert!(!entries[0].parent_is_array);
+
+        let result = estimate(&config(), vec![sample("profile", "null")]);
+        assert!(matches!(result, Err(Error::InvalidJsonDocument(_))));
+    }
+
+    #[test]
+    fn test_estimate_rejects_unknown_columns_and_invalid_plaintexts() {
+        let result = estimate(&config(), vec![sample("missing", "a")]);
+        assert!(matches!(result, Err(Error::UnknownColumn(..))));
+
+        let result = estimate(&config(), vec![sample("tags", "not an array")]);
+        assert!(matches!(result, Err(Error::InvalidArray(_))));
+    }
+
+    #[test]
+    fn test_tokenize_ngrams_with_downcase() {
+        let tokens = tokenize(
+            "AbcD",
+            &Tokenizer::Ngram { token_length: 3 },
+            &[TokenFilter::Downcase],
+            true,
+        );
+
+        assert_eq!(
+            tokens,
+            BTreeSet::from(["abc".to_string(), "bcd".to_string(), "abcd".to_string()])
+        );
+    }
+}
@@ -0,0 +1,74 @@
+//! Expiry of encrypted values.
+//!
+//! A value encrypted with an `expires_at` time, in seconds since the Unix epoch, records it in
+//! the envelope's `exp` field and binds it to the ciphertext as an encryption context value
+//! under [`CONTEXT_KEY`]. Decryption reads the expiry back from the envelope into the context,
+//! so an envelope whose `exp` was changed or removed fails to decrypt instead of extending the
+//! value's life. The expiry is not enforced by ZeroKMS: with the `enforce_expiry` client option,
+//! decryption refuses envelopes whose expiry has passed before making any request.
+
+use crate::Error;
+use cipherstash_client::zerokms;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The encryption context key an expiry is bound to the ciphertext under.
+pub const CONTEXT_KEY: &str = "protect.expires_at";
+
+/// The encryption context entry binding an expiry to a ciphertext.
+pub fn context(expires_at: u64) -> zerokms::Context {
+    zerokms::Context::new_value(CONTEXT_KEY, &expires_at.to_string())
+}
+
+/// The current time, in seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// Check that a value has not expired.
+///
+/// # Errors
+///
+/// Returns [`Error::Expired`] if the expiry is at or before `now`.
+pub fn check(expires_at: u64, now: u64) -> Result<(), Error> {
+    if expires_at <= now {
+        return Err(Error::Expired { expires_at });
+    }
+
+    Ok(())
+}
+
+/// Check that envelopes of a version can record an expiry.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidEnvelope`] for version 1, whose layout has no `exp` field.
+pub fn check_version(version: u16) -> Result<(), Error> {
+    if version < 2 {
+        return Err(Error::InvalidEnvelope(format!(
+            "version {version} envelopes cannot record `exp`, so values with `expires_at` require `emit_version` 2"
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_refuses_expired_values() {
+        assert!(check(1_767_225_600, 1_767_225_599).is_ok());
+        assert!(matches!(
+            check(1_767_225_600, 1_767_225_600),
+            Err(Error::Expired {
+                expires_at: 1_767_225_600
+            })
+        ));
+        assert!(check_version(1).is_err());
+        assert!(check_version(2).is_ok());
+    }
+}
@@ -0,0 +1,139 @@
+//! Validation and output formatting for `real` and `double` column plaintexts.
+
+use crate::Error;
+use serde::{Deserialize, Serialize};
+
+/// Notation used when formatting decrypted floating-point values.
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Notation {
+    /// Positional notation, such as `1500.25`.
+    #[default]
+    Decimal,
+    /// Scientific notation, such as `1.50025e3`.
+    Scientific,
+}
+
+/// Output formatting options for a `real` or `double` column.
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct FloatFormat {
+    /// Notation used for decrypted values.
+    #[serde(default)]
+    pub notation: Notation,
+    /// Number of digits after the decimal point. When absent, the shortest representation that
+    /// parses back to the identical value is used.
+    #[serde(default)]
+    pub precision: Option<usize>,
+}
+
+/// Parse a floating-point plaintext, rejecting values that cannot round-trip.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidFloat`] if the plaintext is not a number, or is NaN or infinite.
+pub fn parse(plaintext: &str) -> Result<f64, Error> {
+    let value: f64 = plaintext
+        .trim()
+        .parse()
+        .map_err(|_| Error::InvalidFloat("the plaintext is not a number"))?;
+
+    if !value.is_finite() {
+        return Err(Error::InvalidFloat(
+            "NaN and infinite values are not supported",
+        ));
+    }
+
+    Ok(value)
+}
+
+/// Format a decrypted floating-point plaintext.
+///
+/// Without a `precision`, the output parses back to the same `f64` as the plaintext that was
+/// encrypted, which is the value PHP supplied.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidFloat`] if the plaintext is not a finite number.
+pub fn format(plaintext: &str, format: &FloatFormat) -> Result<String, Error> {
+    let value = parse(plaintext)?;
+
+    Ok(match (format.notation, format.precision) {
+        (Notation::Decimal, None) => format!("{value}"),
+        (Notation::Decimal, Some(precision)) => format!("{value:.precision$}"),
+        (Notation::Scientific, None) => format!("{value:e}"),
+        (Notation::Scientific, Some(precision)) => format!("{value:.precision$e}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROUND_TRIP_VALUES: &[&str] = &[
+        "0.1",
+        "-0.0",
+        "1.7976931348623157e308",
+        "5e-324",
+        "123456789.123456789",
+        "0.30000000000000004",
+        "1e21",
+    ];
+
+    #[test]
+    fn test_parse_rejects_non_finite_values() {
+        for plaintext in ["NaN", "nan", "inf", "-Infinity", "1e309"] {
+            let result = parse(plaintext);
+            assert!(matches!(result, Err(Error::InvalidFloat(_))), "{plaintext}");
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_non_numbers() {
+        assert!(matches!(parse("1.5kg"), Err(Error::InvalidFloat(_))));
+        assert!(matches!(parse(""), Err(Error::InvalidFloat(_))));
+    }
+
+    #[test]
+    fn test_default_format_round_trips() {
+        for notation in [Notation::Decimal, Notation::Scientific] {
+            let float_format = FloatFormat {
+                notation,
+                precision: None,
+            };
+
+            for plaintext in ROUND_TRIP_VALUES {
+                let formatted = format(plaintext, &float_format).unwrap();
+                let original: f64 = plaintext.parse().unwrap();
+                let restored: f64 = formatted.parse().unwrap();
+
+                assert_eq!(original.to_bits(), restored.to_bits(), "{plaintext}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_format_with_precision() {
+        let decimal = FloatFormat {
+            notation: Notation::Decimal,
+            precision: Some(2),
+        };
+        let scientific = FloatFormat {
+            notation: Notation::Scientific,
+            precision: Some(3),
+        };
+
+        assert_eq!(format("1500.256", &decimal).unwrap(), "1500.26");
+        assert_eq!(format("1500.256", &scientific).unwrap(), "1.500e3");
+        assert_eq!(format("1.50", &FloatFormat::default()).unwrap(), "1.5");
+    }
+
+    #[test]
+    fn test_format_deserializes_with_defaults() {
+        let float_format: FloatFormat = serde_json::from_str("{}").unwrap();
+        assert_eq!(float_format, FloatFormat::default());
+
+        let result = serde_json::from_str::<FloatFormat>(r#"{"digits": 2}"#);
+        assert!(result.is_err());
+    }
+}
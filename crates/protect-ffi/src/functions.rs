@@ -0,0 +1,333 @@
+//! A manifest of the library's exported functions.
+//!
+//! Wrappers that support several releases of the library can check which functions the loaded
+//! library exports, with the parameters each takes and the release that added it, instead of
+//! parsing its version string. The manifest is read from the C header the library is built with,
+//! so it lists exactly the functions the header declares, which a test checks against the
+//! exported functions.
+
+use serde::Serialize;
+
+/// The C header declaring the exported functions.
+const HEADER: &str = include_str!("../../../include/protectphp.h");
+
+/// The exported functions added in each release, oldest first. Every function the header
+/// declares must be listed under the release that added it.
+const RELEASES: &[(&str, &[&str])] = &[
+    (
+        "0.1.0",
+        &[
+            "new_client",
+            "encrypt",
+            "decrypt",
+            "encrypt_bulk",
+            "decrypt_bulk",
+            "create_search_terms",
+            "free_client",
+            "free_string",
+        ],
+    ),
+    (
+        "0.2.0",
+        &[
+            "protect_ffi_abi_version",
+            "selfcheck",
+            "new_client_with_options",
+            "encrypt_bulk_with_options",
+            "create_search_terms_with_options",
+            "format_envelope",
+            "validate_envelope",
+            "convert_envelope",
+            "upgrade_envelope",
+            "reindex_search_terms",
+            "get_key_info",
+            "export_client_state",
+            "assert_config_matches",
+            "import_client_state",
+            "encrypt_json_paths",
+            "decrypt_json_paths",
+            "explode_envelope",
+            "assemble_envelope",
+            "run_benchmark",
+            "estimate_storage",
+            "plan_encrypt",
+            "get_client_warnings",
+            "validate_config",
+            "get_config_digest",
+            "get_startup_diagnostics",
+            "get_metrics",
+            "clear_search_term_cache",
+            "ping_cts",
+            "ping_zerokms",
+            "get_network_policy",
+            "decrypt_single_field",
+            "tokenize_selector",
+            "explain_query_capability",
+            "compare_terms",
+            "derive_client",
+            "expand_context",
+            "encrypt_async",
+            "decrypt_async",
+            "job_poll",
+            "job_result",
+            "job_on_complete",
+            "job_free",
+            "get_last_error",
+            "get_last_error_chain",
+            "get_last_error_code",
+            "get_last_error_request_id",
+            "get_last_error_retry_hint",
+            "get_error_catalog",
+            "list_functions",
+            "clear_last_error",
+            "set_invalid_pointer_handler",
+            "set_deprecation_handler",
+            "set_crash_report_path",
+        ],
+    ),
+];
+
+/// The deprecated names of renamed functions, each with the function replacing it. Each is
+/// exported by a `deprecated_alias!` wrapper and declared in the header after its replacement.
+const DEPRECATED: &[(&str, &str)] = &[];
+
+/// The manifest returned by `list_functions`.
+#[derive(Debug, Serialize)]
+pub struct Manifest {
+    /// The version of the library.
+    pub library_version: &'static str,
+    /// The version of the library's exported functions and their calling conventions.
+    pub abi_version: u32,
+    /// The exported functions, in the order the header declares them.
+    pub functions: Vec<Function>,
+}
+
+/// An exported function.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Function {
+    /// The function's name.
+    pub name: &'static str,
+    /// The C type the function returns.
+    pub returns: &'static str,
+    /// The function's parameters, in order.
+    pub parameters: Vec<Parameter>,
+    /// The release that added the function.
+    pub since: &'static str,
+    /// The function replacing the function, if it is a deprecated name of a renamed function.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replaced_by: Option<&'static str>,
+}
+
+/// A parameter of an exported function.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Parameter {
+    /// The parameter's name.
+    pub name: &'static str,
+    /// The parameter's C type.
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+/// The manifest of the library's exported functions.
+pub fn manifest() -> Manifest {
+    Manifest {
+        library_version: env!("CARGO_PKG_VERSION"),
+        abi_version: crate::abi::ABI_VERSION,
+        functions: HEADER.lines().filter_map(function).collect(),
+    }
+}
+
+/// The function a line of the header declares, if it declares one.
+fn function(line: &'static str) -> Option<Function> {
+    let line = line.trim();
+    if !line.ends_with(");") || line.starts_with("typedef") {
+        return None;
+    }
+
+    let (prefix, rest) = line.split_once('(')?;
+    let name_start = prefix.rfind(['*', ' '])? + 1;
+    let name = &prefix[name_start..];
+    let parameters = &rest[..rest.len() - ");".len()];
+
+    Some(Function {
+        name,
+        returns: prefix[..name_start].trim(),
+        parameters: split_parameters(parameters)
+            .into_iter()
+            .map(parameter)
+            .collect(),
+        since: since(name),
+        replaced_by: DEPRECATED
+            .iter()
+            .find(|(deprecated, _)| *deprecated == name)
+            .map(|(_, replacement)| *replacement),
+    })
+}
+
+/// The release that added a function, or the library's version for a function missing from
+/// [`RELEASES`].
+fn since(name: &str) -> &'static str {
+    RELEASES
+        .iter()
+        .find(|(_, functions)| functions.contains(&name))
+        .map_or(env!("CARGO_PKG_VERSION"), |(release, _)| *release)
+}
+
+/// The parameters of a parameter list, split at commas outside of parentheses.
+fn split_parameters(parameters: &'static str) -> Vec<&'static str> {
+    let parameters = parameters.trim();
+    if parameters.is_empty() || parameters == "void" {
+        return Vec::new();
+    }
+
+    let mut split = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, character) in parameters.char_indices() {
+        match character {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                split.push(parameters[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    split.push(parameters[start..].trim());
+    split
+}
+
+/// A parameter declaration, such as `const char* context_json`, or `void (*handler)(...)` for a
+/// function pointer.
+fn parameter(declaration: &'static str) -> Parameter {
+    if let Some((return_type, rest)) = declaration.split_once("(*") {
+        if let Some((name, arguments)) = rest.split_once(')') {
+            return Parameter {
+                name: name.trim(),
+                ty: format!("{return_type}(*){arguments}"),
+            };
+        }
+    }
+
+    let name_start = declaration.rfind(['*', ' ']).map_or(0, |index| index + 1);
+    Parameter {
+        name: &declaration[name_start..],
+        ty: declaration[..name_start].trim().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest() {
+        let manifest = manifest();
+
+        let encrypt = manifest
+            .functions
+            .iter()
+            .find(|function| function.name == "encrypt")
+            .unwrap();
+        assert_eq!(encrypt.returns, "char*");
+        assert_eq!(
+            encrypt
+                .parameters
+                .iter()
+                .map(|parameter| (parameter.name, parameter.ty.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                ("client", "const Client*"),
+                ("plaintext", "const char*"),
+                ("column", "const char*"),
+                ("table", "const char*"),
+                ("context_json", "const char*"),
+                ("error_out", "char**"),
+            ]
+        );
+        assert_eq!(encrypt.since, "0.1.0");
+
+        let list_functions = manifest
+            .functions
+            .iter()
+            .find(|function| function.name == "list_functions")
+            .unwrap();
+        assert_eq!(list_functions.since, "0.2.0");
+    }
+
+    #[test]
+    fn test_releases_match_header() {
+        let declared: Vec<&str> = manifest()
+            .functions
+            .iter()
+            .map(|function| function.name)
+            .collect();
+        let released: Vec<&str> = RELEASES
+            .iter()
+            .flat_map(|(_, functions)| functions.iter().copied())
+            .collect();
+
+        for name in &declared {
+            assert_eq!(
+                released.iter().filter(|released| *released == name).count(),
+                1,
+                "`{name}` must be listed under exactly one release in `RELEASES`"
+            );
+        }
+        for name in &released {
+            assert!(declared.contains(name), "`{name}` is not declared");
+        }
+
+        let version = |release: &str| -> Vec<u32> {
+            release
+                .split('.')
+                .map(|part| part.parse().unwrap())
+                .collect()
+        };
+        let (latest, _) = RELEASES.last().unwrap();
+        assert!(version(latest) <= version(env!("CARGO_PKG_VERSION")));
+    }
+
+    #[test]
+    fn test_deprecated_match_header() {
+        let declared: Vec<&str> = manifest()
+            .functions
+            .iter()
+            .map(|function| function.name)
+            .collect();
+        let position = |name: &str| declared.iter().position(|function| *function == name);
+
+        for &(deprecated, replacement) in DEPRECATED {
+            let deprecated_position = position(deprecated);
+            let replacement_position = position(replacement);
+            assert!(
+                deprecated_position.is_some(),
+                "`{deprecated}` is not declared"
+            );
+            assert!(
+                replacement_position.is_some(),
+                "`{replacement}` is not declared"
+            );
+            assert!(
+                deprecated_position > replacement_position,
+                "`{deprecated}` must be declared after `{replacement}`"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parameter() {
+        assert_eq!(
+            parameter("void (*handler)(const char* message)"),
+            Parameter {
+                name: "handler",
+                ty: "void (*)(const char* message)".to_string()
+            }
+        );
+        assert_eq!(
+            function("uint32_t protect_ffi_abi_version(void);").map(|function| function.parameters),
+            Some(Vec::new())
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! Text encodings for index terms.
+//!
+//! Index terms are hex encoded by default, as EQL expects. Base64 encoding shortens `hm` and
+//! `ob` terms and `ste_vec` selectors and terms by nearly a third, which reduces row size for
+//! heavily indexed tables that store index terms outside EQL, such as with the `mysql` output
+//! profile.
+//!
+//! Base64 terms carry a [`BASE64_PREFIX`] so that reading a term never has to guess its
+//! encoding: a base64 term made only of hex digits, such as `deadbeef`, would otherwise decode
+//! as hex into different bytes.
+
+use crate::Error;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Deserialize;
+
+/// Prefix marking a base64 index term. Unprefixed terms are hex.
+pub const BASE64_PREFIX: &str = "b64:";
+
+/// Text encoding of index term bytes.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexEncoding {
+    /// Lowercase hexadecimal, as expected by EQL.
+    #[default]
+    Hex,
+    /// Standard base64 with padding, after [`BASE64_PREFIX`].
+    Base64,
+}
+
+impl IndexEncoding {
+    /// Encode index term bytes.
+    pub fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            Self::Hex => hex::encode(bytes),
+            Self::Base64 => format!("{BASE64_PREFIX}{}", STANDARD.encode(bytes)),
+        }
+    }
+}
+
+/// Decode an index term in either encoding, or `None` if it is not a valid term.
+///
+/// Terms starting with [`BASE64_PREFIX`] are decoded as base64 and all others as hex, so terms
+/// written in either encoding are accepted wherever index terms are read back.
+pub fn decode(term: &str) -> Option<Vec<u8>> {
+    match term.strip_prefix(BASE64_PREFIX) {
+        Some(base64_term) => STANDARD.decode(base64_term).ok(),
+        None => hex::decode(term).ok(),
+    }
+}
+
+/// Compare two index terms in either encoding, in time that depends only on their decoded
+/// lengths.
+///
+/// Terms of the same index always have the same length, so comparing them reveals nothing
+/// about where two terms first differ, unlike comparing their strings.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidIndexTerm`] naming the first term that is not a valid term.
+pub fn terms_equal(term_a: &str, term_b: &str) -> Result<bool, Error> {
+    let a = decode(term_a).ok_or(Error::InvalidIndexTerm("term_a"))?;
+    let b = decode(term_b).ok_or(Error::InvalidIndexTerm("term_b"))?;
+
+    if a.len() != b.len() {
+        return Ok(false);
+    }
+
+    let difference = a
+        .iter()
+        .zip(&b)
+        .fold(0u8, |difference, (x, y)| difference | (x ^ y));
+
+    // Keep the compiler from turning the fold into a comparison that exits early.
+    Ok(std::hint::black_box(difference) == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_and_decode_both_encodings() {
+        let bytes = [0xf3, 0xca, 0x71, 0xfd, 0x39];
+
+        let hex_term = IndexEncoding::Hex.encode(&bytes);
+        let base64_term = IndexEncoding::Base64.encode(&bytes);
+
+        assert_eq!(hex_term, "f3ca71fd39");
+        assert_eq!(base64_term, "b64:88px/Tk=");
+        assert_eq!(decode(&hex_term).unwrap(), bytes);
+        assert_eq!(decode(&base64_term).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_base64_is_shorter_than_hex() {
+        let bytes = [0u8; 32];
+
+        assert_eq!(IndexEncoding::Hex.encode(&bytes).len(), 64);
+        assert_eq!(IndexEncoding::Base64.encode(&bytes).len(), 48);
+    }
+
+    #[test]
+    fn test_terms_equal_across_encodings() {
+        assert!(terms_equal("f3ca71fd39", "F3CA71FD39").unwrap());
+        assert!(terms_equal("f3ca71fd39", "b64:88px/Tk=").unwrap());
+        assert!(!terms_equal("f3ca71fd39", "f3ca71fd38").unwrap());
+        assert!(!terms_equal("f3ca71fd39", "f3ca71fd").unwrap());
+        assert!(matches!(
+            terms_equal("f3ca71fd39", "not a term!"),
+            Err(Error::InvalidIndexTerm("term_b"))
+        ));
+    }
+
+    #[test]
+    fn test_decode_does_not_guess_encoding() {
+        let bytes = STANDARD.decode("deadbeef").unwrap();
+
+        assert_eq!(decode("b64:deadbeef").unwrap(), bytes);
+        assert_eq!(decode("deadbeef").unwrap(), [0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(decode("88px/Tk="), None);
+    }
+
+    #[test]
+    fn test_decode_rejects_other_text() {
+        assert_eq!(decode("not an index term!"), None);
+        assert_eq!(decode("b64:not base64!"), None);
+    }
+}
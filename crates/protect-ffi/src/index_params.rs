@@ -0,0 +1,343 @@
+//! Index term parameters recorded in version 3 envelopes.
+//!
+//! Version 2 envelopes have one `v` for the whole envelope, so a change to one index, such as a
+//! new bloom filter size or ORE scheme, can only be adopted by re-encrypting every row. Version 3
+//! envelopes add an `ix` object recording, for each index term, the version of the index scheme
+//! and the parameters it was created with: `k`, `m`, and the tokenizer fingerprint `t` for `bf`
+//! terms and the ORE scheme `s` for `ob` terms. Rows written under old and new parameters can
+//! then coexist, and each term is checked against the column's configuration on its own, both
+//! when upgrading envelopes and when creating search terms for rows with recorded parameters.
+
+use crate::encrypt_config::Identifier;
+use crate::normalization::IndexNormalization;
+use crate::Error;
+use cipherstash_client::schema::{
+    column::{IndexType, TokenFilter, Tokenizer},
+    ColumnConfig,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Version of the index schemes this library creates terms with.
+pub const CURRENT_TERM_VERSION: u16 = 1;
+
+/// The ORE scheme of `ob` terms: 64-bit block ORE with 8 blocks of 256 values.
+pub const ORE_SCHEME: &str = "ore_block_u64_8_256";
+
+/// The parameters of a column without indexes.
+pub const UNINDEXED: IndexParams = IndexParams {
+    unique: None,
+    ore: None,
+    r#match: None,
+    ste_vec: None,
+};
+
+/// The parameters of each index term of a value, the `ix` field of version 3 envelopes.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct IndexParams {
+    /// Parameters of the `hm` term.
+    #[serde(rename = "hm", default, skip_serializing_if = "Option::is_none")]
+    pub unique: Option<TermParams>,
+    /// Parameters of the `ob` terms.
+    #[serde(rename = "ob", default, skip_serializing_if = "Option::is_none")]
+    pub ore: Option<TermParams>,
+    /// Parameters of the `bf` terms.
+    #[serde(rename = "bf", default, skip_serializing_if = "Option::is_none")]
+    pub r#match: Option<TermParams>,
+    /// Parameters of the `sv` entries.
+    #[serde(rename = "sv", default, skip_serializing_if = "Option::is_none")]
+    pub ste_vec: Option<TermParams>,
+}
+
+/// The version and parameters of the scheme an index term was created with.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TermParams {
+    /// Version of the index scheme.
+    #[serde(rename = "v")]
+    pub version: u16,
+    /// Number of hash functions of a bloom filter.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub k: Option<usize>,
+    /// Size of a bloom filter in bits.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub m: Option<usize>,
+    /// Fingerprint of the tokenizer, token filters, and text preparation of a bloom filter.
+    #[serde(rename = "t", default, skip_serializing_if = "Option::is_none")]
+    pub tokenizer: Option<String>,
+    /// Identifier of the ORE scheme.
+    #[serde(rename = "s", default, skip_serializing_if = "Option::is_none")]
+    pub scheme: Option<String>,
+}
+
+impl TermParams {
+    fn current() -> Self {
+        Self {
+            version: CURRENT_TERM_VERSION,
+            k: None,
+            m: None,
+            tokenizer: None,
+            scheme: None,
+        }
+    }
+}
+
+impl IndexParams {
+    /// The parameters the terms of a column's configured indexes are created with, given the
+    /// normalization of the column's text policy.
+    pub fn for_column(column_config: &ColumnConfig, normalization: &IndexNormalization) -> Self {
+        let mut params = Self::default();
+
+        for index in &column_config.indexes {
+            match &index.index_type {
+                IndexType::Unique { .. } => params.unique = Some(TermParams::current()),
+                IndexType::Ore => {
+                    params.ore = Some(TermParams {
+                        scheme: Some(ORE_SCHEME.to_string()),
+                        ..TermParams::current()
+                    })
+                }
+                IndexType::Match {
+                    tokenizer,
+                    token_filters,
+                    k,
+                    m,
+                    include_original,
+                } => {
+                    params.r#match = Some(TermParams {
+                        k: Some(*k),
+                        m: Some(*m),
+                        tokenizer: Some(tokenizer_fingerprint(
+                            tokenizer,
+                            token_filters,
+                            *include_original,
+                            normalization,
+                        )),
+                        ..TermParams::current()
+                    })
+                }
+                IndexType::SteVec { .. } => params.ste_vec = Some(TermParams::current()),
+            }
+        }
+
+        params
+    }
+
+    /// The parameters of the term recorded in an envelope field, such as `bf`.
+    pub fn term(&self, field: &str) -> Option<&TermParams> {
+        match field {
+            "hm" => self.unique.as_ref(),
+            "ob" => self.ore.as_ref(),
+            "bf" => self.r#match.as_ref(),
+            "sv" => self.ste_vec.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Why each recorded term cannot be searched with terms created with the current
+    /// parameters of its column, naming the term's field.
+    ///
+    /// A term is incompatible if its scheme version is newer than this library supports, or
+    /// its parameters differ from those of the column's index. Terms of indexes the column no
+    /// longer configures are not compared.
+    pub fn incompatibilities(&self, current: &IndexParams) -> Vec<String> {
+        self.incompatible_terms(current)
+            .into_iter()
+            .map(|(_, reason)| reason)
+            .collect()
+    }
+
+    /// Check that search terms created with the current parameters of a column can match rows
+    /// whose terms were created with these parameters, comparing only the term in `field` when
+    /// a search compares a single term.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::IncompatibleIndexParams`] with why each compared term is incompatible.
+    pub fn check_searchable(
+        &self,
+        current: &IndexParams,
+        field: Option<&str>,
+        identifier: &Identifier,
+    ) -> Result<(), Error> {
+        let reasons: Vec<String> = self
+            .incompatible_terms(current)
+            .into_iter()
+            .filter(|(term, _)| field.is_none_or(|field| field == *term))
+            .map(|(_, reason)| reason)
+            .collect();
+
+        if reasons.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::IncompatibleIndexParams(
+                identifier.clone(),
+                reasons.join("; "),
+            ))
+        }
+    }
+
+    /// The field of each recorded term that is incompatible with the current parameters of its
+    /// column, with why.
+    pub fn incompatible_terms(&self, current: &IndexParams) -> Vec<(&'static str, String)> {
+        let mut reasons = Vec::new();
+
+        for (field, recorded, configured) in [
+            ("hm", &self.unique, &current.unique),
+            ("ob", &self.ore, &current.ore),
+            ("bf", &self.r#match, &current.r#match),
+            ("sv", &self.ste_vec, &current.ste_vec),
+        ] {
+            let (Some(recorded), Some(configured)) = (recorded, configured) else {
+                continue;
+            };
+
+            if recorded.version > CURRENT_TERM_VERSION {
+                reasons.push((field, format!(
+                    "`{field}` term was created with index version {}, newer than the supported version {CURRENT_TERM_VERSION}",
+                    recorded.version
+                )));
+            } else if recorded != configured {
+                reasons.push((
+                    field,
+                    format!(
+                        "`{field}` term was created with {}, but the column's index uses {}",
+                        recorded.describe(),
+                        configured.describe()
+                    ),
+                ));
+            }
+        }
+
+        reasons
+    }
+}
+
+impl TermParams {
+    /// The parameters as `name=value` pairs, for messages.
+    fn describe(&self) -> String {
+        let mut parts = vec![format!("v={}", self.version)];
+        if let Some(k) = self.k {
+            parts.push(format!("k={k}"));
+        }
+        if let Some(m) = self.m {
+            parts.push(format!("m={m}"));
+        }
+        if let Some(tokenizer) = &self.tokenizer {
+            parts.push(format!("t={tokenizer}"));
+        }
+        if let Some(scheme) = &self.scheme {
+            parts.push(format!("s={scheme}"));
+        }
+        parts.join(", ")
+    }
+}
+
+/// A fingerprint of everything that decides the tokens a `match` index adds to its bloom
+/// filter: the tokenizer, token filters, and whether the original value is included, with the
+/// Unicode normalization and edge n-gram mode the text is prepared with.
+///
+/// The fingerprint is the first 8 bytes of a SHA-256 digest of a canonical description, in hex,
+/// so it is stable across processes and versions of this library.
+fn tokenizer_fingerprint(
+    tokenizer: &Tokenizer,
+    token_filters: &[TokenFilter],
+    include_original: bool,
+    normalization: &IndexNormalization,
+) -> String {
+    let edge_ngrams = normalization.match_edge_ngrams.map(|edge_ngrams| {
+        serde_json::json!({
+            "words": edge_ngrams.words,
+            "min_length": edge_ngrams.min_length,
+            "max_length": edge_ngrams.max_length,
+        })
+    });
+    let description = serde_json::json!({
+        "tokenizer": tokenizer,
+        "token_filters": token_filters,
+        "include_original": include_original,
+        "unicode_normalization": normalization.r#match,
+        "edge_ngrams": edge_ngrams,
+    });
+
+    let digest = Sha256::digest(description.to_string().as_bytes());
+    hex::encode(&digest[..8])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cipherstash_client::schema::column::Index;
+    use cipherstash_client::schema::ColumnType;
+
+    fn params(k: usize, m: usize, tokenizer: Tokenizer) -> IndexParams {
+        let column_config = ColumnConfig::build("email".to_string())
+            .casts_as(ColumnType::Utf8Str)
+            .add_index(Index::new_ore())
+            .add_index(Index::new(IndexType::Match {
+                tokenizer,
+                token_filters: Vec::new(),
+                k,
+                m,
+                include_original: false,
+            }));
+
+        IndexParams::for_column(&column_config, &IndexNormalization::default())
+    }
+
+    #[test]
+    fn test_params_for_column() {
+        let params = params(6, 2048, Tokenizer::Standard);
+        let fingerprint = params.r#match.as_ref().unwrap().tokenizer.clone().unwrap();
+
+        assert_eq!(fingerprint.len(), 16);
+        assert_eq!(
+            serde_json::to_value(&params).unwrap(),
+            serde_json::json!({
+                "ob": {"v": 1, "s": ORE_SCHEME},
+                "bf": {"v": 1, "k": 6, "m": 2048, "t": fingerprint}
+            })
+        );
+        assert!(params.unique.is_none());
+        assert_eq!(params.term("ob"), params.ore.as_ref());
+    }
+
+    #[test]
+    fn test_incompatibilities() {
+        let recorded = params(6, 2048, Tokenizer::Standard);
+        assert!(recorded
+            .incompatibilities(&params(6, 2048, Tokenizer::Standard))
+            .is_empty());
+
+        let current = params(8, 4096, Tokenizer::Standard);
+        let fingerprint = current.r#match.as_ref().unwrap().tokenizer.clone().unwrap();
+        assert_eq!(
+            recorded.incompatibilities(&current),
+            [format!("`bf` term was created with v=1, k=6, m=2048, t={fingerprint}, but the column's index uses v=1, k=8, m=4096, t={fingerprint}")]
+        );
+
+        let current = params(6, 2048, Tokenizer::Ngram { token_length: 3 });
+        let terms = recorded.incompatible_terms(&current);
+        assert_eq!(terms.len(), 1);
+        assert_eq!(terms[0].0, "bf");
+
+        let identifier = Identifier::new("users", "email");
+        assert!(recorded
+            .check_searchable(&current, Some("ob"), &identifier)
+            .is_ok());
+        assert!(matches!(
+            recorded.check_searchable(&current, Some("bf"), &identifier),
+            Err(Error::IncompatibleIndexParams(..))
+        ));
+
+        let newer = IndexParams {
+            ore: Some(TermParams {
+                version: CURRENT_TERM_VERSION + 1,
+                ..TermParams::current()
+            }),
+            ..IndexParams::default()
+        };
+        assert_eq!(newer.incompatibilities(&recorded).len(), 1);
+    }
+}
@@ -0,0 +1,158 @@
+//! Environment variable interpolation in encryption configurations.
+//!
+//! A string in a configuration can reference an environment variable as `${NAME}`, or as
+//! `${NAME:-fallback}` to use `fallback` when the variable is unset or empty, so one file can
+//! serve every environment, such as a `ste_vec` prefix or keyset ID per deployment. References
+//! are resolved when the configuration is parsed, and a variable that is unset and has no
+//! fallback is an error. `$${` is a literal `${`.
+//!
+//! Interpolation runs on the JSON text of the configuration, after YAML and TOML are converted.
+//! `$` cannot appear in JSON outside a string, so every reference is inside a string, and the
+//! value of a variable is escaped as JSON string content before it replaces the reference.
+
+use crate::Error;
+use std::borrow::Cow;
+
+/// The configuration JSON with each `${NAME}` reference replaced by its environment variable.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidInterpolation`] if a reference is not terminated, names an invalid
+/// variable, or names a variable that is unset and has no fallback.
+pub fn interpolate(json: &str) -> Result<Cow<'_, str>, Error> {
+    interpolate_with(json, |name| std::env::var(name).ok())
+}
+
+fn interpolate_with(
+    json: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<Cow<'_, str>, Error> {
+    if !json.contains("${") {
+        return Ok(Cow::Borrowed(json));
+    }
+
+    let mut output = String::with_capacity(json.len());
+    let mut rest = json;
+
+    while let Some(start) = rest.find('$') {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        if let Some(escaped) = after.strip_prefix("${") {
+            output.push_str("${");
+            rest = escaped;
+            continue;
+        }
+        let Some(reference) = after.strip_prefix('{') else {
+            output.push('$');
+            rest = after;
+            continue;
+        };
+
+        let end = reference.find('}').ok_or_else(|| {
+            Error::InvalidInterpolation("`${` is not closed with `}`".to_string())
+        })?;
+        let (name, fallback) = match reference[..end].split_once(":-") {
+            Some((name, fallback)) => (name, Some(fallback)),
+            None => (&reference[..end], None),
+        };
+
+        if !is_variable_name(name) {
+            return Err(Error::InvalidInterpolation(format!(
+                "`${{{name}}}` is not a valid environment variable name"
+            )));
+        }
+
+        match (lookup(name), fallback) {
+            (Some(value), None) => output.push_str(&escape(&value)),
+            (Some(value), Some(_)) if !value.is_empty() => output.push_str(&escape(&value)),
+            (_, Some(fallback)) => output.push_str(fallback),
+            (None, None) => {
+                return Err(Error::InvalidInterpolation(format!(
+                "environment variable `{name}` is not set, and `${{{name}}}` has no `:-` fallback"
+            )))
+            }
+        }
+
+        rest = &reference[end + 1..];
+    }
+
+    output.push_str(rest);
+    Ok(Cow::Owned(output))
+}
+
+/// Whether a name is a portable environment variable name: letters, digits, and underscores,
+/// not starting with a digit.
+fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// A value escaped as the content of a JSON string.
+fn escape(value: &str) -> String {
+    let quoted = serde_json::Value::String(value.to_string()).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "PREFIX" => Some("prod".to_string()),
+            "EMPTY" => Some(String::new()),
+            "QUOTED" => Some("a\"b\\c".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_interpolate_references() {
+        for (json, expected) in [
+            (
+                r#"{"prefix": "users/email"}"#,
+                r#"{"prefix": "users/email"}"#,
+            ),
+            (
+                r#"{"prefix": "${PREFIX}/email"}"#,
+                r#"{"prefix": "prod/email"}"#,
+            ),
+            (
+                r#"{"prefix": "${UNSET:-dev}/email"}"#,
+                r#"{"prefix": "dev/email"}"#,
+            ),
+            (r#"{"prefix": "${PREFIX:-dev}"}"#, r#"{"prefix": "prod"}"#),
+            (r#"{"prefix": "${EMPTY:-dev}"}"#, r#"{"prefix": "dev"}"#),
+            (r#"{"prefix": "${EMPTY}"}"#, r#"{"prefix": ""}"#),
+            (r#"{"prefix": "${QUOTED}"}"#, r#"{"prefix": "a\"b\\c"}"#),
+            (
+                r#"{"prefix": "$${PREFIX} costs $5"}"#,
+                r#"{"prefix": "${PREFIX} costs $5"}"#,
+            ),
+        ] {
+            assert_eq!(interpolate_with(json, lookup).unwrap(), expected, "{json}");
+        }
+    }
+
+    #[test]
+    fn test_interpolate_rejects_invalid_references() {
+        for json in [
+            r#"{"prefix": "${UNSET}"}"#,
+            r#"{"prefix": "${PREFIX"}"#,
+            r#"{"prefix": "${1PREFIX}"}"#,
+            r#"{"prefix": "${}"}"#,
+        ] {
+            assert!(
+                matches!(
+                    interpolate_with(json, lookup),
+                    Err(Error::InvalidInterpolation(_))
+                ),
+                "{json}"
+            );
+        }
+    }
+}
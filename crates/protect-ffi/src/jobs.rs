@@ -0,0 +1,289 @@
+//! Background jobs for non-blocking FFI calls.
+//!
+//! `encrypt_async` and `decrypt_async` spawn their operation on the shared runtime and return a
+//! job handle at once, so PHP event loops such as Swoole, ReactPHP, or Fibers keep serving other
+//! work while ZeroKMS responds, instead of blocking a worker thread in `block_on`. The caller
+//! either polls the job with `job_poll` and takes its result with `job_result`, or registers a
+//! completion callback with `job_on_complete`, and frees it with `job_free`. A job holds its own
+//! clone of the client, which shares the client's ciphers and connections, so the client may be
+//! freed while its jobs are running.
+
+use crate::{safe_ffi, Error};
+use std::ffi::{c_char, c_void};
+use std::future::Future;
+use std::ptr;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tokio::runtime::Runtime;
+use tokio::task::JoinHandle;
+
+/// `job_poll` status of a job that is still running.
+pub const JOB_PENDING: i32 = 0;
+
+/// `job_poll` status of a job whose result is ready.
+pub const JOB_DONE: i32 = 1;
+
+/// `job_poll` status of an invalid job, with the error in `error_out`.
+pub const JOB_ERROR: i32 = -1;
+
+/// Callback invoked when a job completes, with the caller's `user_data` and either the job's
+/// result or its error message. Exactly one of `result` and `error` is non-null, and the callback
+/// owns it and must free it with `free_string`.
+pub type JobCallback =
+    extern "C" fn(user_data: *mut c_void, result: *mut c_char, error: *mut c_char);
+
+/// A registered completion callback.
+struct Completion {
+    callback: JobCallback,
+    /// The caller's opaque pointer, stored as an address so the job can move between threads.
+    user_data: usize,
+}
+
+impl Completion {
+    /// Deliver a result to the callback, transferring ownership of the C string.
+    fn deliver(self, result: Result<String, Error>) {
+        let user_data = self.user_data as *mut c_void;
+        let delivered = result.and_then(safe_ffi::string_to_c_string);
+
+        match delivered {
+            Ok(result) => (self.callback)(user_data, result, ptr::null_mut()),
+            Err(error) => {
+                let error =
+                    safe_ffi::string_to_c_string(error.to_string()).unwrap_or(ptr::null_mut());
+                (self.callback)(user_data, ptr::null_mut(), error)
+            }
+        }
+    }
+}
+
+/// The progress of a job.
+enum State {
+    /// The operation is running, and delivers its result to the callback when one is set.
+    Running(Option<Completion>),
+    /// The operation finished and its result is waiting to be taken.
+    Done(Result<String, Error>),
+    /// The result was taken by `job_result`.
+    Taken,
+    /// The result was delivered to the completion callback.
+    Delivered,
+}
+
+/// An operation running on the shared runtime.
+pub struct Job {
+    state: Arc<Mutex<State>>,
+    task: JoinHandle<()>,
+}
+
+impl Job {
+    /// Spawn an operation on the runtime.
+    pub fn spawn<F>(runtime: &Runtime, operation: F) -> Self
+    where
+        F: Future<Output = Result<String, Error>> + Send + 'static,
+    {
+        let state = Arc::new(Mutex::new(State::Running(None)));
+        let task_state = state.clone();
+
+        let task = runtime.spawn(async move {
+            let result = operation.await;
+
+            let mut state = lock(&task_state);
+            match std::mem::replace(&mut *state, State::Delivered) {
+                State::Running(Some(completion)) => {
+                    drop(state);
+                    completion.deliver(result);
+                }
+                _ => *state = State::Done(result),
+            }
+        });
+
+        Self { state, task }
+    }
+
+    /// Whether the job's result is ready.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidJob`] if the result has already been taken or delivered.
+    pub fn is_done(&self) -> Result<bool, Error> {
+        match &*lock(&self.state) {
+            State::Running(_) => Ok(self.task.is_finished()),
+            State::Done(_) => Ok(true),
+            State::Taken => Err(Self::taken()),
+            State::Delivered => Err(Self::delivered()),
+        }
+    }
+
+    /// Take the job's result, which can only be taken once.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidJob`] if the job is still running or its result has already been
+    /// taken or delivered, the error the operation failed with, or [`Error::Runtime`] if it
+    /// panicked.
+    pub fn take_result(&mut self) -> Result<String, Error> {
+        let mut state = lock(&self.state);
+        match std::mem::replace(&mut *state, State::Taken) {
+            State::Done(result) => result,
+            State::Running(completion) if self.task.is_finished() => {
+                drop(completion);
+                Err(Error::Runtime("the job's operation panicked".to_string()))
+            }
+            State::Running(completion) => {
+                *state = State::Running(completion);
+                Err(Error::InvalidJob(
+                    "the job is still running, poll it until it is done".to_string(),
+                ))
+            }
+            State::Taken => Err(Self::taken()),
+            State::Delivered => {
+                *state = State::Delivered;
+                Err(Self::delivered())
+            }
+        }
+    }
+
+    /// Register the callback the job's result is delivered to. When the job has already
+    /// finished, the callback is invoked at once on the calling thread; otherwise it is invoked
+    /// on a runtime thread when the operation completes. Returns whether the callback was
+    /// invoked at once.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidJob`] if the job already has a callback, or its result has
+    /// already been taken or delivered.
+    pub fn on_complete(
+        &self,
+        callback: JobCallback,
+        user_data: *mut c_void,
+    ) -> Result<bool, Error> {
+        let completion = Completion {
+            callback,
+            user_data: user_data as usize,
+        };
+
+        let mut state = lock(&self.state);
+        match std::mem::replace(&mut *state, State::Delivered) {
+            State::Running(None) => {
+                *state = State::Running(Some(completion));
+                Ok(false)
+            }
+            State::Done(result) => {
+                drop(state);
+                completion.deliver(result);
+                Ok(true)
+            }
+            State::Running(Some(existing)) => {
+                *state = State::Running(Some(existing));
+                Err(Error::InvalidJob(
+                    "the job already has a completion callback".to_string(),
+                ))
+            }
+            State::Taken => {
+                *state = State::Taken;
+                Err(Self::taken())
+            }
+            State::Delivered => Err(Self::delivered()),
+        }
+    }
+
+    fn taken() -> Error {
+        Error::InvalidJob("the job's result has already been taken".to_string())
+    }
+
+    fn delivered() -> Error {
+        Error::InvalidJob("the job's result was delivered to its completion callback".to_string())
+    }
+}
+
+impl Drop for Job {
+    /// Cancel the operation if it is still running without a callback. Operations with a
+    /// callback keep running, so the job can be freed as soon as its callback is registered.
+    fn drop(&mut self) {
+        if matches!(&*lock(&self.state), State::Running(None)) {
+            self.task.abort();
+        }
+    }
+}
+
+/// Lock a job's state, recovering it if a callback panicked while holding it.
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    extern "C" fn send_result(user_data: *mut c_void, result: *mut c_char, error: *mut c_char) {
+        let sender = unsafe { &*(user_data as *const mpsc::Sender<Result<String, String>>) };
+        let take = |string: *mut c_char| unsafe { CString::from_raw(string) }.into_string();
+
+        let delivered = if result.is_null() {
+            Err(take(error).unwrap())
+        } else {
+            Ok(take(result).unwrap())
+        };
+        sender.send(delivered).unwrap();
+    }
+
+    fn wait_until_done(job: &Job) {
+        while !job.is_done().unwrap() {
+            std::thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn test_job_result_is_taken_once() {
+        let runtime = Runtime::new().unwrap();
+        let mut job = Job::spawn(&runtime, async { Ok("done".to_string()) });
+        wait_until_done(&job);
+
+        assert_eq!(job.take_result().unwrap(), "done");
+        assert!(matches!(job.is_done(), Err(Error::InvalidJob(_))));
+        assert!(matches!(job.take_result(), Err(Error::InvalidJob(_))));
+    }
+
+    #[test]
+    fn test_job_result_is_unavailable_while_running() {
+        let runtime = Runtime::new().unwrap();
+        let mut job = Job::spawn(&runtime, std::future::pending());
+
+        assert!(!job.is_done().unwrap());
+        assert!(matches!(job.take_result(), Err(Error::InvalidJob(_))));
+    }
+
+    #[test]
+    fn test_callback_receives_result_of_running_job() {
+        let runtime = Runtime::new().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let (start, started) = tokio::sync::oneshot::channel::<()>();
+
+        let job = Job::spawn(&runtime, async move {
+            started.await.ok();
+            Err(Error::InvalidJob("failed".to_string()))
+        });
+        job.on_complete(send_result, &sender as *const _ as *mut c_void)
+            .unwrap();
+        drop(job);
+        start.send(()).unwrap();
+
+        let delivered = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(delivered, Err("invalid job: failed".to_string()));
+    }
+
+    #[test]
+    fn test_callback_receives_result_of_finished_job() {
+        let runtime = Runtime::new().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let mut job = Job::spawn(&runtime, async { Ok("done".to_string()) });
+        wait_until_done(&job);
+
+        job.on_complete(send_result, &sender as *const _ as *mut c_void)
+            .unwrap();
+
+        assert_eq!(receiver.try_recv().unwrap(), Ok("done".to_string()));
+        assert!(matches!(job.take_result(), Err(Error::InvalidJob(_))));
+    }
+}
@@ -0,0 +1,305 @@
+//! Paths to fields inside a JSON document, for encrypting selected fields in place.
+//!
+//! Paths use a small JSONPath subset: `$` followed by `.key` and `[index]` segments, such as
+//! `$.patient.ssn` or `$.contacts[0].email`. Filter paths, which select the values a `ste_vec`
+//! index covers, may also use `[*]` for every element of an array, such as `$.tags[*]`.
+
+use crate::encrypt_config::CastAs;
+use crate::Error;
+use serde_json::Value;
+
+/// A single step in a [`JsonPath`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    /// An object member.
+    Key(String),
+    /// An array element.
+    Index(usize),
+    /// Every element of an array, in filter paths.
+    AnyIndex,
+}
+
+impl Segment {
+    /// Whether this segment of a filter path matches a segment of a value's path.
+    fn matches(&self, segment: &Segment) -> bool {
+        matches!((self, segment), (Segment::AnyIndex, Segment::Index(_))) || self == segment
+    }
+}
+
+/// A parsed path to a field inside a JSON document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+impl JsonPath {
+    /// Parse a path such as `$.patient.ssn` or `$.contacts[0].email`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidJsonPath`] if the path does not start with `$`, contains an
+    /// empty key or a malformed index, or selects the whole document.
+    pub fn parse(path: &str) -> Result<Self, Error> {
+        Self::parse_segments(path, false)
+    }
+
+    /// Parse a filter path, which may also select every element of an array with `[*]`, such
+    /// as `$.tags[*]` or `$.contacts[*].email`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidJsonPath`] if the path is malformed, as for [`JsonPath::parse`].
+    pub fn parse_filter(path: &str) -> Result<Self, Error> {
+        Self::parse_segments(path, true)
+    }
+
+    /// Parse a path, accepting `[*]` segments when `wildcards` is set.
+    fn parse_segments(path: &str, wildcards: bool) -> Result<Self, Error> {
+        let invalid = |reason: &str| Error::InvalidJsonPath {
+            path: path.to_string(),
+            reason: reason.to_string(),
+        };
+
+        let mut rest = path
+            .strip_prefix('$')
+            .ok_or_else(|| invalid("expected the path to start with `$`"))?;
+        let mut segments = Vec::new();
+
+        while !rest.is_empty() {
+            if let Some(after_dot) = rest.strip_prefix('.') {
+                let end = after_dot.find(['.', '[']).unwrap_or(after_dot.len());
+                let key = &after_dot[..end];
+                if key.is_empty() {
+                    return Err(invalid("expected a key after `.`"));
+                }
+                segments.push(Segment::Key(key.to_string()));
+                rest = &after_dot[end..];
+            } else if let Some(after_bracket) = rest.strip_prefix('[') {
+                let end = after_bracket
+                    .find(']')
+                    .ok_or_else(|| invalid("expected `]` after an array index"))?;
+                let index = &after_bracket[..end];
+                if wildcards && index == "*" {
+                    segments.push(Segment::AnyIndex);
+                } else {
+                    let index = index
+                        .parse()
+                        .map_err(|_| invalid("expected an unsigned array index"))?;
+                    segments.push(Segment::Index(index));
+                }
+                rest = &after_bracket[end + 1..];
+            } else {
+                return Err(invalid("expected `.` or `[` after a segment"));
+            }
+        }
+
+        if segments.is_empty() {
+            return Err(invalid(
+                "the path selects the whole document, use `encrypt` instead",
+            ));
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// The steps of this path, from the root.
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Whether this path selects the field at `segments`, or a field containing it.
+    pub fn contains(&self, segments: &[Segment]) -> bool {
+        segments.len() >= self.segments.len()
+            && self
+                .segments
+                .iter()
+                .zip(segments)
+                .all(|(own, segment)| own.matches(segment))
+    }
+
+    /// Whether the field at `segments` contains a field this path selects.
+    pub fn is_inside(&self, segments: &[Segment]) -> bool {
+        segments.len() <= self.segments.len()
+            && self
+                .segments
+                .iter()
+                .zip(segments)
+                .all(|(own, segment)| own.matches(segment))
+    }
+
+    /// Whether this path selects the same field as `other`, or a field inside it.
+    pub fn overlaps(&self, other: &JsonPath) -> bool {
+        let shared = self.segments.len().min(other.segments.len());
+        self.segments[..shared] == other.segments[..shared]
+    }
+
+    /// Get a mutable reference to the field selected by this path, if it exists.
+    pub fn get_mut<'a>(&self, document: &'a mut Value) -> Option<&'a mut Value> {
+        self.segments
+            .iter()
+            .try_fold(document, |value, segment| match segment {
+                Segment::Key(key) => value.as_object_mut()?.get_mut(key),
+                Segment::Index(index) => value.as_array_mut()?.get_mut(*index),
+                Segment::AnyIndex => None,
+            })
+    }
+}
+
+/// Parse a list of paths, rejecting paths that select the same field or a field inside another.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidJsonPath`] if any path is malformed or overlaps another path.
+pub fn parse_all<'a>(paths: impl IntoIterator<Item = &'a str>) -> Result<Vec<JsonPath>, Error> {
+    let mut parsed: Vec<(&str, JsonPath)> = Vec::new();
+
+    for path in paths {
+        let json_path = JsonPath::parse(path)?;
+
+        if let Some((other, _)) = parsed.iter().find(|(_, other)| other.overlaps(&json_path)) {
+            return Err(Error::InvalidJsonPath {
+                path: path.to_string(),
+                reason: format!("overlaps with `{other}`"),
+            });
+        }
+
+        parsed.push((path, json_path));
+    }
+
+    Ok(parsed.into_iter().map(|(_, json_path)| json_path).collect())
+}
+
+/// The plaintext to encrypt for a field value, or `None` for `null` fields.
+///
+/// Strings are encrypted as is, while numbers, booleans, objects, and arrays are encrypted as
+/// their JSON text.
+pub fn plaintext_from_value(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(text) => Some(text.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+/// Restore a decrypted field value, using the data type recorded in its envelope to return
+/// numbers, booleans, and JSON values with their original JSON type.
+///
+/// Plaintexts that do not parse as their data type are returned as strings.
+pub fn value_from_plaintext(plaintext: String, data_type: Option<CastAs>) -> Value {
+    let parsed = match data_type {
+        Some(
+            CastAs::SmallInt
+            | CastAs::Int
+            | CastAs::BigInt
+            | CastAs::Real
+            | CastAs::Double
+            | CastAs::Boolean
+            | CastAs::JsonB
+            | CastAs::TextArray
+            | CastAs::IntArray,
+        ) => serde_json::from_str(&plaintext).ok(),
+        _ => None,
+    };
+
+    parsed.unwrap_or(Value::String(plaintext))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_and_get_nested_fields() {
+        let mut document = json!({
+            "patient": {"ssn": "123-45-6789"},
+            "contacts": [{"email": "john@example.com"}]
+        });
+
+        let ssn = JsonPath::parse("$.patient.ssn").unwrap();
+        let email = JsonPath::parse("$.contacts[0].email").unwrap();
+        let missing = JsonPath::parse("$.contacts[1].email").unwrap();
+
+        assert_eq!(ssn.get_mut(&mut document), Some(&mut json!("123-45-6789")));
+        assert_eq!(
+            email.get_mut(&mut document),
+            Some(&mut json!("john@example.com"))
+        );
+        assert_eq!(missing.get_mut(&mut document), None);
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_paths() {
+        for path in [
+            "patient.ssn",
+            "$",
+            "$.",
+            "$..ssn",
+            "$.contacts[x]",
+            "$.contacts[0",
+        ] {
+            let result = JsonPath::parse(path);
+            assert!(
+                matches!(result, Err(Error::InvalidJsonPath { .. })),
+                "{path}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_filter_paths_match_every_element() {
+        let emails = JsonPath::parse_filter("$.contacts[*].email").unwrap();
+        let path = |index| {
+            [
+                Segment::Key("contacts".to_string()),
+                Segment::Index(index),
+                Segment::Key("email".to_string()),
+            ]
+        };
+
+        assert!(emails.contains(&path(0)));
+        assert!(emails.contains(&path(7)));
+        assert!(emails.is_inside(&path(3)[..2]));
+        assert!(!emails.contains(&path(0)[..2]));
+        assert!(matches!(
+            JsonPath::parse("$.contacts[*].email"),
+            Err(Error::InvalidJsonPath { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_all_rejects_overlapping_paths() {
+        assert!(parse_all(["$.patient.ssn", "$.patient.name"]).is_ok());
+
+        for paths in [
+            ["$.patient", "$.patient.ssn"],
+            ["$.patient.ssn", "$.patient.ssn"],
+        ] {
+            let result = parse_all(paths);
+            assert!(matches!(result, Err(Error::InvalidJsonPath { .. })));
+        }
+    }
+
+    #[test]
+    fn test_plaintext_round_trips_json_types() {
+        let cases = [
+            (json!("john@example.com"), CastAs::Text),
+            (json!(42), CastAs::Int),
+            (json!(19.99), CastAs::Double),
+            (json!(true), CastAs::Boolean),
+            (json!({"city": "Boston"}), CastAs::JsonB),
+            (json!(["admin"]), CastAs::TextArray),
+        ];
+
+        for (value, data_type) in cases {
+            let plaintext = plaintext_from_value(&value).unwrap();
+            assert_eq!(value_from_plaintext(plaintext, Some(data_type)), value);
+        }
+
+        assert_eq!(plaintext_from_value(&Value::Null), None);
+        assert_eq!(
+            value_from_plaintext("42".to_string(), Some(CastAs::Text)),
+            json!("42")
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! Minimal JWT decoding and verification for deriving encryption context from token claims.
+
+use crate::Error;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use sha2::Sha256;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Signature verification settings for a token.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "algorithm", deny_unknown_fields)]
+pub enum Verification {
+    /// HMAC-SHA256 with a shared secret.
+    #[serde(rename = "HS256")]
+    Hs256 {
+        /// The shared secret used to sign the token.
+        secret: String,
+    },
+}
+
+/// Decode a compact JWT and return its claims.
+///
+/// The signature is always checked, and the token header must name the algorithm of the
+/// `verification`, so unsigned tokens are rejected. The `exp` and `nbf` claims are enforced
+/// when present, allowing `leeway` seconds of clock skew.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidToken`] if the token is malformed, the signature does not match, or
+/// the token is expired or not yet valid.
+pub fn decode(
+    token: &str,
+    verification: &Verification,
+    leeway: u64,
+) -> Result<Map<String, Value>, Error> {
+    let mut segments = token.split('.');
+    let (Some(header), Some(payload), Some(encoded_signature), None) = (
+        segments.next(),
+        segments.next(),
+        segments.next(),
+        segments.next(),
+    ) else {
+        return Err(invalid("expected three dot-separated segments"));
+    };
+
+    let header: Map<String, Value> = decode_segment(header, "header")?;
+    let claims: Map<String, Value> = decode_segment(payload, "payload")?;
+
+    let Verification::Hs256 { secret } = verification;
+    if header.get("alg").and_then(Value::as_str) != Some("HS256") {
+        return Err(invalid("token algorithm does not match `HS256`"));
+    }
+
+    let signature = URL_SAFE_NO_PAD
+        .decode(encoded_signature)
+        .map_err(|_| invalid("signature is not valid base64url"))?;
+    let signing_input = &token[..token.len() - encoded_signature.len() - 1];
+
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(signing_input.as_bytes());
+    mac.verify_slice(&signature)
+        .map_err(|_| invalid("signature does not match"))?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|err| Error::Runtime(err.to_string()))?
+        .as_secs();
+
+    if let Some(expires_at) = claims.get("exp").and_then(Value::as_u64) {
+        if now > expires_at.saturating_add(leeway) {
+            return Err(invalid("token has expired"));
+        }
+    }
+
+    if let Some(not_before) = claims.get("nbf").and_then(Value::as_u64) {
+        if now.saturating_add(leeway) < not_before {
+            return Err(invalid("token is not yet valid"));
+        }
+    }
+
+    Ok(claims)
+}
+
+fn decode_segment(segment: &str, name: &str) -> Result<Map<String, Value>, Error> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(segment)
+        .map_err(|_| invalid(format!("{name} is not valid base64url")))?;
+
+    serde_json::from_slice(&bytes).map_err(|_| invalid(format!("{name} is not a JSON object")))
+}
+
+fn invalid(reason: impl Into<String>) -> Error {
+    Error::InvalidToken(reason.into())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use serde_json::json;
+
+    pub const TEST_SECRET: &str = "protect-test-secret";
+
+    /// Build an HS256-signed token for the given claims.
+    pub fn sign(claims: Value, secret: &str) -> String {
+        let header = URL_SAFE_NO_PAD.encode(json!({"alg": "HS256", "typ": "JWT"}).to_string());
+        let payload = URL_SAFE_NO_PAD.encode(claims.to_string());
+        let signing_input = format!("{header}.{payload}");
+
+        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(signing_input.as_bytes());
+        let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+
+        format!("{signing_input}.{signature}")
+    }
+
+    fn hs256(secret: &str) -> Verification {
+        Verification::Hs256 {
+            secret: secret.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_decode_verified_token() {
+        let token = sign(json!({"sub": "user_1"}), TEST_SECRET);
+
+        let claims = decode(&token, &hs256(TEST_SECRET), 0).unwrap();
+
+        assert_eq!(claims["sub"], "user_1");
+    }
+
+    #[test]
+    fn test_decode_rejects_wrong_secret() {
+        let token = sign(json!({"sub": "user_1"}), TEST_SECRET);
+
+        let result = decode(&token, &hs256("other-secret"), 0);
+
+        assert!(matches!(result, Err(Error::InvalidToken(_))));
+    }
+
+    #[test]
+    fn test_decode_rejects_unsigned_token() {
+        let header = URL_SAFE_NO_PAD.encode(json!({"alg": "none"}).to_string());
+        let payload = URL_SAFE_NO_PAD.encode(json!({"sub": "user_1"}).to_string());
+
+        let result = decode(&format!("{header}.{payload}."), &hs256(TEST_SECRET), 0);
+
+        assert!(matches!(result, Err(Error::InvalidToken(_))));
+    }
+
+    #[test]
+    fn test_decode_rejects_expired_token() {
+        let token = sign(json!({"sub": "user_1", "exp": 1}), TEST_SECRET);
+
+        let result = decode(&token, &hs256(TEST_SECRET), 0);
+
+        assert!(matches!(result, Err(Error::InvalidToken(_))));
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed_token() {
+        assert!(matches!(
+            decode("not-a-token", &hs256(TEST_SECRET), 0),
+            Err(Error::InvalidToken(_))
+        ));
+        assert!(matches!(
+            decode("a.b.c", &hs256(TEST_SECRET), 0),
+            Err(Error::InvalidToken(_))
+        ));
+    }
+}
@@ -0,0 +1,170 @@
+//! Key metadata reporting for the keysets a client encrypts under.
+//!
+//! ZeroKMS reports each keyset's name, description, and whether it is disabled, but not its key
+//! generations, creation time, or rotation state, so those are not reported.
+
+use crate::encrypt_config::Identifier;
+use serde::Serialize;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Keyset metadata reported by ZeroKMS.
+#[derive(Debug, Clone)]
+pub struct RemoteKeyset {
+    /// The keyset identifier.
+    pub id: Uuid,
+    /// The keyset name registered in ZeroKMS.
+    pub name: String,
+    /// The keyset description registered in ZeroKMS.
+    pub description: String,
+    /// Whether the keyset is disabled.
+    pub disabled: bool,
+}
+
+/// Key metadata for a client.
+#[derive(Debug, Serialize)]
+pub struct KeyInfo {
+    /// Keysets referenced by the client, starting with the default keyset.
+    pub keysets: Vec<KeysetInfo>,
+    /// Keysets visible to the client credentials that the configuration does not reference.
+    pub unreferenced: Vec<KeysetInfo>,
+}
+
+/// Metadata for a single keyset.
+#[derive(Debug, Serialize)]
+pub struct KeysetInfo {
+    /// Configured keyset name, absent for the default keyset.
+    pub configured_name: Option<String>,
+    /// The keyset identifier, absent for the default keyset.
+    pub id: Option<Uuid>,
+    /// The keyset name registered in ZeroKMS, if the keyset is visible to the client.
+    pub name: Option<String>,
+    /// The keyset description registered in ZeroKMS, if the keyset is visible to the client.
+    pub description: Option<String>,
+    /// Whether the keyset is disabled, if the keyset is visible to the client.
+    pub disabled: Option<bool>,
+    /// Columns encrypted under the keyset, as `table.column`.
+    pub columns: Vec<String>,
+}
+
+/// Combine configured keysets, their columns, and ZeroKMS keyset metadata into a report.
+pub fn key_info(
+    keyset_ids: &HashMap<String, Uuid>,
+    column_keysets: &HashMap<Identifier, String>,
+    columns: impl IntoIterator<Item = Identifier>,
+    remote: Vec<RemoteKeyset>,
+) -> KeyInfo {
+    let mut columns_by_keyset: HashMap<Option<String>, Vec<String>> = HashMap::new();
+    for identifier in columns {
+        let keyset = column_keysets.get(&identifier).cloned();
+        columns_by_keyset
+            .entry(keyset)
+            .or_default()
+            .push(format!("{}.{}", identifier.table, identifier.column));
+    }
+
+    let mut take_columns = |keyset: Option<String>| {
+        let mut columns = columns_by_keyset.remove(&keyset).unwrap_or_default();
+        columns.sort();
+        columns
+    };
+
+    let mut keysets = vec![KeysetInfo {
+        configured_name: None,
+        id: None,
+        name: None,
+        description: None,
+        disabled: None,
+        columns: take_columns(None),
+    }];
+
+    let mut names: Vec<&String> = keyset_ids.keys().collect();
+    names.sort();
+
+    for name in names {
+        let id = keyset_ids[name];
+        let metadata = remote.iter().find(|keyset| keyset.id == id);
+
+        keysets.push(KeysetInfo {
+            configured_name: Some(name.clone()),
+            id: Some(id),
+            name: metadata.map(|keyset| keyset.name.clone()),
+            description: metadata.map(|keyset| keyset.description.clone()),
+            disabled: metadata.map(|keyset| keyset.disabled),
+            columns: take_columns(Some(name.clone())),
+        });
+    }
+
+    let unreferenced = remote
+        .into_iter()
+        .filter(|keyset| !keyset_ids.values().any(|id| *id == keyset.id))
+        .map(|keyset| KeysetInfo {
+            configured_name: None,
+            id: Some(keyset.id),
+            name: Some(keyset.name),
+            description: Some(keyset.description),
+            disabled: Some(keyset.disabled),
+            columns: Vec::new(),
+        })
+        .collect();
+
+    KeyInfo {
+        keysets,
+        unreferenced,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PII_KEYSET: &str = "5d7b1e1a-6c1b-4d3f-9a2e-0c8f1b2a3d4e";
+    const OTHER_KEYSET: &str = "b2f0c3a8-91d4-4e7a-8c65-3f1e2d4a5b6c";
+
+    #[test]
+    fn test_key_info_groups_columns_by_keyset() {
+        let pii = Uuid::parse_str(PII_KEYSET).unwrap();
+        let keyset_ids = HashMap::from([("pii".to_string(), pii)]);
+        let column_keysets =
+            HashMap::from([(Identifier::new("users", "email"), "pii".to_string())]);
+        let columns = [
+            Identifier::new("users", "email"),
+            Identifier::new("users", "name"),
+        ];
+        let remote = vec![
+            RemoteKeyset {
+                id: pii,
+                name: "PII".to_string(),
+                description: "Personal data".to_string(),
+                disabled: false,
+            },
+            RemoteKeyset {
+                id: Uuid::parse_str(OTHER_KEYSET).unwrap(),
+                name: "Other".to_string(),
+                description: String::new(),
+                disabled: true,
+            },
+        ];
+
+        let info = key_info(&keyset_ids, &column_keysets, columns, remote);
+
+        assert_eq!(info.keysets.len(), 2);
+        assert_eq!(info.keysets[0].columns, ["users.name"]);
+        assert_eq!(info.keysets[1].configured_name.as_deref(), Some("pii"));
+        assert_eq!(info.keysets[1].name.as_deref(), Some("PII"));
+        assert_eq!(info.keysets[1].columns, ["users.email"]);
+        assert_eq!(info.unreferenced.len(), 1);
+        assert_eq!(info.unreferenced[0].disabled, Some(true));
+    }
+
+    #[test]
+    fn test_key_info_without_remote_metadata() {
+        let keyset_ids = HashMap::from([("pii".to_string(), Uuid::parse_str(PII_KEYSET).unwrap())]);
+
+        let info = key_info(&keyset_ids, &HashMap::new(), [], Vec::new());
+
+        assert_eq!(info.keysets[1].name, None);
+        assert_eq!(info.keysets[1].disabled, None);
+        assert!(info.unreferenced.is_empty());
+    }
+}
@@ -6,6 +6,7 @@
 //! The main entry point is the [`Client`] type, which manages encryption and decryption
 //! operations. All FFI functions operate on or return a pointer to a [`Client`] instance.
 
+use batching::Batching;
 use cipherstash_client::{
     config::{
         console_config::ConsoleConfig, cts_config::CtsConfig, errors::ConfigError,
@@ -16,26 +17,117 @@ use cipherstash_client::{
         self, EncryptionError, IndexTerm, Plaintext, PlaintextTarget, ReferencedPendingPipeline,
         ScopedCipher, TypeParseError,
     },
-    schema::ColumnConfig,
+    schema::{column::IndexType, ColumnConfig},
     zerokms::{self, EncryptedRecord, WithContext, ZeroKMSWithClientKey},
 };
+use client_options::{ClientOptions, ClientOverlay, IdentifierLookup, OutputProfile};
+use config_format::ConfigFormat;
+use context::{encryption_context_from_value, with_kinds_from_value, ContextCache, ContextItem};
+use context_presets::ContextPresets;
+use diagnostics::StartupDiagnostics;
+use empty::TextPolicy;
 use encrypt_config::{CastAs, EncryptConfig, Identifier};
-use libc::c_char;
+use envelope::VersionedEnvelope;
+use floats::FloatFormat;
+use index_encoding::IndexEncoding;
+use index_params::IndexParams;
+use jobs::{Job, JobCallback};
+use load::Load;
+use network::NetworkPolicy;
 use once_cell::sync::OnceCell;
+use probes::Probes;
+use rate_limit::RateLimiter;
+use search_terms::{Operator, PendingSearchTerm, SearchTerm, SearchTermOptions, SearchTermResult};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::ffi::{c_char, c_void};
 use std::ptr;
 use std::sync::Arc;
 use std::{collections::HashMap, str::FromStr};
+use ste_vec::SteVecPolicy;
+use term_cache::{CacheMetrics, TermCache};
 use tokio::runtime::Runtime;
-
+use uuid::Uuid;
+use warnings::Warning;
+
+mod abi;
+mod arrays;
+mod batching;
+mod benchmark;
+mod casts;
+#[cfg(feature = "checked-pointers")]
+mod checked;
+mod client_options;
+mod client_state;
+mod column_context;
+mod config_format;
+mod context;
+mod context_presets;
+mod crash_report;
+mod dates;
+mod deprecation;
+mod diagnostics;
+mod empty;
 mod encrypt_config;
+mod envelope;
+mod error_catalog;
+mod estimate;
+mod expiry;
+mod floats;
+mod functions;
+mod index_encoding;
+mod index_params;
+mod interpolation;
+mod jobs;
+mod json_paths;
+mod jwt;
+mod keys;
+mod lints;
+mod load;
+mod mysql;
+mod network;
+mod ngrams;
+mod normalization;
 mod plaintext_target;
+mod plan;
+mod probes;
+#[cfg(not(feature = "checked-pointers"))]
+mod quarantine;
+mod rate_limit;
+mod request_ids;
+mod retry_hints;
 mod safe_ffi;
+mod search_terms;
+mod signing;
+mod stats;
+mod ste_vec;
+mod storage;
+mod term_cache;
+mod uints;
+mod warnings;
+
+#[cfg(all(feature = "mimalloc", feature = "jemalloc"))]
+compile_error!("the `mimalloc` and `jemalloc` features are mutually exclusive");
+
+#[cfg(all(feature = "jemalloc", target_env = "msvc"))]
+compile_error!("the `jemalloc` feature is not supported on MSVC targets, use `mimalloc` instead");
+
+#[cfg(panic = "abort")]
+compile_error!("panics must unwind to be caught at the FFI boundary, set `panic = \"unwind\"`");
+
+#[cfg(feature = "mimalloc")]
+#[global_allocator]
+static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
+
+#[cfg(all(feature = "jemalloc", not(feature = "mimalloc")))]
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 
 /// Get the shared async runtime instance.
 ///
 /// Creates a new Tokio runtime on first call within the current process and reuses it
 /// for subsequent calls in the same process. Each PHP process gets its own runtime instance.
+/// Threads in a ZTS process share the runtime, blocking on it concurrently.
 fn runtime() -> Result<&'static Runtime, Error> {
     static RUNTIME: OnceCell<Runtime> = OnceCell::new();
 
@@ -43,15 +135,281 @@ fn runtime() -> Result<&'static Runtime, Error> {
 }
 
 /// An encryption client that manages cipher operations and configuration.
+///
+/// A client is `Send + Sync`, so a single client pointer may be used concurrently from multiple
+/// threads, such as in ZTS PHP builds. Its configuration, options, and ciphers are immutable once
+/// created. Its mutable state is shared through `Arc`s with the clients derived from it, and
+/// each piece synchronizes itself: the rate limiter's token bucket, the open batches, the
+/// operation counts, and the search term cache are each behind a `Mutex`, held only while they
+/// are read or updated and never across an `await`, and the credentials of the connectivity
+/// probes cache their service token with their own locking. Any state added to the client must
+/// preserve this.
 #[derive(Clone)]
 pub struct Client {
+    /// The scoped cipher of the default keyset, or of the overlay's keyset for a derived
+    /// client. Scoped ciphers are initialized once per keyset when the client is created and
+    /// reused by every call, so a call only builds the pipeline its `encrypt` consumes.
     cipher: Arc<ScopedZeroKMSNoRefresh>,
+    /// The scoped cipher of each named keyset, shared with derived clients, which take their
+    /// `cipher` from here rather than initializing another.
+    keyset_ciphers: Arc<HashMap<String, Arc<ScopedZeroKMSNoRefresh>>>,
+    keyset_ids: Arc<HashMap<String, Uuid>>,
     zerokms: Arc<ZeroKMSWithClientKey<ServiceCredentials>>,
+    /// SHA-256 digest of the encryption configuration, for client state snapshots and
+    /// `assert_config_matches`.
+    config_digest: Arc<str>,
     encrypt_config: Arc<HashMap<Identifier, (ColumnConfig, CastAs)>>,
+    column_keysets: Arc<HashMap<Identifier, String>>,
+    float_formats: Arc<HashMap<Identifier, FloatFormat>>,
+    text_policies: Arc<HashMap<Identifier, TextPolicy>>,
+    ste_vec_policies: Arc<HashMap<Identifier, SteVecPolicy>>,
+    index_params: Arc<HashMap<Identifier, IndexParams>>,
+    /// The configured identifier of each case-folded identifier, with case-insensitive lookup.
+    folded_identifiers: Option<Arc<HashMap<Identifier, Identifier>>>,
+    options: Arc<ClientOptions>,
+    warnings: Arc<Vec<Warning>>,
+    default_context: Option<Arc<serde_json::Value>>,
+    /// The context derived from the `context_from` of each column that sets it.
+    column_contexts: Arc<HashMap<Identifier, Vec<ContextItem>>>,
+    context_presets: Arc<ContextPresets>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    batching: Option<Arc<Batching>>,
+    load: Arc<Load>,
+    network: Arc<NetworkPolicy>,
+    probes: Arc<Probes>,
+    search_term_cache: Option<Arc<TermCache>>,
+}
+
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Client>();
+};
+
+impl Client {
+    /// The empty and whitespace-only text handling of a column.
+    fn text_policy(&self, identifier: &Identifier) -> TextPolicy {
+        self.text_policies
+            .get(identifier)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// The parts of documents the `ste_vec` index of a column covers.
+    fn ste_vec_policy(&self, identifier: &Identifier) -> &SteVecPolicy {
+        self.ste_vec_policies
+            .get(identifier)
+            .unwrap_or(&ste_vec::UNLIMITED)
+    }
+
+    /// The parameters the terms of a column's indexes are created with.
+    fn index_params(&self, identifier: &Identifier) -> &IndexParams {
+        self.index_params
+            .get(identifier)
+            .unwrap_or(&index_params::UNINDEXED)
+    }
+
+    /// The number of index source targets that follow each value or array element of a column.
+    fn source_targets(&self, identifier: &Identifier) -> usize {
+        self.text_policy(identifier).normalization.sources().len()
+    }
+
+    /// The configuration and type of a column.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnknownColumn`], with the closest configured columns, if the column is
+    /// not configured.
+    fn column(&self, identifier: &Identifier) -> Result<&(ColumnConfig, CastAs), Error> {
+        encrypt_config::column(&self.encrypt_config, identifier)
+    }
+
+    /// Resolve a requested column to its configured identifier, ignoring the case of its names
+    /// when the `identifier_lookup` option is `case_insensitive`. Unknown columns are returned
+    /// as requested, so looking them up reports the names the caller supplied.
+    fn resolve(&self, identifier: Identifier) -> Identifier {
+        match &self.folded_identifiers {
+            Some(folded_identifiers) => folded_identifiers
+                .get(&identifier.case_folded())
+                .cloned()
+                .unwrap_or(identifier),
+            None => identifier,
+        }
+    }
+
+    /// The context a column derives from its `context_from`, added to the context of its values.
+    fn column_context(&self, identifier: &Identifier) -> Vec<zerokms::Context> {
+        self.column_contexts
+            .get(identifier)
+            .map(|items| items.iter().cloned().map(zerokms::Context::from).collect())
+            .unwrap_or_default()
+    }
+
+    /// The encryption context of a request, or the client's default context when the request
+    /// has none, with a reference to a context preset expanded.
+    fn request_context<'a>(
+        &'a self,
+        context: Option<&'a serde_json::Value>,
+    ) -> Result<Option<Cow<'a, serde_json::Value>>, Error> {
+        context
+            .or(self.default_context.as_deref())
+            .map(|context| self.context_presets.expand(context))
+            .transpose()
+    }
+
+    /// Derive a client that shares this client's ciphers, connections, and configuration, with
+    /// the overlay's default context and keyset in place of this client's.
+    fn derive(&self, overlay: ClientOverlay) -> Result<Self, Error> {
+        let mut client = self.clone();
+
+        if let Some(context) = overlay.context {
+            let context = self.context_presets.expand(&context)?.into_owned();
+            encryption_context_from_value(&context)?;
+            client.default_context = Some(Arc::new(context));
+        }
+        if let Some(keyset) = overlay.keyset {
+            client.cipher = self.cipher_for(Some(&keyset))?;
+            // Search terms of columns without a keyset of their own differ under another keyset.
+            client.search_term_cache = self
+                .options
+                .search_term_cache
+                .map(|config| TermCache::new(config).map(Arc::new))
+                .transpose()?;
+        }
+        if overlay.write_only && !self.options.write_only {
+            client.options = Arc::new(ClientOptions {
+                write_only: true,
+                ..(*self.options).clone()
+            });
+        }
+
+        Ok(client)
+    }
+
+    /// Wait for the client's rate limit to allow a ZeroKMS call.
+    async fn throttle(&self) -> Result<(), Error> {
+        match &self.rate_limiter {
+            Some(rate_limiter) => rate_limiter.acquire().await,
+            None => Ok(()),
+        }
+    }
+
+    /// Check that a plaintext is no larger than the client's `max_plaintext_bytes`, before any
+    /// work is done to encrypt it.
+    fn check_plaintext_size(&self, plaintext: &str) -> Result<(), Error> {
+        match self.options.max_plaintext_bytes {
+            Some(max_bytes) if plaintext.len() > max_bytes => Err(Error::PlaintextTooLarge {
+                bytes: plaintext.len(),
+                max_bytes,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that the client may decrypt.
+    fn ensure_can_decrypt(&self) -> Result<(), Error> {
+        if self.options.write_only {
+            return Err(Error::DecryptionDisabled);
+        }
+
+        Ok(())
+    }
+
+    /// Select the cipher for a named keyset, or the default cipher when no keyset is given.
+    fn cipher_for(&self, keyset: Option<&str>) -> Result<Arc<ScopedZeroKMSNoRefresh>, Error> {
+        match keyset {
+            Some(keyset) => self
+                .keyset_ciphers
+                .get(keyset)
+                .cloned()
+                .ok_or_else(|| Error::UnknownKeyset(keyset.to_string())),
+            None => Ok(self.cipher.clone()),
+        }
+    }
+
+    /// Wrap an encrypted value in the configured envelope version, signing it when a signing
+    /// key is configured.
+    fn emit_envelope(&self, encrypted: Encrypted) -> Result<serde_json::Value, Error> {
+        let index_params = self.index_params(encrypted.identifier());
+        let envelope = VersionedEnvelope::emit(encrypted, self.options.emit_version, index_params)?;
+        let mut envelope = serde_json::to_value(&envelope)?;
+
+        if let Some(signing_key) = &self.options.signing_key {
+            let signature = signing_key.sign(&envelope);
+            envelope[signing::SIGNATURE_FIELD] = serde_json::Value::String(signature);
+        }
+
+        Ok(envelope)
+    }
+
+    /// Emit an encrypted value in the configured output profile.
+    fn emit(&self, encrypted: Encrypted) -> Result<serde_json::Value, Error> {
+        match self.options.output_profile {
+            OutputProfile::Postgres => self.emit_envelope(encrypted),
+            OutputProfile::Mysql => {
+                let (column_config, _) = self.column(encrypted.identifier())?;
+
+                let columns = mysql::columns(encrypted, column_config, |encrypted| {
+                    self.emit_envelope(encrypted)
+                })?;
+
+                Ok(serde_json::Value::Object(columns))
+            }
+        }
+    }
+
+    /// Extract the ciphertext from decryption input, verifying envelope signatures when a
+    /// signing key is configured.
+    ///
+    /// Envelopes referencing a keyset must name a keyset configured for this client. ZeroKMS
+    /// resolves the data key from the record itself.
+    ///
+    /// Envelopes also determine how the decrypted plaintext is reconciled with the current
+    /// configuration of their column. The expiry of an expiring envelope is added to the
+    /// encryption context it was bound to, after checking it when the client enforces expiry,
+    /// and the context its column derives from `context_from` is added too.
+    fn ciphertext_from_input(
+        &self,
+        input: String,
+        encryption_context: &mut Vec<zerokms::Context>,
+    ) -> Result<(String, PlaintextOutput), Error> {
+        if let Some(signing_key) = &self.options.signing_key {
+            signing::verify_input(&input, signing_key, self.options.require_signature)?;
+        }
+
+        let extracted = envelope::extract_ciphertext(input)?;
+
+        if let Some(keyset) = &extracted.keyset {
+            self.cipher_for(Some(keyset))?;
+        }
+
+        if let Some(expires_at) = extracted.expires_at {
+            if self.options.enforce_expiry {
+                expiry::check(expires_at, expiry::now())?;
+            }
+            encryption_context.push(expiry::context(expires_at));
+        }
+
+        if let Some(identifier) = &extracted.identifier {
+            encryption_context.extend(self.column_context(identifier));
+        }
+
+        let output = match extracted.data_type {
+            Some((identifier, data_type)) => PlaintextOutput {
+                type_change: casts::parse_data_type(&data_type).and_then(|stored| {
+                    let (_, configured) = self.encrypt_config.get(&identifier)?;
+                    (stored != *configured).then_some((stored, *configured))
+                }),
+                float_format: self.float_formats.get(&identifier).copied(),
+            },
+            None => PlaintextOutput::default(),
+        };
+
+        Ok((extracted.ciphertext, output))
+    }
 }
 
 /// A structured text encryption vector entry.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct SteVecEntry {
     /// Tokenized selector representing the encrypted JSON path to the value.
     #[serde(rename = "s")]
@@ -62,13 +420,38 @@ pub struct SteVecEntry {
     /// Base85-encoded ciphertext containing the encrypted record data.
     #[serde(rename = "r")]
     record: String,
-    /// Whether the parent JSON element is an array.
+    /// Whether the parent JSON element is an array. Always false for the entry of the document
+    /// root, whether the root is an object, an array, or a scalar, and true for the elements of
+    /// an array-rooted document.
     #[serde(rename = "pa")]
     parent_is_array: bool,
 }
 
+/// Encryption index terms for a single element of an array value.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ElementTerms {
+    /// HMAC index for exact equality queries.
+    #[serde(rename = "hm", default, skip_serializing_if = "Option::is_none")]
+    unique_index: Option<String>,
+    /// Order-revealing encryption index for range comparisons.
+    #[serde(rename = "ob", default, skip_serializing_if = "Option::is_none")]
+    ore_index: Option<Vec<String>>,
+    /// Bloom filter index for full-text search queries.
+    #[serde(rename = "bf", default, skip_serializing_if = "Option::is_none")]
+    match_index: Option<Vec<u16>>,
+}
+
+impl ElementTerms {
+    /// Fill in the terms this element is missing from the terms of a normalization form.
+    fn merge(&mut self, other: ElementTerms) {
+        self.unique_index = self.unique_index.take().or(other.unique_index);
+        self.ore_index = self.ore_index.take().or(other.ore_index);
+        self.match_index = self.match_index.take().or(other.match_index);
+    }
+}
+
 /// An encrypted value with associated encryption indexes or structured text encryption vectors.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(tag = "k")]
 pub enum Encrypted {
     /// Encrypted ciphertext with encryption indexes based on column configuration.
@@ -90,9 +473,19 @@ pub enum Encrypted {
         /// Bloom filter index for full-text search queries.
         #[serde(rename = "bf")]
         match_index: Option<Vec<u16>>,
+        /// Index terms for each element of an array value, in array order.
+        #[serde(rename = "ae", default, skip_serializing_if = "Option::is_none")]
+        elements: Option<Vec<ElementTerms>>,
         /// Table and column identifier for this encrypted value.
         #[serde(rename = "i")]
         identifier: Identifier,
+        /// Name of the keyset the value was encrypted under, absent for the default keyset.
+        #[serde(rename = "ks", default, skip_serializing_if = "Option::is_none")]
+        keyset: Option<String>,
+        /// When the value expires, in seconds since the Unix epoch, absent for values that do
+        /// not expire.
+        #[serde(rename = "exp", default, skip_serializing_if = "Option::is_none")]
+        expires_at: Option<u64>,
         /// Schema version for backward compatibility.
         #[serde(rename = "v")]
         version: u16,
@@ -112,12 +505,45 @@ pub enum Encrypted {
         /// Table and column identifier for this encrypted value.
         #[serde(rename = "i")]
         identifier: Identifier,
+        /// Name of the keyset the value was encrypted under, absent for the default keyset.
+        #[serde(rename = "ks", default, skip_serializing_if = "Option::is_none")]
+        keyset: Option<String>,
+        /// When the value expires, in seconds since the Unix epoch, absent for values that do
+        /// not expire.
+        #[serde(rename = "exp", default, skip_serializing_if = "Option::is_none")]
+        expires_at: Option<u64>,
         /// Schema version for backward compatibility.
         #[serde(rename = "v")]
         version: u16,
     },
 }
 
+impl Encrypted {
+    /// The table and column identifier of the value.
+    fn identifier(&self) -> &Identifier {
+        match self {
+            Self::Ciphertext { identifier, .. } | Self::SteVec { identifier, .. } => identifier,
+        }
+    }
+
+    /// When the value expires, absent for values that do not expire.
+    fn expires_at(&self) -> Option<u64> {
+        match self {
+            Self::Ciphertext { expires_at, .. } | Self::SteVec { expires_at, .. } => *expires_at,
+        }
+    }
+
+    /// Record when the value expires. The expiry must also have been bound to the ciphertext
+    /// as encryption context, or the value cannot be decrypted.
+    fn set_expires_at(&mut self, value: Option<u64>) {
+        match self {
+            Self::Ciphertext { expires_at, .. } | Self::SteVec { expires_at, .. } => {
+                *expires_at = value
+            }
+        }
+    }
+}
+
 /// Errors that can occur during encryption and decryption operations.
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -139,13 +565,198 @@ pub enum Error {
     /// UTF-8 string conversion error.
     #[error(transparent)]
     Utf8(#[from] std::str::Utf8Error),
+    /// YAML or TOML encryption configuration that cannot be parsed.
+    #[error("invalid {0} configuration: {1}")]
+    InvalidConfigSyntax(ConfigFormat, String),
+    /// Environment variable reference in an encryption configuration that cannot be resolved.
+    #[error("invalid interpolation in configuration: {0}")]
+    InvalidInterpolation(String),
+    /// Encryption configuration whose `extends` sources cannot be composed.
+    #[error("invalid `extends`: {0}")]
+    InvalidExtends(String),
 
     /// Unsupported schema version.
     #[error("unsupported schema version {0}: only version 2 is supported")]
     UnsupportedSchemaVersion(u32),
-    /// Unknown column identifier in configuration.
-    #[error("unknown column `{}.{}`", _0.table, _0.column)]
-    UnknownColumn(Identifier),
+    /// Unsupported envelope version.
+    #[error(
+        "unsupported envelope version {0}: expected one of {supported:?}",
+        supported = envelope::SUPPORTED_VERSIONS
+    )]
+    UnsupportedEnvelopeVersion(u64),
+    /// Envelope signature required but not present.
+    #[error("envelope signature is missing")]
+    MissingSignature,
+    /// Envelope signature does not match its contents.
+    #[error("envelope signature is invalid: the envelope may have been tampered with")]
+    InvalidSignature,
+    /// Named encryption context preset in the configuration that cannot be applied.
+    #[error("invalid context preset `{0}`: {1}")]
+    InvalidContextPreset(String, String),
+    /// Encryption context entry that cannot be applied.
+    #[error("invalid encryption context at `{path}`: {reason}")]
+    InvalidContext {
+        /// JSON path to the offending entry.
+        path: String,
+        /// Description of the problem.
+        reason: String,
+    },
+    /// JWT that cannot be decoded or verified.
+    #[error("invalid token: {0}")]
+    InvalidToken(String),
+    /// Decryption denied, most likely because the supplied encryption context does not match.
+    #[error(
+        "decryption denied: the encryption context may not match the context used for encryption (supplied context kinds: {})",
+        context::describe_kinds(kinds)
+    )]
+    ContextMismatch {
+        /// The kinds of context supplied, without their values.
+        kinds: Vec<&'static str>,
+        /// The underlying ZeroKMS error.
+        #[source]
+        source: zerokms::Error,
+    },
+    /// Stored value type that cannot be represented by the column's configured type.
+    #[error("cannot decrypt a `{stored}` value as `{configured}`: narrowing conversions are not supported")]
+    NarrowingCast {
+        /// The data type recorded in the envelope.
+        stored: CastAs,
+        /// The data type configured for the column.
+        configured: CastAs,
+    },
+    /// Stored value type that is unrelated to the column's configured type.
+    #[error("cannot decrypt a `{stored}` value as `{configured}`: the types are incompatible")]
+    IncompatibleCast {
+        /// The data type recorded in the envelope.
+        stored: CastAs,
+        /// The data type configured for the column.
+        configured: CastAs,
+    },
+    /// Array plaintext that is not a JSON array of the column's element type.
+    #[error("invalid array plaintext: {0}")]
+    InvalidArray(String),
+    /// Unsigned integer plaintext that is out of range or malformed.
+    #[error("invalid unsigned integer plaintext: {0}")]
+    InvalidUnsignedInteger(&'static str),
+    /// Integer plaintext that is not a number in the range of its column type.
+    #[error("invalid `{0}` plaintext: expected an integer in range")]
+    InvalidInteger(CastAs),
+    /// Empty or whitespace-only plaintext for a type or column that does not accept it.
+    #[error("empty plaintext is not a valid `{0}` value")]
+    EmptyPlaintext(CastAs),
+    /// Text plaintext with more characters than the `max_length` of its column.
+    #[error("plaintext is {length} characters, longer than the `max_length` of {max_length}")]
+    PlaintextTooLong { length: usize, max_length: usize },
+    /// Text over the limits of a `match` index that rejects it.
+    #[error("plaintext exceeds the limits of the `match` index: {0}")]
+    MatchLimitExceeded(String),
+    /// Date plaintext that is not a calendar date.
+    #[error("invalid date plaintext: {0}")]
+    InvalidDate(&'static str),
+    /// Path to a field inside a JSON document that cannot be used.
+    #[error("invalid JSON path `{path}`: {reason}")]
+    InvalidJsonPath {
+        /// The path as supplied.
+        path: String,
+        /// Description of the problem.
+        reason: String,
+    },
+    /// Index term argument that is neither hex nor prefixed base64.
+    #[error("invalid index term `{0}`: expected a hex or prefixed base64 string")]
+    InvalidIndexTerm(&'static str),
+    /// Search term request that cannot be applied to its column.
+    #[error("invalid search term: {0}")]
+    InvalidSearchTerm(String),
+    /// Search term for rows whose index terms were created with other index parameters, which
+    /// it could not match.
+    #[error("incompatible index parameters for column `{}.{}`: {}", _0.table, _0.column, _1)]
+    IncompatibleIndexParams(Identifier, String),
+    /// Benchmark request that cannot be run.
+    #[error("invalid benchmark: {0}")]
+    InvalidBenchmark(String),
+    /// Envelope storage columns or profile that cannot be split or assembled.
+    #[error("invalid envelope storage: {0}")]
+    InvalidStorage(String),
+    /// Envelope that is valid JSON but not of the kind an operation requires.
+    #[error("invalid envelope: {0}")]
+    InvalidEnvelope(String),
+    /// JSON document that cannot be indexed.
+    #[error("invalid JSON document: {0}")]
+    InvalidJsonDocument(String),
+    /// Floating-point plaintext that cannot be encrypted or formatted.
+    #[error("invalid floating-point plaintext: {0}")]
+    InvalidFloat(&'static str),
+    /// Column configuration with options that cannot be applied.
+    #[error("invalid configuration for column `{}.{}`: {}", _0.table, _0.column, _1)]
+    InvalidColumn(Identifier, String),
+    /// Unknown keyset name in configuration or envelope.
+    #[error("unknown keyset `{0}`")]
+    UnknownKeyset(String),
+    /// Unknown column identifier in configuration, with the closest configured columns.
+    #[error("unknown column `{}.{}`{}", _0.table, _0.column, encrypt_config::did_you_mean(_1))]
+    UnknownColumn(Identifier, Vec<Identifier>),
+    /// A ZeroKMS call that would wait longer than the client's rate limit allows.
+    #[error("rate limit exceeded: the call would be queued for {wait_ms} ms, longer than the `max_wait_ms` of the `rate_limit` client option")]
+    RateLimited {
+        /// How long the call would have waited, in milliseconds.
+        wait_ms: u128,
+    },
+    /// Invalid client options.
+    #[error("invalid client options: {0}")]
+    InvalidClientOptions(String),
+    /// A job whose result is not available.
+    #[error("invalid job: {0}")]
+    InvalidJob(String),
+    /// Client creation that failed to configure or reach ZeroKMS, with the startup diagnostics
+    /// as JSON.
+    #[error("client startup failed, startup diagnostics: {diagnostics}")]
+    ClientStartup {
+        /// The configuration sources and environment variables consulted.
+        diagnostics: String,
+        /// The error that failed client creation.
+        #[source]
+        source: Box<Error>,
+    },
+    /// A service token that CTS could not issue.
+    #[error("credentials error: {0}")]
+    Credentials(String),
+    /// A connection to a host other than CTS or ZeroKMS from an offline client.
+    #[error("network access to `{0}` is forbidden by the `offline` client option")]
+    NetworkForbidden(String),
+    /// A caller whose header or C type sizes do not match the library.
+    #[error("incompatible ABI: {0}")]
+    IncompatibleAbi(String),
+    /// Decryption requested from a write-only client.
+    #[error("decryption is disabled by the `write_only` client option")]
+    DecryptionDisabled,
+    /// An expired value refused by the `enforce_expiry` client option.
+    #[error("the value expired at {expires_at} and the `enforce_expiry` client option refuses to decrypt it")]
+    Expired {
+        /// When the value expired, in seconds since the Unix epoch.
+        expires_at: u64,
+    },
+    /// An operation rejected because the client has as many outstanding operations as it allows.
+    #[error("client overloaded: {outstanding} operations are outstanding, the most the `max_outstanding` client option allows")]
+    Overloaded {
+        /// The number of queued and in-flight operations.
+        outstanding: usize,
+    },
+    /// A client created with a different encryption configuration than the caller expects.
+    #[error("encryption configuration mismatch: the caller expects configuration digest `{expected}`, but the client was created with `{actual}`, so the application and its encryption configuration may have been deployed at different times")]
+    ConfigMismatch {
+        /// The digest the caller expects.
+        expected: String,
+        /// The digest of the client's configuration.
+        actual: String,
+    },
+    /// A plaintext larger than the `max_plaintext_bytes` client option allows.
+    #[error("plaintext is {bytes} bytes, larger than the {max_bytes} bytes the `max_plaintext_bytes` client option allows: encrypt large payloads in smaller parts, or store them outside the database and encrypt a reference to them")]
+    PlaintextTooLarge {
+        /// The size of the plaintext, in bytes.
+        bytes: usize,
+        /// The `max_plaintext_bytes` client option.
+        max_bytes: usize,
+    },
 
     /// Base85 encoding/decoding error.
     #[error("base85 encoding error: {0}")]
@@ -160,27 +771,116 @@ pub enum Error {
     /// Null pointer passed where non-null expected.
     #[error("null pointer provided")]
     NullPointer,
+    /// Pointer that was not returned by this library or has already been freed.
+    #[error("invalid pointer: {0}")]
+    InvalidPointer(String),
     /// String conversion error.
     #[error("string conversion error: {0}")]
     StringConversion(String),
     /// Internal invariant violation - indicates a bug in protect-ffi.
     #[error("internal error: {0} (this is a bug in protect-ffi, please file an issue at https://github.com/cipherstash/protectphp-ffi/issues)")]
     InvariantViolation(String),
+    /// Exported function that panicked, returned instead of unwinding into the caller.
+    #[error("internal error: `{0}` panicked (this is a bug in protect-ffi; set a crash report path with `set_crash_report_path` to record a report, and file an issue at https://github.com/cipherstash/protectphp-ffi/issues)")]
+    Panic(&'static str),
 }
 
 type ScopedZeroKMSNoRefresh = ScopedCipher<ServiceCredentials>;
 
+/// How a decrypted plaintext is reconciled with the current configuration of its column.
+#[derive(Debug, Default)]
+struct PlaintextOutput {
+    /// The type the value was stored as and the type its column is now configured as, when
+    /// they differ.
+    type_change: Option<(CastAs, CastAs)>,
+    /// Output formatting for `real` and `double` columns.
+    float_format: Option<FloatFormat>,
+}
+
+impl PlaintextOutput {
+    /// Reconcile a decrypted plaintext with its column's configured type, then format it.
+    fn apply(&self, plaintext: String) -> Result<String, Error> {
+        let plaintext = match self.type_change {
+            Some((stored, configured)) => casts::reconcile(plaintext, stored, configured)?,
+            None => plaintext,
+        };
+
+        match &self.float_format {
+            Some(float_format) => floats::format(&plaintext, float_format),
+            None => Ok(plaintext),
+        }
+    }
+}
+
 #[derive(Deserialize)]
 struct ClientConfig {
     #[serde(default)]
     _dataset_id: Option<String>,
 }
 
+/// Returns the ABI version of the library's exported functions.
+///
+/// The version increases whenever an exported function changes in a way a header written for
+/// an earlier version cannot call safely. Callers compare it with the version their header was
+/// written for before calling any other function.
+#[no_mangle]
+pub extern "C" fn protect_ffi_abi_version() -> u32 {
+    abi::ABI_VERSION
+}
+
+/// Checks that the caller is compatible with the library before it calls anything else.
+///
+/// The caller passes the ABI version its header was written for and the sizes of a pointer and
+/// of `int32_t` as it sees them. If `callback` is not null, it is invoked once on the calling
+/// thread, in the convention of [`job_on_complete()`] callbacks, with `user_data` and the result
+/// `"ok"`, which the callback must free with [`free_string()`], so the caller can confirm its
+/// callbacks are called correctly.
+///
+/// Returns `0` if the caller is compatible, or `-1` with the mismatch in `error_out`.
+///
+/// # Safety
+///
+/// `user_data` must be valid for the callback, if one is passed.
+#[no_mangle]
+pub extern "C" fn selfcheck(
+    abi_version: u32,
+    pointer_size: usize,
+    int32_size: usize,
+    callback: Option<JobCallback>,
+    user_data: *mut c_void,
+    error_out: *mut *mut c_char,
+) -> i32 {
+    let sizes = abi::TypeSizes {
+        pointer: pointer_size,
+        int32: int32_size,
+    };
+
+    match abi::check(abi_version, &sizes) {
+        Ok(()) => {
+            safe_ffi::clear_error(error_out);
+            safe_ffi::clear_last_error();
+            if let Some(callback) = callback {
+                let result =
+                    safe_ffi::string_to_c_string("ok".to_string()).unwrap_or(ptr::null_mut());
+                callback(user_data, result, ptr::null_mut());
+            }
+            0
+        }
+        Err(error) => {
+            safe_ffi::set_error(error_out, &error);
+            safe_ffi::set_last_error(&error);
+            -1
+        }
+    }
+}
+
 /// Creates a new client instance from the provided encryption configuration.
 ///
+/// The `config_json` parameter may also be written in YAML or TOML, detected from its text.
+///
 /// # Errors
 ///
-/// Returns an error if the `config_json` is invalid JSON, contains unsupported
+/// Returns an error if the `config_json` is invalid, contains unsupported
 /// encryption options, or if the client cannot be initialized.
 ///
 /// # Safety
@@ -192,87 +892,114 @@ pub extern "C" fn new_client(
     config_json: *const c_char,
     error_out: *mut *mut c_char,
 ) -> *mut Client {
-    let result: Result<Box<Client>, Error> = runtime().and_then(|rt| {
-        rt.block_on(async {
-            let config_json = safe_ffi::c_str_to_string(config_json)?;
-            let encrypt_config = EncryptConfig::from_str(&config_json)?;
-            let client = new_client_inner(encrypt_config).await?;
-            Ok(Box::new(client))
+    new_client_with_options(config_json, ptr::null(), error_out)
+}
+
+/// Creates a new client instance from the provided encryption configuration and client options.
+///
+/// The `options_json` parameter is optional. When null, default options are used. The
+/// `config_json` parameter may also be written in YAML or TOML, set by the `config_format`
+/// client option or detected from its text.
+///
+/// # Errors
+///
+/// Returns an error if the `config_json` or `options_json` is invalid, contains
+/// unsupported options, or if the client cannot be initialized.
+///
+/// # Safety
+///
+/// The caller must ensure `config_json` points to a valid null-terminated C string, and that
+/// `options_json` is either null or a valid null-terminated C string.
+/// The returned pointer must be freed using [`free_client()`].
+#[no_mangle]
+pub extern "C" fn new_client_with_options(
+    config_json: *const c_char,
+    options_json: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut Client {
+    let result: Result<Client, Error> = safe_ffi::catch_panic("new_client_with_options", || {
+        runtime().and_then(|rt| {
+            rt.block_on(async {
+                let config_json = safe_ffi::c_str_to_string(config_json)?;
+                let options = match safe_ffi::optional_c_str_to_string(options_json)? {
+                    Some(options_json) => ClientOptions::from_str(&options_json)?,
+                    None => ClientOptions::default(),
+                };
+                let encrypt_config = EncryptConfig::parse(&config_json, options.config_format)?;
+                new_client_inner(encrypt_config, options).await
+            })
         })
     });
 
-    handle_ffi_result!(result, error_out, Box::into_raw)
+    handle_ffi_result!(result, error_out, safe_ffi::client_into_raw)
 }
 
-async fn new_client_inner(encrypt_config: EncryptConfig) -> Result<Client, Error> {
-    let console_config = ConsoleConfig::builder().with_env().build()?;
-    let cts_config = CtsConfig::builder().with_env().build()?;
-    let zerokms_config = ZeroKMSConfig::builder()
-        .add_source(EnvSource::default())
-        .console_config(&console_config)
-        .cts_config(&cts_config)
-        .build_with_client_key()?;
-
-    let zerokms = Arc::new(zerokms_config.create_client());
-
-    let cipher = ScopedZeroKMSNoRefresh::init(zerokms.clone(), None).await?;
+/// Returns the non-fatal warnings about a client's configuration.
+///
+/// Returns a JSON array of objects with a stable `code` and a `message`, such as a warning for
+/// each `match` index that sets `include_original`. The array is empty when there is nothing to
+/// review.
+///
+/// # Errors
+///
+/// Returns an error if the `client` pointer is invalid.
+///
+/// # Safety
+///
+/// The `client` pointer must be a valid pointer returned by [`new_client()`].
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn get_client_warnings(
+    client: *const Client,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("get_client_warnings", || {
+        safe_ffi::client_ref(client)
+            .and_then(|client| serde_json::to_string(&*client.warnings).map_err(Error::from))
+    });
 
-    Ok(Client {
-        cipher: Arc::new(cipher),
-        zerokms,
-        encrypt_config: Arc::new(encrypt_config.into_config_map()),
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
     })
 }
 
-/// Encrypts plaintext for a specific table column.
+/// Validates an encryption configuration without creating a client.
 ///
-/// Returns a JSON string containing the encrypted result and encryption indexes.
+/// Returns a JSON array of the warnings a client created from the configuration would have,
+/// in the format of [`get_client_warnings()`]. Columns whose index cannot serve their type,
+/// such as a `match` index on an `int` column or an `ore` index on a `jsonb` column, are
+/// rejected, unless the column lists the lint in `allow_lints`.
+///
+/// The `config` may be written in JSON, YAML, or TOML, named by the optional `format` as
+/// `json`, `yaml`, or `toml`. When `format` is null, the format is detected from the text.
 ///
 /// # Errors
 ///
-/// Returns an error if the table/column is not found in the encryption configuration,
-/// the encryption context JSON is malformed, or encryption fails.
+/// Returns an error if the `config` is invalid in its format, contains unsupported encryption
+/// options, or configures an index its column's type cannot use, or if the `format` is unknown.
 ///
 /// # Safety
 ///
-/// All pointer parameters must be valid null-terminated C strings.
+/// The caller must ensure `config` points to a valid null-terminated C string, and that
+/// `format` is either null or a valid null-terminated C string.
 /// The returned pointer must be freed using [`free_string()`].
 #[no_mangle]
-pub extern "C" fn encrypt(
-    client: *const Client,
-    plaintext: *const c_char,
-    column: *const c_char,
-    table: *const c_char,
-    context_json: *const c_char,
+pub extern "C" fn validate_config(
+    config: *const c_char,
+    format: *const c_char,
     error_out: *mut *mut c_char,
 ) -> *mut c_char {
-    let result: Result<String, Error> = runtime().and_then(|rt| {
-        rt.block_on(async {
-            let client = safe_ffi::client_ref(client)?;
-            let plaintext = safe_ffi::c_str_to_string(plaintext)?;
-            let column = safe_ffi::c_str_to_string(column)?;
-            let table = safe_ffi::c_str_to_string(table)?;
-            let context = safe_ffi::optional_c_str_to_string(context_json)?;
-
-            let encryption_context = if let Some(context) = context {
-                parse_encryption_context(&context)?
-            } else {
-                Vec::new()
-            };
-
-            let identifier = Identifier::new(table, column);
-            let (column_config, cast_as) = client
-                .encrypt_config
-                .get(&identifier)
-                .ok_or_else(|| Error::UnknownColumn(identifier.clone()))?;
-
-            let mut plaintext_target = plaintext_target::new(plaintext, column_config)?;
-            plaintext_target.context = encryption_context;
-
-            let encrypted =
-                encrypt_inner(client.clone(), plaintext_target, &identifier, cast_as, None).await?;
-
-            serde_json::to_string(&encrypted).map_err(Error::from)
+    let result: Result<String, Error> = safe_ffi::catch_panic("validate_config", || {
+        safe_ffi::c_str_to_string(config).and_then(|config| {
+            let format = safe_ffi::optional_c_str_to_string(format)?
+                .map(|format| {
+                    serde_json::from_value::<ConfigFormat>(serde_json::Value::String(format))
+                })
+                .transpose()?;
+            let encrypt_config = EncryptConfig::parse(&config, format)?;
+            let warnings =
+                config_warnings(&encrypt_config, &encrypt_config.include_original_columns());
+            serde_json::to_string(&warnings).map_err(Error::from)
         })
     });
 
@@ -281,382 +1008,2961 @@ pub extern "C" fn encrypt(
     })
 }
 
-async fn encrypt_inner(
-    client: Client,
-    plaintext_target: PlaintextTarget,
-    identifier: &Identifier,
-    cast_as: &CastAs,
-    service_token: Option<ServiceToken>,
-) -> Result<Encrypted, Error> {
-    let mut pipeline = ReferencedPendingPipeline::new(client.cipher);
-
-    pipeline.add_with_ref::<PlaintextTarget>(plaintext_target, 0)?;
-
-    let mut source_encrypted = pipeline.encrypt(service_token).await?;
-
-    let encrypted = source_encrypted.remove(0).ok_or_else(|| {
-        Error::InvariantViolation(
-            "`encrypt` expected a single result in the pipeline, but there were none".to_string(),
-        )
-    })?;
-
-    to_eql_encrypted(encrypted, identifier, cast_as)
-}
-
-/// Parses JSON encryption context into ZeroKMS context objects.
-fn parse_encryption_context(context_json: &str) -> Result<Vec<zerokms::Context>, Error> {
-    let context: serde_json::Value = serde_json::from_str(context_json)?;
-    let mut encryption_context = Vec::new();
-
-    if let Some(identity_claim) = context.get("identity_claim") {
-        if let Some(claims_array) = identity_claim.as_array() {
-            for claim in claims_array {
-                if let Some(claim) = claim.as_str() {
-                    encryption_context.push(zerokms::Context::new_identity_claim(claim));
-                }
-            }
-        }
-    }
-
-    if let Some(tags) = context.get("tag") {
-        if let Some(tags_array) = tags.as_array() {
-            for tag in tags_array {
-                if let Some(tag) = tag.as_str() {
-                    encryption_context.push(zerokms::Context::new_tag(tag));
-                }
-            }
-        }
-    }
-
-    if let Some(values) = context.get("value") {
-        if let Some(values_array) = values.as_array() {
-            for value_pair in values_array {
-                if let Some(pair_obj) = value_pair.as_object() {
-                    if let (Some(key), Some(value)) = (
-                        pair_obj.get("key").and_then(|k| k.as_str()),
-                        pair_obj.get("value").and_then(|v| v.as_str()),
-                    ) {
-                        encryption_context.push(zerokms::Context::new_value(key, value));
-                    }
-                }
-            }
-        }
-    }
+/// Returns the digest of an encryption configuration without creating a client.
+///
+/// The digest is the SHA-256 hash, in hex, of the parsed configuration with its keys sorted, so
+/// it is the same for configurations that differ only in formatting, key order, or the format
+/// they are written in. Pass it to [`assert_config_matches()`] to check that a client was created
+/// with the configuration. The `config` and `format` are as for [`validate_config()`].
+///
+/// # Errors
+///
+/// Returns an error if the `config` is invalid, or if the `format` is unknown.
+///
+/// # Safety
+///
+/// The caller must ensure `config` points to a valid null-terminated C string, and that
+/// `format` is either null or a valid null-terminated C string.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn get_config_digest(
+    config: *const c_char,
+    format: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("get_config_digest", || {
+        safe_ffi::c_str_to_string(config).and_then(|config| {
+            let format = safe_ffi::optional_c_str_to_string(format)?
+                .map(|format| {
+                    serde_json::from_value::<ConfigFormat>(serde_json::Value::String(format))
+                })
+                .transpose()?;
+            EncryptConfig::parse(&config, format)?.digest()
+        })
+    });
 
-    Ok(encryption_context)
+    handle_ffi_result!(result, error_out, |digest| {
+        safe_ffi::string_to_c_string(digest).unwrap_or(ptr::null_mut())
+    })
 }
 
-/// Decrypts ciphertext with optional encryption context.
+/// Returns the operation counts of a client and the shared runtime.
+///
+/// Returns a JSON object with the client's `queued` async jobs, `in_flight` operations,
+/// `completed` and `rejected` operation totals, and its `max_outstanding` limit, shared with the
+/// clients derived from it, and a `runtime` object with the runtime's `workers`, `alive_tasks`,
+/// and `global_queue_depth`, shared by every client in the process. The `search_term_cache`
+/// object has the `entries`, `capacity`, `hits`, `misses`, `evictions`, and `expirations` of the
+/// client's search term cache, and is null when the cache is disabled.
 ///
 /// # Errors
 ///
-/// Returns an error if the `ciphertext` is invalid, the encryption context JSON is malformed,
-/// or decryption fails due to key or permission issues.
+/// Returns an error if the `client` pointer is invalid or the runtime cannot be started.
 ///
 /// # Safety
 ///
-/// All pointer parameters must be valid null-terminated C strings.
+/// The `client` pointer must be a valid pointer returned by [`new_client()`].
 /// The returned pointer must be freed using [`free_string()`].
 #[no_mangle]
-pub extern "C" fn decrypt(
-    client: *const Client,
-    ciphertext: *const c_char,
-    context_json: *const c_char,
-    error_out: *mut *mut c_char,
-) -> *mut c_char {
-    let result: Result<String, Error> = runtime().and_then(|rt| {
-        rt.block_on(async {
+pub extern "C" fn get_metrics(client: *const Client, error_out: *mut *mut c_char) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("get_metrics", || {
+        runtime().and_then(|rt| {
             let client = safe_ffi::client_ref(client)?;
-            let ciphertext = safe_ffi::c_str_to_string(ciphertext)?;
-            let context = safe_ffi::optional_c_str_to_string(context_json)?;
-
-            let encryption_context = if let Some(context) = context {
-                parse_encryption_context(&context)?
-            } else {
-                Vec::new()
+            let metrics = ClientMetrics {
+                load: client.load.metrics(rt),
+                search_term_cache: client
+                    .search_term_cache
+                    .as_ref()
+                    .map(|cache| cache.metrics()),
             };
-
-            let plaintext =
-                decrypt_inner(client.clone(), ciphertext, encryption_context, None).await?;
-            Ok(plaintext)
+            serde_json::to_string(&metrics).map_err(Error::from)
         })
     });
 
-    handle_ffi_result!(result, error_out, |plaintext| {
-        safe_ffi::string_to_c_string(plaintext).unwrap_or(ptr::null_mut())
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
     })
 }
 
-async fn decrypt_inner(
-    client: Client,
-    ciphertext: String,
-    encryption_context: Vec<zerokms::Context>,
-    service_token: Option<ServiceToken>,
-) -> Result<String, Error> {
-    let encrypted_record = encrypted_record_from_mp_base85(&ciphertext, encryption_context)?;
-
-    let decrypted = client
-        .zerokms
-        .decrypt_single(encrypted_record, service_token)
-        .await?;
-
-    plaintext_from_bytes(decrypted)
+/// The metrics returned by [`get_metrics()`].
+#[derive(Serialize)]
+struct ClientMetrics {
+    /// The operation counts of the client and the runtime.
+    #[serde(flatten)]
+    load: load::Metrics,
+    /// The counts of the client's search term cache, absent when it is disabled.
+    search_term_cache: Option<CacheMetrics>,
 }
 
-fn encrypted_record_from_mp_base85(
-    base85str: &str,
+/// Removes every entry from a client's search term cache.
+///
+/// The cache is shared with the clients derived from the client under the same keyset, so
+/// their entries are removed too. Returns the number of entries removed, `0` when the cache is
+/// disabled, or `-1` with the error in `error_out` if the client pointer is invalid.
+///
+/// # Safety
+///
+/// The `client` pointer must be a valid pointer returned by [`new_client()`].
+#[no_mangle]
+pub extern "C" fn clear_search_term_cache(
+    client: *const Client,
+    error_out: *mut *mut c_char,
+) -> i32 {
+    match safe_ffi::client_ref(client) {
+        Ok(client) => {
+            safe_ffi::clear_error(error_out);
+            safe_ffi::clear_last_error();
+            let removed = client
+                .search_term_cache
+                .as_ref()
+                .map_or(0, |cache| cache.clear());
+            i32::try_from(removed).unwrap_or(i32::MAX)
+        }
+        Err(error) => {
+            safe_ffi::set_error(error_out, &error);
+            safe_ffi::set_last_error(&error);
+            -1
+        }
+    }
+}
+
+/// Returns the configuration sources a client reads at startup.
+///
+/// Returns a JSON object with the `sources` consulted, the configuration `files` read, each
+/// environment variable read with whether it is `required` and its `state` (`present`, `empty`,
+/// or `missing`), and the names of `missing_required` variables. Values are never included.
+/// Client creation that fails to configure or reach ZeroKMS includes the same diagnostics in its
+/// error.
+///
+/// # Safety
+///
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn get_startup_diagnostics(error_out: *mut *mut c_char) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("get_startup_diagnostics", || {
+        serde_json::to_string(&StartupDiagnostics::collect()).map_err(Error::from)
+    });
+
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
+    })
+}
+
+/// Derives a client that shares an existing client's ciphers, ZeroKMS connection, and
+/// configuration, with a different default encryption context or keyset.
+///
+/// The `overlay_json` parameter is an object with optional `context`, `keyset`, and
+/// `write_only` fields. Requests to the derived client that supply no encryption context use
+/// `context`, and columns without a keyset of their own are encrypted under the named `keyset`,
+/// which must be one of the configuration's `keysets`. Setting `write_only` disables decryption
+/// on the derived client, and a write-only client cannot derive one that decrypts. Nothing is
+/// re-initialized, so a client can be derived for each request. The parent and derived clients are independent and may be freed in any order.
+///
+/// # Errors
+///
+/// Returns an error if the `client` pointer is invalid, the overlay is malformed, its context is
+/// invalid, or its keyset is not configured.
+///
+/// # Safety
+///
+/// The `client` pointer must be a valid pointer returned by [`new_client()`], and `overlay_json`
+/// must be a valid null-terminated C string.
+/// The returned pointer must be freed using [`free_client()`].
+#[no_mangle]
+pub extern "C" fn derive_client(
+    client: *const Client,
+    overlay_json: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut Client {
+    let result: Result<Client, Error> = safe_ffi::catch_panic("derive_client", || {
+        safe_ffi::client_ref(client).and_then(|client| {
+            let overlay_json = safe_ffi::c_str_to_string(overlay_json)?;
+            let overlay: ClientOverlay = serde_json::from_str(&overlay_json)?;
+            client.derive(overlay)
+        })
+    });
+
+    handle_ffi_result!(result, error_out, safe_ffi::client_into_raw)
+}
+
+/// Expands a reference to a context preset into the encryption context it stands for.
+///
+/// The `context_json` parameter is any encryption context a request accepts, such as
+/// `"@tenant_scoped"` or `{"@tenant_scoped": {"tenant": "acme"}}`, which reference a preset under
+/// the configuration's `contexts`. Returns the context requests using it are encrypted with, so
+/// the context policy of a call site can be inspected or logged. Contexts that do not reference
+/// a preset are returned unchanged.
+///
+/// # Errors
+///
+/// Returns an error if the `client` pointer is invalid, the context is malformed, it references
+/// an unknown preset, or its parameters do not match the preset's placeholders.
+///
+/// # Safety
+///
+/// The `client` pointer must be a valid pointer returned by [`new_client()`], and
+/// `context_json` must be a valid null-terminated C string.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn expand_context(
+    client: *const Client,
+    context_json: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("expand_context", || {
+        safe_ffi::client_ref(client).and_then(|client| {
+            let context_json = safe_ffi::c_str_to_string(context_json)?;
+            let context: serde_json::Value = serde_json::from_str(&context_json)?;
+            let context = client.context_presets.expand(&context)?;
+            encryption_context_from_value(&context)?;
+
+            serde_json::to_string(&context).map_err(Error::from)
+        })
+    });
+
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
+    })
+}
+
+/// The non-fatal warnings about an encryption configuration, given the columns whose `match`
+/// index sets `include_original`.
+fn config_warnings(
+    encrypt_config: &EncryptConfig,
+    include_original_columns: &[Identifier],
+) -> Vec<Warning> {
+    let mut warnings: Vec<Warning> = include_original_columns
+        .iter()
+        .map(Warning::include_original)
+        .collect();
+    warnings.extend(
+        encrypt_config
+            .lints()
+            .iter()
+            .map(|(identifier, lint, message)| Warning::config_lint(identifier, *lint, message)),
+    );
+    warnings
+}
+
+async fn new_client_inner(
+    encrypt_config: EncryptConfig,
+    options: ClientOptions,
+) -> Result<Client, Error> {
+    let include_original_columns = encrypt_config.include_original_columns();
+    if options.forbid_include_original {
+        if let Some(identifier) = include_original_columns.first() {
+            return Err(Error::InvalidColumn(
+                identifier.clone(),
+                "`include_original` is forbidden by the `forbid_include_original` client option"
+                    .to_string(),
+            ));
+        }
+    }
+    let warnings = config_warnings(&encrypt_config, &include_original_columns);
+    let config_digest = encrypt_config.digest()?;
+    let rate_limiter = options
+        .rate_limit
+        .map(|rate_limit| Arc::new(RateLimiter::new(rate_limit)));
+    let batching = options
+        .batching
+        .map(|window| Arc::new(Batching::new(window)));
+    let load = Arc::new(Load::new(options.max_outstanding));
+    let search_term_cache = options
+        .search_term_cache
+        .map(|config| TermCache::new(config).map(Arc::new))
+        .transpose()?;
+    let folded_identifiers = match options.identifier_lookup {
+        IdentifierLookup::Exact => None,
+        IdentifierLookup::CaseInsensitive => Some(Arc::new(
+            encrypt_config::case_folded_identifiers(&encrypt_config)?,
+        )),
+    };
+
+    let connected = async {
+        let console_config = ConsoleConfig::builder().with_env().build()?;
+        let cts_config = CtsConfig::builder().with_env().build()?;
+        let zerokms_config = ZeroKMSConfig::builder()
+            .add_source(EnvSource::default())
+            .console_config(&console_config)
+            .cts_config(&cts_config)
+            .build_with_client_key()?;
+
+        let zerokms = Arc::new(zerokms_config.create_client());
+        let network = Arc::new(NetworkPolicy {
+            offline: options.offline,
+            cts: endpoint(cts_config.base_url()),
+            zerokms: endpoint(zerokms_config.base_url()),
+        });
+        let probes = Arc::new(Probes {
+            credentials: zerokms_config.credentials(),
+        });
+
+        let cipher = ScopedZeroKMSNoRefresh::init(zerokms.clone(), None).await?;
+
+        let mut keyset_ciphers = HashMap::with_capacity(encrypt_config.keysets.len());
+        for (name, keyset_id) in &encrypt_config.keysets {
+            let keyset_cipher =
+                ScopedZeroKMSNoRefresh::init(zerokms.clone(), Some(*keyset_id)).await?;
+            keyset_ciphers.insert(name.clone(), Arc::new(keyset_cipher));
+        }
+
+        Ok::<_, Error>((zerokms, network, probes, cipher, keyset_ciphers))
+    };
+    let (zerokms, network, probes, cipher, keyset_ciphers) =
+        connected.await.map_err(|error| Error::ClientStartup {
+            diagnostics: serde_json::to_string(&StartupDiagnostics::collect()).unwrap_or_default(),
+            source: Box::new(error),
+        })?;
+
+    let column_keysets = encrypt_config.column_keysets();
+    let float_formats = encrypt_config.float_formats();
+    let text_policies = encrypt_config.text_policies();
+    let ste_vec_policies = encrypt_config.ste_vec_policies();
+    let context_presets = encrypt_config.context_presets()?;
+    let column_contexts = encrypt_config.column_contexts();
+    let config_map = encrypt_config.into_config_map();
+    let index_params = config_map
+        .iter()
+        .map(|(identifier, (column_config, _))| {
+            let text_policy = text_policies.get(identifier).copied().unwrap_or_default();
+            let params = IndexParams::for_column(column_config, &text_policy.normalization);
+            (identifier.clone(), params)
+        })
+        .collect();
+
+    Ok(Client {
+        cipher: Arc::new(cipher),
+        keyset_ciphers: Arc::new(keyset_ciphers),
+        keyset_ids: Arc::new(encrypt_config.keysets.clone()),
+        zerokms,
+        config_digest: config_digest.into(),
+        encrypt_config: Arc::new(config_map),
+        column_keysets: Arc::new(column_keysets),
+        float_formats: Arc::new(float_formats),
+        text_policies: Arc::new(text_policies),
+        ste_vec_policies: Arc::new(ste_vec_policies),
+        index_params: Arc::new(index_params),
+        folded_identifiers,
+        options: Arc::new(options),
+        warnings: Arc::new(warnings),
+        default_context: None,
+        column_contexts: Arc::new(column_contexts),
+        context_presets: Arc::new(context_presets),
+        rate_limiter,
+        batching,
+        load,
+        network,
+        probes,
+        search_term_cache,
+    })
+}
+
+/// The endpoint of a service, from its base URL.
+fn endpoint(url: url::Url) -> network::Endpoint {
+    network::Endpoint::new(url.to_string(), url.host_str(), url.port_or_known_default())
+}
+
+/// Encrypts plaintext for a specific table column.
+///
+/// Returns a JSON string containing the encrypted result and encryption indexes.
+///
+/// # Errors
+///
+/// Returns an error if the table/column is not found in the encryption configuration,
+/// the encryption context JSON is malformed, or encryption fails.
+///
+/// # Safety
+///
+/// All pointer parameters must be valid null-terminated C strings.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn encrypt(
+    client: *const Client,
+    plaintext: *const c_char,
+    column: *const c_char,
+    table: *const c_char,
+    context_json: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("encrypt", || {
+        runtime().and_then(|rt| {
+            rt.block_on(async {
+                let client = safe_ffi::client_ref(client)?;
+                let _operation = client.load.begin()?;
+                let plaintext = safe_ffi::c_str_to_string(plaintext)?;
+                let column = safe_ffi::c_str_to_string(column)?;
+                let table = safe_ffi::c_str_to_string(table)?;
+                let context = safe_ffi::optional_c_str_to_string(context_json)?
+                    .map(|context| serde_json::from_str::<serde_json::Value>(&context))
+                    .transpose()?;
+
+                let identifier = client.resolve(Identifier::new(table, column));
+                encrypt_to_json(client, plaintext, identifier, context).await
+            })
+        })
+    });
+
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
+    })
+}
+
+/// Encrypt a plaintext for a column and serialize its envelope, as `encrypt` does.
+async fn encrypt_to_json(
+    client: &Client,
+    plaintext: String,
+    identifier: Identifier,
+    context: Option<serde_json::Value>,
+) -> Result<String, Error> {
+    client.check_plaintext_size(&plaintext)?;
+    let context = client.request_context(context.as_ref())?;
+    let encrypted = batching::encrypt(client, plaintext, identifier, context.as_deref()).await?;
+    let envelope = client.emit(encrypted)?;
+
+    serde_json::to_string(&envelope).map_err(Error::from)
+}
+
+/// Create the plaintext targets for a value of a configured column.
+///
+/// Scalar columns produce a single target. Array columns produce a target for the whole array
+/// followed by one per element, and only the whole array carries the encryption context.
+/// Text values and elements are followed by one target per index source of the column, such as
+/// a Unicode normalization form. JSON documents whose `ste_vec` index does not cover the whole
+/// document are followed by a target for the indexed subtree. Unsigned integer plaintexts are
+/// normalized so that equal values have equal index terms, and empty plaintexts are handled
+/// according to the column's text policy.
+fn plaintext_targets(
+    plaintext: String,
+    column_config: &ColumnConfig,
+    cast_as: CastAs,
+    text_policy: TextPolicy,
+    ste_vec_policy: &SteVecPolicy,
     encryption_context: Vec<zerokms::Context>,
-) -> Result<WithContext, Error> {
-    let encrypted_record = EncryptedRecord::from_mp_base85(base85str)
-        // The error type from `to_mp_base85` isn't public, so we don't derive an error for this one.
-        // Instead, we use `map_err`.
-        .map_err(|err| Error::Base85(err.to_string()))?;
+) -> Result<Vec<PlaintextTarget>, Error> {
+    let plaintext = empty::prepare(plaintext, cast_as, text_policy)?;
+    let mut plaintext_targets = match (cast_as, cast_as.element_type()) {
+        (_, Some(element_type)) => arrays::targets(
+            &plaintext,
+            element_type,
+            column_config,
+            text_policy.normalization,
+        )?,
+        (CastAs::BigUInt, None) => {
+            vec![plaintext_target::new(
+                uints::normalize(&plaintext)?,
+                column_config,
+            )?]
+        }
+        (CastAs::Text, None) => text_policy
+            .normalization
+            .targets(plaintext, column_config)?,
+        (CastAs::JsonB, None) => ste_vec_policy.targets(plaintext, column_config)?,
+        (_, None) => vec![plaintext_target::new(plaintext, column_config)?],
+    };
+    plaintext_targets[0].context = encryption_context;
+
+    Ok(plaintext_targets)
+}
+
+async fn encrypt_inner(
+    client: &Client,
+    mut plaintext_targets: Vec<PlaintextTarget>,
+    identifier: &Identifier,
+    cast_as: &CastAs,
+    service_token: Option<ServiceToken>,
+) -> Result<Encrypted, Error> {
+    let keyset = client.column_keysets.get(identifier).map(String::as_str);
+    // The pipeline is consumed by `encrypt`, so only the cipher it wraps is reused across calls.
+    let mut pipeline = ReferencedPendingPipeline::new(client.cipher_for(keyset)?);
+    let len = plaintext_targets.len();
+    if let Some(plaintext_target) = plaintext_targets.first_mut() {
+        plaintext_target
+            .context
+            .extend(client.column_context(identifier));
+    }
+
+    for (index, plaintext_target) in plaintext_targets.into_iter().enumerate() {
+        pipeline.add_with_ref::<PlaintextTarget>(plaintext_target, index)?;
+    }
+
+    client.throttle().await?;
+    let mut source_encrypted = pipeline.encrypt(service_token).await?;
+
+    let encrypted = (0..len)
+        .map(|index| {
+            source_encrypted.remove(index).ok_or_else(|| {
+                Error::InvariantViolation(format!(
+                    "`encrypt` expected a result in the pipeline at index {index}, but there was none"
+                ))
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    to_eql_encrypted_value(
+        encrypted,
+        identifier,
+        cast_as,
+        keyset,
+        client.options.index_encoding,
+        client.source_targets(identifier),
+    )
+}
+
+/// Decrypts ciphertext with optional encryption context.
+///
+/// The `ciphertext` parameter accepts either the base85-encoded ciphertext or a complete
+/// version 1, 2, or 3 envelope JSON string, where version 3 envelopes decrypt the same as
+/// version 2 and carry their `ix` index parameters alongside. When the client has a signing
+/// key, signed envelopes are verified before decryption.
+///
+/// # Errors
+///
+/// Returns an error if the client is write-only, the `ciphertext` is invalid, the encryption
+/// context JSON is malformed, or decryption fails due to key or permission issues.
+///
+/// # Safety
+///
+/// All pointer parameters must be valid null-terminated C strings.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn decrypt(
+    client: *const Client,
+    ciphertext: *const c_char,
+    context_json: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("decrypt", || {
+        runtime().and_then(|rt| {
+            rt.block_on(async {
+                let client = safe_ffi::client_ref(client)?;
+                let _operation = client.load.begin()?;
+                let ciphertext = safe_ffi::c_str_to_string(ciphertext)?;
+                let context = safe_ffi::optional_c_str_to_string(context_json)?
+                    .map(|context| serde_json::from_str::<serde_json::Value>(&context))
+                    .transpose()?;
+
+                let context = client.request_context(context.as_ref())?;
+                batching::decrypt(client, ciphertext, context.as_deref()).await
+            })
+        })
+    });
+
+    handle_ffi_result!(result, error_out, |plaintext| {
+        safe_ffi::string_to_c_string(plaintext).unwrap_or(ptr::null_mut())
+    })
+}
+
+/// Starts encrypting plaintext for a specific table column without blocking, returning a job.
+///
+/// Takes the same parameters as [`encrypt()`]. The job's result, taken with [`job_result()`]
+/// once [`job_poll()`] reports it done, is the JSON string [`encrypt()`] would return.
+///
+/// # Errors
+///
+/// Returns an error if a parameter is invalid. Errors of the encryption itself are returned by
+/// [`job_result()`].
+///
+/// # Safety
+///
+/// All non-null pointer parameters must be valid null-terminated C strings.
+/// The returned pointer must be freed using [`job_free()`].
+#[no_mangle]
+pub extern "C" fn encrypt_async(
+    client: *const Client,
+    plaintext: *const c_char,
+    column: *const c_char,
+    table: *const c_char,
+    context_json: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut Job {
+    let result: Result<Job, Error> = safe_ffi::catch_panic("encrypt_async", || {
+        runtime().and_then(|rt| {
+            let client = safe_ffi::client_ref(client)?.clone();
+            let mut operation = client.load.queue()?;
+            let plaintext = safe_ffi::c_str_to_string(plaintext)?;
+            let column = safe_ffi::c_str_to_string(column)?;
+            let table = safe_ffi::c_str_to_string(table)?;
+            let context = safe_ffi::optional_c_str_to_string(context_json)?
+                .map(|context| serde_json::from_str::<serde_json::Value>(&context))
+                .transpose()?;
+
+            Ok(Job::spawn(rt, async move {
+                operation.start();
+                let identifier = client.resolve(Identifier::new(table, column));
+                encrypt_to_json(&client, plaintext, identifier, context).await
+            }))
+        })
+    });
+
+    handle_ffi_result!(result, error_out, safe_ffi::job_into_raw)
+}
+
+/// Starts decrypting a ciphertext without blocking, returning a job.
+///
+/// Takes the same parameters as [`decrypt()`]. The job's result, taken with [`job_result()`]
+/// once [`job_poll()`] reports it done, is the plaintext [`decrypt()`] would return.
+///
+/// # Errors
+///
+/// Returns an error if a parameter is invalid. Errors of the decryption itself are returned by
+/// [`job_result()`].
+///
+/// # Safety
+///
+/// All non-null pointer parameters must be valid null-terminated C strings.
+/// The returned pointer must be freed using [`job_free()`].
+#[no_mangle]
+pub extern "C" fn decrypt_async(
+    client: *const Client,
+    ciphertext: *const c_char,
+    context_json: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut Job {
+    let result: Result<Job, Error> = safe_ffi::catch_panic("decrypt_async", || {
+        runtime().and_then(|rt| {
+            let client = safe_ffi::client_ref(client)?.clone();
+            let mut operation = client.load.queue()?;
+            let ciphertext = safe_ffi::c_str_to_string(ciphertext)?;
+            let context = safe_ffi::optional_c_str_to_string(context_json)?
+                .map(|context| serde_json::from_str::<serde_json::Value>(&context))
+                .transpose()?;
+
+            Ok(Job::spawn(rt, async move {
+                operation.start();
+                let context = client.request_context(context.as_ref())?;
+                batching::decrypt(&client, ciphertext, context.as_deref()).await
+            }))
+        })
+    });
+
+    handle_ffi_result!(result, error_out, safe_ffi::job_into_raw)
+}
+
+/// Checks whether a job's result is ready, without blocking.
+///
+/// Returns `1` when the result is ready, `0` while the job is running, and `-1` with the error
+/// in `error_out` if the job pointer is invalid or its result has already been taken.
+///
+/// # Safety
+///
+/// The `job` pointer must be a valid pointer returned by [`encrypt_async()`] or
+/// [`decrypt_async()`] and not yet freed.
+#[no_mangle]
+pub extern "C" fn job_poll(job: *const Job, error_out: *mut *mut c_char) -> i32 {
+    match safe_ffi::job_ref(job).and_then(Job::is_done) {
+        Ok(done) => {
+            safe_ffi::clear_error(error_out);
+            safe_ffi::clear_last_error();
+            if done {
+                jobs::JOB_DONE
+            } else {
+                jobs::JOB_PENDING
+            }
+        }
+        Err(error) => {
+            safe_ffi::set_error(error_out, &error);
+            safe_ffi::set_last_error(&error);
+            jobs::JOB_ERROR
+        }
+    }
+}
+
+/// Takes the result of a finished job.
+///
+/// The result can only be taken once, and the job must still be freed with [`job_free()`].
+///
+/// # Errors
+///
+/// Returns an error if the job is still running or its result has already been taken, or the
+/// error its operation failed with.
+///
+/// # Safety
+///
+/// The `job` pointer must be a valid pointer returned by [`encrypt_async()`] or
+/// [`decrypt_async()`] and not yet freed.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn job_result(job: *mut Job, error_out: *mut *mut c_char) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("job_result", || {
+        safe_ffi::job_mut(job).and_then(Job::take_result)
+    });
+
+    handle_ffi_result!(result, error_out, |result| {
+        safe_ffi::string_to_c_string(result).unwrap_or(ptr::null_mut())
+    })
+}
+
+/// Registers a callback that receives a job's result when its operation completes.
+///
+/// The callback is invoked with `user_data` and either the result or the error message, each a
+/// string the callback must free with [`free_string()`]. If the job has already finished, the
+/// callback is invoked at once on the calling thread and `1` is returned. Otherwise `0` is
+/// returned and the callback is invoked later on a runtime thread, so it must be thread-safe,
+/// return quickly, and not call blocking functions of this library; it should only hand the
+/// result to the caller's event loop. A job with a callback keeps running when it is freed, so
+/// it can be freed as soon as the callback is registered.
+///
+/// Returns `-1` with the error in `error_out` if the job or callback pointer is invalid, the job
+/// already has a callback, or its result has already been taken.
+///
+/// # Safety
+///
+/// The `job` pointer must be a valid pointer returned by [`encrypt_async()`] or
+/// [`decrypt_async()`] and not yet freed, and `user_data` must remain valid until the callback
+/// is invoked.
+#[no_mangle]
+pub extern "C" fn job_on_complete(
+    job: *const Job,
+    callback: Option<JobCallback>,
+    user_data: *mut c_void,
+    error_out: *mut *mut c_char,
+) -> i32 {
+    let result = safe_ffi::job_ref(job).and_then(|job| {
+        let callback = callback.ok_or(Error::NullPointer)?;
+        job.on_complete(callback, user_data)
+    });
+
+    match result {
+        Ok(invoked) => {
+            safe_ffi::clear_error(error_out);
+            safe_ffi::clear_last_error();
+            if invoked {
+                jobs::JOB_DONE
+            } else {
+                jobs::JOB_PENDING
+            }
+        }
+        Err(error) => {
+            safe_ffi::set_error(error_out, &error);
+            safe_ffi::set_last_error(&error);
+            jobs::JOB_ERROR
+        }
+    }
+}
+
+/// Frees a job, cancelling its operation if it is still running without a completion callback.
+///
+/// # Safety
+///
+/// The `job` pointer must have been returned by [`encrypt_async()`] or [`decrypt_async()`] and
+/// not previously freed.
+#[no_mangle]
+pub extern "C" fn job_free(job: *mut Job) {
+    safe_ffi::free_boxed_job(job);
+}
+
+/// Decrypts the full JSON document of a `jsonb` column from its stored `sv` envelope.
+///
+/// The `envelope_json` parameter accepts the envelope exactly as read from the database, as a
+/// JSON object or as a JSON string holding the object, in any supported envelope version. The
+/// envelope's root ciphertext is decrypted, so callers do not need to extract `c` themselves.
+/// When the client has a signing key, signed envelopes are verified before decryption.
+///
+/// # Errors
+///
+/// Returns an error if the input is not an `sv` envelope, the encryption context JSON is
+/// malformed, or decryption fails due to key or permission issues.
+///
+/// # Safety
+///
+/// All non-null pointer parameters must be valid null-terminated C strings.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn decrypt_single_field(
+    client: *const Client,
+    envelope_json: *const c_char,
+    context_json: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("decrypt_single_field", || {
+        runtime().and_then(|rt| {
+            rt.block_on(async {
+                let client = safe_ffi::client_ref(client)?;
+                let _operation = client.load.begin()?;
+                let envelope_json = safe_ffi::c_str_to_string(envelope_json)?;
+                let context = safe_ffi::optional_c_str_to_string(context_json)?
+                    .map(|context| serde_json::from_str::<serde_json::Value>(&context))
+                    .transpose()?;
+
+                let (encryption_context, context_kinds) =
+                    match client.request_context(context.as_ref())? {
+                        Some(context) => with_kinds_from_value(&context)?,
+                        None => (Vec::new(), Vec::new()),
+                    };
+
+                let envelope_json = envelope::ste_vec_root(&envelope_json)?;
+                decrypt_inner(
+                    client,
+                    envelope_json,
+                    encryption_context,
+                    &context_kinds,
+                    None,
+                )
+                .await
+            })
+        })
+    });
+
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
+    })
+}
+
+async fn decrypt_inner(
+    client: &Client,
+    ciphertext: String,
+    mut encryption_context: Vec<zerokms::Context>,
+    context_kinds: &[&'static str],
+    service_token: Option<ServiceToken>,
+) -> Result<String, Error> {
+    client.ensure_can_decrypt()?;
+
+    let (ciphertext, output) = client.ciphertext_from_input(ciphertext, &mut encryption_context)?;
+    let encrypted_record = encrypted_record_from_mp_base85(&ciphertext, encryption_context)?;
+
+    client.throttle().await?;
+    let decrypted = client
+        .zerokms
+        .decrypt_single(encrypted_record, service_token)
+        .await
+        .map_err(|error| decrypt_error(error, context_kinds))?;
+
+    output.apply(plaintext_from_bytes(decrypted)?)
+}
+
+/// Map a ZeroKMS decryption error, surfacing context denials with the supplied context kinds.
+fn decrypt_error(error: zerokms::Error, context_kinds: &[&'static str]) -> Error {
+    if context::is_context_denial(&error) {
+        Error::ContextMismatch {
+            kinds: context_kinds.to_vec(),
+            source: error,
+        }
+    } else {
+        Error::ZeroKMS(error)
+    }
+}
+
+fn encrypted_record_from_mp_base85(
+    base85str: &str,
+    encryption_context: Vec<zerokms::Context>,
+) -> Result<WithContext, Error> {
+    let encrypted_record = EncryptedRecord::from_mp_base85(base85str)
+        // The error type from `to_mp_base85` isn't public, so we don't derive an error for this one.
+        // Instead, we use `map_err`.
+        .map_err(|err| Error::Base85(err.to_string()))?;
+
+    Ok(WithContext {
+        record: encrypted_record,
+        context: encryption_context,
+    })
+}
+
+fn plaintext_from_bytes(bytes: Vec<u8>) -> Result<String, Error> {
+    let plaintext = Plaintext::from_slice(bytes.as_slice())?;
+
+    match plaintext {
+        Plaintext::Utf8Str(Some(ref inner)) => Ok(inner.clone()),
+        Plaintext::JsonB(Some(ref json_value)) => {
+            serde_json::to_string(json_value).map_err(Error::from)
+        }
+        _ => Err(Error::Unimplemented(format!(
+            "plaintext decryption for type `{:?}`",
+            plaintext
+        ))),
+    }
+}
+
+fn to_eql_encrypted(
+    encrypted: encryption::Encrypted,
+    identifier: &Identifier,
+    cast_as: &CastAs,
+    keyset: Option<&str>,
+    encoding: IndexEncoding,
+) -> Result<Encrypted, Error> {
+    match (cast_as, encrypted) {
+        // JSONB always uses SteVec format. Array and scalar roots are handled like object roots,
+        // with `c` holding the whole document.
+        (CastAs::JsonB, encrypted) => {
+            let (ciphertext, ste_vec_index) = match encrypted {
+                encryption::Encrypted::SteVec(ste_vec_index) => {
+                    let root_ciphertext = ste_vec_index.root_ciphertext().map_err(|e| {
+                        Error::InvariantViolation(format!("failed to get root ciphertext: {}", e))
+                    })?;
+
+                    let ciphertext = root_ciphertext
+                        .to_mp_base85()
+                        // The error type from `to_mp_base85` isn't public, so we don't derive an error for this one.
+                        // Instead, we use `map_err`.
+                        .map_err(|err| Error::Base85(err.to_string()))?;
+
+                    let ste_vec_entries: Result<Vec<SteVecEntry>, Error> = ste_vec_index
+                        .into_iter()
+                        .map(|entry| {
+                            let record = entry
+                                .record
+                                .to_mp_base85()
+                                // The error type from `to_mp_base85` isn't public, so we don't derive an error for this one.
+                                // Instead, we use `map_err`.
+                                .map_err(|err| Error::Base85(err.to_string()))?;
+
+                            Ok(SteVecEntry {
+                                tokenized_selector: encoding
+                                    .encode(entry.tokenized_selector.as_bytes()),
+                                term: encoding.encode(
+                                    &serde_json::to_vec(&entry.term).map_err(Error::Parse)?,
+                                ),
+                                record,
+                                parent_is_array: entry.parent_is_array,
+                            })
+                        })
+                        .collect();
+
+                    (ciphertext, Some(ste_vec_entries?))
+                }
+                encryption::Encrypted::Record(ciphertext, _terms) => {
+                    let ciphertext = ciphertext
+                        .to_mp_base85()
+                        // The error type from `to_mp_base85` isn't public, so we don't derive an error for this one.
+                        // Instead, we use `map_err`.
+                        .map_err(|err| Error::Base85(err.to_string()))?;
+
+                    (ciphertext, None)
+                }
+            };
+
+            Ok(Encrypted::SteVec {
+                ciphertext,
+                data_type: cast_as.to_string(),
+                ste_vec_index,
+                identifier: identifier.to_owned(),
+                keyset: keyset.map(str::to_string),
+                expires_at: None,
+                version: envelope::CURRENT_VERSION,
+            })
+        }
+
+        // Non-JSONB types with indexes
+        (_, encryption::Encrypted::Record(ciphertext, terms)) => {
+            let ciphertext = ciphertext
+                .to_mp_base85()
+                // The error type from `to_mp_base85` isn't public, so we don't derive an error for this one.
+                // Instead, we use `map_err`.
+                .map_err(|err| Error::Base85(err.to_string()))?;
+
+            let mut unique_index = None;
+            let mut ore_index = None;
+            let mut match_index = None;
+
+            for index_term in terms {
+                match index_term {
+                    IndexTerm::Binary(bytes) => {
+                        unique_index = Some(format_index_term_binary(&bytes, encoding))
+                    }
+                    IndexTerm::BitMap(inner) => match_index = Some(inner),
+                    IndexTerm::OreArray(vec_of_bytes) => {
+                        ore_index = Some(format_index_term_ore_array(&vec_of_bytes, encoding));
+                    }
+                    IndexTerm::OreFull(bytes) => {
+                        ore_index = Some(format_index_term_ore(&bytes, encoding));
+                    }
+                    IndexTerm::OreLeft(bytes) => {
+                        ore_index = Some(format_index_term_ore(&bytes, encoding));
+                    }
+                    IndexTerm::Null => {}
+                    term => return Err(Error::Unimplemented(format!("index term `{term:?}`"))),
+                };
+            }
+
+            Ok(Encrypted::Ciphertext {
+                ciphertext,
+                data_type: cast_as.to_string(),
+                unique_index,
+                ore_index,
+                match_index,
+                elements: None,
+                identifier: identifier.to_owned(),
+                keyset: keyset.map(str::to_string),
+                expires_at: None,
+                version: envelope::CURRENT_VERSION,
+            })
+        }
+
+        // Non-JSONB types should never return SteVec
+        (_, encryption::Encrypted::SteVec(_)) => Err(Error::InvariantViolation(
+            "non-JSONB type returned SteVec from encryption library".to_string(),
+        )),
+    }
+}
+
+/// Convert the pipeline results for a single value into the EQL payload format.
+///
+/// The first result is the value itself, followed by the results of its index sources, whose
+/// index terms are attached to the payload. For array columns, the remaining results are the
+/// array's elements, each followed by `source_targets` results of its index sources. Only the
+/// ciphertext of the value itself is kept.
+fn to_eql_encrypted_value(
+    encrypted: Vec<encryption::Encrypted>,
+    identifier: &Identifier,
+    cast_as: &CastAs,
+    keyset: Option<&str>,
+    encoding: IndexEncoding,
+    source_targets: usize,
+) -> Result<Encrypted, Error> {
+    let mut encrypted = encrypted.into_iter();
+    let value = encrypted.next().ok_or_else(|| {
+        Error::InvariantViolation("expected an encrypted value, but there was none".to_string())
+    })?;
+
+    let mut payload = to_eql_encrypted(value, identifier, cast_as, keyset, encoding)?;
+
+    match (cast_as.element_type(), &mut payload) {
+        (Some(element_type), Encrypted::Ciphertext { elements, .. }) => {
+            let mut element_terms = Vec::new();
+
+            while let Some(element) = encrypted.next() {
+                let mut terms = index_terms(element, identifier, &element_type, keyset, encoding)?;
+                for source in encrypted.by_ref().take(source_targets) {
+                    terms.merge(index_terms(
+                        source,
+                        identifier,
+                        &element_type,
+                        keyset,
+                        encoding,
+                    )?);
+                }
+                element_terms.push(terms);
+            }
+
+            *elements = Some(element_terms);
+        }
+        (
+            None,
+            Encrypted::Ciphertext {
+                unique_index,
+                ore_index,
+                match_index,
+                ..
+            },
+        ) => {
+            for source in encrypted {
+                let terms = index_terms(source, identifier, cast_as, keyset, encoding)?;
+                *unique_index = unique_index.take().or(terms.unique_index);
+                *ore_index = ore_index.take().or(terms.ore_index);
+                *match_index = match_index.take().or(terms.match_index);
+            }
+        }
+        (None, Encrypted::SteVec { ste_vec_index, .. }) => {
+            for source in encrypted {
+                if let Encrypted::SteVec {
+                    ste_vec_index: source_index,
+                    ..
+                } = to_eql_encrypted(source, identifier, cast_as, keyset, encoding)?
+                {
+                    *ste_vec_index = ste_vec_index.take().or(source_index);
+                }
+            }
+        }
+        _ => {}
+    }
+
+    Ok(payload)
+}
+
+/// Convert a pipeline result whose ciphertext is discarded into its index terms.
+fn index_terms(
+    encrypted: encryption::Encrypted,
+    identifier: &Identifier,
+    cast_as: &CastAs,
+    keyset: Option<&str>,
+    encoding: IndexEncoding,
+) -> Result<ElementTerms, Error> {
+    match to_eql_encrypted(encrypted, identifier, cast_as, keyset, encoding)? {
+        Encrypted::Ciphertext {
+            unique_index,
+            ore_index,
+            match_index,
+            ..
+        } => Ok(ElementTerms {
+            unique_index,
+            ore_index,
+            match_index,
+        }),
+        Encrypted::SteVec { .. } => Err(Error::InvariantViolation(
+            "index terms returned SteVec from encryption library".to_string(),
+        )),
+    }
+}
+
+/// Formats HMAC index bytes into an encoded string.
+fn format_index_term_binary(index_bytes: &[u8], encoding: IndexEncoding) -> String {
+    encoding.encode(index_bytes)
+}
+
+/// Formats ORE index bytes into an encoded string.
+fn format_index_term_ore_bytes(index_bytes: &[u8], encoding: IndexEncoding) -> String {
+    encoding.encode(index_bytes)
+}
+
+/// Formats ORE index array bytes into encoded strings.
+fn format_index_term_ore_array(
+    ore_byte_arrays: &[Vec<u8>],
+    encoding: IndexEncoding,
+) -> Vec<String> {
+    ore_byte_arrays
+        .iter()
+        .map(|index_bytes| format_index_term_ore_bytes(index_bytes, encoding))
+        .collect()
+}
+
+/// Formats ORE index bytes into a single-element encoded string array.
+fn format_index_term_ore(index_bytes: &[u8], encoding: IndexEncoding) -> Vec<String> {
+    vec![format_index_term_ore_bytes(index_bytes, encoding)]
+}
+
+/// Bulk encryption request item containing plaintext data and metadata.
+///
+/// String fields borrow from the request buffer where possible.
+#[derive(Deserialize)]
+struct BulkEncryptItem<'a> {
+    /// Optional client-supplied identifier echoed back with the result.
+    #[serde(default)]
+    id: Option<serde_json::Value>,
+    /// The plaintext data to encrypt.
+    #[serde(borrow)]
+    plaintext: Cow<'a, str>,
+    /// The target column name.
+    #[serde(borrow)]
+    column: Cow<'a, str>,
+    /// The target table name.
+    #[serde(borrow)]
+    table: Cow<'a, str>,
+    /// Optional encryption context (defaults to empty if not provided).
+    #[serde(default)]
+    context: Option<serde_json::Value>,
+    /// Whether to skip the `unique` index term, leaving `hm` null.
+    #[serde(default)]
+    skip_unique: bool,
+    /// Whether to skip the `ore` index terms, leaving `ob` null.
+    #[serde(default)]
+    skip_ore: bool,
+    /// Whether to skip the `match` index terms, leaving `bf` null.
+    #[serde(default)]
+    skip_match: bool,
+    /// When the value expires, in seconds since the Unix epoch. The expiry is recorded in the
+    /// envelope and bound to the ciphertext as encryption context.
+    #[serde(default)]
+    expires_at: Option<u64>,
+}
+
+impl BulkEncryptItem<'_> {
+    /// The column configuration without the indexes this item opts out of, so that their
+    /// index terms are not computed in the pipeline.
+    fn index_config<'c>(&self, column_config: &'c ColumnConfig) -> Cow<'c, ColumnConfig> {
+        if !(self.skip_unique || self.skip_ore || self.skip_match) {
+            return Cow::Borrowed(column_config);
+        }
+
+        let mut column_config = column_config.clone();
+        column_config
+            .indexes
+            .retain(|index| match index.index_type {
+                IndexType::Unique { .. } => !self.skip_unique,
+                IndexType::Ore => !self.skip_ore,
+                IndexType::Match { .. } => !self.skip_match,
+                IndexType::SteVec { .. } => true,
+            });
+
+        Cow::Owned(column_config)
+    }
+
+    /// The skip flags this item sets for indexes its column does not have.
+    fn ignored_skip_flags(&self, column_config: &ColumnConfig) -> Vec<&'static str> {
+        let has_index = |matches: fn(&IndexType) -> bool| {
+            column_config
+                .indexes
+                .iter()
+                .any(|index| matches(&index.index_type))
+        };
+
+        [
+            (
+                "skip_unique",
+                self.skip_unique,
+                has_index(|index_type| matches!(index_type, IndexType::Unique { .. })),
+            ),
+            (
+                "skip_ore",
+                self.skip_ore,
+                has_index(|index_type| matches!(index_type, IndexType::Ore)),
+            ),
+            (
+                "skip_match",
+                self.skip_match,
+                has_index(|index_type| matches!(index_type, IndexType::Match { .. })),
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, set, has_index)| *set && !has_index)
+        .map(|(flag, _, _)| flag)
+        .collect()
+    }
+}
+
+/// Bulk decryption request item containing ciphertext and optional context.
+///
+/// The ciphertext borrows from the request buffer where possible.
+#[derive(Deserialize)]
+struct BulkDecryptItem<'a> {
+    /// Optional client-supplied identifier echoed back with the result.
+    #[serde(default)]
+    id: Option<serde_json::Value>,
+    /// The ciphertext to decrypt.
+    #[serde(borrow)]
+    ciphertext: Cow<'a, str>,
+    /// Optional encryption context (defaults to empty if not provided).
+    #[serde(default)]
+    context: Option<serde_json::Value>,
+}
+
+/// Bulk operation result paired with the client-supplied item identifier.
+#[derive(Serialize)]
+struct BulkResult<T> {
+    /// The position of the request item, which is also the position of the result.
+    index: usize,
+    /// The identifier supplied with the request item, or null if none was supplied.
+    id: Option<serde_json::Value>,
+    /// The operation result for the item.
+    result: T,
+}
+
+/// Bulk operation results, as a positional array or paired with item identifiers.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum BulkResults<T> {
+    Positional(Vec<T>),
+    Identified(Vec<BulkResult<T>>),
+}
+
+/// Bulk encryption results with the payload statistics and warnings requested in the options.
+#[derive(Serialize)]
+struct BulkEncryptResponse<T> {
+    results: BulkResults<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<stats::BulkStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    warnings: Option<Vec<Warning>>,
+}
+
+/// Pair bulk results with their item positions and identifiers when any item supplied an
+/// identifier.
+///
+/// Results are returned as a plain positional array when no identifiers were supplied.
+fn bulk_results<T>(results: Vec<T>, ids: Vec<Option<serde_json::Value>>) -> BulkResults<T> {
+    if ids.iter().all(Option::is_none) {
+        return BulkResults::Positional(results);
+    }
+
+    BulkResults::Identified(
+        ids.into_iter()
+            .zip(results)
+            .enumerate()
+            .map(|(index, (id, result))| BulkResult { index, id, result })
+            .collect(),
+    )
+}
+
+/// Arrange the results of a bulk operation in the order of its items.
+///
+/// Bulk operations group items, such as by keyset, and complete the groups in any order, so each
+/// result is placed by the position of its item rather than the order it was produced in. The
+/// results are sorted in place, which takes a single pass when they are already in order, as
+/// they are when every item uses the same keyset, and the sorted results reuse their allocation.
+///
+/// # Errors
+///
+/// Returns [`Error::InvariantViolation`] unless there is exactly one result for each of the
+/// `len` items.
+fn in_item_order<T>(
+    operation: &str,
+    len: usize,
+    mut results: Vec<(usize, T)>,
+) -> Result<Vec<T>, Error> {
+    results.sort_unstable_by_key(|(index, _)| *index);
+
+    for (position, &(index, _)) in results.iter().enumerate() {
+        if index >= len {
+            return Err(Error::InvariantViolation(format!(
+                "`{operation}` produced a result for index {index}, but there are only {len} items"
+            )));
+        }
+        if index < position {
+            return Err(Error::InvariantViolation(format!(
+                "`{operation}` produced more than one result for the item at index {index}"
+            )));
+        }
+        if index > position {
+            return Err(Error::InvariantViolation(format!(
+                "`{operation}` produced no result for the item at index {position}"
+            )));
+        }
+    }
+
+    if results.len() < len {
+        return Err(Error::InvariantViolation(format!(
+            "`{operation}` produced no result for the item at index {}",
+            results.len()
+        )));
+    }
+
+    Ok(results.into_iter().map(|(_, result)| result).collect())
+}
+
+/// Serialize bulk results, pairing each with its item identifier when any item supplied one.
+fn bulk_results_to_json<T: Serialize>(
+    results: Vec<T>,
+    ids: Vec<Option<serde_json::Value>>,
+) -> Result<String, Error> {
+    serde_json::to_string(&bulk_results(results, ids)).map_err(Error::from)
+}
+
+/// Deserializes a bulk request array from a request buffer, usually borrowed from the caller's
+/// C string.
+///
+/// Without the `simd-json` feature, the buffer is only read, so a borrowed request is never
+/// copied and string fields declared as borrowed point into the caller's string. With it, the
+/// buffer is copied once if borrowed, then parsed in place using SIMD instructions. Either way,
+/// string fields declared as borrowed avoid a copy where the parser allows it.
+fn parse_bulk_items<'a, T: Deserialize<'a>>(
+    buffer: &'a mut Cow<'a, [u8]>,
+) -> Result<Vec<T>, Error> {
+    #[cfg(feature = "simd-json")]
+    let items = simd_json::serde::from_slice(buffer.to_mut())
+        .map_err(|err| Error::Parse(serde::de::Error::custom(err)))?;
+    #[cfg(not(feature = "simd-json"))]
+    let items = serde_json::from_slice(&**buffer)?;
+
+    Ok(items)
+}
+
+/// Search term creation request item containing plaintext and target metadata.
+#[derive(Deserialize)]
+struct SearchTermItem<'a> {
+    /// The plaintext data to create search terms for, absent for range search terms.
+    #[serde(borrow, default)]
+    plaintext: Option<Cow<'a, str>>,
+    /// Inclusive bounds for a range query, used instead of `plaintext`.
+    #[serde(borrow, default)]
+    range: Option<SearchRange<'a>>,
+    /// Optional query operator the search term is for, checked against the column's indexes.
+    #[serde(default)]
+    operator: Option<Operator>,
+    /// Path to a field inside a `jsonb` document, to compare that field instead of the whole
+    /// document.
+    #[serde(borrow, default)]
+    path: Option<Cow<'a, str>>,
+    /// The target column name.
+    #[serde(borrow)]
+    column: Cow<'a, str>,
+    /// The target table name.
+    #[serde(borrow)]
+    table: Cow<'a, str>,
+    /// Optional encryption context (defaults to empty if not provided).
+    #[serde(default)]
+    context: Option<serde_json::Value>,
+    /// The `ix` index parameters recorded in the version 3 envelopes of the rows searched, to
+    /// check that the search term can match their index terms.
+    #[serde(default)]
+    index_params: Option<IndexParams>,
+}
+
+/// Inclusive bounds of a range search term, for `BETWEEN` queries.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SearchRange<'a> {
+    /// The lower bound plaintext.
+    #[serde(borrow)]
+    from: Cow<'a, str>,
+    /// The upper bound plaintext.
+    #[serde(borrow)]
+    to: Cow<'a, str>,
+}
+
+impl SearchRange<'_> {
+    /// Check that the range can be queried on a column.
+    ///
+    /// Range queries compare order-revealing encryption terms, so the column must have an `ore`
+    /// index. Date bounds must also be in calendar order, since a reversed range matches nothing.
+    fn validate(&self, column_config: &ColumnConfig, cast_as: CastAs) -> Result<(), Error> {
+        let has_ore_index = column_config
+            .indexes
+            .iter()
+            .any(|index| matches!(index.index_type, IndexType::Ore));
+
+        if !has_ore_index {
+            return Err(Error::InvalidSearchTerm(
+                "range search terms require an `ore` index on the column".to_string(),
+            ));
+        }
+
+        if cast_as == CastAs::Date && dates::parse(&self.from)? > dates::parse(&self.to)? {
+            return Err(Error::InvalidSearchTerm(
+                "the range lower bound is after its upper bound".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Encrypts multiple plaintext items in bulk.
+///
+/// # Errors
+///
+/// Returns an error if the JSON input is malformed, contains unknown column/table
+/// combinations, has invalid encryption context, or if encryption fails.
+///
+/// # Safety
+///
+/// All pointer parameters must be valid null-terminated C strings.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn encrypt_bulk(
+    client: *const Client,
+    items_json: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    encrypt_bulk_with_options(client, items_json, ptr::null(), error_out)
+}
+
+/// Encrypts multiple plaintext items in bulk, with response options.
+///
+/// The `options_json` parameter is optional. When null, default options are used. Setting
+/// `stats` returns an object with the `results` array and payload `stats`: item counts,
+/// plaintext, ciphertext, index term, and stored byte totals, a plaintext size histogram, and
+/// the ratio of stored to plaintext bytes, in total and for each `table.column`. Setting
+/// `include_warnings` returns an object with the `results` array and a `warnings` array of
+/// non-fatal issues, each with a `code`, a `message`, and the `index` of the item it concerns.
+///
+/// # Errors
+///
+/// Returns an error if the JSON input or options are malformed, contain unknown column/table
+/// combinations, have invalid encryption context, or if encryption fails.
+///
+/// # Safety
+///
+/// The `client` and `items_json` pointers must be valid, and `options_json` must be either
+/// null or a valid null-terminated C string.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn encrypt_bulk_with_options(
+    client: *const Client,
+    items_json: *const c_char,
+    options_json: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("encrypt_bulk_with_options", || {
+        runtime().and_then(|rt| {
+            rt.block_on(async {
+                let client = safe_ffi::client_ref(client)?;
+                let _operation = client.load.begin()?;
+                let mut items_buffer =
+                    Cow::Borrowed(unsafe { safe_ffi::c_str_to_str(items_json) }?.as_bytes());
+                let items: Vec<BulkEncryptItem> = parse_bulk_items(&mut items_buffer)?;
+                let options: stats::BulkEncryptOptions =
+                    match safe_ffi::optional_c_str_to_string(options_json)? {
+                        Some(options_json) => serde_json::from_str(&options_json)?,
+                        None => stats::BulkEncryptOptions::default(),
+                    };
+
+                let mut bulk_targets = Vec::with_capacity(items.len());
+                let mut ids = Vec::with_capacity(items.len());
+                let mut expiries = Vec::with_capacity(items.len());
+                let mut plaintext_lengths = Vec::with_capacity(items.len());
+                let mut warnings = Vec::new();
+                let mut contexts = ContextCache::default();
+
+                for (index, item) in items.into_iter().enumerate() {
+                    ids.push(item.id);
+                    plaintext_lengths.push(item.plaintext.len());
+                    client.check_plaintext_size(&item.plaintext)?;
+
+                    let mut encryption_context =
+                        match client.request_context(item.context.as_ref())? {
+                            Some(context_value) => contexts.encryption_context(&context_value)?,
+                            None => Vec::new(),
+                        };
+                    if let Some(expires_at) = item.expires_at {
+                        expiry::check_version(client.options.emit_version)?;
+                        encryption_context.push(expiry::context(expires_at));
+                    }
+                    expiries.push(item.expires_at);
+
+                    let identifier = client.resolve(Identifier::new(item.table, item.column));
+                    let (column_config, cast_as) = client.column(&identifier)?;
+
+                    let text_policy = client.text_policy(&identifier);
+                    if options.include_warnings {
+                        for flag in item.ignored_skip_flags(column_config) {
+                            warnings.push(Warning::option_ignored(index, flag, &identifier));
+                        }
+                        if text_policy.trim
+                            && *cast_as == CastAs::Text
+                            && item.plaintext.trim() != item.plaintext
+                        {
+                            warnings.push(Warning::plaintext_trimmed(index, &identifier));
+                        }
+                        if *cast_as == CastAs::Text
+                            && text_policy
+                                .normalization
+                                .match_limits
+                                .is_some_and(|limits| {
+                                    limits.overflow.is_truncate() && limits.exceeds(&item.plaintext)
+                                })
+                        {
+                            warnings.push(Warning::match_input_truncated(index, &identifier));
+                        }
+                    }
+
+                    let column_config = item.index_config(column_config);
+                    let item_targets = plaintext_targets(
+                        item.plaintext.into_owned(),
+                        &column_config,
+                        *cast_as,
+                        text_policy,
+                        client.ste_vec_policy(&identifier),
+                        encryption_context,
+                    )?;
+
+                    bulk_targets.push((item_targets, identifier, *cast_as));
+                }
+
+                if options.include_warnings
+                    && client.options.emit_version < envelope::CURRENT_VERSION
+                {
+                    warnings.push(Warning::deprecated_envelope_version(
+                        client.options.emit_version,
+                    ));
+                }
+
+                let mut encrypted_results = encrypt_bulk_inner(client, bulk_targets, None).await?;
+                for (encrypted, expires_at) in encrypted_results.iter_mut().zip(expiries) {
+                    encrypted.set_expires_at(expires_at);
+                }
+
+                if !options.stats && !options.include_warnings {
+                    let envelopes = encrypted_results
+                        .into_iter()
+                        .map(|encrypted| client.emit(encrypted))
+                        .collect::<Result<Vec<_>, Error>>()?;
+
+                    return bulk_results_to_json(envelopes, ids);
+                }
+
+                let mut stats = options.stats.then(stats::BulkStats::default);
+                let mut envelopes = Vec::with_capacity(encrypted_results.len());
+
+                for (encrypted, plaintext_length) in
+                    encrypted_results.into_iter().zip(plaintext_lengths)
+                {
+                    match &mut stats {
+                        Some(stats) => {
+                            let measured = stats::measure(&encrypted)?;
+                            let envelope = client.emit(encrypted)?;
+                            stats.record(plaintext_length, measured, &envelope)?;
+                            envelopes.push(envelope);
+                        }
+                        None => envelopes.push(client.emit(encrypted)?),
+                    }
+                }
+
+                serde_json::to_string(&BulkEncryptResponse {
+                    results: bulk_results(envelopes, ids),
+                    stats,
+                    warnings: options.include_warnings.then_some(warnings),
+                })
+                .map_err(Error::from)
+            })
+        })
+    });
+
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
+    })
+}
+
+/// Checks bulk encryption items against the configuration without encrypting them.
+///
+/// Accepts the same items as [`encrypt_bulk()`]. Returns a JSON object whose `valid` field is
+/// true when every item would encrypt, and whose `items` describe each item in request order:
+/// its `cast_as`, envelope `kind`, the `indexes` that would produce terms, the named `keyset`,
+/// the number of array `elements`, the plaintext `conversions` that would be applied, and the
+/// `error` encryption would fail with. No ZeroKMS requests are made.
+///
+/// # Errors
+///
+/// Returns an error if the items JSON is malformed. Problems with individual items are
+/// reported in the plan instead.
+///
+/// # Safety
+///
+/// All pointer parameters must be valid null-terminated C strings.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn plan_encrypt(
+    client: *const Client,
+    items_json: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("plan_encrypt", || {
+        safe_ffi::client_ref(client).and_then(|client| {
+            let mut items_buffer =
+                Cow::Borrowed(unsafe { safe_ffi::c_str_to_str(items_json) }?.as_bytes());
+            let items: Vec<BulkEncryptItem> = parse_bulk_items(&mut items_buffer)?;
+
+            serde_json::to_string(&plan::plan(client, items)).map_err(Error::from)
+        })
+    });
+
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
+    })
+}
+
+async fn encrypt_bulk_inner(
+    client: &Client,
+    plaintext_targets: Vec<(Vec<PlaintextTarget>, Identifier, CastAs)>,
+    service_token: Option<ServiceToken>,
+) -> Result<Vec<Encrypted>, Error> {
+    let len = plaintext_targets.len();
+    let mut identifiers: Vec<Identifier> = Vec::with_capacity(len);
+    let mut cast_types: Vec<CastAs> = Vec::with_capacity(len);
+    // Each keyset has its own scoped cipher, so targets are encrypted in one pipeline per keyset.
+    let mut batches: HashMap<Option<&str>, Vec<(usize, Vec<PlaintextTarget>)>> = HashMap::new();
+
+    for (index, (mut item_targets, identifier, cast_type)) in
+        plaintext_targets.into_iter().enumerate()
+    {
+        if let Some(plaintext_target) = item_targets.first_mut() {
+            plaintext_target
+                .context
+                .extend(client.column_context(&identifier));
+        }
+        let keyset = client.column_keysets.get(&identifier).map(String::as_str);
+        // Most columns share the client's keyset, so a new batch is sized for every remaining
+        // item, which it can hold without reallocating.
+        batches
+            .entry(keyset)
+            .or_insert_with(|| Vec::with_capacity(len - index))
+            .push((index, item_targets));
+        identifiers.push(identifier);
+        cast_types.push(cast_type);
+    }
+
+    let mut results: Vec<(usize, Encrypted)> = Vec::with_capacity(len);
+
+    for (keyset, batch) in batches {
+        let mut pipeline = ReferencedPendingPipeline::new(client.cipher_for(keyset)?);
+        // Array items add several targets, so pipeline references are assigned sequentially and
+        // each item records the range of references holding its results.
+        let mut item_refs = Vec::with_capacity(batch.len());
+        let mut next_ref = 0;
+
+        for (index, item_targets) in batch {
+            let start = next_ref;
+            for plaintext_target in item_targets {
+                pipeline.add_with_ref::<PlaintextTarget>(plaintext_target, next_ref)?;
+                next_ref += 1;
+            }
+            item_refs.push((index, start..next_ref));
+        }
+
+        client.throttle().await?;
+        let mut source_encrypted = pipeline.encrypt(service_token.clone()).await?;
+
+        for (index, refs) in item_refs {
+            let encrypted = refs
+                .map(|pipeline_ref| {
+                    source_encrypted.remove(pipeline_ref).ok_or_else(|| {
+                        Error::InvariantViolation(format!(
+                            "`encrypt_bulk` expected a result in the pipeline at index {pipeline_ref}, but there was none"
+                        ))
+                    })
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            let identifier = &identifiers[index];
+            let cast_as = &cast_types[index];
+
+            results.push((
+                index,
+                to_eql_encrypted_value(
+                    encrypted,
+                    identifier,
+                    cast_as,
+                    keyset,
+                    client.options.index_encoding,
+                    client.source_targets(identifier),
+                )?,
+            ));
+        }
+    }
+
+    in_item_order("encrypt_bulk", len, results)
+}
+
+/// Decrypts multiple ciphertext items in bulk.
+///
+/// # Errors
+///
+/// Returns an error if the client is write-only, the JSON input is malformed, contains invalid
+/// `ciphertext`, has malformed encryption context, or if decryption fails.
+///
+/// # Safety
+///
+/// All pointer parameters must be valid null-terminated C strings.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn decrypt_bulk(
+    client: *const Client,
+    items_json: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("decrypt_bulk", || {
+        runtime().and_then(|rt| {
+            rt.block_on(async {
+                let client = safe_ffi::client_ref(client)?;
+                let _operation = client.load.begin()?;
+                let mut items_buffer =
+                    Cow::Borrowed(unsafe { safe_ffi::c_str_to_str(items_json) }?.as_bytes());
+                let items: Vec<BulkDecryptItem> = parse_bulk_items(&mut items_buffer)?;
+
+                let mut ciphertexts = Vec::with_capacity(items.len());
+                let mut context_kinds = Vec::new();
+                let mut ids = Vec::with_capacity(items.len());
+                let mut contexts = ContextCache::default();
+
+                for item in items {
+                    ids.push(item.id);
+
+                    let encryption_context = match client.request_context(item.context.as_ref())? {
+                        Some(context_value) => {
+                            let (encryption_context, kinds) =
+                                contexts.with_kinds(&context_value)?;
+                            context_kinds.extend(kinds);
+                            encryption_context
+                        }
+                        None => Vec::new(),
+                    };
+
+                    ciphertexts.push((item.ciphertext.into_owned(), encryption_context));
+                }
+
+                context_kinds.sort_unstable();
+                context_kinds.dedup();
+
+                let plaintexts =
+                    decrypt_bulk_inner(client, ciphertexts, &context_kinds, None).await?;
+                bulk_results_to_json(plaintexts, ids)
+            })
+        })
+    });
+
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
+    })
+}
+
+async fn decrypt_bulk_inner(
+    client: &Client,
+    ciphertexts: Vec<(String, Vec<zerokms::Context>)>,
+    context_kinds: &[&'static str],
+    service_token: Option<ServiceToken>,
+) -> Result<Vec<String>, Error> {
+    client.ensure_can_decrypt()?;
+
+    let len = ciphertexts.len();
+    let mut encrypted_records: Vec<WithContext> = Vec::with_capacity(ciphertexts.len());
+    let mut outputs: Vec<PlaintextOutput> = Vec::with_capacity(ciphertexts.len());
+
+    for (ciphertext, mut encryption_context) in ciphertexts {
+        let (ciphertext, output) =
+            client.ciphertext_from_input(ciphertext, &mut encryption_context)?;
+        let encrypted_record = encrypted_record_from_mp_base85(&ciphertext, encryption_context)?;
+        encrypted_records.push(encrypted_record);
+        outputs.push(output);
+    }
+
+    client.throttle().await?;
+    let decrypted = client
+        .zerokms
+        .decrypt(encrypted_records, service_token)
+        .await
+        .map_err(|error| decrypt_error(error, context_kinds))?;
+
+    // ZeroKMS returns plaintexts in request order, so a missing plaintext would shift every
+    // later result onto the wrong item.
+    if decrypted.len() != len {
+        return Err(Error::InvariantViolation(format!(
+            "`decrypt_bulk` expected {len} plaintexts from ZeroKMS, but received {}",
+            decrypted.len()
+        )));
+    }
+
+    let mut plaintexts: Vec<String> = Vec::with_capacity(len);
+
+    for (item, output) in decrypted.into_iter().zip(outputs) {
+        plaintexts.push(output.apply(plaintext_from_bytes(item)?)?);
+    }
+
+    Ok(plaintexts)
+}
+
+/// Reindex request item containing a stored envelope and the identifier of its row.
+///
+/// The envelope borrows from the request buffer where possible.
+#[derive(Deserialize)]
+struct ReindexItem<'a> {
+    /// Client-supplied identifier of the row, which keys its patch.
+    id: serde_json::Value,
+    /// The stored envelope.
+    #[serde(borrow)]
+    ciphertext: Cow<'a, str>,
+    /// Optional encryption context (defaults to empty if not provided).
+    #[serde(default)]
+    context: Option<serde_json::Value>,
+}
+
+/// A merge patch that replaces the `match` index terms of a row's envelope.
+#[derive(Serialize)]
+struct ReindexPatch {
+    /// The identifier supplied with the request item.
+    id: serde_json::Value,
+    /// JSON Merge Patch (RFC 7386) to apply to the stored envelope.
+    patch: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A row being reindexed, with the parts of its envelope its patch is built from.
+struct ReindexRow {
+    id: serde_json::Value,
+    identifier: Identifier,
+    envelope: Option<String>,
+    elements: Option<Vec<ElementTerms>>,
+    records_index_params: bool,
+}
+
+/// Regenerates the `match` index terms of stored envelopes after the parameters of their
+/// column's `match` index change, such as `k`, `m`, or the tokenizer.
+///
+/// Each item has the row's `id`, its stored envelope as `ciphertext`, and an optional
+/// `context`. The envelopes are decrypted, and their plaintexts are indexed with the column's
+/// current `match` index alone, in one pipeline per keyset. Returns a JSON array of objects
+/// with each item's `id` and a `patch`, a JSON Merge Patch (RFC 7386) that replaces the
+/// envelope's `bf` terms, and the `bf` terms of array elements in `ae`. The patch also replaces
+/// the `bf` parameters in `ix` of version 3 envelopes, and the signature of signed envelopes
+/// when the client has a signing key. The ciphertext and other index terms are unchanged.
+///
+/// # Errors
+///
+/// Returns an error if the client is write-only, the JSON input is malformed, an envelope is
+/// invalid or belongs to a column without a `match` index, has malformed encryption context,
+/// or if decryption or encryption fails.
+///
+/// # Safety
+///
+/// All pointer parameters must be valid null-terminated C strings.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn reindex_search_terms(
+    client: *const Client,
+    items_json: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("reindex_search_terms", || {
+        runtime().and_then(|rt| {
+            rt.block_on(async {
+                let client = safe_ffi::client_ref(client)?;
+                let _operation = client.load.begin()?;
+                let mut items_buffer =
+                    Cow::Borrowed(unsafe { safe_ffi::c_str_to_str(items_json) }?.as_bytes());
+                let items: Vec<ReindexItem> = parse_bulk_items(&mut items_buffer)?;
+
+                let mut ciphertexts = Vec::with_capacity(items.len());
+                let mut context_kinds = Vec::new();
+                let mut rows = Vec::with_capacity(items.len());
+                let mut contexts = ContextCache::default();
+
+                for item in items {
+                    let envelope = VersionedEnvelope::parse(&item.ciphertext)?;
+                    let identifier = client.resolve(envelope.identifier().clone());
+                    let (column_config, cast_as) = client.column(&identifier)?;
+
+                    if !column_config
+                        .indexes
+                        .iter()
+                        .any(|index| matches!(index.index_type, IndexType::Match { .. }))
+                    {
+                        return Err(Error::InvalidColumn(
+                            identifier,
+                            "the column has no `match` index to reindex".to_string(),
+                        ));
+                    }
+
+                    let (encrypted, recorded_params) = envelope.into_encrypted(cast_as);
+                    let elements = match encrypted {
+                        Encrypted::Ciphertext { elements, .. } => elements,
+                        Encrypted::SteVec { .. } => None,
+                    };
+
+                    let encryption_context = match client.request_context(item.context.as_ref())? {
+                        Some(context_value) => {
+                            let (encryption_context, kinds) =
+                                contexts.with_kinds(&context_value)?;
+                            context_kinds.extend(kinds);
+                            encryption_context
+                        }
+                        None => Vec::new(),
+                    };
+
+                    let ciphertext = item.ciphertext.into_owned();
+                    rows.push(ReindexRow {
+                        id: item.id,
+                        identifier,
+                        // Signed envelopes are kept to sign them again with their new terms.
+                        envelope: client
+                            .options
+                            .signing_key
+                            .is_some()
+                            .then(|| ciphertext.clone()),
+                        elements,
+                        records_index_params: recorded_params.is_some(),
+                    });
+                    ciphertexts.push((ciphertext, encryption_context));
+                }
+
+                context_kinds.sort_unstable();
+                context_kinds.dedup();
+
+                let plaintexts =
+                    decrypt_bulk_inner(client, ciphertexts, &context_kinds, None).await?;
+
+                // Only the `match` index is kept, so no other index terms are computed, and the new
+                // ciphertexts are discarded, so they are encrypted without context.
+                let mut bulk_targets = Vec::with_capacity(rows.len());
+                for (plaintext, row) in plaintexts.into_iter().zip(&rows) {
+                    let (column_config, cast_as) = client.column(&row.identifier)?;
+                    let mut match_config = column_config.clone();
+                    match_config
+                        .indexes
+                        .retain(|index| matches!(index.index_type, IndexType::Match { .. }));
+
+                    let item_targets = plaintext_targets(
+                        plaintext,
+                        &match_config,
+                        *cast_as,
+                        client.text_policy(&row.identifier),
+                        client.ste_vec_policy(&row.identifier),
+                        Vec::new(),
+                    )?;
+                    bulk_targets.push((item_targets, row.identifier.clone(), *cast_as));
+                }
+
+                let reindexed = encrypt_bulk_inner(client, bulk_targets, None).await?;
+
+                let patches = rows
+                    .into_iter()
+                    .zip(reindexed)
+                    .map(|(row, encrypted)| reindex_patch(client, row, encrypted))
+                    .collect::<Result<Vec<_>, Error>>()?;
+
+                Ok(serde_json::to_string(&patches)?)
+            })
+        })
+    });
+
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
+    })
+}
+
+/// Build the patch that replaces the `match` index terms of a row's envelope with those of its
+/// reindexed value.
+fn reindex_patch(
+    client: &Client,
+    row: ReindexRow,
+    reindexed: Encrypted,
+) -> Result<ReindexPatch, Error> {
+    let Encrypted::Ciphertext {
+        match_index,
+        elements: reindexed_elements,
+        ..
+    } = reindexed
+    else {
+        return Err(Error::InvariantViolation(
+            "`reindex_search_terms` expected a ciphertext envelope for a `match` index".to_string(),
+        ));
+    };
+
+    let mut patch = serde_json::Map::new();
+    patch.insert("bf".to_string(), serde_json::to_value(match_index)?);
+
+    if let (Some(mut elements), Some(reindexed_elements)) = (row.elements, reindexed_elements) {
+        for (element, reindexed) in elements.iter_mut().zip(reindexed_elements) {
+            element.match_index = reindexed.match_index;
+        }
+        patch.insert("ae".to_string(), serde_json::to_value(elements)?);
+    }
+
+    if row.records_index_params {
+        let params = client.index_params(&row.identifier);
+        patch.insert(
+            "ix".to_string(),
+            serde_json::json!({ "bf": params.r#match }),
+        );
+    }
+
+    if let (Some(signing_key), Some(envelope)) = (&client.options.signing_key, row.envelope) {
+        let mut envelope: serde_json::Value = serde_json::from_str(&envelope)?;
+        encrypt_config::merge_patch(&mut envelope, serde_json::Value::Object(patch.clone()));
+        patch.insert(
+            signing::SIGNATURE_FIELD.to_string(),
+            serde_json::Value::String(signing_key.sign(&envelope)),
+        );
+    }
+
+    Ok(ReindexPatch { id: row.id, patch })
+}
+
+/// Creates encrypted search terms for querying encrypted data.
+///
+/// Returns a JSON array of encrypted search terms that can be used in database queries.
+/// Each search term contains the encryption indexes (`unique`, `ore`, `match`, `ste_vec`)
+/// but not the full ciphertext. The values of all items, including both bounds of ranges, are
+/// encrypted together in one pipeline per keyset.
+///
+/// Items with a `range` instead of a `plaintext` return an object with `from` and `to` search
+/// terms for the inclusive bounds of a `BETWEEN` query.
+///
+/// Items for `jsonb` columns with a `path` compare the field at that path inside documents
+/// instead of the whole document, returning the single `ste_vec` entry for the field, whose
+/// term supports equality and range operators.
+///
+/// # Errors
+///
+/// Returns an error if the JSON input is malformed, contains unknown column/table
+/// combinations, has invalid encryption context, requests a range on a column without an
+/// `ore` index, or if encryption fails.
+///
+/// # Safety
+///
+/// All pointer parameters must be valid null-terminated C strings.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn create_search_terms(
+    client: *const Client,
+    terms_json: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    create_search_terms_with_options(client, terms_json, ptr::null(), error_out)
+}
+
+/// Creates encrypted search terms for querying encrypted data, with output options.
+///
+/// The `options_json` parameter is optional. When null, default options are used. Setting
+/// `include_data_type` adds the `dt` of the searched value to each term, and setting
+/// `include_operator` adds each item's requested `operator` as `op`. Setting `format` to
+/// `eql_v2` returns each term as an `eql_v2_encrypted` composite literal instead of an object,
+/// and `mysql` returns the index terms as strings to bind against `mysql` profile columns.
+///
+/// # Errors
+///
+/// Returns an error if the JSON input or options are malformed, contain unknown column/table
+/// combinations, have invalid encryption context, request an operator or range the column
+/// has no index for, record `index_params` the column's current index terms cannot match, or
+/// if encryption fails.
+///
+/// # Safety
+///
+/// The `client` and `terms_json` pointers must be valid, and `options_json` must be either
+/// null or a valid null-terminated C string.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn create_search_terms_with_options(
+    client: *const Client,
+    terms_json: *const c_char,
+    options_json: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic(
+        "create_search_terms_with_options",
+        || {
+            runtime().and_then(|rt| {
+        rt.block_on(async {
+            let client = safe_ffi::client_ref(client)?;
+            let _operation = client.load.begin()?;
+            let mut terms_buffer = Cow::Borrowed(unsafe { safe_ffi::c_str_to_str(terms_json) }?.as_bytes());
+            let terms: Vec<SearchTermItem> = parse_bulk_items(&mut terms_buffer)?;
+            let options: SearchTermOptions = match safe_ffi::optional_c_str_to_string(options_json)?
+            {
+                Some(options_json) => serde_json::from_str(&options_json)?,
+                None => SearchTermOptions::default(),
+            };
+
+            let mut bulk_targets = Vec::with_capacity(terms.len());
+            let mut cache_keys = Vec::with_capacity(terms.len());
+            let mut pending = Vec::with_capacity(terms.len());
+            let mut contexts = ContextCache::default();
+
+            for term in terms {
+                let identifier = client.resolve(Identifier::new(term.table, term.column));
+                let context = term.context.as_ref();
+
+                let (column_config, cast_as) = client.column(&identifier)?;
+
+                match (term.operator, &term.path) {
+                    (Some(operator), Some(_)) => operator.validate_path()?,
+                    (Some(operator), None) => operator.validate(column_config)?,
+                    (None, _) => {}
+                }
+                let path = term.path.as_deref();
+
+                if let Some(recorded) = &term.index_params {
+                    let field = match (term.operator, path, &term.range) {
+                        (_, Some(_), _) => Some("sv"),
+                        (_, None, Some(_)) => Some("ob"),
+                        (Some(operator), None, None) => operator.capability(column_config).term,
+                        (None, None, None) => None,
+                    };
+                    recorded.check_searchable(client.index_params(&identifier), field, &identifier)?;
+                }
+
+                let mut pending_term = |plaintext: String| -> Result<PendingSearchTerm, Error> {
+                    let (value, selector) = search_term_value(
+                        client,
+                        plaintext,
+                        path,
+                        &identifier,
+                        context,
+                        &mut contexts,
+                    )?;
+                    let cached = match value {
+                        SearchTermValue::Cached(encrypted) => Some(encrypted),
+                        SearchTermValue::Targets(targets, cast_as, cache_key) => {
+                            bulk_targets.push((targets, identifier.clone(), cast_as));
+                            cache_keys.push(cache_key);
+                            None
+                        }
+                    };
+
+                    Ok(PendingSearchTerm {
+                        selector,
+                        operator: term.operator,
+                        cached,
+                    })
+                };
+
+                let search_term = match (term.plaintext, term.range) {
+                    (Some(plaintext), None) => {
+                        SearchTermResult::Term(pending_term(plaintext.into_owned())?)
+                    }
+                    (None, Some(range)) => {
+                        if path.is_none() {
+                            range.validate(column_config, *cast_as)?;
+                        }
+
+                        SearchTermResult::Range {
+                            from: pending_term(range.from.into_owned())?,
+                            to: pending_term(range.to.into_owned())?,
+                        }
+                    }
+                    _ => {
+                        return Err(Error::InvalidSearchTerm(
+                            "expected exactly one of `plaintext` or `range`".to_string(),
+                        ))
+                    }
+                };
+
+                pending.push(search_term);
+            }
+
+            // Every value is encrypted in one pipeline per keyset, rather than one per term.
+            let encrypted = encrypt_bulk_inner(client, bulk_targets, None).await?;
+            if let Some(cache) = &client.search_term_cache {
+                for (cache_key, encrypted) in cache_keys.into_iter().zip(&encrypted) {
+                    if let Some(cache_key) = cache_key {
+                        cache.insert(cache_key, encrypted.clone());
+                    }
+                }
+            }
+
+            let mut encrypted = encrypted.into_iter();
+            let mut finish = |mut search_term: PendingSearchTerm| -> Result<SearchTerm, Error> {
+                let encrypted = match search_term.cached.take() {
+                    Some(encrypted) => encrypted,
+                    None => encrypted.next().ok_or_else(|| {
+                        Error::InvariantViolation(
+                            "`create_search_terms` expected an encrypted value for every search term, but there were fewer".to_string(),
+                        )
+                    })?,
+                };
+                search_term.finish(encrypted, &options)
+            };
+
+            let search_terms = pending
+                .into_iter()
+                .map(|search_term| {
+                    let search_term = match search_term {
+                        SearchTermResult::Term(term) => SearchTermResult::Term(finish(term)?),
+                        SearchTermResult::Range { from, to } => SearchTermResult::Range {
+                            from: finish(from)?,
+                            to: finish(to)?,
+                        },
+                    };
+                    search_term.into_format(options.format)
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            Ok(serde_json::to_string(&search_terms)?)
+        })
+    })
+        },
+    );
+
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
+    })
+}
+
+/// The value of a search term item: its encrypted value from the client's search term cache, or
+/// the plaintext targets to encrypt, with the key to cache the encrypted value under.
+enum SearchTermValue {
+    Cached(Encrypted),
+    Targets(Vec<PlaintextTarget>, CastAs, Option<term_cache::CacheKey>),
+}
+
+/// Create the value of a search term item, with the encoded selector of the field the term
+/// compares when the item has a `path`.
+///
+/// A field's search term is the `ste_vec` entry for its selector in a query document holding
+/// the value at that path, whose term compares values of the same JSON type, so numbers and
+/// ISO 8601 date strings can be ordered.
+fn search_term_value(
+    client: &Client,
+    plaintext: String,
+    path: Option<&str>,
+    identifier: &Identifier,
+    context: Option<&serde_json::Value>,
+    contexts: &mut ContextCache,
+) -> Result<(SearchTermValue, Option<String>), Error> {
+    let (column_config, cast_as) = client.column(identifier)?;
+
+    let (plaintext, selector) = match path {
+        Some(path) => {
+            let keyset = client.column_keysets.get(identifier).map(String::as_str);
+            let selector = ste_vec::tokenize_selector(
+                &client.cipher_for(keyset)?,
+                column_config,
+                identifier,
+                path,
+            )?;
+            let document = ste_vec::query_document(path, plaintext)?;
+
+            (
+                document.to_string(),
+                Some(client.options.index_encoding.encode(&selector)),
+            )
+        }
+        None => (plaintext, None),
+    };
+
+    let context = client.request_context(context)?;
+    let cache_key = match &client.search_term_cache {
+        Some(cache) => {
+            let key = cache.key(identifier, context.as_deref(), &plaintext);
+            if let Some(encrypted) = cache.get(&key) {
+                return Ok((SearchTermValue::Cached(encrypted), selector));
+            }
+            Some(key)
+        }
+        None => None,
+    };
+
+    let encryption_context = match &context {
+        Some(context_value) => contexts.encryption_context(context_value)?,
+        None => Vec::new(),
+    };
+
+    // Array columns are queried by element, so the plaintext is a single element.
+    let cast_as = cast_as.element_type().unwrap_or(*cast_as);
+
+    // A containment query on values the column does not index would match nothing, or, with
+    // those values removed, match too much, so it is rejected.
+    let ste_vec_policy = client.ste_vec_policy(identifier);
+    if cast_as == CastAs::JsonB && *ste_vec_policy != ste_vec::UNLIMITED {
+        let query = plaintext_target::json_document(&plaintext)?;
+        if ste_vec_policy.indexed(&query)? != query {
+            return Err(Error::InvalidSearchTerm(format!(
+                "the query selects values that the `ste_vec` index of column `{}.{}` does not cover",
+                identifier.table, identifier.column
+            )));
+        }
+    }
+
+    let plaintext_targets = plaintext_targets(
+        plaintext,
+        column_config,
+        cast_as,
+        client.text_policy(identifier),
+        &ste_vec::UNLIMITED,
+        encryption_context,
+    )?;
+
+    Ok((
+        SearchTermValue::Targets(plaintext_targets, cast_as, cache_key),
+        selector,
+    ))
+}
+
+/// Derives the tokenized selector of a JSON path under a `jsonb` column's `ste_vec` prefix.
+///
+/// The `path` parameter uses the `.key`, `[index]`, and `[*]` segments of `ste_vec` filter
+/// paths, such as `$.profile.email` or `$.tags[*]`. The selector is encoded with the client's
+/// index encoding, matching the `s` field of `ste_vec` entries, and is stable for a column and
+/// keyset, so database tooling can compute it once for prepared statements.
+///
+/// # Errors
+///
+/// Returns an error if the path is malformed, the column is unknown or has no `ste_vec` index,
+/// or the selector cannot be derived.
+///
+/// # Safety
+///
+/// All pointer parameters must be valid null-terminated C strings.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn tokenize_selector(
+    client: *const Client,
+    column: *const c_char,
+    table: *const c_char,
+    path: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("tokenize_selector", || {
+        safe_ffi::client_ref(client).and_then(|client| {
+            let column = safe_ffi::c_str_to_string(column)?;
+            let table = safe_ffi::c_str_to_string(table)?;
+            let path = safe_ffi::c_str_to_string(path)?;
+
+            let identifier = client.resolve(Identifier::new(table, column));
+            let (column_config, _) = client.column(&identifier)?;
+
+            let keyset = client.column_keysets.get(&identifier).map(String::as_str);
+            let selector = ste_vec::tokenize_selector(
+                &client.cipher_for(keyset)?,
+                column_config,
+                &identifier,
+                &path,
+            )?;
+
+            Ok(client.options.index_encoding.encode(&selector))
+        })
+    });
+
+    handle_ffi_result!(result, error_out, |selector| {
+        safe_ffi::string_to_c_string(selector).unwrap_or(ptr::null_mut())
+    })
+}
+
+/// Explains whether a column's indexes support a query operator.
+///
+/// The `operator` parameter is the SQL spelling of an operator, such as `=`, `<`, `~~`, `LIKE`,
+/// or `@>`. When `path` is not null, the operator compares the field at that path inside the
+/// column's `jsonb` documents. Returns a JSON object with the `operator` and whether it is
+/// `supported`, with the `index` a query uses and the search term field, `term`, it compares,
+/// or, when it is not supported, the `reason` and the indexes it `requires`. Query builders can
+/// check a query before creating search terms that could never match.
+///
+/// # Errors
+///
+/// Returns an error if the column is unknown or the operator is not recognized.
+///
+/// # Safety
+///
+/// The `client`, `column`, `table`, and `operator` pointers must be valid, and `path` must be
+/// either null or a valid null-terminated C string.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn explain_query_capability(
+    client: *const Client,
+    column: *const c_char,
+    table: *const c_char,
+    operator: *const c_char,
+    path: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("explain_query_capability", || {
+        safe_ffi::client_ref(client).and_then(|client| {
+            let column = safe_ffi::c_str_to_string(column)?;
+            let table = safe_ffi::c_str_to_string(table)?;
+            let operator = Operator::from_str(unsafe { safe_ffi::c_str_to_str(operator) }?)?;
+            let path = safe_ffi::optional_c_str_to_string(path)?;
 
-    Ok(WithContext {
-        record: encrypted_record,
-        context: encryption_context,
+            let identifier = client.resolve(Identifier::new(table, column));
+            let (column_config, _) = client.column(&identifier)?;
+
+            let capability = match path {
+                Some(path) => {
+                    json_paths::JsonPath::parse_filter(&path)?;
+                    operator.path_capability(column_config)
+                }
+                None => operator.capability(column_config),
+            };
+
+            Ok(serde_json::to_string(&capability)?)
+        })
+    });
+
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
     })
 }
 
-fn plaintext_from_bytes(bytes: Vec<u8>) -> Result<String, Error> {
-    let plaintext = Plaintext::from_slice(bytes.as_slice())?;
+/// Compares two index terms, such as the `hm` terms of two envelopes, in constant time.
+///
+/// Comparing terms with string equality takes longer the more leading characters match, which
+/// can leak a term to a caller who can time comparisons against chosen values. Terms may be hex
+/// or `b64:` prefixed base64, and are compared by their decoded bytes in time that depends only
+/// on their lengths.
+///
+/// Returns `1` when the terms are equal, `0` when they differ, and `-1` with the error in
+/// `error_out` if either term is neither hex nor prefixed base64.
+///
+/// # Safety
+///
+/// All pointer parameters must be valid null-terminated C strings.
+#[no_mangle]
+pub extern "C" fn compare_terms(
+    term_a: *const c_char,
+    term_b: *const c_char,
+    error_out: *mut *mut c_char,
+) -> i32 {
+    let result = safe_ffi::catch_panic("compare_terms", || {
+        unsafe { safe_ffi::c_str_to_str(term_a) }.and_then(|term_a| {
+            index_encoding::terms_equal(term_a, unsafe { safe_ffi::c_str_to_str(term_b) }?)
+        })
+    });
 
-    match plaintext {
-        Plaintext::Utf8Str(Some(ref inner)) => Ok(inner.clone()),
-        Plaintext::JsonB(Some(ref json_value)) => {
-            serde_json::to_string(json_value).map_err(Error::from)
+    match result {
+        Ok(equal) => {
+            safe_ffi::clear_error(error_out);
+            safe_ffi::clear_last_error();
+            i32::from(equal)
+        }
+        Err(error) => {
+            safe_ffi::set_error(error_out, &error);
+            safe_ffi::set_last_error(&error);
+            -1
         }
-        _ => Err(Error::Unimplemented(format!(
-            "plaintext decryption for type `{:?}`",
-            plaintext
-        ))),
     }
 }
 
-fn to_eql_encrypted(
-    encrypted: encryption::Encrypted,
-    identifier: &Identifier,
-    cast_as: &CastAs,
-) -> Result<Encrypted, Error> {
-    match (cast_as, encrypted) {
-        // JSONB always uses SteVec format
-        (CastAs::JsonB, encrypted) => {
-            let (ciphertext, ste_vec_index) = match encrypted {
-                encryption::Encrypted::SteVec(ste_vec_index) => {
-                    let root_ciphertext = ste_vec_index.root_ciphertext().map_err(|e| {
-                        Error::InvariantViolation(format!("failed to get root ciphertext: {}", e))
-                    })?;
+/// Field selected for encryption inside a JSON document.
+#[derive(Deserialize)]
+struct JsonPathItem<'a> {
+    /// Path to the field, such as `$.patient.ssn`.
+    #[serde(borrow)]
+    path: Cow<'a, str>,
+    /// The column whose configuration encrypts the field.
+    #[serde(borrow)]
+    column: Cow<'a, str>,
+    /// The table containing the column.
+    #[serde(borrow)]
+    table: Cow<'a, str>,
+}
 
-                    let ciphertext = root_ciphertext
-                        .to_mp_base85()
-                        // The error type from `to_mp_base85` isn't public, so we don't derive an error for this one.
-                        // Instead, we use `map_err`.
-                        .map_err(|err| Error::Base85(err.to_string()))?;
+/// Encrypts selected fields of a JSON document, leaving the rest of the document in plaintext.
+///
+/// The `paths_json` parameter is a JSON array of `path`, `table`, and `column` objects. Each
+/// selected field is encrypted with its column's configuration and replaced by an envelope
+/// object. Fields that are absent or `null` are left unchanged.
+///
+/// # Errors
+///
+/// Returns an error if the document or paths JSON is malformed, a path is invalid or overlaps
+/// another path, a column is unknown, the encryption context is invalid, or encryption fails.
+///
+/// # Safety
+///
+/// All non-null pointer parameters must be valid null-terminated C strings.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn encrypt_json_paths(
+    client: *const Client,
+    document_json: *const c_char,
+    paths_json: *const c_char,
+    context_json: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("encrypt_json_paths", || {
+        runtime().and_then(|rt| {
+            rt.block_on(async {
+                let client = safe_ffi::client_ref(client)?;
+                let _operation = client.load.begin()?;
+                let document_json = unsafe { safe_ffi::c_str_to_str(document_json) }?;
+                let mut paths_buffer =
+                    Cow::Borrowed(unsafe { safe_ffi::c_str_to_str(paths_json) }?.as_bytes());
+                let items: Vec<JsonPathItem> = parse_bulk_items(&mut paths_buffer)?;
+                let context = safe_ffi::optional_c_str_to_string(context_json)?
+                    .map(|context| serde_json::from_str::<serde_json::Value>(&context))
+                    .transpose()?;
+                let context = client.request_context(context.as_ref())?;
+
+                let mut document: serde_json::Value = serde_json::from_str(document_json)?;
+                let paths = json_paths::parse_all(items.iter().map(|item| item.path.as_ref()))?;
+
+                let mut bulk_targets = Vec::with_capacity(items.len());
+                let mut selected = Vec::with_capacity(items.len());
+                let mut contexts = ContextCache::default();
+
+                for (item, path) in items.into_iter().zip(paths) {
+                    let Some(plaintext) = path
+                        .get_mut(&mut document)
+                        .and_then(|value| json_paths::plaintext_from_value(value))
+                    else {
+                        continue;
+                    };
+                    client.check_plaintext_size(&plaintext)?;
+
+                    let encryption_context = match &context {
+                        Some(context_value) => contexts.encryption_context(context_value)?,
+                        None => Vec::new(),
+                    };
+
+                    let identifier = client.resolve(Identifier::new(item.table, item.column));
+                    let (column_config, cast_as) = client.column(&identifier)?;
+
+                    let item_targets = plaintext_targets(
+                        plaintext,
+                        column_config,
+                        *cast_as,
+                        client.text_policy(&identifier),
+                        client.ste_vec_policy(&identifier),
+                        encryption_context,
+                    )?;
+
+                    bulk_targets.push((item_targets, identifier, *cast_as));
+                    selected.push(path);
+                }
 
-                    let ste_vec_entries: Result<Vec<SteVecEntry>, Error> = ste_vec_index
-                        .into_iter()
-                        .map(|entry| {
-                            let record = entry
-                                .record
-                                .to_mp_base85()
-                                // The error type from `to_mp_base85` isn't public, so we don't derive an error for this one.
-                                // Instead, we use `map_err`.
-                                .map_err(|err| Error::Base85(err.to_string()))?;
+                let encrypted_results = encrypt_bulk_inner(client, bulk_targets, None).await?;
 
-                            Ok(SteVecEntry {
-                                tokenized_selector: hex::encode(
-                                    entry.tokenized_selector.as_bytes(),
-                                ),
-                                term: hex::encode(
-                                    &serde_json::to_vec(&entry.term).map_err(Error::Parse)?,
-                                ),
-                                record,
-                                parent_is_array: entry.parent_is_array,
-                            })
-                        })
-                        .collect();
+                for (path, encrypted) in selected.into_iter().zip(encrypted_results) {
+                    let envelope = client.emit_envelope(encrypted)?;
+                    if let Some(field) = path.get_mut(&mut document) {
+                        *field = envelope;
+                    }
+                }
 
-                    (ciphertext, Some(ste_vec_entries?))
+                serde_json::to_string(&document).map_err(Error::from)
+            })
+        })
+    });
+
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
+    })
+}
+
+/// Decrypts selected fields of a JSON document produced by [`encrypt_json_paths()`].
+///
+/// The `paths_json` parameter is a JSON array of path strings. Each selected envelope is
+/// decrypted and replaced by its plaintext, restored to its original JSON type using the data
+/// type recorded in the envelope. Fields that are absent or `null` are left unchanged.
+///
+/// # Errors
+///
+/// Returns an error if the document or paths JSON is malformed, a path is invalid or overlaps
+/// another path, the encryption context is invalid, or decryption fails.
+///
+/// # Safety
+///
+/// All non-null pointer parameters must be valid null-terminated C strings.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn decrypt_json_paths(
+    client: *const Client,
+    document_json: *const c_char,
+    paths_json: *const c_char,
+    context_json: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("decrypt_json_paths", || {
+        runtime().and_then(|rt| {
+            rt.block_on(async {
+                let client = safe_ffi::client_ref(client)?;
+                let _operation = client.load.begin()?;
+                let document_json = unsafe { safe_ffi::c_str_to_str(document_json) }?;
+                let paths_json = safe_ffi::c_str_to_string(paths_json)?;
+                let context = safe_ffi::optional_c_str_to_string(context_json)?
+                    .map(|context| serde_json::from_str::<serde_json::Value>(&context))
+                    .transpose()?;
+                let context = client.request_context(context.as_ref())?;
+
+                let mut document: serde_json::Value = serde_json::from_str(document_json)?;
+                let paths: Vec<String> = serde_json::from_str(&paths_json)?;
+                let paths = json_paths::parse_all(paths.iter().map(String::as_str))?;
+
+                let mut contexts = ContextCache::default();
+                let context_kinds = match &context {
+                    Some(context_value) => contexts.with_kinds(context_value)?.1,
+                    None => Vec::new(),
+                };
+
+                let mut ciphertexts = Vec::with_capacity(paths.len());
+                let mut selected = Vec::with_capacity(paths.len());
+
+                for path in paths {
+                    let Some(field) = path.get_mut(&mut document) else {
+                        continue;
+                    };
+
+                    let ciphertext = match &*field {
+                        serde_json::Value::Null => continue,
+                        serde_json::Value::String(ciphertext) => ciphertext.clone(),
+                        envelope => envelope.to_string(),
+                    };
+                    let data_type = field
+                        .get("dt")
+                        .and_then(serde_json::Value::as_str)
+                        .and_then(casts::parse_data_type);
+
+                    let encryption_context = match &context {
+                        Some(context_value) => contexts.encryption_context(context_value)?,
+                        None => Vec::new(),
+                    };
+
+                    ciphertexts.push((ciphertext, encryption_context));
+                    selected.push((path, data_type));
                 }
-                encryption::Encrypted::Record(ciphertext, _terms) => {
-                    let ciphertext = ciphertext
-                        .to_mp_base85()
-                        // The error type from `to_mp_base85` isn't public, so we don't derive an error for this one.
-                        // Instead, we use `map_err`.
-                        .map_err(|err| Error::Base85(err.to_string()))?;
 
-                    (ciphertext, None)
+                let plaintexts =
+                    decrypt_bulk_inner(client, ciphertexts, &context_kinds, None).await?;
+
+                for ((path, data_type), plaintext) in selected.into_iter().zip(plaintexts) {
+                    if let Some(field) = path.get_mut(&mut document) {
+                        *field = json_paths::value_from_plaintext(plaintext, data_type);
+                    }
                 }
-            };
 
-            Ok(Encrypted::SteVec {
-                ciphertext,
-                data_type: cast_as.to_string(),
-                ste_vec_index,
-                identifier: identifier.to_owned(),
-                version: 2,
+                serde_json::to_string(&document).map_err(Error::from)
             })
-        }
+        })
+    });
 
-        // Non-JSONB types with indexes
-        (_, encryption::Encrypted::Record(ciphertext, terms)) => {
-            let ciphertext = ciphertext
-                .to_mp_base85()
-                // The error type from `to_mp_base85` isn't public, so we don't derive an error for this one.
-                // Instead, we use `map_err`.
-                .map_err(|err| Error::Base85(err.to_string()))?;
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
+    })
+}
 
-            let mut unique_index = None;
-            let mut ore_index = None;
-            let mut match_index = None;
+/// Renders an encrypted envelope as a human-readable summary for logs and support tickets.
+///
+/// The `options_json` parameter is optional and accepts `pretty`, `sort_keys`, `redact`, and
+/// `max_length` keys. By default, output is pretty-printed with sorted keys, and ciphertexts and
+/// index terms are truncated.
+///
+/// # Errors
+///
+/// Returns an error if the envelope or options JSON is malformed.
+///
+/// # Safety
+///
+/// All non-null pointer parameters must be valid null-terminated C strings.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn format_envelope(
+    envelope_json: *const c_char,
+    options_json: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("format_envelope", || {
+        let envelope_json = safe_ffi::c_str_to_string(envelope_json)?;
+        let options = match safe_ffi::optional_c_str_to_string(options_json)? {
+            Some(options_json) => serde_json::from_str(&options_json)?,
+            None => envelope::FormatOptions::default(),
+        };
+
+        envelope::format(&envelope_json, &options)
+    });
 
-            for index_term in terms {
-                match index_term {
-                    IndexTerm::Binary(bytes) => {
-                        unique_index = Some(format_index_term_binary(&bytes))
-                    }
-                    IndexTerm::BitMap(inner) => match_index = Some(inner),
-                    IndexTerm::OreArray(vec_of_bytes) => {
-                        ore_index = Some(format_index_term_ore_array(&vec_of_bytes));
-                    }
-                    IndexTerm::OreFull(bytes) => {
-                        ore_index = Some(format_index_term_ore(&bytes));
-                    }
-                    IndexTerm::OreLeft(bytes) => {
-                        ore_index = Some(format_index_term_ore(&bytes));
-                    }
-                    IndexTerm::Null => {}
-                    term => return Err(Error::Unimplemented(format!("index term `{term:?}`"))),
-                };
-            }
+    handle_ffi_result!(result, error_out, |rendered| {
+        safe_ffi::string_to_c_string(rendered).unwrap_or(ptr::null_mut())
+    })
+}
 
-            Ok(Encrypted::Ciphertext {
-                ciphertext,
-                data_type: cast_as.to_string(),
-                unique_index,
-                ore_index,
-                match_index,
-                identifier: identifier.to_owned(),
-                version: 2,
-            })
-        }
+/// Splits an encrypted envelope into the columns of a storage profile.
+///
+/// The `profile_json` parameter names the `ciphertext` and `metadata` columns, and optionally
+/// `unique`, `ore`, `match`, and `ste_vec` columns for index terms stored separately. Returns a
+/// JSON object of column names to values.
+///
+/// # Errors
+///
+/// Returns an error if the envelope or profile JSON is malformed, or if the profile maps more
+/// than one part of the envelope to the same column.
+///
+/// # Safety
+///
+/// All pointer parameters must be valid null-terminated C strings.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn explode_envelope(
+    envelope_json: *const c_char,
+    profile_json: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("explode_envelope", || {
+        let envelope_json = safe_ffi::c_str_to_string(envelope_json)?;
+        let profile_json = safe_ffi::c_str_to_string(profile_json)?;
+        let profile: storage::StorageProfile = serde_json::from_str(&profile_json)?;
 
-        // Non-JSONB types should never return SteVec
-        (_, encryption::Encrypted::SteVec(_)) => Err(Error::InvariantViolation(
-            "non-JSONB type returned SteVec from encryption library".to_string(),
-        )),
-    }
+        let columns = storage::explode(&envelope_json, &profile)?;
+        Ok(serde_json::to_string(&columns)?)
+    });
+
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
+    })
+}
+
+/// Assembles an encrypted envelope from the columns of a storage profile.
+///
+/// This reverses [`explode_envelope()`], so the assembled envelope can be passed to
+/// [`decrypt()`] and keeps a valid signature.
+///
+/// # Errors
+///
+/// Returns an error if the columns or profile JSON is malformed, or if the ciphertext or
+/// metadata column is missing.
+///
+/// # Safety
+///
+/// All pointer parameters must be valid null-terminated C strings.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn assemble_envelope(
+    columns_json: *const c_char,
+    profile_json: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("assemble_envelope", || {
+        let columns_json = safe_ffi::c_str_to_string(columns_json)?;
+        let profile_json = safe_ffi::c_str_to_string(profile_json)?;
+        let profile: storage::StorageProfile = serde_json::from_str(&profile_json)?;
+        let columns: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(&columns_json)?;
+
+        let envelope = storage::assemble(&columns, &profile)?;
+        Ok(serde_json::to_string(&envelope)?)
+    });
+
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
+    })
+}
+
+/// Estimates the storage size of sample values under an encryption configuration.
+///
+/// The `sample_values_json` parameter is a JSON array of objects with the `table`, `column`,
+/// and `plaintext` of representative values. No client or ZeroKMS access is needed: envelopes
+/// are modelled with placeholder ciphertext and index terms of the sizes the configured indexes
+/// produce. Returns the same statistics as `encrypt_bulk` with the `stats` option.
+///
+/// # Errors
+///
+/// Returns an error if the configuration or samples are malformed, a sample names an unknown
+/// column, or a sample plaintext cannot be encrypted for its column.
+///
+/// # Safety
+///
+/// All pointer parameters must be valid null-terminated C strings.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn estimate_storage(
+    config_json: *const c_char,
+    sample_values_json: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("estimate_storage", || {
+        let config_json = safe_ffi::c_str_to_string(config_json)?;
+        let sample_values_json = safe_ffi::c_str_to_string(sample_values_json)?;
+        let samples: Vec<estimate::Sample> = serde_json::from_str(&sample_values_json)?;
+
+        let stats = estimate::estimate(&config_json, samples)?;
+        serde_json::to_string(&stats).map_err(Error::from)
+    });
+
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
+    })
 }
 
-/// Formats HMAC index bytes into hex-encoded string.
-fn format_index_term_binary(index_bytes: &[u8]) -> String {
-    hex::encode(index_bytes)
-}
+/// Validates an arbitrary JSON blob against the encrypted envelope schema.
+///
+/// Returns a JSON report containing `valid`, the detected `version`, and a list of
+/// field-level `problems`, each with a JSON `path` and a `message`.
+///
+/// # Errors
+///
+/// Returns an error if the input is not valid JSON.
+///
+/// # Safety
+///
+/// The `envelope_json` pointer must be a valid null-terminated C string.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn validate_envelope(
+    envelope_json: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("validate_envelope", || {
+        let envelope_json = safe_ffi::c_str_to_string(envelope_json)?;
+        let report = envelope::validate(&envelope_json)?;
+
+        serde_json::to_string(&report).map_err(Error::from)
+    });
 
-/// Formats ORE index bytes into hex-encoded string.
-fn format_index_term_ore_bytes(index_bytes: &[u8]) -> String {
-    hex::encode(index_bytes)
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
+    })
 }
 
-/// Formats ORE index array bytes into hex-encoded strings.
-fn format_index_term_ore_array(ore_byte_arrays: &[Vec<u8>]) -> Vec<String> {
-    ore_byte_arrays
-        .iter()
-        .map(|index_bytes| format_index_term_ore_bytes(index_bytes))
-        .collect()
-}
+/// Converts an encrypted envelope between schema versions.
+///
+/// The envelope's table and column must be present in the client's encryption configuration,
+/// which supplies the data type when upgrading a version 1 envelope and the index parameters
+/// recorded by version 3 envelopes. The ciphertext and index terms are carried over unchanged.
+///
+/// # Errors
+///
+/// Returns an error if the envelope is malformed, the target version is unsupported, or the
+/// envelope's table/column is not found in the encryption configuration.
+///
+/// # Safety
+///
+/// All pointer parameters must be valid null-terminated C strings.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn convert_envelope(
+    client: *const Client,
+    envelope_json: *const c_char,
+    version: u16,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("convert_envelope", || {
+        let client = safe_ffi::client_ref(client)?;
+        let envelope_json = safe_ffi::c_str_to_string(envelope_json)?;
 
-/// Formats ORE index bytes into a single-element hex-encoded string array.
-fn format_index_term_ore(index_bytes: &[u8]) -> Vec<String> {
-    vec![format_index_term_ore_bytes(index_bytes)]
-}
+        let envelope = VersionedEnvelope::parse(&envelope_json)?;
+        let identifier = envelope.identifier();
+        let (_, cast_as) = client.column(identifier)?;
+        let index_params = client.index_params(identifier);
 
-/// Bulk encryption request item containing plaintext data and metadata.
-#[derive(Deserialize)]
-struct BulkEncryptItem {
-    /// The plaintext data to encrypt.
-    plaintext: String,
-    /// The target column name.
-    column: String,
-    /// The target table name.
-    table: String,
-    /// Optional encryption context (defaults to empty if not provided).
-    #[serde(default)]
-    context: Option<serde_json::Value>,
-}
+        let (encrypted, _) = envelope.into_encrypted(cast_as);
+        let converted = VersionedEnvelope::emit(encrypted, version, index_params)?;
 
-/// Bulk decryption request item containing ciphertext and optional context.
-#[derive(Deserialize)]
-struct BulkDecryptItem {
-    /// The ciphertext to decrypt.
-    ciphertext: String,
-    /// Optional encryption context (defaults to empty if not provided).
-    #[serde(default)]
-    context: Option<serde_json::Value>,
-}
+        serde_json::to_string(&converted).map_err(Error::from)
+    });
 
-/// Search term creation request item containing plaintext and target metadata.
-#[derive(Deserialize)]
-struct SearchTermItem {
-    /// The plaintext data to create search terms for.
-    plaintext: String,
-    /// The target column name.
-    column: String,
-    /// The target table name.
-    table: String,
-    /// Optional encryption context (defaults to empty if not provided).
-    #[serde(default)]
-    context: Option<serde_json::Value>,
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
+    })
 }
 
-/// Encrypts multiple plaintext items in bulk.
+/// Upgrades an encrypted envelope to the current schema version without decrypting it.
+///
+/// Returns a JSON object containing the upgraded `envelope`, whether it was `upgraded`, and
+/// whether the record `requires_reencryption`, with `reasons` describing why. Records that need
+/// re-encryption have a null `envelope` and must be decrypted and encrypted again.
 ///
 /// # Errors
 ///
-/// Returns an error if the JSON input is malformed, contains unknown column/table
-/// combinations, has invalid encryption context, or if encryption fails.
+/// Returns an error if the envelope is malformed or its table/column is not found in the
+/// encryption configuration.
 ///
 /// # Safety
 ///
 /// All pointer parameters must be valid null-terminated C strings.
 /// The returned pointer must be freed using [`free_string()`].
 #[no_mangle]
-pub extern "C" fn encrypt_bulk(
+pub extern "C" fn upgrade_envelope(
     client: *const Client,
-    items_json: *const c_char,
+    envelope_json: *const c_char,
     error_out: *mut *mut c_char,
 ) -> *mut c_char {
-    let result: Result<String, Error> = runtime().and_then(|rt| {
-        rt.block_on(async {
-            let client = safe_ffi::client_ref(client)?;
-            let items_json_string = safe_ffi::c_str_to_string(items_json)?;
-            let items: Vec<BulkEncryptItem> = serde_json::from_str(&items_json_string)?;
+    let result: Result<String, Error> = safe_ffi::catch_panic("upgrade_envelope", || {
+        let client = safe_ffi::client_ref(client)?;
+        let envelope_json = safe_ffi::c_str_to_string(envelope_json)?;
 
-            let mut plaintext_targets = Vec::new();
+        let envelope = VersionedEnvelope::parse(&envelope_json)?;
+        let identifier = envelope.identifier();
+        let (column_config, cast_as) = client.column(identifier)?;
 
-            for item in items {
-                let encryption_context = if let Some(context_value) = item.context {
-                    let context_json = serde_json::to_string(&context_value)?;
-                    parse_encryption_context(&context_json)?
-                } else {
-                    Vec::new()
-                };
+        let index_params = client.index_params(identifier);
 
-                let identifier = Identifier::new(item.table, item.column);
-                let (column_config, cast_as) = client
-                    .encrypt_config
-                    .get(&identifier)
-                    .ok_or_else(|| Error::UnknownColumn(identifier.clone()))?;
+        let outcome = envelope::upgrade_to_current(envelope, column_config, cast_as, index_params);
 
-                let mut plaintext_target = plaintext_target::new(item.plaintext, column_config)?;
-                plaintext_target.context = encryption_context;
+        serde_json::to_string(&outcome).map_err(Error::from)
+    });
 
-                plaintext_targets.push((plaintext_target, identifier, *cast_as));
-            }
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
+    })
+}
 
-            let encrypted_results =
-                encrypt_bulk_inner(client.clone(), plaintext_targets, None).await?;
-            serde_json::to_string(&encrypted_results).map_err(Error::from)
+/// Reports metadata for the keysets a client encrypts under.
+///
+/// Returns a JSON object listing the default keyset and each configured keyset with its
+/// ZeroKMS `id`, `name`, `description`, `disabled` state, and the `columns` encrypted under it,
+/// followed by `unreferenced` keysets visible to the client credentials but not referenced by
+/// the configuration.
+///
+/// # Errors
+///
+/// Returns an error if the keysets cannot be listed from ZeroKMS.
+///
+/// # Safety
+///
+/// The `client` pointer must be a valid pointer returned by [`new_client()`].
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn get_key_info(client: *const Client, error_out: *mut *mut c_char) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("get_key_info", || {
+        runtime().and_then(|rt| {
+            rt.block_on(async {
+                let client = safe_ffi::client_ref(client)?;
+
+                client.throttle().await?;
+                let remote = client
+                    .zerokms
+                    .list_keysets(None)
+                    .await?
+                    .into_iter()
+                    .map(|keyset| keys::RemoteKeyset {
+                        id: keyset.id,
+                        name: keyset.name,
+                        description: keyset.description,
+                        disabled: keyset.is_disabled,
+                    })
+                    .collect();
+
+                let info = keys::key_info(
+                    &client.keyset_ids,
+                    &client.column_keysets,
+                    client.encrypt_config.keys().cloned(),
+                    remote,
+                );
+
+                serde_json::to_string(&info).map_err(Error::from)
+            })
         })
     });
 
@@ -665,92 +3971,287 @@ pub extern "C" fn encrypt_bulk(
     })
 }
 
-async fn encrypt_bulk_inner(
-    client: Client,
-    plaintext_targets: Vec<(PlaintextTarget, Identifier, CastAs)>,
-    service_token: Option<ServiceToken>,
-) -> Result<Vec<Encrypted>, Error> {
-    let len = plaintext_targets.len();
-    let mut pipeline = ReferencedPendingPipeline::new(client.cipher);
-    let (plaintext_targets, identifiers, cast_types): (
-        Vec<PlaintextTarget>,
-        Vec<Identifier>,
-        Vec<CastAs>,
-    ) = plaintext_targets.into_iter().fold(
-        (Vec::new(), Vec::new(), Vec::new()),
-        |(mut plaintext_targets, mut identifiers, mut cast_types),
-         (plaintext_target, identifier, cast_type)| {
-            plaintext_targets.push(plaintext_target);
-            identifiers.push(identifier);
-            cast_types.push(cast_type);
-            (plaintext_targets, identifiers, cast_types)
-        },
-    );
-
-    for (index, plaintext_target) in plaintext_targets.into_iter().enumerate() {
-        pipeline.add_with_ref::<PlaintextTarget>(plaintext_target, index)?;
-    }
+/// Probes CTS, the service that issues the tokens authenticating a client's requests.
+///
+/// Resolves the CTS host and times a request for a fresh service token. Returns a JSON object
+/// with the `service`, the probe's `status` (`ok`, `dns_failure`, or `failure`), the `endpoint`
+/// URL, the `addresses` its host resolved to, `resolve_ms` and `latency_ms` timings, and the
+/// `error` of a failed probe. The client's own cached token is not affected.
+///
+/// # Errors
+///
+/// Returns an error if the `client` pointer is invalid. A failed probe is reported in its
+/// result.
+///
+/// # Safety
+///
+/// The `client` pointer must be a valid pointer returned by [`new_client()`].
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn ping_cts(client: *const Client, error_out: *mut *mut c_char) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("ping_cts", || {
+        runtime().and_then(|rt| {
+            rt.block_on(async {
+                let client = safe_ffi::client_ref(client)?;
+                let probe = client.probes.cts(&client.network).await;
+
+                serde_json::to_string(&probe).map_err(Error::from)
+            })
+        })
+    });
 
-    let mut source_encrypted = pipeline.encrypt(service_token).await?;
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
+    })
+}
 
-    let mut results: Vec<Encrypted> = Vec::with_capacity(len);
+/// Probes ZeroKMS, the service that manages a client's data keys.
+///
+/// Resolves the ZeroKMS host and times a keyset listing with the client's service token.
+/// Returns a JSON object in the shape [`ping_cts()`] returns. A listing that fails because a
+/// token could not be issued also fails [`ping_cts()`], which tells the two outages apart.
+///
+/// # Errors
+///
+/// Returns an error if the `client` pointer is invalid, or the call would exceed the client's
+/// rate limit. A failed probe is reported in its result.
+///
+/// # Safety
+///
+/// The `client` pointer must be a valid pointer returned by [`new_client()`].
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn ping_zerokms(client: *const Client, error_out: *mut *mut c_char) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("ping_zerokms", || {
+        runtime().and_then(|rt| {
+            rt.block_on(async {
+                let client = safe_ffi::client_ref(client)?;
+
+                client.throttle().await?;
+                let network = &client.network;
+                let probe = probes::probe("zerokms", network, &network.zerokms, async {
+                    client.zerokms.list_keysets(None).await?;
+                    Ok(())
+                })
+                .await;
+
+                serde_json::to_string(&probe).map_err(Error::from)
+            })
+        })
+    });
 
-    for index in 0..len {
-        let encrypted = source_encrypted.remove(index).ok_or_else(|| {
-            Error::InvariantViolation(format!(
-                "`encrypt_bulk` expected a result in the pipeline at index {index}, but there was none"
-            ))
-        })?;
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
+    })
+}
 
-        let identifier = &identifiers[index];
-        let cast_as = &cast_types[index];
+/// Returns the network destinations a client may contact.
+///
+/// Returns a JSON object with `offline`, whether the client refuses connections to hosts other
+/// than CTS and ZeroKMS, and the `cts` and `zerokms` endpoints, each with its `url`, `host`,
+/// and `port`. The crate sends no telemetry and makes no update checks, so these are the only
+/// destinations a client connects to.
+///
+/// # Errors
+///
+/// Returns an error if the `client` pointer is invalid.
+///
+/// # Safety
+///
+/// The `client` pointer must be a valid pointer returned by [`new_client()`].
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn get_network_policy(
+    client: *const Client,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("get_network_policy", || {
+        safe_ffi::client_ref(client)
+            .and_then(|client| serde_json::to_string(&*client.network).map_err(Error::from))
+    });
 
-        let eql_payload = to_eql_encrypted(encrypted, identifier, cast_as)?;
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
+    })
+}
 
-        results.push(eql_payload);
-    }
+/// Returns a snapshot of a client's non-secret state.
+///
+/// Returns a JSON object with the snapshot format version `v`, the `library_version`, when the
+/// snapshot was `exported_at` in seconds since the Unix epoch, the `config_digest` of the
+/// encryption configuration, the ID of each named keyset in `keysets`, and the `cts` and
+/// `zerokms` endpoints, each with its `url`, `host`, `port`, and the `addresses` its host
+/// resolves to now. Credentials and service tokens are never included. Pass the snapshot to
+/// [`import_client_state()`] in another worker to check that its client matches.
+///
+/// # Errors
+///
+/// Returns an error if the `client` pointer is invalid.
+///
+/// # Safety
+///
+/// The `client` pointer must be a valid pointer returned by [`new_client()`].
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn export_client_state(
+    client: *const Client,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("export_client_state", || {
+        runtime().and_then(|rt| {
+            rt.block_on(async {
+                let client = safe_ffi::client_ref(client)?;
+                let state = client_state::capture(
+                    &client.config_digest,
+                    &client.keyset_ids,
+                    &client.network,
+                    true,
+                )
+                .await;
+
+                serde_json::to_string(&state).map_err(Error::from)
+            })
+        })
+    });
 
-    Ok(results)
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
+    })
 }
 
-/// Decrypts multiple ciphertext items in bulk.
+/// Compares a snapshot from [`export_client_state()`] with a client.
+///
+/// Returns a JSON object with whether the snapshot is `compatible` with the client, the
+/// `differences` between them, such as a different encryption configuration, named keysets,
+/// endpoints, or library version, and the snapshot's `age_secs`. Resolved addresses are not
+/// compared. The encryption library does not accept warm state, so importing a snapshot does
+/// not change the client.
 ///
 /// # Errors
 ///
-/// Returns an error if the JSON input is malformed, contains invalid `ciphertext`,
-/// has malformed encryption context, or if decryption fails.
+/// Returns an error if the `client` pointer is invalid or the snapshot cannot be parsed.
 ///
 /// # Safety
 ///
-/// All pointer parameters must be valid null-terminated C strings.
+/// The `client` pointer must be a valid pointer returned by [`new_client()`], and
+/// `state_json` a valid null-terminated C string.
 /// The returned pointer must be freed using [`free_string()`].
 #[no_mangle]
-pub extern "C" fn decrypt_bulk(
+pub extern "C" fn import_client_state(
     client: *const Client,
-    items_json: *const c_char,
+    state_json: *const c_char,
     error_out: *mut *mut c_char,
 ) -> *mut c_char {
-    let result: Result<String, Error> = runtime().and_then(|rt| {
-        rt.block_on(async {
-            let client = safe_ffi::client_ref(client)?;
-            let items_json_string = safe_ffi::c_str_to_string(items_json)?;
-            let items: Vec<BulkDecryptItem> = serde_json::from_str(&items_json_string)?;
+    let result: Result<String, Error> = safe_ffi::catch_panic("import_client_state", || {
+        runtime().and_then(|rt| {
+            rt.block_on(async {
+                let client = safe_ffi::client_ref(client)?;
+                let imported: client_state::ClientState =
+                    serde_json::from_str(unsafe { safe_ffi::c_str_to_str(state_json) }?)?;
+                let current = client_state::capture(
+                    &client.config_digest,
+                    &client.keyset_ids,
+                    &client.network,
+                    false,
+                )
+                .await;
+
+                serde_json::to_string(&imported.compare(&current)).map_err(Error::from)
+            })
+        })
+    });
 
-            let mut ciphertexts = Vec::new();
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
+    })
+}
 
-            for item in items {
-                let encryption_context = if let Some(context_value) = item.context {
-                    let context_json = serde_json::to_string(&context_value)?;
-                    parse_encryption_context(&context_json)?
-                } else {
-                    Vec::new()
-                };
+/// Checks that a client was created with the encryption configuration the caller expects.
+///
+/// `config_digest` is the digest of the configuration the caller believes is active, as
+/// returned by [`get_config_digest()`]. Checking it before a request catches deploy races,
+/// where application code is rolled out before or after the encryption configuration it was
+/// written for.
+///
+/// Returns `0` if the client's configuration has the digest, or `-1` with the error in
+/// `error_out`.
+///
+/// # Errors
+///
+/// Returns [`Error::ConfigMismatch`] with both digests if the configurations differ.
+///
+/// # Safety
+///
+/// The `client` pointer must be a valid pointer returned by [`new_client()`], and
+/// `config_digest` a valid null-terminated C string.
+#[no_mangle]
+pub extern "C" fn assert_config_matches(
+    client: *const Client,
+    config_digest: *const c_char,
+    error_out: *mut *mut c_char,
+) -> i32 {
+    let result: Result<(), Error> = safe_ffi::catch_panic("assert_config_matches", || {
+        let client = safe_ffi::client_ref(client)?;
+        let expected = unsafe { safe_ffi::c_str_to_str(config_digest) }?.trim();
+
+        if expected.eq_ignore_ascii_case(&client.config_digest) {
+            Ok(())
+        } else {
+            Err(Error::ConfigMismatch {
+                expected: expected.to_string(),
+                actual: client.config_digest.to_string(),
+            })
+        }
+    });
 
-                ciphertexts.push((item.ciphertext, encryption_context));
-            }
+    match result {
+        Ok(()) => {
+            safe_ffi::clear_error(error_out);
+            safe_ffi::clear_last_error();
+            0
+        }
+        Err(error) => {
+            safe_ffi::set_error(error_out, &error);
+            safe_ffi::set_last_error(&error);
+            -1
+        }
+    }
+}
 
-            let plaintexts = decrypt_bulk_inner(client.clone(), ciphertexts, None).await?;
-            serde_json::to_string(&plaintexts).map_err(Error::from)
+/// Measures the throughput of an operation on this host.
+///
+/// The `spec_json` parameter names the `operation` (`encrypt`, `encrypt_bulk`, `decrypt`, or
+/// `decrypt_bulk`), the `table` and `column` to encrypt for, and the `plaintext` to use, with
+/// optional `batch_size`, `iterations`, `warmup`, and `max_mean_ms` fields. Returns a JSON
+/// report of iteration times in milliseconds and items per second, with `within_budget` set
+/// when `max_mean_ms` is given.
+///
+/// # Errors
+///
+/// Returns an error if the spec is invalid, the column is not found in the encryption
+/// configuration, or any encryption or decryption fails.
+///
+/// # Safety
+///
+/// The `client` pointer must be a valid pointer returned by [`new_client()`].
+/// The `spec_json` pointer must be a valid null-terminated C string.
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn run_benchmark(
+    client: *const Client,
+    spec_json: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("run_benchmark", || {
+        runtime().and_then(|rt| {
+            rt.block_on(async {
+                let client = safe_ffi::client_ref(client)?;
+                let spec_json = safe_ffi::c_str_to_string(spec_json)?;
+                let spec: benchmark::BenchmarkSpec = serde_json::from_str(&spec_json)?;
+
+                let report = benchmark::run(client, &spec).await?;
+                serde_json::to_string(&report).map_err(Error::from)
+            })
         })
     });
 
@@ -759,125 +4260,153 @@ pub extern "C" fn decrypt_bulk(
     })
 }
 
-async fn decrypt_bulk_inner(
-    client: Client,
-    ciphertexts: Vec<(String, Vec<zerokms::Context>)>,
-    service_token: Option<ServiceToken>,
-) -> Result<Vec<String>, Error> {
-    let len = ciphertexts.len();
-    let mut encrypted_records: Vec<WithContext> = Vec::with_capacity(ciphertexts.len());
+/// Frees a client instance and its associated resources.
+///
+/// The client is poisoned rather than deallocated at once, so until its memory is reused after
+/// 64 more clients are freed, functions called with the stale pointer fail with an invalid
+/// pointer error instead of causing undefined behavior.
+///
+/// # Safety
+///
+/// The `client` pointer must have been returned by [`new_client()`] and not previously freed.
+/// Freeing a client while another thread is using it is undefined behavior.
+#[no_mangle]
+pub extern "C" fn free_client(client: *mut Client) {
+    safe_ffi::free_boxed_client(client);
+}
 
-    for (ciphertext, encryption_context) in ciphertexts {
-        let encrypted_record = encrypted_record_from_mp_base85(&ciphertext, encryption_context)?;
-        encrypted_records.push(encrypted_record);
-    }
+/// Frees a C string allocated by this library.
+///
+/// # Safety
+///
+/// The `string` pointer must have been returned by this library and not previously freed.
+#[no_mangle]
+pub extern "C" fn free_string(string: *mut c_char) {
+    safe_ffi::free_c_string(string);
+}
 
-    let decrypted = client
-        .zerokms
-        .decrypt(encrypted_records, service_token)
-        .await?;
+/// Returns the error message from the most recent failed call on the current thread.
+///
+/// Every function that reports errors through `error_out` also records them here, and clears
+/// the recorded error on success. Callers may pass a null `error_out` and use this instead.
+/// Returns null if the most recent call succeeded.
+///
+/// # Safety
+///
+/// The returned pointer, if not null, must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn get_last_error() -> *mut c_char {
+    safe_ffi::last_error()
+        .and_then(|message| safe_ffi::string_to_c_string(message).ok())
+        .unwrap_or(ptr::null_mut())
+}
 
-    let mut plaintexts: Vec<String> = Vec::with_capacity(len);
+/// Returns the messages of the most recent error on the current thread and its underlying
+/// causes as a JSON array, outermost first.
+///
+/// Messages reported through `error_out` and [`get_last_error()`] join the same chain with
+/// ` → `. Returns null if the most recent call succeeded.
+///
+/// # Safety
+///
+/// The returned pointer, if not null, must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn get_last_error_chain() -> *mut c_char {
+    safe_ffi::last_error_chain()
+        .and_then(|chain| serde_json::to_string(&chain).ok())
+        .and_then(|json_string| safe_ffi::string_to_c_string(json_string).ok())
+        .unwrap_or(ptr::null_mut())
+}
 
-    for item in decrypted {
-        plaintexts.push(plaintext_from_bytes(item)?);
-    }
+/// Returns the catalog code of the most recent error on the current thread, such as
+/// `rate_limited` or `context_mismatch`.
+///
+/// Returns null if the most recent call succeeded. The codes are listed by
+/// [`get_error_catalog()`].
+///
+/// # Safety
+///
+/// The returned pointer, if not null, must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn get_last_error_code() -> *mut c_char {
+    safe_ffi::last_error_code()
+        .and_then(|code| safe_ffi::string_to_c_string(code.to_string()).ok())
+        .unwrap_or(ptr::null_mut())
+}
 
-    Ok(plaintexts)
+/// Returns the server-side request ID of the ZeroKMS or CTS request that caused the most recent
+/// error on the current thread.
+///
+/// CipherStash support can find a request by its ID, so include it when reporting a failed
+/// request. Returns null if the most recent call succeeded, or its error did not come from a
+/// response that reported a request ID.
+///
+/// # Safety
+///
+/// The returned pointer, if not null, must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn get_last_error_request_id() -> *mut c_char {
+    safe_ffi::last_error_request_id()
+        .and_then(|request_id| safe_ffi::string_to_c_string(request_id).ok())
+        .unwrap_or(ptr::null_mut())
 }
 
-/// Creates encrypted search terms for querying encrypted data.
+/// Returns how long to back off after the most recent error on the current thread, as JSON.
 ///
-/// Returns a JSON array of encrypted search terms that can be used in database queries.
-/// Each search term contains the encryption indexes (`unique`, `ore`, `match`, `ste_vec`)
-/// but not the full ciphertext.
+/// Returns an object with `retry_after_ms`, how long ZeroKMS or CTS asked callers to wait
+/// before retrying, and the workspace's `quota`, with the `limit` of requests in its window,
+/// the requests `remaining`, and `reset_ms` until it resets, omitting anything the service did
+/// not report. A call refused by the `rate_limit` client option reports how long it would have
+/// been queued as `retry_after_ms`. Returns null if the most recent call succeeded, or its error
+/// reported no hints.
 ///
-/// # Errors
+/// # Safety
 ///
-/// Returns an error if the JSON input is malformed, contains unknown column/table
-/// combinations, has invalid encryption context, or if encryption fails.
+/// The returned pointer, if not null, must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn get_last_error_retry_hint() -> *mut c_char {
+    safe_ffi::last_error_retry_hint()
+        .and_then(|hint| serde_json::to_string(&hint).ok())
+        .and_then(|json_string| safe_ffi::string_to_c_string(json_string).ok())
+        .unwrap_or(ptr::null_mut())
+}
+
+/// Returns every error code the library can report.
+///
+/// Returns a JSON array of objects with each error's stable `code`, the `name` of its variant,
+/// whether an operation that failed with it is `retryable` unchanged, and the `http_status` a
+/// web application would typically respond with. Wrappers can generate their exception types
+/// from the catalog and map errors to them with [`get_last_error_code()`].
 ///
 /// # Safety
 ///
-/// All pointer parameters must be valid null-terminated C strings.
 /// The returned pointer must be freed using [`free_string()`].
 #[no_mangle]
-pub extern "C" fn create_search_terms(
-    client: *const Client,
-    terms_json: *const c_char,
-    error_out: *mut *mut c_char,
-) -> *mut c_char {
-    let result: Result<String, Error> = runtime().and_then(|rt| {
-        rt.block_on(async {
-            let client = safe_ffi::client_ref(client)?;
-            let terms_json = safe_ffi::c_str_to_string(terms_json)?;
-            let terms: Vec<SearchTermItem> = serde_json::from_str(&terms_json)?;
-
-            let mut search_terms_json = Vec::new();
-
-            for term in terms {
-                let encryption_context = if let Some(context_value) = term.context {
-                    let context_json = serde_json::to_string(&context_value)?;
-                    parse_encryption_context(&context_json)?
-                } else {
-                    Vec::new()
-                };
-
-                let identifier = Identifier::new(term.table, term.column);
-                let (column_config, cast_as) = client
-                    .encrypt_config
-                    .get(&identifier)
-                    .ok_or_else(|| Error::UnknownColumn(identifier.clone()))?;
-
-                let mut plaintext_target = plaintext_target::new(term.plaintext, column_config)?;
-                plaintext_target.context = encryption_context;
-
-                let encrypted =
-                    encrypt_inner(client.clone(), plaintext_target, &identifier, cast_as, None)
-                        .await?;
-
-                let search_term_json = match encrypted {
-                    Encrypted::Ciphertext {
-                        unique_index,
-                        ore_index,
-                        match_index,
-                        identifier,
-                        ..
-                    } => {
-                        let hm_json = serde_json::to_string(&unique_index)?;
-                        let ob_json = serde_json::to_string(&ore_index)?;
-                        let bf_json = serde_json::to_string(&match_index)?;
-                        let i_json = format!(
-                            r#"{{"t":"{}","c":"{}"}}"#,
-                            identifier.table, identifier.column
-                        );
-
-                        format!(
-                            r#"{{"hm":{},"ob":{},"bf":{},"i":{}}}"#,
-                            hm_json, ob_json, bf_json, i_json
-                        )
-                    }
-                    Encrypted::SteVec {
-                        ste_vec_index,
-                        identifier,
-                        ..
-                    } => {
-                        let sv_json = serde_json::to_string(&ste_vec_index)?;
-                        let i_json = format!(
-                            r#"{{"t":"{}","c":"{}"}}"#,
-                            identifier.table, identifier.column
-                        );
-
-                        format!(r#"{{"sv":{},"i":{}}}"#, sv_json, i_json)
-                    }
-                };
+pub extern "C" fn get_error_catalog(error_out: *mut *mut c_char) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("get_error_catalog", || {
+        serde_json::to_string(error_catalog::CATALOG).map_err(Error::from)
+    });
 
-                search_terms_json.push(search_term_json);
-            }
+    handle_ffi_result!(result, error_out, |json_string| {
+        safe_ffi::string_to_c_string(json_string).unwrap_or(ptr::null_mut())
+    })
+}
 
-            let result = format!("[{}]", search_terms_json.join(","));
-            Ok(result)
-        })
+/// Returns a manifest of the functions the library exports.
+///
+/// Returns a JSON object with the `library_version`, the `abi_version`, and the `functions` the
+/// library exports, each with its `name`, the C type it `returns`, its `parameters` with the
+/// `name` and `type` of each, and the release it was added in, `since`. Wrappers can check
+/// whether the loaded library supports a function before calling it, instead of parsing its
+/// version.
+///
+/// # Safety
+///
+/// The returned pointer must be freed using [`free_string()`].
+#[no_mangle]
+pub extern "C" fn list_functions(error_out: *mut *mut c_char) -> *mut c_char {
+    let result: Result<String, Error> = safe_ffi::catch_panic("list_functions", || {
+        serde_json::to_string(&functions::manifest()).map_err(Error::from)
     });
 
     handle_ffi_result!(result, error_out, |json_string| {
@@ -885,30 +4414,77 @@ pub extern "C" fn create_search_terms(
     })
 }
 
-/// Frees a client instance and its associated resources.
+/// Clears the error recorded by the most recent failed call on the current thread.
+#[no_mangle]
+pub extern "C" fn clear_last_error() {
+    safe_ffi::clear_last_error();
+}
+
+/// Sets the callback invoked when [`free_client()`] or [`free_string()`] receives a pointer
+/// that was not returned by this library or was already freed.
+///
+/// Invalid pointers are only detected when the library is built with the `checked-pointers`
+/// feature. Passing null restores the default of writing the report to standard error.
 ///
 /// # Safety
 ///
-/// The `client` pointer must have been returned by [`new_client()`] and not previously freed.
+/// The `handler` must remain valid for as long as it is registered, and must not retain the
+/// message pointer after it returns.
 #[no_mangle]
-pub extern "C" fn free_client(client: *mut Client) {
-    safe_ffi::free_boxed_client(client);
+pub extern "C" fn set_invalid_pointer_handler(handler: Option<safe_ffi::InvalidPointerHandler>) {
+    safe_ffi::set_invalid_pointer_handler(handler);
 }
 
-/// Frees a C string allocated by this library.
+/// Sets the callback invoked on the first call in the process to each deprecated function, with
+/// the function's name and the name of the function replacing it.
+///
+/// Deprecated functions are the old names of renamed functions, kept until the next major
+/// release. Passing null restores the default of writing the report to standard error.
 ///
 /// # Safety
 ///
-/// The `string` pointer must have been returned by this library and not previously freed.
+/// The `handler` must remain valid for as long as it is registered, and must not retain the
+/// name pointers after it returns.
 #[no_mangle]
-pub extern "C" fn free_string(string: *mut c_char) {
-    safe_ffi::free_c_string(string);
+pub extern "C" fn set_deprecation_handler(handler: Option<deprecation::DeprecationHandler>) {
+    deprecation::set_deprecation_handler(handler);
+}
+
+/// Sets the file crash reports are appended to when an operation panics.
+///
+/// The first call installs a process-wide panic hook, which appends a JSON line to the file for
+/// every panic, before the panic is returned to the caller as an error: the operation that was
+/// running, the panic's location and redacted message, a backtrace, and the versions of this
+/// library and the encryption library. The default panic output is still written to standard
+/// error. Passing null stops writing reports.
+///
+/// Returns `0` once the path is set, or `-1` with the error in `error_out`.
+///
+/// # Safety
+///
+/// The `path` must be either null or a valid null-terminated C string.
+#[no_mangle]
+pub extern "C" fn set_crash_report_path(path: *const c_char, error_out: *mut *mut c_char) -> i32 {
+    match safe_ffi::optional_c_str_to_string(path) {
+        Ok(path) => {
+            safe_ffi::clear_error(error_out);
+            safe_ffi::clear_last_error();
+            crash_report::set_path(path.map(std::path::PathBuf::from));
+            0
+        }
+        Err(error) => {
+            safe_ffi::set_error(error_out, &error);
+            safe_ffi::set_last_error(&error);
+            -1
+        }
+    }
 }
 
 #[cfg(test)]
 mod lib {
     mod tests {
         use crate::*;
+        use cipherstash_client::schema::{column::Index, ColumnType};
         use std::ffi::{CStr, CString};
         use std::ptr;
 
@@ -932,10 +4508,13 @@ mod lib {
                 unique_index: None,
                 ore_index: None,
                 match_index: None,
+                elements: None,
                 identifier: Identifier {
                     table: table.to_string(),
                     column: column.to_string(),
                 },
+                keyset: None,
+                expires_at: None,
                 version: TEST_SCHEMA_VERSION,
             }
         }
@@ -956,6 +4535,8 @@ mod lib {
                     table: table.to_string(),
                     column: column.to_string(),
                 },
+                keyset: None,
+                expires_at: None,
                 version: TEST_SCHEMA_VERSION,
             }
         }
@@ -1001,12 +4582,32 @@ mod lib {
             assert_eq!(parsed_json["ob"], serde_json::Value::Null);
             assert_eq!(parsed_json["bf"], serde_json::Value::Null);
             assert_eq!(parsed_json["v"], TEST_SCHEMA_VERSION);
+            assert!(parsed_json.get("ks").is_none());
+            assert!(parsed_json.get("ae").is_none());
 
             let identifier_json = &parsed_json["i"];
             assert_eq!(identifier_json["t"], TEST_TABLE);
             assert_eq!(identifier_json["c"], TEST_COLUMN);
         }
 
+        #[test]
+        fn test_encrypted_array_json_format() {
+            let mut sample_encrypted =
+                create_encrypted_ciphertext(TEST_TABLE, "tags", TEST_CIPHERTEXT, "text[]");
+            if let Encrypted::Ciphertext { elements, .. } = &mut sample_encrypted {
+                *elements = Some(vec![ElementTerms {
+                    unique_index: Some("abcd".to_string()),
+                    ore_index: None,
+                    match_index: None,
+                }]);
+            }
+
+            let parsed_json = serde_json::to_value(&sample_encrypted).unwrap();
+
+            assert_eq!(parsed_json["dt"], "text[]");
+            assert_eq!(parsed_json["ae"], serde_json::json!([{"hm": "abcd"}]));
+        }
+
         #[test]
         fn test_encrypted_ste_vec_json_format_with_null_entries() {
             let sample_encrypted =
@@ -1030,6 +4631,96 @@ mod lib {
             assert_null_pointer_error(error_ptr);
         }
 
+        #[test]
+        fn test_validate_config_rejects_lints_and_returns_warnings() {
+            let mut error_ptr: *mut c_char = ptr::null_mut();
+            let error_out = &mut error_ptr as *mut *mut c_char;
+
+            let config = CString::new(
+                r#"{"v": 2, "tables": {"users": {"age": {"cast_as": "int", "indexes": {"match": {}}}}}}"#,
+            )
+            .unwrap();
+            let result = validate_config(config.as_ptr(), ptr::null(), error_out);
+            assert!(result.is_null());
+            assert!(!error_ptr.is_null());
+            free_string(error_ptr);
+            error_ptr = ptr::null_mut();
+
+            let config = CString::new(
+                r#"{"v": 2, "tables": {"users": {"profile": {"cast_as": "jsonb", "indexes": {"unique": {}}}}}}"#,
+            )
+            .unwrap();
+            let result = validate_config(config.as_ptr(), ptr::null(), error_out);
+            assert!(error_ptr.is_null());
+
+            let json_string = unsafe { CStr::from_ptr(result) }
+                .to_str()
+                .unwrap()
+                .to_string();
+            free_string(result);
+            let warnings: serde_json::Value = serde_json::from_str(&json_string).unwrap();
+            assert_eq!(warnings[0]["code"], "unique_on_jsonb");
+
+            let result = validate_config(ptr::null(), ptr::null(), error_out);
+            assert!(result.is_null());
+            assert_null_pointer_error(error_ptr);
+        }
+
+        #[test]
+        #[cfg(feature = "yaml")]
+        fn test_validate_config_yaml() {
+            let mut error_ptr: *mut c_char = ptr::null_mut();
+            let error_out = &mut error_ptr as *mut *mut c_char;
+
+            let config = CString::new(
+                "v: 2\ntables:\n  users:\n    email:\n      cast_as: text\n      indexes:\n        unique: {}\n",
+            )
+            .unwrap();
+            let result = validate_config(config.as_ptr(), ptr::null(), error_out);
+            assert!(error_ptr.is_null());
+            assert!(!result.is_null());
+            free_string(result);
+
+            let format = CString::new("yaml").unwrap();
+            let result = validate_config(config.as_ptr(), format.as_ptr(), error_out);
+            assert!(error_ptr.is_null());
+            free_string(result);
+
+            let format = CString::new("xml").unwrap();
+            let result = validate_config(config.as_ptr(), format.as_ptr(), error_out);
+            assert!(result.is_null());
+            assert!(!error_ptr.is_null());
+            free_string(error_ptr);
+        }
+
+        #[test]
+        fn test_get_config_digest_ignores_formatting() {
+            let mut error_ptr: *mut c_char = ptr::null_mut();
+            let error_out = &mut error_ptr as *mut *mut c_char;
+
+            let digest = |config: &str| {
+                let config = CString::new(config).unwrap();
+                let result = get_config_digest(config.as_ptr(), ptr::null(), error_out);
+                assert!(!result.is_null());
+                let digest = unsafe { CStr::from_ptr(result) }
+                    .to_str()
+                    .unwrap()
+                    .to_string();
+                free_string(result);
+                digest
+            };
+
+            let json = digest(
+                r#"{"v": 2, "tables": {"users": {"email": {"cast_as": "text", "indexes": {"unique": {}}}}}}"#,
+            );
+            let reordered = digest(
+                "{\n  \"tables\": {\"users\": {\"email\": {\"indexes\": {\"unique\": {}}, \"cast_as\": \"text\"}}},\n  \"v\": 2\n}",
+            );
+            assert_eq!(json, reordered);
+            assert_eq!(json.len(), 64);
+            assert!(error_ptr.is_null());
+        }
+
         #[test]
         fn test_encrypt_null_client() {
             let mut error_ptr: *mut c_char = ptr::null_mut();
@@ -1065,6 +4756,362 @@ mod lib {
             assert_null_pointer_error(error_ptr);
         }
 
+        #[test]
+        fn test_reindex_search_terms_null_client() {
+            let mut error_ptr: *mut c_char = ptr::null_mut();
+            let error_out = &mut error_ptr as *mut *mut c_char;
+
+            let items = CString::new("[]").unwrap();
+
+            let result = reindex_search_terms(ptr::null(), items.as_ptr(), error_out);
+
+            assert!(result.is_null());
+            assert_null_pointer_error(error_ptr);
+        }
+
+        #[test]
+        fn test_get_key_info_null_client() {
+            let mut error_ptr: *mut c_char = ptr::null_mut();
+            let error_out = &mut error_ptr as *mut *mut c_char;
+
+            let result = get_key_info(ptr::null(), error_out);
+
+            assert!(result.is_null());
+            assert_null_pointer_error(error_ptr);
+        }
+
+        #[test]
+        fn test_clear_search_term_cache_null_client() {
+            let mut error_ptr: *mut c_char = ptr::null_mut();
+
+            assert_eq!(clear_search_term_cache(ptr::null(), &mut error_ptr), -1);
+            assert_null_pointer_error(error_ptr);
+        }
+
+        #[test]
+        fn test_compare_terms() {
+            let term = CString::new("f3ca71fd39").unwrap();
+            let base64_term = CString::new("b64:88px/Tk=").unwrap();
+            let other_term = CString::new("f3ca71fd38").unwrap();
+            let mut error_ptr: *mut c_char = ptr::null_mut();
+
+            assert_eq!(
+                compare_terms(term.as_ptr(), base64_term.as_ptr(), &mut error_ptr),
+                1
+            );
+            assert_eq!(
+                compare_terms(term.as_ptr(), other_term.as_ptr(), &mut error_ptr),
+                0
+            );
+            assert!(error_ptr.is_null());
+
+            assert_eq!(
+                compare_terms(term.as_ptr(), ptr::null(), &mut error_ptr),
+                -1
+            );
+            assert_null_pointer_error(error_ptr);
+        }
+
+        #[test]
+        fn test_bulk_results_to_json_without_ids_is_positional() {
+            let json = bulk_results_to_json(vec!["a", "b"], vec![None, None]).unwrap();
+
+            assert_eq!(json, r#"["a","b"]"#);
+        }
+
+        #[test]
+        fn test_bulk_results_to_json_echoes_ids() {
+            let ids = vec![Some(serde_json::json!("user_1")), None];
+
+            let json = bulk_results_to_json(vec!["a", "b"], ids).unwrap();
+
+            assert_eq!(
+                json,
+                r#"[{"index":0,"id":"user_1","result":"a"},{"index":1,"id":null,"result":"b"}]"#
+            );
+        }
+
+        #[test]
+        fn test_in_item_order_places_results_by_item_index() {
+            let results = vec![(2, "c"), (0, "a"), (1, "b")];
+
+            let ordered = in_item_order("encrypt_bulk", 3, results).unwrap();
+
+            assert_eq!(ordered, ["a", "b", "c"]);
+        }
+
+        #[test]
+        fn test_in_item_order_rejects_missing_duplicate_and_extra_results() {
+            for (results, expected) in [
+                (vec![(0, "a")], "no result for the item at index 1"),
+                (vec![(1, "b")], "no result for the item at index 0"),
+                (vec![(0, "a"), (0, "b")], "more than one result"),
+                (vec![(0, "a"), (1, "b"), (2, "c")], "result for index 2"),
+            ] {
+                let result = in_item_order("encrypt_bulk", 2, results);
+
+                assert!(
+                    matches!(&result, Err(Error::InvariantViolation(message)) if message.contains(expected)),
+                    "{result:?}"
+                );
+            }
+        }
+
+        #[test]
+        fn test_parse_bulk_items_borrows_strings() {
+            let request = format!(
+                r#"[{{"id":1,"ciphertext":"{TEST_CIPHERTEXT}","context":{{"tag":["pii"]}}}},{{"ciphertext":"a\"b"}}]"#
+            );
+            let mut buffer = Cow::Borrowed(request.as_bytes());
+
+            let items: Vec<BulkDecryptItem> = parse_bulk_items(&mut buffer).unwrap();
+
+            assert_eq!(items.len(), 2);
+            assert!(matches!(
+                items[0].ciphertext,
+                Cow::Borrowed(TEST_CIPHERTEXT)
+            ));
+            assert_eq!(items[0].id, Some(serde_json::json!(1)));
+            assert!(items[0].context.is_some());
+            assert_eq!(items[1].ciphertext, "a\"b");
+
+            // Without `simd-json`, borrowed fields point into the request rather than a copy.
+            #[cfg(not(feature = "simd-json"))]
+            assert!(request
+                .as_bytes()
+                .as_ptr_range()
+                .contains(&items[0].ciphertext.as_ptr()));
+        }
+
+        #[test]
+        fn test_bulk_encrypt_item_skips_indexes() {
+            let request = format!(
+                r#"[{{"plaintext":"{TEST_EMAIL}","column":"{TEST_COLUMN}","table":"{TEST_TABLE}","skip_match":true}}]"#
+            );
+            let mut buffer = Cow::Borrowed(request.as_bytes());
+            let items: Vec<BulkEncryptItem> = parse_bulk_items(&mut buffer).unwrap();
+
+            let column_config = ColumnConfig::build(TEST_COLUMN.to_string())
+                .casts_as(ColumnType::Utf8Str)
+                .add_index(Index::new(IndexType::Unique {
+                    token_filters: vec![],
+                }))
+                .add_index(Index::new_match());
+
+            let index_config = items[0].index_config(&column_config);
+
+            assert_eq!(index_config.indexes.len(), 1);
+            assert!(matches!(
+                index_config.indexes[0].index_type,
+                IndexType::Unique { .. }
+            ));
+        }
+
+        #[test]
+        fn test_parse_bulk_items_rejects_malformed_json() {
+            let mut buffer = Cow::Borrowed(&b"[{\"ciphertext\":}]"[..]);
+
+            let result = parse_bulk_items::<BulkDecryptItem>(&mut buffer);
+
+            assert!(matches!(result, Err(Error::Parse(_))));
+        }
+
+        #[test]
+        fn test_concurrent_ffi_calls() {
+            const THREADS: usize = 16;
+            const ITERATIONS: usize = 200;
+
+            let encrypted = create_encrypted_ciphertext(
+                TEST_TABLE,
+                TEST_COLUMN,
+                TEST_CIPHERTEXT,
+                TEST_DATA_TYPE,
+            );
+            let envelope = CString::new(serde_json::to_string(&encrypted).unwrap()).unwrap();
+
+            std::thread::scope(|scope| {
+                for _ in 0..THREADS {
+                    scope.spawn(|| {
+                        for _ in 0..ITERATIONS {
+                            let mut error_ptr: *mut c_char = ptr::null_mut();
+
+                            let rendered =
+                                format_envelope(envelope.as_ptr(), ptr::null(), &mut error_ptr);
+                            assert!(error_ptr.is_null());
+                            assert!(!rendered.is_null());
+                            free_string(rendered);
+
+                            let result = decrypt(
+                                ptr::null(),
+                                envelope.as_ptr(),
+                                ptr::null(),
+                                &mut error_ptr,
+                            );
+                            assert!(result.is_null());
+                            assert_null_pointer_error(error_ptr);
+                        }
+                    });
+                }
+            });
+        }
+
+        /// Uses the mutable state a client shares from many threads, as the threads of a ZTS
+        /// process sharing one client pointer do. Creating a client requires ZeroKMS, so the
+        /// state is exercised directly rather than through the exported functions.
+        #[test]
+        fn test_concurrent_shared_client_state() {
+            const THREADS: usize = 16;
+            const ITERATIONS: usize = 200;
+            const BURST: u32 = 10;
+
+            let cache = term_cache::TermCache::new(term_cache::CacheConfig {
+                capacity: 64,
+                ttl_ms: 60_000,
+            })
+            .unwrap();
+            let load = Arc::new(load::Load::new(Some(THREADS)));
+            let rate_limiter = rate_limit::RateLimiter::new(rate_limit::RateLimit {
+                requests_per_second: 0.001,
+                burst: BURST,
+                max_wait_ms: 0,
+            });
+            let admitted = std::sync::atomic::AtomicUsize::new(0);
+            let identifier = Identifier::new(TEST_TABLE, TEST_COLUMN);
+            let encrypted = create_encrypted_ciphertext(
+                TEST_TABLE,
+                TEST_COLUMN,
+                TEST_CIPHERTEXT,
+                TEST_DATA_TYPE,
+            );
+
+            std::thread::scope(|scope| {
+                for thread in 0..THREADS {
+                    let (cache, load, rate_limiter, admitted, identifier, encrypted) = (
+                        &cache,
+                        &load,
+                        &rate_limiter,
+                        &admitted,
+                        &identifier,
+                        &encrypted,
+                    );
+                    scope.spawn(move || {
+                        for iteration in 0..ITERATIONS {
+                            let _operation = load.begin().unwrap();
+
+                            let plaintext = format!("value-{}", (thread + iteration) % 128);
+                            let key = cache.key(identifier, None, &plaintext);
+                            if cache.get(&key).is_none() {
+                                cache.insert(key, encrypted.clone());
+                            }
+
+                            if runtime().unwrap().block_on(rate_limiter.acquire()).is_ok() {
+                                admitted.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                            }
+                        }
+                    });
+                }
+            });
+
+            let metrics = load.metrics(runtime().unwrap());
+            assert_eq!((metrics.queued, metrics.in_flight), (0, 0));
+            assert_eq!(metrics.completed, (THREADS * ITERATIONS) as u64);
+            assert_eq!(metrics.rejected, 0);
+
+            let metrics = cache.metrics();
+            assert_eq!(metrics.hits + metrics.misses, (THREADS * ITERATIONS) as u64);
+            assert!(metrics.entries <= 64);
+
+            assert_eq!(
+                admitted.load(std::sync::atomic::Ordering::Relaxed),
+                BURST as usize
+            );
+        }
+
+        #[test]
+        fn test_get_last_error_after_failed_call() {
+            let ciphertext = CString::new(TEST_CIPHERTEXT).unwrap();
+
+            let result = decrypt(
+                ptr::null(),
+                ciphertext.as_ptr(),
+                ptr::null(),
+                ptr::null_mut(),
+            );
+            assert!(result.is_null());
+
+            let error_ptr = get_last_error();
+            assert_null_pointer_error(error_ptr);
+
+            clear_last_error();
+            assert!(get_last_error().is_null());
+        }
+
+        #[test]
+        fn test_expand_context_with_null_client() {
+            let context = CString::new(r#""@tenant_scoped""#).unwrap();
+            let mut error_ptr: *mut c_char = ptr::null_mut();
+
+            let result = expand_context(ptr::null(), context.as_ptr(), &mut error_ptr);
+            assert!(result.is_null());
+            assert_null_pointer_error(error_ptr);
+        }
+
+        #[test]
+        fn test_client_state_with_null_client() {
+            let mut error_ptr: *mut c_char = ptr::null_mut();
+
+            let result = export_client_state(ptr::null(), &mut error_ptr);
+            assert!(result.is_null());
+            assert_null_pointer_error(error_ptr);
+
+            let state = CString::new("{}").unwrap();
+            let mut error_ptr: *mut c_char = ptr::null_mut();
+            let result = import_client_state(ptr::null(), state.as_ptr(), &mut error_ptr);
+            assert!(result.is_null());
+            assert_null_pointer_error(error_ptr);
+        }
+
+        #[test]
+        fn test_assert_config_matches_null_client() {
+            let digest = CString::new("digest").unwrap();
+            let mut error_ptr: *mut c_char = ptr::null_mut();
+
+            let result = assert_config_matches(ptr::null(), digest.as_ptr(), &mut error_ptr);
+            assert_eq!(result, -1);
+            assert_null_pointer_error(error_ptr);
+        }
+
+        #[test]
+        fn test_set_crash_report_path() {
+            let path = std::env::temp_dir().join(format!(
+                "protect-ffi-crash-report-{}.jsonl",
+                std::process::id()
+            ));
+            let path_c_string = CString::new(path.to_str().unwrap()).unwrap();
+            let mut error_ptr: *mut c_char = ptr::null_mut();
+
+            assert_eq!(
+                set_crash_report_path(path_c_string.as_ptr(), &mut error_ptr),
+                0
+            );
+            let result: Result<(), Error> =
+                safe_ffi::catch_panic("encrypt_bulk", || panic!("failed on \"john@example.com\""));
+            assert_eq!(set_crash_report_path(ptr::null(), &mut error_ptr), 0);
+
+            assert!(matches!(result, Err(Error::Panic("encrypt_bulk"))));
+            let contents = std::fs::read_to_string(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            let report = contents
+                .lines()
+                .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+                .find(|report| report["operation"] == "encrypt_bulk")
+                .unwrap();
+            assert_eq!(report["message"], "failed on \"[redacted]\"");
+            assert_eq!(report["versions"]["protect-ffi"], env!("CARGO_PKG_VERSION"));
+            assert!(report["backtrace"].is_string());
+        }
+
         #[test]
         fn test_free_functions_with_null() {
             free_client(ptr::null_mut());
@@ -1086,7 +5133,16 @@ mod lib {
                 Error::Parse(json_error),
                 Error::Utf8(utf8_error),
                 Error::UnsupportedSchemaVersion(1),
-                Error::UnknownColumn(identifier),
+                Error::UnsupportedEnvelopeVersion(9),
+                Error::MissingSignature,
+                Error::InvalidSignature,
+                Error::InvalidContext {
+                    path: "$.tag[0]".to_string(),
+                    reason: "expected a string".to_string(),
+                },
+                Error::InvalidToken("token has expired".to_string()),
+                Error::UnknownKeyset("pii".to_string()),
+                Error::UnknownColumn(identifier, Vec::new()),
                 Error::Base85("invalid character".to_string()),
                 Error::Unimplemented("bulk operations".to_string()),
                 Error::Runtime("tokio runtime failed".to_string()),
@@ -1113,5 +5169,66 @@ mod lib {
             let converted_error: Error = json_parse_error.into();
             assert!(matches!(converted_error, Error::Parse(_)));
         }
+
+        /// Count the parameters in a parameter list, ignoring commas in nested parentheses.
+        fn parameter_count(parameters: &str) -> usize {
+            let parameters = parameters.trim().trim_end_matches(',');
+            if parameters.is_empty() || parameters == "void" {
+                return 0;
+            }
+
+            let mut depth = 0;
+            let mut count = 1;
+            for character in parameters.chars() {
+                match character {
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    ',' if depth == 0 => count += 1,
+                    _ => {}
+                }
+            }
+            count
+        }
+
+        /// The parameter list that starts after an opening parenthesis.
+        fn parameter_list(after_parenthesis: &str) -> &str {
+            let mut depth = 1;
+            for (index, character) in after_parenthesis.char_indices() {
+                match character {
+                    '(' => depth += 1,
+                    ')' if depth == 1 => return &after_parenthesis[..index],
+                    ')' => depth -= 1,
+                    _ => {}
+                }
+            }
+            after_parenthesis
+        }
+
+        #[test]
+        fn test_header_declares_every_exported_function() {
+            // Read line by line, so the check also holds for checkouts with CRLF line endings.
+            let declared: HashMap<&str, usize> = include_str!("../../../include/protectphp.h")
+                .lines()
+                .map(str::trim)
+                .filter(|line| line.ends_with(");") && !line.starts_with("typedef"))
+                .map(|line| {
+                    let (prefix, rest) = line.split_once('(').unwrap();
+                    let name = prefix.rsplit(['*', ' ']).next().unwrap();
+                    (name, parameter_count(parameter_list(rest)))
+                })
+                .collect();
+
+            let exported: HashMap<&str, usize> = include_str!("lib.rs")
+                .split("#[no_mangle]")
+                .skip(1)
+                .filter_map(|item| item.trim_start().strip_prefix("pub extern \"C\" fn "))
+                .map(|signature| {
+                    let (name, rest) = signature.split_once('(').unwrap();
+                    (name, parameter_count(parameter_list(rest)))
+                })
+                .collect();
+
+            assert_eq!(declared, exported);
+        }
     }
 }
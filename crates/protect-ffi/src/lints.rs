@@ -0,0 +1,126 @@
+//! Lints of column configurations that combine a type with an index that cannot serve it.
+//!
+//! The encryption library attaches any index to any column type, so a `match` index on an
+//! `int` column or an `ore` index on a `jsonb` column is accepted but produces terms no query
+//! can use. Error lints reject the configuration when it is parsed, and warning lints are
+//! returned with the client's configuration warnings. A column lists the lints it accepts in
+//! `allow_lints`, which silences them, for configurations that knowingly keep such an index.
+
+use crate::encrypt_config::CastAs;
+use serde::{Deserialize, Serialize};
+
+/// A combination of column type and index that is rarely what a configuration means.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Lint {
+    /// A `match` index on a column whose values are not text.
+    MatchRequiresText,
+    /// A `ste_vec` index on a column whose values are not `jsonb` documents.
+    SteVecRequiresJsonb,
+    /// An `ore` index on a `jsonb` column, which cannot order documents.
+    OreOnJsonb,
+    /// A `unique` index on a `jsonb` column, which compares documents by their exact text.
+    UniqueOnJsonb,
+}
+
+/// The indexes a column configures.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ColumnIndexes {
+    pub unique: bool,
+    pub ore: bool,
+    pub r#match: bool,
+    pub ste_vec: bool,
+}
+
+impl Lint {
+    /// The name of the lint, as listed in `allow_lints`.
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::MatchRequiresText => "match_requires_text",
+            Self::SteVecRequiresJsonb => "ste_vec_requires_jsonb",
+            Self::OreOnJsonb => "ore_on_jsonb",
+            Self::UniqueOnJsonb => "unique_on_jsonb",
+        }
+    }
+
+    /// Whether the lint rejects the configuration, rather than warning about it.
+    pub fn is_error(self) -> bool {
+        !matches!(self, Self::UniqueOnJsonb)
+    }
+
+    /// A description of the problem for a column of a type, with how to allow it.
+    pub fn message(self, cast_as: CastAs) -> String {
+        let problem = match self {
+            Self::MatchRequiresText => format!(
+                "`match` indexes tokenize text, so they cannot search `{cast_as}` values; use a `unique` or `ore` index, or `cast_as` `text`"
+            ),
+            Self::SteVecRequiresJsonb => format!(
+                "`ste_vec` indexes index the fields of documents, so they require `cast_as` to be `jsonb`, not `{cast_as}`"
+            ),
+            Self::OreOnJsonb => "`ore` indexes cannot order `jsonb` documents; use a `ste_vec` index, whose terms order the fields of documents".to_string(),
+            Self::UniqueOnJsonb => "`unique` indexes compare `jsonb` documents by their exact text, so documents that differ only in key order or whitespace are not equal; use a `ste_vec` index to query documents".to_string(),
+        };
+
+        format!(
+            "{problem} (allow with `\"allow_lints\": [\"{}\"]`)",
+            self.code()
+        )
+    }
+}
+
+/// The lints of a column type and its indexes.
+pub fn check(cast_as: CastAs, indexes: ColumnIndexes) -> Vec<Lint> {
+    let element_type = cast_as.element_type().unwrap_or(cast_as);
+    let mut lints = Vec::new();
+
+    if indexes.r#match && element_type != CastAs::Text {
+        lints.push(Lint::MatchRequiresText);
+    }
+    if indexes.ste_vec && cast_as != CastAs::JsonB {
+        lints.push(Lint::SteVecRequiresJsonb);
+    }
+    if indexes.ore && cast_as == CastAs::JsonB {
+        lints.push(Lint::OreOnJsonb);
+    }
+    if indexes.unique && cast_as == CastAs::JsonB {
+        lints.push(Lint::UniqueOnJsonb);
+    }
+
+    lints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_flags_indexes_the_type_cannot_use() {
+        let text_indexes = ColumnIndexes {
+            unique: true,
+            r#match: true,
+            ..ColumnIndexes::default()
+        };
+        assert!(check(CastAs::Text, text_indexes).is_empty());
+        assert!(check(CastAs::TextArray, text_indexes).is_empty());
+        assert_eq!(
+            check(CastAs::Int, text_indexes),
+            vec![Lint::MatchRequiresText]
+        );
+
+        let jsonb_indexes = ColumnIndexes {
+            unique: true,
+            ore: true,
+            ste_vec: true,
+            ..ColumnIndexes::default()
+        };
+        assert_eq!(
+            check(CastAs::JsonB, jsonb_indexes),
+            vec![Lint::OreOnJsonb, Lint::UniqueOnJsonb]
+        );
+        assert_eq!(
+            check(CastAs::Text, jsonb_indexes),
+            vec![Lint::SteVecRequiresJsonb]
+        );
+        assert!(!Lint::UniqueOnJsonb.is_error());
+    }
+}
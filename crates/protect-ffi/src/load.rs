@@ -0,0 +1,196 @@
+//! Backpressure on a client's outstanding operations.
+//!
+//! Every encryption and decryption call holds an [`Operation`] while it runs. Async jobs hold
+//! theirs from the moment they are started, counted as queued until the runtime begins running
+//! them. With the `max_outstanding` client option, a call that finds the client with that many
+//! queued and in-flight operations fails at once with [`Error::Overloaded`], instead of waiting
+//! behind the backlog, so latency-sensitive requests can shed load or fall back. The counts
+//! are reported by `get_metrics`, together with the task counts of the shared runtime.
+
+use crate::Error;
+use serde::Serialize;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tokio::runtime::Runtime;
+
+/// The operations of a client, shared with the clients derived from it.
+#[derive(Debug)]
+pub struct Load {
+    max_outstanding: Option<usize>,
+    counts: Mutex<Counts>,
+}
+
+/// The number of operations in each state.
+#[derive(Debug, Default)]
+struct Counts {
+    queued: usize,
+    in_flight: usize,
+    completed: u64,
+    rejected: u64,
+}
+
+impl Load {
+    /// Create the load of a client, with an optional limit on its outstanding operations.
+    pub fn new(max_outstanding: Option<usize>) -> Self {
+        Self {
+            max_outstanding,
+            counts: Mutex::new(Counts::default()),
+        }
+    }
+
+    /// Admit an operation that starts running at once.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Overloaded`] if the client has `max_outstanding` operations.
+    pub fn begin(self: &Arc<Self>) -> Result<Operation, Error> {
+        let mut operation = self.queue()?;
+        operation.start();
+        Ok(operation)
+    }
+
+    /// Admit an operation that is queued until [`Operation::start`] is called.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Overloaded`] if the client has `max_outstanding` operations.
+    pub fn queue(self: &Arc<Self>) -> Result<Operation, Error> {
+        let mut counts = self.lock();
+        let outstanding = counts.queued + counts.in_flight;
+
+        if self
+            .max_outstanding
+            .is_some_and(|max_outstanding| outstanding >= max_outstanding)
+        {
+            counts.rejected += 1;
+            return Err(Error::Overloaded { outstanding });
+        }
+
+        counts.queued += 1;
+        Ok(Operation {
+            load: self.clone(),
+            started: false,
+        })
+    }
+
+    /// The client's operation counts, with the task counts of the runtime.
+    pub fn metrics(&self, runtime: &Runtime) -> Metrics {
+        let counts = self.lock();
+        let runtime = runtime.metrics();
+
+        Metrics {
+            queued: counts.queued,
+            in_flight: counts.in_flight,
+            completed: counts.completed,
+            rejected: counts.rejected,
+            max_outstanding: self.max_outstanding,
+            runtime: RuntimeMetrics {
+                workers: runtime.num_workers(),
+                alive_tasks: runtime.num_alive_tasks(),
+                global_queue_depth: runtime.global_queue_depth(),
+            },
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Counts> {
+        self.counts.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// An admitted operation, counted until it is dropped.
+#[derive(Debug)]
+pub struct Operation {
+    load: Arc<Load>,
+    started: bool,
+}
+
+impl Operation {
+    /// Count a queued operation as in flight.
+    pub fn start(&mut self) {
+        if !self.started {
+            let mut counts = self.load.lock();
+            counts.queued -= 1;
+            counts.in_flight += 1;
+            self.started = true;
+        }
+    }
+}
+
+impl Drop for Operation {
+    fn drop(&mut self) {
+        let mut counts = self.load.lock();
+        if self.started {
+            counts.in_flight -= 1;
+            counts.completed += 1;
+        } else {
+            counts.queued -= 1;
+        }
+    }
+}
+
+/// The operation counts returned by `get_metrics`.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Metrics {
+    /// Async jobs waiting for the runtime to start them.
+    pub queued: usize,
+    /// Operations running.
+    pub in_flight: usize,
+    /// Operations that have run, successfully or not.
+    pub completed: u64,
+    /// Operations rejected by the `max_outstanding` limit.
+    pub rejected: u64,
+    /// The `max_outstanding` client option.
+    pub max_outstanding: Option<usize>,
+    /// The task counts of the runtime, which all clients of the process share.
+    pub runtime: RuntimeMetrics,
+}
+
+/// The task counts of the shared runtime.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct RuntimeMetrics {
+    /// The number of worker threads.
+    pub workers: usize,
+    /// The number of tasks that have not finished, including blocking calls.
+    pub alive_tasks: usize,
+    /// The number of tasks waiting in the runtime's global queue.
+    pub global_queue_depth: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operations_are_counted_until_dropped() {
+        let load = Arc::new(Load::new(None));
+        let runtime = Runtime::new().unwrap();
+
+        let mut queued = load.queue().unwrap();
+        let running = load.begin().unwrap();
+        let metrics = load.metrics(&runtime);
+        assert_eq!((metrics.queued, metrics.in_flight), (1, 1));
+
+        queued.start();
+        drop(running);
+        let metrics = load.metrics(&runtime);
+        assert_eq!((metrics.queued, metrics.in_flight), (0, 1));
+        assert_eq!(metrics.completed, 1);
+
+        drop(queued);
+        assert_eq!(load.metrics(&runtime).in_flight, 0);
+    }
+
+    #[test]
+    fn test_max_outstanding_rejects_new_operations() {
+        let load = Arc::new(Load::new(Some(2)));
+        let runtime = Runtime::new().unwrap();
+
+        let first = load.begin().unwrap();
+        let _second = load.queue().unwrap();
+        let result = load.begin();
+        assert!(matches!(result, Err(Error::Overloaded { outstanding: 2 })));
+        assert_eq!(load.metrics(&runtime).rejected, 1);
+
+        drop(first);
+        assert!(load.begin().is_ok());
+    }
+}
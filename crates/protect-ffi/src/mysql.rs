@@ -0,0 +1,340 @@
+//! Output for databases without EQL, such as MySQL.
+//!
+//! MySQL has no `jsonb` containment operators or composite types, so each index term is stored
+//! in a column of its own next to the envelope. For a column named `email`, the columns are:
+//!
+//! - `email` holds the envelope, without index terms, as JSON text
+//! - `email_hm` holds the `unique` index term as a hex string
+//! - `email_ob` holds the `ore` index terms as a JSON array
+//! - `email_bf` holds the `match` index terms as a JSON array
+//! - `email_sv` holds the `ste_vec` entries as a JSON array
+//!
+//! Only the columns for the indexes configured on the column are included, so every value of a
+//! column has the same set of storage columns.
+
+use crate::search_terms::{Operator, SearchTerm};
+use crate::{ElementTerms, Encrypted, Error, SteVecEntry};
+use cipherstash_client::schema::{column::IndexType, ColumnConfig};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Suffix of the `unique` index term column.
+pub const UNIQUE_SUFFIX: &str = "_hm";
+/// Suffix of the `ore` index term column.
+pub const ORE_SUFFIX: &str = "_ob";
+/// Suffix of the `match` index term column.
+pub const MATCH_SUFFIX: &str = "_bf";
+/// Suffix of the `ste_vec` entries column.
+pub const STE_VEC_SUFFIX: &str = "_sv";
+
+/// Split an encrypted value into storage columns named after its column.
+///
+/// The index terms are moved out of the value before `emit_envelope` wraps it, so an envelope
+/// signature covers exactly the envelope stored in the base column. Array values store one
+/// index term per element, as a JSON array in element order.
+///
+/// # Errors
+///
+/// Returns an error if the envelope cannot be emitted or serialized.
+pub fn columns(
+    encrypted: Encrypted,
+    column_config: &ColumnConfig,
+    emit_envelope: impl FnOnce(Encrypted) -> Result<Value, Error>,
+) -> Result<Map<String, Value>, Error> {
+    let mut columns = Map::new();
+
+    let (encrypted, terms) = match encrypted {
+        Encrypted::Ciphertext {
+            ciphertext,
+            data_type,
+            unique_index,
+            ore_index,
+            match_index,
+            elements,
+            identifier,
+            keyset,
+            expires_at,
+            version,
+        } => {
+            let terms = match elements {
+                Some(elements) => IndexTerms::from_elements(elements)?,
+                None => IndexTerms {
+                    unique: unique_index.map(Value::String),
+                    ore: json_text(&ore_index)?,
+                    matches: json_text(&match_index)?,
+                    ste_vec: None,
+                },
+            };
+
+            let encrypted = Encrypted::Ciphertext {
+                ciphertext,
+                data_type,
+                unique_index: None,
+                ore_index: None,
+                match_index: None,
+                elements: None,
+                identifier,
+                keyset,
+                expires_at,
+                version,
+            };
+
+            (encrypted, terms)
+        }
+        Encrypted::SteVec {
+            ciphertext,
+            data_type,
+            ste_vec_index,
+            identifier,
+            keyset,
+            expires_at,
+            version,
+        } => {
+            let terms = IndexTerms {
+                ste_vec: json_text(&ste_vec_index)?,
+                ..IndexTerms::default()
+            };
+
+            let encrypted = Encrypted::SteVec {
+                ciphertext,
+                data_type,
+                ste_vec_index: None,
+                identifier,
+                keyset,
+                expires_at,
+                version,
+            };
+
+            (encrypted, terms)
+        }
+    };
+
+    let column = column_config.name.clone();
+    let envelope = emit_envelope(encrypted)?;
+    columns.insert(
+        column.clone(),
+        Value::String(serde_json::to_string(&envelope)?),
+    );
+
+    for index in &column_config.indexes {
+        let (suffix, term) = match index.index_type {
+            IndexType::Unique { .. } => (UNIQUE_SUFFIX, &terms.unique),
+            IndexType::Ore => (ORE_SUFFIX, &terms.ore),
+            IndexType::Match { .. } => (MATCH_SUFFIX, &terms.matches),
+            IndexType::SteVec { .. } => (STE_VEC_SUFFIX, &terms.ste_vec),
+        };
+
+        columns.insert(
+            format!("{column}{suffix}"),
+            term.clone().unwrap_or(Value::Null),
+        );
+    }
+
+    Ok(columns)
+}
+
+/// Index terms of a value, as storage column values.
+#[derive(Default)]
+struct IndexTerms {
+    unique: Option<Value>,
+    ore: Option<Value>,
+    matches: Option<Value>,
+    ste_vec: Option<Value>,
+}
+
+impl IndexTerms {
+    /// Collect the index terms of each array element into JSON arrays.
+    fn from_elements(elements: Vec<ElementTerms>) -> Result<Self, Error> {
+        let mut unique = Vec::with_capacity(elements.len());
+        let mut ore = Vec::with_capacity(elements.len());
+        let mut matches = Vec::with_capacity(elements.len());
+
+        for element in elements {
+            unique.extend(element.unique_index);
+            ore.extend(element.ore_index);
+            matches.extend(element.match_index);
+        }
+
+        Ok(Self {
+            unique: Some(Value::String(serde_json::to_string(&unique)?)),
+            ore: Some(Value::String(serde_json::to_string(&ore)?)),
+            matches: Some(Value::String(serde_json::to_string(&matches)?)),
+            ste_vec: None,
+        })
+    }
+}
+
+/// Serialize an index term as JSON text, keeping absent terms absent.
+fn json_text<T: Serialize>(term: &Option<T>) -> Result<Option<Value>, Error> {
+    term.as_ref()
+        .map(|term| Ok(Value::String(serde_json::to_string(term)?)))
+        .transpose()
+}
+
+/// A search term for MySQL, with each index term ready to bind as a query parameter.
+///
+/// `hm` compares with `=` against the `_hm` column, while `bf` and `sv` are JSON text for
+/// `JSON_CONTAINS` against the `_bf` and `_sv` columns.
+#[derive(Debug, Serialize)]
+pub struct MysqlSearchTerm {
+    /// HMAC index for exact equality queries.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hm: Option<String>,
+    /// Order-revealing encryption index terms as JSON text.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ob: Option<String>,
+    /// Bloom filter index terms as JSON text.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bf: Option<String>,
+    /// Structured text encryption vector selectors and terms as JSON text.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sv: Option<String>,
+    /// Data type of the searched value, when requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dt: Option<String>,
+    /// Requested query operator, when requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    op: Option<Operator>,
+}
+
+/// Selector and term of a `ste_vec` entry, without the record, so that a search term is
+/// contained in every stored entry list with the same selector and term.
+#[derive(Serialize)]
+struct SteVecQueryEntry<'a> {
+    s: &'a str,
+    t: &'a str,
+}
+
+impl MysqlSearchTerm {
+    /// Render a search term for MySQL.
+    pub fn new(term: SearchTerm) -> Result<Self, Error> {
+        match term {
+            SearchTerm::Indexes {
+                hm, ob, bf, dt, op, ..
+            } => Ok(Self {
+                hm,
+                ob: ob.as_ref().map(serde_json::to_string).transpose()?,
+                bf: bf.as_ref().map(serde_json::to_string).transpose()?,
+                sv: None,
+                dt,
+                op,
+            }),
+            SearchTerm::SteVec { sv, dt, op, .. } => {
+                let sv = sv
+                    .as_deref()
+                    .map(|entries: &[SteVecEntry]| {
+                        let entries: Vec<_> = entries
+                            .iter()
+                            .map(|entry| SteVecQueryEntry {
+                                s: &entry.tokenized_selector,
+                                t: &entry.term,
+                            })
+                            .collect();
+                        serde_json::to_string(&entries)
+                    })
+                    .transpose()?;
+
+                Ok(Self {
+                    hm: None,
+                    ob: None,
+                    bf: None,
+                    sv,
+                    dt,
+                    op,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encrypt_config::Identifier;
+    use crate::search_terms::SearchTermOptions;
+    use cipherstash_client::schema::{column::Index, ColumnType};
+
+    fn email_config() -> ColumnConfig {
+        ColumnConfig::build("email".to_string())
+            .casts_as(ColumnType::Utf8Str)
+            .add_index(Index::new(IndexType::Unique {
+                token_filters: vec![],
+            }))
+            .add_index(Index::new_match())
+    }
+
+    fn encrypted_email() -> Encrypted {
+        Encrypted::Ciphertext {
+            ciphertext: "mBbKlk}G7QdaGiNj$dL7#+AOrA^}*VJx".to_string(),
+            data_type: "text".to_string(),
+            unique_index: Some("f3ca71fd".to_string()),
+            ore_index: None,
+            match_index: Some(vec![1124, 2134]),
+            elements: None,
+            identifier: Identifier::new("users", "email"),
+            keyset: None,
+            expires_at: None,
+            version: 2,
+        }
+    }
+
+    #[test]
+    fn test_columns_split_index_terms() {
+        let columns = columns(encrypted_email(), &email_config(), |encrypted| {
+            Ok(serde_json::to_value(encrypted)?)
+        })
+        .unwrap();
+
+        assert_eq!(columns.len(), 3);
+        assert_eq!(columns["email_hm"], "f3ca71fd");
+        assert_eq!(columns["email_bf"], "[1124,2134]");
+
+        let envelope: Value = serde_json::from_str(columns["email"].as_str().unwrap()).unwrap();
+        assert_eq!(envelope["c"], "mBbKlk}G7QdaGiNj$dL7#+AOrA^}*VJx");
+        assert_eq!(envelope["hm"], Value::Null);
+        assert_eq!(envelope["bf"], Value::Null);
+    }
+
+    #[test]
+    fn test_columns_store_element_terms_as_arrays() {
+        let mut encrypted = encrypted_email();
+        if let Encrypted::Ciphertext {
+            unique_index,
+            match_index,
+            elements,
+            ..
+        } = &mut encrypted
+        {
+            *unique_index = None;
+            *match_index = None;
+            *elements = Some(vec![
+                ElementTerms {
+                    unique_index: Some("aa".to_string()),
+                    ore_index: None,
+                    match_index: Some(vec![1]),
+                },
+                ElementTerms {
+                    unique_index: Some("bb".to_string()),
+                    ore_index: None,
+                    match_index: Some(vec![2]),
+                },
+            ]);
+        }
+
+        let columns = columns(encrypted, &email_config(), |encrypted| {
+            Ok(serde_json::to_value(encrypted)?)
+        })
+        .unwrap();
+
+        assert_eq!(columns["email_hm"], r#"["aa","bb"]"#);
+        assert_eq!(columns["email_bf"], "[[1],[2]]");
+    }
+
+    #[test]
+    fn test_search_term_binds_json_text() {
+        let term = SearchTerm::new(encrypted_email(), None, &SearchTermOptions::default());
+        let rendered = serde_json::to_string(&MysqlSearchTerm::new(term).unwrap()).unwrap();
+
+        assert_eq!(rendered, r#"{"hm":"f3ca71fd","bf":"[1124,2134]"}"#);
+    }
+}
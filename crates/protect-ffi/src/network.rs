@@ -0,0 +1,103 @@
+//! The network destinations of a client.
+//!
+//! A client connects only to CTS, which issues the service tokens that authenticate its
+//! requests, and to ZeroKMS, which generates and retrieves its data keys. The crate sends no
+//! telemetry and makes no update checks. The `offline` client option turns this into a
+//! guarantee for restricted environments: every connection the crate opens itself is checked
+//! against the client's CTS and ZeroKMS hosts, and a connection to any other host fails with
+//! [`Error::NetworkForbidden`] before the host is resolved. `get_network_policy` reports the
+//! destinations a client may contact, so deployments can verify the policy in effect.
+
+use crate::Error;
+use serde::Serialize;
+
+/// The address of a service.
+#[derive(Clone, Debug, Serialize)]
+pub struct Endpoint {
+    /// The service's base URL.
+    pub url: String,
+    /// The service's host.
+    pub host: String,
+    /// The service's port.
+    pub port: u16,
+}
+
+impl Endpoint {
+    /// Create the endpoint of a service from its base URL's parts.
+    pub fn new(url: String, host: Option<&str>, port: Option<u16>) -> Self {
+        Self {
+            url,
+            host: host.unwrap_or_default().to_string(),
+            port: port.unwrap_or(443),
+        }
+    }
+}
+
+/// The destinations a client may contact, returned by `get_network_policy`.
+#[derive(Debug, Serialize)]
+pub struct NetworkPolicy {
+    /// Whether connections to hosts other than CTS and ZeroKMS are refused.
+    pub offline: bool,
+    /// The CTS endpoint.
+    pub cts: Endpoint,
+    /// The ZeroKMS endpoint.
+    pub zerokms: Endpoint,
+}
+
+impl NetworkPolicy {
+    /// Check that the client may connect to an endpoint.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NetworkForbidden`] if the client is offline and the endpoint's host is
+    /// neither the CTS nor the ZeroKMS host.
+    pub fn check(&self, endpoint: &Endpoint) -> Result<(), Error> {
+        let allowed = [&self.cts, &self.zerokms]
+            .iter()
+            .any(|service| service.host.eq_ignore_ascii_case(&endpoint.host));
+
+        if self.offline && !allowed {
+            return Err(Error::NetworkForbidden(endpoint.host.clone()));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint(host: &str) -> Endpoint {
+        Endpoint::new(format!("https://{host}/"), Some(host), None)
+    }
+
+    fn policy(offline: bool) -> NetworkPolicy {
+        NetworkPolicy {
+            offline,
+            cts: endpoint("ap-southeast-2.aws.auth.viturhosted.net"),
+            zerokms: endpoint("ap-southeast-2.aws.viturhosted.net"),
+        }
+    }
+
+    #[test]
+    fn test_offline_policy_allows_only_cts_and_zerokms() {
+        let policy = policy(true);
+
+        assert!(policy
+            .check(&endpoint("AP-SOUTHEAST-2.aws.viturhosted.net"))
+            .is_ok());
+        assert!(policy.check(&policy.cts).is_ok());
+        assert!(matches!(
+            policy.check(&endpoint("telemetry.example.com")),
+            Err(Error::NetworkForbidden(host)) if host == "telemetry.example.com"
+        ));
+    }
+
+    #[test]
+    fn test_online_policy_allows_any_host() {
+        assert!(policy(false)
+            .check(&endpoint("telemetry.example.com"))
+            .is_ok());
+    }
+}
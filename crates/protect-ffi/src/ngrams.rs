@@ -0,0 +1,437 @@
+//! Tokenizers for `match` indexes, including the edge n-gram modes used for prefix search.
+//!
+//! The `standard` and `ngram` tokenizers are applied by the encryption library. The library has
+//! no tokenizer for prefixes, so the `edge_ngram` and `prefix` modes compute their tokens here:
+//! the leading characters of each word, or of the whole value. Tokens are hex-encoded, so that
+//! none contains a separator, and indexed with the `standard` tokenizer. Search terms are
+//! tokenized the same way, so a search term matches every value with a word, or a value, that
+//! starts with it.
+//!
+//! A `match` index can also limit the text it tokenizes with `max_tokens` and `max_input_bytes`,
+//! so that one very large value cannot produce enough tokens to slow down the encryption of its
+//! whole batch. Text over a limit is truncated to its leading part within the limits, or
+//! rejected when the index sets `overflow` to `error`. Only the index terms are affected: the
+//! ciphertext always holds the plaintext as supplied.
+
+use crate::Error;
+use cipherstash_client::schema::column::{TokenFilter, Tokenizer};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Maximum token length of the `ngram`, `edge_ngram`, and `prefix` tokenizers.
+pub const MAX_TOKEN_LENGTH: usize = 32;
+
+/// The tokenizer of a `match` index.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MatchTokenizer {
+    /// Split text into words.
+    #[default]
+    Standard,
+    /// Split text into every run of `token_length` characters.
+    Ngram {
+        /// The number of characters in each token.
+        #[serde(default = "default_token_length")]
+        token_length: usize,
+    },
+    /// Index the leading characters of each word, for prefix search on words.
+    EdgeNgram {
+        /// The length of the shortest prefix indexed.
+        #[serde(default = "default_min_length")]
+        min_length: usize,
+        /// The length of the longest prefix indexed.
+        #[serde(default = "default_max_length")]
+        max_length: usize,
+    },
+    /// Index the leading characters of the whole value, for autocomplete on values.
+    Prefix {
+        /// The length of the shortest prefix indexed.
+        #[serde(default = "default_min_length")]
+        min_length: usize,
+        /// The length of the longest prefix indexed.
+        #[serde(default = "default_max_length")]
+        max_length: usize,
+    },
+}
+
+/// Default token length of the `ngram` tokenizer.
+fn default_token_length() -> usize {
+    3
+}
+
+/// Default length of the shortest prefix indexed by the edge modes.
+fn default_min_length() -> usize {
+    1
+}
+
+/// Default length of the longest prefix indexed by the edge modes.
+fn default_max_length() -> usize {
+    10
+}
+
+impl MatchTokenizer {
+    /// Check that token lengths are in range.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first token length that is out of range.
+    pub fn validate(&self) -> Result<(), String> {
+        match *self {
+            Self::Standard => Ok(()),
+            Self::Ngram { token_length } if !(1..=MAX_TOKEN_LENGTH).contains(&token_length) => Err(
+                format!("`token_length` must be between 1 and {MAX_TOKEN_LENGTH}"),
+            ),
+            Self::Ngram { .. } => Ok(()),
+            Self::EdgeNgram {
+                min_length,
+                max_length,
+            }
+            | Self::Prefix {
+                min_length,
+                max_length,
+            } => {
+                if !(1..=MAX_TOKEN_LENGTH).contains(&max_length) {
+                    Err(format!(
+                        "`max_length` must be between 1 and {MAX_TOKEN_LENGTH}"
+                    ))
+                } else if !(1..=max_length).contains(&min_length) {
+                    Err("`min_length` must be between 1 and `max_length`".to_string())
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    /// The length in bytes of the longest leading part of text that produces at most
+    /// `max_tokens` tokens, counting each prefix of the edge modes as a token.
+    fn tokens_end(&self, text: &str, max_tokens: usize) -> usize {
+        let chars_end = |chars: usize| {
+            text.char_indices()
+                .nth(chars)
+                .map_or(text.len(), |(end, _)| end)
+        };
+
+        match *self {
+            Self::Ngram { token_length } => chars_end(max_tokens + token_length - 1),
+            Self::Prefix {
+                min_length,
+                max_length,
+            } if max_tokens <= max_length - min_length => chars_end(min_length - 1 + max_tokens),
+            Self::Prefix { .. } => text.len(),
+            Self::Standard | Self::EdgeNgram { .. } => {
+                let mut tokens = 0;
+                let mut end = 0;
+                for (start, word) in words(text) {
+                    tokens += match *self {
+                        Self::EdgeNgram {
+                            min_length,
+                            max_length,
+                        } => (word.chars().count().min(max_length) + 1).saturating_sub(min_length),
+                        _ => 1,
+                    };
+                    if tokens > max_tokens {
+                        return end;
+                    }
+                    end = start + word.len();
+                }
+                text.len()
+            }
+        }
+    }
+
+    /// The tokenizer the encryption library applies to the indexed text.
+    pub fn library_tokenizer(&self) -> Tokenizer {
+        match *self {
+            Self::Ngram { token_length } => Tokenizer::Ngram { token_length },
+            _ => Tokenizer::Standard,
+        }
+    }
+
+    /// The edge n-grams of the edge modes, with the index's token filters applied first.
+    pub fn edge_ngrams(&self, token_filters: &[TokenFilter]) -> Option<EdgeNgrams> {
+        let (words, min_length, max_length) = match *self {
+            Self::EdgeNgram {
+                min_length,
+                max_length,
+            } => (true, min_length, max_length),
+            Self::Prefix {
+                min_length,
+                max_length,
+            } => (false, min_length, max_length),
+            _ => return None,
+        };
+
+        Some(EdgeNgrams {
+            words,
+            min_length,
+            max_length,
+            downcase: token_filters.contains(&TokenFilter::Downcase),
+        })
+    }
+}
+
+/// What happens to text over the limits of a `match` index.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitOverflow {
+    /// Index the leading part of the text within the limits.
+    #[default]
+    Truncate,
+    /// Reject the text.
+    Error,
+}
+
+impl LimitOverflow {
+    /// Whether text over the limits is truncated, which is the default.
+    pub fn is_truncate(&self) -> bool {
+        *self == Self::Truncate
+    }
+}
+
+/// The limits on the text a `match` index tokenizes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MatchLimits {
+    /// The tokenizer whose tokens are counted.
+    pub tokenizer: MatchTokenizer,
+    /// The most tokens indexed, if limited.
+    pub max_tokens: Option<usize>,
+    /// The most bytes of text tokenized, if limited.
+    pub max_input_bytes: Option<usize>,
+    /// What happens to text over the limits.
+    pub overflow: LimitOverflow,
+}
+
+impl MatchLimits {
+    /// The leading part of text within the limits, cut at a character boundary.
+    pub fn truncate<'a>(&self, text: &'a str) -> &'a str {
+        let mut end = text.len();
+        if let Some(max_input_bytes) = self.max_input_bytes.filter(|&max| max < end) {
+            end = (0..=max_input_bytes)
+                .rev()
+                .find(|&index| text.is_char_boundary(index))
+                .unwrap_or(0);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            end = self.tokenizer.tokens_end(&text[..end], max_tokens);
+        }
+
+        &text[..end]
+    }
+
+    /// Whether text is over the limits.
+    pub fn exceeds(&self, text: &str) -> bool {
+        self.truncate(text).len() < text.len()
+    }
+
+    /// Check that text is within the limits.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::MatchLimitExceeded`] naming the first limit the text is over.
+    pub fn check(&self, text: &str) -> Result<(), Error> {
+        if let Some(max_input_bytes) = self.max_input_bytes.filter(|&max| text.len() > max) {
+            return Err(Error::MatchLimitExceeded(format!(
+                "the text is {} bytes, more than the `max_input_bytes` of {max_input_bytes}",
+                text.len()
+            )));
+        }
+        if let Some(max_tokens) = self.max_tokens.filter(|_| self.exceeds(text)) {
+            return Err(Error::MatchLimitExceeded(format!(
+                "the text has more tokens than the `max_tokens` of {max_tokens}"
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// The words of text with their byte offsets, split as the `standard` tokenizer splits them.
+fn words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(move |word| (word.as_ptr() as usize - text.as_ptr() as usize, word))
+}
+
+/// The prefixes an edge mode indexes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct EdgeNgrams {
+    /// Whether prefixes are taken from each word, rather than from the whole value.
+    pub words: bool,
+    /// The length of the shortest prefix, in characters.
+    pub min_length: usize,
+    /// The length of the longest prefix, in characters.
+    pub max_length: usize,
+    /// Whether text is lowercased before taking prefixes.
+    pub downcase: bool,
+}
+
+impl EdgeNgrams {
+    /// Rewrite text as its hex-encoded prefixes, separated by spaces.
+    pub fn apply(&self, text: &str) -> String {
+        let text = if self.downcase {
+            text.to_lowercase()
+        } else {
+            text.to_string()
+        };
+
+        let sources: Vec<&str> = if self.words {
+            text.split(|c: char| !c.is_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .collect()
+        } else {
+            vec![text.as_str()]
+        };
+
+        let mut tokens = BTreeSet::new();
+        for source in sources {
+            let prefix_ends = source
+                .char_indices()
+                .map(|(start, c)| start + c.len_utf8())
+                .take(self.max_length)
+                .skip(self.min_length - 1);
+
+            for end in prefix_ends {
+                tokens.insert(hex::encode(&source[..end]));
+            }
+        }
+
+        tokens.into_iter().collect::<Vec<_>>().join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(tokens: &str) -> Vec<String> {
+        tokens
+            .split(' ')
+            .map(|token| String::from_utf8(hex::decode(token).unwrap()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenizer_defaults() {
+        let tokenizer: MatchTokenizer = serde_json::from_str(r#"{"kind": "ngram"}"#).unwrap();
+        assert_eq!(tokenizer, MatchTokenizer::Ngram { token_length: 3 });
+
+        let tokenizer: MatchTokenizer = serde_json::from_str(r#"{"kind": "prefix"}"#).unwrap();
+        assert_eq!(
+            tokenizer,
+            MatchTokenizer::Prefix {
+                min_length: 1,
+                max_length: 10
+            }
+        );
+    }
+
+    #[test]
+    fn test_validate_token_lengths() {
+        for tokenizer in [
+            MatchTokenizer::Ngram { token_length: 0 },
+            MatchTokenizer::Ngram { token_length: 33 },
+            MatchTokenizer::EdgeNgram {
+                min_length: 0,
+                max_length: 5,
+            },
+            MatchTokenizer::Prefix {
+                min_length: 6,
+                max_length: 5,
+            },
+        ] {
+            assert!(tokenizer.validate().is_err(), "{tokenizer:?}");
+        }
+
+        assert!(MatchTokenizer::Ngram { token_length: 3 }.validate().is_ok());
+    }
+
+    #[test]
+    fn test_limits_truncate_to_whole_tokens() {
+        let limits = |tokenizer, max_tokens, max_input_bytes: Option<usize>| MatchLimits {
+            tokenizer,
+            max_tokens,
+            max_input_bytes,
+            overflow: LimitOverflow::Truncate,
+        };
+        let text = "Ada Lovelace, Countess of Lovelace";
+
+        let standard = limits(MatchTokenizer::Standard, Some(2), None);
+        assert_eq!(standard.truncate(text), "Ada Lovelace");
+        assert_eq!(standard.truncate("Ada"), "Ada");
+
+        let ngram = limits(MatchTokenizer::Ngram { token_length: 3 }, Some(2), None);
+        assert_eq!(ngram.truncate(text), "Ada ");
+
+        let edge_ngram = MatchTokenizer::EdgeNgram {
+            min_length: 2,
+            max_length: 4,
+        };
+        assert_eq!(limits(edge_ngram, Some(4), None).truncate(text), "Ada");
+        assert_eq!(
+            limits(edge_ngram, Some(5), None).truncate(text),
+            "Ada Lovelace"
+        );
+
+        let prefix = MatchTokenizer::Prefix {
+            min_length: 1,
+            max_length: 10,
+        };
+        assert_eq!(limits(prefix, Some(3), None).truncate(text), "Ada");
+        assert!(!limits(prefix, Some(10), None).exceeds(text));
+
+        let bytes = limits(MatchTokenizer::Standard, None, Some(2));
+        assert_eq!(bytes.truncate("é@b"), "é");
+        assert!(!bytes.exceeds("ab"));
+    }
+
+    #[test]
+    fn test_limits_check_names_the_limit() {
+        let limits = MatchLimits {
+            tokenizer: MatchTokenizer::Standard,
+            max_tokens: Some(2),
+            max_input_bytes: Some(16),
+            overflow: LimitOverflow::Error,
+        };
+
+        assert!(limits.check("Ada Lovelace").is_ok());
+        assert!(matches!(
+            limits.check("Ada King Lovelace"),
+            Err(Error::MatchLimitExceeded(message)) if message.contains("`max_input_bytes` of 16")
+        ));
+        assert!(matches!(
+            limits.check("a b c"),
+            Err(Error::MatchLimitExceeded(message)) if message.contains("`max_tokens` of 2")
+        ));
+    }
+
+    #[test]
+    fn test_edge_ngrams_of_words() {
+        let edge_ngrams = MatchTokenizer::EdgeNgram {
+            min_length: 2,
+            max_length: 3,
+        }
+        .edge_ngrams(&[TokenFilter::Downcase])
+        .unwrap();
+
+        assert_eq!(
+            decode(&edge_ngrams.apply("Ada Lovelace")),
+            ["ad", "ada", "lo", "lov"]
+        );
+    }
+
+    #[test]
+    fn test_prefixes_of_value() {
+        let edge_ngrams = MatchTokenizer::Prefix {
+            min_length: 1,
+            max_length: 4,
+        }
+        .edge_ngrams(&[])
+        .unwrap();
+
+        assert_eq!(
+            decode(&edge_ngrams.apply("é@b.c")),
+            ["é", "é@", "é@b", "é@b."]
+        );
+    }
+}
@@ -0,0 +1,290 @@
+//! Unicode normalization of text before indexing.
+//!
+//! Composed and decomposed forms of the same text, such as `é` as a single code point or as `e`
+//! followed by a combining accent, are different strings and produce different index terms. A
+//! `unique` or `match` index with a `unicode_normalization` form indexes a normalized copy of the
+//! plaintext, so equivalent text produces matching terms, while the ciphertext keeps the
+//! plaintext exactly as supplied.
+//!
+//! The `edge_ngram` and `prefix` tokenizers of a `match` index similarly index a rewritten copy
+//! of the plaintext, as do the `max_tokens` and `max_input_bytes` limits, as described in
+//! [`crate::ngrams`].
+//!
+//! A value is encrypted with the column's other indexes, followed by one target per distinct
+//! source of index text carrying only the indexes created from that source. The ciphertexts of
+//! the source targets are discarded and their index terms are attached to the value.
+
+use crate::ngrams::{EdgeNgrams, LimitOverflow, MatchLimits};
+use crate::plaintext_target;
+use crate::Error;
+use cipherstash_client::{
+    encryption::PlaintextTarget,
+    schema::{column::IndexType, ColumnConfig},
+};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use strum::Display;
+use unicode_normalization::UnicodeNormalization as _;
+
+/// A Unicode normalization form.
+#[derive(Clone, Copy, Debug, Deserialize, Display, Eq, PartialEq, Serialize)]
+pub enum UnicodeNormalization {
+    /// Canonical composition, which unifies composed and decomposed characters.
+    #[serde(rename = "nfc", alias = "NFC")]
+    #[strum(serialize = "nfc")]
+    Nfc,
+    /// Compatibility composition, which also unifies compatibility characters such as ligatures
+    /// and full-width forms with their plain equivalents.
+    #[serde(rename = "nfkc", alias = "NFKC")]
+    #[strum(serialize = "nfkc")]
+    Nfkc,
+}
+
+impl UnicodeNormalization {
+    /// Normalize text to this form.
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            Self::Nfc => text.nfc().collect(),
+            Self::Nfkc => text.nfkc().collect(),
+        }
+    }
+}
+
+/// The text a column's `unique` and `match` index terms are created from.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct IndexNormalization {
+    /// The form `unique` terms are created from, if any.
+    pub unique: Option<UnicodeNormalization>,
+    /// The form `match` terms are created from, if any.
+    pub r#match: Option<UnicodeNormalization>,
+    /// The prefixes `match` terms are created from, for the edge tokenizer modes.
+    pub match_edge_ngrams: Option<EdgeNgrams>,
+    /// The limits on the text `match` terms are created from, if any.
+    pub match_limits: Option<MatchLimits>,
+}
+
+/// A rewritten copy of a plaintext that index terms are created from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IndexSource {
+    /// The normalization form of the text, if any.
+    pub form: Option<UnicodeNormalization>,
+    /// The prefixes taken from the normalized text, if any.
+    pub edge_ngrams: Option<EdgeNgrams>,
+    /// The limits the normalized text is truncated to, if any.
+    pub limits: Option<MatchLimits>,
+}
+
+impl IndexSource {
+    /// Rewrite a plaintext into the text index terms are created from.
+    pub fn apply(&self, text: &str) -> String {
+        let text = self.normalized(text);
+        let text = match self.limits {
+            Some(limits) => limits.truncate(&text),
+            None => &*text,
+        };
+
+        match self.edge_ngrams {
+            Some(edge_ngrams) => edge_ngrams.apply(text),
+            None => text.to_string(),
+        }
+    }
+
+    /// Check that a plaintext is within the source's limits, if exceeding them is an error
+    /// rather than truncating the text.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::MatchLimitExceeded`] if the normalized text is over a limit.
+    pub fn check(&self, text: &str) -> Result<(), Error> {
+        match self.limits {
+            Some(limits) if limits.overflow == LimitOverflow::Error => {
+                limits.check(&self.normalized(text))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn normalized<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self.form {
+            Some(form) => Cow::Owned(form.apply(text)),
+            None => Cow::Borrowed(text),
+        }
+    }
+}
+
+impl IndexNormalization {
+    /// The distinct sources, in the order their targets follow the value's own target.
+    pub fn sources(&self) -> Vec<IndexSource> {
+        let mut sources = Vec::with_capacity(2);
+        for source in [self.unique_source(), self.match_source()]
+            .into_iter()
+            .flatten()
+        {
+            if !sources.contains(&source) {
+                sources.push(source);
+            }
+        }
+        sources
+    }
+
+    /// Create the targets for a text value: the value with the indexes created from the
+    /// plaintext as supplied, followed by one target per distinct source.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::MatchLimitExceeded`] if the plaintext is over limits that reject it, or
+    /// an error if a target cannot be created.
+    pub fn targets(
+        &self,
+        plaintext: String,
+        column_config: &ColumnConfig,
+    ) -> Result<Vec<PlaintextTarget>, Error> {
+        let sources = self.sources();
+        if sources.is_empty() {
+            return Ok(vec![plaintext_target::new(plaintext, column_config)?]);
+        }
+
+        let mut targets = Vec::with_capacity(sources.len() + 1);
+        for source in sources {
+            source.check(&plaintext)?;
+            let mut source_config = column_config.clone();
+            source_config
+                .indexes
+                .retain(|index| self.source_of(&index.index_type) == Some(source));
+            targets.push(plaintext_target::new(
+                source.apply(&plaintext),
+                &source_config,
+            )?);
+        }
+
+        let mut value_config = column_config.clone();
+        value_config
+            .indexes
+            .retain(|index| self.source_of(&index.index_type).is_none());
+        targets.insert(0, plaintext_target::new(plaintext, &value_config)?);
+
+        Ok(targets)
+    }
+
+    /// The source an index is created from, if it is not the plaintext as supplied.
+    pub fn source_of(&self, index_type: &IndexType) -> Option<IndexSource> {
+        match index_type {
+            IndexType::Unique { .. } => self.unique_source(),
+            IndexType::Match { .. } => self.match_source(),
+            _ => None,
+        }
+    }
+
+    /// The source of the `unique` index, if it is not the plaintext as supplied.
+    fn unique_source(&self) -> Option<IndexSource> {
+        self.unique.map(|form| IndexSource {
+            form: Some(form),
+            edge_ngrams: None,
+            limits: None,
+        })
+    }
+
+    /// The source of the `match` index, if it is not the plaintext as supplied.
+    fn match_source(&self) -> Option<IndexSource> {
+        (self.r#match.is_some() || self.match_edge_ngrams.is_some() || self.match_limits.is_some())
+            .then_some(IndexSource {
+                form: self.r#match,
+                edge_ngrams: self.match_edge_ngrams,
+                limits: self.match_limits,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cipherstash_client::schema::{column::Index, ColumnType};
+
+    #[test]
+    fn test_apply_unifies_composed_and_decomposed_text() {
+        let composed = "caf\u{e9}";
+        let decomposed = "cafe\u{301}";
+
+        assert_ne!(composed, decomposed);
+        assert_eq!(UnicodeNormalization::Nfc.apply(decomposed), composed);
+        assert_eq!(UnicodeNormalization::Nfkc.apply(decomposed), composed);
+        assert_eq!(UnicodeNormalization::Nfc.apply("\u{fb01}le"), "\u{fb01}le");
+        assert_eq!(UnicodeNormalization::Nfkc.apply("\u{fb01}le"), "file");
+    }
+
+    #[test]
+    fn test_sources_are_distinct() {
+        let nfc = IndexSource {
+            form: Some(UnicodeNormalization::Nfc),
+            edge_ngrams: None,
+            limits: None,
+        };
+
+        let normalization = IndexNormalization {
+            unique: Some(UnicodeNormalization::Nfc),
+            r#match: Some(UnicodeNormalization::Nfc),
+            match_edge_ngrams: None,
+            match_limits: None,
+        };
+        assert_eq!(normalization.sources(), [nfc]);
+
+        let edge_ngrams = EdgeNgrams {
+            words: true,
+            min_length: 1,
+            max_length: 10,
+            downcase: false,
+        };
+        let normalization = IndexNormalization {
+            unique: Some(UnicodeNormalization::Nfc),
+            r#match: Some(UnicodeNormalization::Nfc),
+            match_edge_ngrams: Some(edge_ngrams),
+            match_limits: None,
+        };
+        assert_eq!(
+            normalization.sources(),
+            [
+                nfc,
+                IndexSource {
+                    form: Some(UnicodeNormalization::Nfc),
+                    edge_ngrams: Some(edge_ngrams),
+                    limits: None,
+                }
+            ]
+        );
+        assert!(IndexNormalization::default().sources().is_empty());
+    }
+
+    #[test]
+    fn test_targets_split_normalized_indexes() {
+        let column_config = ColumnConfig::build("email".to_string())
+            .casts_as(ColumnType::Utf8Str)
+            .add_index(Index::new(IndexType::Unique {
+                token_filters: vec![],
+            }))
+            .add_index(Index::new_ore());
+        let normalization = IndexNormalization {
+            unique: Some(UnicodeNormalization::Nfc),
+            ..IndexNormalization::default()
+        };
+
+        let targets = normalization
+            .targets("cafe\u{301}".to_string(), &column_config)
+            .unwrap();
+
+        assert_eq!(targets.len(), 2);
+        assert!(matches!(
+            targets[0].config.indexes[..],
+            [Index {
+                index_type: IndexType::Ore,
+                ..
+            }]
+        ));
+        assert!(matches!(
+            targets[1].config.indexes[..],
+            [Index {
+                index_type: IndexType::Unique { .. },
+                ..
+            }]
+        ));
+    }
+}
@@ -5,7 +5,7 @@ use cipherstash_client::{
     schema::{column::IndexType, ColumnConfig, ColumnType},
 };
 
-use crate::Error;
+use crate::{dates, floats, Error};
 
 /// Creates a [`PlaintextTarget`] with specialized handling for JSONB columns with `ste_vec` indexes.
 ///
@@ -16,11 +16,31 @@ use crate::Error;
 /// [`cipherstash_client::encryption::Plaintext::JsonB`], so this pre-parsing step ensures the
 /// correct type inference for `ste_vec` index compatibility.
 ///
+/// The document root may be an object, an array, or a scalar. Each is indexed as a whole by the
+/// root entry, with `parent_is_array` false, followed by an entry for every nested value, with
+/// `parent_is_array` true for the elements of arrays, including those of an array root. A `null`
+/// root has no value to index, so it is rejected in favor of storing SQL `NULL`.
+///
+/// Floating-point plaintexts must be finite numbers, so that decryption returns the value that
+/// was supplied. Date plaintexts must be calendar dates, and are normalized to `YYYY-MM-DD` so
+/// that their order-revealing encryption terms follow calendar order.
+///
 /// # Errors
 ///
-/// Returns an error if the input string is not valid JSON when targeting a JSONB column
-/// with `ste_vec` indexes.
+/// Returns an error if the input string is not valid JSON or is `null` when targeting a JSONB
+/// column with `ste_vec` indexes, is not a finite number when targeting a `real` or `double` column, or
+/// is not a calendar date when targeting a `date` column.
 pub fn new(plaintext: String, column_config: &ColumnConfig) -> Result<PlaintextTarget, Error> {
+    if column_config.cast_type == ColumnType::Float {
+        floats::parse(&plaintext)?;
+    }
+
+    let plaintext = if column_config.cast_type == ColumnType::Date {
+        dates::normalize(&plaintext)?
+    } else {
+        plaintext
+    };
+
     let needs_json_parsing = column_config.cast_type == ColumnType::JsonB
         && column_config
             .indexes
@@ -28,17 +48,57 @@ pub fn new(plaintext: String, column_config: &ColumnConfig) -> Result<PlaintextT
             .any(|idx| matches!(idx.index_type, IndexType::SteVec { .. }));
 
     if needs_json_parsing {
-        let json_value: serde_json::Value =
-            serde_json::from_str(&plaintext).map_err(Error::Parse)?;
+        let json_value = json_document(&plaintext)?;
         Ok(PlaintextTarget::new(json_value, column_config.clone()))
     } else {
         Ok(PlaintextTarget::new(plaintext, column_config.clone()))
     }
 }
 
+/// Parse a JSON document for `ste_vec` indexing.
+///
+/// # Errors
+///
+/// Returns [`Error::Parse`] if the plaintext is not valid JSON, or
+/// [`Error::InvalidJsonDocument`] if it is `null`.
+pub fn json_document(plaintext: &str) -> Result<serde_json::Value, Error> {
+    let json_value: serde_json::Value = serde_json::from_str(plaintext).map_err(Error::Parse)?;
+    if JsonRoot::of(&json_value).is_none() {
+        return Err(Error::InvalidJsonDocument(
+            "a `null` document cannot be indexed, store SQL `NULL` instead".to_string(),
+        ));
+    }
+    Ok(json_value)
+}
+
+/// The kind of value at the root of a JSON document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JsonRoot {
+    /// An object, whose fields are indexed with `parent_is_array` false.
+    Object,
+    /// An array, whose elements are indexed with `parent_is_array` true.
+    Array,
+    /// A string, number, or boolean, indexed by the root entry alone.
+    Scalar,
+}
+
+impl JsonRoot {
+    /// The kind of root of a document, or `None` for a `null` document.
+    pub fn of(document: &serde_json::Value) -> Option<Self> {
+        match document {
+            serde_json::Value::Null => None,
+            serde_json::Value::Object(_) => Some(Self::Object),
+            serde_json::Value::Array(_) => Some(Self::Array),
+            _ => Some(Self::Scalar),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use cipherstash_client::schema::column::Index;
+    use serde_json::json;
 
     #[test]
     fn test_new_with_text_plaintext() {
@@ -60,6 +120,32 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_new_with_array_and_scalar_jsonb_roots() {
+        let column_config = ColumnConfig::build("metadata".to_string())
+            .casts_as(ColumnType::JsonB)
+            .add_index(Index::new(IndexType::SteVec {
+                prefix: "users/metadata".to_string(),
+            }));
+
+        for plaintext in [r#"[1, {"a": "b"}]"#, r#""hello""#, "42", "true"] {
+            let result = new(plaintext.to_string(), &column_config);
+            assert!(result.is_ok(), "{plaintext}");
+        }
+
+        let result = new("null".to_string(), &column_config);
+        assert!(matches!(result, Err(Error::InvalidJsonDocument(_))));
+    }
+
+    #[test]
+    fn test_json_root_kinds() {
+        assert_eq!(JsonRoot::of(&json!({"a": 1})), Some(JsonRoot::Object));
+        assert_eq!(JsonRoot::of(&json!([1, 2])), Some(JsonRoot::Array));
+        assert_eq!(JsonRoot::of(&json!("a")), Some(JsonRoot::Scalar));
+        assert_eq!(JsonRoot::of(&json!(1.5)), Some(JsonRoot::Scalar));
+        assert_eq!(JsonRoot::of(&json!(null)), None);
+    }
+
     #[test]
     fn test_new_with_jsonb_no_validation() {
         let column_config = ColumnConfig::build("metadata".to_string()).casts_as(ColumnType::JsonB);
@@ -70,4 +156,26 @@ mod tests {
 
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_new_rejects_non_finite_float() {
+        let column_config = ColumnConfig::build("price".to_string()).casts_as(ColumnType::Float);
+
+        for plaintext in ["NaN", "INF", "-infinity"] {
+            let result = new(plaintext.to_string(), &column_config);
+            assert!(matches!(result, Err(Error::InvalidFloat(_))), "{plaintext}");
+        }
+
+        assert!(new("19.99".to_string(), &column_config).is_ok());
+    }
+
+    #[test]
+    fn test_new_rejects_date_timestamps() {
+        let column_config = ColumnConfig::build("born_on".to_string()).casts_as(ColumnType::Date);
+
+        let result = new("2020-11-10T23:30:00-05:00".to_string(), &column_config);
+        assert!(matches!(result, Err(Error::InvalidDate(_))));
+
+        assert!(new("2020-11-10".to_string(), &column_config).is_ok());
+    }
 }
@@ -0,0 +1,213 @@
+//! Dry-run encryption plans for `plan_encrypt`.
+//!
+//! A plan checks each bulk encryption item against the client's configuration exactly as
+//! `encrypt_bulk` would before encrypting, without contacting ZeroKMS. Items are checked
+//! independently, so a single plan reports every problem in a batch.
+
+use crate::context::encryption_context_from_value;
+use crate::encrypt_config::{CastAs, Identifier};
+use crate::{
+    arrays, dates, empty, expiry, plaintext_target, plaintext_targets, uints, BulkEncryptItem,
+    Client, Error,
+};
+use cipherstash_client::schema::column::IndexType;
+use serde::Serialize;
+
+/// A plaintext conversion applied before encryption.
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Conversion {
+    /// The array is split into elements, each carrying its own index terms.
+    SplitArray,
+    /// The JSON document is parsed for `ste_vec` indexing.
+    ParseJson,
+    /// Values outside the `ste_vec` index's paths and limits are left out of the index.
+    FilterIndexedValues,
+    /// The date is rewritten as `YYYY-MM-DD`.
+    NormalizeDate,
+    /// The unsigned integer is rewritten without leading zeros or a sign.
+    NormalizeUnsignedInteger,
+    /// The text is indexed in a different Unicode normalization form than it was supplied in.
+    NormalizeUnicode,
+    /// Only the leading part of the text is added to the `match` index, which limits its tokens.
+    TruncateMatchInput,
+}
+
+/// What encrypting a single item would do, or why it would fail.
+#[derive(Debug, Serialize)]
+pub struct ItemPlan {
+    /// The identifier supplied with the item, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<serde_json::Value>,
+    /// The target table.
+    pub table: String,
+    /// The target column.
+    pub column: String,
+    /// The configured `cast_as` of the column, when the column is known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cast_as: Option<CastAs>,
+    /// The envelope kind that would be produced: `ct` or `sv`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<&'static str>,
+    /// The indexes that would produce terms, after the item's skip flags.
+    pub indexes: Vec<&'static str>,
+    /// The named keyset the item would be encrypted under, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keyset: Option<String>,
+    /// The number of array elements, for array columns.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elements: Option<usize>,
+    /// The conversions applied to the plaintext before encryption.
+    pub conversions: Vec<Conversion>,
+    /// The error encryption would fail with, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The plans of a bulk encryption request.
+#[derive(Debug, Serialize)]
+pub struct EncryptPlan {
+    /// Whether every item would encrypt successfully.
+    pub valid: bool,
+    /// The plan of each item, in request order.
+    pub items: Vec<ItemPlan>,
+}
+
+/// Plan the encryption of bulk items.
+pub fn plan(client: &Client, items: Vec<BulkEncryptItem>) -> EncryptPlan {
+    let items: Vec<ItemPlan> = items
+        .into_iter()
+        .map(|item| {
+            let mut plan = ItemPlan {
+                id: item.id.clone(),
+                table: item.table.to_string(),
+                column: item.column.to_string(),
+                cast_as: None,
+                kind: None,
+                indexes: Vec::new(),
+                keyset: None,
+                elements: None,
+                conversions: Vec::new(),
+                error: None,
+            };
+
+            if let Err(error) = plan_item(client, &item, &mut plan) {
+                plan.error = Some(error.to_string());
+            }
+
+            plan
+        })
+        .collect();
+
+    EncryptPlan {
+        valid: items.iter().all(|item| item.error.is_none()),
+        items,
+    }
+}
+
+/// Fill in the plan of an item, stopping at the first check the item fails.
+fn plan_item(client: &Client, item: &BulkEncryptItem, plan: &mut ItemPlan) -> Result<(), Error> {
+    if let Some(context) = client.request_context(item.context.as_ref())? {
+        encryption_context_from_value(&context)?;
+    }
+    if item.expires_at.is_some() {
+        expiry::check_version(client.options.emit_version)?;
+    }
+    client.check_plaintext_size(&item.plaintext)?;
+
+    let identifier = client.resolve(Identifier::new(item.table.as_ref(), item.column.as_ref()));
+    let (column_config, cast_as) = client.column(&identifier)?;
+    plan.cast_as = Some(*cast_as);
+
+    let keyset = client.column_keysets.get(&identifier);
+    client.cipher_for(keyset.map(String::as_str))?;
+    plan.keyset = keyset.cloned();
+
+    let column_config = item.index_config(column_config);
+    plan.kind = Some(if *cast_as == CastAs::JsonB {
+        "sv"
+    } else {
+        "ct"
+    });
+    plan.indexes = column_config
+        .indexes
+        .iter()
+        .map(|index| match index.index_type {
+            IndexType::Unique { .. } => "unique",
+            IndexType::Ore => "ore",
+            IndexType::Match { .. } => "match",
+            IndexType::SteVec { .. } => "ste_vec",
+        })
+        .collect();
+
+    let text_policy = client.text_policy(&identifier);
+    let plaintext = empty::prepare(item.plaintext.to_string(), *cast_as, text_policy)?;
+    let plaintext = plaintext.as_str();
+    match (*cast_as, cast_as.element_type()) {
+        (_, Some(element_type)) => {
+            let (_, elements) = arrays::parse(plaintext, element_type)?;
+            plan.elements = Some(elements.len());
+            plan.conversions.push(Conversion::SplitArray);
+        }
+        (CastAs::JsonB, None) if plan.indexes.contains(&"ste_vec") => {
+            plan.conversions.push(Conversion::ParseJson);
+
+            let document = plaintext_target::json_document(plaintext)?;
+            if client.ste_vec_policy(&identifier).indexed(&document)? != document {
+                plan.conversions.push(Conversion::FilterIndexedValues);
+            }
+        }
+        (CastAs::Date, None) if dates::normalize(plaintext)? != plaintext => {
+            plan.conversions.push(Conversion::NormalizeDate);
+        }
+        (CastAs::BigUInt, None) if uints::normalize(plaintext)? != plaintext => {
+            plan.conversions.push(Conversion::NormalizeUnsignedInteger);
+        }
+        (CastAs::Text, None)
+            if [
+                text_policy.normalization.unique,
+                text_policy.normalization.r#match,
+            ]
+            .into_iter()
+            .flatten()
+            .any(|form| form.apply(plaintext) != plaintext) =>
+        {
+            plan.conversions.push(Conversion::NormalizeUnicode);
+        }
+        _ => {}
+    }
+
+    if let Some(limits) = text_policy.normalization.match_limits {
+        if *cast_as == CastAs::Text && plan.indexes.contains(&"match") {
+            if limits.overflow.is_truncate() {
+                if limits.exceeds(plaintext) {
+                    plan.conversions.push(Conversion::TruncateMatchInput);
+                }
+            } else {
+                limits.check(plaintext)?;
+            }
+        }
+    }
+
+    // Integers are parsed during encryption, so check them here.
+    let valid_integer = match cast_as {
+        CastAs::SmallInt => plaintext.parse::<i16>().is_ok(),
+        CastAs::Int => plaintext.parse::<i32>().is_ok(),
+        CastAs::BigInt => plaintext.parse::<i64>().is_ok(),
+        _ => true,
+    };
+    if !valid_integer {
+        return Err(Error::InvalidInteger(*cast_as));
+    }
+
+    plaintext_targets(
+        plaintext.to_string(),
+        &column_config,
+        *cast_as,
+        text_policy,
+        client.ste_vec_policy(&identifier),
+        Vec::new(),
+    )?;
+
+    Ok(())
+}
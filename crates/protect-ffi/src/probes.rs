@@ -0,0 +1,188 @@
+//! Connectivity probes for the services a client depends on.
+//!
+//! A client needs CTS to issue the service tokens that authenticate its requests, and ZeroKMS to
+//! generate and retrieve data keys. `ping_cts` and `ping_zerokms` check each service on its own,
+//! so an authentication outage can be told apart from a data-plane problem. A probe resolves the
+//! service's host, then times a single request: a fresh token for CTS, and a keyset listing for
+//! ZeroKMS. A failed probe is reported in the result rather than as an error, so tooling can
+//! always inspect the endpoint and addresses it tried.
+
+use crate::network::{Endpoint, NetworkPolicy};
+use crate::Error;
+use cipherstash_client::credentials::{Credentials, ServiceCredentials};
+use serde::Serialize;
+use std::future::Future;
+use std::time::Instant;
+
+/// The state a client's probes need of their own.
+pub struct Probes {
+    /// Credentials used only by the CTS probe, so clearing their cached token does not affect
+    /// the client's requests.
+    pub credentials: ServiceCredentials,
+}
+
+impl Probes {
+    /// Check that CTS issues a fresh service token.
+    pub async fn cts(&self, network: &NetworkPolicy) -> Probe {
+        probe("cts", network, &network.cts, async {
+            self.credentials.clear_token().await;
+            self.credentials
+                .get_token()
+                .await
+                .map(drop)
+                .map_err(|error| Error::Credentials(error.to_string()))
+        })
+        .await
+    }
+}
+
+/// The outcome of a probe.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeStatus {
+    /// The service responded successfully.
+    Ok,
+    /// The service's host could not be resolved.
+    DnsFailure,
+    /// The service's host was resolved, but the request failed.
+    Failure,
+}
+
+/// The result of probing a service.
+#[derive(Debug, Serialize)]
+pub struct Probe {
+    /// The service probed: `cts` or `zerokms`.
+    pub service: &'static str,
+    /// The outcome of the probe.
+    pub status: ProbeStatus,
+    /// The service's base URL.
+    pub endpoint: String,
+    /// The addresses the service's host resolved to.
+    pub addresses: Vec<String>,
+    /// How long resolving the host took, in milliseconds.
+    pub resolve_ms: f64,
+    /// How long the request took, in milliseconds, or zero when the host was not resolved.
+    pub latency_ms: f64,
+    /// Why the probe failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Resolve a service's host, then time a request to it. A host the client's network policy
+/// forbids is reported as a failure without being resolved.
+pub async fn probe<F>(
+    service: &'static str,
+    network: &NetworkPolicy,
+    endpoint: &Endpoint,
+    request: F,
+) -> Probe
+where
+    F: Future<Output = Result<(), Error>>,
+{
+    let mut probe = Probe {
+        service,
+        status: ProbeStatus::Ok,
+        endpoint: endpoint.url.clone(),
+        addresses: Vec::new(),
+        resolve_ms: 0.0,
+        latency_ms: 0.0,
+        error: None,
+    };
+
+    if let Err(error) = network.check(endpoint) {
+        probe.status = ProbeStatus::Failure;
+        probe.error = Some(error.to_string());
+        return probe;
+    }
+
+    let started = Instant::now();
+    let resolved = tokio::net::lookup_host((endpoint.host.as_str(), endpoint.port)).await;
+    probe.resolve_ms = elapsed_ms(started);
+
+    match resolved {
+        Ok(addresses) => probe.addresses = addresses.map(|address| address.to_string()).collect(),
+        Err(error) => {
+            probe.status = ProbeStatus::DnsFailure;
+            probe.error = Some(format!("could not resolve `{}`: {error}", endpoint.host));
+            return probe;
+        }
+    }
+
+    let started = Instant::now();
+    let result = request.await;
+    probe.latency_ms = elapsed_ms(started);
+
+    if let Err(error) = result {
+        probe.status = ProbeStatus::Failure;
+        probe.error = Some(error.to_string());
+    }
+
+    probe
+}
+
+/// The milliseconds since an instant, with microsecond precision.
+fn elapsed_ms(started: Instant) -> f64 {
+    (started.elapsed().as_micros() as f64) / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn localhost() -> Endpoint {
+        Endpoint::new(
+            "https://localhost/".to_string(),
+            Some("localhost"),
+            Some(443),
+        )
+    }
+
+    fn network(offline: bool) -> NetworkPolicy {
+        NetworkPolicy {
+            offline,
+            cts: localhost(),
+            zerokms: localhost(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_probe_reports_resolved_addresses() {
+        let probe = probe("zerokms", &network(false), &localhost(), async { Ok(()) }).await;
+
+        assert_eq!(probe.status, ProbeStatus::Ok);
+        assert_eq!(probe.endpoint, "https://localhost/");
+        assert!(!probe.addresses.is_empty());
+        assert!(probe.error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_probe_reports_failed_requests() {
+        let probe = probe("cts", &network(false), &localhost(), async {
+            Err(Error::Runtime("connection refused".to_string()))
+        })
+        .await;
+
+        assert_eq!(probe.status, ProbeStatus::Failure);
+        assert_eq!(
+            probe.error.as_deref(),
+            Some("runtime error: connection refused")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_probe_refuses_hosts_forbidden_offline() {
+        let elsewhere = Endpoint::new(
+            "https://example.com/".to_string(),
+            Some("example.com"),
+            None,
+        );
+        let probe = probe("zerokms", &network(true), &elsewhere, async { Ok(()) }).await;
+
+        assert_eq!(probe.status, ProbeStatus::Failure);
+        assert!(probe.addresses.is_empty());
+        assert_eq!(
+            probe.error.as_deref(),
+            Some("network access to `example.com` is forbidden by the `offline` client option")
+        );
+    }
+}
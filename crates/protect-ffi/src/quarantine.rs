@@ -0,0 +1,137 @@
+//! Poisoning of freed clients.
+//!
+//! Without the `checked-pointers` feature, client pointers are not validated, so calling a
+//! function with a client that was already freed is undefined behavior. To make that mistake
+//! fail cleanly in most cases, freeing a client poisons it instead of returning its memory at
+//! once: the client's resources are released, a `poisoned` flag stored next to it is set, and
+//! the emptied allocation is kept in a quarantine of recently freed allocations. Every function
+//! that takes a client checks the flag, so a stale pointer used while its allocation is
+//! quarantined fails with [`Error::InvalidPointer`]. An allocation is only returned to the
+//! allocator once [`QUARANTINE_SIZE`] more have been freed.
+//!
+//! Freeing a client while another thread is still using it remains undefined behavior.
+
+use crate::Error;
+use std::collections::VecDeque;
+use std::mem::{offset_of, ManuallyDrop};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+/// The number of freed allocations kept poisoned before their memory is reused.
+pub const QUARANTINE_SIZE: usize = 64;
+
+/// The most recently freed allocations, oldest first.
+static QUARANTINE: Mutex<VecDeque<Quarantined>> = Mutex::new(VecDeque::new());
+
+/// A freed allocation, stored by address so the quarantine can be shared between threads.
+struct Quarantined {
+    address: usize,
+    deallocate: unsafe fn(usize),
+}
+
+/// A value with its poisoned flag.
+#[repr(C)]
+struct Poisonable<T> {
+    poisoned: AtomicBool,
+    value: ManuallyDrop<T>,
+}
+
+fn allocation<T>(value: *const T) -> *mut Poisonable<T> {
+    value
+        .cast::<u8>()
+        .wrapping_sub(offset_of!(Poisonable<T>, value))
+        .cast::<Poisonable<T>>()
+        .cast_mut()
+}
+
+/// Return the memory of an evicted allocation, whose value was already dropped.
+unsafe fn deallocate<T>(address: usize) {
+    // `ManuallyDrop` keeps the value from being dropped a second time.
+    drop(Box::from_raw(address as *mut Poisonable<T>));
+}
+
+/// Move a value into a poisonable allocation and return a pointer to the value.
+pub fn into_raw<T>(value: T) -> *mut T {
+    let allocation = Box::into_raw(Box::new(Poisonable {
+        poisoned: AtomicBool::new(false),
+        value: ManuallyDrop::new(value),
+    }));
+
+    unsafe { ptr::addr_of_mut!((*allocation).value).cast() }
+}
+
+/// Check that a value returned by [`into_raw`] has not been freed.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidPointer`] if the value was freed and is still quarantined.
+///
+/// # Safety
+///
+/// The caller must ensure the pointer is non-null and was returned by [`into_raw`].
+pub fn check<T>(value: *const T, function: &str) -> Result<(), Error> {
+    if unsafe { (*allocation(value)).poisoned.load(Ordering::Acquire) } {
+        return Err(Error::InvalidPointer(format!(
+            "`{function}` received a pointer that was already freed"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Drop a value and quarantine its allocation. Freeing a value that is still quarantined is
+/// ignored.
+///
+/// # Safety
+///
+/// The caller must ensure the pointer is non-null and was returned by [`into_raw`].
+pub fn free<T>(value: *mut T) {
+    let allocation = allocation(value);
+
+    unsafe {
+        if (*allocation).poisoned.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        ManuallyDrop::drop(&mut (*allocation).value);
+    }
+
+    let evicted = {
+        let mut quarantine = QUARANTINE.lock().unwrap_or_else(PoisonError::into_inner);
+        quarantine.push_back(Quarantined {
+            address: allocation as usize,
+            deallocate: deallocate::<T>,
+        });
+        if quarantine.len() > QUARANTINE_SIZE {
+            quarantine.pop_front()
+        } else {
+            None
+        }
+    };
+
+    if let Some(evicted) = evicted {
+        unsafe { (evicted.deallocate)(evicted.address) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_freed_value_is_poisoned() {
+        let value = into_raw("john@example.com".to_string());
+        assert!(check(value, "client_ref").is_ok());
+        assert_eq!(unsafe { &*value }, "john@example.com");
+
+        free(value);
+
+        let result = check(value, "client_ref");
+        assert!(
+            matches!(result, Err(Error::InvalidPointer(message)) if message.contains("already freed"))
+        );
+
+        // A second free of a quarantined value is ignored.
+        free(value);
+    }
+}
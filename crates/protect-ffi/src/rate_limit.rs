@@ -0,0 +1,166 @@
+//! Client-side rate limits on calls to ZeroKMS.
+//!
+//! Every encryption pipeline, decryption request, and keyset listing is a ZeroKMS call, which
+//! also refreshes the CTS token when it expires. A client with a `rate_limit` option takes a
+//! token from a bucket that refills at `requests_per_second`, up to `burst` tokens, before each
+//! call. A call that finds the bucket empty is queued until its token is available, for at most
+//! `max_wait_ms`, and fails with [`Error::RateLimited`] when it would wait longer, so a runaway
+//! job fails fast instead of exhausting a shared workspace's quota.
+
+use crate::Error;
+use serde::Deserialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The rate limit of a client's ZeroKMS calls.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimit {
+    /// The sustained number of calls allowed per second.
+    pub requests_per_second: f64,
+    /// The number of calls allowed at once after the client has been idle.
+    #[serde(default = "default_burst")]
+    pub burst: u32,
+    /// The longest a call is queued for its turn, in milliseconds.
+    #[serde(default = "default_max_wait_ms")]
+    pub max_wait_ms: u64,
+}
+
+/// Default number of calls allowed at once.
+fn default_burst() -> u32 {
+    1
+}
+
+/// Default longest queueing time of a call, in milliseconds.
+fn default_max_wait_ms() -> u64 {
+    1000
+}
+
+impl RateLimit {
+    /// Check that the rate and burst are positive.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first setting that is out of range.
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.requests_per_second.is_finite() || self.requests_per_second <= 0.0 {
+            return Err("`requests_per_second` must be a positive number".to_string());
+        }
+        if self.burst == 0 {
+            return Err("`burst` must be at least 1".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// A token bucket shared by a client and the clients derived from it.
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: RateLimit,
+    bucket: Mutex<Bucket>,
+}
+
+/// The tokens available at an instant. Tokens go negative while calls are queued.
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    /// Create a rate limiter with a full bucket.
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            bucket: Mutex::new(Bucket {
+                tokens: f64::from(limit.burst),
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// Wait for the turn of a call.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::RateLimited`] if the call would be queued for longer than
+    /// `max_wait_ms`.
+    pub async fn acquire(&self) -> Result<(), Error> {
+        let wait = self.reserve(Instant::now())?;
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+
+        Ok(())
+    }
+
+    /// Take a token at an instant, returning how long the call must wait for it.
+    fn reserve(&self, now: Instant) -> Result<Duration, Error> {
+        let mut bucket = self
+            .bucket
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.limit.requests_per_second)
+            .min(f64::from(self.limit.burst));
+        bucket.updated = now;
+
+        bucket.tokens -= 1.0;
+        if bucket.tokens >= 0.0 {
+            return Ok(Duration::ZERO);
+        }
+
+        let wait = Duration::from_secs_f64(-bucket.tokens / self.limit.requests_per_second);
+        if wait > Duration::from_millis(self.limit.max_wait_ms) {
+            bucket.tokens += 1.0;
+            return Err(Error::RateLimited {
+                wait_ms: wait.as_millis(),
+            });
+        }
+
+        Ok(wait)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit_defaults_and_validation() {
+        let limit: RateLimit = serde_json::from_str(r#"{"requests_per_second": 5}"#).unwrap();
+        assert_eq!(limit.burst, 1);
+        assert_eq!(limit.max_wait_ms, 1000);
+        assert!(limit.validate().is_ok());
+
+        let limit: RateLimit =
+            serde_json::from_str(r#"{"requests_per_second": 0, "burst": 2}"#).unwrap();
+        assert!(limit.validate().is_err());
+
+        let limit: RateLimit =
+            serde_json::from_str(r#"{"requests_per_second": 1, "burst": 0}"#).unwrap();
+        assert!(limit.validate().is_err());
+    }
+
+    #[test]
+    fn test_reserve_queues_calls_beyond_burst() {
+        let limiter = RateLimiter::new(RateLimit {
+            requests_per_second: 10.0,
+            burst: 2,
+            max_wait_ms: 150,
+        });
+        let now = Instant::now();
+
+        assert_eq!(limiter.reserve(now).unwrap(), Duration::ZERO);
+        assert_eq!(limiter.reserve(now).unwrap(), Duration::ZERO);
+        assert_eq!(limiter.reserve(now).unwrap(), Duration::from_millis(100));
+
+        let result = limiter.reserve(now);
+        assert!(matches!(result, Err(Error::RateLimited { wait_ms: 200 })));
+
+        let later = now + Duration::from_millis(100);
+        assert_eq!(limiter.reserve(later).unwrap(), Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,110 @@
+//! Server-side request IDs of failed ZeroKMS and CTS requests.
+//!
+//! ZeroKMS and CTS assign every request an ID, which CipherStash support can use to find the
+//! request in their logs. The encryption library does not expose response headers, but it
+//! includes the body of a failed response in its error, and error bodies carry the request ID
+//! as a `request_id` or `requestId` field, or in a `Request ID: ...` sentence. The ID is found in
+//! the messages of an error's chain and recorded with the error, so `get_last_error_request_id`
+//! can report it without capturing traffic.
+
+/// The names a request ID is given in error bodies, in lowercase. Where labels overlap, as
+/// `x-request-id` and `request-id` do, the one starting first is used.
+const LABELS: &[&str] = &[
+    "x-request-id",
+    "request_id",
+    "request-id",
+    "requestid",
+    "request id",
+];
+
+/// The fewest characters a request ID has, so that words such as `missing` in
+/// `request ID missing` are not mistaken for one.
+const MIN_LENGTH: usize = 8;
+
+/// The request ID reported in an error or one of its sources, if any.
+pub fn request_id(error: &dyn std::error::Error) -> Option<String> {
+    let mut current = Some(error);
+
+    while let Some(error) = current {
+        if let Some(request_id) = find(&error.to_string()) {
+            return Some(request_id);
+        }
+        current = error.source();
+    }
+
+    None
+}
+
+/// The first request ID labelled in a message.
+fn find(message: &str) -> Option<String> {
+    let lowercase = message.to_ascii_lowercase();
+
+    let mut offset = 0;
+    while offset < lowercase.len() {
+        let (start, label) = LABELS
+            .iter()
+            .filter_map(|label| {
+                lowercase[offset..]
+                    .find(label)
+                    .map(|start| (offset + start, label))
+            })
+            .min_by_key(|(start, _)| *start)?;
+
+        let value = message[start + label.len()..]
+            .trim_start_matches(|c: char| c == '"' || c == '\'' || c == ':' || c == '=')
+            .trim_start()
+            .trim_start_matches(['"', '\'']);
+        let id: String = value
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+            .collect();
+
+        if id.len() >= MIN_LENGTH {
+            return Some(id);
+        }
+        offset = start + label.len();
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn test_find() {
+        for (message, expected) in [
+            (
+                r#"Server error: {"message":"keyset not found","request_id":"01J8ZK3W5Q2V6X9T"}"#,
+                Some("01J8ZK3W5Q2V6X9T"),
+            ),
+            (
+                r#"{"error": "forbidden", "requestId": "6f1c2a9e-3b7d-4e0a-9c55-2d8f1b7a4e31"}"#,
+                Some("6f1c2a9e-3b7d-4e0a-9c55-2d8f1b7a4e31"),
+            ),
+            (
+                "Decrypt failed. Request ID: req_7Hq2LmX9. Contact support.",
+                Some("req_7Hq2LmX9"),
+            ),
+            ("x-request-id=abc123def456", Some("abc123def456")),
+            (
+                "request ID missing, request_id=0123456789",
+                Some("0123456789"),
+            ),
+            ("request ID missing", None),
+            ("connection refused", None),
+        ] {
+            assert_eq!(find(message).as_deref(), expected, "{message}");
+        }
+    }
+
+    #[test]
+    fn test_request_id_searches_the_chain() {
+        let error = Error::InvariantViolation("request_id: 0123456789ab".to_string());
+        assert_eq!(request_id(&error).as_deref(), Some("0123456789ab"));
+
+        assert_eq!(request_id(&Error::NullPointer), None);
+    }
+}
@@ -0,0 +1,177 @@
+//! Backoff hints of throttled ZeroKMS and CTS requests.
+//!
+//! A workspace that exceeds its request rate or quota receives `429 Too Many Requests` with how
+//! long to wait in `Retry-After`, and its quota in `RateLimit-Limit`, `RateLimit-Remaining`, and
+//! `RateLimit-Reset`. The encryption library does not expose response headers, but includes the
+//! response in its error, so the hints are read from the messages of an error's chain, as
+//! request IDs are by [`crate::request_ids`]. Only delays in seconds are recognized, not HTTP
+//! dates. A call refused by the client's own `rate_limit` option reports how long it would have
+//! been queued instead. `get_last_error_retry_hint` reports the hints, so jobs can back off for
+//! as long as the service asks instead of retrying at once.
+
+use crate::Error;
+use serde::Serialize;
+
+/// Labels of the delay before retrying, in lowercase, with the milliseconds in a unit of each.
+const RETRY_AFTER: &[(&str, f64)] = &[
+    ("retry-after-ms", 1.0),
+    ("retry_after_ms", 1.0),
+    ("retryafterms", 1.0),
+    ("retry-after", 1000.0),
+    ("retry_after", 1000.0),
+    ("retryafter", 1000.0),
+    ("retry after", 1000.0),
+];
+
+/// Labels of the number of requests a quota allows.
+const QUOTA_LIMIT: &[(&str, f64)] = &[
+    ("ratelimit-limit", 1.0),
+    ("quota_limit", 1.0),
+    ("quotalimit", 1.0),
+];
+
+/// Labels of the number of requests left in a quota.
+const QUOTA_REMAINING: &[(&str, f64)] = &[
+    ("ratelimit-remaining", 1.0),
+    ("quota_remaining", 1.0),
+    ("quotaremaining", 1.0),
+];
+
+/// Labels of the seconds until a quota resets.
+const QUOTA_RESET: &[(&str, f64)] = &[
+    ("ratelimit-reset", 1000.0),
+    ("quota_reset", 1000.0),
+    ("quotareset", 1000.0),
+];
+
+/// How long to wait before retrying a failed call, and the quota it exceeded.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct RetryHint {
+    /// How long to wait before retrying, in milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_ms: Option<u64>,
+    /// The quota the call exceeded, if the service reported it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quota: Option<Quota>,
+}
+
+/// A workspace's request quota, as reported by the service.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Quota {
+    /// The number of requests the quota allows in its window.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+    /// The number of requests left in the current window.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining: Option<u64>,
+    /// How long until the quota resets, in milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reset_ms: Option<u64>,
+}
+
+/// The backoff hints of an error, if it or one of its sources reports any.
+pub fn retry_hint(error: &Error) -> Option<RetryHint> {
+    if let Error::RateLimited { wait_ms } = error {
+        return Some(RetryHint {
+            retry_after_ms: Some(u64::try_from(*wait_ms).unwrap_or(u64::MAX)),
+            quota: None,
+        });
+    }
+
+    let mut hint = RetryHint::default();
+    let mut quota = Quota::default();
+    let mut current: Option<&dyn std::error::Error> = Some(error);
+
+    while let Some(error) = current {
+        let message = error.to_string();
+        hint.retry_after_ms = hint.retry_after_ms.or_else(|| find(&message, RETRY_AFTER));
+        quota.limit = quota.limit.or_else(|| find(&message, QUOTA_LIMIT));
+        quota.remaining = quota.remaining.or_else(|| find(&message, QUOTA_REMAINING));
+        quota.reset_ms = quota.reset_ms.or_else(|| find(&message, QUOTA_RESET));
+        current = error.source();
+    }
+
+    if quota != Quota::default() {
+        hint.quota = Some(quota);
+    }
+    (hint != RetryHint::default()).then_some(hint)
+}
+
+/// The first number in a message with one of the labels, in the label's unit. Where labels
+/// overlap, as `retry-after-ms` and `retry-after` do, the longest is used.
+fn find(message: &str, labels: &[(&str, f64)]) -> Option<u64> {
+    let lowercase = message.to_ascii_lowercase();
+
+    let mut offset = 0;
+    while offset < lowercase.len() {
+        let (start, label, scale) = labels
+            .iter()
+            .filter_map(|(label, scale)| {
+                lowercase[offset..]
+                    .find(label)
+                    .map(|start| (offset + start, *label, *scale))
+            })
+            .min_by_key(|(start, label, _)| (*start, std::cmp::Reverse(label.len())))?;
+
+        let value = lowercase[start + label.len()..]
+            .trim_start_matches(|c: char| c == '"' || c == '\'' || c == ':' || c == '=')
+            .trim_start();
+        let number: String = value
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || *c == '.')
+            .collect();
+
+        if let Ok(number) = number.parse::<f64>() {
+            return Some((number * scale).round() as u64);
+        }
+        offset = start + label.len();
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        for (message, expected) in [
+            ("429 Too Many Requests, Retry-After: 30", Some(30_000)),
+            (r#"{"error":"throttled","retry_after":1.5}"#, Some(1_500)),
+            (r#"{"retryAfterMs": 250}"#, Some(250)),
+            ("quota exceeded, please retry after 2 seconds", Some(2_000)),
+            ("retry after a while, retry-after=4", Some(4_000)),
+            ("connection refused", None),
+        ] {
+            assert_eq!(find(message, RETRY_AFTER), expected, "{message}");
+        }
+    }
+
+    #[test]
+    fn test_retry_hint() {
+        let error = Error::InvariantViolation(
+            "429 Too Many Requests: x-ratelimit-limit: 1000, x-ratelimit-remaining: 0, \
+             x-ratelimit-reset: 12, retry-after: 12"
+                .to_string(),
+        );
+        let hint = retry_hint(&error).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&hint).unwrap(),
+            serde_json::json!({
+                "retry_after_ms": 12000,
+                "quota": {"limit": 1000, "remaining": 0, "reset_ms": 12000}
+            })
+        );
+
+        assert_eq!(
+            retry_hint(&Error::RateLimited { wait_ms: 200 }),
+            Some(RetryHint {
+                retry_after_ms: Some(200),
+                quota: None
+            })
+        );
+        assert_eq!(retry_hint(&Error::NullPointer), None);
+    }
+}
@@ -1,25 +1,113 @@
 //! Safe FFI utility functions for pointer validation and C string conversion.
 
-use crate::{Client, Error};
-use libc::c_char;
-use std::ffi::{CStr, CString};
+use crate::retry_hints::{self, RetryHint};
+use crate::{crash_report, jobs::Job, request_ids, Client, Error};
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
 use std::ptr;
+use std::sync::RwLock;
+
+/// Callback invoked with a description of an invalid pointer passed to a free function.
+pub type InvalidPointerHandler = extern "C" fn(message: *const c_char);
+
+static INVALID_POINTER_HANDLER: RwLock<Option<InvalidPointerHandler>> = RwLock::new(None);
+
+thread_local! {
+    /// The most recent failed FFI call's error on this thread.
+    static LAST_ERROR: RefCell<Option<LastError>> = const { RefCell::new(None) };
+}
+
+/// An error recorded by a failed FFI call.
+struct LastError {
+    /// The error's code in the error catalog.
+    code: &'static str,
+    /// The messages of the error and its sources, outermost first.
+    chain: Vec<String>,
+    /// The server-side ID of the request that failed, if the error reports one.
+    request_id: Option<String>,
+    /// How long to back off before retrying, if the error reports it.
+    retry_hint: Option<RetryHint>,
+}
+
+/// Separator between messages when an error chain is rendered as a single message.
+const CHAIN_SEPARATOR: &str = " → ";
+
+/// Run the body of an exported function, returning a panic as [`Error::Panic`] instead of
+/// unwinding into the caller, which aborts the process.
+///
+/// The operation is recorded for the crash report written by the panic hook, if enabled.
+pub fn catch_panic<T>(
+    operation: &'static str,
+    body: impl FnOnce() -> Result<T, Error>,
+) -> Result<T, Error> {
+    let _operation = crash_report::enter(operation);
+
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|_| Err(Error::Panic(operation)))
+}
 
 /// Safely convert a raw client pointer to a reference.
 ///
 /// # Errors
 ///
-/// Returns [`Error::NullPointer`] if the provided pointer is null.
+/// Returns [`Error::NullPointer`] if the provided pointer is null, and [`Error::InvalidPointer`]
+/// if the client was freed and is still quarantined. With the `checked-pointers` feature, also
+/// returns [`Error::InvalidPointer`] if the pointer was not returned by [`client_into_raw`] or
+/// has been freed.
 ///
 /// # Safety
 ///
 /// The caller must ensure the pointer is valid and properly aligned.
 pub fn client_ref<'a>(client: *const Client) -> Result<&'a Client, Error> {
     if client.is_null() {
-        Err(Error::NullPointer)
-    } else {
-        unsafe { Ok(&*client) }
+        return Err(Error::NullPointer);
+    }
+
+    #[cfg(feature = "checked-pointers")]
+    match crate::checked::validate_client(client) {
+        crate::checked::Validity::Live => {}
+        invalid => return Err(Error::InvalidPointer(invalid.describe("client_ref"))),
+    }
+
+    #[cfg(not(feature = "checked-pointers"))]
+    crate::quarantine::check(client, "client_ref")?;
+
+    unsafe { Ok(&*client) }
+}
+
+/// Safely convert a raw job pointer to a reference.
+///
+/// # Errors
+///
+/// Returns [`Error::NullPointer`] if the provided pointer is null.
+///
+/// # Safety
+///
+/// The caller must ensure the pointer was returned by [`job_into_raw`] and hasn't been freed.
+pub fn job_ref<'a>(job: *const Job) -> Result<&'a Job, Error> {
+    if job.is_null() {
+        return Err(Error::NullPointer);
+    }
+
+    unsafe { Ok(&*job) }
+}
+
+/// Safely convert a raw job pointer to a mutable reference.
+///
+/// # Errors
+///
+/// Returns [`Error::NullPointer`] if the provided pointer is null.
+///
+/// # Safety
+///
+/// The caller must ensure the pointer was returned by [`job_into_raw`], hasn't been freed, and
+/// isn't used from another thread at the same time.
+pub fn job_mut<'a>(job: *mut Job) -> Result<&'a mut Job, Error> {
+    if job.is_null() {
+        return Err(Error::NullPointer);
     }
+
+    unsafe { Ok(&mut *job) }
 }
 
 /// Safely convert a raw C string to a Rust [`String`].
@@ -33,13 +121,26 @@ pub fn client_ref<'a>(client: *const Client) -> Result<&'a Client, Error> {
 ///
 /// The caller must ensure the pointer points to a valid null-terminated C string.
 pub fn c_str_to_string(c_str_ptr: *const c_char) -> Result<String, Error> {
+    unsafe { c_str_to_str(c_str_ptr) }.map(str::to_owned)
+}
+
+/// Borrow a raw C string as a Rust [`str`], without copying it.
+///
+/// # Errors
+///
+/// Returns [`Error::NullPointer`] if the provided pointer is null, or
+/// [`Error::Utf8`] if the C string contains invalid UTF-8.
+///
+/// # Safety
+///
+/// The pointer must be null or point to a valid null-terminated C string. The caller chooses
+/// the lifetime `'a`, and must ensure the string is not modified or freed during it, which for
+/// an argument of an exported function means not keeping the reference after the call returns.
+pub unsafe fn c_str_to_str<'a>(c_str_ptr: *const c_char) -> Result<&'a str, Error> {
     if c_str_ptr.is_null() {
         Err(Error::NullPointer)
     } else {
-        unsafe {
-            let c_str = CStr::from_ptr(c_str_ptr);
-            Ok(c_str.to_str()?.to_owned())
-        }
+        unsafe { Ok(CStr::from_ptr(c_str_ptr).to_str()?) }
     }
 }
 
@@ -70,20 +171,78 @@ pub fn optional_c_str_to_string(c_str_ptr: *const c_char) -> Result<Option<Strin
 /// Returns [`Error::StringConversion`] if the string contains null bytes.
 pub fn string_to_c_string(string: String) -> Result<*mut c_char, Error> {
     CString::new(string)
-        .map(|cs| cs.into_raw())
+        .map(c_string_into_raw)
         .map_err(|e| Error::StringConversion(e.to_string()))
 }
 
+/// Transfer ownership of a C string to the caller.
+///
+/// The returned pointer must be freed with [`free_c_string`].
+fn c_string_into_raw(c_string: CString) -> *mut c_char {
+    #[cfg(feature = "checked-pointers")]
+    {
+        crate::checked::string_into_raw(c_string)
+    }
+
+    #[cfg(not(feature = "checked-pointers"))]
+    {
+        c_string.into_raw()
+    }
+}
+
+/// Transfer ownership of a client to the caller.
+///
+/// The returned pointer must be freed with [`free_boxed_client`].
+pub fn client_into_raw(client: Client) -> *mut Client {
+    #[cfg(feature = "checked-pointers")]
+    {
+        crate::checked::client_into_raw(client)
+    }
+
+    #[cfg(not(feature = "checked-pointers"))]
+    {
+        crate::quarantine::into_raw(client)
+    }
+}
+
 /// Safely free a boxed client pointer.
 ///
 /// # Safety
 ///
-/// The caller must ensure the pointer was created by [`Box::into_raw`] and hasn't been freed.
+/// The caller must ensure the pointer was created by [`client_into_raw`] and hasn't been freed.
+/// A client freed again while it is quarantined is ignored. With the `checked-pointers`
+/// feature, violations are reported through the invalid pointer handler instead.
 pub fn free_boxed_client(client: *mut Client) {
-    if !client.is_null() {
-        unsafe {
-            drop(Box::from_raw(client));
-        }
+    if client.is_null() {
+        return;
+    }
+
+    #[cfg(feature = "checked-pointers")]
+    crate::checked::free_client(client);
+
+    #[cfg(not(feature = "checked-pointers"))]
+    crate::quarantine::free(client);
+}
+
+/// Transfer ownership of a job to the caller.
+///
+/// The returned pointer must be freed with [`free_boxed_job`].
+pub fn job_into_raw(job: Job) -> *mut Job {
+    Box::into_raw(Box::new(job))
+}
+
+/// Safely free a boxed job pointer.
+///
+/// # Safety
+///
+/// The caller must ensure the pointer was created by [`job_into_raw`] and hasn't been freed.
+pub fn free_boxed_job(job: *mut Job) {
+    if job.is_null() {
+        return;
+    }
+
+    unsafe {
+        drop(Box::from_raw(job));
     }
 }
 
@@ -91,12 +250,42 @@ pub fn free_boxed_client(client: *mut Client) {
 ///
 /// # Safety
 ///
-/// The caller must ensure the pointer was created by [`CString::into_raw`] and hasn't been freed.
+/// The caller must ensure the pointer was created by [`string_to_c_string`] and hasn't been
+/// freed. With the `checked-pointers` feature, violations are reported through the invalid
+/// pointer handler instead.
 pub fn free_c_string(c_string_ptr: *mut c_char) {
-    if !c_string_ptr.is_null() {
-        unsafe {
-            drop(CString::from_raw(c_string_ptr));
-        }
+    if c_string_ptr.is_null() {
+        return;
+    }
+
+    #[cfg(feature = "checked-pointers")]
+    crate::checked::free_string(c_string_ptr);
+
+    #[cfg(not(feature = "checked-pointers"))]
+    unsafe {
+        drop(CString::from_raw(c_string_ptr));
+    }
+}
+
+/// Set the callback invoked when an invalid pointer is passed to a free function.
+///
+/// Passing `None` restores the default of writing the report to standard error.
+pub fn set_invalid_pointer_handler(handler: Option<InvalidPointerHandler>) {
+    let mut current = INVALID_POINTER_HANDLER
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    *current = handler;
+}
+
+/// Report an invalid pointer through the registered handler, or to standard error if none is set.
+pub fn report_invalid_pointer(message: &str) {
+    let handler = *INVALID_POINTER_HANDLER
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    match (handler, CString::new(message)) {
+        (Some(handler), Ok(message)) => handler(message.as_ptr()),
+        _ => eprintln!("protect-ffi: {message}"),
     }
 }
 
@@ -107,15 +296,87 @@ pub fn free_c_string(c_string_ptr: *mut c_char) {
 /// The caller must ensure `error_out` points to a valid mutable pointer.
 pub fn set_error(error_out: *mut *mut c_char, error: &Error) {
     if !error_out.is_null() {
-        let error_msg = format!("{}", error);
+        let error_msg = error_chain(error).join(CHAIN_SEPARATOR);
         if let Ok(c_error) = CString::new(error_msg) {
             unsafe {
-                *error_out = c_error.into_raw();
+                *error_out = c_string_into_raw(c_error);
             }
         }
     }
 }
 
+/// Record an error as the most recent error on the current thread.
+pub fn set_last_error(error: &Error) {
+    let last = LastError {
+        code: error.catalog_entry().code,
+        chain: error_chain(error),
+        request_id: request_ids::request_id(error),
+        retry_hint: retry_hints::retry_hint(error),
+    };
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(last));
+}
+
+/// Get the most recent error recorded on the current thread, if any.
+pub fn last_error() -> Option<String> {
+    last_error_chain().map(|chain| chain.join(CHAIN_SEPARATOR))
+}
+
+/// Get the message chain of the most recent error recorded on the current thread, if any.
+pub fn last_error_chain() -> Option<Vec<String>> {
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ref().map(|last| last.chain.clone()))
+}
+
+/// Get the catalog code of the most recent error recorded on the current thread, if any.
+pub fn last_error_code() -> Option<&'static str> {
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ref().map(|last| last.code))
+}
+
+/// Get the server-side request ID of the most recent error recorded on the current thread, if
+/// it reports one.
+pub fn last_error_request_id() -> Option<String> {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .and_then(|last| last.request_id.clone())
+    })
+}
+
+/// Get the backoff hints of the most recent error recorded on the current thread, if it
+/// reports any.
+pub fn last_error_retry_hint() -> Option<RetryHint> {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .and_then(|last| last.retry_hint.clone())
+    })
+}
+
+/// Collect the messages of an error and its sources, outermost first.
+///
+/// Sources whose message is already included in an earlier message are skipped, since many
+/// errors embed their source in their own message.
+pub fn error_chain(error: &dyn std::error::Error) -> Vec<String> {
+    let mut chain = vec![error.to_string()];
+    let mut source = error.source();
+
+    while let Some(cause) = source {
+        let message = cause.to_string();
+        if !chain.iter().any(|earlier| earlier.contains(&message)) {
+            chain.push(message);
+        }
+        source = cause.source();
+    }
+
+    chain
+}
+
+/// Clear the most recent error recorded on the current thread.
+pub fn clear_last_error() {
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = None);
+}
+
 /// Clear the error output pointer.
 ///
 /// # Safety
@@ -131,18 +392,21 @@ pub fn clear_error(error_out: *mut *mut c_char) {
 
 /// Macro for handling FFI results with proper error handling.
 ///
-/// On success, clears the error output and applies the success transformation.
-/// On error, sets the error message and returns a null pointer.
+/// On success, clears the error output and the thread's last error, then applies the success
+/// transformation. On error, sets the error message and the thread's last error, and returns a
+/// null pointer.
 #[macro_export]
 macro_rules! handle_ffi_result {
     ($result:expr, $error_out:expr, $success_transform:expr) => {
         match $result {
             Ok(success_value) => {
                 $crate::safe_ffi::clear_error($error_out);
+                $crate::safe_ffi::clear_last_error();
                 $success_transform(success_value)
             }
             Err(error) => {
                 $crate::safe_ffi::set_error($error_out, &error);
+                $crate::safe_ffi::set_last_error(&error);
                 ptr::null_mut()
             }
         }
@@ -155,6 +419,14 @@ mod tests {
     use std::ffi::CString;
     use std::ptr;
 
+    #[test]
+    fn test_catch_panic() {
+        let result: Result<(), Error> = catch_panic("encrypt", || panic!("unexpected"));
+        assert!(matches!(result, Err(Error::Panic("encrypt"))));
+
+        assert_eq!(catch_panic("encrypt", || Ok(1)).unwrap(), 1);
+    }
+
     #[test]
     fn test_client_ref_null_pointer() {
         let result = client_ref(ptr::null());
@@ -236,8 +508,7 @@ mod tests {
 
     #[test]
     fn test_free_c_string_valid() {
-        let metadata = CString::new("metadata").unwrap();
-        let metadata_ptr = metadata.into_raw();
+        let metadata_ptr = string_to_c_string("metadata".to_string()).unwrap();
 
         free_c_string(metadata_ptr);
     }
@@ -263,6 +534,73 @@ mod tests {
         free_c_string(error_ptr);
     }
 
+    #[test]
+    fn test_report_invalid_pointer_without_handler() {
+        report_invalid_pointer("`free_string` received a pointer that was already freed");
+    }
+
+    #[test]
+    fn test_last_error_is_thread_local() {
+        set_last_error(&Error::NullPointer);
+
+        let other_thread = std::thread::spawn(last_error).join().unwrap();
+
+        assert_eq!(other_thread, None);
+        assert_eq!(last_error(), Some(Error::NullPointer.to_string()));
+
+        assert_eq!(last_error_code(), Some("null_pointer"));
+        assert_eq!(last_error_request_id(), None);
+
+        set_last_error(&Error::InvariantViolation(
+            "request_id: 0123456789ab".to_string(),
+        ));
+        assert_eq!(last_error_request_id().as_deref(), Some("0123456789ab"));
+        assert_eq!(last_error_retry_hint(), None);
+
+        set_last_error(&Error::RateLimited { wait_ms: 200 });
+        assert_eq!(
+            last_error_retry_hint().and_then(|hint| hint.retry_after_ms),
+            Some(200)
+        );
+
+        clear_last_error();
+        assert_eq!(last_error(), None);
+        assert_eq!(last_error_code(), None);
+        assert_eq!(last_error_request_id(), None);
+        assert_eq!(last_error_retry_hint(), None);
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    enum ChainedError {
+        #[error("config error")]
+        Config(#[source] Box<ChainedError>),
+        #[error("missing env var {0}")]
+        MissingVar(&'static str),
+        #[error("invalid config: {0}")]
+        Embedded(#[source] Box<ChainedError>),
+    }
+
+    #[test]
+    fn test_error_chain_includes_sources() {
+        let error = ChainedError::Config(Box::new(ChainedError::MissingVar("CS_WORKSPACE_ID")));
+
+        assert_eq!(
+            error_chain(&error),
+            ["config error", "missing env var CS_WORKSPACE_ID"]
+        );
+        assert_eq!(error_chain(&Error::NullPointer), ["null pointer provided"]);
+    }
+
+    #[test]
+    fn test_error_chain_skips_embedded_sources() {
+        let error = ChainedError::Embedded(Box::new(ChainedError::MissingVar("CS_WORKSPACE_ID")));
+
+        assert_eq!(
+            error_chain(&error),
+            ["invalid config: missing env var CS_WORKSPACE_ID"]
+        );
+    }
+
     #[test]
     fn test_clear_error_null_pointer() {
         clear_error(ptr::null_mut());
@@ -285,11 +623,12 @@ mod tests {
 
         let result: Result<String, Error> = Ok("9jqo^BlbD-BleB1djH3bb1ULW4j$".to_string());
         let output = handle_ffi_result!(result, error_out, |ciphertext| {
-            CString::new(ciphertext).unwrap().into_raw()
+            string_to_c_string(ciphertext).unwrap()
         });
 
         assert!(!output.is_null());
         assert!(error_ptr.is_null());
+        assert_eq!(last_error(), None);
 
         free_c_string(output);
     }
@@ -301,11 +640,12 @@ mod tests {
 
         let result: Result<String, Error> = Err(Error::NullPointer);
         let output = handle_ffi_result!(result, error_out, |plaintext| {
-            CString::new(plaintext).unwrap().into_raw()
+            string_to_c_string(plaintext).unwrap()
         });
 
         assert!(output.is_null());
         assert!(!error_ptr.is_null());
+        assert_eq!(last_error(), Some(Error::NullPointer.to_string()));
 
         let error_c_str = unsafe { CStr::from_ptr(error_ptr) };
         assert!(error_c_str.to_str().is_ok());
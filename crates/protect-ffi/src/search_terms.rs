@@ -0,0 +1,675 @@
+//! Search term responses for `create_search_terms`.
+
+use crate::encrypt_config::Identifier;
+use crate::envelope::CURRENT_VERSION;
+use crate::mysql::MysqlSearchTerm;
+use crate::{Encrypted, Error, SteVecEntry};
+use cipherstash_client::schema::{column::IndexType, ColumnConfig};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Output options for [`crate::create_search_terms_with_options`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchTermOptions {
+    /// Whether to include the data type of the searched value as `dt` in each term.
+    pub include_data_type: bool,
+    /// Whether to include each item's requested `operator` as `op` in each term.
+    pub include_operator: bool,
+    /// Shape of each search term in the response.
+    pub format: SearchTermFormat,
+}
+
+/// Shape of each search term in a `create_search_terms` response.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchTermFormat {
+    /// Search term objects with the encryption indexes.
+    #[default]
+    Terms,
+    /// `eql_v2_encrypted` composite literals that can be bound directly as query parameters.
+    EqlV2,
+    /// Index terms as strings to bind against the storage columns of the `mysql` output profile.
+    Mysql,
+}
+
+/// Query operator a search term is created for.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+pub enum Operator {
+    /// Equality.
+    #[serde(rename = "=", alias = "eq")]
+    Eq,
+    /// Inequality.
+    #[serde(rename = "<>")]
+    NotEq,
+    /// Less than.
+    #[serde(rename = "<", alias = "lt")]
+    Lt,
+    /// Less than or equal to.
+    #[serde(rename = "<=", alias = "lte")]
+    Lte,
+    /// Greater than.
+    #[serde(rename = ">", alias = "gt")]
+    Gt,
+    /// Greater than or equal to.
+    #[serde(rename = ">=", alias = "gte")]
+    Gte,
+    /// Full-text match, as used by `LIKE`.
+    #[serde(rename = "~~", alias = "like", alias = "LIKE")]
+    Like,
+    /// Case-insensitive full-text match, as used by `ILIKE`.
+    #[serde(rename = "~~*", alias = "ilike", alias = "ILIKE")]
+    ILike,
+    /// JSONB containment.
+    #[serde(rename = "@>")]
+    Contains,
+    /// JSONB contained by.
+    #[serde(rename = "<@")]
+    ContainedBy,
+}
+
+impl Operator {
+    /// The SQL spelling of the operator.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Eq => "=",
+            Self::NotEq => "<>",
+            Self::Lt => "<",
+            Self::Lte => "<=",
+            Self::Gt => ">",
+            Self::Gte => ">=",
+            Self::Like => "~~",
+            Self::ILike => "~~*",
+            Self::Contains => "@>",
+            Self::ContainedBy => "<@",
+        }
+    }
+
+    /// The indexes that support this operator, in the order EQL prefers them, with a
+    /// description of the requirement for error messages.
+    fn supporting_indexes(self) -> (&'static [SupportingIndex], &'static str) {
+        match self {
+            Self::Eq | Self::NotEq => (&[UNIQUE, ORE], "a `unique` or `ore` index"),
+            Self::Lt | Self::Lte | Self::Gt | Self::Gte => (&[ORE], "an `ore` index"),
+            Self::Like | Self::ILike => (&[MATCH], "a `match` index"),
+            Self::Contains | Self::ContainedBy => (&[STE_VEC], "a `ste_vec` index"),
+        }
+    }
+
+    /// Whether the column has an index that supports this operator, and which index and term a
+    /// query with the operator uses.
+    pub fn capability(self, column_config: &ColumnConfig) -> QueryCapability {
+        let (indexes, required) = self.supporting_indexes();
+
+        match indexes.iter().find(|index| index.configured(column_config)) {
+            Some(index) => QueryCapability::supported(self, index),
+            None => QueryCapability::unsupported(
+                self,
+                format!(
+                    "operator `{}` requires {required} on the column",
+                    self.as_str()
+                ),
+                indexes,
+            ),
+        }
+    }
+
+    /// Whether the column supports this operator on a field inside its documents, and which
+    /// index and term a query with the operator uses.
+    pub fn path_capability(self, column_config: &ColumnConfig) -> QueryCapability {
+        if let Err(Error::InvalidSearchTerm(reason)) = self.validate_path() {
+            return QueryCapability::unsupported(self, reason, &[]);
+        }
+
+        if STE_VEC.configured(column_config) {
+            QueryCapability::supported(self, &STE_VEC)
+        } else {
+            QueryCapability::unsupported(
+                self,
+                "a `path` requires a `ste_vec` index on the column".to_string(),
+                &[STE_VEC],
+            )
+        }
+    }
+
+    /// Check that the column has an index that supports this operator.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidSearchTerm`] naming the indexes the operator requires.
+    pub fn validate(self, column_config: &ColumnConfig) -> Result<(), Error> {
+        match self.capability(column_config).reason {
+            Some(reason) => Err(Error::InvalidSearchTerm(reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// Check that this operator can compare a field inside a document.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidSearchTerm`] for operators other than equality and ordering.
+    pub fn validate_path(self) -> Result<(), Error> {
+        match self {
+            Self::Eq | Self::NotEq | Self::Lt | Self::Lte | Self::Gt | Self::Gte => Ok(()),
+            _ => Err(Error::InvalidSearchTerm(format!(
+                "operator `{}` cannot be used with a `path`, which compares a single field",
+                self.as_str()
+            ))),
+        }
+    }
+}
+
+impl FromStr for Operator {
+    type Err = Error;
+
+    fn from_str(operator: &str) -> Result<Self, Self::Err> {
+        serde_json::from_value(serde_json::Value::String(operator.to_string()))
+            .map_err(|_| Error::InvalidSearchTerm(format!("unknown operator `{operator}`")))
+    }
+}
+
+/// An index that supports query operators, with the search term field compared by queries.
+#[derive(Debug)]
+struct SupportingIndex {
+    name: &'static str,
+    term: &'static str,
+    matches: fn(&IndexType) -> bool,
+}
+
+impl SupportingIndex {
+    /// Whether the column has this index.
+    fn configured(&self, column_config: &ColumnConfig) -> bool {
+        column_config
+            .indexes
+            .iter()
+            .any(|index| (self.matches)(&index.index_type))
+    }
+}
+
+const UNIQUE: SupportingIndex = SupportingIndex {
+    name: "unique",
+    term: "hm",
+    matches: |index| matches!(index, IndexType::Unique { .. }),
+};
+const ORE: SupportingIndex = SupportingIndex {
+    name: "ore",
+    term: "ob",
+    matches: |index| matches!(index, IndexType::Ore),
+};
+const MATCH: SupportingIndex = SupportingIndex {
+    name: "match",
+    term: "bf",
+    matches: |index| matches!(index, IndexType::Match { .. }),
+};
+const STE_VEC: SupportingIndex = SupportingIndex {
+    name: "ste_vec",
+    term: "sv",
+    matches: |index| matches!(index, IndexType::SteVec { .. }),
+};
+
+/// Whether a column supports a query operator, returned by `explain_query_capability`.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct QueryCapability {
+    /// The operator, in its SQL spelling.
+    pub operator: Operator,
+    /// Whether the column's indexes support the operator.
+    pub supported: bool,
+    /// The index a query with the operator uses, when supported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<&'static str>,
+    /// The search term field the query compares, when supported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub term: Option<&'static str>,
+    /// Why the operator is not supported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// The indexes, any of which would support the operator, when it is not supported.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub requires: Vec<&'static str>,
+}
+
+impl QueryCapability {
+    fn supported(operator: Operator, index: &SupportingIndex) -> Self {
+        Self {
+            operator,
+            supported: true,
+            index: Some(index.name),
+            term: Some(index.term),
+            reason: None,
+            requires: Vec::new(),
+        }
+    }
+
+    fn unsupported(operator: Operator, reason: String, indexes: &[SupportingIndex]) -> Self {
+        Self {
+            operator,
+            supported: false,
+            index: None,
+            term: None,
+            reason: Some(reason),
+            requires: indexes.iter().map(|index| index.name).collect(),
+        }
+    }
+}
+
+/// Encryption indexes for a single searched value, without the ciphertext.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum SearchTerm {
+    /// Terms for `unique`, `ore`, and `match` indexes.
+    Indexes {
+        /// HMAC index for exact equality queries.
+        hm: Option<String>,
+        /// Order-revealing encryption index for range queries.
+        ob: Option<Vec<String>>,
+        /// Bloom filter index for full-text search queries.
+        bf: Option<Vec<u16>>,
+        /// Data type of the searched value, when requested.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        dt: Option<String>,
+        /// Requested query operator, when requested.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        op: Option<Operator>,
+        /// Table and column identifier.
+        i: Identifier,
+    },
+    /// Structured text encryption vector for JSONB containment queries.
+    SteVec {
+        /// Structured text encryption vector entries.
+        sv: Option<Vec<SteVecEntry>>,
+        /// Data type of the searched value, when requested.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        dt: Option<String>,
+        /// Requested query operator, when requested.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        op: Option<Operator>,
+        /// Table and column identifier.
+        i: Identifier,
+    },
+}
+
+impl SearchTerm {
+    /// Build a search term from an encrypted value, discarding its ciphertext.
+    pub fn new(
+        encrypted: Encrypted,
+        operator: Option<Operator>,
+        options: &SearchTermOptions,
+    ) -> Self {
+        let op = operator.filter(|_| options.include_operator);
+
+        match encrypted {
+            Encrypted::Ciphertext {
+                unique_index,
+                ore_index,
+                match_index,
+                data_type,
+                identifier,
+                ..
+            } => Self::Indexes {
+                hm: unique_index,
+                ob: ore_index,
+                bf: match_index,
+                dt: options.include_data_type.then_some(data_type),
+                op,
+                i: identifier,
+            },
+            Encrypted::SteVec {
+                ste_vec_index,
+                data_type,
+                identifier,
+                ..
+            } => Self::SteVec {
+                sv: ste_vec_index,
+                dt: options.include_data_type.then_some(data_type),
+                op,
+                i: identifier,
+            },
+        }
+    }
+}
+
+/// A search term waiting for its value to be encrypted along with the other values of its
+/// request.
+#[derive(Debug)]
+pub struct PendingSearchTerm {
+    /// The encoded selector of the field the term compares, for items with a `path`.
+    pub selector: Option<String>,
+    /// The operator the item requested.
+    pub operator: Option<Operator>,
+    /// The value from the client's search term cache, when it was cached.
+    pub cached: Option<Encrypted>,
+}
+
+impl PendingSearchTerm {
+    /// Build the search term from its encrypted value.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidSearchTerm`] if the term compares a field the value has no
+    /// `ste_vec` entry for.
+    pub fn finish(
+        self,
+        encrypted: Encrypted,
+        options: &SearchTermOptions,
+    ) -> Result<SearchTerm, Error> {
+        let search_term = SearchTerm::new(encrypted, self.operator, options);
+
+        match self.selector {
+            Some(selector) => search_term.select_entry(&selector),
+            None => Ok(search_term),
+        }
+    }
+}
+
+impl SearchTerm {
+    /// Keep only the `ste_vec` entry with a selector, for comparisons on a field inside a
+    /// document.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidSearchTerm`] if the search term has no entry with the selector.
+    pub fn select_entry(self, selector: &str) -> Result<Self, Error> {
+        match self {
+            Self::SteVec {
+                sv: Some(entries),
+                dt,
+                op,
+                i,
+            } => {
+                let entry = entries
+                    .into_iter()
+                    .find(|entry| entry.tokenized_selector == selector)
+                    .ok_or_else(|| {
+                        Error::InvalidSearchTerm(
+                            "the `ste_vec` index produced no entry for the path".to_string(),
+                        )
+                    })?;
+
+                Ok(Self::SteVec {
+                    sv: Some(vec![entry]),
+                    dt,
+                    op,
+                    i,
+                })
+            }
+            _ => Err(Error::InvalidSearchTerm(
+                "a `path` requires a `ste_vec` index on the column".to_string(),
+            )),
+        }
+    }
+
+    /// Render the search term as an `eql_v2_encrypted` composite literal.
+    ///
+    /// EQL compares encrypted columns against `eql_v2_encrypted` values, whose payload must
+    /// carry the schema version. The payload is quoted as the single field of the composite, so
+    /// a literal such as `("{""hm"":""f3ca..."",...,""v"":2}")` can be bound as `$1` without any
+    /// further processing.
+    pub fn to_eql_v2_literal(&self) -> Result<String, Error> {
+        let payload = serde_json::to_string(&EqlV2Payload {
+            term: self,
+            version: CURRENT_VERSION,
+        })?;
+        let quoted = payload.replace('\\', "\\\\").replace('"', "\"\"");
+
+        Ok(format!("(\"{quoted}\")"))
+    }
+}
+
+/// Payload of an `eql_v2_encrypted` query value.
+#[derive(Serialize)]
+struct EqlV2Payload<'a> {
+    #[serde(flatten)]
+    term: &'a SearchTerm,
+    #[serde(rename = "v")]
+    version: u16,
+}
+
+/// A search term rendered in the requested [`SearchTermFormat`].
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum FormattedTerm {
+    /// Search term object.
+    Term(SearchTerm),
+    /// `eql_v2_encrypted` composite literal.
+    EqlV2(String),
+    /// Index terms for MySQL storage columns.
+    Mysql(MysqlSearchTerm),
+}
+
+/// Response entry for a single search term request item.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum SearchTermResult<T = SearchTerm> {
+    /// Search term for a single plaintext.
+    Term(T),
+    /// Search terms for the inclusive bounds of a range query.
+    Range {
+        /// Lower bound search term.
+        from: T,
+        /// Upper bound search term.
+        to: T,
+    },
+}
+
+impl SearchTermResult {
+    /// Render the search terms in the requested response format.
+    pub fn into_format(
+        self,
+        format: SearchTermFormat,
+    ) -> Result<SearchTermResult<FormattedTerm>, Error> {
+        let render = |term: SearchTerm| -> Result<FormattedTerm, Error> {
+            match format {
+                SearchTermFormat::Terms => Ok(FormattedTerm::Term(term)),
+                SearchTermFormat::EqlV2 => Ok(FormattedTerm::EqlV2(term.to_eql_v2_literal()?)),
+                SearchTermFormat::Mysql => Ok(FormattedTerm::Mysql(MysqlSearchTerm::new(term)?)),
+            }
+        };
+
+        Ok(match self {
+            Self::Term(term) => SearchTermResult::Term(render(term)?),
+            Self::Range { from, to } => SearchTermResult::Range {
+                from: render(from)?,
+                to: render(to)?,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cipherstash_client::schema::{column::Index, ColumnType};
+    use serde_json::json;
+
+    fn encrypted_ciphertext() -> Encrypted {
+        Encrypted::Ciphertext {
+            ciphertext: "9jqo^BlbD-BleB1djH3bb1ULW4j$".to_string(),
+            data_type: "text".to_string(),
+            unique_index: Some("abcd".to_string()),
+            ore_index: None,
+            match_index: Some(vec![1, 2]),
+            elements: None,
+            identifier: Identifier::new("users", "email"),
+            keyset: None,
+            expires_at: None,
+            version: 2,
+        }
+    }
+
+    #[test]
+    fn test_search_term_default_format() {
+        let term = SearchTerm::new(
+            encrypted_ciphertext(),
+            Some(Operator::Eq),
+            &SearchTermOptions::default(),
+        );
+
+        assert_eq!(
+            serde_json::to_string(&term).unwrap(),
+            r#"{"hm":"abcd","ob":null,"bf":[1,2],"i":{"t":"users","c":"email"}}"#
+        );
+    }
+
+    #[test]
+    fn test_search_term_includes_data_type_and_operator() {
+        let options = SearchTermOptions {
+            include_data_type: true,
+            include_operator: true,
+            ..SearchTermOptions::default()
+        };
+
+        let term = SearchTerm::new(encrypted_ciphertext(), Some(Operator::Like), &options);
+        let value = serde_json::to_value(&term).unwrap();
+
+        assert_eq!(value["dt"], "text");
+        assert_eq!(value["op"], "~~");
+    }
+
+    #[test]
+    fn test_pending_search_term_finish() {
+        let pending = PendingSearchTerm {
+            selector: None,
+            operator: Some(Operator::Eq),
+            cached: None,
+        };
+        let term = pending
+            .finish(encrypted_ciphertext(), &SearchTermOptions::default())
+            .unwrap();
+        assert!(matches!(term, SearchTerm::Indexes { .. }));
+
+        let pending = PendingSearchTerm {
+            selector: Some("abcd".to_string()),
+            operator: None,
+            cached: None,
+        };
+        let result = pending.finish(encrypted_ciphertext(), &SearchTermOptions::default());
+        assert!(matches!(result, Err(Error::InvalidSearchTerm(_))));
+    }
+
+    #[test]
+    fn test_search_term_escapes_identifiers() {
+        let mut encrypted = encrypted_ciphertext();
+        if let Encrypted::Ciphertext { identifier, .. } = &mut encrypted {
+            *identifier = Identifier::new(r#"us"ers"#, r#"e\mail"#);
+        }
+
+        let term = SearchTerm::new(encrypted, None, &SearchTermOptions::default());
+        let rendered = serde_json::to_string(&term).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+
+        assert_eq!(value["i"], json!({"t": r#"us"ers"#, "c": r#"e\mail"#}));
+    }
+
+    #[test]
+    fn test_operator_requires_supporting_index() {
+        let column_config = ColumnConfig::build("email".to_string())
+            .casts_as(ColumnType::Utf8Str)
+            .add_index(Index::new(IndexType::Unique {
+                token_filters: vec![],
+            }));
+
+        assert!(Operator::Eq.validate(&column_config).is_ok());
+
+        for operator in [Operator::Lt, Operator::Like, Operator::Contains] {
+            let result = operator.validate(&column_config);
+            assert!(matches!(result, Err(Error::InvalidSearchTerm(_))));
+        }
+    }
+
+    #[test]
+    fn test_operator_capability() {
+        let column_config = ColumnConfig::build("email".to_string())
+            .casts_as(ColumnType::Utf8Str)
+            .add_index(Index::new(IndexType::Ore))
+            .add_index(Index::new(IndexType::Unique {
+                token_filters: vec![],
+            }));
+
+        let capability = Operator::from_str("=").unwrap().capability(&column_config);
+        assert_eq!(
+            (capability.index, capability.term),
+            (Some("unique"), Some("hm"))
+        );
+        assert_eq!(Operator::Lt.capability(&column_config).term, Some("ob"));
+
+        let capability = Operator::from_str("LIKE")
+            .unwrap()
+            .capability(&column_config);
+        assert!(!capability.supported);
+        assert_eq!(capability.requires, vec!["match"]);
+        assert_eq!(
+            serde_json::to_value(&capability).unwrap(),
+            json!({
+                "operator": "~~",
+                "supported": false,
+                "reason": "operator `~~` requires a `match` index on the column",
+                "requires": ["match"],
+            })
+        );
+
+        let capability = Operator::Gt.path_capability(&column_config);
+        assert_eq!(capability.requires, vec!["ste_vec"]);
+        assert!(Operator::Like
+            .path_capability(&column_config)
+            .requires
+            .is_empty());
+        assert!(matches!(
+            Operator::from_str("=="),
+            Err(Error::InvalidSearchTerm(_))
+        ));
+    }
+
+    #[test]
+    fn test_search_term_eql_v2_literal() {
+        let term = SearchTerm::new(encrypted_ciphertext(), None, &SearchTermOptions::default());
+
+        assert_eq!(
+            term.to_eql_v2_literal().unwrap(),
+            r#"("{""hm"":""abcd"",""ob"":null,""bf"":[1,2],""i"":{""t"":""users"",""c"":""email""},""v"":2}")"#
+        );
+    }
+
+    #[test]
+    fn test_search_term_eql_v2_literal_escapes_backslashes() {
+        let mut encrypted = encrypted_ciphertext();
+        if let Encrypted::Ciphertext { identifier, .. } = &mut encrypted {
+            *identifier = Identifier::new("users", r#"e\mail"#);
+        }
+
+        let term = SearchTerm::new(encrypted, None, &SearchTermOptions::default());
+        let literal = term.to_eql_v2_literal().unwrap();
+
+        assert!(literal.contains(r#"""c"":""e\\\\mail"""#), "{literal}");
+    }
+
+    #[test]
+    fn test_range_into_eql_v2_format() {
+        let options = SearchTermOptions::default();
+        let range = SearchTermResult::Range {
+            from: SearchTerm::new(encrypted_ciphertext(), None, &options),
+            to: SearchTerm::new(encrypted_ciphertext(), None, &options),
+        };
+
+        let value =
+            serde_json::to_value(range.into_format(SearchTermFormat::EqlV2).unwrap()).unwrap();
+
+        assert!(value["from"].as_str().unwrap().starts_with(r#"("{"#));
+        assert!(value["to"].as_str().unwrap().ends_with(r#"}")"#));
+    }
+
+    #[test]
+    fn test_options_parse_format() {
+        let options: SearchTermOptions = serde_json::from_str(r#"{"format": "eql_v2"}"#).unwrap();
+        assert_eq!(options.format, SearchTermFormat::EqlV2);
+
+        let result = serde_json::from_str::<SearchTermOptions>(r#"{"format": "eql_v3"}"#);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_options_reject_unknown_fields() {
+        let result = serde_json::from_str::<SearchTermOptions>(r#"{"include_dt": true}"#);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,258 @@
+//! HMAC signing and verification of encrypted envelopes for tamper evidence.
+
+use crate::Error;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Envelope field holding the hex-encoded signature.
+pub const SIGNATURE_FIELD: &str = "sig";
+
+/// Minimum signing key length in bytes.
+const MIN_KEY_LENGTH: usize = 32;
+
+/// Secret key used to sign and verify envelopes.
+#[derive(Clone, PartialEq)]
+pub struct SigningKey(Vec<u8>);
+
+impl std::fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SigningKey(<redacted>)")
+    }
+}
+
+impl<'de> Deserialize<'de> for SigningKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let encoded = String::deserialize(deserializer)?;
+        let bytes = hex::decode(encoded).map_err(serde::de::Error::custom)?;
+
+        if bytes.len() < MIN_KEY_LENGTH {
+            return Err(serde::de::Error::custom(format!(
+                "signing key must be at least {MIN_KEY_LENGTH} bytes"
+            )));
+        }
+
+        Ok(Self(bytes))
+    }
+}
+
+impl SigningKey {
+    /// Compute the hex-encoded signature over the canonical form of an envelope.
+    ///
+    /// Any existing signature field is excluded from the signed payload.
+    pub fn sign(&self, envelope: &Value) -> String {
+        hex::encode(self.mac(envelope).finalize().into_bytes())
+    }
+
+    /// Verify the signature carried by an envelope.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::MissingSignature`] if the envelope is not signed, or
+    /// [`Error::InvalidSignature`] if the signature does not match.
+    pub fn verify(&self, envelope: &Value) -> Result<(), Error> {
+        let signature = envelope
+            .get(SIGNATURE_FIELD)
+            .and_then(Value::as_str)
+            .ok_or(Error::MissingSignature)?;
+        let signature = hex::decode(signature).map_err(|_| Error::InvalidSignature)?;
+
+        self.mac(envelope)
+            .verify_slice(&signature)
+            .map_err(|_| Error::InvalidSignature)
+    }
+
+    fn mac(&self, envelope: &Value) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.0).expect("HMAC accepts keys of any length");
+        mac.update(canonical(envelope).as_bytes());
+        mac
+    }
+}
+
+/// Verify decryption input when a signing key is configured.
+///
+/// Envelope input carrying a signature is always verified. Unsigned envelopes and raw
+/// ciphertexts are only accepted when `require_signature` is disabled, since anyone who can
+/// modify an envelope can also remove its signature.
+///
+/// # Errors
+///
+/// Returns [`Error::MissingSignature`] or [`Error::InvalidSignature`] if verification fails,
+/// or [`Error::Parse`] if envelope input is malformed.
+pub fn verify_input(input: &str, key: &SigningKey, require_signature: bool) -> Result<(), Error> {
+    if !input.trim_start().starts_with('{') {
+        if require_signature {
+            return Err(Error::MissingSignature);
+        }
+        return Ok(());
+    }
+
+    let envelope: Value = serde_json::from_str(input)?;
+
+    match envelope.get(SIGNATURE_FIELD) {
+        Some(_) => key.verify(&envelope),
+        None if require_signature => Err(Error::MissingSignature),
+        None => Ok(()),
+    }
+}
+
+/// Render a JSON value in canonical form: compact, with object keys sorted and the
+/// top-level signature field omitted.
+fn canonical(envelope: &Value) -> String {
+    let mut output = String::new();
+
+    match envelope {
+        Value::Object(fields) => write_object(
+            &mut output,
+            fields.iter().filter(|(key, _)| *key != SIGNATURE_FIELD),
+        ),
+        value => write_value(&mut output, value),
+    }
+
+    output
+}
+
+fn write_object<'a>(output: &mut String, fields: impl Iterator<Item = (&'a String, &'a Value)>) {
+    let mut fields: Vec<_> = fields.collect();
+    fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    output.push('{');
+    for (index, (key, value)) in fields.into_iter().enumerate() {
+        if index > 0 {
+            output.push(',');
+        }
+        output.push_str(&Value::String(key.clone()).to_string());
+        output.push(':');
+        write_value(output, value);
+    }
+    output.push('}');
+}
+
+fn write_value(output: &mut String, value: &Value) {
+    match value {
+        Value::Object(fields) => write_object(output, fields.iter()),
+        Value::Array(items) => {
+            output.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    output.push(',');
+                }
+                write_value(output, item);
+            }
+            output.push(']');
+        }
+        scalar => output.push_str(&scalar.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client_options::ClientOptions;
+    use serde_json::json;
+
+    const TEST_KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    fn test_key() -> SigningKey {
+        serde_json::from_value(json!(TEST_KEY)).unwrap()
+    }
+
+    fn test_envelope() -> Value {
+        json!({
+            "k": "ct",
+            "c": "mBbKlk}G7QdaGiNj$dL7#+AOrA^}*VJx",
+            "dt": "text",
+            "hm": "f3ca71fd39ae9d3d1d1fc25141bcb6da",
+            "ob": null,
+            "bf": [1124, 2134],
+            "i": {"t": "users", "c": "email"},
+            "v": 2
+        })
+    }
+
+    fn signed_envelope() -> Value {
+        let mut envelope = test_envelope();
+        let signature = test_key().sign(&envelope);
+        envelope[SIGNATURE_FIELD] = Value::String(signature);
+        envelope
+    }
+
+    #[test]
+    fn test_canonical_sorts_keys_and_omits_signature() {
+        let envelope = json!({"v": 2, "sig": "abcd", "i": {"t": "users", "c": "email"}});
+
+        assert_eq!(
+            canonical(&envelope),
+            r#"{"i":{"c":"email","t":"users"},"v":2}"#
+        );
+    }
+
+    #[test]
+    fn test_sign_and_verify_roundtrip() {
+        assert!(test_key().verify(&signed_envelope()).is_ok());
+    }
+
+    #[test]
+    fn test_verify_detects_tampering() {
+        let mut envelope = signed_envelope();
+        envelope["hm"] = json!("00000000000000000000000000000000");
+
+        let result = test_key().verify(&envelope);
+
+        assert!(matches!(result, Err(Error::InvalidSignature)));
+    }
+
+    #[test]
+    fn test_verify_input_requires_signature_when_configured() {
+        let unsigned = test_envelope().to_string();
+
+        assert!(verify_input(&unsigned, &test_key(), false).is_ok());
+        assert!(matches!(
+            verify_input(&unsigned, &test_key(), true),
+            Err(Error::MissingSignature)
+        ));
+        assert!(matches!(
+            verify_input("mBbKlk}G7QdaGiNj", &test_key(), true),
+            Err(Error::MissingSignature)
+        ));
+    }
+
+    #[test]
+    fn test_verify_input_checks_signed_envelopes() {
+        let signed = signed_envelope().to_string();
+
+        assert!(verify_input(&signed, &test_key(), false).is_ok());
+        assert!(verify_input(&signed, &test_key(), true).is_ok());
+    }
+
+    #[test]
+    fn test_verify_input_rejects_stripped_signature() {
+        let mut envelope = signed_envelope();
+        envelope["hm"] = json!("00000000000000000000000000000000");
+        envelope.as_object_mut().unwrap().remove(SIGNATURE_FIELD);
+        let require_signature = ClientOptions::default().require_signature;
+
+        assert!(matches!(
+            verify_input(&envelope.to_string(), &test_key(), require_signature),
+            Err(Error::MissingSignature)
+        ));
+    }
+
+    #[test]
+    fn test_short_signing_key_fails() {
+        let result = serde_json::from_value::<SigningKey>(json!("0001"));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_signing_key_debug_is_redacted() {
+        assert_eq!(format!("{:?}", test_key()), "SigningKey(<redacted>)");
+    }
+}
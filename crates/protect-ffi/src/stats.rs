@@ -0,0 +1,298 @@
+//! Payload statistics for bulk encryption.
+//!
+//! Statistics report how much storage encrypted values take compared to their plaintexts, in
+//! total and per column, so the storage growth of a migration can be estimated from a sample
+//! batch. Sizes are the byte lengths of values as they are serialized in the response: index
+//! terms as JSON, and stored values as the emitted envelope or storage columns.
+
+use crate::{Encrypted, Error};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Upper bounds of the plaintext size histogram buckets, in bytes. A final bucket counts
+/// larger plaintexts.
+const HISTOGRAM_BOUNDS: [u64; 7] = [16, 64, 256, 1_024, 4_096, 16_384, 65_536];
+
+/// Options for bulk encryption.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BulkEncryptOptions {
+    /// Return payload statistics alongside the results.
+    pub stats: bool,
+    /// Return non-fatal warnings alongside the results.
+    pub include_warnings: bool,
+}
+
+/// Byte lengths of the index terms of encrypted values.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct IndexBytes {
+    /// Bytes of `unique` index terms.
+    pub unique: u64,
+    /// Bytes of `ore` index terms.
+    pub ore: u64,
+    /// Bytes of `match` index terms.
+    #[serde(rename = "match")]
+    pub r#match: u64,
+    /// Bytes of `ste_vec` entries.
+    pub ste_vec: u64,
+}
+
+/// Number of plaintexts of at most `max_bytes` bytes, and larger than the previous bucket.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct HistogramBucket {
+    /// Upper bound of the bucket, or `null` for the bucket of the largest plaintexts.
+    pub max_bytes: Option<u64>,
+    /// Number of plaintexts in the bucket.
+    pub count: u64,
+}
+
+/// Aggregate sizes of a set of encrypted values.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PayloadStats {
+    /// Number of values.
+    pub items: u64,
+    /// Bytes of plaintext.
+    pub plaintext_bytes: u64,
+    /// Bytes of base85-encoded ciphertext.
+    pub ciphertext_bytes: u64,
+    /// Bytes of index terms, by index type.
+    pub index_bytes: IndexBytes,
+    /// Bytes of the serialized values as returned for storage.
+    pub stored_bytes: u64,
+    /// Stored bytes per plaintext byte, or `null` when there was no plaintext.
+    pub expansion_ratio: Option<f64>,
+    /// Distribution of plaintext sizes.
+    pub plaintext_size_histogram: Vec<HistogramBucket>,
+}
+
+impl Default for PayloadStats {
+    fn default() -> Self {
+        let plaintext_size_histogram = HISTOGRAM_BOUNDS
+            .iter()
+            .map(|bound| Some(*bound))
+            .chain([None])
+            .map(|max_bytes| HistogramBucket {
+                max_bytes,
+                count: 0,
+            })
+            .collect();
+
+        Self {
+            items: 0,
+            plaintext_bytes: 0,
+            ciphertext_bytes: 0,
+            index_bytes: IndexBytes::default(),
+            stored_bytes: 0,
+            expansion_ratio: None,
+            plaintext_size_histogram,
+        }
+    }
+}
+
+/// Sizes of a single encrypted value.
+struct ItemSizes {
+    plaintext: u64,
+    ciphertext: u64,
+    index: IndexBytes,
+    stored: u64,
+}
+
+impl PayloadStats {
+    fn add(&mut self, sizes: &ItemSizes) {
+        self.items += 1;
+        self.plaintext_bytes += sizes.plaintext;
+        self.ciphertext_bytes += sizes.ciphertext;
+        self.index_bytes.unique += sizes.index.unique;
+        self.index_bytes.ore += sizes.index.ore;
+        self.index_bytes.r#match += sizes.index.r#match;
+        self.index_bytes.ste_vec += sizes.index.ste_vec;
+        self.stored_bytes += sizes.stored;
+        self.expansion_ratio = (self.plaintext_bytes > 0)
+            .then(|| self.stored_bytes as f64 / self.plaintext_bytes as f64);
+
+        let bucket = HISTOGRAM_BOUNDS
+            .iter()
+            .position(|bound| sizes.plaintext <= *bound)
+            .unwrap_or(HISTOGRAM_BOUNDS.len());
+        self.plaintext_size_histogram[bucket].count += 1;
+    }
+}
+
+/// Payload statistics of a bulk encryption request, in total and by `table.column`.
+#[derive(Debug, Default, Serialize)]
+pub struct BulkStats {
+    /// Statistics of all values.
+    #[serde(flatten)]
+    pub total: PayloadStats,
+    /// Statistics of the values of each column.
+    pub columns: BTreeMap<String, PayloadStats>,
+}
+
+impl BulkStats {
+    /// Record a value measured with [`measure()`], its plaintext length, and the value emitted
+    /// for storage.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the emitted value cannot be serialized.
+    pub fn record(
+        &mut self,
+        plaintext_bytes: usize,
+        measured: EncryptedSizes,
+        emitted: &Value,
+    ) -> Result<(), Error> {
+        let sizes = ItemSizes {
+            plaintext: plaintext_bytes as u64,
+            ciphertext: measured.ciphertext,
+            index: measured.index,
+            stored: serde_json::to_string(emitted)?.len() as u64,
+        };
+
+        self.total.add(&sizes);
+        self.columns.entry(measured.column).or_default().add(&sizes);
+
+        Ok(())
+    }
+}
+
+/// Ciphertext and index term sizes of an encrypted value, measured before it is emitted.
+pub struct EncryptedSizes {
+    column: String,
+    ciphertext: u64,
+    index: IndexBytes,
+}
+
+/// Measure the ciphertext and index terms of an encrypted value.
+///
+/// # Errors
+///
+/// Returns an error if the index terms cannot be serialized.
+pub fn measure(encrypted: &Encrypted) -> Result<EncryptedSizes, Error> {
+    let (ciphertext, identifier, index) = match encrypted {
+        Encrypted::Ciphertext {
+            ciphertext,
+            unique_index,
+            ore_index,
+            match_index,
+            elements,
+            identifier,
+            ..
+        } => {
+            let mut index = IndexBytes {
+                unique: json_len(unique_index)?,
+                ore: json_len(ore_index)?,
+                r#match: json_len(match_index)?,
+                ste_vec: 0,
+            };
+
+            for element in elements.iter().flatten() {
+                index.unique += json_len(&element.unique_index)?;
+                index.ore += json_len(&element.ore_index)?;
+                index.r#match += json_len(&element.match_index)?;
+            }
+
+            (ciphertext, identifier, index)
+        }
+        Encrypted::SteVec {
+            ciphertext,
+            ste_vec_index,
+            identifier,
+            ..
+        } => {
+            let index = IndexBytes {
+                ste_vec: json_len(ste_vec_index)?,
+                ..IndexBytes::default()
+            };
+
+            (ciphertext, identifier, index)
+        }
+    };
+
+    Ok(EncryptedSizes {
+        column: format!("{}.{}", identifier.table, identifier.column),
+        ciphertext: ciphertext.len() as u64,
+        index,
+    })
+}
+
+/// Serialized length of an index term, or zero when the value has none.
+fn json_len<T: Serialize>(term: &Option<T>) -> Result<u64, Error> {
+    match term {
+        Some(term) => Ok(serde_json::to_string(term)?.len() as u64),
+        None => Ok(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encrypt_config::Identifier;
+    use serde_json::json;
+
+    fn encrypted_email(unique_index: &str) -> Encrypted {
+        Encrypted::Ciphertext {
+            ciphertext: "mBbKlk}G7QdaGiNj".to_string(),
+            data_type: "text".to_string(),
+            unique_index: Some(unique_index.to_string()),
+            ore_index: None,
+            match_index: Some(vec![1124, 2134]),
+            elements: None,
+            identifier: Identifier::new("users", "email"),
+            keyset: None,
+            expires_at: None,
+            version: 2,
+        }
+    }
+
+    #[test]
+    fn test_record_aggregates_totals_and_columns() {
+        let mut stats = BulkStats::default();
+        let emitted = json!({"c": "mBbKlk}G7QdaGiNj"});
+
+        let measured = measure(&encrypted_email("f3ca71fd")).unwrap();
+        stats.record(10, measured, &emitted).unwrap();
+        let measured = measure(&encrypted_email("00ff")).unwrap();
+        stats.record(100, measured, &emitted).unwrap();
+
+        assert_eq!(stats.total.items, 2);
+        assert_eq!(stats.total.plaintext_bytes, 110);
+        assert_eq!(stats.total.ciphertext_bytes, 32);
+        assert_eq!(stats.total.index_bytes.unique, 16);
+        assert_eq!(stats.total.index_bytes.r#match, 22);
+        assert_eq!(stats.total.index_bytes.ore, 0);
+        assert_eq!(stats.total.stored_bytes, 48);
+        assert_eq!(stats.columns["users.email"], stats.total);
+
+        let counts: Vec<u64> = stats
+            .total
+            .plaintext_size_histogram
+            .iter()
+            .map(|bucket| bucket.count)
+            .collect();
+        assert_eq!(counts, [1, 0, 1, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_expansion_ratio_requires_plaintext() {
+        let mut stats = PayloadStats::default();
+        assert_eq!(stats.expansion_ratio, None);
+
+        stats.add(&ItemSizes {
+            plaintext: 0,
+            ciphertext: 10,
+            index: IndexBytes::default(),
+            stored: 40,
+        });
+        assert_eq!(stats.expansion_ratio, None);
+
+        stats.add(&ItemSizes {
+            plaintext: 100_000,
+            ciphertext: 10,
+            index: IndexBytes::default(),
+            stored: 160,
+        });
+        assert_eq!(stats.expansion_ratio, Some(0.002));
+        assert_eq!(stats.plaintext_size_histogram[7].count, 1);
+    }
+}
@@ -0,0 +1,348 @@
+//! Limits on the parts of a JSON document indexed by a `ste_vec` index.
+//!
+//! A `ste_vec` index adds an entry for every value in a document, so large or deeply nested
+//! documents produce very large envelopes. A column can limit its index to the queryable subtree
+//! with `max_depth` and `include` and `exclude` paths, and cap the number of entries with
+//! `max_entries`. Values outside the indexed subtree are still encrypted in the root ciphertext,
+//! so decryption returns the whole document, but containment queries cannot match them.
+//!
+//! When a limit removes part of a document, the document is encrypted without the `ste_vec`
+//! index, followed by a target for the indexed subtree whose ciphertext is discarded and whose
+//! entries are attached to the document.
+
+use crate::encrypt_config::Identifier;
+use crate::json_paths::{JsonPath, Segment};
+use crate::plaintext_target;
+use crate::{Error, ScopedZeroKMSNoRefresh};
+use cipherstash_client::{
+    encryption::{IndexTerm, Plaintext, PlaintextTarget, QueryBuilder, QueryOp},
+    schema::{column::IndexType, ColumnConfig},
+};
+use serde_json::{Map, Value};
+
+/// The parts of a document a column's `ste_vec` index covers.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SteVecPolicy {
+    /// The deepest level of nesting indexed, where fields of the root are at depth 1.
+    pub max_depth: Option<usize>,
+    /// The largest number of entries a document may produce.
+    pub max_entries: Option<usize>,
+    /// The paths indexed, with their ancestors, or every path when empty.
+    pub include: Vec<JsonPath>,
+    /// The paths not indexed.
+    pub exclude: Vec<JsonPath>,
+}
+
+/// The policy of columns without limits.
+pub static UNLIMITED: SteVecPolicy = SteVecPolicy {
+    max_depth: None,
+    max_entries: None,
+    include: Vec::new(),
+    exclude: Vec::new(),
+};
+
+impl SteVecPolicy {
+    /// Create the targets for a JSON document: the document with its `ste_vec` entries, or,
+    /// when the limits remove part of it, the document without the `ste_vec` index followed by
+    /// the indexed subtree.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the plaintext is not a JSON document, or if the indexed subtree has
+    /// more entries than `max_entries`.
+    pub fn targets(
+        &self,
+        plaintext: String,
+        column_config: &ColumnConfig,
+    ) -> Result<Vec<PlaintextTarget>, Error> {
+        if *self == UNLIMITED {
+            return Ok(vec![plaintext_target::new(plaintext, column_config)?]);
+        }
+
+        let document = plaintext_target::json_document(&plaintext)?;
+        let indexed = self.indexed(&document)?;
+        if indexed == document {
+            return Ok(vec![PlaintextTarget::new(document, column_config.clone())]);
+        }
+
+        let mut document_config = column_config.clone();
+        document_config
+            .indexes
+            .retain(|index| !matches!(index.index_type, IndexType::SteVec { .. }));
+
+        Ok(vec![
+            PlaintextTarget::new(document, document_config),
+            PlaintextTarget::new(indexed, column_config.clone()),
+        ])
+    }
+
+    /// The subtree of a document that is indexed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidJsonDocument`] if the subtree has more entries than
+    /// `max_entries`.
+    pub fn indexed(&self, document: &Value) -> Result<Value, Error> {
+        let indexed = self.prune(document, &mut Vec::new());
+
+        if let Some(max_entries) = self.max_entries {
+            let entries = entries(&indexed);
+            if entries > max_entries {
+                return Err(Error::InvalidJsonDocument(format!(
+                    "the document has {entries} indexed values, more than the `max_entries` limit of {max_entries}"
+                )));
+            }
+        }
+
+        Ok(indexed)
+    }
+
+    /// Whether the value at a path is indexed.
+    fn indexes(&self, path: &[Segment]) -> bool {
+        self.max_depth
+            .is_none_or(|max_depth| path.len() <= max_depth)
+            && !self.exclude.iter().any(|exclude| exclude.contains(path))
+            && (self.include.is_empty()
+                || self
+                    .include
+                    .iter()
+                    .any(|include| include.contains(path) || include.is_inside(path)))
+    }
+
+    /// Copy a value, keeping only the nested values that are indexed.
+    fn prune(&self, value: &Value, path: &mut Vec<Segment>) -> Value {
+        match value {
+            Value::Object(fields) => {
+                let mut kept = Map::new();
+                for (key, field) in fields {
+                    path.push(Segment::Key(key.clone()));
+                    if self.indexes(path) {
+                        kept.insert(key.clone(), self.prune(field, path));
+                    }
+                    path.pop();
+                }
+                Value::Object(kept)
+            }
+            Value::Array(elements) => {
+                let mut kept = Vec::new();
+                for (index, element) in elements.iter().enumerate() {
+                    path.push(Segment::Index(index));
+                    if self.indexes(path) {
+                        kept.push(self.prune(element, path));
+                    }
+                    path.pop();
+                }
+                Value::Array(kept)
+            }
+            scalar => scalar.clone(),
+        }
+    }
+}
+
+/// Derive the tokenized selector of a JSON path under a column's `ste_vec` prefix.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidJsonPath`] if the path is malformed, [`Error::InvalidSearchTerm`] if
+/// the column has no `ste_vec` index, or an encryption error if the selector cannot be derived.
+pub fn tokenize_selector(
+    cipher: &ScopedZeroKMSNoRefresh,
+    column_config: &ColumnConfig,
+    identifier: &Identifier,
+    path: &str,
+) -> Result<Vec<u8>, Error> {
+    JsonPath::parse_filter(path)?;
+
+    let index = column_config
+        .indexes
+        .iter()
+        .find(|index| matches!(index.index_type, IndexType::SteVec { .. }))
+        .ok_or_else(|| {
+            Error::InvalidSearchTerm(format!(
+                "selectors require a `ste_vec` index, which column `{}.{}` does not have",
+                identifier.table, identifier.column
+            ))
+        })?;
+
+    match (index, Plaintext::from(path.to_string()))
+        .build_queryable(cipher, QueryOp::SteVecSelector)?
+    {
+        IndexTerm::SteVecSelector(selector) => Ok(selector.as_bytes().to_vec()),
+        term => Err(Error::InvariantViolation(format!(
+            "expected a `ste_vec` selector, but the encryption library returned `{term:?}`"
+        ))),
+    }
+}
+
+/// Build the smallest document with a value at a path, for a search term on that field.
+///
+/// Plaintexts that parse as JSON, such as `42` or `true`, are used as JSON values, and other
+/// plaintexts as strings.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidJsonPath`] if the path is malformed or selects a specific array
+/// position, or [`Error::InvalidSearchTerm`] if the value is an object or array.
+pub fn query_document(path: &str, plaintext: String) -> Result<Value, Error> {
+    let json_path = JsonPath::parse_filter(path)?;
+    let value = serde_json::from_str(&plaintext).unwrap_or(Value::String(plaintext));
+    if value.is_object() || value.is_array() {
+        return Err(Error::InvalidSearchTerm(
+            "a `path` search term compares a single value, not an object or array".to_string(),
+        ));
+    }
+
+    json_path
+        .segments()
+        .iter()
+        .rev()
+        .try_fold(value, |value, segment| match segment {
+            Segment::Key(key) => Ok(Value::Object(Map::from_iter([(key.clone(), value)]))),
+            Segment::AnyIndex => Ok(Value::Array(vec![value])),
+            Segment::Index(_) => Err(Error::InvalidJsonPath {
+                path: path.to_string(),
+                reason: "search terms select array elements with `[*]`".to_string(),
+            }),
+        })
+}
+
+/// The number of `ste_vec` entries of a document: one for every value, including the root.
+pub fn entries(value: &Value) -> usize {
+    1 + match value {
+        Value::Object(fields) => fields.values().map(entries).sum(),
+        Value::Array(elements) => elements.iter().map(entries).sum(),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cipherstash_client::schema::{column::Index, ColumnType};
+    use serde_json::json;
+
+    fn paths(paths: &[&str]) -> Vec<JsonPath> {
+        paths
+            .iter()
+            .map(|path| JsonPath::parse_filter(path).unwrap())
+            .collect()
+    }
+
+    fn document() -> Value {
+        json!({
+            "profile": {"email": "ada@example.com", "name": {"first": "Ada"}},
+            "tags": ["admin", "editor"],
+            "raw_payload": {"headers": {"accept": "*/*"}}
+        })
+    }
+
+    #[test]
+    fn test_indexed_limits_depth() {
+        let policy = SteVecPolicy {
+            max_depth: Some(1),
+            ..SteVecPolicy::default()
+        };
+
+        assert_eq!(
+            policy.indexed(&document()).unwrap(),
+            json!({"profile": {}, "tags": [], "raw_payload": {}})
+        );
+    }
+
+    #[test]
+    fn test_indexed_filters_paths() {
+        let policy = SteVecPolicy {
+            include: paths(&["$.profile", "$.tags[1]"]),
+            exclude: paths(&["$.profile.name"]),
+            ..SteVecPolicy::default()
+        };
+
+        assert_eq!(
+            policy.indexed(&document()).unwrap(),
+            json!({"profile": {"email": "ada@example.com"}, "tags": ["editor"]})
+        );
+
+        let policy = SteVecPolicy {
+            exclude: paths(&["$.raw_payload"]),
+            ..SteVecPolicy::default()
+        };
+        assert!(policy
+            .indexed(&document())
+            .unwrap()
+            .get("raw_payload")
+            .is_none());
+    }
+
+    #[test]
+    fn test_indexed_filters_every_array_element() {
+        let policy = SteVecPolicy {
+            include: paths(&["$.profile.email", "$.tags[*]"]),
+            ..SteVecPolicy::default()
+        };
+
+        assert_eq!(
+            policy.indexed(&document()).unwrap(),
+            json!({"profile": {"email": "ada@example.com"}, "tags": ["admin", "editor"]})
+        );
+    }
+
+    #[test]
+    fn test_query_document_nests_value_at_path() {
+        assert_eq!(
+            query_document("$.profile.age", "42".to_string()).unwrap(),
+            json!({"profile": {"age": 42}})
+        );
+        assert_eq!(
+            query_document("$.visits[*].on", "2024-03-15".to_string()).unwrap(),
+            json!({"visits": [{"on": "2024-03-15"}]})
+        );
+
+        let result = query_document("$.visits[0]", "1".to_string());
+        assert!(matches!(result, Err(Error::InvalidJsonPath { .. })));
+
+        let result = query_document("$.profile", r#"{"age": 42}"#.to_string());
+        assert!(matches!(result, Err(Error::InvalidSearchTerm(_))));
+    }
+
+    #[test]
+    fn test_indexed_rejects_documents_over_max_entries() {
+        let policy = SteVecPolicy {
+            max_entries: Some(11),
+            ..SteVecPolicy::default()
+        };
+        assert_eq!(entries(&document()), 11);
+        assert!(policy.indexed(&document()).is_ok());
+
+        let policy = SteVecPolicy {
+            max_entries: Some(10),
+            ..SteVecPolicy::default()
+        };
+        let result = policy.indexed(&document());
+        assert!(matches!(result, Err(Error::InvalidJsonDocument(_))));
+    }
+
+    #[test]
+    fn test_targets_split_document_and_indexed_subtree() {
+        let column_config = ColumnConfig::build("metadata".to_string())
+            .casts_as(ColumnType::JsonB)
+            .add_index(Index::new(IndexType::SteVec {
+                prefix: "users/metadata".to_string(),
+            }));
+        let policy = SteVecPolicy {
+            exclude: paths(&["$.raw_payload"]),
+            ..SteVecPolicy::default()
+        };
+
+        let targets = policy
+            .targets(document().to_string(), &column_config)
+            .unwrap();
+        assert_eq!(targets.len(), 2);
+        assert!(targets[0].config.indexes.is_empty());
+        assert_eq!(targets[1].config.indexes.len(), 1);
+
+        let targets = policy
+            .targets(json!({"tags": ["admin"]}).to_string(), &column_config)
+            .unwrap();
+        assert_eq!(targets.len(), 1);
+    }
+}
@@ -0,0 +1,363 @@
+//! Splitting envelopes into storage columns and assembling them again.
+//!
+//! A storage profile names the column each part of an envelope is stored in. The ciphertext and
+//! any mapped index terms get columns of their own, and every other envelope field, such as the
+//! identifier, version, and signature, is stored as JSON text in the metadata column. Assembling
+//! the columns restores the original envelope, so envelope signatures remain valid.
+
+use crate::envelope;
+use crate::Error;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+/// Column names for the parts of an envelope.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StorageProfile {
+    /// Column holding the ciphertext.
+    pub ciphertext: String,
+    /// Column holding the remaining envelope fields as JSON text.
+    pub metadata: String,
+    /// Column holding the `unique` index term, if stored separately.
+    #[serde(default)]
+    pub unique: Option<String>,
+    /// Column holding the `ore` index terms, if stored separately.
+    #[serde(default)]
+    pub ore: Option<String>,
+    /// Column holding the `match` index terms, if stored separately.
+    #[serde(default, rename = "match")]
+    pub r#match: Option<String>,
+    /// Column holding the `ste_vec` entries, if stored separately.
+    #[serde(default)]
+    pub ste_vec: Option<String>,
+}
+
+/// An index part of an envelope that a profile can map to a column.
+#[derive(Clone, Copy)]
+enum IndexPart {
+    Unique,
+    Ore,
+    Match,
+    SteVec,
+}
+
+impl StorageProfile {
+    /// Check that every part of the envelope is mapped to a different column.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidStorage`] if a column name is empty or used for more than one part.
+    fn validate(&self) -> Result<(), Error> {
+        let mut columns = vec![self.ciphertext.as_str(), self.metadata.as_str()];
+        columns.extend(self.index_columns().map(|(_, column)| column));
+
+        for (position, column) in columns.iter().enumerate() {
+            if column.is_empty() {
+                return Err(Error::InvalidStorage(
+                    "column names must not be empty".to_string(),
+                ));
+            }
+            if columns[..position].contains(column) {
+                return Err(Error::InvalidStorage(format!(
+                    "column `{column}` is used for more than one part of the envelope"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The mapped index parts and their columns.
+    fn index_columns(&self) -> impl Iterator<Item = (IndexPart, &str)> {
+        [
+            (IndexPart::Unique, &self.unique),
+            (IndexPart::Ore, &self.ore),
+            (IndexPart::Match, &self.r#match),
+            (IndexPart::SteVec, &self.ste_vec),
+        ]
+        .into_iter()
+        .filter_map(|(part, column)| Some((part, column.as_deref()?)))
+    }
+}
+
+/// The envelope field holding an index part, and whether envelopes of the kind always carry it.
+fn index_field(part: IndexPart, kind: Option<&str>, version: Option<u64>) -> (&'static str, bool) {
+    let [unique, ore, r#match] = envelope::index_field_names(version);
+    let is_ciphertext = kind == Some("ct");
+
+    match part {
+        IndexPart::Unique => (unique, is_ciphertext),
+        IndexPart::Ore => (ore, is_ciphertext),
+        IndexPart::Match => (r#match, is_ciphertext),
+        IndexPart::SteVec => ("sv", kind == Some("sv")),
+    }
+}
+
+/// Split an envelope into the columns of a storage profile.
+///
+/// The unique index term is stored as a string, and array index terms as JSON text. Mapped
+/// columns for index terms the envelope does not carry are `null`.
+///
+/// # Errors
+///
+/// Returns [`Error::Parse`] if the envelope is malformed, or [`Error::InvalidStorage`] if the
+/// profile is invalid or the envelope has no ciphertext.
+pub fn explode(envelope_json: &str, profile: &StorageProfile) -> Result<Map<String, Value>, Error> {
+    profile.validate()?;
+
+    let Value::Object(mut envelope) = serde_json::from_str(envelope_json)? else {
+        return Err(Error::InvalidStorage(
+            "expected an envelope object".to_string(),
+        ));
+    };
+
+    let kind = envelope
+        .get("k")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let version = envelope.get("v").and_then(Value::as_u64);
+    let mut columns = Map::new();
+
+    match envelope.remove("c") {
+        Some(ciphertext @ Value::String(_)) => {
+            columns.insert(profile.ciphertext.clone(), ciphertext);
+        }
+        _ => {
+            return Err(Error::InvalidStorage(
+                "the envelope has no ciphertext".to_string(),
+            ))
+        }
+    }
+
+    for (part, column) in profile.index_columns() {
+        let (field, _) = index_field(part, kind.as_deref(), version);
+        let value = match envelope.remove(field) {
+            None | Some(Value::Null) => Value::Null,
+            Some(term @ Value::String(_)) => term,
+            Some(terms) => Value::String(serde_json::to_string(&terms)?),
+        };
+
+        columns.insert(column.to_string(), value);
+    }
+
+    columns.insert(
+        profile.metadata.clone(),
+        Value::String(serde_json::to_string(&envelope)?),
+    );
+
+    Ok(columns)
+}
+
+/// Assemble an envelope from the columns of a storage profile.
+///
+/// Missing or `null` index columns restore a `null` term only for fields that envelopes of the
+/// recorded kind always carry, so that the assembled envelope matches the one that was split.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidStorage`] if the profile is invalid, the ciphertext or metadata
+/// column is missing or malformed, or an index column holds terms the envelope kind does not
+/// carry.
+pub fn assemble(columns: &Map<String, Value>, profile: &StorageProfile) -> Result<Value, Error> {
+    profile.validate()?;
+
+    let metadata = match columns.get(&profile.metadata) {
+        Some(Value::String(metadata)) => metadata,
+        _ => {
+            return Err(Error::InvalidStorage(format!(
+                "expected the `{}` metadata column to be a JSON string",
+                profile.metadata
+            )))
+        }
+    };
+
+    let Value::Object(mut envelope) = serde_json::from_str(metadata)? else {
+        return Err(Error::InvalidStorage(format!(
+            "expected the `{}` metadata column to hold a JSON object",
+            profile.metadata
+        )));
+    };
+
+    match columns.get(&profile.ciphertext) {
+        Some(ciphertext @ Value::String(_)) => {
+            envelope.insert("c".to_string(), ciphertext.clone());
+        }
+        _ => {
+            return Err(Error::InvalidStorage(format!(
+                "expected the `{}` ciphertext column to be a string",
+                profile.ciphertext
+            )))
+        }
+    }
+
+    let kind = envelope
+        .get("k")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let version = envelope.get("v").and_then(Value::as_u64);
+
+    for (part, column) in profile.index_columns() {
+        let (field, carried) = index_field(part, kind.as_deref(), version);
+
+        let value = match (columns.get(column), part) {
+            (None | Some(Value::Null), _) if carried => Value::Null,
+            (None | Some(Value::Null), _) => continue,
+            (Some(_), _) if !carried => {
+                return Err(Error::InvalidStorage(format!(
+                    "column `{column}` holds index terms that this envelope kind does not carry"
+                )))
+            }
+            (Some(term @ Value::String(_)), IndexPart::Unique) => term.clone(),
+            (Some(Value::String(terms)), _) => serde_json::from_str(terms)?,
+            (Some(_), _) => {
+                return Err(Error::InvalidStorage(format!(
+                    "expected the `{column}` column to be a string"
+                )))
+            }
+        };
+
+        envelope.insert(field.to_string(), value);
+    }
+
+    Ok(Value::Object(envelope))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn profile() -> StorageProfile {
+        serde_json::from_value(json!({
+            "ciphertext": "email_ct",
+            "metadata": "email_meta",
+            "unique": "email_hm",
+            "ore": "email_ob",
+            "match": "email_bf",
+        }))
+        .unwrap()
+    }
+
+    fn ciphertext_envelope() -> Value {
+        json!({
+            "k": "ct",
+            "c": "mBbKlk}G7QdaGiNj$dL7#+AOrA^}*VJx",
+            "dt": "text",
+            "hm": "f3ca71fd",
+            "ob": null,
+            "bf": [1124, 2134],
+            "i": {"t": "users", "c": "email"},
+            "v": 2,
+            "sig": "00ff"
+        })
+    }
+
+    #[test]
+    fn test_explode_splits_envelope_parts() {
+        let columns = explode(&ciphertext_envelope().to_string(), &profile()).unwrap();
+
+        assert_eq!(columns["email_ct"], "mBbKlk}G7QdaGiNj$dL7#+AOrA^}*VJx");
+        assert_eq!(columns["email_hm"], "f3ca71fd");
+        assert_eq!(columns["email_ob"], Value::Null);
+        assert_eq!(columns["email_bf"], "[1124,2134]");
+
+        let metadata: Value =
+            serde_json::from_str(columns["email_meta"].as_str().unwrap()).unwrap();
+        assert_eq!(
+            metadata,
+            json!({
+                "k": "ct",
+                "dt": "text",
+                "i": {"t": "users", "c": "email"},
+                "v": 2,
+                "sig": "00ff"
+            })
+        );
+    }
+
+    #[test]
+    fn test_assemble_restores_exploded_envelope() {
+        let envelope = ciphertext_envelope();
+        let columns = explode(&envelope.to_string(), &profile()).unwrap();
+
+        assert_eq!(assemble(&columns, &profile()).unwrap(), envelope);
+    }
+
+    #[test]
+    fn test_roundtrip_ste_vec_and_v1_envelopes() {
+        let ste_vec_profile: StorageProfile = serde_json::from_value(json!({
+            "ciphertext": "c",
+            "metadata": "m",
+            "unique": "hm",
+            "ste_vec": "sv",
+        }))
+        .unwrap();
+        let ste_vec_envelope = json!({
+            "k": "sv",
+            "c": "mBbKlk}G7QdaGiNj",
+            "dt": "jsonb",
+            "sv": [{"s": "dd46", "t": "2230", "r": "mBbL", "pa": false}],
+            "i": {"t": "users", "c": "contact"},
+            "v": 2
+        });
+
+        let columns = explode(&ste_vec_envelope.to_string(), &ste_vec_profile).unwrap();
+        assert_eq!(columns["hm"], Value::Null);
+        assert_eq!(
+            assemble(&columns, &ste_vec_profile).unwrap(),
+            ste_vec_envelope
+        );
+
+        let v1_envelope = json!({
+            "k": "ct",
+            "c": "mBbKlk}G7QdaGiNj",
+            "u": "f3ca71fd",
+            "o": null,
+            "m": null,
+            "i": {"t": "users", "c": "email"},
+            "v": 1
+        });
+
+        let columns = explode(&v1_envelope.to_string(), &profile()).unwrap();
+        assert_eq!(columns["email_hm"], "f3ca71fd");
+        assert_eq!(assemble(&columns, &profile()).unwrap(), v1_envelope);
+    }
+
+    #[test]
+    fn test_assemble_rejects_terms_the_kind_does_not_carry() {
+        let ste_vec_profile: StorageProfile = serde_json::from_value(json!({
+            "ciphertext": "c",
+            "metadata": "m",
+            "ste_vec": "sv",
+        }))
+        .unwrap();
+        let columns = json!({
+            "c": "mBbKlk}G7QdaGiNj",
+            "m": r#"{"k":"ct","dt":"text","hm":null,"ob":null,"bf":null,"i":{"t":"users","c":"email"},"v":2}"#,
+            "sv": "[]",
+        });
+
+        let result = assemble(columns.as_object().unwrap(), &ste_vec_profile);
+        assert!(matches!(result, Err(Error::InvalidStorage(_))));
+    }
+
+    #[test]
+    fn test_assemble_requires_ciphertext_and_metadata() {
+        let columns = json!({"email_ct": "mBbKlk}G7QdaGiNj"});
+
+        let result = assemble(columns.as_object().unwrap(), &profile());
+        assert!(matches!(result, Err(Error::InvalidStorage(_))));
+    }
+
+    #[test]
+    fn test_profile_rejects_shared_columns() {
+        let shared: StorageProfile = serde_json::from_value(json!({
+            "ciphertext": "email",
+            "metadata": "email",
+        }))
+        .unwrap();
+
+        let result = explode(&ciphertext_envelope().to_string(), &shared);
+        assert!(matches!(result, Err(Error::InvalidStorage(_))));
+    }
+}
@@ -0,0 +1,375 @@
+//! Caching of the encrypted values behind search terms.
+//!
+//! Query values repeat heavily, such as status enums and country filters, and every search
+//! term otherwise runs the full index pipeline and a ZeroKMS request. With the
+//! `search_term_cache` client option, `create_search_terms` keeps the encrypted value of each
+//! search term in a least recently used cache of `capacity` entries, each used for at most
+//! `ttl_ms` after it was created.
+//!
+//! Entries are keyed by an HMAC-SHA256 of the column, the encryption context, and the
+//! plaintext, under a random key generated for each cache and never exported. An unkeyed digest
+//! of a low-entropy plaintext, such as a boolean or an enum, could be inverted by hashing every
+//! candidate, so only the process holding the cache can relate its keys to plaintexts. The
+//! cache is shared with the clients derived from a client, except those derived with a
+//! different keyset, which start their own.
+
+use crate::encrypt_config::Identifier;
+use crate::{Encrypted, Error};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+/// The size and lifetime of a client's search term cache.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CacheConfig {
+    /// The most entries kept, after which the least recently used entry is evicted.
+    #[serde(default = "default_capacity")]
+    pub capacity: usize,
+    /// How long an entry is used after it was created, in milliseconds.
+    #[serde(default = "default_ttl_ms")]
+    pub ttl_ms: u64,
+}
+
+/// Default number of entries kept.
+fn default_capacity() -> usize {
+    1000
+}
+
+/// Default lifetime of an entry, in milliseconds.
+fn default_ttl_ms() -> u64 {
+    60_000
+}
+
+impl CacheConfig {
+    /// Check that the capacity and lifetime are positive.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first setting that is out of range.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.capacity == 0 {
+            return Err("`capacity` must be at least 1".to_string());
+        }
+        if self.ttl_ms == 0 {
+            return Err("`ttl_ms` must be at least 1".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// The key of a cache entry: an HMAC of the column, encryption context, and plaintext.
+pub type CacheKey = [u8; 32];
+
+/// A least recently used cache of encrypted search term values.
+pub struct TermCache {
+    config: CacheConfig,
+    /// The random key of the HMAC that entries are keyed by.
+    hmac_key: [u8; 32],
+    state: Mutex<State>,
+}
+
+impl std::fmt::Debug for TermCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TermCache")
+            .field("config", &self.config)
+            .field("hmac_key", &"<redacted>")
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+/// The entries of a cache, with their order of use and the cache's counts.
+#[derive(Debug, Default)]
+struct State {
+    entries: HashMap<CacheKey, Entry>,
+    /// The key of each entry by its last use, from least to most recent.
+    recency: BTreeMap<u64, CacheKey>,
+    next_use: u64,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+    expirations: u64,
+}
+
+/// A cached value, with when it was created and last used.
+#[derive(Debug)]
+struct Entry {
+    encrypted: Encrypted,
+    created: Instant,
+    last_use: u64,
+}
+
+impl State {
+    /// Record a use of the entry with a key, returning its new position in the order of use.
+    fn touch(&mut self, key: CacheKey, previous_use: Option<u64>) -> u64 {
+        if let Some(previous_use) = previous_use {
+            self.recency.remove(&previous_use);
+        }
+        let last_use = self.next_use;
+        self.next_use += 1;
+        self.recency.insert(last_use, key);
+        last_use
+    }
+
+    /// Remove the entry with a key.
+    fn remove(&mut self, key: &CacheKey) -> Option<Entry> {
+        let entry = self.entries.remove(key)?;
+        self.recency.remove(&entry.last_use);
+        Some(entry)
+    }
+}
+
+impl TermCache {
+    /// Create an empty cache with a random HMAC key.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Runtime`] if the operating system cannot provide random bytes.
+    pub fn new(config: CacheConfig) -> Result<Self, Error> {
+        let mut hmac_key = [0; 32];
+        getrandom::getrandom(&mut hmac_key)
+            .map_err(|error| Error::Runtime(format!("cannot generate a cache key: {error}")))?;
+
+        Ok(Self {
+            config,
+            hmac_key,
+            state: Mutex::new(State::default()),
+        })
+    }
+
+    /// The cache key of a search term's plaintext, encrypted for a column with the request's
+    /// encryption context.
+    pub fn key(
+        &self,
+        identifier: &Identifier,
+        context: Option<&Value>,
+        plaintext: &str,
+    ) -> CacheKey {
+        let context = context.map(Value::to_string).unwrap_or_default();
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.hmac_key)
+            .expect("HMAC accepts keys of any length");
+
+        // Each part is prefixed with its length, so no two sets of parts have the same input.
+        for part in [
+            identifier.table.as_str(),
+            identifier.column.as_str(),
+            &context,
+            plaintext,
+        ] {
+            mac.update(&(part.len() as u64).to_be_bytes());
+            mac.update(part.as_bytes());
+        }
+
+        mac.finalize().into_bytes().into()
+    }
+
+    /// The cached value of a key, if it has not expired.
+    pub fn get(&self, key: &CacheKey) -> Option<Encrypted> {
+        self.get_at(key, Instant::now())
+    }
+
+    /// Cache the value of a key, evicting the least recently used entry when the cache is full.
+    pub fn insert(&self, key: CacheKey, encrypted: Encrypted) {
+        self.insert_at(key, encrypted, Instant::now());
+    }
+
+    /// Remove every entry, returning how many were removed.
+    pub fn clear(&self) -> usize {
+        let mut state = self.lock();
+        let removed = state.entries.len();
+        state.entries.clear();
+        state.recency.clear();
+        removed
+    }
+
+    /// The cache's size and counts, for `get_metrics`.
+    pub fn metrics(&self) -> CacheMetrics {
+        let state = self.lock();
+
+        CacheMetrics {
+            entries: state.entries.len(),
+            capacity: self.config.capacity,
+            hits: state.hits,
+            misses: state.misses,
+            evictions: state.evictions,
+            expirations: state.expirations,
+        }
+    }
+
+    fn get_at(&self, key: &CacheKey, now: Instant) -> Option<Encrypted> {
+        let mut state = self.lock();
+        let ttl = Duration::from_millis(self.config.ttl_ms);
+
+        let Some(entry) = state.entries.get(key) else {
+            state.misses += 1;
+            return None;
+        };
+
+        if now.saturating_duration_since(entry.created) >= ttl {
+            state.remove(key);
+            state.expirations += 1;
+            state.misses += 1;
+            return None;
+        }
+
+        let previous_use = entry.last_use;
+        let last_use = state.touch(*key, Some(previous_use));
+        state.hits += 1;
+
+        let entry = state.entries.get_mut(key)?;
+        entry.last_use = last_use;
+        Some(entry.encrypted.clone())
+    }
+
+    fn insert_at(&self, key: CacheKey, encrypted: Encrypted, now: Instant) {
+        let mut state = self.lock();
+
+        state.remove(&key);
+        let last_use = state.touch(key, None);
+        state.entries.insert(
+            key,
+            Entry {
+                encrypted,
+                created: now,
+                last_use,
+            },
+        );
+
+        while state.entries.len() > self.config.capacity {
+            let Some((_, evicted)) = state.recency.pop_first() else {
+                break;
+            };
+            state.entries.remove(&evicted);
+            state.evictions += 1;
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// The size and counts of a search term cache, returned by `get_metrics`.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct CacheMetrics {
+    /// Entries in the cache.
+    pub entries: usize,
+    /// The `capacity` of the cache.
+    pub capacity: usize,
+    /// Search terms whose value was found in the cache.
+    pub hits: u64,
+    /// Search terms whose value was encrypted, including those whose entry had expired.
+    pub misses: u64,
+    /// Entries removed to make room for newer ones.
+    pub evictions: u64,
+    /// Entries removed because they were used after `ttl_ms`.
+    pub expirations: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encrypted(ciphertext: &str) -> Encrypted {
+        Encrypted::Ciphertext {
+            ciphertext: ciphertext.to_string(),
+            data_type: "text".to_string(),
+            unique_index: Some("abcd".to_string()),
+            ore_index: None,
+            match_index: None,
+            elements: None,
+            identifier: Identifier::new("users", "status"),
+            keyset: None,
+            expires_at: None,
+            version: 2,
+        }
+    }
+
+    fn ciphertext(encrypted: Option<Encrypted>) -> Option<String> {
+        match encrypted? {
+            Encrypted::Ciphertext { ciphertext, .. } => Some(ciphertext),
+            Encrypted::SteVec { .. } => None,
+        }
+    }
+
+    fn cache(capacity: usize, ttl_ms: u64) -> TermCache {
+        TermCache::new(CacheConfig { capacity, ttl_ms }).unwrap()
+    }
+
+    #[test]
+    fn test_key_separates_columns_and_contexts() {
+        let cache = cache(10, 60_000);
+        let identifier = Identifier::new("users", "status");
+        let context = serde_json::json!({"tag": ["tenant-a"]});
+
+        let plain = cache.key(&identifier, None, "active");
+        assert_eq!(plain, cache.key(&identifier, None, "active"));
+        assert_ne!(plain, cache.key(&identifier, Some(&context), "active"));
+        assert_ne!(
+            plain,
+            cache.key(&Identifier::new("users", "state"), None, "active")
+        );
+        assert_ne!(
+            cache.key(&Identifier::new("users", "ab"), None, "c"),
+            cache.key(&Identifier::new("users", "a"), None, "bc")
+        );
+    }
+
+    #[test]
+    fn test_key_is_keyed_per_cache() {
+        let identifier = Identifier::new("users", "active");
+
+        assert_ne!(
+            cache(10, 60_000).key(&identifier, None, "true"),
+            cache(10, 60_000).key(&identifier, None, "true")
+        );
+        assert!(!format!("{:?}", cache(10, 60_000)).contains("hmac_key: ["));
+    }
+
+    #[test]
+    fn test_least_recently_used_entry_is_evicted() {
+        let cache = cache(2, 60_000);
+        let identifier = Identifier::new("users", "status");
+        let [active, pending, closed] = ["active", "pending", "closed"]
+            .map(|plaintext| cache.key(&identifier, None, plaintext));
+
+        cache.insert(active, encrypted("a"));
+        cache.insert(pending, encrypted("p"));
+        assert_eq!(ciphertext(cache.get(&active)).as_deref(), Some("a"));
+
+        cache.insert(closed, encrypted("c"));
+        assert!(cache.get(&pending).is_none());
+        assert!(cache.get(&active).is_some());
+
+        let metrics = cache.metrics();
+        assert_eq!((metrics.entries, metrics.evictions), (2, 1));
+        assert_eq!((metrics.hits, metrics.misses), (2, 1));
+
+        assert_eq!(cache.clear(), 2);
+        assert_eq!(cache.metrics().entries, 0);
+    }
+
+    #[test]
+    fn test_entries_expire_after_ttl() {
+        let cache = cache(10, 1000);
+        let key = cache.key(&Identifier::new("users", "status"), None, "active");
+        let created = Instant::now();
+
+        cache.insert_at(key, encrypted("a"), created);
+        assert!(cache
+            .get_at(&key, created + Duration::from_millis(999))
+            .is_some());
+        assert!(cache
+            .get_at(&key, created + Duration::from_millis(1000))
+            .is_none());
+
+        let metrics = cache.metrics();
+        assert_eq!((metrics.entries, metrics.expirations), (0, 1));
+    }
+}
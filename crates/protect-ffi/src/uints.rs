@@ -0,0 +1,72 @@
+//! Validation of `big_uint` column plaintexts.
+//!
+//! Unsigned integers up to 128 bits, such as `u64` identifiers and 128-bit snowflakes, do not
+//! fit the signed 64-bit values that order-revealing encryption supports. They are encrypted
+//! as canonical decimal text, so `big_uint` columns support equality queries only.
+
+use crate::Error;
+
+/// Normalize a `big_uint` plaintext to its canonical decimal form.
+///
+/// Leading zeros are removed so that equal values produce identical `unique` index terms.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidUnsignedInteger`] if the plaintext is not a decimal integer between
+/// zero and 2^128 - 1.
+pub fn normalize(plaintext: &str) -> Result<String, Error> {
+    let plaintext = plaintext.trim();
+
+    if !plaintext.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(Error::InvalidUnsignedInteger(
+            "expected decimal digits without a sign",
+        ));
+    }
+
+    let value: u128 = plaintext
+        .parse()
+        .map_err(|_| Error::InvalidUnsignedInteger("expected a value between 0 and 2^128 - 1"))?;
+
+    Ok(value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_accepts_values_beyond_i64() {
+        assert_eq!(
+            normalize("18446744073709551615").unwrap(),
+            u64::MAX.to_string()
+        );
+        assert_eq!(
+            normalize("340282366920938463463374607431768211455").unwrap(),
+            u128::MAX.to_string()
+        );
+    }
+
+    #[test]
+    fn test_normalize_removes_leading_zeros() {
+        assert_eq!(normalize("0042").unwrap(), "42");
+        assert_eq!(normalize(" 0 ").unwrap(), "0");
+    }
+
+    #[test]
+    fn test_normalize_rejects_invalid_values() {
+        for plaintext in [
+            "-1",
+            "+1",
+            "1.5",
+            "1e3",
+            "",
+            "340282366920938463463374607431768211456",
+        ] {
+            let result = normalize(plaintext);
+            assert!(
+                matches!(result, Err(Error::InvalidUnsignedInteger(_))),
+                "{plaintext}"
+            );
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Non-fatal warnings about a client's configuration and the requests it serves.
+//!
+//! Warnings describe something that worked but deserves review, such as index options that
+//! weaken the protection of encrypted values, or request options that had no effect. Warnings
+//! about the configuration are collected when a client is created and returned by
+//! `get_client_warnings`. Warnings about a request are returned with its results when the
+//! request asks for them, so issues surface without failing production writes.
+
+use crate::encrypt_config::Identifier;
+use crate::lints::Lint;
+use serde::Serialize;
+
+/// A non-fatal warning.
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct Warning {
+    /// A stable identifier of the kind of warning.
+    pub code: &'static str,
+    /// A description of the warning.
+    pub message: String,
+    /// The position of the request item the warning is about, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+}
+
+impl Warning {
+    /// A `match` index that adds the whole value to its bloom filter.
+    pub fn include_original(identifier: &Identifier) -> Self {
+        Self {
+            code: "include_original",
+            message: format!(
+                "the `match` index of column `{}.{}` sets `include_original`, so its `bf` terms can confirm guesses of whole plaintexts",
+                identifier.table, identifier.column
+            ),
+            index: None,
+        }
+    }
+
+    /// A column whose type and index combination has a warning lint, with its description.
+    pub fn config_lint(identifier: &Identifier, lint: Lint, message: &str) -> Self {
+        Self {
+            code: lint.code(),
+            message: format!(
+                "column `{}.{}`: {message}",
+                identifier.table, identifier.column
+            ),
+            index: None,
+        }
+    }
+
+    /// Envelopes emitted in a schema version older than the current one.
+    pub fn deprecated_envelope_version(version: u16) -> Self {
+        Self {
+            code: "deprecated_envelope_version",
+            message: format!(
+                "envelopes were emitted in deprecated version {version}, set by the `emit_version` client option"
+            ),
+            index: None,
+        }
+    }
+
+    /// An item option that had no effect on the item's column.
+    pub fn option_ignored(index: usize, option: &str, identifier: &Identifier) -> Self {
+        Self {
+            code: "option_ignored",
+            message: format!(
+                "`{option}` was ignored because column `{}.{}` has no such index",
+                identifier.table, identifier.column
+            ),
+            index: Some(index),
+        }
+    }
+
+    /// A plaintext whose surrounding whitespace was removed by the column's `trim` option.
+    pub fn plaintext_trimmed(index: usize, identifier: &Identifier) -> Self {
+        Self {
+            code: "plaintext_trimmed",
+            message: format!(
+                "surrounding whitespace was removed by the `trim` option of column `{}.{}`",
+                identifier.table, identifier.column
+            ),
+            index: Some(index),
+        }
+    }
+
+    /// A plaintext whose `match` index terms were created from its leading part only, because
+    /// it is over the `max_tokens` or `max_input_bytes` of the index.
+    pub fn match_input_truncated(index: usize, identifier: &Identifier) -> Self {
+        Self {
+            code: "match_input_truncated",
+            message: format!(
+                "only the leading part of the plaintext was added to the `match` index of column `{}.{}`, which limits its tokens",
+                identifier.table, identifier.column
+            ),
+            index: Some(index),
+        }
+    }
+}
@@ -6,14 +6,70 @@
  * All functions declared here are exposed by the underlying Rust library.
  */
 
+#include <stddef.h>
 #include <stdint.h>
 
 typedef struct Client Client;
+typedef struct Job Job;
+typedef void (*JobCallback)(void* user_data, char* result, char* error);
+uint32_t protect_ffi_abi_version(void);
+int32_t selfcheck(uint32_t abi_version, size_t pointer_size, size_t int32_size, JobCallback callback, void* user_data, char** error_out);
 Client* new_client(const char* config_json, char** error_out);
+Client* new_client_with_options(const char* config_json, const char* options_json, char** error_out);
 char* encrypt(const Client* client, const char* plaintext, const char* column, const char* table, const char* context_json, char** error_out);
 char* decrypt(const Client* client, const char* ciphertext, const char* context_json, char** error_out);
 char* encrypt_bulk(const Client* client, const char* items_json, char** error_out);
+char* encrypt_bulk_with_options(const Client* client, const char* items_json, const char* options_json, char** error_out);
 char* decrypt_bulk(const Client* client, const char* items_json, char** error_out);
 char* create_search_terms(const Client* client, const char* terms_json, char** error_out);
+char* create_search_terms_with_options(const Client* client, const char* terms_json, const char* options_json, char** error_out);
+char* format_envelope(const char* envelope_json, const char* options_json, char** error_out);
+char* validate_envelope(const char* envelope_json, char** error_out);
+char* convert_envelope(const Client* client, const char* envelope_json, uint16_t version, char** error_out);
+char* upgrade_envelope(const Client* client, const char* envelope_json, char** error_out);
+char* reindex_search_terms(const Client* client, const char* items_json, char** error_out);
+char* get_key_info(const Client* client, char** error_out);
+char* export_client_state(const Client* client, char** error_out);
+int32_t assert_config_matches(const Client* client, const char* config_digest, char** error_out);
+char* import_client_state(const Client* client, const char* state_json, char** error_out);
+char* encrypt_json_paths(const Client* client, const char* document_json, const char* paths_json, const char* context_json, char** error_out);
+char* decrypt_json_paths(const Client* client, const char* document_json, const char* paths_json, const char* context_json, char** error_out);
+char* explode_envelope(const char* envelope_json, const char* profile_json, char** error_out);
+char* assemble_envelope(const char* columns_json, const char* profile_json, char** error_out);
+char* run_benchmark(const Client* client, const char* spec_json, char** error_out);
+char* estimate_storage(const char* config_json, const char* sample_values_json, char** error_out);
+char* plan_encrypt(const Client* client, const char* items_json, char** error_out);
+char* get_client_warnings(const Client* client, char** error_out);
+char* validate_config(const char* config, const char* format, char** error_out);
+char* get_config_digest(const char* config, const char* format, char** error_out);
+char* get_startup_diagnostics(char** error_out);
+char* get_metrics(const Client* client, char** error_out);
+int32_t clear_search_term_cache(const Client* client, char** error_out);
+char* ping_cts(const Client* client, char** error_out);
+char* ping_zerokms(const Client* client, char** error_out);
+char* get_network_policy(const Client* client, char** error_out);
+char* decrypt_single_field(const Client* client, const char* envelope_json, const char* context_json, char** error_out);
+char* tokenize_selector(const Client* client, const char* column, const char* table, const char* path, char** error_out);
+char* explain_query_capability(const Client* client, const char* column, const char* table, const char* operator, const char* path, char** error_out);
+int32_t compare_terms(const char* term_a, const char* term_b, char** error_out);
+Client* derive_client(const Client* client, const char* overlay_json, char** error_out);
+char* expand_context(const Client* client, const char* context_json, char** error_out);
+Job* encrypt_async(const Client* client, const char* plaintext, const char* column, const char* table, const char* context_json, char** error_out);
+Job* decrypt_async(const Client* client, const char* ciphertext, const char* context_json, char** error_out);
+int32_t job_poll(const Job* job, char** error_out);
+char* job_result(Job* job, char** error_out);
+int32_t job_on_complete(const Job* job, JobCallback callback, void* user_data, char** error_out);
+void job_free(Job* job);
 void free_client(Client* client);
 void free_string(char* s);
+char* get_last_error(void);
+char* get_last_error_chain(void);
+char* get_last_error_code(void);
+char* get_last_error_request_id(void);
+char* get_last_error_retry_hint(void);
+char* get_error_catalog(char** error_out);
+char* list_functions(char** error_out);
+void clear_last_error(void);
+void set_invalid_pointer_handler(void (*handler)(const char* message));
+void set_deprecation_handler(void (*handler)(const char* name, const char* replacement));
+int32_t set_crash_report_path(const char* path, char** error_out);
@@ -25,6 +25,12 @@ class Client
      */
     private bool $initialized = false;
 
+    /**
+     * The callback the native library reports calls to deprecated functions through, kept
+     * referenced for as long as it is registered.
+     */
+    private static ?\Closure $deprecationHandler = null;
+
     /**
      * Create a new client instance.
      *
@@ -37,6 +43,10 @@ class Client
         try {
             $this->ffi = $this->createFFIInstance();
 
+            $this->verifyNativeLibrary();
+
+            $this->registerDeprecationHandler();
+
             $this->initialized = true;
         } catch (Throwable $e) {
             throw FFIException::failedToInitializeClient($e->getMessage());
@@ -48,14 +58,15 @@ class Client
      *
      * Authentication is handled through environment variables.
      *
-     * @param  string  $configJson  Encryption configuration as a JSON string
+     * @param  string  $configJson  Encryption configuration as a JSON, YAML, or TOML string
+     * @param  string|null  $optionsJson  Client options as a JSON string
      *
      * @throws FFIException When client creation fails
      */
-    public function newClient(string $configJson): \FFI\CData
+    public function newClient(string $configJson, ?string $optionsJson = null): \FFI\CData
     {
-        $client = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($configJson): ?\FFI\CData {
-            $result = $this->ffi->new_client($configJson, \FFI::addr($errorPtr));
+        $client = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($configJson, $optionsJson): ?\FFI\CData {
+            $result = $this->ffi->new_client_with_options($configJson, $optionsJson, \FFI::addr($errorPtr));
 
             return $result instanceof \FFI\CData ? $result : null;
         }, FFIException::clientCreationFailed(...));
@@ -125,14 +136,15 @@ class Client
      * Encrypt multiple values in a single batch operation.
      *
      * @param  string  $itemsJson  Items to encrypt as a JSON string
+     * @param  string|null  $optionsJson  Bulk encryption options as a JSON string
      * @return string Encrypted envelopes as a JSON string
      *
      * @throws FFIException When encryption fails
      */
-    public function encryptBulk(\FFI\CData $client, string $itemsJson): string
+    public function encryptBulk(\FFI\CData $client, string $itemsJson, ?string $optionsJson = null): string
     {
-        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client, $itemsJson): ?\FFI\CData {
-            $result = $this->ffi->encrypt_bulk($client, $itemsJson, \FFI::addr($errorPtr));
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client, $itemsJson, $optionsJson): ?\FFI\CData {
+            $result = $this->ffi->encrypt_bulk_with_options($client, $itemsJson, $optionsJson, \FFI::addr($errorPtr));
 
             return $result instanceof \FFI\CData ? $result : null;
         }, FFIException::failedToBulkEncrypt(...));
@@ -171,14 +183,15 @@ class Client
      * Create search terms for querying encrypted data.
      *
      * @param  string  $itemsJson  Items to create search terms for as a JSON string
+     * @param  string|null  $optionsJson  Search term output options as a JSON string
      * @return string Search terms as a JSON string
      *
      * @throws FFIException When search term creation fails
      */
-    public function createSearchTerms(\FFI\CData $client, string $itemsJson): string
+    public function createSearchTerms(\FFI\CData $client, string $itemsJson, ?string $optionsJson = null): string
     {
-        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client, $itemsJson): ?\FFI\CData {
-            $result = $this->ffi->create_search_terms($client, $itemsJson, \FFI::addr($errorPtr));
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client, $itemsJson, $optionsJson): ?\FFI\CData {
+            $result = $this->ffi->create_search_terms_with_options($client, $itemsJson, $optionsJson, \FFI::addr($errorPtr));
 
             return $result instanceof \FFI\CData ? $result : null;
         }, FFIException::failedToCreateSearchTerms(...));
@@ -191,30 +204,197 @@ class Client
     }
 
     /**
-     * Release the client instance and free associated resources.
+     * Render an encrypted envelope as a human-readable summary.
      *
-     * This method should be called when you're done with the client
-     * to prevent memory leaks and properly clean up resources.
+     * @param  string  $envelopeJson  Encrypted envelope as a JSON string
+     * @param  string|null  $optionsJson  Rendering options as a JSON string
+     * @return string Rendered envelope summary
+     *
+     * @throws FFIException When the envelope cannot be formatted
      */
-    public function freeClient(\FFI\CData $client): void
+    public function formatEnvelope(string $envelopeJson, ?string $optionsJson = null): string
     {
-        try {
-            $this->ffi->free_client($client);
-        } catch (Throwable) {
-            // Silently ignore any exceptions during cleanup
-        }
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($envelopeJson, $optionsJson): ?\FFI\CData {
+            $result = $this->ffi->format_envelope($envelopeJson, $optionsJson, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToFormatEnvelope(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
     }
 
     /**
-     * Execute an FFI operation with error handling.
+     * Validate a JSON blob against the encrypted envelope schema.
      *
-     * @param  callable(\FFI\CData $errorPtr): ?\FFI\CData  $operation
-     * @param  callable(string $message): FFIException  $createException
-     * @return \FFI\CData FFI pointer result
+     * @param  string  $envelopeJson  Envelope to validate as a JSON string
+     * @return string Validation report with field-level problems as a JSON string
      *
-     * @throws FFIException When client is not initialized or FFI operation fails
+     * @throws FFIException When the envelope cannot be validated
      */
-    private function executeFFIOperation(callable $operation, callable $createException): \FFI\CData
+    public function validateEnvelope(string $envelopeJson): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($envelopeJson): ?\FFI\CData {
+            $result = $this->ffi->validate_envelope($envelopeJson, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToValidateEnvelope(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Convert an encrypted envelope to another schema version.
+     *
+     * @param  string  $envelopeJson  Encrypted envelope as a JSON string
+     * @param  int  $version  Target envelope schema version
+     * @return string Converted envelope as a JSON string
+     *
+     * @throws FFIException When the envelope cannot be converted
+     */
+    public function convertEnvelope(\FFI\CData $client, string $envelopeJson, int $version): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client, $envelopeJson, $version): ?\FFI\CData {
+            $result = $this->ffi->convert_envelope($client, $envelopeJson, $version, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToConvertEnvelope(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Upgrade an encrypted envelope to the current schema version without decrypting it.
+     *
+     * @param  string  $envelopeJson  Encrypted envelope as a JSON string
+     * @return string Upgrade outcome with the upgraded envelope as a JSON string
+     *
+     * @throws FFIException When the envelope cannot be upgraded
+     */
+    public function upgradeEnvelope(\FFI\CData $client, string $envelopeJson): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client, $envelopeJson): ?\FFI\CData {
+            $result = $this->ffi->upgrade_envelope($client, $envelopeJson, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToUpgradeEnvelope(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Regenerate the match index terms of stored envelopes after the column's match index changes.
+     *
+     * @param  string  $itemsJson  Row identifiers and stored envelopes as a JSON string
+     * @return string Merge patches for each row's envelope as a JSON string
+     *
+     * @throws FFIException When the envelopes cannot be reindexed
+     */
+    public function reindexSearchTerms(\FFI\CData $client, string $itemsJson): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client, $itemsJson): ?\FFI\CData {
+            $result = $this->ffi->reindex_search_terms($client, $itemsJson, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToReindexSearchTerms(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Get metadata for the keysets the client encrypts under.
+     *
+     * @return string Keyset metadata as a JSON string
+     *
+     * @throws FFIException When the keysets cannot be listed
+     */
+    public function getKeyInfo(\FFI\CData $client): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client): ?\FFI\CData {
+            $result = $this->ffi->get_key_info($client, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToGetKeyInfo(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Export a snapshot of the client's non-secret state, such as its configuration digest and resolved endpoints.
+     *
+     * @return string Client state as a JSON string
+     *
+     * @throws FFIException When the state cannot be exported
+     */
+    public function exportClientState(\FFI\CData $client): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client): ?\FFI\CData {
+            $result = $this->ffi->export_client_state($client, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToExportClientState(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Compare a client state snapshot with the client.
+     *
+     * @param  string  $stateJson  Client state from exportClientState() as a JSON string
+     * @return string Comparison with `compatible`, `differences`, and `age_secs` as a JSON string
+     *
+     * @throws FFIException When the snapshot cannot be parsed
+     */
+    public function importClientState(\FFI\CData $client, string $stateJson): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client, $stateJson): ?\FFI\CData {
+            $result = $this->ffi->import_client_state($client, $stateJson, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToImportClientState(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Check that a client was created with the encryption configuration the caller expects.
+     *
+     * @param  string  $configDigest  Digest of the expected configuration from getConfigDigest()
+     *
+     * @throws FFIException When the client was created with a different configuration
+     */
+    public function assertConfigMatches(\FFI\CData $client, string $configDigest): void
     {
         if (! $this->isInitialized()) {
             throw FFIException::clientNotInitialized();
@@ -223,43 +403,1000 @@ class Client
         $errorPtr = $this->createStringPointer();
 
         try {
-            $result = $operation($errorPtr);
-
-            if ($result === null) {
-                $message = $this->convertStringPointer($errorPtr);
-
-                throw $createException($message);
+            if ($this->ffi->assert_config_matches($client, $configDigest, \FFI::addr($errorPtr)) < 0) {
+                throw FFIException::failedToAssertConfigMatches($this->convertStringPointer($errorPtr));
             }
-
-            return $result;
-        } catch (FFIException $e) {
-            throw $e;
-        } catch (Throwable $e) {
-            throw $createException($e->getMessage());
         } finally {
             $this->freeStringPointer($errorPtr);
         }
     }
 
     /**
-     * Check if the client has been initialized.
+     * Get the error message from the most recent failed operation on the current thread.
+     *
+     * Operations throw an exception on failure, so this is only needed when calling
+     * the native library directly without an error output pointer.
+     *
+     * @return string|null Error message, or null if the most recent operation succeeded
+     *
+     * @throws FFIException When the client is not initialized
      */
-    private function isInitialized(): bool
+    public function getLastError(): ?string
     {
-        return $this->initialized;
+        if (! $this->isInitialized()) {
+            throw FFIException::clientNotInitialized();
+        }
+
+        $errorPtr = $this->ffi->get_last_error();
+
+        if (! $errorPtr instanceof \FFI\CData) {
+            return null;
+        }
+
+        $message = $this->convertStringPointer($errorPtr);
+
+        $this->freeStringPointer($errorPtr);
+
+        return $message;
     }
 
     /**
-     * Create the FFI instance with library and header definitions.
+     * Get the messages of the most recent error on the current thread and its underlying causes.
      *
-     * @throws FFIException When FFI instance creation fails
+     * @return list<string>|null Error messages, outermost first, or null if the most recent operation succeeded
+     *
+     * @throws FFIException When the client is not initialized
      */
-    private function createFFIInstance(): \FFI
+    public function getLastErrorChain(): ?array
     {
-        $libraryPath = Loader::getLibraryPath();
-        $headerContent = $this->loadHeaderFile();
+        if (! $this->isInitialized()) {
+            throw FFIException::clientNotInitialized();
+        }
 
-        return \FFI::cdef($headerContent, $libraryPath);
+        $chainPtr = $this->ffi->get_last_error_chain();
+
+        if (! $chainPtr instanceof \FFI\CData) {
+            return null;
+        }
+
+        $chainJson = $this->convertStringPointer($chainPtr);
+
+        $this->freeStringPointer($chainPtr);
+
+        /** @var list<string> */
+        return json_decode($chainJson, associative: true, flags: JSON_THROW_ON_ERROR);
+    }
+
+    /**
+     * Get the catalog code of the most recent error on the current thread, such as `rate_limited`.
+     *
+     * @return string|null Error code, or null if the most recent operation succeeded
+     *
+     * @throws FFIException When the client is not initialized
+     */
+    public function getLastErrorCode(): ?string
+    {
+        if (! $this->isInitialized()) {
+            throw FFIException::clientNotInitialized();
+        }
+
+        $codePtr = $this->ffi->get_last_error_code();
+
+        if (! $codePtr instanceof \FFI\CData) {
+            return null;
+        }
+
+        $code = $this->convertStringPointer($codePtr);
+
+        $this->freeStringPointer($codePtr);
+
+        return $code;
+    }
+
+    /**
+     * Get the server-side ID of the ZeroKMS or CTS request that caused the most recent error on the current thread.
+     *
+     * Include the ID when reporting a failed request to CipherStash support.
+     *
+     * @return string|null Request ID, or null if the most recent operation succeeded or its error reported no request ID
+     *
+     * @throws FFIException When the client is not initialized
+     */
+    public function getLastErrorRequestId(): ?string
+    {
+        if (! $this->isInitialized()) {
+            throw FFIException::clientNotInitialized();
+        }
+
+        $requestIdPtr = $this->ffi->get_last_error_request_id();
+
+        if (! $requestIdPtr instanceof \FFI\CData) {
+            return null;
+        }
+
+        $requestId = $this->convertStringPointer($requestIdPtr);
+
+        $this->freeStringPointer($requestIdPtr);
+
+        return $requestId;
+    }
+
+    /**
+     * Get how long to back off after the most recent error on the current thread, and the quota it exceeded.
+     *
+     * @return array{retry_after_ms?: int, quota?: array{limit?: int, remaining?: int, reset_ms?: int}}|null Backoff hints, or null if the most recent operation succeeded or its error reported none
+     *
+     * @throws FFIException When the client is not initialized
+     */
+    public function getLastErrorRetryHint(): ?array
+    {
+        if (! $this->isInitialized()) {
+            throw FFIException::clientNotInitialized();
+        }
+
+        $hintPtr = $this->ffi->get_last_error_retry_hint();
+
+        if (! $hintPtr instanceof \FFI\CData) {
+            return null;
+        }
+
+        $hintJson = $this->convertStringPointer($hintPtr);
+
+        $this->freeStringPointer($hintPtr);
+
+        /** @var array{retry_after_ms?: int, quota?: array{limit?: int, remaining?: int, reset_ms?: int}} */
+        return json_decode($hintJson, associative: true, flags: JSON_THROW_ON_ERROR);
+    }
+
+    /**
+     * Get every error code the native library can report, with its retryability and suggested HTTP status.
+     *
+     * @return string Error catalog as a JSON array
+     *
+     * @throws FFIException When the catalog cannot be retrieved
+     */
+    public function getErrorCatalog(): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr): ?\FFI\CData {
+            $result = $this->ffi->get_error_catalog(\FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToGetErrorCatalog(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Get a manifest of the functions the native library exports, with their parameters and the release that added each.
+     *
+     * @return string Function manifest as a JSON object
+     *
+     * @throws FFIException When the manifest cannot be retrieved
+     */
+    public function listFunctions(): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr): ?\FFI\CData {
+            $result = $this->ffi->list_functions(\FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToListFunctions(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Clear the error recorded by the most recent failed operation on the current thread.
+     *
+     * @throws FFIException When the client is not initialized
+     */
+    public function clearLastError(): void
+    {
+        if (! $this->isInitialized()) {
+            throw FFIException::clientNotInitialized();
+        }
+
+        $this->ffi->clear_last_error();
+    }
+
+    /**
+     * Set the file a crash report is appended to whenever the native library panics, or stop writing reports.
+     *
+     * @param  string|null  $path  Crash report file path, or null to stop writing reports
+     *
+     * @throws FFIException When the path cannot be set
+     */
+    public function setCrashReportPath(?string $path): void
+    {
+        if (! $this->isInitialized()) {
+            throw FFIException::clientNotInitialized();
+        }
+
+        $errorPtr = $this->createStringPointer();
+
+        try {
+            if ($this->ffi->set_crash_report_path($path, \FFI::addr($errorPtr)) < 0) {
+                throw FFIException::failedToSetCrashReportPath($this->convertStringPointer($errorPtr));
+            }
+        } finally {
+            $this->freeStringPointer($errorPtr);
+        }
+    }
+
+    /**
+     * Encrypt selected fields of a JSON document, leaving the rest in plaintext.
+     *
+     * @param  string  $documentJson  JSON document as a string
+     * @param  string  $pathsJson  Fields to encrypt as a JSON array of path, table, and column objects
+     * @param  string|null  $contextJson  Encryption context as a JSON string
+     * @return string JSON document with the selected fields replaced by encrypted envelopes
+     *
+     * @throws FFIException When encryption fails
+     */
+    public function encryptJsonPaths(\FFI\CData $client, string $documentJson, string $pathsJson, ?string $contextJson = null): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client, $documentJson, $pathsJson, $contextJson): ?\FFI\CData {
+            $result = $this->ffi->encrypt_json_paths($client, $documentJson, $pathsJson, $contextJson, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToEncryptJsonPaths(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Decrypt selected fields of a JSON document encrypted with encryptJsonPaths().
+     *
+     * @param  string  $documentJson  JSON document as a string
+     * @param  string  $pathsJson  Fields to decrypt as a JSON array of path strings
+     * @param  string|null  $contextJson  Encryption context as a JSON string
+     * @return string JSON document with the selected fields decrypted
+     *
+     * @throws FFIException When decryption fails
+     */
+    public function decryptJsonPaths(\FFI\CData $client, string $documentJson, string $pathsJson, ?string $contextJson = null): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client, $documentJson, $pathsJson, $contextJson): ?\FFI\CData {
+            $result = $this->ffi->decrypt_json_paths($client, $documentJson, $pathsJson, $contextJson, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToDecryptJsonPaths(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Split an encrypted envelope into the columns of a storage profile.
+     *
+     * @param  string  $envelopeJson  Encrypted envelope as a JSON string
+     * @param  string  $profileJson  Storage profile column names as a JSON string
+     * @return string Column names and values as a JSON string
+     *
+     * @throws FFIException When the envelope cannot be split
+     */
+    public function explodeEnvelope(string $envelopeJson, string $profileJson): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($envelopeJson, $profileJson): ?\FFI\CData {
+            $result = $this->ffi->explode_envelope($envelopeJson, $profileJson, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToExplodeEnvelope(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Assemble an encrypted envelope from the columns of a storage profile.
+     *
+     * @param  string  $columnsJson  Column names and values as a JSON string
+     * @param  string  $profileJson  Storage profile column names as a JSON string
+     * @return string Encrypted envelope as a JSON string
+     *
+     * @throws FFIException When the envelope cannot be assembled
+     */
+    public function assembleEnvelope(string $columnsJson, string $profileJson): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($columnsJson, $profileJson): ?\FFI\CData {
+            $result = $this->ffi->assemble_envelope($columnsJson, $profileJson, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToAssembleEnvelope(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Measure the throughput of an operation on this host.
+     *
+     * @param  string  $specJson  Benchmark spec as a JSON string
+     * @return string Benchmark report as a JSON string
+     *
+     * @throws FFIException When the benchmark cannot be run
+     */
+    public function runBenchmark(\FFI\CData $client, string $specJson): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client, $specJson): ?\FFI\CData {
+            $result = $this->ffi->run_benchmark($client, $specJson, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToRunBenchmark(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Estimate the storage size of sample values under an encryption configuration without contacting ZeroKMS.
+     *
+     * @param  string  $configJson  Encryption configuration as a JSON string
+     * @param  string  $sampleValuesJson  Sample values as a JSON string
+     * @return string Storage estimate as a JSON string
+     *
+     * @throws FFIException When the configuration or samples are invalid
+     */
+    public function estimateStorage(string $configJson, string $sampleValuesJson): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($configJson, $sampleValuesJson): ?\FFI\CData {
+            $result = $this->ffi->estimate_storage($configJson, $sampleValuesJson, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToEstimateStorage(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Check items to encrypt against the configuration without encrypting them.
+     *
+     * @param  string  $itemsJson  Items to encrypt as a JSON string
+     * @return string Encryption plan as a JSON string
+     *
+     * @throws FFIException When the items cannot be parsed
+     */
+    public function planEncrypt(\FFI\CData $client, string $itemsJson): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client, $itemsJson): ?\FFI\CData {
+            $result = $this->ffi->plan_encrypt($client, $itemsJson, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToPlanEncrypt(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Get the non-fatal warnings about the client's configuration.
+     *
+     * @return string Warnings as a JSON array
+     *
+     * @throws FFIException When the warnings cannot be retrieved
+     */
+    public function getClientWarnings(\FFI\CData $client): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client): ?\FFI\CData {
+            $result = $this->ffi->get_client_warnings($client, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToGetClientWarnings(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Validate an encryption configuration without creating a client.
+     *
+     * @param  string  $config  Encryption configuration as a JSON, YAML, or TOML string
+     * @param  string|null  $format  Format of the configuration (`json`, `yaml`, or `toml`), detected when null
+     * @return string Warnings a client created from the configuration would have, as a JSON array
+     *
+     * @throws FFIException When the configuration is invalid
+     */
+    public function validateConfig(string $config, ?string $format = null): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($config, $format): ?\FFI\CData {
+            $result = $this->ffi->validate_config($config, $format, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToValidateConfig(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Get the digest of an encryption configuration without creating a client.
+     *
+     * @param  string  $config  Encryption configuration as a JSON, YAML, or TOML string
+     * @param  string|null  $format  Format of the configuration (`json`, `yaml`, or `toml`), detected when null
+     * @return string SHA-256 digest of the configuration in hex
+     *
+     * @throws FFIException When the configuration is invalid
+     */
+    public function getConfigDigest(string $config, ?string $format = null): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($config, $format): ?\FFI\CData {
+            $result = $this->ffi->get_config_digest($config, $format, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToGetConfigDigest(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Get the configuration sources and environment variables a client reads at startup, without their values.
+     *
+     * @return string Startup diagnostics as a JSON object
+     *
+     * @throws FFIException When the diagnostics cannot be collected
+     */
+    public function getStartupDiagnostics(): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr): ?\FFI\CData {
+            $result = $this->ffi->get_startup_diagnostics(\FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToGetStartupDiagnostics(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Get the operation counts of the client and the shared runtime.
+     *
+     * @return string Metrics as a JSON object
+     *
+     * @throws FFIException When the metrics cannot be retrieved
+     */
+    public function getMetrics(\FFI\CData $client): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client): ?\FFI\CData {
+            $result = $this->ffi->get_metrics($client, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToGetMetrics(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Remove every entry from the client's search term cache, including the entries of clients derived under the same keyset.
+     *
+     * @return int Number of entries removed
+     *
+     * @throws FFIException When the client pointer is invalid
+     */
+    public function clearSearchTermCache(\FFI\CData $client): int
+    {
+        if (! $this->isInitialized()) {
+            throw FFIException::clientNotInitialized();
+        }
+
+        $errorPtr = $this->createStringPointer();
+
+        try {
+            $removed = $this->ffi->clear_search_term_cache($client, \FFI::addr($errorPtr));
+
+            if ($removed < 0) {
+                throw FFIException::failedToClearSearchTermCache($this->convertStringPointer($errorPtr));
+            }
+
+            return $removed;
+        } finally {
+            $this->freeStringPointer($errorPtr);
+        }
+    }
+
+    /**
+     * Check that CTS issues service tokens, reporting its endpoint, addresses, and latency.
+     *
+     * @return string Probe result as a JSON object
+     *
+     * @throws FFIException When the client pointer is invalid
+     */
+    public function pingCts(\FFI\CData $client): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client): ?\FFI\CData {
+            $result = $this->ffi->ping_cts($client, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToPingService(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Check that ZeroKMS responds to the client, reporting its endpoint, addresses, and latency.
+     *
+     * @return string Probe result as a JSON object
+     *
+     * @throws FFIException When the client pointer is invalid
+     */
+    public function pingZeroKms(\FFI\CData $client): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client): ?\FFI\CData {
+            $result = $this->ffi->ping_zerokms($client, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToPingService(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Get the network destinations the client may contact, and whether it refuses all others.
+     *
+     * @return string Network policy as a JSON object
+     *
+     * @throws FFIException When the client pointer is invalid
+     */
+    public function getNetworkPolicy(\FFI\CData $client): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client): ?\FFI\CData {
+            $result = $this->ffi->get_network_policy($client, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToGetNetworkPolicy(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Decrypt the full JSON document of a `jsonb` column from its stored `sv` envelope.
+     *
+     * @param  string  $envelopeJson  Stored `sv` envelope as a JSON string
+     * @param  string|null  $contextJson  Decryption context as a JSON string
+     * @return string The decrypted JSON document as a JSON string
+     *
+     * @throws FFIException When decryption fails
+     */
+    public function decryptSingleField(\FFI\CData $client, string $envelopeJson, ?string $contextJson = null): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client, $envelopeJson, $contextJson): ?\FFI\CData {
+            $result = $this->ffi->decrypt_single_field($client, $envelopeJson, $contextJson, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToDecrypt(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Derive the tokenized selector of a JSON path under a `jsonb` column's `ste_vec` prefix.
+     *
+     * @param  string  $column  Column name
+     * @param  string  $table  Table name
+     * @param  string  $path  JSON path, such as `$.profile.email`
+     * @return string The encoded selector
+     *
+     * @throws FFIException When the selector cannot be derived
+     */
+    public function tokenizeSelector(\FFI\CData $client, string $column, string $table, string $path): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client, $column, $table, $path): ?\FFI\CData {
+            $result = $this->ffi->tokenize_selector($client, $column, $table, $path, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToTokenizeSelector(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Explain whether a column's indexes support a query operator, and which index and term a query uses.
+     *
+     * @param  string  $column  Column name
+     * @param  string  $table  Table name
+     * @param  string  $operator  SQL operator, such as `=`, `<`, `LIKE`, or `@>`
+     * @param  string|null  $path  JSON path of a field inside `jsonb` documents, such as `$.profile.email`
+     * @return string Query capability as a JSON object
+     *
+     * @throws FFIException When the column is unknown or the operator is not recognized
+     */
+    public function explainQueryCapability(\FFI\CData $client, string $column, string $table, string $operator, ?string $path = null): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client, $column, $table, $operator, $path): ?\FFI\CData {
+            $result = $this->ffi->explain_query_capability($client, $column, $table, $operator, $path, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToExplainQueryCapability(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Compare two index terms, such as the `hm` terms of two envelopes, in constant time.
+     *
+     * @param  string  $termA  Hex or `b64:` prefixed base64 index term
+     * @param  string  $termB  Hex or `b64:` prefixed base64 index term
+     * @return bool Whether the terms are equal
+     *
+     * @throws FFIException When either term is neither hex nor prefixed base64
+     */
+    public function compareTerms(string $termA, string $termB): bool
+    {
+        if (! $this->isInitialized()) {
+            throw FFIException::clientNotInitialized();
+        }
+
+        $errorPtr = $this->createStringPointer();
+
+        try {
+            $status = $this->ffi->compare_terms($termA, $termB, \FFI::addr($errorPtr));
+
+            if ($status < 0) {
+                throw FFIException::failedToCompareTerms($this->convertStringPointer($errorPtr));
+            }
+
+            return $status === 1;
+        } finally {
+            $this->freeStringPointer($errorPtr);
+        }
+    }
+
+    /**
+     * Derive a client that shares an existing client's ciphers and connections, with a different default context or keyset.
+     *
+     * @param  string  $overlayJson  Client overlay as a JSON string, with optional `context` and `keyset` fields
+     *
+     * @throws FFIException When the overlay is invalid or client derivation fails
+     */
+    public function deriveClient(\FFI\CData $client, string $overlayJson): \FFI\CData
+    {
+        $derived = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client, $overlayJson): ?\FFI\CData {
+            $result = $this->ffi->derive_client($client, $overlayJson, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToDeriveClient(...));
+
+        return $derived;
+    }
+
+    /**
+     * Expand a reference to a context preset into the encryption context it stands for.
+     *
+     * @param  string  $contextJson  Encryption context as a JSON string, such as `"@tenant_scoped"` or `{"@tenant_scoped": {"tenant": "acme"}}`
+     * @return string Expanded encryption context as a JSON string
+     *
+     * @throws FFIException When the context is invalid or references an unknown preset
+     */
+    public function expandContext(\FFI\CData $client, string $contextJson): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client, $contextJson): ?\FFI\CData {
+            $result = $this->ffi->expand_context($client, $contextJson, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToExpandContext(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Start encrypting plaintext for a specific table column without blocking.
+     *
+     * Poll the returned job with `jobPoll()`, take its result with `jobResult()`, and free it with `jobFree()`.
+     *
+     * @param  string|null  $contextJson  Encryption context as a JSON string
+     * @return \FFI\CData Job handle whose result is the encrypted envelope as a JSON string
+     *
+     * @throws FFIException When the encryption job cannot be started
+     */
+    public function encryptAsync(\FFI\CData $client, string $plaintext, string $column, string $table, ?string $contextJson = null): \FFI\CData
+    {
+        return $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client, $plaintext, $column, $table, $contextJson): ?\FFI\CData {
+            $result = $this->ffi->encrypt_async(
+                $client,
+                $plaintext,
+                $column,
+                $table,
+                $contextJson,
+                \FFI::addr($errorPtr)
+            );
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToEncrypt(...));
+    }
+
+    /**
+     * Start decrypting ciphertext without blocking.
+     *
+     * Poll the returned job with `jobPoll()`, take its result with `jobResult()`, and free it with `jobFree()`.
+     *
+     * @param  string|null  $contextJson  Decryption context as a JSON string
+     * @return \FFI\CData Job handle whose result is the decrypted plaintext
+     *
+     * @throws FFIException When the decryption job cannot be started
+     */
+    public function decryptAsync(\FFI\CData $client, string $ciphertext, ?string $contextJson = null): \FFI\CData
+    {
+        return $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($client, $ciphertext, $contextJson): ?\FFI\CData {
+            $result = $this->ffi->decrypt_async(
+                $client,
+                $ciphertext,
+                $contextJson,
+                \FFI::addr($errorPtr)
+            );
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToDecrypt(...));
+    }
+
+    /**
+     * Check whether the result of a job is ready, without blocking.
+     *
+     * @throws FFIException When the job is invalid or its result has already been taken
+     */
+    public function jobPoll(\FFI\CData $job): bool
+    {
+        if (! $this->isInitialized()) {
+            throw FFIException::clientNotInitialized();
+        }
+
+        $errorPtr = $this->createStringPointer();
+
+        try {
+            $status = $this->ffi->job_poll($job, \FFI::addr($errorPtr));
+
+            if ($status < 0) {
+                throw FFIException::failedToRunJob($this->convertStringPointer($errorPtr));
+            }
+
+            return $status === 1;
+        } finally {
+            $this->freeStringPointer($errorPtr);
+        }
+    }
+
+    /**
+     * Take the result of a finished job, which can only be taken once.
+     *
+     * @return string The result of the job's operation
+     *
+     * @throws FFIException When the job is still running, its result has already been taken, or its operation failed
+     */
+    public function jobResult(\FFI\CData $job): string
+    {
+        $resultPtr = $this->executeFFIOperation(function (\FFI\CData $errorPtr) use ($job): ?\FFI\CData {
+            $result = $this->ffi->job_result($job, \FFI::addr($errorPtr));
+
+            return $result instanceof \FFI\CData ? $result : null;
+        }, FFIException::failedToRunJob(...));
+
+        $result = $this->convertStringPointer($resultPtr);
+
+        $this->freeStringPointer($resultPtr);
+
+        return $result;
+    }
+
+    /**
+     * Free a job, cancelling its operation if it is still running.
+     */
+    public function jobFree(\FFI\CData $job): void
+    {
+        try {
+            $this->ffi->job_free($job);
+        } catch (Throwable) {
+            // Silently ignore any exceptions during cleanup
+        }
+    }
+
+    /**
+     * Release the client instance and free associated resources.
+     *
+     * This method should be called when you're done with the client
+     * to prevent memory leaks and properly clean up resources.
+     */
+    public function freeClient(\FFI\CData $client): void
+    {
+        try {
+            $this->ffi->free_client($client);
+        } catch (Throwable) {
+            // Silently ignore any exceptions during cleanup
+        }
+    }
+
+    /**
+     * Execute an FFI operation with error handling.
+     *
+     * @param  callable(\FFI\CData $errorPtr): ?\FFI\CData  $operation
+     * @param  callable(string $message): FFIException  $createException
+     * @return \FFI\CData FFI pointer result
+     *
+     * @throws FFIException When client is not initialized or FFI operation fails
+     */
+    private function executeFFIOperation(callable $operation, callable $createException): \FFI\CData
+    {
+        if (! $this->isInitialized()) {
+            throw FFIException::clientNotInitialized();
+        }
+
+        $errorPtr = $this->createStringPointer();
+
+        try {
+            $result = $operation($errorPtr);
+
+            if ($result === null) {
+                $message = $this->convertStringPointer($errorPtr);
+
+                throw $createException($message);
+            }
+
+            return $result;
+        } catch (FFIException $e) {
+            throw $e;
+        } catch (Throwable $e) {
+            throw $createException($e->getMessage());
+        } finally {
+            $this->freeStringPointer($errorPtr);
+        }
+    }
+
+    /**
+     * Check if the client has been initialized.
+     */
+    private function isInitialized(): bool
+    {
+        return $this->initialized;
+    }
+
+    /**
+     * Create the FFI instance with library and header definitions.
+     *
+     * @throws FFIException When FFI instance creation fails
+     */
+    private function createFFIInstance(): \FFI
+    {
+        $libraryPath = Loader::getLibraryPath();
+        $headerContent = $this->loadHeaderFile();
+
+        return \FFI::cdef($headerContent, $libraryPath);
+    }
+
+    /**
+     * Check that the native library implements the ABI the C header is written for, before
+     * calling anything that could crash on a mismatch.
+     *
+     * @throws FFIException When the native library is incompatible
+     */
+    private function verifyNativeLibrary(): void
+    {
+        $abiVersion = $this->ffi->protect_ffi_abi_version();
+
+        if ($abiVersion !== Loader::ABI_VERSION) {
+            throw FFIException::incompatibleNativeLibrary(
+                "library implements ABI version {$abiVersion}, but the header requires version ".Loader::ABI_VERSION
+            );
+        }
+
+        $errorPtr = $this->createStringPointer();
+
+        try {
+            $status = $this->ffi->selfcheck(
+                Loader::ABI_VERSION,
+                \FFI::sizeof($this->ffi->type('void*')),
+                \FFI::sizeof($this->ffi->type('int32_t')),
+                null,
+                null,
+                \FFI::addr($errorPtr),
+            );
+
+            if ($status !== 0) {
+                throw FFIException::incompatibleNativeLibrary($this->convertStringPointer($errorPtr));
+            }
+        } finally {
+            $this->freeStringPointer($errorPtr);
+        }
+    }
+
+    /**
+     * Report calls to deprecated native functions, the old names of renamed functions, as PHP
+     * deprecation notices.
+     *
+     * The handler is shared by every client in the process, so it is registered once per
+     * request, and unregistered when the request shuts down, before PHP frees the callback.
+     */
+    private function registerDeprecationHandler(): void
+    {
+        if (self::$deprecationHandler !== null) {
+            return;
+        }
+
+        self::$deprecationHandler = static function (\FFI\CData|string $name, \FFI\CData|string $replacement): void {
+            $name = is_string($name) ? $name : \FFI::string($name);
+            $replacement = is_string($replacement) ? $replacement : \FFI::string($replacement);
+
+            trigger_error("Native function {$name}() is deprecated, use {$replacement}() instead.", E_USER_DEPRECATED);
+        };
+
+        $ffi = $this->ffi;
+        $ffi->set_deprecation_handler(self::$deprecationHandler);
+
+        register_shutdown_function(static function () use ($ffi): void {
+            $ffi->set_deprecation_handler(null);
+            self::$deprecationHandler = null;
+        });
     }
 
     /**
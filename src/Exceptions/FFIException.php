@@ -43,6 +43,14 @@ final class FFIException extends Exception
         return new self("The FFI header file is not readable at [{$path}]. Please check file permissions or ensure the file exists.");
     }
 
+    /**
+     * Create a new exception for when the native library does not match the C header.
+     */
+    public static function incompatibleNativeLibrary(string $reason): self
+    {
+        return new self("The native library is incompatible with this version of the package: [{$reason}].");
+    }
+
     /**
      * Create a new exception for when string pointer creation fails.
      */
@@ -91,6 +99,270 @@ final class FFIException extends Exception
         return new self("Failed to create search terms: [{$reason}].");
     }
 
+    /**
+     * Create a new exception for when envelope formatting fails.
+     */
+    public static function failedToFormatEnvelope(string $reason): self
+    {
+        return new self("Failed to format encrypted envelope: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for when envelope validation fails.
+     */
+    public static function failedToValidateEnvelope(string $reason): self
+    {
+        return new self("Failed to validate encrypted envelope: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for when envelope conversion fails.
+     */
+    public static function failedToConvertEnvelope(string $reason): self
+    {
+        return new self("Failed to convert encrypted envelope: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for when envelope upgrade fails.
+     */
+    public static function failedToUpgradeEnvelope(string $reason): self
+    {
+        return new self("Failed to upgrade encrypted envelope: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for when search term reindexing fails.
+     */
+    public static function failedToReindexSearchTerms(string $reason): self
+    {
+        return new self("Failed to reindex search terms: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for when key metadata retrieval fails.
+     */
+    public static function failedToGetKeyInfo(string $reason): self
+    {
+        return new self("Failed to get key metadata: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for when exporting a client's state fails.
+     */
+    public static function failedToExportClientState(string $reason): self
+    {
+        return new self("Failed to export client state: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for when importing a client state snapshot fails.
+     */
+    public static function failedToImportClientState(string $reason): self
+    {
+        return new self("Failed to import client state: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for when a client's configuration does not match the expected one.
+     */
+    public static function failedToAssertConfigMatches(string $reason): self
+    {
+        return new self("Failed to assert config matches: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for when encrypting JSON document fields fails.
+     */
+    public static function failedToEncryptJsonPaths(string $reason): self
+    {
+        return new self("Failed to encrypt JSON document fields: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for when decrypting JSON document fields fails.
+     */
+    public static function failedToDecryptJsonPaths(string $reason): self
+    {
+        return new self("Failed to decrypt JSON document fields: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for when splitting an envelope into storage columns fails.
+     */
+    public static function failedToExplodeEnvelope(string $reason): self
+    {
+        return new self("Failed to explode envelope: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for when assembling an envelope from storage columns fails.
+     */
+    public static function failedToAssembleEnvelope(string $reason): self
+    {
+        return new self("Failed to assemble envelope: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for benchmark failures.
+     */
+    public static function failedToRunBenchmark(string $reason): self
+    {
+        return new self("Failed to run benchmark: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for storage estimate failures.
+     */
+    public static function failedToEstimateStorage(string $reason): self
+    {
+        return new self("Failed to estimate storage: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for encryption plan failures.
+     */
+    public static function failedToPlanEncrypt(string $reason): self
+    {
+        return new self("Failed to plan encryption: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for client warning retrieval failures.
+     */
+    public static function failedToGetClientWarnings(string $reason): self
+    {
+        return new self("Failed to get client warnings: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for configuration validation failures.
+     */
+    public static function failedToValidateConfig(string $reason): self
+    {
+        return new self("Failed to validate config: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for when computing a configuration digest fails.
+     */
+    public static function failedToGetConfigDigest(string $reason): self
+    {
+        return new self("Failed to get config digest: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for selector tokenization failures.
+     */
+    public static function failedToTokenizeSelector(string $reason): self
+    {
+        return new self("Failed to tokenize selector through FFI operation: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for query capability explanation failures.
+     */
+    public static function failedToExplainQueryCapability(string $reason): self
+    {
+        return new self("Failed to explain query capability through FFI operation: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for index term comparison failures.
+     */
+    public static function failedToCompareTerms(string $reason): self
+    {
+        return new self("Failed to compare terms through FFI operation: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for client derivation failures.
+     */
+    public static function failedToDeriveClient(string $reason): self
+    {
+        return new self("Failed to derive client through FFI operation: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for context preset expansion failures.
+     */
+    public static function failedToExpandContext(string $reason): self
+    {
+        return new self("Failed to expand context through FFI operation: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for asynchronous job failures.
+     */
+    public static function failedToRunJob(string $reason): self
+    {
+        return new self("Failed to run job through FFI operation: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for connectivity probe failures.
+     */
+    public static function failedToPingService(string $reason): self
+    {
+        return new self("Failed to ping service: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for network policy retrieval failures.
+     */
+    public static function failedToGetNetworkPolicy(string $reason): self
+    {
+        return new self("Failed to get network policy: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for startup diagnostics failures.
+     */
+    public static function failedToGetStartupDiagnostics(string $reason): self
+    {
+        return new self("Failed to get startup diagnostics: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for error catalog retrieval failures.
+     */
+    public static function failedToGetErrorCatalog(string $reason): self
+    {
+        return new self("Failed to get error catalog: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for function manifest retrieval failures.
+     */
+    public static function failedToListFunctions(string $reason): self
+    {
+        return new self("Failed to list functions: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for metrics retrieval failures.
+     */
+    public static function failedToGetMetrics(string $reason): self
+    {
+        return new self("Failed to get metrics: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for search term cache clearing failures.
+     */
+    public static function failedToClearSearchTermCache(string $reason): self
+    {
+        return new self("Failed to clear search term cache: [{$reason}].");
+    }
+
+    /**
+     * Create a new exception for when the crash report path cannot be set.
+     */
+    public static function failedToSetCrashReportPath(string $reason): self
+    {
+        return new self("Failed to set crash report path: [{$reason}].");
+    }
+
     /**
      * Create a new exception for string conversion failures.
      */
@@ -29,7 +29,7 @@ final class LoaderException extends Exception
     {
         return new self(
             "Platform [{$platform}] is not supported. ".
-            'Supported platforms: darwin-arm64, darwin-x64, linux-arm64-gnu, linux-x64-gnu, win32-x64-msvc.'
+            'Supported platforms: darwin-arm64, darwin-x64, linux-arm64-gnu, linux-x64-gnu, linux-arm64-musl, linux-x64-musl, win32-x64-msvc.'
         );
     }
 }
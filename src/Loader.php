@@ -11,6 +11,11 @@ use CipherStash\Protect\FFI\Exceptions\LoaderException;
  */
 class Loader
 {
+    /**
+     * ABI version of the native library that the C header is written for.
+     */
+    public const ABI_VERSION = 1;
+
     /**
      * Platform-specific library paths in priority order.
      *
@@ -33,6 +38,14 @@ class Loader
             'target/x86_64-unknown-linux-gnu/release/libprotect_ffi.so',
             'platforms/linux-x64-gnu/libprotect_ffi.so',
         ],
+        'linux-arm64-musl' => [
+            'target/aarch64-unknown-linux-musl/release/libprotect_ffi.so',
+            'platforms/linux-arm64-musl/libprotect_ffi.so',
+        ],
+        'linux-x64-musl' => [
+            'target/x86_64-unknown-linux-musl/release/libprotect_ffi.so',
+            'platforms/linux-x64-musl/libprotect_ffi.so',
+        ],
         'win32-x64-msvc' => [
             'target/x86_64-pc-windows-msvc/release/protect_ffi.dll',
             'platforms/win32-x64-msvc/protect_ffi.dll',
@@ -70,8 +83,17 @@ class Loader
     {
         $os = strtolower(PHP_OS_FAMILY);
         $arch = self::normalizeArchitecture(php_uname('m'));
+        $libc = $os === 'linux' && self::isMusl() ? '-musl' : '';
 
-        return self::normalizePlatform("{$os}-{$arch}");
+        return self::normalizePlatform("{$os}-{$arch}{$libc}");
+    }
+
+    /**
+     * Detect whether the system's C library is musl, as on Alpine Linux.
+     */
+    private static function isMusl(): bool
+    {
+        return ! empty(glob('/lib/ld-musl-*.so.1'));
     }
 
     /**
@@ -100,6 +122,8 @@ class Loader
             'darwin-x64' => 'darwin-x64',
             'linux-arm64' => 'linux-arm64-gnu',
             'linux-x64' => 'linux-x64-gnu',
+            'linux-arm64-musl' => 'linux-arm64-musl',
+            'linux-x64-musl' => 'linux-x64-musl',
             'windows-x64' => 'win32-x64-msvc',
             default => throw LoaderException::unsupportedPlatform($platform)
         };
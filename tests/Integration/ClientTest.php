@@ -31,6 +31,12 @@ class ClientTest extends TestCase
                             'ore' => (object) [],
                         ],
                     ],
+                    'born_on' => [
+                        'cast_as' => 'date',
+                        'indexes' => [
+                            'ore' => (object) [],
+                        ],
+                    ],
                     'job_title' => [
                         'cast_as' => 'text',
                         'indexes' => [
@@ -93,6 +99,38 @@ class ClientTest extends TestCase
         $client->newClient('invalid-config');
     }
 
+    public function test_get_client_warnings_reports_include_original(): void
+    {
+        $config = json_decode(json: self::$config, flags: JSON_THROW_ON_ERROR);
+
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $this->assertSame('[]', $client->getClientWarnings($clientPtr));
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+
+        $config->tables->users->job_title->indexes->match = (object) ['include_original' => true];
+        $configJson = json_encode(value: $config, flags: JSON_THROW_ON_ERROR);
+        $clientPtr = $client->newClient($configJson);
+
+        try {
+            $warnings = json_decode(json: $client->getClientWarnings($clientPtr), associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($warnings);
+            $this->assertCount(1, $warnings);
+            $this->assertSame('include_original', $warnings[0]['code']);
+            $this->assertStringContainsString('users.job_title', $warnings[0]['message']);
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+
+        $this->expectException(FFIException::class);
+        $this->expectExceptionMessage('`include_original` is forbidden');
+        $client->newClient($configJson, json_encode(['forbid_include_original' => true], JSON_THROW_ON_ERROR));
+    }
+
     public function test_encrypt_decrypt_roundtrip(): void
     {
         $client = new Client;
@@ -817,154 +855,1301 @@ class ClientTest extends TestCase
         }
     }
 
-    public function test_encrypt_bulk_throws_exception_with_invalid_items(): void
+    public function test_encrypt_bulk_skips_index_terms(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $items = [
+                ['plaintext' => 'john@example.com', 'column' => 'email', 'table' => 'users', 'skip_match' => true],
+                ['plaintext' => 'john@example.com', 'column' => 'email', 'table' => 'users'],
+            ];
+
+            $itemsJson = json_encode($items, JSON_THROW_ON_ERROR);
+            $encryptResultsJson = $client->encryptBulk($clientPtr, $itemsJson);
+
+            $encryptResults = json_decode(json: $encryptResultsJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($encryptResults);
+
+            [$skipped, $complete] = $encryptResults;
+            $this->assertNull($skipped['bf']);
+            $this->assertNotNull($complete['bf']);
+            $this->assertSame($complete['hm'], $skipped['hm']);
+
+            $this->assertSame('john@example.com', $client->decrypt($clientPtr, json_encode($skipped, JSON_THROW_ON_ERROR)));
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_encrypt_bulk_with_stats(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $items = [
+                ['plaintext' => 'john@example.com', 'column' => 'email', 'table' => 'users'],
+                ['plaintext' => '29', 'column' => 'age', 'table' => 'users'],
+            ];
+
+            $itemsJson = json_encode($items, JSON_THROW_ON_ERROR);
+            $optionsJson = json_encode(['stats' => true], JSON_THROW_ON_ERROR);
+            $responseJson = $client->encryptBulk($clientPtr, $itemsJson, $optionsJson);
+
+            $response = json_decode(json: $responseJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($response);
+            $this->assertCount(2, $response['results']);
+
+            $stats = $response['stats'];
+            $this->assertSame(2, $stats['items']);
+            $this->assertSame(18, $stats['plaintext_bytes']);
+            $this->assertGreaterThan($stats['plaintext_bytes'], $stats['stored_bytes']);
+            $this->assertGreaterThan(1, $stats['expansion_ratio']);
+            $this->assertSame(['max_bytes' => 16, 'count' => 2], $stats['plaintext_size_histogram'][0]);
+
+            $this->assertSame(16, $stats['columns']['users.email']['plaintext_bytes']);
+            $this->assertGreaterThan(0, $stats['columns']['users.email']['index_bytes']['unique']);
+            $this->assertGreaterThan(0, $stats['columns']['users.email']['index_bytes']['match']);
+            $this->assertSame(0, $stats['columns']['users.email']['index_bytes']['ore']);
+            $this->assertGreaterThan(0, $stats['columns']['users.age']['index_bytes']['ore']);
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_encrypt_bulk_with_warnings(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $items = [
+                ['plaintext' => 'john@example.com', 'column' => 'email', 'table' => 'users', 'skip_ore' => true],
+                ['plaintext' => '29', 'column' => 'age', 'table' => 'users', 'skip_ore' => true],
+            ];
+
+            $itemsJson = json_encode($items, JSON_THROW_ON_ERROR);
+            $optionsJson = json_encode(['include_warnings' => true], JSON_THROW_ON_ERROR);
+            $responseJson = $client->encryptBulk($clientPtr, $itemsJson, $optionsJson);
+
+            $response = json_decode(json: $responseJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($response);
+            $this->assertCount(2, $response['results']);
+            $this->assertArrayNotHasKey('stats', $response);
+
+            $this->assertCount(1, $response['warnings']);
+            $this->assertSame('option_ignored', $response['warnings'][0]['code']);
+            $this->assertSame(0, $response['warnings'][0]['index']);
+            $this->assertStringContainsString('skip_ore', $response['warnings'][0]['message']);
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_decrypt_single_field_returns_ste_vec_document(): void
     {
         $client = new Client;
         $clientPtr = $client->newClient(self::$config);
 
         try {
+            $documentJson = json_encode(['plan' => 'premium', 'tags' => ['beta', 'early']], JSON_THROW_ON_ERROR);
+            $envelopeJson = $client->encrypt($clientPtr, $documentJson, 'metadata', 'users');
+
+            $this->assertSame($documentJson, $client->decryptSingleField($clientPtr, $envelopeJson));
+
+            $encodedEnvelopeJson = json_encode($envelopeJson, JSON_THROW_ON_ERROR);
+            $this->assertSame($documentJson, $client->decryptSingleField($clientPtr, $encodedEnvelopeJson));
+
+            $ciphertextEnvelopeJson = $client->encrypt($clientPtr, 'john@example.com', 'email', 'users');
+
             $this->expectException(FFIException::class);
-            $client->encryptBulk($clientPtr, 'invalid-items');
+            $this->expectExceptionMessage('expected an `sv` envelope');
+
+            $client->decryptSingleField($clientPtr, $ciphertextEnvelopeJson);
         } finally {
             $client->freeClient($clientPtr);
         }
     }
 
-    public function test_decrypt_bulk_throws_exception_with_invalid_items(): void
+    public function test_encrypt_decrypt_ste_vec_array_and_scalar_roots(): void
     {
         $client = new Client;
         $clientPtr = $client->newClient(self::$config);
 
         try {
+            foreach (['["admin","editor"]', '"admin"', '42', 'true'] as $documentJson) {
+                $envelopeJson = $client->encrypt($clientPtr, $documentJson, 'metadata', 'users');
+
+                $envelope = json_decode(json: $envelopeJson, associative: true, flags: JSON_THROW_ON_ERROR);
+                $this->assertSame('sv', $envelope['k']);
+                $this->assertNotEmpty($envelope['sv']);
+                $this->assertSame($documentJson, $client->decrypt($clientPtr, $envelopeJson));
+
+                $elementEntries = array_filter($envelope['sv'], fn (array $entry): bool => $entry['pa']);
+                $this->assertSame(str_starts_with($documentJson, '[') ? 2 : 0, count($elementEntries));
+            }
+
             $this->expectException(FFIException::class);
-            $client->decryptBulk($clientPtr, 'invalid-items');
+            $this->expectExceptionMessage('a `null` document cannot be indexed');
+
+            $client->encrypt($clientPtr, 'null', 'metadata', 'users');
         } finally {
             $client->freeClient($clientPtr);
         }
     }
 
-    public function test_create_search_terms(): void
+    public function test_ste_vec_limits_index_only_covered_values(): void
+    {
+        $config = json_decode(json: self::$config, flags: JSON_THROW_ON_ERROR);
+        $config->tables->users->metadata->indexes->ste_vec->max_depth = 2;
+        $config->tables->users->metadata->indexes->ste_vec->exclude = ['$.raw_payload'];
+
+        $client = new Client;
+        $clientPtr = $client->newClient(json_encode(value: $config, flags: JSON_THROW_ON_ERROR));
+
+        try {
+            $documentJson = json_encode([
+                'profile' => ['email' => 'ada@example.com', 'address' => ['city' => 'London']],
+                'raw_payload' => ['headers' => ['accept' => 'text/html']],
+            ], JSON_THROW_ON_ERROR);
+
+            $envelopeJson = $client->encrypt($clientPtr, $documentJson, 'metadata', 'users');
+
+            $envelope = json_decode(json: $envelopeJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertSame('sv', $envelope['k']);
+            $this->assertNotEmpty($envelope['sv']);
+            $this->assertSame($documentJson, $client->decrypt($clientPtr, $envelopeJson));
+
+            $unlimitedPtr = $client->newClient(self::$config);
+
+            try {
+                $unlimitedJson = $client->encrypt($unlimitedPtr, $documentJson, 'metadata', 'users');
+                $unlimited = json_decode(json: $unlimitedJson, associative: true, flags: JSON_THROW_ON_ERROR);
+                $this->assertLessThan(count($unlimited['sv']), count($envelope['sv']));
+            } finally {
+                $client->freeClient($unlimitedPtr);
+            }
+
+            $termsJson = json_encode([
+                ['plaintext' => '{"raw_payload":{"headers":{}}}', 'column' => 'metadata', 'table' => 'users'],
+            ], JSON_THROW_ON_ERROR);
+
+            $this->expectException(FFIException::class);
+            $this->expectExceptionMessage('does not cover');
+
+            $client->createSearchTerms($clientPtr, $termsJson);
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_ste_vec_include_filters_with_wildcards(): void
+    {
+        $config = json_decode(json: self::$config, flags: JSON_THROW_ON_ERROR);
+        $config->tables->users->metadata->indexes->ste_vec->include = ['$.profile.email', '$.tags[*]'];
+
+        $client = new Client;
+        $clientPtr = $client->newClient(json_encode(value: $config, flags: JSON_THROW_ON_ERROR));
+
+        try {
+            $documentJson = json_encode([
+                'profile' => ['email' => 'ada@example.com', 'bio' => 'Mathematician'],
+                'tags' => ['admin', 'editor'],
+                'raw_payload' => ['headers' => ['accept' => 'text/html']],
+            ], JSON_THROW_ON_ERROR);
+
+            $envelopeJson = $client->encrypt($clientPtr, $documentJson, 'metadata', 'users');
+
+            $envelope = json_decode(json: $envelopeJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertNotEmpty($envelope['sv']);
+            $this->assertSame($documentJson, $client->decrypt($clientPtr, $envelopeJson));
+
+            $itemsJson = json_encode([
+                ['plaintext' => $documentJson, 'column' => 'metadata', 'table' => 'users'],
+            ], JSON_THROW_ON_ERROR);
+            $plan = json_decode(json: $client->planEncrypt($clientPtr, $itemsJson), associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertSame(['parse_json', 'filter_indexed_values'], $plan['items'][0]['conversions']);
+
+            $termsJson = json_encode([
+                ['plaintext' => '{"tags":["admin"]}', 'column' => 'metadata', 'table' => 'users'],
+            ], JSON_THROW_ON_ERROR);
+            $this->assertNotEmpty($client->createSearchTerms($clientPtr, $termsJson));
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_tokenize_selector_matches_ste_vec_entries(): void
     {
         $client = new Client;
         $clientPtr = $client->newClient(self::$config);
 
         try {
-            $items = [
-                [
-                    'plaintext' => 'john@example.com',
-                    'column' => 'email',
-                    'table' => 'users',
-                ],
-                [
-                    'plaintext' => '29',
-                    'column' => 'age',
-                    'table' => 'users',
-                ],
+            $envelopeJson = $client->encrypt($clientPtr, '{"profile":{"email":"ada@example.com"}}', 'metadata', 'users');
+            $envelope = json_decode(json: $envelopeJson, associative: true, flags: JSON_THROW_ON_ERROR);
+
+            $selector = $client->tokenizeSelector($clientPtr, 'metadata', 'users', '$.profile.email');
+
+            $this->assertContains($selector, array_column($envelope['sv'], 's'));
+            $this->assertSame($selector, $client->tokenizeSelector($clientPtr, 'metadata', 'users', '$.profile.email'));
+            $this->assertNotSame($selector, $client->tokenizeSelector($clientPtr, 'metadata', 'users', '$.profile.name'));
+
+            $this->expectException(FFIException::class);
+            $this->expectExceptionMessage('ste_vec');
+
+            $client->tokenizeSelector($clientPtr, 'email', 'users', '$.profile.email');
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_create_search_terms_compares_fields_inside_jsonb_documents(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $termsJson = json_encode(value: [
                 [
-                    'plaintext' => 'Software Engineer',
-                    'column' => 'job_title',
+                    'plaintext' => '30',
+                    'path' => '$.profile.age',
+                    'operator' => 'gt',
+                    'column' => 'metadata',
                     'table' => 'users',
                 ],
                 [
-                    'plaintext' => '{"city":"Boston","state":"MA"}',
+                    'range' => ['from' => '2024-01-01', 'to' => '2024-12-31'],
+                    'path' => '$.visits[*].on',
                     'column' => 'metadata',
                     'table' => 'users',
                 ],
-            ];
+            ], flags: JSON_THROW_ON_ERROR);
 
-            $itemsJson = json_encode($items, JSON_THROW_ON_ERROR);
-            $searchTermResultsJson = $client->createSearchTerms($clientPtr, $itemsJson);
+            $resultJson = $client->createSearchTerms($clientPtr, $termsJson);
+            $result = json_decode(json: $resultJson, associative: true, flags: JSON_THROW_ON_ERROR);
 
-            $searchTermResults = json_decode(json: $searchTermResultsJson, associative: true, flags: JSON_THROW_ON_ERROR);
-            $this->assertIsArray($searchTermResults);
-            $this->assertCount(4, $searchTermResults);
+            $this->assertCount(1, $result[0]['sv']);
+            $this->assertSame(
+                $client->tokenizeSelector($clientPtr, 'metadata', 'users', '$.profile.age'),
+                $result[0]['sv'][0]['s'],
+            );
+            $this->assertCount(1, $result[1]['from']['sv']);
+            $this->assertCount(1, $result[1]['to']['sv']);
 
-            $emailTerm = $searchTermResults[0];
-            $this->assertIsArray($emailTerm);
-            $this->assertNotNull($emailTerm['hm']);
-            $this->assertNull($emailTerm['ob']);
-            $this->assertNotNull($emailTerm['bf']);
-            $this->assertArrayHasKey('i', $emailTerm);
+            $this->expectException(FFIException::class);
+            $this->expectExceptionMessage('path');
+
+            $client->createSearchTerms($clientPtr, json_encode(value: [[
+                'plaintext' => 'engineer',
+                'path' => '$.profile.title',
+                'operator' => '~~',
+                'column' => 'metadata',
+                'table' => 'users',
+            ]], flags: JSON_THROW_ON_ERROR));
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
 
-            $ageTerm = $searchTermResults[1];
-            $this->assertIsArray($ageTerm);
-            $this->assertNull($ageTerm['hm']);
-            $this->assertNotNull($ageTerm['ob']);
-            $this->assertNull($ageTerm['bf']);
-            $this->assertArrayHasKey('i', $ageTerm);
+    public function test_derive_client_applies_default_context(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+        $contextJson = json_encode(['tag' => ['tenant-a']], JSON_THROW_ON_ERROR);
+        $derivedPtr = $client->deriveClient($clientPtr, json_encode(['context' => ['tag' => ['tenant-a']]], JSON_THROW_ON_ERROR));
 
-            $jobTitleTerm = $searchTermResults[2];
-            $this->assertIsArray($jobTitleTerm);
-            $this->assertNull($jobTitleTerm['hm']);
-            $this->assertNull($jobTitleTerm['ob']);
-            $this->assertNotNull($jobTitleTerm['bf']);
-            $this->assertArrayHasKey('i', $jobTitleTerm);
+        try {
+            $encryptResultJson = $client->encrypt($derivedPtr, 'john@example.com', 'email', 'users');
+            $encryptResult = json_decode(json: $encryptResultJson, associative: true, flags: JSON_THROW_ON_ERROR);
 
-            $metadataTerm = $searchTermResults[3];
-            $this->assertIsArray($metadataTerm);
-            $this->assertArrayHasKey('sv', $metadataTerm);
-            $this->assertIsArray($metadataTerm['sv']);
-            $this->assertNotEmpty($metadataTerm['sv']);
-            $this->assertArrayHasKey('i', $metadataTerm);
+            $this->assertSame('john@example.com', $client->decrypt($derivedPtr, $encryptResult['c']));
+            $this->assertSame('john@example.com', $client->decrypt($clientPtr, $encryptResult['c'], $contextJson));
 
-            foreach ($searchTermResults as $searchTerm) {
-                $this->assertIsArray($searchTerm);
-                $identifier = $searchTerm['i'];
-                $this->assertIsArray($identifier);
-                $this->assertSame('users', $identifier['t']);
-                $this->assertContains($identifier['c'], ['email', 'age', 'job_title', 'metadata']);
-            }
+            $this->expectException(FFIException::class);
+            $client->decrypt($clientPtr, $encryptResult['c']);
         } finally {
+            $client->freeClient($derivedPtr);
             $client->freeClient($clientPtr);
         }
     }
 
-    public function test_create_search_terms_with_context(): void
+    public function test_derive_client_throws_exception_with_unknown_keyset(): void
     {
         $client = new Client;
         $clientPtr = $client->newClient(self::$config);
 
         try {
-            $items = [
-                [
-                    'plaintext' => 'john@example.com',
-                    'column' => 'email',
-                    'table' => 'users',
-                    'context' => ['tag' => ['test-context']],
-                ],
-            ];
+            $this->expectException(FFIException::class);
+            $this->expectExceptionMessage('tenant_a');
 
-            $itemsJson = json_encode($items, JSON_THROW_ON_ERROR);
-            $searchTermResultsJson = $client->createSearchTerms($clientPtr, $itemsJson);
+            $client->deriveClient($clientPtr, json_encode(['keyset' => 'tenant_a'], JSON_THROW_ON_ERROR));
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
 
-            $searchTermResults = json_decode(json: $searchTermResultsJson, associative: true, flags: JSON_THROW_ON_ERROR);
-            $this->assertIsArray($searchTermResults);
-            $this->assertCount(1, $searchTermResults);
+    public function test_write_only_client_encrypts_but_does_not_decrypt(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config, json_encode(['write_only' => true], JSON_THROW_ON_ERROR));
 
-            $searchTerm = $searchTermResults[0];
-            $this->assertIsArray($searchTerm);
-            $this->assertNotNull($searchTerm['hm']);
-            $this->assertNull($searchTerm['ob']);
-            $this->assertNotNull($searchTerm['bf']);
-            $this->assertArrayHasKey('i', $searchTerm);
+        try {
+            $encryptResultJson = $client->encrypt($clientPtr, 'john@example.com', 'email', 'users');
+            $encryptResult = json_decode(json: $encryptResultJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsString($encryptResult['c']);
+
+            $derivedPtr = $client->deriveClient($clientPtr, json_encode(['write_only' => false], JSON_THROW_ON_ERROR));
+            try {
+                $client->decrypt($derivedPtr, $encryptResult['c']);
+                $this->fail('A client derived from a write-only client decrypted a value.');
+            } catch (FFIException $e) {
+                $this->assertStringContainsString('write_only', $e->getMessage());
+            } finally {
+                $client->freeClient($derivedPtr);
+            }
 
-            $identifier = $searchTerm['i'];
-            $this->assertIsArray($identifier);
-            $this->assertSame('users', $identifier['t']);
-            $this->assertSame('email', $identifier['c']);
+            $this->expectException(FFIException::class);
+            $this->expectExceptionMessage('write_only');
+
+            $client->decryptBulk($clientPtr, json_encode([['ciphertext' => $encryptResult['c']]], JSON_THROW_ON_ERROR));
         } finally {
             $client->freeClient($clientPtr);
         }
     }
 
-    public function test_create_search_terms_throws_exception_with_invalid_terms(): void
+    public function test_rate_limited_client_rejects_calls_beyond_its_limit(): void
     {
         $client = new Client;
-        $clientPtr = $client->newClient(self::$config);
+        $optionsJson = json_encode([
+            'rate_limit' => ['requests_per_second' => 0.01, 'burst' => 1, 'max_wait_ms' => 0],
+        ], JSON_THROW_ON_ERROR);
+        $clientPtr = $client->newClient(self::$config, $optionsJson);
 
         try {
+            $client->encrypt($clientPtr, 'john@example.com', 'email', 'users');
+
             $this->expectException(FFIException::class);
-            $client->createSearchTerms($clientPtr, 'invalid-terms');
+            $this->expectExceptionMessage('rate limit exceeded');
+
+            $client->encrypt($clientPtr, 'jane@example.com', 'email', 'users');
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_batching_client_encrypts_and_decrypts_single_values(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config, json_encode(['batching' => ['window_ms' => 1]], JSON_THROW_ON_ERROR));
+        $contextJson = json_encode(['tag' => ['batched']], JSON_THROW_ON_ERROR);
+
+        try {
+            $encryptResultJson = $client->encrypt($clientPtr, 'john@example.com', 'email', 'users', $contextJson);
+            $encryptResult = json_decode(json: $encryptResultJson, associative: true, flags: JSON_THROW_ON_ERROR);
+
+            $this->assertSame('john@example.com', $client->decrypt($clientPtr, $encryptResult['c'], $contextJson));
+
+            $this->expectException(FFIException::class);
+            $client->decrypt($clientPtr, $encryptResult['c']);
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_async_jobs_encrypt_and_decrypt_without_blocking(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+        $await = function (\FFI\CData $job) use ($client): string {
+            try {
+                while (! $client->jobPoll($job)) {
+                    usleep(1000);
+                }
+
+                return $client->jobResult($job);
+            } finally {
+                $client->jobFree($job);
+            }
+        };
+
+        try {
+            $encryptResultJson = $await($client->encryptAsync($clientPtr, 'john@example.com', 'email', 'users'));
+            $encryptResult = json_decode(json: $encryptResultJson, associative: true, flags: JSON_THROW_ON_ERROR);
+
+            $this->assertSame('john@example.com', $await($client->decryptAsync($clientPtr, $encryptResult['c'])));
+
+            $this->expectException(FFIException::class);
+            $await($client->decryptAsync($clientPtr, 'invalid-ciphertext'));
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_get_metrics_counts_completed_operations(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config, json_encode(['max_outstanding' => 4], JSON_THROW_ON_ERROR));
+
+        try {
+            $client->encrypt($clientPtr, 'john@example.com', 'email', 'users');
+
+            $metrics = json_decode(json: $client->getMetrics($clientPtr), associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertSame(0, $metrics['queued']);
+            $this->assertSame(0, $metrics['in_flight']);
+            $this->assertSame(1, $metrics['completed']);
+            $this->assertSame(0, $metrics['rejected']);
+            $this->assertSame(4, $metrics['max_outstanding']);
+            $this->assertGreaterThan(0, $metrics['runtime']['workers']);
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_bulk_results_follow_item_order(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $plaintexts = ['john@example.com', 'Engineer', 'jane@example.com', 'Designer'];
+            $items = [];
+            foreach ($plaintexts as $index => $plaintext) {
+                $items[] = [
+                    'id' => "item_{$index}",
+                    'plaintext' => $plaintext,
+                    'column' => $index % 2 === 0 ? 'email' : 'job_title',
+                    'table' => 'users',
+                ];
+            }
+
+            $encryptResultsJson = $client->encryptBulk($clientPtr, json_encode($items, JSON_THROW_ON_ERROR));
+            $encryptResults = json_decode(json: $encryptResultsJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($encryptResults);
+
+            $decryptItems = [];
+            foreach ($encryptResults as $index => $encryptResult) {
+                $this->assertSame($index, $encryptResult['index']);
+                $this->assertSame("item_{$index}", $encryptResult['id']);
+                $decryptItems[] = ['ciphertext' => $encryptResult['result']['c']];
+            }
+
+            $decryptResultsJson = $client->decryptBulk($clientPtr, json_encode($decryptItems, JSON_THROW_ON_ERROR));
+            $this->assertSame($plaintexts, json_decode(json: $decryptResultsJson, associative: true, flags: JSON_THROW_ON_ERROR));
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_case_insensitive_identifier_lookup_records_configured_names(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config, json_encode(['identifier_lookup' => 'case_insensitive'], JSON_THROW_ON_ERROR));
+
+        try {
+            $encryptResultJson = $client->encrypt($clientPtr, 'john@example.com', 'EMAIL', 'Users');
+
+            $encryptResult = json_decode(json: $encryptResultJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($encryptResult);
+            $this->assertSame(['t' => 'users', 'c' => 'email'], $encryptResult['i']);
+            $this->assertSame('john@example.com', $client->decrypt($clientPtr, $encryptResultJson));
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $this->expectException(FFIException::class);
+            $this->expectExceptionMessage('unknown column `Users.EMAIL` (did you mean `users.email`?)');
+
+            $client->encrypt($clientPtr, 'john@example.com', 'EMAIL', 'Users');
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_ping_probes_report_each_service(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            foreach (['cts' => $client->pingCts($clientPtr), 'zerokms' => $client->pingZeroKms($clientPtr)] as $service => $probeJson) {
+                $probe = json_decode(json: $probeJson, associative: true, flags: JSON_THROW_ON_ERROR);
+                $this->assertIsArray($probe);
+                $this->assertSame($service, $probe['service']);
+                $this->assertSame('ok', $probe['status']);
+                $this->assertStringStartsWith('https://', $probe['endpoint']);
+                $this->assertNotEmpty($probe['addresses']);
+                $this->assertArrayNotHasKey('error', $probe);
+            }
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_offline_client_reports_its_network_policy(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config, json_encode(['offline' => true], JSON_THROW_ON_ERROR));
+
+        try {
+            $policyJson = $client->getNetworkPolicy($clientPtr);
+
+            $policy = json_decode(json: $policyJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($policy);
+            $this->assertTrue($policy['offline']);
+            $this->assertStringStartsWith('https://', $policy['cts']['url']);
+            $this->assertStringStartsWith('https://', $policy['zerokms']['url']);
+
+            $probe = json_decode(json: $client->pingZeroKms($clientPtr), associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertSame('ok', $probe['status']);
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_get_startup_diagnostics_reports_variables_without_values(): void
+    {
+        $client = new Client;
+
+        $diagnosticsJson = $client->getStartupDiagnostics();
+
+        $diagnostics = json_decode(json: $diagnosticsJson, associative: true, flags: JSON_THROW_ON_ERROR);
+        $this->assertIsArray($diagnostics);
+        $this->assertSame(['environment'], $diagnostics['sources']);
+        $this->assertSame([], $diagnostics['missing_required']);
+
+        $variables = array_column($diagnostics['variables'], 'state', 'name');
+        $this->assertSame('present', $variables['CS_CLIENT_ID']);
+
+        $clientId = getenv('CS_CLIENT_ID');
+        $this->assertIsString($clientId);
+        $this->assertStringNotContainsString($clientId, $diagnosticsJson);
+    }
+
+    public function test_encrypt_bulk_throws_exception_with_invalid_items(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $this->expectException(FFIException::class);
+            $client->encryptBulk($clientPtr, 'invalid-items');
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_decrypt_bulk_throws_exception_with_invalid_items(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $this->expectException(FFIException::class);
+            $client->decryptBulk($clientPtr, 'invalid-items');
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_create_search_terms(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $items = [
+                [
+                    'plaintext' => 'john@example.com',
+                    'column' => 'email',
+                    'table' => 'users',
+                ],
+                [
+                    'plaintext' => '29',
+                    'column' => 'age',
+                    'table' => 'users',
+                ],
+                [
+                    'plaintext' => 'Software Engineer',
+                    'column' => 'job_title',
+                    'table' => 'users',
+                ],
+                [
+                    'plaintext' => '{"city":"Boston","state":"MA"}',
+                    'column' => 'metadata',
+                    'table' => 'users',
+                ],
+            ];
+
+            $itemsJson = json_encode($items, JSON_THROW_ON_ERROR);
+            $searchTermResultsJson = $client->createSearchTerms($clientPtr, $itemsJson);
+
+            $searchTermResults = json_decode(json: $searchTermResultsJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($searchTermResults);
+            $this->assertCount(4, $searchTermResults);
+
+            $emailTerm = $searchTermResults[0];
+            $this->assertIsArray($emailTerm);
+            $this->assertNotNull($emailTerm['hm']);
+            $this->assertNull($emailTerm['ob']);
+            $this->assertNotNull($emailTerm['bf']);
+            $this->assertArrayHasKey('i', $emailTerm);
+
+            $ageTerm = $searchTermResults[1];
+            $this->assertIsArray($ageTerm);
+            $this->assertNull($ageTerm['hm']);
+            $this->assertNotNull($ageTerm['ob']);
+            $this->assertNull($ageTerm['bf']);
+            $this->assertArrayHasKey('i', $ageTerm);
+
+            $jobTitleTerm = $searchTermResults[2];
+            $this->assertIsArray($jobTitleTerm);
+            $this->assertNull($jobTitleTerm['hm']);
+            $this->assertNull($jobTitleTerm['ob']);
+            $this->assertNotNull($jobTitleTerm['bf']);
+            $this->assertArrayHasKey('i', $jobTitleTerm);
+
+            $metadataTerm = $searchTermResults[3];
+            $this->assertIsArray($metadataTerm);
+            $this->assertArrayHasKey('sv', $metadataTerm);
+            $this->assertIsArray($metadataTerm['sv']);
+            $this->assertNotEmpty($metadataTerm['sv']);
+            $this->assertArrayHasKey('i', $metadataTerm);
+
+            foreach ($searchTermResults as $searchTerm) {
+                $this->assertIsArray($searchTerm);
+                $identifier = $searchTerm['i'];
+                $this->assertIsArray($identifier);
+                $this->assertSame('users', $identifier['t']);
+                $this->assertContains($identifier['c'], ['email', 'age', 'job_title', 'metadata']);
+            }
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_create_search_terms_with_context(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $items = [
+                [
+                    'plaintext' => 'john@example.com',
+                    'column' => 'email',
+                    'table' => 'users',
+                    'context' => ['tag' => ['test-context']],
+                ],
+            ];
+
+            $itemsJson = json_encode($items, JSON_THROW_ON_ERROR);
+            $searchTermResultsJson = $client->createSearchTerms($clientPtr, $itemsJson);
+
+            $searchTermResults = json_decode(json: $searchTermResultsJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($searchTermResults);
+            $this->assertCount(1, $searchTermResults);
+
+            $searchTerm = $searchTermResults[0];
+            $this->assertIsArray($searchTerm);
+            $this->assertNotNull($searchTerm['hm']);
+            $this->assertNull($searchTerm['ob']);
+            $this->assertNotNull($searchTerm['bf']);
+            $this->assertArrayHasKey('i', $searchTerm);
+
+            $identifier = $searchTerm['i'];
+            $this->assertIsArray($identifier);
+            $this->assertSame('users', $identifier['t']);
+            $this->assertSame('email', $identifier['c']);
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_create_search_terms_throws_exception_with_invalid_terms(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $this->expectException(FFIException::class);
+            $client->createSearchTerms($clientPtr, 'invalid-terms');
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_encrypt_decrypt_date_roundtrip(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            foreach (['1969-12-31', '1970-01-01', '2000-02-29'] as $plaintext) {
+                $encryptResultJson = $client->encrypt($clientPtr, $plaintext, 'born_on', 'users');
+
+                $encryptResult = json_decode(json: $encryptResultJson, associative: true, flags: JSON_THROW_ON_ERROR);
+                $this->assertIsArray($encryptResult);
+                $this->assertSame('date', $encryptResult['dt']);
+                $this->assertIsArray($encryptResult['ob']);
+                $this->assertNotEmpty($encryptResult['ob']);
+
+                $decryptResult = $client->decrypt($clientPtr, $encryptResultJson);
+                $this->assertSame($plaintext, $decryptResult);
+            }
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_encrypt_date_throws_exception_with_timestamp(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $this->expectException(FFIException::class);
+            $this->expectExceptionMessage('timestamps are not supported');
+            $client->encrypt($clientPtr, '2020-11-10T23:30:00-05:00', 'born_on', 'users');
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_encrypt_decrypt_empty_text_roundtrip(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $encryptResultJson = $client->encrypt($clientPtr, '', 'email', 'users');
+
+            $encryptResult = json_decode(json: $encryptResultJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($encryptResult);
+            $this->assertIsString($encryptResult['hm']);
+
+            $decryptResult = $client->decrypt($clientPtr, $encryptResultJson);
+            $this->assertSame('', $decryptResult);
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_encrypt_throws_exception_with_empty_int(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $this->expectException(FFIException::class);
+            $this->expectExceptionMessage('empty plaintext is not a valid `int` value');
+            $client->encrypt($clientPtr, ' ', 'age', 'users');
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_encrypt_trims_and_rejects_empty_text_with_column_policy(): void
+    {
+        $config = json_decode(json: self::$config, flags: JSON_THROW_ON_ERROR);
+        $config->tables->users->email->allow_empty = false;
+        $config->tables->users->email->trim = true;
+
+        $client = new Client;
+        $clientPtr = $client->newClient(json_encode(value: $config, flags: JSON_THROW_ON_ERROR));
+
+        try {
+            $encryptResultJson = $client->encrypt($clientPtr, '  john@example.com ', 'email', 'users');
+            $this->assertSame('john@example.com', $client->decrypt($clientPtr, $encryptResultJson));
+
+            $this->expectException(FFIException::class);
+            $this->expectExceptionMessage('empty plaintext is not a valid `text` value');
+            $client->encrypt($clientPtr, '   ', 'email', 'users');
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_encrypt_normalizes_unicode_for_index_terms(): void
+    {
+        $config = json_decode(json: self::$config, flags: JSON_THROW_ON_ERROR);
+        $config->tables->users->email->indexes->unique = (object) ['unicode_normalization' => 'nfc'];
+
+        $client = new Client;
+        $clientPtr = $client->newClient(json_encode(value: $config, flags: JSON_THROW_ON_ERROR));
+
+        try {
+            $composed = "caf\u{e9}@example.com";
+            $decomposed = "cafe\u{301}@example.com";
+
+            $composedResultJson = $client->encrypt($clientPtr, $composed, 'email', 'users');
+            $decomposedResultJson = $client->encrypt($clientPtr, $decomposed, 'email', 'users');
+
+            $composedResult = json_decode(json: $composedResultJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $decomposedResult = json_decode(json: $decomposedResultJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($composedResult);
+            $this->assertIsArray($decomposedResult);
+            $this->assertSame($composedResult['hm'], $decomposedResult['hm']);
+            $this->assertNotNull($decomposedResult['bf']);
+
+            $this->assertSame($decomposed, $client->decrypt($clientPtr, $decomposedResultJson));
+
+            $termsJson = json_encode([['plaintext' => $decomposed, 'column' => 'email', 'table' => 'users']], JSON_THROW_ON_ERROR);
+            $terms = json_decode(json: $client->createSearchTerms($clientPtr, $termsJson), associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($terms);
+            $this->assertSame($composedResult['hm'], $terms[0]['hm']);
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_encrypt_case_insensitive_unique_index(): void
+    {
+        $config = json_decode(json: self::$config, flags: JSON_THROW_ON_ERROR);
+        $config->tables->users->email->indexes->unique = (object) ['case_insensitive' => true];
+
+        $client = new Client;
+        $clientPtr = $client->newClient(json_encode(value: $config, flags: JSON_THROW_ON_ERROR));
+
+        try {
+            $lowerResult = json_decode(json: $client->encrypt($clientPtr, 'john@example.com', 'email', 'users'), associative: true, flags: JSON_THROW_ON_ERROR);
+            $mixedResultJson = $client->encrypt($clientPtr, 'John@Example.com', 'email', 'users');
+            $mixedResult = json_decode(json: $mixedResultJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($lowerResult);
+            $this->assertIsArray($mixedResult);
+            $this->assertSame($lowerResult['hm'], $mixedResult['hm']);
+            $this->assertSame('John@Example.com', $client->decrypt($clientPtr, $mixedResultJson));
+
+            $termsJson = json_encode([['plaintext' => 'JOHN@EXAMPLE.COM', 'column' => 'email', 'table' => 'users']], JSON_THROW_ON_ERROR);
+            $terms = json_decode(json: $client->createSearchTerms($clientPtr, $termsJson), associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($terms);
+            $this->assertSame($lowerResult['hm'], $terms[0]['hm']);
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_encrypt_edge_ngram_match_index_matches_prefixes(): void
+    {
+        $config = json_decode(json: self::$config, flags: JSON_THROW_ON_ERROR);
+        $config->tables->users->job_title->indexes->match = (object) [
+            'tokenizer' => (object) ['kind' => 'edge_ngram', 'min_length' => 2, 'max_length' => 10],
+        ];
+
+        $client = new Client;
+        $clientPtr = $client->newClient(json_encode(value: $config, flags: JSON_THROW_ON_ERROR));
+
+        try {
+            $encryptResultJson = $client->encrypt($clientPtr, 'Software Engineer', 'job_title', 'users');
+            $encryptResult = json_decode(json: $encryptResultJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($encryptResult);
+            $this->assertIsArray($encryptResult['bf']);
+            $this->assertSame('Software Engineer', $client->decrypt($clientPtr, $encryptResultJson));
+
+            $termsJson = json_encode([
+                ['plaintext' => 'Engi', 'column' => 'job_title', 'table' => 'users'],
+                ['plaintext' => 'Manager', 'column' => 'job_title', 'table' => 'users'],
+            ], JSON_THROW_ON_ERROR);
+            $terms = json_decode(json: $client->createSearchTerms($clientPtr, $termsJson), associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($terms);
+
+            $this->assertSame([], array_diff($terms[0]['bf'], $encryptResult['bf']));
+            $this->assertNotSame([], array_diff($terms[1]['bf'], $encryptResult['bf']));
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_new_client_throws_exception_with_invalid_token_length(): void
+    {
+        $config = json_decode(json: self::$config, flags: JSON_THROW_ON_ERROR);
+        $config->tables->users->job_title->indexes->match = (object) [
+            'tokenizer' => (object) ['kind' => 'ngram', 'token_length' => 0],
+        ];
+
+        $client = new Client;
+
+        $this->expectException(FFIException::class);
+        $this->expectExceptionMessage('`token_length` must be between 1 and 32');
+        $client->newClient(json_encode(value: $config, flags: JSON_THROW_ON_ERROR));
+    }
+
+    public function test_create_search_terms_with_date_range(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $items = [
+                [
+                    'range' => ['from' => '1969-01-01', 'to' => '1970-12-31'],
+                    'column' => 'born_on',
+                    'table' => 'users',
+                ],
+            ];
+
+            $itemsJson = json_encode($items, JSON_THROW_ON_ERROR);
+            $searchTermResultsJson = $client->createSearchTerms($clientPtr, $itemsJson);
+
+            $searchTermResults = json_decode(json: $searchTermResultsJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($searchTermResults);
+            $this->assertCount(1, $searchTermResults);
+
+            $rangeTerm = $searchTermResults[0];
+            $this->assertIsArray($rangeTerm);
+
+            foreach (['from', 'to'] as $bound) {
+                $this->assertArrayHasKey($bound, $rangeTerm);
+                $this->assertIsArray($rangeTerm[$bound]);
+                $this->assertNotNull($rangeTerm[$bound]['ob']);
+                $this->assertSame(['t' => 'users', 'c' => 'born_on'], $rangeTerm[$bound]['i']);
+            }
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_create_search_terms_throws_exception_with_invalid_date_range(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        $invalidRanges = [
+            ['range' => ['from' => '1970-12-31', 'to' => '1969-01-01'], 'column' => 'born_on', 'table' => 'users'],
+            ['range' => ['from' => 'a', 'to' => 'z'], 'column' => 'email', 'table' => 'users'],
+        ];
+
+        try {
+            foreach ($invalidRanges as $item) {
+                try {
+                    $client->createSearchTerms($clientPtr, json_encode([$item], JSON_THROW_ON_ERROR));
+                    $this->fail('Expected an FFIException for an invalid range.');
+                } catch (FFIException $e) {
+                    $this->assertStringContainsString('invalid search term', $e->getMessage());
+                }
+            }
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_create_search_terms_with_options(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $items = [
+                ['plaintext' => '29', 'column' => 'age', 'table' => 'users', 'operator' => '>='],
+                ['plaintext' => 'john@example.com', 'column' => 'email', 'table' => 'users'],
+            ];
+
+            $itemsJson = json_encode($items, JSON_THROW_ON_ERROR);
+            $optionsJson = json_encode(['include_data_type' => true, 'include_operator' => true], JSON_THROW_ON_ERROR);
+            $searchTermResultsJson = $client->createSearchTerms($clientPtr, $itemsJson, $optionsJson);
+
+            $searchTermResults = json_decode(json: $searchTermResultsJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($searchTermResults);
+            $this->assertCount(2, $searchTermResults);
+
+            $this->assertSame('int', $searchTermResults[0]['dt']);
+            $this->assertSame('>=', $searchTermResults[0]['op']);
+            $this->assertSame('text', $searchTermResults[1]['dt']);
+            $this->assertArrayNotHasKey('op', $searchTermResults[1]);
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_create_search_terms_with_eql_v2_format(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $items = [
+                ['plaintext' => 'john@example.com', 'column' => 'email', 'table' => 'users'],
+                ['range' => ['from' => '1969-01-01', 'to' => '1970-12-31'], 'column' => 'born_on', 'table' => 'users'],
+            ];
+
+            $itemsJson = json_encode($items, JSON_THROW_ON_ERROR);
+            $optionsJson = json_encode(['format' => 'eql_v2'], JSON_THROW_ON_ERROR);
+            $searchTermResultsJson = $client->createSearchTerms($clientPtr, $itemsJson, $optionsJson);
+
+            $searchTermResults = json_decode(json: $searchTermResultsJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($searchTermResults);
+            $this->assertCount(2, $searchTermResults);
+
+            [$emailTerm, $rangeTerm] = $searchTermResults;
+            $this->assertIsString($emailTerm);
+            $this->assertStringStartsWith('("{', $emailTerm);
+            $this->assertStringEndsWith('}")', $emailTerm);
+
+            $payload = json_decode(str_replace('""', '"', substr($emailTerm, 2, -2)), true, flags: JSON_THROW_ON_ERROR);
+            $this->assertSame(2, $payload['v']);
+            $this->assertSame(['t' => 'users', 'c' => 'email'], $payload['i']);
+            $this->assertNotNull($payload['hm']);
+
+            $this->assertIsString($rangeTerm['from']);
+            $this->assertIsString($rangeTerm['to']);
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_encrypt_decrypt_with_mysql_output_profile(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config, json_encode(['output_profile' => 'mysql'], JSON_THROW_ON_ERROR));
+
+        try {
+            $columnsJson = $client->encrypt($clientPtr, 'john@example.com', 'email', 'users');
+
+            $columns = json_decode(json: $columnsJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($columns);
+            $this->assertEqualsCanonicalizing(['email', 'email_hm', 'email_bf'], array_keys($columns));
+            $this->assertIsString($columns['email_hm']);
+            $this->assertIsArray(json_decode($columns['email_bf'], true, flags: JSON_THROW_ON_ERROR));
+
+            $this->assertSame('john@example.com', $client->decrypt($clientPtr, $columns['email']));
+
+            $itemsJson = json_encode([['plaintext' => 'john@example.com', 'column' => 'email', 'table' => 'users']], JSON_THROW_ON_ERROR);
+            $optionsJson = json_encode(['format' => 'mysql'], JSON_THROW_ON_ERROR);
+            $searchTermResults = json_decode($client->createSearchTerms($clientPtr, $itemsJson, $optionsJson), true, flags: JSON_THROW_ON_ERROR);
+
+            $this->assertSame($columns['email_hm'], $searchTermResults[0]['hm']);
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_explode_assemble_envelope_roundtrip(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $envelopeJson = $client->encrypt($clientPtr, 'john@example.com', 'email', 'users');
+            $profileJson = json_encode([
+                'ciphertext' => 'email_ct',
+                'metadata' => 'email_meta',
+                'unique' => 'email_hm',
+                'match' => 'email_bf',
+            ], JSON_THROW_ON_ERROR);
+
+            $columnsJson = $client->explodeEnvelope($envelopeJson, $profileJson);
+
+            $columns = json_decode(json: $columnsJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($columns);
+            $this->assertEqualsCanonicalizing(['email_ct', 'email_meta', 'email_hm', 'email_bf'], array_keys($columns));
+
+            $envelope = json_decode(json: $envelopeJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertSame($envelope['c'], $columns['email_ct']);
+            $this->assertSame($envelope['hm'], $columns['email_hm']);
+
+            $assembledJson = $client->assembleEnvelope($columnsJson, $profileJson);
+            $this->assertEquals($envelope, json_decode(json: $assembledJson, associative: true, flags: JSON_THROW_ON_ERROR));
+            $this->assertSame('john@example.com', $client->decrypt($clientPtr, $assembledJson));
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_create_search_terms_throws_exception_with_unsupported_operator(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $items = [
+                ['plaintext' => '29', 'column' => 'age', 'table' => 'users', 'operator' => '~~'],
+            ];
+
+            $this->expectException(FFIException::class);
+            $this->expectExceptionMessage('requires a `match` index');
+
+            $client->createSearchTerms($clientPtr, json_encode($items, JSON_THROW_ON_ERROR));
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_encrypt_decrypt_json_paths_roundtrip(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $document = [
+                'profile' => ['email' => 'john@example.com', 'age' => 29, 'nickname' => 'Johnny'],
+                'tags' => ['admin'],
+            ];
+
+            $paths = [
+                ['path' => '$.profile.email', 'table' => 'users', 'column' => 'email'],
+                ['path' => '$.profile.age', 'table' => 'users', 'column' => 'age'],
+                ['path' => '$.profile.missing', 'table' => 'users', 'column' => 'email'],
+            ];
+
+            $encryptedJson = $client->encryptJsonPaths(
+                $clientPtr,
+                json_encode($document, JSON_THROW_ON_ERROR),
+                json_encode($paths, JSON_THROW_ON_ERROR),
+            );
+
+            $encrypted = json_decode(json: $encryptedJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($encrypted);
+            $this->assertSame('ct', $encrypted['profile']['email']['k']);
+            $this->assertSame('int', $encrypted['profile']['age']['dt']);
+            $this->assertSame('Johnny', $encrypted['profile']['nickname']);
+            $this->assertSame(['admin'], $encrypted['tags']);
+            $this->assertArrayNotHasKey('missing', $encrypted['profile']);
+
+            $decryptedJson = $client->decryptJsonPaths(
+                $clientPtr,
+                $encryptedJson,
+                json_encode(['$.profile.email', '$.profile.age'], JSON_THROW_ON_ERROR),
+            );
+
+            $decrypted = json_decode(json: $decryptedJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertSame($document, $decrypted);
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_run_benchmark_reports_throughput(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $spec = [
+                'operation' => 'decrypt_bulk',
+                'table' => 'users',
+                'column' => 'email',
+                'plaintext' => 'john@example.com',
+                'batch_size' => 5,
+                'iterations' => 3,
+                'max_mean_ms' => 60000,
+            ];
+
+            $reportJson = $client->runBenchmark($clientPtr, json_encode($spec, JSON_THROW_ON_ERROR));
+
+            $report = json_decode(json: $reportJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($report);
+            $this->assertSame('decrypt_bulk', $report['operation']);
+            $this->assertSame(3, $report['iterations']);
+            $this->assertSame(5, $report['batch_size']);
+            $this->assertGreaterThan(0, $report['items_per_second']);
+            $this->assertLessThanOrEqual($report['max_ms'], $report['p50_ms']);
+            $this->assertTrue($report['within_budget']);
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_run_benchmark_throws_exception_with_invalid_spec(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $spec = [
+                'operation' => 'encrypt',
+                'table' => 'users',
+                'column' => 'email',
+                'plaintext' => 'john@example.com',
+                'iterations' => 0,
+            ];
+
+            $this->expectException(FFIException::class);
+            $this->expectExceptionMessage('`iterations` must be between 1 and 1000');
+
+            $client->runBenchmark($clientPtr, json_encode($spec, JSON_THROW_ON_ERROR));
+        } finally {
+            $client->freeClient($clientPtr);
+        }
+    }
+
+    public function test_estimate_storage_without_client(): void
+    {
+        $client = new Client;
+
+        $samples = [
+            ['table' => 'users', 'column' => 'email', 'plaintext' => 'john@example.com'],
+            ['table' => 'users', 'column' => 'age', 'plaintext' => '29'],
+            ['table' => 'users', 'column' => 'metadata', 'plaintext' => '{"plan":"pro","seats":5}'],
+        ];
+
+        $estimateJson = $client->estimateStorage(self::$config, json_encode($samples, JSON_THROW_ON_ERROR));
+
+        $estimate = json_decode(json: $estimateJson, associative: true, flags: JSON_THROW_ON_ERROR);
+        $this->assertIsArray($estimate);
+        $this->assertSame(3, $estimate['items']);
+        $this->assertGreaterThan($estimate['plaintext_bytes'], $estimate['stored_bytes']);
+        $this->assertSame(66, $estimate['columns']['users.email']['index_bytes']['unique']);
+        $this->assertGreaterThan(0, $estimate['columns']['users.email']['index_bytes']['match']);
+        $this->assertGreaterThan(0, $estimate['columns']['users.age']['index_bytes']['ore']);
+        $this->assertGreaterThan(0, $estimate['columns']['users.metadata']['index_bytes']['ste_vec']);
+    }
+
+    public function test_estimate_storage_throws_exception_with_unknown_column(): void
+    {
+        $client = new Client;
+
+        $samples = [
+            ['table' => 'users', 'column' => 'missing', 'plaintext' => 'john@example.com'],
+        ];
+
+        $this->expectException(FFIException::class);
+        $this->expectExceptionMessage('unknown column `users.missing`');
+
+        $client->estimateStorage(self::$config, json_encode($samples, JSON_THROW_ON_ERROR));
+    }
+
+    public function test_plan_encrypt_reports_items_without_encrypting(): void
+    {
+        $client = new Client;
+        $clientPtr = $client->newClient(self::$config);
+
+        try {
+            $items = [
+                ['id' => 1, 'plaintext' => 'john@example.com', 'column' => 'email', 'table' => 'users', 'skip_match' => true],
+                ['id' => 2, 'plaintext' => '2024-1-5', 'column' => 'born_on', 'table' => 'users'],
+                ['id' => 3, 'plaintext' => '{"plan":"pro"}', 'column' => 'metadata', 'table' => 'users'],
+                ['id' => 4, 'plaintext' => 'not a number', 'column' => 'age', 'table' => 'users'],
+                ['id' => 5, 'plaintext' => 'john@example.com', 'column' => 'missing', 'table' => 'users'],
+            ];
+
+            $planJson = $client->planEncrypt($clientPtr, json_encode($items, JSON_THROW_ON_ERROR));
+
+            $plan = json_decode(json: $planJson, associative: true, flags: JSON_THROW_ON_ERROR);
+            $this->assertIsArray($plan);
+            $this->assertFalse($plan['valid']);
+
+            [$email, $bornOn, $metadata, $age, $missing] = $plan['items'];
+
+            $this->assertSame(1, $email['id']);
+            $this->assertSame('ct', $email['kind']);
+            $this->assertSame(['unique'], $email['indexes']);
+            $this->assertArrayNotHasKey('error', $email);
+
+            $this->assertSame('date', $bornOn['cast_as']);
+            $this->assertSame(['normalize_date'], $bornOn['conversions']);
+
+            $this->assertSame('sv', $metadata['kind']);
+            $this->assertSame(['parse_json'], $metadata['conversions']);
+
+            $this->assertSame('int', $age['cast_as']);
+            $this->assertStringContainsString('invalid `int` plaintext', $age['error']);
+
+            $this->assertArrayNotHasKey('cast_as', $missing);
+            $this->assertStringContainsString('unknown column `users.missing`', $missing['error']);
         } finally {
             $client->freeClient($clientPtr);
         }
@@ -6,6 +6,7 @@ namespace CipherStash\Protect\FFI\Tests\Unit;
 
 use CipherStash\Protect\FFI\Client;
 use CipherStash\Protect\FFI\Exceptions\FFIException;
+use CipherStash\Protect\FFI\Loader;
 use PHPUnit\Framework\TestCase;
 
 class ClientTest extends TestCase
@@ -24,6 +25,35 @@ class ClientTest extends TestCase
         $this->assertInstanceOf(\FFI::class, $ffiProperty->getValue($client));
     }
 
+    public function test_native_library_matches_header_abi(): void
+    {
+        $client = new Client;
+        $reflection = new \ReflectionClass($client);
+
+        $ffiProperty = $reflection->getProperty('ffi');
+        $ffiProperty->setAccessible(true);
+        $ffi = $ffiProperty->getValue($client);
+
+        $this->assertSame(Loader::ABI_VERSION, $ffi->protect_ffi_abi_version());
+
+        $errorPtr = $ffi->new('char*');
+        $status = $ffi->selfcheck(Loader::ABI_VERSION + 1, \FFI::sizeof($ffi->type('void*')), 4, null, null, \FFI::addr($errorPtr));
+
+        $this->assertSame(-1, $status);
+        $this->assertStringContainsString('incompatible ABI', \FFI::string($errorPtr));
+        $ffi->free_string($errorPtr);
+    }
+
+    public function test_constructor_registers_deprecation_handler(): void
+    {
+        new Client;
+        $reflection = new \ReflectionClass(Client::class);
+
+        $handlerProperty = $reflection->getProperty('deprecationHandler');
+        $handlerProperty->setAccessible(true);
+        $this->assertInstanceOf(\Closure::class, $handlerProperty->getValue());
+    }
+
     public function test_load_header_file_returns_string_content(): void
     {
         $client = new Client;
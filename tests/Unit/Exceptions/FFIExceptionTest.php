@@ -35,6 +35,15 @@ class FFIExceptionTest extends TestCase
         $this->assertNotEmpty($exception->getMessage());
     }
 
+    public function test_incompatible_native_library(): void
+    {
+        $reason = 'library implements ABI version 2, but the header requires version 1';
+        $exception = FFIException::incompatibleNativeLibrary($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
     public function test_client_creation_failed(): void
     {
         $reason = 'Invalid configuration provided';
@@ -105,4 +114,301 @@ class FFIExceptionTest extends TestCase
         $this->assertInstanceOf(FFIException::class, $exception);
         $this->assertNotEmpty($exception->getMessage());
     }
+
+    public function test_failed_to_format_envelope(): void
+    {
+        $reason = 'Malformed envelope JSON';
+        $exception = FFIException::failedToFormatEnvelope($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_validate_envelope(): void
+    {
+        $reason = 'Malformed envelope JSON';
+        $exception = FFIException::failedToValidateEnvelope($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_convert_envelope(): void
+    {
+        $reason = 'Unsupported envelope version';
+        $exception = FFIException::failedToConvertEnvelope($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_upgrade_envelope(): void
+    {
+        $reason = 'Unknown column in configuration';
+        $exception = FFIException::failedToUpgradeEnvelope($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_reindex_search_terms(): void
+    {
+        $reason = 'Column has no match index';
+        $exception = FFIException::failedToReindexSearchTerms($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_get_key_info(): void
+    {
+        $reason = 'Failed to list keysets';
+        $exception = FFIException::failedToGetKeyInfo($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_export_client_state(): void
+    {
+        $reason = 'null pointer provided';
+        $exception = FFIException::failedToExportClientState($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_import_client_state(): void
+    {
+        $reason = 'missing field `config_digest`';
+        $exception = FFIException::failedToImportClientState($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_assert_config_matches(): void
+    {
+        $reason = 'Encryption configuration mismatch';
+        $exception = FFIException::failedToAssertConfigMatches($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_encrypt_json_paths(): void
+    {
+        $reason = 'Invalid JSON path';
+        $exception = FFIException::failedToEncryptJsonPaths($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_decrypt_json_paths(): void
+    {
+        $reason = 'Decryption failed';
+        $exception = FFIException::failedToDecryptJsonPaths($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_explode_envelope(): void
+    {
+        $reason = 'Invalid envelope storage';
+        $exception = FFIException::failedToExplodeEnvelope($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_assemble_envelope(): void
+    {
+        $reason = 'Missing ciphertext column';
+        $exception = FFIException::failedToAssembleEnvelope($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_run_benchmark(): void
+    {
+        $reason = 'invalid benchmark: `iterations` must be between 1 and 1000';
+        $exception = FFIException::failedToRunBenchmark($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_estimate_storage(): void
+    {
+        $reason = 'unknown column `users.missing`';
+        $exception = FFIException::failedToEstimateStorage($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_plan_encrypt(): void
+    {
+        $reason = 'JSON parsing error: expected value at line 1 column 1';
+        $exception = FFIException::failedToPlanEncrypt($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_get_client_warnings(): void
+    {
+        $reason = 'Invalid client pointer';
+        $exception = FFIException::failedToGetClientWarnings($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_validate_config(): void
+    {
+        $reason = 'Column `users.age` has a `match` index';
+        $exception = FFIException::failedToValidateConfig($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_get_config_digest(): void
+    {
+        $reason = 'Invalid configuration';
+        $exception = FFIException::failedToGetConfigDigest($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_tokenize_selector(): void
+    {
+        $reason = 'Column has no ste_vec index';
+        $exception = FFIException::failedToTokenizeSelector($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_explain_query_capability(): void
+    {
+        $reason = 'unknown operator `==`';
+        $exception = FFIException::failedToExplainQueryCapability($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_compare_terms(): void
+    {
+        $reason = 'invalid index term `term_b`: expected a hex or prefixed base64 string';
+        $exception = FFIException::failedToCompareTerms($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_derive_client(): void
+    {
+        $reason = 'unknown keyset: tenant_a';
+        $exception = FFIException::failedToDeriveClient($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_expand_context(): void
+    {
+        $reason = 'invalid encryption context at `$`: unknown context preset `tenant_scoped`, define it under `contexts` in the configuration';
+        $exception = FFIException::failedToExpandContext($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_run_job(): void
+    {
+        $reason = 'invalid job: the job is still running, poll it until it is done';
+        $exception = FFIException::failedToRunJob($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_ping_service(): void
+    {
+        $reason = 'Invalid client pointer';
+        $exception = FFIException::failedToPingService($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_get_network_policy(): void
+    {
+        $reason = 'Invalid client pointer';
+        $exception = FFIException::failedToGetNetworkPolicy($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_get_startup_diagnostics(): void
+    {
+        $reason = 'Failed to serialize diagnostics';
+        $exception = FFIException::failedToGetStartupDiagnostics($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_get_error_catalog(): void
+    {
+        $reason = 'Failed to serialize catalog';
+        $exception = FFIException::failedToGetErrorCatalog($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_list_functions(): void
+    {
+        $reason = 'Failed to serialize manifest';
+        $exception = FFIException::failedToListFunctions($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_get_metrics(): void
+    {
+        $reason = 'Invalid client pointer';
+        $exception = FFIException::failedToGetMetrics($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_clear_search_term_cache(): void
+    {
+        $reason = 'Invalid client pointer';
+        $exception = FFIException::failedToClearSearchTermCache($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
+
+    public function test_failed_to_set_crash_report_path(): void
+    {
+        $reason = 'Invalid UTF-8 in path';
+        $exception = FFIException::failedToSetCrashReportPath($reason);
+
+        $this->assertInstanceOf(FFIException::class, $exception);
+        $this->assertNotEmpty($exception->getMessage());
+    }
 }
@@ -122,6 +122,8 @@ class LoaderTest extends TestCase
             'darwin-x64 maps correctly' => ['darwin-x64', 'darwin-x64'],
             'linux-arm64 maps to gnu variant' => ['linux-arm64', 'linux-arm64-gnu'],
             'linux-x64 maps to gnu variant' => ['linux-x64', 'linux-x64-gnu'],
+            'linux-arm64-musl maps correctly' => ['linux-arm64-musl', 'linux-arm64-musl'],
+            'linux-x64-musl maps correctly' => ['linux-x64-musl', 'linux-x64-musl'],
             'windows-x64 maps to msvc variant' => ['windows-x64', 'win32-x64-msvc'],
         ];
     }